@@ -0,0 +1,55 @@
+//! Scalar vs SIMD similarity kernels on common embedding widths
+//!
+//! Run with `cargo bench --bench similarity`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mcp_performance_core::simd;
+use std::hint::black_box;
+
+/// Deterministic pseudo-random vectors in [-1, 1)
+fn vectors(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+    };
+    (0..count).map(|_| (0..dimension).map(|_| next()).collect()).collect()
+}
+
+fn kernels(c: &mut Criterion) {
+    for dimension in [768, 1536] {
+        let pair = vectors(2, dimension, dimension as u64);
+        let (a, b) = (&pair[0], &pair[1]);
+        let mut group = c.benchmark_group(format!("pair/{dimension}"));
+        group.throughput(Throughput::Elements(dimension as u64));
+        group.bench_function("dot/scalar", |bench| bench.iter(|| simd::scalar::dot(black_box(a), black_box(b))));
+        group.bench_function("dot/simd", |bench| bench.iter(|| simd::dot(black_box(a), black_box(b))));
+        group.bench_function("cosine/scalar", |bench| bench.iter(|| simd::scalar::cosine(black_box(a), black_box(b))));
+        group.bench_function("cosine/simd", |bench| bench.iter(|| simd::cosine(black_box(a), black_box(b))));
+        group.bench_function("normalize/scalar", |bench| bench.iter(|| simd::scalar::normalized(black_box(a))));
+        group.bench_function("normalize/simd", |bench| bench.iter(|| simd::normalized(black_box(a))));
+        group.finish();
+    }
+}
+
+/// One query scored against a few thousand stored memories
+fn batch(c: &mut Criterion) {
+    let memories = 5000;
+    let mut group = c.benchmark_group("batch_cosine");
+    group.sample_size(30);
+    for dimension in [768, 1536] {
+        let corpus = vectors(memories, dimension, 7);
+        let query = &vectors(1, dimension, 11)[0];
+        group.throughput(Throughput::Elements(memories as u64));
+        group.bench_with_input(BenchmarkId::new("scalar", dimension), &corpus, |bench, corpus| {
+            bench.iter(|| corpus.iter().map(|v| simd::scalar::cosine(query, v)).fold(f32::MIN, f32::max))
+        });
+        group.bench_with_input(BenchmarkId::new("simd", dimension), &corpus, |bench, corpus| {
+            bench.iter(|| corpus.iter().map(|v| simd::cosine(query, v)).fold(f32::MIN, f32::max))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernels, batch);
+criterion_main!(benches);
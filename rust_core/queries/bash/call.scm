@@ -0,0 +1,4 @@
+; Every command, including builtins and external programs
+(command
+  name: (command_name
+    (word) @name)) @call
@@ -0,0 +1,12 @@
+; `readonly` variables, and top-level assignments to UPPER_CASE names
+[(program
+  (declaration_command
+    "readonly"
+    (variable_assignment name: (variable_name) @name)) @constant)
+ (program
+  (variable_assignment name: (variable_name) @name) @constant
+  (#match? @name "^[A-Z][A-Z0-9_]*$"))
+ (program
+  (declaration_command
+    (variable_assignment name: (variable_name) @name)) @constant
+  (#match? @name "^[A-Z][A-Z0-9_]*$"))]
@@ -0,0 +1,4 @@
+; Both `f() { ... }` and `function f { ... }`
+(function_definition
+  name: (word) @name
+  body: (_) @body) @function
@@ -0,0 +1,3 @@
+(program
+  (variable_assignment name: (variable_name) @name) @global
+  (#not-match? @name "^[A-Z][A-Z0-9_]*$"))
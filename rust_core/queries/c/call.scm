@@ -0,0 +1,4 @@
+; Calls of functions and of function pointers in struct fields
+(call_expression
+  function: [(identifier) @name
+             (field_expression field: (field_identifier) @name)]) @call
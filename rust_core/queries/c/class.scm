@@ -0,0 +1,4 @@
+; C only has structs, not classes
+(struct_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list) @body) @class
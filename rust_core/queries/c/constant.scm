@@ -0,0 +1,2 @@
+(preproc_def
+  name: (identifier) @name) @constant
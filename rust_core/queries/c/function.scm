@@ -0,0 +1,4 @@
+(function_definition
+  declarator: (function_declarator
+    declarator: (_) @name)
+  body: (compound_statement) @body) @function
@@ -0,0 +1,7 @@
+[(translation_unit
+  (declaration
+    declarator: (identifier) @name) @global)
+ (translation_unit
+  (declaration
+    declarator: (init_declarator
+      declarator: (identifier) @name)) @global)]
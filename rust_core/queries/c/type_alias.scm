@@ -0,0 +1,2 @@
+(type_definition
+  declarator: (type_identifier) @name) @type_alias
@@ -0,0 +1,5 @@
+; Calls of functions, qualified functions (`std::sort`) and methods
+(call_expression
+  function: [(identifier) @name
+             (qualified_identifier name: (identifier) @name)
+             (field_expression field: (field_identifier) @name)]) @call
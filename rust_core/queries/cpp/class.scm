@@ -0,0 +1,7 @@
+; C++ has both classes and structs - use alternation to capture both
+[(class_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list) @body)
+ (struct_specifier
+  name: (type_identifier) @name
+  body: (field_declaration_list) @body)] @class
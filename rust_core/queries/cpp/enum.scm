@@ -0,0 +1,3 @@
+(enum_specifier
+  name: (type_identifier) @name
+  body: (enumerator_list)) @enum
@@ -0,0 +1,4 @@
+[(type_definition
+  declarator: (type_identifier) @name)
+ (alias_declaration
+  name: (type_identifier) @name)] @type_alias
@@ -0,0 +1,7 @@
+; Method calls and constructor calls
+(invocation_expression
+  function: [(identifier) @name
+             (member_access_expression name: (identifier) @name)]) @call
+
+(object_creation_expression
+  type: (identifier) @name) @call
@@ -0,0 +1,6 @@
+[(class_declaration
+  name: (identifier) @name)
+ (interface_declaration
+  name: (identifier) @name)
+ (struct_declaration
+  name: (identifier) @name)] @class
@@ -0,0 +1,5 @@
+(field_declaration
+  (modifier) @modifier
+  (variable_declaration
+    (variable_declarator name: (identifier) @name))
+  (#eq? @modifier "const")) @constant
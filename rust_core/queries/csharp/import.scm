@@ -0,0 +1,3 @@
+; The namespace is the last name, after any `Alias =`
+(using_directive
+  [(qualified_name) (identifier)] @name .) @import
@@ -0,0 +1,4 @@
+; Calls of functions, and of methods or package functions through a selector
+(call_expression
+  function: [(identifier) @name
+             (selector_expression field: (field_identifier) @name)]) @call
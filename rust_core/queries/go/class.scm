@@ -0,0 +1,6 @@
+; Capture the type_spec so grouped `type ( ... )` declarations
+; yield one span per struct
+(type_declaration
+  (type_spec
+    name: (type_identifier) @name
+    type: (struct_type) @body) @class)
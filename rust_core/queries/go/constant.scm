@@ -0,0 +1,3 @@
+(source_file
+  (const_declaration
+    (const_spec name: (identifier) @name) @constant))
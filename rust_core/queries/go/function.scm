@@ -0,0 +1,10 @@
+(function_declaration
+  name: (identifier) @name
+  parameters: (parameter_list) @params
+  body: (block) @body) @function
+
+; Methods; qualified by their receiver's type
+(method_declaration
+  name: (field_identifier) @name
+  parameters: (parameter_list) @params
+  body: (block) @body) @function
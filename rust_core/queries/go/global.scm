@@ -0,0 +1,3 @@
+(source_file
+  (var_declaration
+    (var_spec name: (identifier) @name) @global))
@@ -0,0 +1,3 @@
+; One unit per spec, so grouped `import ( ... )` blocks yield every path
+(import_spec
+  path: (_) @name) @import
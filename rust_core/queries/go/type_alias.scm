@@ -0,0 +1,7 @@
+[(type_declaration
+  (type_alias name: (type_identifier) @name) @type_alias)
+ (type_declaration
+  (type_spec
+    name: (type_identifier) @name
+    type: [(type_identifier) (qualified_type) (pointer_type)
+           (slice_type) (map_type) (function_type)]) @type_alias)]
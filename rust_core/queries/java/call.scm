@@ -0,0 +1,6 @@
+; Method calls and constructor calls
+(method_invocation
+  name: (identifier) @name) @call
+
+(object_creation_expression
+  type: (type_identifier) @name) @call
@@ -0,0 +1,3 @@
+(class_declaration
+  name: (identifier) @name
+  body: (class_body) @body) @class
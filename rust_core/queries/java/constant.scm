@@ -0,0 +1,5 @@
+(field_declaration
+  (modifiers) @modifiers
+  declarator: (variable_declarator name: (identifier) @name)
+  (#match? @modifiers "static")
+  (#match? @modifiers "final")) @constant
@@ -0,0 +1,4 @@
+(method_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @params
+  body: (block) @body) @function
@@ -0,0 +1,2 @@
+(import_declaration
+  [(scoped_identifier) (identifier)] @name) @import
@@ -0,0 +1,3 @@
+(interface_declaration
+  name: (identifier) @name
+  body: (interface_body) @body) @interface
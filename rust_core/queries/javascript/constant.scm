@@ -0,0 +1,9 @@
+[(program
+  (lexical_declaration
+    "const"
+    (variable_declarator name: (identifier) @name)) @constant)
+ (program
+  (export_statement
+    (lexical_declaration
+      "const"
+      (variable_declarator name: (identifier) @name))) @constant)]
@@ -0,0 +1,9 @@
+(function_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @params
+  body: (statement_block) @body) @function
+
+(method_definition
+  name: (_) @name
+  parameters: (formal_parameters) @params
+  body: (statement_block) @body) @function
@@ -0,0 +1,8 @@
+; Plain calls, and method calls named by their last segment
+(call_expression
+  (simple_identifier) @name) @call
+
+(call_expression
+  (navigation_expression
+    (navigation_suffix
+      (simple_identifier) @name))) @call
@@ -0,0 +1,7 @@
+; Classes, interfaces, enum classes and objects
+[(class_declaration
+  (type_identifier) @name
+  [(class_body) (enum_class_body)]? @body)
+ (object_declaration
+  (type_identifier) @name
+  (class_body)? @body)] @class
@@ -0,0 +1,5 @@
+; Top-level `val` and `const val` properties
+(source_file
+  (property_declaration
+    (binding_pattern_kind "val")
+    (variable_declaration (simple_identifier) @name)) @constant)
@@ -0,0 +1,5 @@
+; Top-level functions, methods and extension functions (`fun String.slug()`)
+(function_declaration
+  (simple_identifier) @name
+  (function_value_parameters) @params
+  (function_body)? @body) @function
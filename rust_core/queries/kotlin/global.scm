@@ -0,0 +1,4 @@
+(source_file
+  (property_declaration
+    (binding_pattern_kind "var")
+    (variable_declaration (simple_identifier) @name)) @global)
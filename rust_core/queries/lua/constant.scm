@@ -0,0 +1,10 @@
+; Top-level assignments, local or not, to UPPER_CASE names
+[(chunk
+  (variable_declaration
+    (assignment_statement
+      (variable_list name: (identifier) @name))) @constant
+  (#match? @name "^[A-Z][A-Z0-9_]*$"))
+ (chunk
+  (assignment_statement
+    (variable_list name: (identifier) @name)) @constant
+  (#match? @name "^[A-Z][A-Z0-9_]*$"))]
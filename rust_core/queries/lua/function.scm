@@ -0,0 +1,6 @@
+; Named and local functions; `function M.f()` and `function M:f()` keep
+; their table in the name
+(function_declaration
+  name: (_) @name
+  parameters: (parameters) @params
+  body: (block)? @body) @function
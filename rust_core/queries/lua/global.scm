@@ -0,0 +1,9 @@
+[(chunk
+  (variable_declaration
+    (assignment_statement
+      (variable_list name: (identifier) @name))) @global
+  (#not-match? @name "^[A-Z][A-Z0-9_]*$"))
+ (chunk
+  (assignment_statement
+    (variable_list name: (identifier) @name)) @global
+  (#not-match? @name "^[A-Z][A-Z0-9_]*$"))]
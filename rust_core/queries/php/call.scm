@@ -0,0 +1,9 @@
+; Function calls, method calls and static method calls
+(function_call_expression
+  function: (name) @name) @call
+
+(member_call_expression
+  name: (name) @name) @call
+
+(scoped_call_expression
+  name: (name) @name) @call
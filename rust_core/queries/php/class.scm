@@ -0,0 +1,10 @@
+; PHP classes, interfaces, and traits
+[(class_declaration
+  name: (name) @name
+  body: (declaration_list) @body)
+ (interface_declaration
+  name: (name) @name
+  body: (declaration_list) @body)
+ (trait_declaration
+  name: (name) @name
+  body: (declaration_list) @body)] @class
@@ -0,0 +1,2 @@
+(const_declaration
+  (const_element (name) @name)) @constant
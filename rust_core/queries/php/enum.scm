@@ -0,0 +1,2 @@
+(enum_declaration
+  name: (name) @name) @enum
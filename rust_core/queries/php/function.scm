@@ -0,0 +1,9 @@
+(function_definition
+  name: (name) @name
+  parameters: (formal_parameters) @params
+  body: (compound_statement) @body) @function
+
+(method_declaration
+  name: (name) @name
+  parameters: (formal_parameters) @params
+  body: (compound_statement) @body) @function
@@ -0,0 +1,7 @@
+; `use` clauses name the namespace before any `as` alias
+[(namespace_use_declaration
+  (namespace_use_clause . [(qualified_name) (name)] @name))
+ (include_expression (_) @name)
+ (include_once_expression (_) @name)
+ (require_expression (_) @name)
+ (require_once_expression (_) @name)] @import
@@ -0,0 +1,4 @@
+; Calls of functions and methods, named after the callee
+(call
+  function: [(identifier) @name
+             (attribute attribute: (identifier) @name)]) @call
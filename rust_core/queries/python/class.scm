@@ -0,0 +1,3 @@
+(class_definition
+  name: (identifier) @name
+  body: (block) @body) @class
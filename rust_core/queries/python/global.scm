@@ -0,0 +1,5 @@
+(module
+  (expression_statement
+    (assignment
+      left: (identifier) @name
+      (#not-match? @name "^[A-Z][A-Z0-9_]*$"))) @global)
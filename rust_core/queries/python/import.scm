@@ -0,0 +1,6 @@
+; One unit per imported module; `from` imports are named after the module
+[(import_statement
+  name: [(dotted_name) @name
+         (aliased_import name: (dotted_name) @name)])
+ (import_from_statement
+  module_name: (_) @name)] @import
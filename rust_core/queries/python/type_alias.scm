@@ -0,0 +1,6 @@
+(module
+  (expression_statement
+    (assignment
+      left: (identifier) @name
+      type: (type) @annotation
+      (#eq? @annotation "TypeAlias"))) @type_alias)
@@ -0,0 +1,3 @@
+; Method calls, with or without a receiver
+(call
+  method: (identifier) @name) @call
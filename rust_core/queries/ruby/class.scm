@@ -0,0 +1,5 @@
+; Ruby has both classes and modules
+[(class
+  name: (constant) @name)
+ (module
+  name: (constant) @name)] @class
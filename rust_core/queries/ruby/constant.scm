@@ -0,0 +1,3 @@
+(program
+  (assignment
+    left: (constant) @name) @constant)
@@ -0,0 +1,3 @@
+(method
+  name: (_) @name
+  parameters: (method_parameters)? @params) @function
@@ -0,0 +1,2 @@
+(assignment
+  left: (global_variable) @name) @global
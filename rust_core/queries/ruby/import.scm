@@ -0,0 +1,4 @@
+(call
+  method: (identifier) @method
+  arguments: (argument_list . (string (string_content) @name))
+  (#match? @method "^(require|require_relative|load)$")) @import
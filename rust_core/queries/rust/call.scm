@@ -0,0 +1,5 @@
+; Calls of functions, paths (`Vec::new`) and methods
+(call_expression
+  function: [(identifier) @name
+             (scoped_identifier name: (identifier) @name)
+             (field_expression field: (field_identifier) @name)]) @call
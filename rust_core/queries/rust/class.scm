@@ -0,0 +1,3 @@
+(struct_item
+  name: (type_identifier) @name
+  body: (field_declaration_list) @body) @class
@@ -0,0 +1,3 @@
+(source_file
+  (const_item
+    name: (identifier) @name) @constant)
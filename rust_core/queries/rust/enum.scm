@@ -0,0 +1,2 @@
+(enum_item
+  name: (type_identifier) @name) @enum
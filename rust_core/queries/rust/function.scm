@@ -0,0 +1,4 @@
+(function_item
+  name: (identifier) @name
+  parameters: (parameters) @params
+  body: (block) @body) @function
@@ -0,0 +1,3 @@
+(source_file
+  (static_item
+    name: (identifier) @name) @global)
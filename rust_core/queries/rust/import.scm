@@ -0,0 +1,4 @@
+[(use_declaration
+  argument: (_) @name)
+ (extern_crate_declaration
+  name: (identifier) @name)] @import
@@ -0,0 +1,3 @@
+(trait_item
+  name: (type_identifier) @name
+  body: (declaration_list) @body) @trait
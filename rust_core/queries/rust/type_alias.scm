@@ -0,0 +1,2 @@
+(type_item
+  name: (type_identifier) @name) @type_alias
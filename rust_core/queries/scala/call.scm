@@ -0,0 +1,7 @@
+(call_expression
+  function: (identifier) @name) @call
+
+; Method calls, named by the method
+(call_expression
+  function: (field_expression
+    field: (identifier) @name)) @call
@@ -0,0 +1,6 @@
+[(class_definition
+  name: (identifier) @name)
+ (object_definition
+  name: (identifier) @name)
+ (trait_definition
+  name: (identifier) @name)] @class
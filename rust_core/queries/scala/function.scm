@@ -0,0 +1,3 @@
+; Methods may have several parameter lists, or none
+(function_definition
+  name: (identifier) @name) @function
@@ -0,0 +1,5 @@
+; SQL tables and views as "class" equivalents
+[
+  (create_table) @class
+  (create_view) @class
+]
@@ -0,0 +1,2 @@
+; SQL functions and procedures
+(create_function) @function
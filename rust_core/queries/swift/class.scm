@@ -0,0 +1,5 @@
+; class_declaration also covers structs, enums and actors
+[(class_declaration
+  (type_identifier) @name)
+ (protocol_declaration
+  (type_identifier) @name)] @class
@@ -0,0 +1,8 @@
+(function_declaration
+  (simple_identifier) @name
+  (function_body) @body) @function
+
+; Initializers, named `init`
+(init_declaration
+  "init" @name
+  body: (function_body) @body) @function
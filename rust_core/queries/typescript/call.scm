@@ -0,0 +1,7 @@
+; Calls of functions and methods, and constructor calls
+(call_expression
+  function: [(identifier) @name
+             (member_expression property: (_) @name)]) @call
+
+(new_expression
+  constructor: (identifier) @name) @call
@@ -0,0 +1,4 @@
+; TypeScript can use both identifier and type_identifier for class names
+(class_declaration
+  name: (_) @name
+  body: (class_body) @body) @class
@@ -0,0 +1,7 @@
+[(program
+  (variable_declaration
+    (variable_declarator name: (identifier) @name)) @global)
+ (program
+  (lexical_declaration
+    "let"
+    (variable_declarator name: (identifier) @name)) @global)]
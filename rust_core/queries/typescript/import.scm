@@ -0,0 +1,9 @@
+; ES module imports and re-exports, and CommonJS `require("...")`
+[(import_statement
+  source: (string) @name)
+ (export_statement
+  source: (string) @name)
+ (call_expression
+  function: (identifier) @function_name
+  arguments: (arguments . (string) @name)
+  (#eq? @function_name "require"))] @import
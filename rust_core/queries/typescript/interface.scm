@@ -0,0 +1,2 @@
+(interface_declaration
+  name: (type_identifier) @name) @interface
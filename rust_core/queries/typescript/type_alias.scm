@@ -0,0 +1,2 @@
+(type_alias_declaration
+  name: (type_identifier) @name) @type_alias
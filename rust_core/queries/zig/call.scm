@@ -0,0 +1,7 @@
+(call_expression
+  . (identifier) @name) @call
+
+; Method and namespaced calls (`list.append(x)`, `std.debug.print(...)`)
+(call_expression
+  . (field_expression
+    (identifier) @name .)) @call
@@ -0,0 +1,4 @@
+; Zig types are containers bound to constants (`const Point = struct { ... };`)
+(variable_declaration
+  . (identifier) @name
+  [(struct_declaration) (union_declaration) (enum_declaration)]) @class
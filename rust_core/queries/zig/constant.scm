@@ -0,0 +1,10 @@
+; Containers (`const Point = struct { ... };`) are left to `class.scm` and
+; `@import` bindings aren't constants
+(source_file
+  (variable_declaration
+    "const"
+    .
+    (identifier) @name
+    "="
+    (_) @value) @constant
+  (#not-match? @value "^((extern|packed) )?(struct|union|enum)\\b|^@import\\("))
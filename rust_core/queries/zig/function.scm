@@ -0,0 +1,4 @@
+(function_declaration
+  . (identifier) @name
+  (parameters) @params
+  (block)? @body) @function
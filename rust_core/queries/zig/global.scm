@@ -0,0 +1,5 @@
+(source_file
+  (variable_declaration
+    "var"
+    .
+    (identifier) @name) @global)
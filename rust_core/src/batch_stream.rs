@@ -0,0 +1,182 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::core_config;
+use crate::parsing::{parse_any_file, parse_path, ParseOptions, ParseResult};
+
+/// Files parsed per batch unless the caller says otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 256;
+
+/// How far a streamed batch parse has got
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[pyclass]
+pub struct ParseProgress {
+    #[pyo3(get)]
+    pub files_done: usize,
+    #[pyo3(get)]
+    pub files_total: usize,
+    #[pyo3(get)]
+    pub units_found: usize,
+    #[pyo3(get)]
+    pub errors: usize, // Files that couldn't be read or parsed
+    #[pyo3(get)]
+    pub elapsed_ms: f64,
+}
+
+#[pymethods]
+impl ParseProgress {
+    fn __repr__(&self) -> String {
+        format!(
+            "ParseProgress(files_done={}/{}, units_found={}, errors={}, elapsed_ms={:.1})",
+            self.files_done, self.files_total, self.units_found, self.errors, self.elapsed_ms
+        )
+    }
+}
+
+impl ParseProgress {
+    fn record(&mut self, batch: &Batch, started: Instant) {
+        self.files_done += batch.results.len() + batch.failures.len();
+        self.units_found += batch.results.iter().map(|result| result.units.len()).sum::<usize>();
+        self.errors += batch.failures.len();
+        self.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    }
+}
+
+/// A file to parse: a path read natively, or a `(path, content)` pair
+#[derive(Debug, Clone, FromPyObject)]
+pub enum BatchItem {
+    Path(String),
+    File(String, String),
+}
+
+impl BatchItem {
+    fn path(&self) -> &str {
+        match self {
+            BatchItem::Path(path) | BatchItem::File(path, _) => path,
+        }
+    }
+}
+
+/// One batch's results in input order, and the `(path, error)` of each
+/// file in it that failed
+#[derive(Debug, Default)]
+struct Batch {
+    results: Vec<ParseResult>,
+    failures: Vec<(String, String)>,
+}
+
+/// Parse a batch in parallel. Unlike `parse_batch`, a file that fails is
+/// recorded and the others still parse.
+fn parse_items(items: &[BatchItem], options: &ParseOptions) -> Batch {
+    let outcomes: Vec<Result<ParseResult, String>> = core_config::install(|| {
+        items
+            .par_iter()
+            .map(|item| match item {
+                BatchItem::Path(path) => parse_path(path, options),
+                BatchItem::File(path, content) => parse_any_file(path, content, options),
+            })
+            .collect()
+    });
+
+    let mut batch = Batch::default();
+    for (item, outcome) in items.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => batch.results.push(result),
+            Err(message) => batch.failures.push((item.path().to_string(), message)),
+        }
+    }
+    batch
+}
+
+/// Batch parse files, handing results to `callback` a batch at a time
+///
+/// `batch_parse_files` and `batch_parse_paths` return nothing until every
+/// file is parsed and hold every result at once. This parses `batch_size`
+/// files at a time in parallel, calls `callback` with each batch as soon
+/// as it's done, and then drops it, so indexing a large repository reports
+/// progress as it goes and needs memory for one batch. A file that fails
+/// doesn't stop the parse.
+///
+/// Args:
+///     files: Paths to read natively, or `(path, content)` pairs; the two
+///         may be mixed
+///     callback: Called after each batch as
+///         `callback(results, failures, progress)`: the batch's ParseResults
+///         in input order, `(path, error)` for each of its files that
+///         failed, and a ParseProgress. Returning False stops the parse;
+///         an exception it raises propagates.
+///     batch_size: Files parsed per batch
+///     unit_kinds, sql_dialect, parse_template_host, content_refs,
+///         skip_generated: As for `batch_parse_files`
+///
+/// Returns:
+///     The final ParseProgress. Raises ValueError if batch_size is 0.
+#[pyfunction]
+#[pyo3(signature = (
+    files,
+    callback,
+    batch_size=DEFAULT_BATCH_SIZE,
+    unit_kinds=None,
+    sql_dialect=None,
+    parse_template_host=false,
+    content_refs=false,
+    skip_generated=false
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_parse_stream(
+    py: Python<'_>,
+    files: Vec<BatchItem>,
+    callback: Bound<'_, PyAny>,
+    batch_size: usize,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+    skip_generated: bool,
+) -> PyResult<ParseProgress> {
+    if batch_size == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err("batch_size must be at least 1"));
+    }
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let options = ParseOptions { skip_generated, ..options };
+    let started = Instant::now();
+    let mut progress = ParseProgress { files_total: files.len(), ..Default::default() };
+    for items in files.chunks(batch_size) {
+        let batch = py.detach(|| parse_items(items, &options));
+        progress.record(&batch, started);
+        let reply = callback.call1((batch.results, batch.failures, progress.clone()))?;
+        if reply.extract::<bool>().ok() == Some(false) {
+            break;
+        }
+    }
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_record_failures_and_progress() {
+        let items = vec![
+            BatchItem::File("a.py".to_string(), "def a():\n    pass\n\ndef b():\n    pass\n".to_string()),
+            BatchItem::Path("/no/such/file.py".to_string()),
+            BatchItem::File("c.rs".to_string(), "fn c() {}\n".to_string()),
+        ];
+        let started = Instant::now();
+        let mut progress = ParseProgress { files_total: 5, ..Default::default() };
+        let batch = parse_items(&items, &ParseOptions::default());
+        progress.record(&batch, started);
+
+        let parsed: Vec<&str> = batch.results.iter().map(|result| result.file_path.as_str()).collect();
+        assert_eq!(parsed, vec!["a.py", "c.rs"]);
+        assert_eq!(batch.failures.len(), 1);
+        assert_eq!(batch.failures[0].0, "/no/such/file.py");
+        assert_eq!((progress.files_done, progress.units_found, progress.errors), (3, 3, 1));
+
+        progress.record(&parse_items(&items[2..], &ParseOptions::default()), started);
+        assert_eq!((progress.files_done, progress.units_found, progress.errors), (4, 4, 1));
+    }
+}
@@ -0,0 +1,13 @@
+use clap::Parser;
+use mcp_performance_core::cli::{run, Cli};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run(Cli::parse(), &mut std::io::stdout().lock()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("memory-core: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tree_sitter::{Node, Parser};
+
+use crate::parsing::{ParseResult, SemanticUnit};
+
+/// Rule attributes that reference other targets, and the edge kind each produces
+const EDGE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("deps", "dep"),
+    ("runtime_deps", "runtime_dep"),
+    ("exported_deps", "export"),
+    ("exports", "export"),
+    ("data", "data"),
+    ("visibility", "visibility"),
+];
+
+/// A dependency between two build targets, by absolute label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct BuildEdge {
+    #[pyo3(get)]
+    pub source: String, // `//pkg:target` declaring the attribute
+    #[pyo3(get)]
+    pub target: String, // Referenced label (a package spec such as `//foo:__pkg__` for visibility)
+    #[pyo3(get)]
+    pub kind: String, // "dep", "runtime_dep", "export", "data", or "visibility"
+}
+
+#[pymethods]
+impl BuildEdge {
+    fn __repr__(&self) -> String {
+        format!("BuildEdge({} -[{}]-> {})", self.source, self.kind, self.target)
+    }
+}
+
+/// Whether a file is a Bazel or Buck build file (`BUILD`, `BUILD.bazel`, `BUCK`, `TARGETS`)
+pub fn is_build_file(file_path: &str) -> bool {
+    let name = std::path::Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    matches!(name, "BUILD" | "BUILD.bazel" | "BUCK" | "TARGETS")
+}
+
+/// Package of a build file: its directory relative to the workspace root
+fn package_of(file_path: &str) -> String {
+    let parent = std::path::Path::new(file_path)
+        .parent()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    parent.trim_start_matches("./").trim_matches('/').to_string()
+}
+
+/// Make a label absolute: `:lib` and `lib` are package-relative, `//a/b`
+/// is shorthand for `//a/b:b`; external (`@repo//x`) and cell (`cell//x`)
+/// labels keep their prefix
+pub fn normalize_label(label: &str, package: &str) -> String {
+    let label = label.trim();
+    if let Some(name) = label.strip_prefix(':') {
+        return format!("//{}:{}", package, name);
+    }
+    let Some(slashes) = label.find("//") else {
+        return format!("//{}:{}", package, label);
+    };
+    let path = &label[slashes + 2..];
+    if path.contains(':') {
+        return label.to_string();
+    }
+    let last = path.rsplit('/').next().unwrap_or(path);
+    format!("{}:{}", label, last)
+}
+
+fn node_text<'a>(node: Node, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or("")
+}
+
+/// The value of a string literal node, without quotes or prefixes
+fn string_value(node: Node, source: &[u8]) -> Option<String> {
+    if node.kind() != "string" {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let content: String = node
+        .children(&mut cursor)
+        .filter(|c| c.kind() == "string_content")
+        .map(|c| node_text(c, source))
+        .collect();
+    Some(content)
+}
+
+/// String literals in an attribute value, looking through lists, `+`
+/// concatenation, and `select()` (whose dict keys are conditions, not labels)
+fn collect_strings(node: Node, source: &[u8], out: &mut Vec<String>) {
+    if let Some(value) = string_value(node, source) {
+        out.push(value);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "pair" => {
+                if let Some(value) = child.child_by_field_name("value") {
+                    collect_strings(value, source, out);
+                }
+            }
+            "comment" => {}
+            _ => collect_strings(child, source, out),
+        }
+    }
+}
+
+/// Keyword arguments of a call, by name
+fn keyword_arguments<'a>(call: Node<'a>, source: &[u8]) -> Vec<(String, Node<'a>)> {
+    let Some(arguments) = call.child_by_field_name("arguments") else { return Vec::new() };
+    let mut cursor = arguments.walk();
+    arguments
+        .named_children(&mut cursor)
+        .filter(|a| a.kind() == "keyword_argument")
+        .filter_map(|a| {
+            let name = a.child_by_field_name("name")?;
+            let value = a.child_by_field_name("value")?;
+            Some((node_text(name, source).to_string(), value))
+        })
+        .collect()
+}
+
+/// Top-level rule invocations (`cc_library(name = ...)`) of a build file
+fn top_level_calls(root: Node) -> Vec<Node> {
+    let mut cursor = root.walk();
+    root.named_children(&mut cursor)
+        .filter(|n| n.kind() == "expression_statement")
+        .filter_map(|n| n.named_child(0))
+        .filter(|n| n.kind() == "call")
+        .collect()
+}
+
+/// Parse a BUILD/BUCK file into one "target" unit per rule invocation.
+///
+/// Starlark is parsed with the Python grammar. Each unit's metadata holds the
+/// rule name, the absolute `label`, and the comma-joined absolute labels of
+/// each dependency attribute (`deps`, `runtime_deps`, `exports`, `data`,
+/// `visibility`); targets without `visibility` inherit the package's
+/// `default_visibility`. Paths are taken as relative to the workspace root.
+pub fn parse_build_file(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .map_err(|e| e.to_string())?;
+    let tree = parser.parse(source_code, None).ok_or("Failed to parse build file")?;
+    let source = source_code.as_bytes();
+    let package = package_of(file_path);
+
+    let mut default_visibility = None;
+    let mut units = Vec::new();
+    for call in top_level_calls(tree.root_node()) {
+        let Some(function) = call.child_by_field_name("function") else { continue };
+        // `native.cc_library` in macros
+        let rule = node_text(function, source).rsplit('.').next().unwrap_or("").to_string();
+        let arguments = keyword_arguments(call, source);
+
+        if rule == "package" {
+            if let Some((_, value)) = arguments.iter().find(|(name, _)| name == "default_visibility") {
+                let mut labels = Vec::new();
+                collect_strings(*value, source, &mut labels);
+                default_visibility = Some(labels);
+            }
+            continue;
+        }
+
+        let Some(name) = arguments
+            .iter()
+            .find(|(arg, _)| arg == "name")
+            .and_then(|(_, value)| string_value(*value, source))
+        else {
+            continue;
+        };
+
+        let label = format!("//{}:{}", package, name);
+        let mut metadata = HashMap::new();
+        metadata.insert("rule".to_string(), rule.clone());
+        metadata.insert("label".to_string(), label);
+        for (attribute, _) in EDGE_ATTRIBUTES {
+            let Some((_, value)) = arguments.iter().find(|(arg, _)| arg == attribute) else { continue };
+            let mut labels = Vec::new();
+            collect_strings(*value, source, &mut labels);
+            let labels: Vec<String> = labels.iter().map(|l| normalize_label(l, &package)).collect();
+            metadata.insert(attribute.to_string(), labels.join(","));
+        }
+        if !metadata.contains_key("visibility") {
+            if let Some(labels) = &default_visibility {
+                let labels: Vec<String> = labels.iter().map(|l| normalize_label(l, &package)).collect();
+                metadata.insert("visibility".to_string(), labels.join(","));
+            }
+        }
+
+        let content = node_text(call, source);
+        units.push(SemanticUnit {
+            unit_type: "target".into(),
+            name: name.clone(),
+            parent_name: None,
+            qualified_name: name,
+            start_line: call.start_position().row + 1,
+            end_line: call.end_position().row + 1,
+            start_byte: call.start_byte(),
+            end_byte: call.end_byte(),
+            signature: content.lines().next().unwrap_or("").trim().to_string(),
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: content.to_string(),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: "Starlark".into(),
+            metadata,
+        });
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Starlark".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings: Vec::new(),
+        file_classification: String::new(),
+    })
+}
+
+/// Dependency edges declared by the targets of a parsed build file
+pub fn target_edges(result: &ParseResult) -> Vec<BuildEdge> {
+    let mut edges = Vec::new();
+    for unit in result.units.iter().filter(|u| u.unit_type == "target") {
+        let Some(source) = unit.metadata.get("label") else { continue };
+        for (attribute, kind) in EDGE_ATTRIBUTES {
+            let Some(labels) = unit.metadata.get(*attribute) else { continue };
+            edges.extend(labels.split(',').filter(|l| !l.is_empty()).map(|target| BuildEdge {
+                source: source.clone(),
+                target: target.to_string(),
+                kind: kind.to_string(),
+            }));
+        }
+    }
+    edges
+}
+
+/// Extract the target dependency graph from Bazel/Buck build files
+///
+/// `files` holds `(path, source)` pairs with paths relative to the workspace
+/// root (the path's directory is the package). Returns one edge per label in
+/// each target's `deps`, `runtime_deps`, `exports`/`exported_deps`, `data`,
+/// and `visibility`, in input order.
+#[pyfunction]
+pub fn extract_build_graph(files: Vec<(String, String)>) -> PyResult<Vec<BuildEdge>> {
+    let mut edges = Vec::new();
+    for (path, source) in &files {
+        let result = parse_build_file(path, source).map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+        edges.extend(target_edges(&result));
+    }
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD: &str = r#"load("@rules_cc//cc:defs.bzl", "cc_library")
+
+package(default_visibility = ["//visibility:public"])
+
+cc_library(
+    name = "core",
+    srcs = glob(["*.cc"]),
+    deps = [
+        ":util",
+        "//third_party/absl",
+        "@com_google_protobuf//:protobuf",
+    ] + select({
+        "//conditions:linux": ["//platform/linux:io"],
+        "//conditions:default": [],
+    }),
+)
+
+cc_library(
+    name = "util",
+    visibility = [":__pkg__"],
+)
+"#;
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label(":util", "src/core"), "//src/core:util");
+        assert_eq!(normalize_label("util", "src/core"), "//src/core:util");
+        assert_eq!(normalize_label("//third_party/absl", "x"), "//third_party/absl:absl");
+        assert_eq!(normalize_label("@repo//:lib", "x"), "@repo//:lib");
+        assert_eq!(normalize_label("fbcode//a/b:c", "x"), "fbcode//a/b:c");
+    }
+
+    #[test]
+    fn test_build_file_targets() {
+        let result = parse_build_file("src/core/BUILD.bazel", BUILD).unwrap();
+        let names: Vec<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["core", "util"]);
+
+        let core = &result.units[0];
+        assert_eq!(core.metadata["rule"], "cc_library");
+        assert_eq!(core.metadata["label"], "//src/core:core");
+        assert_eq!(
+            core.metadata["deps"],
+            "//src/core:util,//third_party/absl:absl,@com_google_protobuf//:protobuf,//platform/linux:io"
+        );
+        assert_eq!(core.metadata["visibility"], "//visibility:public");
+        assert_eq!(result.units[1].metadata["visibility"], "//src/core:__pkg__");
+    }
+
+    #[test]
+    fn test_target_edges() {
+        let result = parse_build_file("pkg/BUCK", "java_library(name = 'a', deps = [':b'], exported_deps = ['//lib:c'])\n").unwrap();
+        let edges = target_edges(&result);
+        let described: Vec<(&str, &str, &str)> =
+            edges.iter().map(|e| (e.source.as_str(), e.target.as_str(), e.kind.as_str())).collect();
+        assert_eq!(described, vec![("//pkg:a", "//pkg:b", "dep"), ("//pkg:a", "//lib:c", "export")]);
+    }
+
+    #[test]
+    fn test_is_build_file() {
+        assert!(is_build_file("a/b/BUILD"));
+        assert!(is_build_file("BUILD.bazel"));
+        assert!(is_build_file("x/TARGETS"));
+        assert!(!is_build_file("a/build.py"));
+    }
+}
@@ -0,0 +1,339 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+use crate::parsing::{ParseResult, SemanticUnit};
+
+/// A content line with continuation lines folded back in, and the physical
+/// lines it spans
+struct ContentLine {
+    text: String,
+    first: usize, // 1-based
+    last: usize,
+    start_byte: usize,
+    end_byte: usize,
+}
+
+/// Content lines of an iCalendar file: a line starting with a space or tab
+/// continues the one before (RFC 5545 section 3.1)
+fn unfold(source: &str) -> Vec<ContentLine> {
+    let mut lines: Vec<ContentLine> = Vec::new();
+    let mut offset = 0;
+    for (index, raw) in source.split_inclusive('\n').enumerate() {
+        let (start, end) = (offset, offset + raw.len());
+        offset = end;
+        let text = raw.trim_end_matches(['\r', '\n']);
+        match (text.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(line)) => {
+                line.text.push_str(rest);
+                line.last = index + 1;
+                line.end_byte = end;
+            }
+            _ if text.trim().is_empty() => {}
+            _ => {
+                let (first, last) = (index + 1, index + 1);
+                lines.push(ContentLine { text: text.to_string(), first, last, start_byte: start, end_byte: end });
+            }
+        }
+    }
+    lines
+}
+
+/// A property's name (uppercased), parameters and value:
+/// `ATTENDEE;CN="Doe, Jo";ROLE=CHAIR:mailto:jo@example.com`
+fn property(line: &str) -> Option<(String, HashMap<String, String>, &str)> {
+    // The value starts at the first colon outside a quoted parameter value
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in line[..colon].char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&line[start..colon]);
+    let name = parts[0].to_ascii_uppercase();
+    let params = parts[1..]
+        .iter()
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some((name, params, &line[colon + 1..]))
+}
+
+/// A TEXT value with its escapes undone
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+/// A DATE or DATE-TIME value as ISO 8601, with its Unix time when it names
+/// an instant: UTC times (`Z`) and all-day dates, read as UTC midnight.
+/// Local times in a `TZID` zone keep their wall-clock time.
+fn date_time(value: &str) -> Option<(String, Option<i64>)> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((time.format("%Y-%m-%dT%H:%M:%SZ").to_string(), Some(time.and_utc().timestamp())));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some((time.format("%Y-%m-%dT%H:%M:%S").to_string(), None));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some((date.format("%Y-%m-%d").to_string(), date.and_hms_opt(0, 0, 0).map(|time| time.and_utc().timestamp())))
+}
+
+/// A calendar user as "Name <address>", or just the address
+fn calendar_user(value: &str, params: &HashMap<String, String>) -> String {
+    let address = value.trim();
+    let address = address.strip_prefix("mailto:").or_else(|| address.strip_prefix("MAILTO:")).unwrap_or(address);
+    match params.get("CN").filter(|name| !name.is_empty()) {
+        Some(name) => format!("{} <{}>", name, address),
+        None => address.to_string(),
+    }
+}
+
+/// The properties of an event read so far
+#[derive(Default)]
+struct Event {
+    first: usize,
+    start_byte: usize,
+    summary: String,
+    description: String,
+    location: String,
+    attendees: Vec<String>,
+    metadata: HashMap<String, String>,
+}
+
+impl Event {
+    fn read(&mut self, name: &str, params: &HashMap<String, String>, value: &str) {
+        let mut set = |key: &str, text: String| {
+            if !text.is_empty() {
+                self.metadata.insert(key.to_string(), text);
+            }
+        };
+        match name {
+            "SUMMARY" => self.summary = unescape(value).trim().to_string(),
+            "DESCRIPTION" => self.description = unescape(value).trim().to_string(),
+            "LOCATION" => self.location = unescape(value).trim().to_string(),
+            "ATTENDEE" => self.attendees.push(calendar_user(value, params)),
+            "ORGANIZER" => set("organizer", calendar_user(value, params)),
+            "UID" => set("uid", value.trim().to_string()),
+            "STATUS" => set("status", value.trim().to_ascii_lowercase()),
+            "RRULE" => set("rrule", value.trim().to_string()),
+            "CATEGORIES" => {
+                let categories: Vec<String> = unescape(value).split(',').map(|c| c.trim().to_string()).collect();
+                set("categories", categories.join(", "));
+            }
+            "DTSTART" | "DTEND" | "RECURRENCE-ID" => {
+                let key = match name {
+                    "DTSTART" => "start",
+                    "DTEND" => "end",
+                    _ => "recurrence_id",
+                };
+                let Some((iso, timestamp)) = date_time(value) else { return };
+                if let (Some(timestamp), "DTSTART") = (timestamp, name) {
+                    set("timestamp", timestamp.to_string());
+                }
+                if let (Some(zone), "DTSTART") = (params.get("TZID"), name) {
+                    set("timezone", zone.clone());
+                }
+                if params.get("VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || !iso.contains('T') {
+                    set("all_day", "true".to_string());
+                }
+                set(key, iso);
+            }
+            _ => {}
+        }
+    }
+
+    /// The event as a unit: its title, when and where, who, then its description
+    fn into_unit(mut self, last: usize, end_byte: usize) -> SemanticUnit {
+        let name = if self.summary.is_empty() { "(untitled event)".to_string() } else { self.summary.clone() };
+        let start = self.metadata.get("start").cloned().unwrap_or_default();
+        let mut content = vec![name.clone()];
+        if !start.is_empty() {
+            let end = self.metadata.get("end").map_or(String::new(), |end| format!(" to {}", end));
+            content.push(format!("When: {}{}", start, end));
+        }
+        if !self.location.is_empty() {
+            content.push(format!("Where: {}", self.location));
+            self.metadata.insert("location".to_string(), self.location.clone());
+        }
+        if !self.attendees.is_empty() {
+            content.push(format!("Attendees: {}", self.attendees.join(", ")));
+            self.metadata.insert("attendees".to_string(), self.attendees.join(", "));
+        }
+        if !self.description.is_empty() {
+            content.push(String::new());
+            content.push(self.description.clone());
+        }
+        SemanticUnit {
+            unit_type: "event".into(),
+            name: name.clone(),
+            parent_name: None,
+            qualified_name: name.clone(),
+            start_line: self.first,
+            end_line: last,
+            start_byte: self.start_byte,
+            end_byte,
+            signature: if start.is_empty() { name } else { format!("{}: {}", start, name) },
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: content.join("\n"),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: "Calendar".into(),
+            metadata: self.metadata,
+        }
+    }
+}
+
+/// Parse an iCalendar (`.ics`) file into one "event" unit per VEVENT.
+///
+/// A unit's content reads as a memory: the title, a "When:" line, location,
+/// attendees, then the description. `metadata` holds `start` and `end` in
+/// ISO 8601 (`Z` for UTC, a date alone for all-day events, otherwise local
+/// time in `timezone`), `timestamp` (Unix time of the start, when it names
+/// an instant), `organizer`, `attendees`, `location`, `status`, `uid`,
+/// `rrule` for recurring events and `recurrence_id` for changed occurrences.
+/// Alarms and other components are skipped.
+pub fn parse_calendar(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let lines = unfold(source_code);
+    if !lines.first().is_some_and(|line| line.text.eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+        return Err("Not an iCalendar file: missing BEGIN:VCALENDAR".to_string());
+    }
+
+    let mut units = Vec::new();
+    let mut warnings = Vec::new();
+    let mut event: Option<Event> = None;
+    // Components nested in the open event (VALARM), whose properties aren't the event's
+    let mut nested = 0;
+    for line in &lines {
+        let Some((name, params, value)) = property(&line.text) else {
+            warnings.push(format!("Line {}: not a property", line.first));
+            continue;
+        };
+        match (name.as_str(), value.trim().to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") if event.is_none() => {
+                event = Some(Event { first: line.first, start_byte: line.start_byte, ..Default::default() })
+            }
+            ("END", "VEVENT") if nested == 0 => match event.take() {
+                Some(open) => units.push(open.into_unit(line.last, line.end_byte)),
+                None => warnings.push(format!("Line {}: END:VEVENT without BEGIN", line.first)),
+            },
+            ("BEGIN", _) if event.is_some() => nested += 1,
+            ("END", _) if event.is_some() => nested -= 1,
+            _ if nested == 0 => {
+                if let Some(open) = event.as_mut() {
+                    open.read(&name, &params, value);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(open) = event {
+        warnings.push(format!("Line {}: event is never closed", open.first));
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Calendar".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:review-14@example.com\r
+DTSTART:20240314T150000Z\r
+DTEND:20240314T160000Z\r
+SUMMARY:Design review: storage\\, v2\r
+LOCATION:Room 4\r
+ORGANIZER;CN=Alice:mailto:alice@example.com\r
+ATTENDEE;CN=\"Stone, Bob\";ROLE=REQ-PARTICIPANT:mailto:bob@example.com\r
+ATTENDEE:mailto:carol@example.com\r
+DESCRIPTION:Decide between sharding schemes.\\nBring numbers from the lo\r
+ ad test.\r
+BEGIN:VALARM\r
+DESCRIPTION:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Berlin:20240318T093000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO\r
+SUMMARY:Standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240401\r
+SUMMARY:Release freeze\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_events_become_units() {
+        let result = parse_calendar("team.ics", ICS).unwrap();
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        let names: Vec<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["Design review: storage, v2", "Standup", "Release freeze"]);
+
+        let review = &result.units[0];
+        assert_eq!((review.start_line, review.end_line), (6, 20));
+        assert_eq!(review.signature, "2024-03-14T15:00:00Z: Design review: storage, v2");
+        assert_eq!(review.metadata["timestamp"], "1710428400");
+        assert_eq!(review.metadata["attendees"], "Stone, Bob <bob@example.com>, carol@example.com");
+        assert_eq!(review.metadata["organizer"], "Alice <alice@example.com>");
+        assert_eq!(
+            review.content,
+            "Design review: storage, v2\nWhen: 2024-03-14T15:00:00Z to 2024-03-14T16:00:00Z\nWhere: Room 4\n\
+             Attendees: Stone, Bob <bob@example.com>, carol@example.com\n\n\
+             Decide between sharding schemes.\nBring numbers from the load test."
+        );
+
+        let standup = &result.units[1];
+        assert_eq!(standup.metadata["start"], "2024-03-18T09:30:00");
+        assert_eq!(standup.metadata["timezone"], "Europe/Berlin");
+        assert!(!standup.metadata.contains_key("timestamp"));
+        assert_eq!(result.units[2].metadata["all_day"], "true");
+        assert!(parse_calendar("notes.ics", "hello").is_err());
+    }
+}
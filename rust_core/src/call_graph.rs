@@ -0,0 +1,169 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use streaming_iterator::StreamingIterator;
+use tree_sitter::QueryCursor;
+
+use crate::parsing::{with_thread_parser, ParseOptions, SupportedLanguage};
+use crate::query_packs;
+
+/// The calls made by one function
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct FunctionCalls {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub qualified_name: String,
+    #[pyo3(get)]
+    pub start_line: usize,
+    #[pyo3(get)]
+    pub end_line: usize,
+    #[pyo3(get)]
+    pub calls: Vec<String>, // Called function and method names, once each, in order of first call
+}
+
+#[pymethods]
+impl FunctionCalls {
+    fn __repr__(&self) -> String {
+        format!("FunctionCalls(name={}, calls={})", self.qualified_name, self.calls.len())
+    }
+}
+
+/// The functions of one file and what each of them calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct CallGraph {
+    #[pyo3(get)]
+    pub file_path: String,
+    #[pyo3(get)]
+    pub language: String,
+    #[pyo3(get)]
+    pub functions: Vec<FunctionCalls>,
+}
+
+#[pymethods]
+impl CallGraph {
+    /// Qualified names of the functions in this file that call `name`
+    pub fn callers(&self, name: &str) -> Vec<String> {
+        self.functions
+            .iter()
+            .filter(|function| function.calls.iter().any(|call| call == name))
+            .map(|function| function.qualified_name.clone())
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CallGraph(file={}, functions={})", self.file_path, self.functions.len())
+    }
+}
+
+/// Extract the functions of a source file and the names each one calls.
+/// Calls are attributed to the innermost function containing them; calls
+/// outside any function (module level) are left out.
+pub(crate) fn extract_call_graph(file_path: &str, source_code: &str) -> Result<CallGraph, String> {
+    let extension = std::path::Path::new(file_path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let lang = SupportedLanguage::from_extension(extension)
+        .ok_or(format!("Unsupported file extension: {}", extension))?;
+    let query = query_packs::query(lang, "call").ok_or(format!("No call query for {:?}", lang))??;
+
+    let (result, tree) = with_thread_parser(|parser| {
+        parser.parse_tree_with_language(file_path, source_code, lang, &ParseOptions::default())
+    })?;
+    let mut functions: Vec<FunctionCalls> = Vec::new();
+    let mut spans = Vec::new();
+    for unit in result.units.into_iter().filter(|u| u.unit_type == "function") {
+        spans.push(unit.start_byte..unit.end_byte);
+        functions.push(FunctionCalls {
+            name: unit.name,
+            qualified_name: unit.qualified_name,
+            start_line: unit.start_line,
+            end_line: unit.end_line,
+            calls: Vec::new(),
+        });
+    }
+
+    let name_index = query.capture_index_for_name("name");
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), source_code.as_bytes());
+    while let Some(match_) = matches.next() {
+        let Some(callee) = match_.captures.iter().find(|c| Some(c.index) == name_index).map(|c| c.node) else {
+            continue;
+        };
+        let Ok(callee_name) = callee.utf8_text(source_code.as_bytes()) else {
+            continue;
+        };
+        let innermost = spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| span.contains(&callee.start_byte()))
+            .min_by_key(|(_, span)| span.len())
+            .map(|(index, _)| index);
+        if let Some(function) = innermost.map(|index| &mut functions[index]) {
+            if !function.calls.iter().any(|call| call == callee_name) {
+                function.calls.push(callee_name.to_string());
+            }
+        }
+    }
+
+    Ok(CallGraph { file_path: file_path.to_string(), language: result.language, functions })
+}
+
+/// Extract a file's call graph: each function with the names of the
+/// functions and methods it calls
+///
+/// Callees are matched by name only (`self.save()` and `save()` both record
+/// "save"), without resolving imports or types, which is enough to answer
+/// "what calls X?" through `CallGraph.callers`. The language is detected
+/// from `file_path`'s extension. Raises RuntimeError for unsupported files.
+#[pyfunction]
+pub fn parse_call_graph(py: Python<'_>, file_path: String, source_code: String) -> PyResult<CallGraph> {
+    py.detach(|| extract_call_graph(&file_path, &source_code)).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calls(graph: &CallGraph) -> Vec<(&str, Vec<&str>)> {
+        graph
+            .functions
+            .iter()
+            .map(|f| (f.qualified_name.as_str(), f.calls.iter().map(String::as_str).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_calls_are_attributed_to_innermost_function() {
+        let source = "setup()\n\nclass Cart:\n    def total(self):\n        items = self.items()\n        \
+            return sum(price(i) for i in items) + sum([])\n\n    def checkout(self):\n        \
+            def charge():\n            gateway.pay(self.total())\n        charge()\n";
+        let graph = extract_call_graph("cart.py", source).unwrap();
+        assert_eq!(
+            calls(&graph),
+            vec![
+                ("Cart.total", vec!["items", "sum", "price"]),
+                ("Cart.checkout", vec!["charge"]),
+                ("Cart.checkout.charge", vec!["pay", "total"]),
+            ]
+        );
+        assert_eq!(graph.callers("total"), vec!["Cart.checkout.charge"]);
+        assert!(graph.callers("setup").is_empty());
+    }
+
+    #[test]
+    fn test_call_graphs_in_other_languages() {
+        let go = "package main\nfunc run() {\n    s := NewServer()\n    s.Listen(addr())\n}\n";
+        let graph = extract_call_graph("main.go", go).unwrap();
+        assert_eq!(calls(&graph), vec![("run", vec!["NewServer", "Listen", "addr"])]);
+
+        let java = "class A {\n    void f() {\n        List l = new ArrayList();\n        l.add(g());\n    }\n}\n";
+        let graph = extract_call_graph("A.java", java).unwrap();
+        assert_eq!(calls(&graph), vec![("A.f", vec!["ArrayList", "add", "g"])]);
+
+        let rust = "fn main() {\n    let v = Vec::new();\n    v.push(compute(1));\n}\n";
+        let graph = extract_call_graph("main.rs", rust).unwrap();
+        assert_eq!(calls(&graph), vec![("main", vec!["new", "push", "compute"])]);
+
+        assert!(extract_call_graph("notes.txt", "").is_err());
+    }
+}
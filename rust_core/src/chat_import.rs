@@ -0,0 +1,497 @@
+use chrono::{DateTime, Utc};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Longest thread title, in characters, before it's cut short
+const TITLE_CHARS: usize = 80;
+
+/// Slack message subtypes that record channel housekeeping, not conversation
+const SLACK_HOUSEKEEPING: &[&str] = &[
+    "channel_join",
+    "channel_leave",
+    "channel_topic",
+    "channel_purpose",
+    "channel_name",
+    "channel_archive",
+    "channel_unarchive",
+    "group_join",
+    "group_leave",
+    "pinned_item",
+];
+
+/// One message of a chat export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct ChatMessage {
+    #[pyo3(get)]
+    pub id: String, // Slack `ts`, Discord message id
+    #[pyo3(get)]
+    pub author: String, // Display name, falling back to the user id
+    #[pyo3(get)]
+    pub author_id: String,
+    #[pyo3(get)]
+    pub timestamp: f64, // Unix seconds
+    #[pyo3(get)]
+    pub text: String, // Mentions, channel links and URLs rendered readably
+    #[pyo3(get)]
+    pub metadata: HashMap<String, String>, // E.g. attached file names, comma-joined
+}
+
+#[pymethods]
+impl ChatMessage {
+    fn __repr__(&self) -> String {
+        format!("ChatMessage(author={}, timestamp={})", self.author, self.timestamp)
+    }
+}
+
+/// A thread, or a channel's unthreaded messages of one day: one memory record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct ChatThread {
+    #[pyo3(get)]
+    pub id: String, // "slack:<channel>:<thread ts>", or "<platform>:<channel>:<YYYY-MM-DD>" for a day
+    #[pyo3(get)]
+    pub channel: String,
+    #[pyo3(get)]
+    pub title: String, // First line of a thread's first message, or "#channel on <date>"
+    #[pyo3(get)]
+    pub started: f64,
+    #[pyo3(get)]
+    pub ended: f64,
+    #[pyo3(get)]
+    pub participants: Vec<String>, // Authors in order of first message
+    #[pyo3(get)]
+    pub messages: Vec<ChatMessage>, // In time order
+    #[pyo3(get)]
+    pub text: String, // Transcript, one "[YYYY-MM-DD HH:MM] author: text" entry per message
+}
+
+#[pymethods]
+impl ChatThread {
+    fn __repr__(&self) -> String {
+        format!("ChatThread(id={}, messages={})", self.id, self.messages.len())
+    }
+}
+
+/// The threads of a chat export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct ChatImport {
+    #[pyo3(get)]
+    pub threads: Vec<ChatThread>, // By start time
+    #[pyo3(get)]
+    pub warnings: Vec<String>, // Files or messages that couldn't be read
+}
+
+#[pymethods]
+impl ChatImport {
+    fn __repr__(&self) -> String {
+        let messages: usize = self.threads.iter().map(|thread| thread.messages.len()).sum();
+        format!("ChatImport(threads={}, messages={})", self.threads.len(), messages)
+    }
+}
+
+fn time_of(timestamp: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp.floor() as i64, 0)
+}
+
+fn date_of(timestamp: f64) -> String {
+    time_of(timestamp).map_or_else(String::new, |time| time.format("%Y-%m-%d").to_string())
+}
+
+/// A file's name and the name of the directory holding it
+fn name_and_parent(path: &str) -> (&str, &str) {
+    let mut segments = path.rsplit(['/', '\\']);
+    (segments.next().unwrap_or(""), segments.next().unwrap_or(""))
+}
+
+/// A text's first line, cut to `TITLE_CHARS`
+fn title_of(text: &str) -> String {
+    let line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+    match line.char_indices().nth(TITLE_CHARS) {
+        Some((end, _)) => format!("{}...", line[..end].trim_end()),
+        None => line.to_string(),
+    }
+}
+
+/// Threads of messages grouped by (channel, key), where `day_keys` are the
+/// keys of a channel's unthreaded messages of one day
+fn assemble(
+    platform: &str,
+    groups: HashMap<(String, String), Vec<ChatMessage>>,
+    day_keys: &HashSet<(String, String)>,
+) -> Vec<ChatThread> {
+    let mut threads: Vec<ChatThread> = groups
+        .into_iter()
+        .filter(|(_, messages)| !messages.is_empty())
+        .map(|((channel, key), mut messages)| {
+            messages.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+            let mut participants = Vec::new();
+            let mut text = String::new();
+            for message in &messages {
+                if !participants.contains(&message.author) {
+                    participants.push(message.author.clone());
+                }
+                let time = time_of(message.timestamp).map(|time| time.format("%Y-%m-%d %H:%M"));
+                let time = time.map_or_else(String::new, |time| time.to_string());
+                text.push_str(&format!("[{}] {}: {}\n", time, message.author, message.text));
+            }
+            let title = if day_keys.contains(&(channel.clone(), key.clone())) {
+                format!("#{} on {}", channel, key)
+            } else {
+                title_of(&messages[0].text)
+            };
+            ChatThread {
+                id: format!("{}:{}:{}", platform, channel, key),
+                channel,
+                title,
+                started: messages[0].timestamp,
+                ended: messages[messages.len() - 1].timestamp,
+                participants,
+                text: text.trim_end().to_string(),
+                messages,
+            }
+        })
+        .collect();
+    threads.sort_by(|a, b| a.started.total_cmp(&b.started).then_with(|| a.id.cmp(&b.id)));
+    threads
+}
+
+/// Slack message markup made readable: `<@U1>` as `@name`, `<#C1|general>`
+/// as `#general`, `<!here>` as `@here`, `<url|label>` as `label (url)`,
+/// and HTML entities unescaped
+fn slack_text(text: &str, users: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>').map(|close| open + close) else { break };
+        rendered.push_str(&rest[..open]);
+        let inner = &rest[open + 1..close];
+        let (target, label) = inner.split_once('|').map_or((inner, None), |(target, label)| (target, Some(label)));
+        let shown = match target.chars().next() {
+            Some('@') => format!("@{}", label.or(users.get(&target[1..]).map(String::as_str)).unwrap_or(&target[1..])),
+            Some('#') => format!("#{}", label.unwrap_or(&target[1..])),
+            Some('!') => {
+                let special = target[1..].split('^').next().unwrap_or("");
+                label.map_or_else(|| format!("@{}", special), str::to_string)
+            }
+            _ => label.map_or_else(|| target.to_string(), |label| format!("{} ({})", label, target)),
+        };
+        rendered.push_str(&shown);
+        rest = &rest[close + 1..];
+    }
+    rendered.push_str(rest);
+    rendered.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+/// Display names of a Slack `users.json`, by user id
+fn slack_users(json: &Value) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for user in json.as_array().into_iter().flatten() {
+        let Some(id) = user["id"].as_str() else { continue };
+        let name = [&user["profile"]["display_name"], &user["real_name"], &user["profile"]["real_name"], &user["name"]]
+            .into_iter()
+            .filter_map(Value::as_str)
+            .find(|name| !name.is_empty())
+            .unwrap_or(id);
+        names.insert(id.to_string(), name.to_string());
+    }
+    names
+}
+
+/// Whether a file is one day of a Slack channel (`general/2024-03-04.json`)
+fn is_slack_day(name: &str) -> bool {
+    let digit_or_dash = |(i, c): (usize, u8)| if i == 4 || i == 7 { c == b'-' } else { c.is_ascii_digit() };
+    name.strip_suffix(".json").is_some_and(|stem| stem.len() == 10 && stem.bytes().enumerate().all(digit_or_dash))
+}
+
+pub(crate) fn import_slack(files: &[(String, String)]) -> ChatImport {
+    let mut warnings = Vec::new();
+    let mut users = HashMap::new();
+    for (path, content) in files {
+        if name_and_parent(path).0 == "users.json" {
+            match serde_json::from_str(content) {
+                Ok(json) => users.extend(slack_users(&json)),
+                Err(e) => warnings.push(format!("{}: {}", path, e)),
+            }
+        }
+    }
+
+    let mut groups: HashMap<(String, String), Vec<ChatMessage>> = HashMap::new();
+    let mut day_keys = HashSet::new();
+    for (path, content) in files {
+        let (name, channel) = name_and_parent(path);
+        if !is_slack_day(name) {
+            continue;
+        }
+        let messages: Vec<Value> = match serde_json::from_str(content) {
+            Ok(messages) => messages,
+            Err(e) => {
+                warnings.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+        for message in &messages {
+            let subtype = message["subtype"].as_str().unwrap_or("");
+            if message["type"].as_str().is_some_and(|kind| kind != "message") || SLACK_HOUSEKEEPING.contains(&subtype) {
+                continue;
+            }
+            let Some(ts) = message["ts"].as_str() else {
+                warnings.push(format!("{}: message without a ts", path));
+                continue;
+            };
+            let author_id = message["user"].as_str().or(message["bot_id"].as_str()).unwrap_or("").to_string();
+            let author = [&message["user_profile"]["display_name"], &message["user_profile"]["real_name"]]
+                .into_iter()
+                .filter_map(Value::as_str)
+                .find(|name| !name.is_empty())
+                .or(users.get(&author_id).map(String::as_str))
+                .or(message["username"].as_str())
+                .or(message["bot_profile"]["name"].as_str())
+                .unwrap_or(&author_id)
+                .to_string();
+            let mut metadata = HashMap::new();
+            let attached: Vec<&str> =
+                message["files"].as_array().into_iter().flatten().filter_map(|file| file["name"].as_str()).collect();
+            if !attached.is_empty() {
+                metadata.insert("files".to_string(), attached.join(", "));
+            }
+            if !subtype.is_empty() {
+                metadata.insert("subtype".to_string(), subtype.to_string());
+            }
+            let timestamp = ts.parse::<f64>().unwrap_or(0.0);
+            let key = match message["thread_ts"].as_str() {
+                Some(thread) => thread.to_string(),
+                None => {
+                    let day = name.trim_end_matches(".json").to_string();
+                    day_keys.insert((channel.to_string(), day.clone()));
+                    day
+                }
+            };
+            let text = slack_text(message["text"].as_str().unwrap_or(""), &users);
+            let message = ChatMessage { id: ts.to_string(), author, author_id, timestamp, text, metadata };
+            groups.entry((channel.to_string(), key)).or_default().push(message);
+        }
+    }
+    ChatImport { threads: assemble("slack", groups, &day_keys), warnings }
+}
+
+/// Unix seconds of a Discord package timestamp (`2024-03-04 10:00:00.123000+00:00`,
+/// or RFC 3339 in newer packages)
+fn discord_time(text: &str) -> Option<f64> {
+    let time = DateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M:%S%.f%:z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text.trim()))
+        .ok()?;
+    Some(time.timestamp() as f64 + f64::from(time.timestamp_subsec_millis()) / 1000.0)
+}
+
+/// The rows of a channel's `messages.json` or older `messages.csv`, as
+/// (id, timestamp, contents, attachments)
+fn discord_rows(name: &str, content: &str) -> Result<Vec<[String; 4]>, String> {
+    const FIELDS: [&str; 4] = ["ID", "Timestamp", "Contents", "Attachments"];
+    if name.ends_with(".csv") {
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        let rows: Result<Vec<HashMap<String, String>>, _> = reader.deserialize().collect();
+        let rows = rows.map_err(|e| e.to_string())?;
+        return Ok(rows.into_iter().map(|mut row| FIELDS.map(|field| row.remove(field).unwrap_or_default())).collect());
+    }
+    let rows: Vec<Value> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            FIELDS.map(|field| match &row[field] {
+                Value::String(text) => text.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            })
+        })
+        .collect())
+}
+
+pub(crate) fn import_discord(files: &[(String, String)]) -> ChatImport {
+    let mut warnings = Vec::new();
+    let mut owner = ("".to_string(), "me".to_string());
+    let mut index: HashMap<String, String> = HashMap::new();
+    let mut channels: HashMap<&str, String> = HashMap::new();
+    for (path, content) in files {
+        let (name, parent) = name_and_parent(path);
+        let json = || serde_json::from_str::<Value>(content).map_err(|e| format!("{}: {}", path, e));
+        match (parent, name) {
+            ("account", "user.json") => match json() {
+                Ok(user) => {
+                    let id = user["id"].as_str().unwrap_or("").to_string();
+                    let shown = user["global_name"].as_str().or(user["username"].as_str()).unwrap_or("me");
+                    owner = (id, shown.to_string());
+                }
+                Err(e) => warnings.push(e),
+            },
+            ("messages", "index.json") => match json() {
+                Ok(Value::Object(names)) => {
+                    for (id, name) in names {
+                        index.insert(id, name.as_str().unwrap_or("").to_string());
+                    }
+                }
+                Ok(_) => warnings.push(format!("{}: not an object", path)),
+                Err(e) => warnings.push(e),
+            },
+            (_, "channel.json") => match json() {
+                Ok(channel) => {
+                    let guild = channel["guild"]["name"].as_str();
+                    let shown = match (channel["name"].as_str(), guild) {
+                        (Some(name), Some(guild)) => format!("{} ({})", name, guild),
+                        (Some(name), None) => name.to_string(),
+                        _ => String::new(),
+                    };
+                    channels.insert(parent, shown);
+                }
+                Err(e) => warnings.push(e),
+            },
+            _ => {}
+        }
+    }
+
+    let mut groups: HashMap<(String, String), Vec<ChatMessage>> = HashMap::new();
+    let mut day_keys = HashSet::new();
+    for (path, content) in files {
+        let (name, folder) = name_and_parent(path);
+        if name != "messages.json" && name != "messages.csv" {
+            continue;
+        }
+        let id = folder.trim_start_matches('c');
+        let channel = channels
+            .get(folder)
+            .filter(|name| !name.is_empty())
+            .or(index.get(id).filter(|name| !name.is_empty()))
+            .cloned()
+            .unwrap_or_else(|| id.to_string());
+        let rows = match discord_rows(name, content) {
+            Ok(rows) => rows,
+            Err(e) => {
+                warnings.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+        for [message_id, time, contents, attachments] in rows {
+            let Some(timestamp) = discord_time(&time) else {
+                warnings.push(format!("{}: message {} has an invalid timestamp", path, message_id));
+                continue;
+            };
+            let mut metadata = HashMap::new();
+            if !attachments.trim().is_empty() {
+                metadata.insert("files".to_string(), attachments.split_whitespace().collect::<Vec<_>>().join(", "));
+            }
+            let day = date_of(timestamp);
+            day_keys.insert((channel.clone(), day.clone()));
+            let (author_id, author) = owner.clone();
+            let message = ChatMessage { id: message_id, author, author_id, timestamp, text: contents, metadata };
+            groups.entry((channel.clone(), day)).or_default().push(message);
+        }
+    }
+    ChatImport { threads: assemble("discord", groups, &day_keys), warnings }
+}
+
+/// Import a Slack workspace export as threaded memory records
+///
+/// Args:
+///     files: (export-relative path, content) pairs from the unzipped
+///         export: `users.json` and each channel's `<channel>/<date>.json`
+///         files; other files are skipped
+///
+/// Returns:
+///     ChatImport with one ChatThread per thread (a message and its
+///     replies) and one per channel per day for messages outside a thread.
+///     Authors are named from `users.json`; mentions, channel links and
+///     URLs are rendered readably. Join/leave and other housekeeping
+///     messages are left out.
+#[pyfunction]
+pub fn import_slack_export(py: Python<'_>, files: Vec<(String, String)>) -> ChatImport {
+    py.detach(|| import_slack(&files))
+}
+
+/// Import a Discord data package as memory records
+///
+/// Args:
+///     files: (package-relative path, content) pairs: `account/user.json`,
+///         `messages/index.json` and each channel's `channel.json` and
+///         `messages.json` (or `messages.csv` in older packages); other
+///         files are skipped
+///
+/// Returns:
+///     ChatImport with one ChatThread per channel per day. A data package
+///     holds only its owner's messages, so every message is authored by
+///     the account in `user.json`; channels are named "name (server)".
+#[pyfunction]
+pub fn import_discord_package(py: Python<'_>, files: Vec<(String, String)>) -> ChatImport {
+    py.detach(|| import_discord(&files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(path, content)| (path.to_string(), content.to_string())).collect()
+    }
+
+    #[test]
+    fn test_slack_export() {
+        let users = r#"[{"id": "U1", "name": "alice", "profile": {"display_name": "Alice"}},
+                        {"id": "U2", "name": "bob", "real_name": "Bob Stone", "profile": {"display_name": ""}}]"#;
+        let day = r#"[
+            {"type": "message", "subtype": "channel_join", "user": "U2", "text": "<@U2> has joined",
+             "ts": "1709546400.000000"},
+            {"type": "message", "user": "U1", "text": "Should we pin &lt;tokio&gt; to 1.36?", "ts": "1709550000.000100",
+             "thread_ts": "1709550000.000100", "reply_count": 1},
+            {"type": "message", "user": "U2", "text": "Yes, <@U1> see <https://github.com/x/y/pull/7|the PR>",
+             "ts": "1709551000.000200", "thread_ts": "1709550000.000100", "parent_user_id": "U1"},
+            {"type": "message", "user": "U2", "text": "Deploying now in <#C9|ops>", "ts": "1709560000.000300",
+             "files": [{"name": "plan.pdf"}]}
+        ]"#;
+        let import = import_slack(&files(&[("users.json", users), ("eng/2024-03-04.json", day), ("eng/README", "")]));
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        let ids: Vec<&str> = import.threads.iter().map(|thread| thread.id.as_str()).collect();
+        assert_eq!(ids, vec!["slack:eng:1709550000.000100", "slack:eng:2024-03-04"]);
+
+        let thread = &import.threads[0];
+        assert_eq!(thread.title, "Should we pin <tokio> to 1.36?");
+        assert_eq!(thread.participants, vec!["Alice", "Bob Stone"]);
+        assert_eq!(thread.messages[1].text, "Yes, @Alice see the PR (https://github.com/x/y/pull/7)");
+        assert_eq!(thread.text.lines().next(), Some("[2024-03-04 11:00] Alice: Should we pin <tokio> to 1.36?"));
+
+        let loose = &import.threads[1];
+        assert_eq!(loose.title, "#eng on 2024-03-04");
+        assert_eq!(loose.messages[0].text, "Deploying now in #ops");
+        assert_eq!(loose.messages[0].metadata["files"], "plan.pdf");
+    }
+
+    #[test]
+    fn test_discord_package() {
+        let messages = r#"[
+            {"ID": 11, "Timestamp": "2024-03-04 10:00:00.500000+00:00", "Contents": "ship it", "Attachments": ""},
+            {"ID": 12, "Timestamp": "2024-03-05 09:00:00+00:00", "Contents": "rolled back", "Attachments": ""}
+        ]"#;
+        let csv = "ID,Timestamp,Contents,Attachments\n21,2024-03-04 12:00:00.000000+00:00,\"lunch, anyone?\",\n";
+        let import = import_discord(&files(&[
+            ("account/user.json", r#"{"id": "7", "username": "dana"}"#),
+            ("messages/index.json", r#"{"100": "general in Infra", "200": "Direct Message with bob"}"#),
+            ("messages/c100/channel.json", r#"{"name": "general", "guild": {"id": "1", "name": "Infra"}}"#),
+            ("messages/c100/messages.json", messages),
+            ("messages/c200/messages.csv", csv),
+        ]));
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        let titles: Vec<&str> = import.threads.iter().map(|thread| thread.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "#general (Infra) on 2024-03-04",
+                "#Direct Message with bob on 2024-03-04",
+                "#general (Infra) on 2024-03-05",
+            ]
+        );
+        assert_eq!(import.threads[0].messages[0].timestamp, 1709546400.5);
+        assert_eq!(import.threads[1].messages[0].text, "lunch, anyone?");
+        assert_eq!(import.threads[1].participants, vec!["dana"]);
+    }
+}
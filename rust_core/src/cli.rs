@@ -0,0 +1,256 @@
+//! The `memory-core` command-line tool: index, search and inspect a store
+//! of parsed units without going through the MCP server
+//!
+//! The store is a saved `SymbolIndex` (JSON), so the Python side can load
+//! what the tool indexes and vice versa.
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::keyword_index::KeywordIndex;
+use crate::parsing::{parse_path, ParseOptions, SemanticUnit};
+use crate::repo_scan::{scan_repository_files, DEFAULT_MAX_FILE_BYTES};
+use crate::symbol_index::{IndexedFile, SymbolIndex};
+
+#[derive(Debug, Parser)]
+#[command(name = "memory-core", version, about = "Index, search and inspect a memory store of code units")]
+pub struct Cli {
+    /// Store file, created by `index` if missing
+    #[arg(long, global = true, default_value = ".memory-core.json")]
+    pub store: String,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Parse a directory into the store, replacing what it held for that directory
+    Index {
+        dir: String,
+        /// Only index files matching these gitignore-style globs
+        #[arg(long)]
+        include: Vec<String>,
+        #[arg(long)]
+        exclude: Vec<String>,
+        #[arg(long, default_value_t = DEFAULT_MAX_FILE_BYTES)]
+        max_file_bytes: u64,
+        /// Leave out generated and minified files
+        #[arg(long)]
+        skip_generated: bool,
+    },
+    /// Rank stored units against a keyword query (BM25)
+    Search {
+        query: String,
+        #[arg(short = 'k', long, default_value_t = 10)]
+        top_k: usize,
+        /// Print one JSON object per hit
+        #[arg(long)]
+        json: bool,
+    },
+    /// Count stored files and units by language and unit type
+    Stats,
+    /// Re-parse stored files and report those missing or changed on disk
+    Verify,
+    /// Write every stored unit as a line of JSON
+    Export {
+        /// File to write instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
+
+/// A unit with the file it came from, as `search --json` and `export` print it
+#[derive(Serialize)]
+struct Row<'a> {
+    file_path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f32>,
+    #[serde(flatten)]
+    unit: &'a SemanticUnit,
+}
+
+fn load(store: &str) -> Result<SymbolIndex, String> {
+    SymbolIndex::load_from(store)
+}
+
+fn io_error(e: std::io::Error) -> String {
+    format!("Failed to write output: {}", e)
+}
+
+/// Run a command, writing its report to `out`. Verify exits with 1 when
+/// any file is stale; errors are left to the caller, which exits with 2.
+pub fn run(cli: Cli, out: &mut dyn Write) -> Result<ExitCode, String> {
+    match cli.command {
+        Command::Index { dir, include, exclude, max_file_bytes, skip_generated } => {
+            let mut index = if Path::new(&cli.store).exists() { load(&cli.store)? } else { SymbolIndex::default() };
+            let options = ParseOptions { skip_generated, ..Default::default() };
+            let scan = scan_repository_files(&dir, &include, &exclude, max_file_bytes, &options)?;
+            let root = Path::new(&dir);
+            let scanned: HashSet<&str> = scan.results.iter().map(|result| result.file_path.as_str()).collect();
+            index.files.retain(|path, _| !Path::new(path).starts_with(root) || scanned.contains(path.as_str()));
+            let units: usize = scan.results.iter().map(|result| result.units.len()).sum();
+            let files = scan.results.len();
+            for result in scan.results {
+                let source = std::fs::read(&result.file_path)
+                    .map_err(|e| format!("Failed to read {}: {}", result.file_path, e))?;
+                let path = result.file_path.clone();
+                index.files.insert(path, IndexedFile::new(&String::from_utf8_lossy(&source), result));
+            }
+            index.save_to(&cli.store)?;
+            writeln!(
+                out,
+                "Indexed {} files ({} units) from {}; {} skipped, {} failed",
+                files,
+                units,
+                dir,
+                scan.skipped.len(),
+                scan.errors.len()
+            )
+            .map_err(io_error)?;
+            for (path, message) in &scan.errors {
+                writeln!(out, "error: {}: {}", path, message).map_err(io_error)?;
+            }
+        }
+        Command::Search { query, top_k, json } => {
+            let index = load(&cli.store)?;
+            let mut keywords = KeywordIndex::default();
+            let mut units = BTreeMap::new();
+            for (path, file) in &index.files {
+                for (i, unit) in file.units.iter().enumerate() {
+                    let id = format!("{}#{}", path, i);
+                    let docstring = unit.docstring.as_deref().unwrap_or("");
+                    let text = format!("{} {} {} {}", unit.qualified_name, unit.signature, docstring, unit.content);
+                    keywords.insert(&id, &text);
+                    units.insert(id, (path.as_str(), unit));
+                }
+            }
+            for (id, score) in keywords.search(&query, top_k) {
+                let (file_path, unit) = units[&id];
+                if json {
+                    let row = Row { file_path, score: Some(score), unit };
+                    writeln!(out, "{}", serde_json::to_string(&row).map_err(|e| e.to_string())?).map_err(io_error)?;
+                } else {
+                    writeln!(
+                        out,
+                        "{:7.3}  {}:{}-{}  {} {}",
+                        score, file_path, unit.start_line, unit.end_line, unit.unit_type, unit.qualified_name
+                    )
+                    .map_err(io_error)?;
+                }
+            }
+        }
+        Command::Stats => {
+            let index = load(&cli.store)?;
+            let mut languages: BTreeMap<&str, usize> = BTreeMap::new();
+            let mut unit_types: BTreeMap<&str, usize> = BTreeMap::new();
+            for file in index.files.values() {
+                *languages.entry(&file.language).or_default() += 1;
+                for unit in &file.units {
+                    *unit_types.entry(unit.unit_type.as_str()).or_default() += 1;
+                }
+            }
+            let size = std::fs::metadata(&cli.store).map(|m| m.len()).unwrap_or(0);
+            writeln!(out, "store: {} ({} bytes)", cli.store, size).map_err(io_error)?;
+            writeln!(out, "files: {}", index.files.len()).map_err(io_error)?;
+            writeln!(out, "units: {}", unit_types.values().sum::<usize>()).map_err(io_error)?;
+            for (heading, counts) in [("languages", &languages), ("unit types", &unit_types)] {
+                writeln!(out, "{}:", heading).map_err(io_error)?;
+                for (name, count) in counts {
+                    writeln!(out, "  {:<16} {}", name, count).map_err(io_error)?;
+                }
+            }
+        }
+        Command::Verify => {
+            let index = load(&cli.store)?;
+            let mut paths: Vec<&String> = index.files.keys().collect();
+            paths.sort();
+            let mut stale = 0;
+            for path in paths {
+                let problem = match parse_path(path, &ParseOptions::default()) {
+                    Err(_) if !Path::new(path).exists() => Some("missing"),
+                    Err(_) => Some("unparseable"),
+                    Ok(result) => {
+                        let hashes = |units: &[SemanticUnit]| {
+                            let mut hashes: Vec<(String, String)> =
+                                units.iter().map(|u| (u.unit_id.clone(), u.content_hash.clone())).collect();
+                            hashes.sort();
+                            hashes
+                        };
+                        (hashes(&result.units) != hashes(&index.files[path].units)).then_some("changed")
+                    }
+                };
+                if let Some(problem) = problem {
+                    stale += 1;
+                    writeln!(out, "{}: {}", problem, path).map_err(io_error)?;
+                }
+            }
+            writeln!(out, "{} files verified, {} stale", index.files.len(), stale).map_err(io_error)?;
+            if stale > 0 {
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Command::Export { output } => {
+            let index = load(&cli.store)?;
+            let mut file;
+            let out: &mut dyn Write = match &output {
+                Some(path) => {
+                    file = std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+                    &mut file
+                }
+                None => out,
+            };
+            let mut paths: Vec<&String> = index.files.keys().collect();
+            paths.sort();
+            for path in paths {
+                for unit in &index.files[path].units {
+                    let row = Row { file_path: path, score: None, unit };
+                    writeln!(out, "{}", serde_json::to_string(&row).map_err(|e| e.to_string())?).map_err(io_error)?;
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> (ExitCode, String) {
+        let mut out = Vec::new();
+        let code = run(Cli::try_parse_from(args).unwrap(), &mut out).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_index_search_verify_and_export() {
+        let root = std::env::temp_dir().join(format!("memory_core_cli_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let app = root.join("src/app.py");
+        std::fs::write(&app, "def load_config():\n    pass\n\ndef render_page():\n    pass\n").unwrap();
+        let (dir, store) = (root.join("src"), root.join("store.json"));
+        let (dir, store) = (dir.to_str().unwrap(), store.to_str().unwrap());
+
+        let (_, report) = run_args(&["memory-core", "--store", store, "index", dir]);
+        assert!(report.starts_with("Indexed 1 files (2 units)"), "{}", report);
+        let (_, hits) = run_args(&["memory-core", "--store", store, "search", "config", "-k", "1"]);
+        assert!(hits.contains("app.py:1-2  function load_config"), "{}", hits);
+        let (_, stats) = run_args(&["memory-core", "stats", "--store", store]);
+        assert!(stats.contains("units: 2"), "{}", stats);
+        let (_, export) = run_args(&["memory-core", "--store", store, "export"]);
+        assert_eq!(export.lines().count(), 2);
+        assert!(export.contains("\"name\":\"render_page\""));
+
+        assert_eq!(run_args(&["memory-core", "--store", store, "verify"]).0, ExitCode::SUCCESS);
+        std::fs::write(&app, "def load_config():\n    return {}\n").unwrap();
+        let (code, report) = run_args(&["memory-core", "--store", store, "verify"]);
+        assert_eq!(code, ExitCode::from(1));
+        assert!(report.contains("changed: "), "{}", report);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
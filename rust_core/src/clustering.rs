@@ -0,0 +1,243 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// Result of `cluster_embeddings`
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct Clustering {
+    #[pyo3(get)]
+    pub assignments: Vec<usize>, // Cluster of each input embedding
+    #[pyo3(get)]
+    pub centroids: Vec<Vec<f32>>,
+    #[pyo3(get)]
+    pub inertia: f64, // Sum of squared distances to the assigned centroids
+    #[pyo3(get)]
+    pub iterations: usize,
+}
+
+#[pymethods]
+impl Clustering {
+    fn __repr__(&self) -> String {
+        format!(
+            "Clustering(clusters={}, points={}, inertia={:.4}, iterations={})",
+            self.centroids.len(),
+            self.assignments.len(),
+            self.inertia,
+            self.iterations
+        )
+    }
+}
+
+/// SplitMix64, so seeded runs are reproducible without a `rand` dependency
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Nearest centroid of `point` and its squared distance, lowest index on ties
+fn nearest(point: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, squared_distance(point, c)))
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+        .expect("at least one centroid")
+}
+
+/// k-means++ seeding: each further centroid is drawn with probability
+/// proportional to its squared distance from the nearest chosen one
+fn seed_centroids(points: &[Vec<f32>], k: usize, rng: &mut SplitMix) -> Vec<Vec<f32>> {
+    let mut centroids = vec![points[(rng.next() % points.len() as u64) as usize].clone()];
+    let mut distances: Vec<f32> = points.par_iter().map(|p| squared_distance(p, &centroids[0])).collect();
+    while centroids.len() < k {
+        let total: f64 = distances.iter().map(|&d| d as f64).sum();
+        let chosen = if total == 0.0 {
+            // Every point coincides with a centroid; duplicates are unavoidable
+            (rng.next() % points.len() as u64) as usize
+        } else {
+            let mut target = rng.unit() * total;
+            distances
+                .iter()
+                .position(|&d| {
+                    target -= d as f64;
+                    target < 0.0
+                })
+                .unwrap_or(points.len() - 1)
+        };
+        let centroid = points[chosen].clone();
+        distances
+            .par_iter_mut()
+            .zip(points.par_iter())
+            .for_each(|(distance, p)| *distance = distance.min(squared_distance(p, &centroid)));
+        centroids.push(centroid);
+    }
+    centroids
+}
+
+/// Lloyd's k-means over the rows of `points`, with k-means++ seeding.
+///
+/// Assignment and centroid updates run in parallel. Stops when no point
+/// changes cluster or after `max_iters` rounds (at least one). A cluster
+/// left empty is re-seeded with the point farthest from its centroid.
+pub fn kmeans(points: &[Vec<f32>], k: usize, max_iters: usize, seed: u64) -> Result<Clustering, String> {
+    if k == 0 {
+        return Err("k must be at least 1".to_string());
+    }
+    if k > points.len() {
+        return Err(format!("k is {} but only {} embeddings were given", k, points.len()));
+    }
+    let dimension = points[0].len();
+    if let Some(index) = points.iter().position(|p| p.len() != dimension) {
+        return Err(format!("Embedding {} has length {}, expected {}", index, points[index].len(), dimension));
+    }
+
+    let mut rng = SplitMix(seed);
+    let mut centroids = seed_centroids(points, k, &mut rng);
+    let mut assignments = vec![usize::MAX; points.len()];
+    let mut distances = vec![0.0f32; points.len()];
+    let mut iterations = 0;
+    loop {
+        iterations += 1;
+        let nearest: Vec<(usize, f32)> = points.par_iter().map(|p| nearest(p, &centroids)).collect();
+        let changed = nearest.iter().zip(&assignments).any(|(n, &a)| n.0 != a);
+        for (i, (cluster, distance)) in nearest.into_iter().enumerate() {
+            assignments[i] = cluster;
+            distances[i] = distance;
+        }
+        // Always end on an assignment, so results match the centroids
+        if !changed || iterations >= max_iters {
+            break;
+        }
+
+        // Per-cluster sums, folded per thread then merged
+        let (sums, counts) = points
+            .par_iter()
+            .zip(assignments.par_iter())
+            .fold(
+                || (vec![vec![0.0f64; dimension]; k], vec![0usize; k]),
+                |(mut sums, mut counts), (point, &cluster)| {
+                    for (sum, &x) in sums[cluster].iter_mut().zip(point) {
+                        *sum += x as f64;
+                    }
+                    counts[cluster] += 1;
+                    (sums, counts)
+                },
+            )
+            .reduce(
+                || (vec![vec![0.0f64; dimension]; k], vec![0usize; k]),
+                |(mut sums, mut counts), (other_sums, other_counts)| {
+                    for (sum, other) in sums.iter_mut().zip(&other_sums) {
+                        for (s, o) in sum.iter_mut().zip(other) {
+                            *s += o;
+                        }
+                    }
+                    for (count, other) in counts.iter_mut().zip(&other_counts) {
+                        *count += other;
+                    }
+                    (sums, counts)
+                },
+            );
+        for (cluster, (sum, &count)) in sums.iter().zip(&counts).enumerate() {
+            if count > 0 {
+                centroids[cluster] = sum.iter().map(|s| (s / count as f64) as f32).collect();
+            }
+        }
+        for cluster in (0..k).filter(|&c| counts[c] == 0) {
+            let farthest = (0..points.len()).max_by(|&a, &b| distances[a].total_cmp(&distances[b])).unwrap_or(0);
+            centroids[cluster] = points[farthest].clone();
+            distances[farthest] = 0.0;
+        }
+    }
+
+    let inertia = points
+        .par_iter()
+        .zip(assignments.par_iter())
+        .map(|(p, &cluster)| squared_distance(p, &centroids[cluster]) as f64)
+        .sum();
+    Ok(Clustering { assignments, centroids, inertia, iterations })
+}
+
+/// Cluster embeddings with k-means, for consolidating similar memories.
+///
+/// Distances are Euclidean; normalize the embeddings first to cluster by
+/// cosine similarity. Seeding is k-means++ and reproducible for a given
+/// `seed`.
+///
+/// Args:
+///     embeddings: List of embedding vectors of equal length
+///     k: Number of clusters (at most the number of embeddings)
+///     max_iters: Maximum number of assignment/update rounds
+///     seed: Seed for centroid initialization
+///
+/// Returns:
+///     Clustering with per-embedding assignments, centroids and inertia
+#[pyfunction]
+#[pyo3(signature = (embeddings, k, max_iters=100, seed=0))]
+pub fn cluster_embeddings(
+    py: Python<'_>,
+    embeddings: Vec<Vec<f32>>,
+    k: usize,
+    max_iters: usize,
+    seed: u64,
+) -> PyResult<Clustering> {
+    py.detach(|| kmeans(&embeddings, k, max_iters, seed))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blobs() -> Vec<Vec<f32>> {
+        let mut points = Vec::new();
+        for (cx, cy) in [(0.0, 0.0), (10.0, 10.0), (-10.0, 10.0)] {
+            for (dx, dy) in [(0.1, 0.0), (-0.1, 0.0), (0.0, 0.1), (0.0, -0.1)] {
+                points.push(vec![cx + dx, cy + dy]);
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_separates_blobs() {
+        let points = blobs();
+        let clustering = kmeans(&points, 3, 50, 7).unwrap();
+        for blob in clustering.assignments.chunks(4) {
+            assert!(blob.iter().all(|&c| c == blob[0]));
+        }
+        let mut clusters: Vec<usize> = clustering.assignments.iter().step_by(4).copied().collect();
+        clusters.sort();
+        clusters.dedup();
+        assert_eq!(clusters.len(), 3);
+        assert!((clustering.inertia - 0.12).abs() < 1e-3);
+        let first = &clustering.centroids[clustering.assignments[0]];
+        assert!(first[0].abs() < 1e-5 && first[1].abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_seeded_runs_repeat_and_inputs_are_checked() {
+        let points = blobs();
+        assert_eq!(kmeans(&points, 2, 50, 3).unwrap().assignments, kmeans(&points, 2, 50, 3).unwrap().assignments);
+        assert_eq!(kmeans(&points, 12, 50, 3).unwrap().inertia, 0.0);
+        assert_eq!(kmeans(&points, 3, 0, 3).unwrap().iterations, 1);
+        assert!(kmeans(&points, 0, 50, 3).is_err());
+        assert!(kmeans(&points, 13, 50, 3).is_err());
+        assert!(kmeans(&[vec![1.0], vec![1.0, 2.0]], 1, 50, 3).is_err());
+    }
+}
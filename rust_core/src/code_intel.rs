@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::lsp::{uri_to_path, SymbolLocation};
+
+/// `SymbolRole.Definition` bit of a SCIP occurrence
+const SCIP_DEFINITION: u64 = 0x1;
+
+/// A symbol occurrence recorded in a SCIP index or LSIF dump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Occurrence {
+    /// Identifies the symbol across files: the SCIP symbol, or the LSIF
+    /// result its ranges share
+    pub symbol: String,
+    /// 1-based; columns are as recorded (UTF-16 unless the indexer says otherwise)
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub definition: bool,
+}
+
+impl Occurrence {
+    /// Whether a cursor at `line`/`column` is on the occurrence, end included
+    pub fn contains(&self, line: usize, column: usize) -> bool {
+        (self.line, self.column) <= (line, column) && (line, column) <= (self.end_line, self.end_column)
+    }
+
+    pub fn location(&self, path: &str) -> SymbolLocation {
+        SymbolLocation {
+            path: path.to_string(),
+            line: self.line,
+            column: self.column,
+            end_line: self.end_line,
+            end_column: self.end_column,
+            precise: true,
+        }
+    }
+}
+
+/// Occurrences of a dump by file path, each file's sorted by position
+pub type Occurrences = HashMap<String, Vec<Occurrence>>;
+
+/// Read a SCIP index (protobuf) or an LSIF dump (JSON lines or a JSON
+/// array), telling them apart by the first non-blank byte
+pub fn parse_dump(bytes: &[u8]) -> Result<Occurrences, String> {
+    let mut occurrences = match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{' | b'[') => {
+            parse_lsif(std::str::from_utf8(bytes).map_err(|e| format!("Invalid LSIF dump: {}", e))?)?
+        }
+        _ => parse_scip(bytes)?,
+    };
+    for found in occurrences.values_mut() {
+        found.sort_by_key(|o| (o.line, o.column, o.end_line, o.end_column));
+        found.dedup();
+    }
+    Ok(occurrences)
+}
+
+/// A protobuf field value; fixed-width fields are skipped since SCIP has none
+enum Wire<'a> {
+    Varint(u64),
+    /// Strings, messages and packed repeated fields
+    Bytes(&'a [u8]),
+}
+
+fn varint(bytes: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or("Truncated SCIP index")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Malformed varint in SCIP index".to_string())
+}
+
+/// Top-level fields of a protobuf message as `(field number, value)`
+fn fields(bytes: &[u8]) -> Result<Vec<(u64, Wire<'_>)>, String> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let key = varint(bytes, &mut pos)?;
+        let value = match key & 7 {
+            0 => Some(Wire::Varint(varint(bytes, &mut pos)?)),
+            1 => {
+                pos += 8;
+                None
+            }
+            2 => {
+                let len = varint(bytes, &mut pos)? as usize;
+                let end = pos.checked_add(len).filter(|&end| end <= bytes.len()).ok_or("Truncated SCIP index")?;
+                let payload = &bytes[pos..end];
+                pos = end;
+                Some(Wire::Bytes(payload))
+            }
+            5 => {
+                pos += 4;
+                None
+            }
+            wire => return Err(format!("Unsupported protobuf wire type {} in SCIP index", wire)),
+        };
+        if pos > bytes.len() {
+            return Err("Truncated SCIP index".to_string());
+        }
+        fields.extend(value.map(|value| (key >> 3, value)));
+    }
+    Ok(fields)
+}
+
+/// Occurrences of a SCIP `Index`, keyed by each document's `relative_path`
+pub fn parse_scip(bytes: &[u8]) -> Result<Occurrences, String> {
+    let mut occurrences = Occurrences::new();
+    for (number, value) in fields(bytes)? {
+        if let (2, Wire::Bytes(document)) = (number, value) {
+            let (path, found) = scip_document(document)?;
+            occurrences.entry(path).or_default().extend(found);
+        }
+    }
+    Ok(occurrences)
+}
+
+fn scip_document(bytes: &[u8]) -> Result<(String, Vec<Occurrence>), String> {
+    let mut path = String::new();
+    let mut found = Vec::new();
+    for (number, value) in fields(bytes)? {
+        match (number, value) {
+            (1, Wire::Bytes(relative_path)) => path = String::from_utf8_lossy(relative_path).into_owned(),
+            (2, Wire::Bytes(occurrence)) => found.extend(scip_occurrence(occurrence)?),
+            _ => {}
+        }
+    }
+    // Local symbols are only unique within their document
+    for occurrence in found.iter_mut().filter(|o| o.symbol.starts_with("local ")) {
+        occurrence.symbol = format!("{} {}", path, occurrence.symbol);
+    }
+    Ok((path, found))
+}
+
+/// None for an occurrence without a symbol or with a malformed range
+fn scip_occurrence(bytes: &[u8]) -> Result<Option<Occurrence>, String> {
+    let (mut range, mut symbol, mut roles) = (Vec::new(), String::new(), 0);
+    for (number, value) in fields(bytes)? {
+        match (number, value) {
+            (1, Wire::Varint(value)) => range.push(value as usize),
+            (1, Wire::Bytes(packed)) => {
+                let mut pos = 0;
+                while pos < packed.len() {
+                    range.push(varint(packed, &mut pos)? as usize);
+                }
+            }
+            (2, Wire::Bytes(name)) => symbol = String::from_utf8_lossy(name).into_owned(),
+            (3, Wire::Varint(value)) => roles = value,
+            _ => {}
+        }
+    }
+    // `[line, column, end_column]` within one line, else `[line, column, end_line, end_column]`
+    let (line, column, end_line, end_column) = match range[..] {
+        [line, column, end_column] => (line, column, line, end_column),
+        [line, column, end_line, end_column] => (line, column, end_line, end_column),
+        _ => return Ok(None),
+    };
+    if symbol.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Occurrence {
+        symbol,
+        line: line + 1,
+        column,
+        end_line: end_line + 1,
+        end_column,
+        definition: roles & SCIP_DEFINITION != 0,
+    }))
+}
+
+/// Occurrences of an LSIF dump, keyed by document path relative to the
+/// project root of its `metaData` vertex.
+///
+/// Ranges reach their definition and reference results through `next`
+/// chains of result sets; `item` edges list the ranges of each result.
+/// Ranges sharing a definition result are one symbol.
+pub fn parse_lsif(text: &str) -> Result<Occurrences, String> {
+    let elements: Vec<Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).map_err(|e| format!("Invalid LSIF dump: {}", e))?
+    } else {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| format!("Invalid LSIF line: {}", e)))
+            .collect::<Result<_, _>>()?
+    };
+
+    // Ids may be numbers or strings; their JSON text is unique either way
+    let key = |element: &Value, field: &str| element.get(field).map(Value::to_string);
+    let keys = |element: &Value| -> Vec<String> {
+        let ids = element.get("inVs").and_then(Value::as_array);
+        ids.map(|ids| ids.iter().map(Value::to_string).collect()).unwrap_or_default()
+    };
+    let position = |range: &Value, end: &str, field: &str| {
+        range.get(end).and_then(|p| p.get(field)).and_then(Value::as_u64).unwrap_or(0) as usize
+    };
+
+    let mut root = None;
+    let mut documents: HashMap<String, String> = HashMap::new();
+    let mut ranges: HashMap<String, (usize, usize, usize, usize)> = HashMap::new();
+    let mut range_document: HashMap<String, String> = HashMap::new();
+    let mut next: HashMap<String, String> = HashMap::new();
+    let mut definition_results: HashMap<String, String> = HashMap::new();
+    let mut reference_results: HashMap<String, String> = HashMap::new();
+    let mut items: Vec<(String, Vec<String>, Option<String>)> = Vec::new();
+    for element in &elements {
+        let (Some(id), Some(label)) = (key(element, "id"), element.get("label").and_then(Value::as_str)) else {
+            continue;
+        };
+        let (out_v, in_v) = (key(element, "outV"), key(element, "inV"));
+        match (label, out_v, in_v) {
+            ("metaData", _, _) => root = element.get("projectRoot").and_then(Value::as_str).and_then(uri_to_path),
+            ("document", _, _) => {
+                documents.insert(id, element.get("uri").and_then(Value::as_str).unwrap_or_default().to_string());
+            }
+            ("range", _, _) => {
+                let bounds = (
+                    position(element, "start", "line"),
+                    position(element, "start", "character"),
+                    position(element, "end", "line"),
+                    position(element, "end", "character"),
+                );
+                ranges.insert(id, bounds);
+            }
+            ("contains", Some(out_v), _) => {
+                range_document.extend(keys(element).into_iter().map(|range| (range, out_v.clone())));
+            }
+            ("next", Some(out_v), Some(in_v)) => {
+                next.insert(out_v, in_v);
+            }
+            ("textDocument/definition", Some(out_v), Some(in_v)) => {
+                definition_results.insert(out_v, in_v);
+            }
+            ("textDocument/references", Some(out_v), Some(in_v)) => {
+                reference_results.insert(out_v, in_v);
+            }
+            ("item", Some(out_v), _) => {
+                // Newer dumps name the ranges' document in `shard`, older ones in `document`
+                if let Some(document) = key(element, "shard").or_else(|| key(element, "document")) {
+                    for range in keys(element) {
+                        range_document.entry(range).or_insert_with(|| document.clone());
+                    }
+                }
+                let property = element.get("property").and_then(Value::as_str).map(str::to_string);
+                items.push((out_v, keys(element), property));
+            }
+            _ => {}
+        }
+    }
+
+    // A reference result belongs to the symbol of the definition result
+    // next to it, or is its own symbol when there is none
+    let definition_ids: HashSet<&String> = definition_results.values().collect();
+    let reference_symbols: HashMap<&String, &String> = reference_results
+        .iter()
+        .map(|(vertex, result)| (result, definition_results.get(vertex).unwrap_or(result)))
+        .collect();
+    let symbol_of = |range: &String| {
+        let mut vertex = range;
+        for _ in 0..=next.len() {
+            if let Some(result) = definition_results.get(vertex) {
+                return Some(result);
+            }
+            if let Some(result) = reference_results.get(vertex) {
+                return reference_symbols.get(result).copied();
+            }
+            vertex = next.get(vertex)?;
+        }
+        None
+    };
+
+    let mut resolved: HashMap<&String, (&String, bool)> = HashMap::new();
+    for (result, ranges_of, property) in &items {
+        let (symbol, definition) = if definition_ids.contains(result) {
+            (result, true)
+        } else if let Some(symbol) = reference_symbols.get(result) {
+            (*symbol, property.as_deref() == Some("definitions"))
+        } else {
+            continue;
+        };
+        for range in ranges_of {
+            let entry = resolved.entry(range).or_insert((symbol, definition));
+            entry.1 |= definition;
+        }
+    }
+    for range in ranges.keys() {
+        if let Some(symbol) = symbol_of(range) {
+            resolved.entry(range).or_insert((symbol, false));
+        }
+    }
+
+    let mut occurrences = Occurrences::new();
+    for (range, (symbol, definition)) in resolved {
+        let (Some(&(line, column, end_line, end_column)), Some(uri)) =
+            (ranges.get(range), range_document.get(range).and_then(|document| documents.get(document)))
+        else {
+            continue;
+        };
+        occurrences.entry(document_path(uri, root.as_deref())).or_default().push(Occurrence {
+            symbol: format!("lsif:{}", symbol),
+            line: line + 1,
+            column,
+            end_line: end_line + 1,
+            end_column,
+            definition,
+        });
+    }
+    Ok(occurrences)
+}
+
+/// Path of a document URI relative to the project root, or the URI's whole
+/// path (the URI itself for other schemes) outside it
+fn document_path(uri: &str, root: Option<&Path>) -> String {
+    let Some(path) = uri_to_path(uri) else { return uri.to_string() };
+    let relative = root.and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path.as_path());
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn message(number: u64, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint(number << 3 | 2, &mut out);
+        encode_varint(payload.len() as u64, &mut out);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn scip_occurrence(range: &[u64], symbol: &str, roles: u64) -> Vec<u8> {
+        let mut packed = Vec::new();
+        for &value in range {
+            encode_varint(value, &mut packed);
+        }
+        let mut out = message(1, &packed);
+        out.extend(message(2, symbol.as_bytes()));
+        out.extend([3 << 3, roles as u8]);
+        message(2, &out)
+    }
+
+    #[test]
+    fn test_parse_scip_documents() {
+        let mut cart = message(1, b"src/cart.py");
+        cart.extend(scip_occurrence(&[1, 8, 13], "py . cart/Cart#total().", 1));
+        cart.extend(scip_occurrence(&[4, 4, 5, 0], "local 0", 1));
+        let mut shop = scip_occurrence(&[3, 16, 21], "py . cart/Cart#total().", 8);
+        shop.extend(scip_occurrence(&[3, 0], "py . broken", 0));
+        shop.extend(message(1, b"src/shop.py"));
+        let mut index = message(1, &message(3, b"file:///repo"));
+        index.extend(message(2, &cart));
+        index.extend(message(2, &shop));
+
+        let occurrences = parse_dump(&index).unwrap();
+        let cart = &occurrences["src/cart.py"];
+        assert_eq!((cart[0].line, cart[0].column, cart[0].end_line, cart[0].end_column), (2, 8, 2, 13));
+        assert!(cart[0].definition);
+        assert_eq!((cart[1].symbol.as_str(), cart[1].end_line), ("src/cart.py local 0", 6));
+        let shop = &occurrences["src/shop.py"];
+        assert_eq!(shop.len(), 1);
+        assert_eq!((shop[0].symbol.as_str(), shop[0].definition), ("py . cart/Cart#total().", false));
+        assert!(parse_scip(&[0x12, 0x05, 0x0a]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lsif_links_ranges_through_result_sets() {
+        let dump = r#"
+            {"id":1,"type":"vertex","label":"metaData","projectRoot":"file:///repo"}
+            {"id":2,"type":"vertex","label":"document","uri":"file:///repo/cart.py"}
+            {"id":3,"type":"vertex","label":"document","uri":"file:///repo/shop.py"}
+            {"id":4,"type":"vertex","label":"range","start":{"line":1,"character":8},"end":{"line":1,"character":13}}
+            {"id":5,"type":"vertex","label":"range","start":{"line":3,"character":16},"end":{"line":3,"character":21}}
+            {"id":6,"type":"vertex","label":"range","start":{"line":0,"character":6},"end":{"line":0,"character":10}}
+            {"id":7,"type":"edge","label":"contains","outV":2,"inVs":[4,6]}
+            {"id":8,"type":"edge","label":"contains","outV":3,"inVs":[5]}
+            {"id":9,"type":"vertex","label":"resultSet"}
+            {"id":10,"type":"edge","label":"next","outV":4,"inV":9}
+            {"id":11,"type":"edge","label":"next","outV":5,"inV":9}
+            {"id":12,"type":"vertex","label":"definitionResult"}
+            {"id":13,"type":"edge","label":"textDocument/definition","outV":9,"inV":12}
+            {"id":14,"type":"edge","label":"item","outV":12,"inVs":[4],"document":2}
+            {"id":15,"type":"vertex","label":"referenceResult"}
+            {"id":16,"type":"edge","label":"textDocument/references","outV":9,"inV":15}
+            {"id":17,"type":"edge","label":"item","outV":15,"inVs":[5],"document":3,"property":"references"}
+        "#;
+        let occurrences = parse_dump(dump.as_bytes()).unwrap();
+        let cart = &occurrences["cart.py"];
+        assert_eq!(cart.len(), 1, "range 6 has no result and is dropped");
+        assert_eq!((cart[0].line, cart[0].column, cart[0].definition), (2, 8, true));
+        let shop = &occurrences["shop.py"];
+        assert_eq!((shop[0].line, shop[0].column, shop[0].definition), (4, 16, false));
+        assert_eq!(shop[0].symbol, cart[0].symbol);
+        assert!(parse_dump(b"{not json").is_err());
+    }
+}
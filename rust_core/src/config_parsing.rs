@@ -1,171 +1,1039 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
 use toml::Value as TomlValue;
 
-use crate::parsing::{SemanticUnit, ParseResult};
+use crate::parsing::{line_starts, SemanticUnit, ParseResult};
 
-/// Parse JSON configuration files and extract top-level keys as semantic units
-pub fn parse_json(_file_path: &str, source_code: &str) -> Result<Vec<SemanticUnit>, String> {
-    let parsed: JsonValue = serde_json::from_str(source_code)
-        .map_err(|e| format!("JSON parse error: {}", e))?;
+/// A config entry: its key and the byte span of its source text
+type Entry = (String, (usize, usize));
 
-    let mut units = Vec::new();
+/// A nested config entry with its parsed value
+type Child<'v, V> = (String, (usize, usize), &'v V);
 
-    if let JsonValue::Object(map) = parsed {
-        for (key, value) in map.iter() {
-            // Calculate approximate line numbers by searching in source
-            let (start_line, end_line) = find_key_lines(source_code, key);
-
-            // Create a semantic unit for this top-level key
-            let content = format_json_section(key, value);
-
-            units.push(SemanticUnit {
-                unit_type: "class".to_string(), // Top-level sections as "class" units
-                name: key.clone(),
-                start_line,
-                end_line,
-                start_byte: 0, // Not accurately calculable from parsed JSON
-                end_byte: content.len(),
-                signature: key.clone(),
-                content,
-                language: "Json".to_string(),
-            });
+/// Lists the entries of the section at a path, given its span and value
+type Children<'a, 'v, V> = &'a dyn Fn(&[String], (usize, usize), &'v V) -> Vec<Child<'v, V>>;
+
+/// How far config files are split into units below their top-level keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigNesting {
+    /// Deepest level emitted, the top-level keys being level 1
+    pub max_depth: usize,
+    /// Only sections longer than this many bytes are split; all when `None`
+    pub max_unit_bytes: Option<usize>,
+}
+
+impl Default for ConfigNesting {
+    /// Top-level keys only
+    fn default() -> Self {
+        Self { max_depth: 1, max_unit_bytes: None }
+    }
+}
+
+impl ConfigNesting {
+    /// Whether the section at `depth` spanning `span` is split into its children
+    fn splits(&self, depth: usize, (start, end): (usize, usize)) -> bool {
+        depth < self.max_depth && self.max_unit_bytes.is_none_or(|max| end - start > max)
+    }
+}
+
+/// A "class" unit for the section at `path` (its keys from the top level
+/// down), with its source text verbatim
+fn config_unit(
+    source: &str,
+    line_starts: &[usize],
+    path: &[String],
+    span: (usize, usize),
+    language: &str,
+    metadata: HashMap<String, String>,
+) -> SemanticUnit {
+    let qualified_name = path.join(".");
+    let name = path.last().cloned().unwrap_or_default();
+    SemanticUnit {
+        parent_name: path.len().checked_sub(2).map(|parent| path[parent].clone()),
+        qualified_name: qualified_name.clone(),
+        signature: qualified_name,
+        metadata,
+        // Config sections as "class" units
+        ..SemanticUnit::spanning("class", name, source, line_starts, span, language)
+    }
+}
+
+/// A "config_section" unit, for the formats whose values are untyped text
+fn section_unit(
+    source: &str,
+    line_starts: &[usize],
+    path: &[String],
+    span: (usize, usize),
+    language: &str,
+) -> SemanticUnit {
+    SemanticUnit {
+        unit_type: "config_section".into(),
+        ..config_unit(source, line_starts, path, span, language, HashMap::new())
+    }
+}
+
+/// What walking the nested sections of a config format needs
+struct Sections<'a, 'v, V> {
+    source: &'a str,
+    line_starts: &'a [usize],
+    language: &'a str,
+    nesting: &'a ConfigNesting,
+    /// Entries of the section at a path with their values; empty unless
+    /// the section holds a mapping
+    children: Children<'a, 'v, V>,
+    /// Whether a value is a mapping or list, and so a section of its own
+    is_collection: fn(&V) -> bool,
+}
+
+impl<'v, V> Sections<'_, 'v, V> {
+    /// The unit for top-level entry `key` followed by those nested in it
+    fn units(&self, key: String, span: (usize, usize), value: &'v V) -> Vec<SemanticUnit> {
+        let path = [key];
+        let mut units = vec![config_unit(self.source, self.line_starts, &path, span, self.language, HashMap::new())];
+        self.nested_units(&path, span, value, &mut units);
+        units
+    }
+
+    /// Units for the sections nested in the one at `path`, depth first, as
+    /// far as the nesting limits allow. Scalars stay in their parent's unit.
+    fn nested_units(&self, path: &[String], span: (usize, usize), value: &'v V, units: &mut Vec<SemanticUnit>) {
+        if !self.nesting.splits(path.len(), span) {
+            return;
+        }
+        for (key, span, child) in (self.children)(path, span, value) {
+            if !(self.is_collection)(child) {
+                continue;
+            }
+            let mut path = path.to_vec();
+            path.push(key);
+            units.push(config_unit(self.source, self.line_starts, &path, span, self.language, HashMap::new()));
+            self.nested_units(&path, span, child, units);
         }
     }
+}
 
-    Ok(units)
+/// End of the JSON string starting at `start` (its opening quote, `'` for
+/// JSON5)
+fn json_string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
 }
 
-/// Parse YAML configuration files and extract top-level keys as semantic units
-pub fn parse_yaml(_file_path: &str, source_code: &str) -> Result<Vec<SemanticUnit>, String> {
-    let parsed: YamlValue = serde_yaml::from_str(source_code)
-        .map_err(|e| format!("YAML parse error: {}", e))?;
+/// End of the JSON object key starting at `start`: a string, or a JSON5
+/// identifier
+fn json_key_end(bytes: &[u8], start: usize) -> usize {
+    if matches!(bytes[start], b'"' | b'\'') {
+        return json_string_end(bytes, start);
+    }
+    let is_identifier = |&c: &u8| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'$');
+    start + bytes[start..].iter().take_while(|&c| is_identifier(c)).count()
+}
 
-    let mut units = Vec::new();
+/// `source` with its comments and trailing commas (JSONC, JSON5) blanked
+/// out, every other byte staying where it was so spans still index `source`
+fn blank_json_comments(source: &str) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    let mut trailing_comma = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = json_string_end(&bytes, i);
+                trailing_comma = None;
+                continue;
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(&(b'/' | b'*'))) => {
+                let end = if bytes[i + 1] == b'/' {
+                    source[i..].find('\n').map_or(bytes.len(), |n| i + n)
+                } else {
+                    source[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4)
+                };
+                // Newlines stay, keeping line numbers
+                bytes[i..end].iter_mut().filter(|c| **c != b'\n').for_each(|c| *c = b' ');
+                i = end;
+                continue;
+            }
+            b',' => trailing_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = trailing_comma.take() {
+                    bytes[comma] = b' ';
+                }
+            }
+            c if c.is_ascii_whitespace() => {}
+            _ => trailing_comma = None,
+        }
+        i += 1;
+    }
+    // Only ASCII was written, over whole comments
+    String::from_utf8(bytes).unwrap_or_else(|_| source.to_string())
+}
 
-    if let YamlValue::Mapping(map) = parsed {
-        for (key, value) in map.iter() {
-            if let YamlValue::String(key_str) = key {
-                let (start_line, end_line) = find_key_lines(source_code, key_str);
+/// End of the JSON value starting at `start`
+fn json_value_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' if depth == 0 => return json_string_end(bytes, i),
+            b'"' | b'\'' => {
+                i = json_string_end(bytes, i);
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            // A scalar ends at the delimiter after it
+            b',' | b'}' | b']' if depth == 0 => return i,
+            c if depth == 0 && c.is_ascii_whitespace() => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
 
-                let content = format_yaml_section(key_str, value);
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    i
+}
+
+/// Start of the value of the JSON member whose key starts at `key_start`
+fn json_value_start(bytes: &[u8], key_start: usize) -> usize {
+    // Past the colon to the value
+    skip_whitespace(bytes, skip_whitespace(bytes, json_key_end(bytes, key_start)) + 1)
+}
+
+/// Members of the JSON object whose opening brace is at `open`, from key to
+/// end of value. The source must already have parsed as JSON, with its
+/// comments blanked out.
+fn json_members(source: &str, open: usize) -> Vec<Entry> {
+    let bytes = source.as_bytes();
+    let mut entries = Vec::new();
+    if bytes.get(open) != Some(&b'{') {
+        return entries;
+    }
+    let mut i = open + 1;
+    loop {
+        i = skip_whitespace(bytes, i);
+        if !bytes.get(i).is_some_and(|&c| c.is_ascii_alphabetic() || matches!(c, b'"' | b'\'' | b'_' | b'$')) {
+            break;
+        }
+        let key_end = json_key_end(bytes, i);
+        let key = match bytes[i] {
+            b'"' => serde_json::from_str::<String>(&source[i..key_end]).unwrap_or_default(),
+            b'\'' => json5::from_str::<String>(&source[i..key_end]).unwrap_or_default(),
+            _ => source[i..key_end].to_string(),
+        };
+        let value_end = json_value_end(bytes, json_value_start(bytes, i));
+        entries.push((key, (i, value_end)));
+        i = skip_whitespace(bytes, value_end);
+        if bytes.get(i) != Some(&b',') {
+            break;
+        }
+        i += 1;
+    }
+    entries
+}
 
-                units.push(SemanticUnit {
-                    unit_type: "class".to_string(),
-                    name: key_str.clone(),
-                    start_line,
-                    end_line,
-                    start_byte: 0,
-                    end_byte: content.len(),
-                    signature: key_str.clone(),
-                    content,
-                    language: "Yaml".to_string(),
-                });
+/// Members of the JSON object in the member spanning `span`, with their values
+fn json_children<'v>(source: &str, (start, _): (usize, usize), value: &'v JsonValue) -> Vec<Child<'v, JsonValue>> {
+    let JsonValue::Object(object) = value else {
+        return Vec::new();
+    };
+    json_members(source, json_value_start(source.as_bytes(), start))
+        .into_iter()
+        .filter_map(|(key, span)| object.get(&key).map(|child| (key, span, child)))
+        .collect()
+}
+
+/// Parse JSON configuration files and extract top-level keys, and the
+/// sections `nesting` allows below them, as semantic units. Sources that
+/// aren't strict JSON are read as JSONC (comments, trailing commas, as in
+/// `tsconfig.json`) and then JSON5 (unquoted keys, single quotes...).
+pub fn parse_json(_file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Result<Vec<SemanticUnit>, String> {
+    let blanked;
+    let (parsed, scanned): (JsonValue, &str) = match serde_json::from_str(source_code) {
+        Ok(parsed) => (parsed, source_code),
+        Err(_) => {
+            blanked = blank_json_comments(source_code);
+            let parsed = match serde_json::from_str(&blanked) {
+                Ok(parsed) => parsed,
+                Err(e) => json5::from_str(&blanked).map_err(|_| format!("JSON parse error: {}", e))?,
+            };
+            (parsed, blanked.as_str())
+        }
+    };
+    let JsonValue::Object(object) = &parsed else {
+        return Ok(Vec::new());
+    };
+
+    let line_starts = line_starts(source_code);
+    let sections = Sections {
+        source: source_code,
+        line_starts: &line_starts,
+        language: "Json",
+        nesting,
+        children: &|_, span, value| json_children(scanned, span, value),
+        is_collection: |value: &JsonValue| value.is_object() || value.is_array(),
+    };
+    let members = json_members(scanned, skip_whitespace(scanned.as_bytes(), 0));
+    Ok(members
+        .into_iter()
+        .filter_map(|(key, span)| {
+            let value = object.get(&key)?;
+            Some(sections.units(key, span, value))
+        })
+        .flatten()
+        .collect())
+}
+
+/// The key of a top-level YAML mapping line (`name: x`, `"quoted key": x`),
+/// or None for lines that don't start a plain or quoted key
+fn yaml_key(line: &str) -> Option<String> {
+    let first = line.chars().next()?;
+    if first == '"' || first == '\'' {
+        let close = if first == '"' {
+            json_string_end(line.as_bytes(), 0)
+        } else {
+            // Single-quoted keys escape a quote by doubling it
+            let mut i = 1;
+            while let Some(offset) = line[i..].find('\'') {
+                i += offset + 1;
+                if !line[i..].starts_with('\'') {
+                    break;
+                }
+                i += 1;
+            }
+            i
+        };
+        let rest = line.get(close..)?.trim_start();
+        return rest.starts_with(':').then(|| serde_yaml::from_str(&line[..close]).ok()).flatten();
+    }
+    if "{[?&*!|>%@`#,-".contains(first) {
+        return None;
+    }
+    // The key ends at the first colon followed by whitespace or the line end
+    let (colon, _) = line
+        .match_indices(':')
+        .find(|(i, _)| line[i + 1..].chars().next().is_none_or(char::is_whitespace))?;
+    Some(line[..colon].trim().to_string())
+}
+
+/// Top-level keys of YAML text with the rows (0-based, inclusive) their
+/// entries span: from the key line to the last indented or `- ` line
+/// before the next top-level line, leaving out blank and comment lines
+/// at the end. Every `---` document is read.
+fn yaml_entries(text: &str) -> Vec<(String, usize, usize)> {
+    let mut entries = Vec::new();
+    let mut current: Option<(String, usize, usize)> = None;
+    for (row, line) in text.lines().enumerate() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let marker = line.starts_with("---") || line.starts_with("...") || line.starts_with('%');
+        // Block sequences may sit at the key's own indentation
+        if line.starts_with([' ', '\t']) || (line.starts_with('-') && !marker) {
+            if let Some(entry) = current.as_mut() {
+                entry.2 = row;
+            }
+            continue;
+        }
+        entries.extend(current.take());
+        if !marker {
+            current = yaml_key(line.trim_end()).map(|key| (key, row, row));
+        }
+    }
+    entries.extend(current);
+    entries
+}
+
+/// Keys of the mapping nested in the YAML entry on rows `first..=last` of
+/// `lines`, with the rows their entries span: the entry's body is
+/// dedented and scanned like a document
+fn yaml_body_entries(lines: &[&str], first: usize, last: usize) -> Vec<(String, usize, usize)> {
+    let body = &lines[first + 1..=last];
+    let is_content = |line: &&&str| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with('#') && line != "~"
+    };
+    let Some(indent) = body
+        .iter()
+        .find(is_content)
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .filter(|&indent| indent > 0)
+    else {
+        return Vec::new();
+    };
+    let dedented: Vec<&str> = body
+        .iter()
+        .map(|line| match line.get(..indent) {
+            Some(margin) if margin.bytes().all(|b| b == b' ') => &line[indent..],
+            // Blank and comment lines may sit anywhere; any other line
+            // indented less than the body is a template stand-in
+            _ if line.trim().is_empty() || line.trim_start().starts_with('#') => line.trim_start(),
+            _ => " ~",
+        })
+        .collect();
+    yaml_entries(&dedented.join("\n"))
+        .into_iter()
+        .map(|(key, start, end)| (key, first + 1 + start, first + 1 + end))
+        .collect()
+}
+
+/// Byte span of rows `first..=last` in `source`
+fn row_span(source: &str, line_starts: &[usize], first: usize, last: usize) -> (usize, usize) {
+    (line_starts[first], line_starts.get(last + 1).copied().unwrap_or(source.len()))
+}
+
+/// Rows of the first and last line of `span`
+fn span_rows(line_starts: &[usize], (start, end): (usize, usize)) -> (usize, usize) {
+    let row_of = |byte: usize| line_starts.partition_point(|&line| line <= byte) - 1;
+    (row_of(start), row_of(end.saturating_sub(1).max(start)))
+}
+
+/// Entries of the YAML mapping in the entry spanning `span`, with their
+/// values; `lines` are the rows of `source` as scanned for structure
+fn yaml_children<'v>(
+    source: &str,
+    lines: &[&str],
+    line_starts: &[usize],
+    span: (usize, usize),
+    value: &'v YamlValue,
+) -> Vec<Child<'v, YamlValue>> {
+    let YamlValue::Mapping(map) = value else {
+        return Vec::new();
+    };
+    let (first, last) = span_rows(line_starts, span);
+    yaml_body_entries(lines, first, last)
+        .into_iter()
+        .filter_map(|(key, first, last)| {
+            let child = map.get(key.as_str())?;
+            Some((key, row_span(source, line_starts, first, last), child))
+        })
+        .collect()
+}
+
+/// Parse YAML configuration files and extract top-level keys, and the
+/// sections `nesting` allows below them, as semantic units
+///
+/// Files containing Go template expressions (Helm charts) that fail strict
+/// parsing are retried in tolerant mode; see `parse_yaml_template`.
+pub fn parse_yaml(file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Result<Vec<SemanticUnit>, String> {
+    let parsed: YamlValue = match serde_yaml::from_str(source_code) {
+        Ok(parsed) => parsed,
+        Err(e) if source_code.contains("{{") => {
+            return parse_yaml_template(file_path, source_code, nesting)
+                .map_err(|te| format!("YAML parse error: {} (template-tolerant parse also failed: {})", e, te));
+        }
+        Err(e) => return Err(format!("YAML parse error: {}", e)),
+    };
+    let YamlValue::Mapping(map) = &parsed else {
+        return Ok(Vec::new());
+    };
+
+    let line_starts = line_starts(source_code);
+    let lines: Vec<&str> = source_code.lines().collect();
+    let sections = Sections {
+        source: source_code,
+        line_starts: &line_starts,
+        language: "Yaml",
+        nesting,
+        children: &|_, span, value| yaml_children(source_code, &lines, &line_starts, span, value),
+        is_collection: |value: &YamlValue| value.is_mapping() || value.is_sequence(),
+    };
+    Ok(yaml_entries(source_code)
+        .into_iter()
+        .filter_map(|(key, first, last)| {
+            let value = map.get(key.as_str())?;
+            Some(sections.units(key, row_span(source_code, &line_starts, first, last), value))
+        })
+        .flatten()
+        .collect())
+}
+
+/// Placeholder substituted for inline template expressions before YAML parsing
+const TEMPLATE_PLACEHOLDER: &str = "__template__";
+
+/// A `{{ ... }}` Go template expression and the (1-indexed) line it starts on
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateRegion {
+    pub line: usize,
+    pub expression: String,
+}
+
+/// Remove Go template expressions so the remaining YAML can be parsed.
+///
+/// Lines holding only template actions (`{{- if ... }}`, `{{ end }}`,
+/// `{{ include ... | nindent 4 }}`) are blanked; inline expressions used as
+/// values are replaced with a placeholder scalar. Newlines are preserved so
+/// line numbers still match the original file.
+pub fn strip_go_templates(source: &str) -> (String, Vec<TemplateRegion>) {
+    let mut stripped = String::with_capacity(source.len());
+    let mut regions = Vec::new();
+
+    for (idx, line) in source.split_inclusive('\n').enumerate() {
+        let mut rest = line;
+        let mut rewritten = String::new();
+        let mut found = false;
+        let mut only_templates = true;
+
+        while let Some(open) = rest.find("{{") {
+            let Some(close) = rest[open..].find("}}") else {
+                break;
+            };
+            let expression = &rest[open + 2..open + close];
+            regions.push(TemplateRegion {
+                line: idx + 1,
+                expression: expression.trim_matches(|c: char| c == '-' || c.is_whitespace()).to_string(),
+            });
+            found = true;
+            if !rest[..open].trim().is_empty() {
+                only_templates = false;
             }
+            rewritten.push_str(&rest[..open]);
+            rewritten.push_str(TEMPLATE_PLACEHOLDER);
+            rest = &rest[open + close + 2..];
+        }
+
+        if found && only_templates && rest.trim().is_empty() {
+            // Pure template action line: keep only the line break
+            stripped.push_str(&line[line.trim_end_matches(['\r', '\n']).len()..]);
+        } else {
+            stripped.push_str(&rewritten);
+            stripped.push_str(rest);
+        }
+    }
+
+    (stripped, regions)
+}
+
+/// Tolerant YAML parsing for Helm-style templates.
+///
+/// Template expressions are stripped before parsing, every `---` document is
+/// read, and unit content is taken verbatim from the original source (so it
+/// shows the real expressions rather than placeholders). Units record
+/// `templated`, `template_lines` and `template_expressions` metadata for the
+/// expressions inside their line range.
+pub fn parse_yaml_template(
+    _file_path: &str,
+    source_code: &str,
+    nesting: &ConfigNesting,
+) -> Result<Vec<SemanticUnit>, String> {
+    use serde::Deserialize;
+
+    let (stripped, regions) = strip_go_templates(source_code);
+    let mut keys = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(&stripped) {
+        let parsed = YamlValue::deserialize(document)
+            .map_err(|e| format!("YAML parse error: {}", e))?;
+        if let YamlValue::Mapping(map) = parsed {
+            keys.extend(map.into_iter().filter_map(|(key, _)| key.as_str().map(str::to_string)));
+        }
+    }
+
+    // Stripping keeps every line in place, so rows found in the stripped
+    // text index the original source too. Lines holding only template
+    // actions are blank once stripped; an indented stand-in keeps them in
+    // the entry around them.
+    let structure: Vec<&str> = stripped
+        .lines()
+        .zip(source_code.lines())
+        .map(|(line, original)| if line.trim().is_empty() && !original.trim().is_empty() { " ~" } else { line })
+        .collect();
+    let line_starts = line_starts(source_code);
+    let stripped_lines: Vec<&str> = stripped.lines().collect();
+    let entries: Vec<(String, (usize, usize), YamlValue)> = yaml_entries(&structure.join("\n"))
+        .into_iter()
+        .filter(|(key, _, _)| keys.contains(key))
+        .map(|(key, first, last)| {
+            let span = row_span(source_code, &line_starts, first, last);
+            // Keys repeat across documents, so an entry's value is parsed
+            // from its own lines
+            let value = if nesting.splits(1, span) {
+                serde_yaml::from_str::<YamlValue>(&stripped_lines[first..=last].join("\n"))
+                    .ok()
+                    .and_then(|entry| entry.get(key.as_str()).cloned())
+                    .unwrap_or(YamlValue::Null)
+            } else {
+                YamlValue::Null
+            };
+            (key, span, value)
+        })
+        .collect();
+
+    let sections = Sections {
+        source: source_code,
+        line_starts: &line_starts,
+        language: "Yaml",
+        nesting,
+        children: &|_, span, value| yaml_children(source_code, &structure, &line_starts, span, value),
+        is_collection: |value: &YamlValue| value.is_mapping() || value.is_sequence(),
+    };
+    let mut units: Vec<SemanticUnit> =
+        entries.iter().flat_map(|(key, span, value)| sections.units(key.clone(), *span, value)).collect();
+    for unit in &mut units {
+        let inside: Vec<&TemplateRegion> = regions
+            .iter()
+            .filter(|r| r.line >= unit.start_line && r.line <= unit.end_line)
+            .collect();
+        unit.metadata.insert("templated".to_string(), (!inside.is_empty()).to_string());
+        if !inside.is_empty() {
+            let mut lines: Vec<String> = inside.iter().map(|r| r.line.to_string()).collect();
+            lines.dedup();
+            unit.metadata.insert("template_lines".to_string(), lines.join(","));
+            unit.metadata.insert(
+                "template_expressions".to_string(),
+                inside.iter().map(|r| r.expression.as_str()).collect::<Vec<_>>().join("\n"),
+            );
         }
     }
 
     Ok(units)
 }
 
-/// Parse TOML configuration files and extract top-level sections as semantic units
-pub fn parse_toml(_file_path: &str, source_code: &str) -> Result<Vec<SemanticUnit>, String> {
+/// End of the TOML key/value statement starting at `start`: the newline
+/// after its value, skipping multi-line arrays, inline tables and strings
+fn toml_statement_end(source: &str, start: usize) -> usize {
+    let bytes = source.as_bytes();
+    let line_end = |at: usize| source[at..].find('\n').map_or(bytes.len(), |n| at + n);
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < bytes.len() {
+        if let Some(quotes) = [b"\"\"\"", b"'''"].into_iter().find(|q| bytes[i..].starts_with(*q)) {
+            // Multi-line strings; only basic (double-quoted) ones have escapes
+            let mut j = i + 3;
+            while j < bytes.len() && !bytes[j..].starts_with(quotes) {
+                j += if quotes[0] == b'"' && bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            // Up to two quotes may directly precede the closing delimiter
+            i = j + 3;
+            while bytes.get(i) == Some(&quotes[0]) {
+                i += 1;
+            }
+            continue;
+        }
+        match bytes[i] {
+            b'"' => {
+                i = json_string_end(bytes, i);
+                continue;
+            }
+            b'\'' => {
+                i = source[i + 1..].find('\'').map_or(bytes.len(), |n| i + n + 2);
+                continue;
+            }
+            b'#' => {
+                i = line_end(i);
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            b'\n' if depth == 0 => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Segments of the dotted TOML key at the start of `text` (`a."b c".d`)
+fn toml_key_path(text: &str) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t']);
+        let segment = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = rest[1..].split(quote).next().unwrap_or("");
+                rest = rest.get(inner.len() + 2..).unwrap_or("");
+                inner.to_string()
+            }
+            _ => {
+                let bare: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '-').collect();
+                rest = &rest[bare.len()..];
+                bare
+            }
+        };
+        path.push(segment);
+        match rest.trim_start_matches([' ', '\t']).strip_prefix('.') {
+            Some(after) => rest = after,
+            None => return path,
+        }
+    }
+}
+
+/// Keys one level below `prefix` in the TOML source within `span`, with the
+/// span of their source text; the whole document and an empty prefix give
+/// its top-level keys. A table runs from its header (`[a]`, `[a.b]`,
+/// `[[a]]`) to its last statement; consecutive tables and statements under
+/// the same key form one entry, so a key split across the file yields one
+/// entry per run.
+fn toml_entries(source: &str, (start, end): (usize, usize), prefix: &[String]) -> Vec<Entry> {
+    let bytes = source.as_bytes();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut table: Vec<String> = Vec::new();
+    // Whether the previous table or statement was under a key of `prefix`
+    let mut in_run = false;
+    let mut i = start;
+    while i < end {
+        let (path, item_end) = match bytes[i] {
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'#' => {
+                i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'[' => {
+                let header_end = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                table = toml_key_path(source[i..header_end].trim_start_matches('['));
+                (table.clone(), header_end)
+            }
+            // Statements belong to the table above them
+            _ => {
+                let mut path = table.clone();
+                path.extend(toml_key_path(&source[i..]));
+                (path, toml_statement_end(source, i))
+            }
+        };
+        let key = path.strip_prefix(prefix).and_then(<[String]>::first);
+        if let Some(key) = key {
+            match entries.last_mut() {
+                Some((last, (_, last_end))) if in_run && *last == *key => *last_end = item_end,
+                _ => entries.push((key.clone(), (i, item_end))),
+            }
+        }
+        in_run = key.is_some();
+        i = item_end;
+    }
+    entries
+}
+
+/// Entries of the TOML table at `path`, spanning `span`, with their values
+fn toml_children<'v>(
+    source: &str,
+    path: &[String],
+    span: (usize, usize),
+    value: &'v TomlValue,
+) -> Vec<Child<'v, TomlValue>> {
+    let TomlValue::Table(table) = value else {
+        return Vec::new();
+    };
+    toml_entries(source, span, path)
+        .into_iter()
+        .filter_map(|(key, span)| table.get(&key).map(|child| (key, span, child)))
+        .collect()
+}
+
+/// Parse TOML configuration files and extract top-level sections, and the
+/// sections `nesting` allows below them, as semantic units
+pub fn parse_toml(_file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Result<Vec<SemanticUnit>, String> {
     let parsed: TomlValue = source_code.parse()
         .map_err(|e: toml::de::Error| format!("TOML parse error: {}", e))?;
+    let TomlValue::Table(table) = &parsed else {
+        return Ok(Vec::new());
+    };
+
+    let line_starts = line_starts(source_code);
+    let sections = Sections {
+        source: source_code,
+        line_starts: &line_starts,
+        language: "Toml",
+        nesting,
+        children: &|path, span, value| toml_children(source_code, path, span, value),
+        is_collection: |value: &TomlValue| value.is_table() || value.is_array(),
+    };
+    Ok(toml_entries(source_code, (0, source_code.len()), &[])
+        .into_iter()
+        .filter_map(|(key, span)| {
+            let value = table.get(&key)?;
+            Some(sections.units(key, span, value))
+        })
+        .flatten()
+        .collect())
+}
+
+/// A key of a line-based format (INI, .env, .properties): its dotted
+/// segments and the rows its entry spans
+type KeyRows = (Vec<String>, usize, usize);
+
+/// Runs of consecutive `keys` sharing their segment at `index`
+fn key_runs(keys: &[KeyRows], index: usize) -> Vec<(String, &[KeyRows])> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=keys.len() {
+        if i == keys.len() || keys[i].0.get(index) != keys[start].0.get(index) {
+            runs.push((keys[start].0.get(index).cloned().unwrap_or_default(), &keys[start..i]));
+            start = i;
+        }
+    }
+    runs
+}
 
+/// Units for the keys of a line-based format: one per run of consecutive
+/// keys sharing their first segment, then, as far as `nesting` allows, one
+/// per run sharing a deeper segment among keys that go deeper still
+fn key_units(source: &str, keys: &[KeyRows], language: &str, nesting: &ConfigNesting) -> Vec<SemanticUnit> {
+    let line_starts = line_starts(source);
     let mut units = Vec::new();
+    let mut pending: Vec<(Vec<String>, &[KeyRows])> =
+        key_runs(keys, 0).into_iter().rev().map(|(segment, run)| (vec![segment], run)).collect();
+    while let Some((path, run)) = pending.pop() {
+        let span = row_span(source, &line_starts, run[0].1, run[run.len() - 1].2);
+        units.push(section_unit(source, &line_starts, &path, span, language));
+        if !nesting.splits(path.len(), span) {
+            continue;
+        }
+        let depth = path.len();
+        pending.extend(
+            key_runs(run, depth)
+                .into_iter()
+                .rev()
+                .filter(|(_, run)| run.iter().any(|(segments, _, _)| segments.len() > depth + 1))
+                .map(|(segment, run)| {
+                    let mut path = path.clone();
+                    path.push(segment);
+                    (path, run)
+                }),
+        );
+    }
+    units
+}
 
-    if let TomlValue::Table(table) = parsed {
-        for (key, value) in table.iter() {
-            let (start_line, end_line) = find_key_lines(source_code, key);
-
-            let content = format_toml_section(key, value);
-
-            units.push(SemanticUnit {
-                unit_type: "class".to_string(),
-                name: key.clone(),
-                start_line,
-                end_line,
-                start_byte: 0,
-                end_byte: content.len(),
-                signature: key.clone(),
-                content,
-                language: "Toml".to_string(),
-            });
+/// Sections of an INI file, from header to last key, and the keys before
+/// the first section
+fn ini_keys(source: &str) -> Vec<KeyRows> {
+    let mut keys: Vec<KeyRows> = Vec::new();
+    let mut in_section = false;
+    for (row, line) in source.lines().enumerate() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with([';', '#']) {
+            continue;
+        }
+        if let Some(header) = content.strip_prefix('[') {
+            let name = header.split(']').next().unwrap_or("").trim();
+            keys.push((vec![name.to_string()], row, row));
+            in_section = true;
+        } else if in_section || line.starts_with([' ', '\t']) {
+            // Keys belong to the section above them; indented lines continue a value
+            if let Some(last) = keys.last_mut() {
+                last.2 = row;
+            }
+        } else {
+            let key = content.split(['=', ':']).next().unwrap_or(content).trim();
+            keys.push((vec![key.to_string()], row, row));
         }
     }
+    keys
+}
 
-    Ok(units)
+/// Parse INI files: each section, and each key before the first section,
+/// becomes a "config_section" unit
+pub fn parse_ini(_file_path: &str, source_code: &str) -> Vec<SemanticUnit> {
+    key_units(source_code, &ini_keys(source_code), "Ini", &ConfigNesting::default())
 }
 
-/// Find approximate line numbers for a key in the source code
-fn find_key_lines(source: &str, key: &str) -> (usize, usize) {
-    let lines: Vec<&str> = source.lines().collect();
+/// Whether a file is a dotenv file: `.env`, `.env.local`, `prod.env`
+pub fn is_env_file(file_path: &str) -> bool {
+    let name = std::path::Path::new(file_path).file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+}
 
-    // Search for the key
-    for (idx, line) in lines.iter().enumerate() {
-        if line.contains(key) {
-            let start = idx + 1; // 1-indexed
+/// Whether `text` holds the closing `quote` of a value, `\"` escapes aside
+fn closes_quote(text: &str, quote: char) -> bool {
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
 
-            // Estimate end line by looking for next top-level key or end of file
-            let mut end = start;
-            for i in (idx + 1)..lines.len() {
-                // Simple heuristic: next non-indented line or end of file
-                if !lines[i].starts_with(' ') && !lines[i].starts_with('\t') && !lines[i].trim().is_empty() {
-                    end = i; // Line before next key
-                    break;
-                }
-                end = i + 1;
+/// Variables of a dotenv file; quoted values may span lines
+fn env_keys(source: &str) -> Vec<KeyRows> {
+    let mut keys = Vec::new();
+    let mut lines = source.lines().enumerate();
+    while let Some((row, line)) = lines.next() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let content = content.strip_prefix("export ").map_or(content, str::trim_start);
+        let Some((key, value)) = content.split_once('=') else {
+            continue;
+        };
+        let mut last = row;
+        let value = value.trim_start();
+        if let Some(quote @ ('"' | '\'')) = value.chars().next() {
+            let mut rest = &value[1..];
+            while !closes_quote(rest, quote) {
+                let Some((row, line)) = lines.next() else { break };
+                (last, rest) = (row, line);
             }
-
-            return (start, end);
         }
+        keys.push((vec![key.trim().to_string()], row, last));
     }
+    keys
+}
 
-    (1, lines.len()) // Fallback: entire file
+/// Parse dotenv files: each variable becomes a "config_section" unit
+pub fn parse_env(_file_path: &str, source_code: &str) -> Vec<SemanticUnit> {
+    key_units(source_code, &env_keys(source_code), "Env", &ConfigNesting::default())
 }
 
-/// Format a JSON section for content display
-fn format_json_section(key: &str, value: &JsonValue) -> String {
-    match serde_json::to_string_pretty(value) {
-        Ok(pretty) => format!("\"{}\": {}", key, pretty),
-        Err(_) => format!("\"{}\": [complex object]", key),
+/// The key of a properties line: up to the first unescaped `=`, `:` or
+/// whitespace
+fn properties_key(line: &str) -> String {
+    let mut key = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => key.extend(chars.next()),
+            '=' | ':' => break,
+            c if c.is_whitespace() => break,
+            c => key.push(c),
+        }
     }
+    key
 }
 
-/// Format a YAML section for content display
-fn format_yaml_section(key: &str, value: &YamlValue) -> String {
-    match serde_yaml::to_string(value) {
-        Ok(yaml_str) => {
-            // Indent the value part
-            let indented = yaml_str
-                .lines()
-                .map(|line| if line.is_empty() { line.to_string() } else { format!("  {}", line) })
-                .collect::<Vec<_>>()
-                .join("\n");
-            format!("{}:\n{}", key, indented)
+/// Keys of a Java properties file split on dots; a line ending in an odd
+/// number of backslashes continues on the next
+fn properties_keys(source: &str) -> Vec<KeyRows> {
+    let continues = |line: &str| (line.len() - line.trim_end_matches('\\').len()) % 2 == 1;
+    let mut keys = Vec::new();
+    let mut lines = source.lines().enumerate();
+    while let Some((row, line)) = lines.next() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with(['#', '!']) {
+            continue;
+        }
+        let mut last = (row, line);
+        while continues(last.1) {
+            let Some(next) = lines.next() else { break };
+            last = next;
         }
-        Err(_) => format!("{}: [complex object]", key),
+        keys.push((properties_key(content).split('.').map(str::to_string).collect(), row, last.0));
     }
+    keys
 }
 
-/// Format a TOML section for content display
-fn format_toml_section(key: &str, value: &TomlValue) -> String {
-    match toml::to_string(value) {
-        Ok(toml_str) => {
-            if value.is_table() {
-                format!("[{}]\n{}", key, toml_str)
-            } else {
-                format!("{} = {}", key, toml_str.trim())
+/// Parse Java properties files: consecutive keys sharing their first
+/// segment (`spring.datasource.url`, `spring.jpa.show-sql`) form one
+/// "config_section" unit, split by deeper segments as `nesting` allows
+pub fn parse_properties(_file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Vec<SemanticUnit> {
+    key_units(source_code, &properties_keys(source_code), "Properties", nesting)
+}
+
+/// An XML element and where it sits in the source
+struct XmlElement {
+    name: String,
+    parent: Option<usize>,
+    depth: usize, // 0 for the root
+    start: usize,
+    start_tag_end: usize,
+    end: usize,
+    has_children: bool,
+}
+
+/// Parse XML files: the root element's children become "config_section"
+/// units, as do elements nested in them that hold elements of their own,
+/// as far as `nesting` allows. Units are qualified by their path below the
+/// root (`build.plugins`) and their signature is the start tag.
+pub fn parse_xml(_file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Result<Vec<SemanticUnit>, String> {
+    let mut reader = Reader::from_str(source_code);
+    let mut elements: Vec<XmlElement> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("XML parse error at byte {}: {}", reader.error_position(), e))?;
+        let end = reader.buffer_position() as usize;
+        match event {
+            Event::Start(ref tag) | Event::Empty(ref tag) => {
+                let parent = open.last().copied();
+                if let Some(parent) = parent {
+                    elements[parent].has_children = true;
+                }
+                // After text the reader may already be past the tag's `<`
+                let start = source_code.get(..=start).and_then(|head| head.rfind('<')).unwrap_or(start);
+                let name = String::from_utf8_lossy(tag.name().as_ref()).into_owned();
+                let depth = open.len();
+                elements.push(XmlElement { name, parent, depth, start, start_tag_end: end, end, has_children: false });
+                if matches!(event, Event::Start(_)) {
+                    open.push(elements.len() - 1);
+                }
             }
+            Event::End(_) => {
+                if let Some(element) = open.pop() {
+                    elements[element].end = end;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
         }
-        Err(_) => format!("[{}]\n[complex section]", key),
     }
+
+    let line_starts = line_starts(source_code);
+    let mut emitted = vec![false; elements.len()];
+    let mut units = Vec::new();
+    for (i, element) in elements.iter().enumerate() {
+        let Some(parent) = element.parent else {
+            continue;
+        };
+        let above = &elements[parent];
+        // The root's children are the top-level sections
+        let parent_splits = above.parent.is_none()
+            || (emitted[parent] && nesting.splits(above.depth, (above.start, above.end)));
+        if !parent_splits || (element.depth > 1 && !element.has_children) {
+            continue;
+        }
+        emitted[i] = true;
+
+        let mut path = vec![element.name.clone()];
+        let mut ancestor = parent;
+        while let Some(up) = elements[ancestor].parent {
+            path.push(elements[ancestor].name.clone());
+            ancestor = up;
+        }
+        path.reverse();
+        let mut unit = section_unit(source_code, &line_starts, &path, (element.start, element.end), "Xml");
+        let start_tag = &source_code[element.start..element.start_tag_end];
+        unit.signature = start_tag.split_whitespace().collect::<Vec<_>>().join(" ");
+        units.push(unit);
+    }
+    Ok(units)
 }
 
-/// Parse a configuration file based on its extension
-pub fn parse_config_file(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
-    let start = std::time::Instant::now();
+/// Parse a configuration file based on its extension, or its name for dotenv files
+pub fn parse_config_file(file_path: &str, source_code: &str, nesting: &ConfigNesting) -> Result<ParseResult, String> {
+    if is_env_file(file_path) {
+        return parse_config_source(file_path, source_code, "env", nesting);
+    }
 
     // Detect format from file extension
     let extension = std::path::Path::new(file_path)
@@ -173,11 +1041,29 @@ pub fn parse_config_file(file_path: &str, source_code: &str) -> Result<ParseResu
         .and_then(|e| e.to_str())
         .ok_or("No file extension")?;
 
-    let (units, language) = match extension {
-        "json" => (parse_json(file_path, source_code)?, "Json"),
-        "yaml" | "yml" => (parse_yaml(file_path, source_code)?, "Yaml"),
-        "toml" => (parse_toml(file_path, source_code)?, "Toml"),
-        _ => return Err(format!("Unsupported config file extension: {}", extension)),
+    parse_config_source(file_path, source_code, extension, nesting)
+}
+
+/// Parse config source in an explicit format ("json"/"jsonc"/"json5",
+/// "yaml"/"yml", "toml", "ini"/"cfg", "env", "properties" or "xml");
+/// `file_path` is only used for labelling units and may be empty.
+pub fn parse_config_source(
+    file_path: &str,
+    source_code: &str,
+    format: &str,
+    nesting: &ConfigNesting,
+) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+
+    let (units, language) = match format {
+        "json" | "jsonc" | "json5" => (parse_json(file_path, source_code, nesting)?, "Json"),
+        "yaml" | "yml" => (parse_yaml(file_path, source_code, nesting)?, "Yaml"),
+        "toml" => (parse_toml(file_path, source_code, nesting)?, "Toml"),
+        "ini" | "cfg" => (parse_ini(file_path, source_code), "Ini"),
+        "env" => (parse_env(file_path, source_code), "Env"),
+        "properties" => (parse_properties(file_path, source_code, nesting), "Properties"),
+        "xml" => (parse_xml(file_path, source_code, nesting)?, "Xml"),
+        _ => return Err(format!("Unsupported config file extension: {}", format)),
     };
 
     let elapsed = start.elapsed();
@@ -187,5 +1073,228 @@ pub fn parse_config_file(file_path: &str, source_code: &str) -> Result<ParseResu
         language: language.to_string(),
         units,
         parse_time_ms: elapsed.as_secs_f64() * 1000.0,
+        warnings: Vec::new(),
+        file_classification: String::new(),
     })
 }
+
+/// Parse a config file into its sections, nested ones included
+///
+/// Besides the top-level keys `parse_source_file` reports, mappings and
+/// lists nested up to `max_depth` levels deep become units of their own,
+/// qualified by their dotted path, so a Kubernetes manifest yields
+/// `spec.template.spec.containers` rather than one giant `spec`. Lists are
+/// not split into their items.
+///
+/// Args:
+///     file_path: Path of the file; its extension (or a `.env` name)
+///         selects the format: JSON, YAML, TOML, INI, dotenv, Java
+///         properties or XML
+///     source_code: Contents of the file
+///     max_depth: Deepest level to emit, 1 being the top-level keys
+///     max_unit_bytes: Only split sections longer than this, keeping
+///         smaller ones whole; by default every section is split
+///
+/// Returns:
+///     ParseResult with each section's unit followed by those nested in it.
+///     Raises ValueError when max_depth is 0.
+#[pyfunction]
+#[pyo3(name = "parse_config_file", signature = (file_path, source_code, max_depth=3, max_unit_bytes=None))]
+pub fn parse_config_sections(
+    py: Python<'_>,
+    file_path: String,
+    source_code: String,
+    max_depth: usize,
+    max_unit_bytes: Option<usize>,
+) -> PyResult<ParseResult> {
+    if max_depth == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err("max_depth must be at least 1"));
+    }
+    let nesting = ConfigNesting { max_depth, max_unit_bytes };
+    py.detach(|| parse_config_file(&file_path, &source_code, &nesting))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELM_DEPLOYMENT: &str = "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: {{ include \"app.fullname\" . }}\n  labels:\n    {{- include \"app.labels\" . | nindent 4 }}\nspec:\n  {{- if not .Values.autoscaling.enabled }}\n  replicas: {{ .Values.replicaCount }}\n  {{- end }}\n  template:\n    spec:\n      containers:\n        - image: \"{{ .Values.image.repository }}:{{ .Values.image.tag }}\"\n";
+
+    #[test]
+    fn test_strip_go_templates_preserves_lines() {
+        let (stripped, regions) = strip_go_templates(HELM_DEPLOYMENT);
+        assert_eq!(stripped.lines().count(), HELM_DEPLOYMENT.lines().count());
+        assert!(!stripped.contains("{{"));
+        assert_eq!(stripped.lines().nth(5), Some(""));
+        assert_eq!(stripped.lines().nth(8), Some("  replicas: __template__"));
+        assert_eq!(regions.len(), 7);
+        assert_eq!(regions[0], TemplateRegion { line: 4, expression: "include \"app.fullname\" .".to_string() });
+    }
+
+    #[test]
+    fn test_helm_template_parses_in_tolerant_mode() {
+        assert!(serde_yaml::from_str::<YamlValue>(HELM_DEPLOYMENT).is_err());
+
+        let units = parse_yaml("chart/templates/deployment.yaml", HELM_DEPLOYMENT, &ConfigNesting::default()).unwrap();
+        let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["apiVersion", "kind", "metadata", "spec"]);
+
+        let metadata = &units[2];
+        assert_eq!(metadata.metadata["templated"], "true");
+        assert_eq!(metadata.metadata["template_lines"], "4,6");
+        assert!(metadata.content.contains("{{ include \"app.fullname\" . }}"));
+
+        let spec = &units[3];
+        assert_eq!(spec.metadata["template_lines"], "8,9,10,14");
+        assert!(spec.metadata["template_expressions"].contains(".Values.replicaCount"));
+        assert_eq!(units[0].metadata["templated"], "false");
+    }
+
+    #[test]
+    fn test_helm_multi_document_templates() {
+        let source = "apiVersion: v1\nkind: Service\n---\n{{- if .Values.ingress.enabled }}\napiVersion: networking.k8s.io/v1\nkind: Ingress\n{{- end }}\n";
+        let units = parse_yaml("templates/all.yaml", source, &ConfigNesting::default()).unwrap();
+        assert_eq!(units.len(), 4);
+    }
+
+    fn spans(units: &[SemanticUnit]) -> Vec<(&str, usize, usize)> {
+        units.iter().map(|u| (u.name.as_str(), u.start_line, u.end_line)).collect()
+    }
+
+    #[test]
+    fn test_spans_are_exact_when_keys_appear_in_values() {
+        let json = "{\n  \"a\": \"name\",\n  \"name\": {\n    \"x\": [1, \"}\"]\n  }\n}\n";
+        let units = parse_json("a.json", json, &ConfigNesting::default()).unwrap();
+        assert_eq!(spans(&units), vec![("a", 2, 2), ("name", 3, 5)]);
+        assert_eq!(units[1].content, "\"name\": {\n    \"x\": [1, \"}\"]\n  }");
+        assert_eq!(&json[units[1].start_byte..units[1].end_byte], units[1].content);
+
+        let yaml = "# settings for name\nimage: name\nname:\n  first: a\n\n  # trailing\nlist:\n- one\n- two\n";
+        let units = parse_yaml("a.yaml", yaml, &ConfigNesting::default()).unwrap();
+        assert_eq!(spans(&units), vec![("image", 2, 2), ("name", 3, 4), ("list", 7, 9)]);
+        assert_eq!(units[1].content, "name:\n  first: a");
+
+        let toml = "title = \"package\" # [package]\nnotes = \"\"\"\n[fake]\n\"\"\"\n\n[package]\nname = \"x\"\n\n\
+            # Release builds\n[profile.release]\nlto = true\n[profile.dev]\nopt-level = 0\n";
+        let units = parse_toml("a.toml", toml, &ConfigNesting::default()).unwrap();
+        assert_eq!(spans(&units), vec![("title", 1, 1), ("notes", 2, 4), ("package", 6, 7), ("profile", 10, 13)]);
+        assert_eq!(&toml[units[2].start_byte..units[2].end_byte], "[package]\nname = \"x\"");
+    }
+
+    #[test]
+    fn test_plain_yaml_errors_are_not_retried() {
+        let err = parse_yaml("bad.yaml", "key: [unclosed", &ConfigNesting::default()).unwrap_err();
+        assert!(err.starts_with("YAML parse error"));
+        assert!(!err.contains("template-tolerant"));
+    }
+
+    #[test]
+    fn test_nested_sections_have_dotted_paths() {
+        let nesting = ConfigNesting { max_depth: 4, max_unit_bytes: None };
+        let paths = |units: Vec<SemanticUnit>| -> Vec<(String, usize, usize)> {
+            units.into_iter().map(|u| (u.qualified_name, u.start_line, u.end_line)).collect()
+        };
+        let path = |name: &str, start: usize, end: usize| (name.to_string(), start, end);
+
+        let yaml = "kind: Deployment\nspec:\n  replicas: 2\n  template:\n    # pod\n    spec:\n      \
+            containers:\n      - name: app\n        image: app:1\n      volumes: []\n";
+        let units = parse_yaml("deploy.yaml", yaml, &nesting).unwrap();
+        assert_eq!(units[3].parent_name.as_deref(), Some("template"));
+        assert_eq!(
+            paths(units),
+            vec![
+                path("kind", 1, 1),
+                path("spec", 2, 10),
+                path("spec.template", 4, 10),
+                path("spec.template.spec", 6, 10),
+                path("spec.template.spec.containers", 7, 9),
+                path("spec.template.spec.volumes", 10, 10),
+            ]
+        );
+
+        let json = "{\"a\": {\"b\": {\"c\": [1]}, \"n\": 1}}";
+        let units = parse_json("a.json", json, &nesting).unwrap();
+        assert_eq!(units[2].content, "\"c\": [1]");
+        assert_eq!(paths(units), vec![path("a", 1, 1), path("a.b", 1, 1), path("a.b.c", 1, 1)]);
+
+        let toml = "[profile.release]\nlto = true\n[profile.release.build-override]\nopt-level = 3\n\
+            [profile.dev]\nopt-level = 0\n";
+        let units = parse_toml("Cargo.toml", toml, &nesting).unwrap();
+        assert_eq!(
+            paths(units),
+            vec![
+                path("profile", 1, 6),
+                path("profile.release", 1, 4),
+                path("profile.release.build-override", 3, 4),
+                path("profile.dev", 5, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nesting_limits() {
+        let yaml = "small:\n  a: {x: 1}\nlarge:\n  a:\n    b:\n      c: [1, 2, 3]\n";
+        let names = |nesting: ConfigNesting| -> Vec<String> {
+            let units = parse_yaml("a.yaml", yaml, &nesting).unwrap();
+            units.into_iter().map(|u| u.qualified_name).collect()
+        };
+        assert_eq!(names(ConfigNesting::default()), vec!["small", "large"]);
+        let two_levels = ConfigNesting { max_depth: 2, max_unit_bytes: None };
+        assert_eq!(names(two_levels), vec!["small", "small.a", "large", "large.a"]);
+        assert_eq!(
+            names(ConfigNesting { max_depth: 4, max_unit_bytes: Some(20) }),
+            vec!["small", "large", "large.a", "large.a.b", "large.a.b.c"]
+        );
+    }
+
+    #[test]
+    fn test_ini_env_properties_and_xml_sections() {
+        let ini = "; global\nroot = /srv\n\n[server]\nhost = localhost\nport: 8080\n\n\
+            [database]\nurl = x\n  continued\n";
+        let units = parse_ini("app.ini", ini);
+        assert_eq!(spans(&units), vec![("root", 2, 2), ("server", 4, 6), ("database", 8, 10)]);
+        assert!(units.iter().all(|u| u.unit_type.as_str() == "config_section"));
+
+        let env = "# secrets\nexport API_URL=https://x\nCERT=\"line one\nline two\"\nDEBUG=1\n";
+        assert!(is_env_file("config/.env.local"));
+        assert_eq!(spans(&parse_env(".env", env)), vec![("API_URL", 2, 2), ("CERT", 3, 4), ("DEBUG", 5, 5)]);
+
+        let nesting = ConfigNesting { max_depth: 2, max_unit_bytes: None };
+        let properties = "app.name=demo\nspring.datasource.url=jdbc:h2:mem\nspring.datasource.user=sa\n\
+            spring.jpa.show-sql=true\nspring.profile=dev\nmessage = hello \\\n    world\n";
+        let units: Vec<(String, usize, usize)> = parse_properties("application.properties", properties, &nesting)
+            .into_iter()
+            .map(|u| (u.qualified_name, u.start_line, u.end_line))
+            .collect();
+        let expected =
+            [("app", 1, 1), ("spring", 2, 5), ("spring.datasource", 2, 3), ("spring.jpa", 4, 4), ("message", 6, 7)];
+        assert_eq!(units, expected.map(|(name, start, end)| (name.to_string(), start, end)));
+
+        let xml = "<?xml version=\"1.0\"?>\n<project>\n  <modelVersion>4.0.0</modelVersion>\n  <dependencies>\n    \
+            <dependency><artifactId>a</artifactId></dependency>\n    <dependency/>\n  </dependencies>\n</project>\n";
+        let units = parse_xml("pom.xml", xml, &nesting).unwrap();
+        assert_eq!(spans(&units), vec![("modelVersion", 3, 3), ("dependencies", 4, 7), ("dependency", 5, 5)]);
+        assert_eq!(units[2].qualified_name, "dependencies.dependency");
+        assert_eq!(units[1].signature, "<dependencies>");
+        assert!(parse_xml("bad.xml", "<a><b></a>", &nesting).is_err());
+    }
+
+    #[test]
+    fn test_jsonc_and_json5_fall_back() {
+        let tsconfig = "// Base config\n{\n  \"compilerOptions\": {\n    /* ES2020, not \"ESNext\" */\n    \
+            \"target\": \"ES2020\", // url: \"http://x\"\n    \"strict\": true,\n  },\n  \"include\": [\"src\",],\n}\n";
+        assert!(serde_json::from_str::<JsonValue>(tsconfig).is_err());
+        let units = parse_json("tsconfig.json", tsconfig, &ConfigNesting::default()).unwrap();
+        assert_eq!(spans(&units), vec![("compilerOptions", 3, 7), ("include", 8, 8)]);
+        assert!(units[0].content.contains("/* ES2020"));
+
+        let json5 = "{\n  name: 'app',\n  'build': {out: 'dist',},\n  $schema: \"x\",\n}\n";
+        let nesting = ConfigNesting { max_depth: 2, max_unit_bytes: None };
+        let units = parse_json("app.json5", json5, &nesting).unwrap();
+        assert_eq!(spans(&units), vec![("name", 2, 2), ("build", 3, 3), ("$schema", 4, 4)]);
+
+        let err = parse_json("bad.jsonc", "{\n  // note\n  \"a\": [1,\n}\n", &nesting).unwrap_err();
+        assert!(err.starts_with("JSON parse error"));
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::parsing::SemanticUnit;
+
+/// An unresolved merge conflict, as byte ranges into the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// From the start of the `<<<<<<<` line to the end of the `>>>>>>>` line
+    pub span: Range<usize>,
+    pub ours: Range<usize>,
+    /// Only present for diff3-style conflicts (`|||||||` section)
+    pub base: Option<Range<usize>>,
+    pub theirs: Range<usize>,
+    pub ours_label: String,
+    pub base_label: Option<String>,
+    pub theirs_label: String,
+}
+
+/// Marker lines are the 7-character marker, alone or followed by a label
+fn marker_label<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(marker)?;
+    if rest.is_empty() {
+        return Some("");
+    }
+    rest.strip_prefix(' ').map(str::trim)
+}
+
+/// Find well-formed conflict regions. Unterminated or out-of-order markers
+/// are ignored rather than guessed at.
+pub fn find_conflicts(source: &str) -> Vec<Conflict> {
+    enum State {
+        Outside,
+        Ours { start: usize, label: String, body: usize },
+        Base { start: usize, ours_label: String, ours: Range<usize>, label: String, body: usize },
+        Theirs { start: usize, ours_label: String, ours: Range<usize>, base: Option<(Range<usize>, String)>, body: usize },
+    }
+
+    let mut conflicts = Vec::new();
+    let mut state = State::Outside;
+    let mut offset = 0;
+
+    for raw_line in source.split_inclusive('\n') {
+        let line_start = offset;
+        let line_end = offset + raw_line.len();
+        offset = line_end;
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+
+        if let Some(label) = marker_label(line, "<<<<<<<") {
+            // A new opening marker restarts detection
+            state = State::Ours { start: line_start, label: label.to_string(), body: line_end };
+            continue;
+        }
+
+        state = match state {
+            State::Ours { start, label, body } => {
+                if let Some(base_label) = marker_label(line, "|||||||") {
+                    State::Base { start, ours_label: label, ours: body..line_start, label: base_label.to_string(), body: line_end }
+                } else if line == "=======" {
+                    State::Theirs { start, ours_label: label, ours: body..line_start, base: None, body: line_end }
+                } else {
+                    State::Ours { start, label, body }
+                }
+            }
+            State::Base { start, ours_label, ours, label, body } => {
+                if line == "=======" {
+                    State::Theirs { start, ours_label, ours, base: Some((body..line_start, label)), body: line_end }
+                } else {
+                    State::Base { start, ours_label, ours, label, body }
+                }
+            }
+            State::Theirs { start, ours_label, ours, base, body } => {
+                if let Some(label) = marker_label(line, ">>>>>>>") {
+                    let (base, base_label) = match base {
+                        Some((range, label)) => (Some(range), Some(label)),
+                        None => (None, None),
+                    };
+                    conflicts.push(Conflict {
+                        span: start..line_end,
+                        ours,
+                        base,
+                        theirs: body..line_start,
+                        ours_label,
+                        base_label,
+                        theirs_label: label.to_string(),
+                    });
+                    State::Outside
+                } else {
+                    State::Theirs { start, ours_label, ours, base, body }
+                }
+            }
+            State::Outside => State::Outside,
+        };
+    }
+
+    conflicts
+}
+
+/// Resolve every conflict to "ours" for parsing: marker lines and the base
+/// and theirs sections are blanked to spaces (newlines kept), so byte offsets
+/// and line numbers are unchanged.
+pub fn keep_ours(source: &str, conflicts: &[Conflict]) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    for conflict in conflicts {
+        for range in [conflict.span.start..conflict.ours.start, conflict.ours.end..conflict.span.end] {
+            for byte in &mut bytes[range] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+        }
+    }
+    // Only whole UTF-8 sequences are replaced, each byte by an ASCII space
+    String::from_utf8(bytes).unwrap_or_else(|_| source.to_string())
+}
+
+/// One "conflict" unit per region, named after the innermost unit enclosing
+/// it (or its line when at top level). Each side's text and label, and the
+/// enclosing unit, are recorded in metadata.
+pub fn conflict_units(
+    source: &str,
+    conflicts: &[Conflict],
+    units: &[SemanticUnit],
+    language: &str,
+) -> Vec<SemanticUnit> {
+    conflicts
+        .iter()
+        .map(|conflict| {
+            let content = &source[conflict.span.clone()];
+            let start_line = source[..conflict.span.start].matches('\n').count() + 1;
+            let end_line = start_line + content.trim_end_matches('\n').matches('\n').count();
+
+            let enclosing = units
+                .iter()
+                // Blanked markers don't extend a unit, so a unit encloses the
+                // conflict when the region starts inside it
+                .filter(|u| u.unit_type != "conflict" && u.start_byte < conflict.span.start && conflict.span.start < u.end_byte)
+                .min_by_key(|u| u.end_byte - u.start_byte);
+
+            let mut metadata = HashMap::new();
+            metadata.insert("ours".to_string(), source[conflict.ours.clone()].to_string());
+            metadata.insert("theirs".to_string(), source[conflict.theirs.clone()].to_string());
+            metadata.insert("ours_label".to_string(), conflict.ours_label.clone());
+            metadata.insert("theirs_label".to_string(), conflict.theirs_label.clone());
+            if let Some(base) = &conflict.base {
+                metadata.insert("base".to_string(), source[base.clone()].to_string());
+            }
+            if let Some(label) = &conflict.base_label {
+                metadata.insert("base_label".to_string(), label.clone());
+            }
+            if let Some(unit) = enclosing {
+                metadata.insert("enclosing".to_string(), unit.name.clone());
+                metadata.insert("enclosing_type".to_string(), unit.unit_type.to_string());
+            }
+
+            let name = enclosing.map(|u| u.name.clone()).unwrap_or_else(|| format!("line {}", start_line));
+            SemanticUnit {
+                unit_type: "conflict".into(),
+                parent_name: enclosing.and_then(|u| u.parent_name.clone()),
+                qualified_name: enclosing.map(|u| u.qualified_name.clone()).unwrap_or_else(|| name.clone()),
+                name,
+                start_line,
+                end_line,
+                start_byte: conflict.span.start,
+                end_byte: conflict.span.end,
+                signature: content.lines().next().unwrap_or("").trim().to_string(),
+                parameters: None,
+                docstring: None,
+                decorators: Vec::new(),
+                content: content.to_string(),
+                content_ref: None,
+                content_hash: String::new(),
+                unit_id: String::new(),
+                language: language.into(),
+                metadata,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{CodeParser, ParseOptions};
+
+    const CONFLICTED: &str = "def total(items):\n<<<<<<< HEAD\n    return sum(items)\n=======\n    return sum(i.price for i in items)\n>>>>>>> feature/pricing\n\n\ndef other():\n    pass\n";
+
+    #[test]
+    fn test_find_conflicts_splits_sides() {
+        let conflicts = find_conflicts(CONFLICTED);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(&CONFLICTED[conflict.ours.clone()], "    return sum(items)\n");
+        assert_eq!(&CONFLICTED[conflict.theirs.clone()], "    return sum(i.price for i in items)\n");
+        assert_eq!(conflict.ours_label, "HEAD");
+        assert_eq!(conflict.theirs_label, "feature/pricing");
+        assert_eq!(conflict.base, None);
+    }
+
+    #[test]
+    fn test_find_conflicts_reads_diff3_base() {
+        let source = "<<<<<<< ours\na\n||||||| merged common ancestors\nb\n=======\nc\n>>>>>>> theirs\n";
+        let conflict = &find_conflicts(source)[0];
+        assert_eq!(&source[conflict.base.clone().unwrap()], "b\n");
+        assert_eq!(conflict.base_label.as_deref(), Some("merged common ancestors"));
+        assert_eq!(&source[conflict.theirs.clone()], "c\n");
+    }
+
+    #[test]
+    fn test_unterminated_markers_are_ignored() {
+        assert!(find_conflicts("<<<<<<< HEAD\na\n=======\nb\n").is_empty());
+        assert!(find_conflicts("=======\n>>>>>>> x\n").is_empty());
+    }
+
+    #[test]
+    fn test_keep_ours_preserves_offsets() {
+        let conflicts = find_conflicts(CONFLICTED);
+        let resolved = keep_ours(CONFLICTED, &conflicts);
+        assert_eq!(resolved.len(), CONFLICTED.len());
+        assert_eq!(resolved.matches('\n').count(), CONFLICTED.matches('\n').count());
+        assert!(resolved.contains("return sum(items)"));
+        assert!(!resolved.contains("i.price"));
+        assert!(!resolved.contains("<<<<<<<"));
+    }
+
+    #[test]
+    fn test_parsed_conflict_names_enclosing_function() {
+        let result = CodeParser::new().parse_file("cart.py", CONFLICTED, &ParseOptions::default()).unwrap();
+        let conflict = result.units.iter().find(|u| u.unit_type == "conflict").unwrap();
+        assert_eq!(conflict.name, "total");
+        assert_eq!((conflict.start_line, conflict.end_line), (2, 6));
+        assert_eq!(conflict.metadata["enclosing_type"], "function");
+        assert_eq!(conflict.metadata["ours"], "    return sum(items)\n");
+        assert!(result.units.iter().any(|u| u.unit_type == "function" && u.name == "other"));
+    }
+}
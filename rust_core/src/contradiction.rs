@@ -0,0 +1,180 @@
+use pyo3::prelude::*;
+use std::collections::BTreeSet;
+
+use crate::retrieval::RetrievalPipeline;
+
+/// Words that flip the meaning of a statement
+const NEGATIONS: &[&str] = &["not", "no", "never", "none", "nothing", "cannot", "without", "neither", "nor"];
+
+/// Opposed word forms; a memory using only one side of a pair contradicts
+/// one using only the other
+const ANTONYMS: &[(&[&str], &[&str])] = &[
+    (&["enable", "enabled", "enables"], &["disable", "disabled", "disables"]),
+    (&["true"], &["false"]),
+    (&["allow", "allowed", "allows"], &["deny", "denied", "denies", "forbid", "forbidden"]),
+    (&["include", "included", "includes"], &["exclude", "excluded", "excludes"]),
+    (&["required", "mandatory"], &["optional"]),
+    (&["sync", "synchronous"], &["async", "asynchronous"]),
+];
+
+/// Lexical facts of a memory's text that near-duplicates should agree on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Claims {
+    negated: bool,
+    /// `(pair index, side)` of each antonym form used
+    polarities: BTreeSet<(usize, bool)>,
+    numbers: BTreeSet<String>,
+    versions: BTreeSet<String>,
+}
+
+impl Claims {
+    pub fn of(text: &str) -> Self {
+        let mut claims = Claims::default();
+        for raw in text.split_whitespace() {
+            let word = raw
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .trim_end_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+                .replace('\u{2019}', "'");
+            if word.is_empty() {
+                continue;
+            }
+            if NEGATIONS.contains(&word.as_str()) || word.ends_with("n't") {
+                claims.negated = true;
+            }
+            for (index, (left, right)) in ANTONYMS.iter().enumerate() {
+                if left.contains(&word.as_str()) {
+                    claims.polarities.insert((index, false));
+                } else if right.contains(&word.as_str()) {
+                    claims.polarities.insert((index, true));
+                }
+            }
+            if is_version(&word) {
+                claims.versions.insert(word.trim_start_matches('v').to_string());
+            } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+                claims.numbers.insert(word);
+            }
+        }
+        claims
+    }
+
+    /// Why two texts might contradict: "negation", "antonym", "number" or
+    /// "version". Numbers and versions only count when both texts have some.
+    pub fn differences(&self, other: &Claims) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if self.negated != other.negated {
+            reasons.push("negation");
+        }
+        let sides = |claims: &Claims, index: usize| {
+            claims.polarities.iter().filter(|(i, _)| *i == index).map(|&(_, side)| side).collect::<Vec<bool>>()
+        };
+        let flipped = (0..ANTONYMS.len()).any(|index| {
+            let (mine, theirs) = (sides(self, index), sides(other, index));
+            mine.len() == 1 && theirs.len() == 1 && mine != theirs
+        });
+        if flipped {
+            reasons.push("antonym");
+        }
+        let disagree = |a: &BTreeSet<String>, b: &BTreeSet<String>| !a.is_empty() && !b.is_empty() && a != b;
+        if disagree(&self.numbers, &other.numbers) {
+            reasons.push("number");
+        }
+        if disagree(&self.versions, &other.versions) {
+            reasons.push("version");
+        }
+        reasons.into_iter().map(String::from).collect()
+    }
+}
+
+/// `v2`, `v1.4` or a dotted triple such as `3.11.2`
+fn is_version(word: &str) -> bool {
+    let digits = word.strip_prefix('v').filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+    let dotted = |s: &str| s.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    match digits {
+        Some(rest) => dotted(rest),
+        None => word.matches('.').count() >= 2 && dotted(word),
+    }
+}
+
+/// A stored memory that may contradict a new one
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct ContradictionCandidate {
+    #[pyo3(get)]
+    pub id: String,
+    #[pyo3(get)]
+    pub similarity: f32, // Cosine similarity to the new memory
+    #[pyo3(get)]
+    pub reasons: Vec<String>, // "negation", "antonym", "number", and/or "version"
+}
+
+#[pymethods]
+impl ContradictionCandidate {
+    fn __repr__(&self) -> String {
+        format!("ContradictionCandidate(id={}, similarity={:.4}, reasons={:?})", self.id, self.similarity, self.reasons)
+    }
+}
+
+/// Find stored memories that may contradict a new one.
+///
+/// Scans the store for memories whose similarity to the new embedding lies
+/// within `threshold_band` and whose text differs lexically in negation,
+/// opposed words (enabled/disabled), numbers, or version strings. Only these
+/// few candidates need adjudicating.
+///
+/// Args:
+///     new_memory_vec: Embedding of the new memory
+///     new_memory_text: Text of the new memory
+///     store: RetrievalPipeline holding the existing memories
+///     threshold_band: Inclusive (low, high) similarity range
+///     limit: Maximum number of candidates
+///
+/// Returns:
+///     List of ContradictionCandidate, most similar first
+#[pyfunction]
+#[pyo3(signature = (new_memory_vec, new_memory_text, store, threshold_band=(0.85, 1.0), limit=10))]
+pub fn find_contradiction_candidates(
+    py: Python<'_>,
+    new_memory_vec: Vec<f32>,
+    new_memory_text: &str,
+    store: PyRef<'_, RetrievalPipeline>,
+    threshold_band: (f32, f32),
+    limit: usize,
+) -> PyResult<Vec<ContradictionCandidate>> {
+    let (low, high) = threshold_band;
+    if !(-1.0..=1.0).contains(&low) || !(-1.0..=1.0).contains(&high) || low > high {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "threshold_band must be (low, high) with -1 <= low <= high <= 1",
+        ));
+    }
+    let claims = Claims::of(new_memory_text);
+    let store = &*store;
+    py.detach(|| store.contradiction_candidates(&new_memory_vec, &claims, threshold_band, limit))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasons(a: &str, b: &str) -> Vec<String> {
+        Claims::of(a).differences(&Claims::of(b))
+    }
+
+    #[test]
+    fn test_negation_and_antonyms() {
+        assert_eq!(reasons("The cache is enabled in prod.", "The cache isn't enabled in prod."), vec!["negation"]);
+        assert_eq!(reasons("Telemetry is enabled", "Telemetry is disabled"), vec!["antonym"]);
+        assert!(reasons("Telemetry is enabled", "Telemetry is enabled by default").is_empty());
+    }
+
+    #[test]
+    fn test_numbers_and_versions() {
+        assert_eq!(reasons("API listens on port 8080.", "API listens on port 9090."), vec!["number"]);
+        assert_eq!(reasons("Requires Python 3.11.2", "Requires Python 3.12.0"), vec!["version"]);
+        assert_eq!(reasons("Pinned to v2", "Pinned to v3"), vec!["version"]);
+        // One side without numbers says nothing about them
+        assert!(reasons("Timeout is 30s", "Timeout is configurable").is_empty());
+        assert!(is_version("v1.4") && is_version("1.2.3") && !is_version("0.5") && !is_version("version"));
+    }
+}
@@ -0,0 +1,156 @@
+use pyo3::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::retrieval::PipelineConfig;
+
+/// Settings of the core's subsystems that can change while the server runs
+///
+/// Loaded from TOML, where every key is optional:
+///
+/// ```toml
+/// threads = 8
+/// parser_cache_size = 12
+///
+/// [search]
+/// top_k = 20
+/// lexical_weight = 0.3
+///
+/// [search.profiles.recent]
+/// vector = 1.0
+/// recency = 0.5
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[pyclass(get_all)]
+pub struct CoreConfig {
+    /// Worker threads for batch parsing and repository scans; 0 for one per core
+    pub threads: usize,
+    /// Tree-sitter parsers each thread keeps, one per language; 0 for no limit
+    pub parser_cache_size: usize,
+    /// Candidate counts, blending and scoring weights (as named profiles)
+    /// for pipelines created without a config
+    pub search: PipelineConfig,
+}
+
+#[pymethods]
+impl CoreConfig {
+    fn __repr__(&self) -> String {
+        format!(
+            "CoreConfig(threads={}, parser_cache_size={}, top_k={}, profiles={})",
+            self.threads,
+            self.parser_cache_size,
+            self.search.top_k,
+            self.search.profiles.len()
+        )
+    }
+}
+
+impl CoreConfig {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e: toml::de::Error| format!("TOML parse error: {}", e))?;
+        config.search.validate()?;
+        Ok(config)
+    }
+}
+
+/// The active config and the pool its `threads` built, if it set any
+struct Active {
+    config: Arc<CoreConfig>,
+    pool: Option<Arc<ThreadPool>>,
+}
+
+fn active() -> &'static RwLock<Active> {
+    static ACTIVE: OnceLock<RwLock<Active>> = OnceLock::new();
+    ACTIVE.get_or_init(|| RwLock::new(Active { config: Arc::default(), pool: None }))
+}
+
+/// The config in force; read it at the point of use so reloads take effect
+pub fn current() -> Arc<CoreConfig> {
+    active().read().map(|active| active.config.clone()).unwrap_or_default()
+}
+
+/// Make `config` the one in force. A new thread count builds a new pool;
+/// work already running finishes on the old one.
+pub fn apply(config: CoreConfig) -> Result<(), String> {
+    config.search.validate()?;
+    let mut active = active().write().map_err(|_| "Config lock poisoned".to_string())?;
+    if config.threads != active.config.threads {
+        active.pool = match config.threads {
+            0 => None,
+            threads => Some(Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|i| format!("memory-core-{}", i))
+                    .build()
+                    .map_err(|e| format!("Failed to start {} threads: {}", threads, e))?,
+            )),
+        };
+    }
+    active.config = Arc::new(config);
+    Ok(())
+}
+
+/// Run parallel work on the configured pool (rayon's global pool while
+/// `threads` is 0)
+pub fn install<R: Send>(work: impl FnOnce() -> R + Send) -> R {
+    let pool = active().read().ok().and_then(|active| active.pool.clone());
+    match pool {
+        Some(pool) => pool.install(work),
+        None => work(),
+    }
+}
+
+/// Load a TOML config and apply it without restarting
+///
+/// Keys missing from the file take their defaults, so removing a key
+/// reverts it. Thread count and parser cache size apply to the next batch
+/// parse or scan; search defaults to pipelines created afterwards without a
+/// config (assign `core_config().search` to an existing pipeline's
+/// `config` to update it). Nothing changes if the file is invalid.
+///
+/// Args:
+///     path: TOML file to load
+///
+/// Returns:
+///     The CoreConfig now in force
+#[pyfunction]
+pub fn reload_config(path: &str) -> PyResult<CoreConfig> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Failed to read {}: {}", path, e)))?;
+    let config = CoreConfig::from_toml(&text).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    apply(config.clone()).map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(config)
+}
+
+/// The CoreConfig in force
+#[pyfunction]
+pub fn core_config() -> CoreConfig {
+    (*current()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_applies_valid_configs_only() {
+        let config = CoreConfig::from_toml(
+            "threads = 3\n\n[search]\ntop_k = 25\n\n[search.profiles.recent]\nvector = 1.0\nrecency = 0.5\n",
+        )
+        .unwrap();
+        assert_eq!((config.threads, config.parser_cache_size, config.search.top_k), (3, 0, 25));
+        assert_eq!(config.search.ann_candidates, PipelineConfig::default().ann_candidates);
+        assert_eq!(config.search.profiles["recent"].recency, 0.5);
+
+        apply(config.clone()).unwrap();
+        assert_eq!(*current(), config);
+        assert_eq!(install(rayon::current_num_threads), 3);
+
+        assert!(CoreConfig::from_toml("[search]\nlexical_weight = 2.0\n").is_err());
+        assert!(CoreConfig::from_toml("thread = 4\n").is_err());
+        apply(CoreConfig::default()).unwrap();
+        assert_eq!(install(rayon::current_num_threads), rayon::current_num_threads());
+    }
+}
@@ -0,0 +1,286 @@
+use pyo3::prelude::*;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parsing::{parse_any_file, ParseOptions, SemanticUnit};
+use crate::trace_parsing::match_context_path;
+
+/// Line hit counts for one file, keyed by 1-based line number. Lines absent
+/// from the map are not coverable (blank lines, comments, declarations).
+pub type LineHits = BTreeMap<usize, u64>;
+
+/// Coverage of one semantic unit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct UnitCoverage {
+    #[pyo3(get)]
+    pub file_path: String,
+    #[pyo3(get)]
+    pub unit: SemanticUnit,
+    #[pyo3(get)]
+    pub covered_lines: usize,
+    #[pyo3(get)]
+    pub coverable_lines: usize,
+    #[pyo3(get)]
+    pub coverage_percent: Option<f64>, // None when the unit has no coverable lines
+    #[pyo3(get)]
+    pub uncovered: Vec<usize>, // Coverable lines that were never hit
+}
+
+#[pymethods]
+impl UnitCoverage {
+    fn __repr__(&self) -> String {
+        format!(
+            "UnitCoverage(unit={}, covered={}/{})",
+            self.unit.name, self.covered_lines, self.coverable_lines
+        )
+    }
+}
+
+/// Coverage report formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageFormat {
+    Lcov,
+    /// Cobertura XML, as written by `coverage xml`
+    CoberturaXml,
+}
+
+impl CoverageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lcov" | "info" => Some(CoverageFormat::Lcov),
+            "xml" | "cobertura" | "coverage.py" => Some(CoverageFormat::CoberturaXml),
+            _ => None,
+        }
+    }
+
+    pub fn detect(report: &str) -> Self {
+        if report.trim_start().starts_with('<') {
+            CoverageFormat::CoberturaXml
+        } else {
+            CoverageFormat::Lcov
+        }
+    }
+}
+
+/// Read `SF:` / `DA:line,hits` records from an lcov tracefile
+pub fn parse_lcov(report: &str) -> Result<HashMap<String, LineHits>, String> {
+    let mut files: HashMap<String, LineHits> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for (index, line) in report.lines().enumerate() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = Some(path.to_string());
+            files.entry(path.to_string()).or_default();
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let path = current.as_ref().ok_or(format!("lcov DA record outside a file on line {}", index + 1))?;
+            let mut fields = data.split(',');
+            let (Some(number), Some(hits)) = (fields.next(), fields.next()) else {
+                return Err(format!("Malformed lcov DA record on line {}: {}", index + 1, line));
+            };
+            let number: usize = number.parse().map_err(|_| format!("Malformed lcov line number on line {}", index + 1))?;
+            // Some generators emit negative or fractional counts; anything positive counts as a hit
+            let hits = hits.parse::<f64>().map_err(|_| format!("Malformed lcov hit count on line {}", index + 1))?;
+            let entry = files.entry(path.clone()).or_default().entry(number).or_insert(0);
+            *entry += hits.max(0.0) as u64;
+        } else if line == "end_of_record" {
+            current = None;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Read `<class filename=...>` / `<line number hits>` elements from a
+/// Cobertura XML report. Filenames are relative to the report's `<source>`
+/// roots and are returned as written.
+pub fn parse_cobertura(report: &str) -> Result<HashMap<String, LineHits>, String> {
+    let mut reader = Reader::from_str(report);
+    let mut files: HashMap<String, LineHits> = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut in_methods = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid coverage XML at byte {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => match e.name().as_ref() {
+                b"class" => {
+                    let filename = e
+                        .try_get_attribute("filename")
+                        .map_err(|e| e.to_string())?
+                        .map(|a| a.unescape_value().map(|v| v.into_owned()))
+                        .transpose()
+                        .map_err(|e| e.to_string())?;
+                    if let Some(filename) = &filename {
+                        files.entry(filename.clone()).or_default();
+                    }
+                    current = filename;
+                }
+                b"methods" => in_methods = matches!(event, Event::Start(_)),
+                // Method elements repeat their lines; count each line once, from the class
+                b"line" if !in_methods => {
+                    let Some(path) = current.as_ref() else { continue };
+                    let value = |name: &str| -> Result<Option<String>, String> {
+                        e.try_get_attribute(name)
+                            .map_err(|e| e.to_string())?
+                            .map(|a| a.unescape_value().map(|v| v.into_owned()).map_err(|e| e.to_string()))
+                            .transpose()
+                    };
+                    let (Some(number), Some(hits)) = (value("number")?, value("hits")?) else { continue };
+                    let number: usize = number.parse().map_err(|_| format!("Malformed line number: {}", number))?;
+                    let hits: u64 = hits.parse().map_err(|_| format!("Malformed hit count: {}", hits))?;
+                    *files.entry(path.clone()).or_default().entry(number).or_insert(0) += hits;
+                }
+                _ => {}
+            },
+            Event::End(ref e) => match e.name().as_ref() {
+                b"class" => current = None,
+                b"methods" => in_methods = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(files)
+}
+
+/// Coverage of `unit` from the hit counts of its file
+fn unit_coverage(file_path: &str, unit: SemanticUnit, hits: &LineHits) -> UnitCoverage {
+    let mut covered_lines = 0;
+    let mut uncovered = Vec::new();
+    for (&line, &count) in hits.range(unit.start_line..=unit.end_line) {
+        if count > 0 {
+            covered_lines += 1;
+        } else {
+            uncovered.push(line);
+        }
+    }
+    let coverable_lines = covered_lines + uncovered.len();
+    UnitCoverage {
+        file_path: file_path.to_string(),
+        unit,
+        covered_lines,
+        coverable_lines,
+        coverage_percent: (coverable_lines > 0).then(|| covered_lines as f64 * 100.0 / coverable_lines as f64),
+        uncovered,
+    }
+}
+
+/// Map report line coverage onto the units of the context files.
+///
+/// Report paths are matched to `parse_context` paths by exact match or path
+/// suffix (reports often hold absolute or source-root-relative paths). Context
+/// files the report doesn't mention are skipped. Results follow report file
+/// order (sorted by path), then unit order within each file.
+pub fn map_coverage(
+    report: &str,
+    format: CoverageFormat,
+    parse_context: &HashMap<String, String>,
+) -> Result<Vec<UnitCoverage>, String> {
+    let files = match format {
+        CoverageFormat::Lcov => parse_lcov(report)?,
+        CoverageFormat::CoberturaXml => parse_cobertura(report)?,
+    };
+
+    let mut report_paths: Vec<&String> = files.keys().collect();
+    report_paths.sort();
+
+    let options = ParseOptions::default();
+    let mut coverage = Vec::new();
+    for report_path in report_paths {
+        let Some(key) = match_context_path(report_path, parse_context) else { continue };
+        let Ok(parsed) = parse_any_file(key, &parse_context[key], &options) else { continue };
+        let hits = &files[report_path];
+        coverage.extend(parsed.units.into_iter().map(|unit| unit_coverage(key, unit, hits)));
+    }
+
+    Ok(coverage)
+}
+
+/// Map an lcov or coverage.py XML report onto semantic units
+///
+/// `parse_context` maps file paths to contents; every unit of a file the
+/// report covers gets covered/coverable line counts, the never-hit lines, and
+/// `coverage_percent`. `format` ("lcov" or "xml") is detected when omitted.
+/// Raises ValueError for an unknown format or a malformed report.
+#[pyfunction]
+#[pyo3(signature = (report, parse_context, format=None))]
+pub fn map_coverage_to_units(
+    report: String,
+    parse_context: HashMap<String, String>,
+    format: Option<String>,
+) -> PyResult<Vec<UnitCoverage>> {
+    let format = match format {
+        Some(name) => CoverageFormat::from_name(&name).ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!("Unknown coverage format: {}", name))
+        })?,
+        None => CoverageFormat::detect(&report),
+    };
+    map_coverage(&report, format, &parse_context).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "def tested(x):\n    if x:\n        return 1\n    return 2\n\n\ndef untested():\n    return 3\n";
+
+    fn context() -> HashMap<String, String> {
+        HashMap::from([("pkg/calc.py".to_string(), SOURCE.to_string())])
+    }
+
+    #[test]
+    fn test_lcov_maps_onto_units() {
+        let report = "TN:\nSF:/ci/repo/pkg/calc.py\nDA:1,1\nDA:2,4\nDA:3,4\nDA:4,0\nDA:7,1\nDA:8,0\nend_of_record\n";
+        let coverage = map_coverage(report, CoverageFormat::Lcov, &context()).unwrap();
+        assert_eq!(coverage.len(), 2);
+
+        assert_eq!(coverage[0].unit.name, "tested");
+        assert_eq!((coverage[0].covered_lines, coverage[0].coverable_lines), (3, 4));
+        assert_eq!(coverage[0].coverage_percent, Some(75.0));
+        assert_eq!(coverage[0].uncovered, vec![4]);
+
+        assert_eq!(coverage[1].unit.name, "untested");
+        assert_eq!(coverage[1].coverage_percent, Some(50.0));
+        assert_eq!(coverage[1].file_path, "pkg/calc.py");
+    }
+
+    #[test]
+    fn test_cobertura_xml_maps_onto_units() {
+        let report = r#"<?xml version="1.0" ?>
+<coverage version="7.4.0"><sources><source>/ci/repo</source></sources>
+<packages><package name="pkg"><classes>
+  <class name="calc.py" filename="pkg/calc.py"><methods><method name="tested"><lines><line number="1" hits="1"/></lines></method></methods><lines>
+    <line number="1" hits="1"/><line number="2" hits="1" branch="true" condition-coverage="50% (1/2)"/>
+    <line number="3" hits="0"/><line number="4" hits="1"/><line number="7" hits="0"/><line number="8" hits="0"/>
+  </lines></class>
+</classes></package></packages></coverage>"#;
+        assert_eq!(CoverageFormat::detect(report), CoverageFormat::CoberturaXml);
+        let coverage = map_coverage(report, CoverageFormat::CoberturaXml, &context()).unwrap();
+        assert_eq!(coverage[0].coverage_percent, Some(75.0));
+        assert_eq!(coverage[1].coverage_percent, Some(0.0));
+        assert_eq!(coverage[1].uncovered, vec![7, 8]);
+    }
+
+    #[test]
+    fn test_units_without_coverable_lines_have_no_percent() {
+        let hits = LineHits::new();
+        let parsed = parse_any_file("pkg/calc.py", SOURCE, &ParseOptions::default()).unwrap();
+        let coverage = unit_coverage("pkg/calc.py", parsed.units[0].clone(), &hits);
+        assert_eq!(coverage.coverage_percent, None);
+    }
+
+    #[test]
+    fn test_malformed_lcov_is_an_error() {
+        assert!(parse_lcov("DA:1,1\n").is_err());
+        assert!(parse_lcov("SF:a.py\nDA:x,1\n").is_err());
+        assert_eq!(CoverageFormat::from_name("LCOV"), Some(CoverageFormat::Lcov));
+    }
+}
@@ -0,0 +1,235 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::parsing::{assign_unit_ids, SemanticUnit, SupportedLanguage};
+use crate::scopes;
+
+/// One entry of a ctags file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub path: String,
+    pub kind: String,
+    /// 1-based line, from the `line` field or a numeric address
+    pub line: Option<usize>,
+    /// Last line, from the `end` field
+    pub end: Option<usize>,
+    /// Search pattern address (`/^def f():$/`), without delimiters
+    pub pattern: Option<String>,
+    pub scope: Option<String>,
+    pub signature: Option<String>,
+    pub language: Option<String>,
+}
+
+/// Tags of a universal-ctags file in the classic tab-separated format or
+/// the JSON lines format (`--output-format=json`). Pseudo-tags and
+/// malformed lines are skipped.
+pub fn parse_tags(text: &str) -> Vec<Tag> {
+    text.lines()
+        .filter(|line| !line.starts_with("!_"))
+        .filter_map(|line| if line.starts_with('{') { json_tag(line) } else { classic_tag(line) })
+        .collect()
+}
+
+/// `name<TAB>path<TAB>address;"<TAB>kind<TAB>field:value...`; the address
+/// may hold tabs, so the extension fields start after the last `;"<TAB>`
+fn classic_tag(line: &str) -> Option<Tag> {
+    let mut columns = line.splitn(3, '\t');
+    let (name, path, rest) = (columns.next()?, columns.next()?, columns.next()?);
+    let (address, fields) = match rest.rfind(";\"\t") {
+        Some(end) => (&rest[..end], &rest[end + 3..]),
+        None => (rest.trim_end_matches(";\""), ""),
+    };
+    let mut tag = Tag { name: name.to_string(), path: path.to_string(), ..Tag::default() };
+    match address.parse::<usize>() {
+        Ok(line) => tag.line = Some(line),
+        Err(_) => tag.pattern = pattern_text(address),
+    }
+    for field in fields.split('\t').filter(|f| !f.is_empty()) {
+        match field.split_once(':') {
+            None => tag.kind = field.to_string(),
+            Some(("kind", kind)) => tag.kind = kind.to_string(),
+            Some(("line", line)) => tag.line = line.parse().ok().or(tag.line),
+            Some(("end", end)) => tag.end = end.parse().ok(),
+            Some(("signature", signature)) => tag.signature = Some(signature.to_string()),
+            Some(("language", language)) => tag.language = Some(language.to_string()),
+            Some(("scope", scope)) => tag.scope = scope.split_once(':').map(|(_, name)| name.to_string()),
+            // Scope written as `<scope kind>:<name>`, e.g. `class:Cart`
+            Some((key, value)) if SCOPE_KINDS.contains(&key) => tag.scope = Some(value.to_string()),
+            Some(_) => {}
+        }
+    }
+    Some(tag)
+}
+
+/// Field names universal-ctags uses for the enclosing scope
+const SCOPE_KINDS: &[&str] = &["class", "struct", "interface", "namespace", "module", "enum", "function", "method"];
+
+fn json_tag(line: &str) -> Option<Tag> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    if value.get("_type").and_then(Value::as_str).is_some_and(|t| t != "tag") {
+        return None;
+    }
+    let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let number = |key: &str| value.get(key).and_then(Value::as_u64).map(|n| n as usize);
+    Some(Tag {
+        name: text("name")?,
+        path: text("path")?,
+        kind: text("kind").unwrap_or_default(),
+        line: number("line"),
+        end: number("end"),
+        pattern: text("pattern").and_then(|p| pattern_text(&p)),
+        scope: text("scope"),
+        signature: text("signature"),
+        language: text("language"),
+    })
+}
+
+/// Line text a `/^...$/` or `?^...$?` search pattern matches, unescaped;
+/// None for a pattern that isn't anchored at the line start
+fn pattern_text(address: &str) -> Option<String> {
+    let delimiter = address.chars().next().filter(|&c| c == '/' || c == '?')?;
+    let inner = address[1..].strip_suffix(delimiter)?.strip_prefix('^')?;
+    let inner = inner.strip_suffix('$').unwrap_or(inner);
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            c => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+/// `unit_type` for a ctags kind, by full name or by the letters that mean
+/// the same in every language; None for kinds the index doesn't keep
+/// (fields, locals, parameters...)
+fn unit_type(kind: &str, scoped: bool) -> Option<&'static str> {
+    match kind {
+        "function" | "method" | "func" | "procedure" | "subroutine" | "singletonMethod" | "constructor" | "f" => {
+            Some("function")
+        }
+        "class" | "struct" | "interface" | "trait" | "union" | "c" | "s" | "i" => Some("class"),
+        "enum" | "g" => Some("enum"),
+        "typedef" | "alias" | "type" | "t" => Some("type_alias"),
+        "constant" | "macro" | "define" | "d" => Some("constant"),
+        "variable" | "v" if !scoped => Some("global"),
+        _ => None,
+    }
+}
+
+/// Language name as the parser reports it, from the tag's language or the
+/// file extension
+fn language_of(tag: &Tag) -> String {
+    let extension = Path::new(&tag.path).extension().and_then(|e| e.to_str());
+    tag.language
+        .as_deref()
+        .and_then(SupportedLanguage::from_name)
+        .or_else(|| extension.and_then(SupportedLanguage::from_name))
+        .map(|language| format!("{:?}", language))
+        .or_else(|| tag.language.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Semantic units of one file's tags, spanning whole lines of `source`.
+/// Tags without a line are located by their pattern; tags that can't be
+/// located, or of kinds the index doesn't keep, are dropped.
+pub fn tag_units(tags: &[&Tag], source: &str, line_starts: &[usize]) -> Vec<SemanticUnit> {
+    let line_end = |line: usize| line_starts.get(line).map_or(source.len(), |next| next - 1);
+    let mut pattern_lines: HashMap<&str, usize> = HashMap::new();
+    for (index, text) in source.lines().enumerate() {
+        pattern_lines.entry(text).or_insert(index + 1);
+    }
+    let mut units = Vec::new();
+    for tag in tags {
+        let Some(unit_type) = unit_type(&tag.kind, tag.scope.is_some()) else { continue };
+        let line = tag.line.or_else(|| tag.pattern.as_deref().and_then(|p| pattern_lines.get(p).copied()));
+        let Some(line) = line.filter(|l| (1..=line_starts.len()).contains(l)) else { continue };
+        let end_line = tag.end.unwrap_or(line).clamp(line, line_starts.len());
+        let first_line = &source[line_starts[line - 1]..line_end(line)];
+        let start_byte = line_starts[line - 1] + first_line.len() - first_line.trim_start().len();
+        let end_byte = line_end(end_line);
+        let content = &source[start_byte..end_byte.max(start_byte)];
+        let mut metadata = HashMap::from([("source".to_string(), "ctags".to_string())]);
+        if let Some(scope) = &tag.scope {
+            metadata.insert("scope".to_string(), scope.clone());
+        }
+        let language = language_of(tag);
+        let qualified_name = match &tag.scope {
+            Some(scope) => format!("{}{}{}", scope, scopes::separator(&language), tag.name),
+            None => tag.name.clone(),
+        };
+        units.push(SemanticUnit {
+            unit_type: unit_type.into(),
+            name: tag.name.clone(),
+            // Scopes may be qualified themselves (`Outer.Inner`)
+            parent_name: tag.scope.as_deref().and_then(|scope| scope.rsplit(['.', ':']).next()).map(str::to_string),
+            qualified_name,
+            start_line: line,
+            end_line,
+            start_byte,
+            end_byte: end_byte.max(start_byte),
+            signature: content.lines().next().unwrap_or("").trim().to_string(),
+            parameters: tag.signature.clone(),
+            docstring: None,
+            decorators: Vec::new(),
+            content: content.to_string(),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: language.into(),
+            metadata,
+        });
+    }
+    if let Some(tag) = tags.first() {
+        assign_unit_ids(tag.path.strip_prefix("./").unwrap_or(&tag.path), &mut units, source);
+    }
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::line_starts;
+
+    const TAGS: &str = "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+        Cart\tcart.py\t/^class Cart:$/;\"\tkind:class\tline:1\tlanguage:Python\tend:6\n\
+        total\tcart.py\t/^    def total(self):$/;\"\tkind:member\tclass:Cart\tsignature:(self)\n\
+        total\tcart.py\t/^    def total(self):$/;\"\tkind:function\tclass:Cart\tsignature:(self)\tend:3\n\
+        LIMIT\tcart.py\t9;\"\tv\n\
+        broken line\n\
+        {\"_type\": \"tag\", \"name\": \"main\", \"path\": \"app.go\", \"line\": 3, \"kind\": \"func\"}\n\
+        {\"_type\": \"ptag\", \"name\": \"JSON_OUTPUT_VERSION\", \"path\": \"0.0\"}\n";
+
+    const SOURCE: &str = "class Cart:\n    def total(self):\n        return 1\n\n    \
+        def empty(self):\n        return 0\n\n\nLIMIT = 1\n";
+
+    #[test]
+    fn test_parse_both_formats() {
+        let tags = parse_tags(TAGS);
+        assert_eq!(tags.len(), 5);
+        assert_eq!((tags[0].line, tags[0].end, tags[0].kind.as_str()), (Some(1), Some(6), "class"));
+        assert_eq!(tags[1].pattern.as_deref(), Some("    def total(self):"));
+        assert_eq!(tags[1].scope.as_deref(), Some("Cart"));
+        assert_eq!(tags[3].line, Some(9));
+        assert_eq!((tags[4].name.as_str(), tags[4].path.as_str(), tags[4].line), ("main", "app.go", Some(3)));
+        assert_eq!(pattern_text(r"/^a \/ b\\$/").as_deref(), Some(r"a / b\"));
+    }
+
+    #[test]
+    fn test_units_span_tagged_lines() {
+        let tags = parse_tags(TAGS);
+        let file_tags: Vec<&Tag> = tags.iter().filter(|t| t.path == "cart.py").collect();
+        let units = tag_units(&file_tags, SOURCE, &line_starts(SOURCE));
+        let summary: Vec<(&str, &str, usize, usize)> =
+            units.iter().map(|u| (u.unit_type.as_str(), u.name.as_str(), u.start_line, u.end_line)).collect();
+        assert_eq!(summary, vec![("class", "Cart", 1, 6), ("function", "total", 2, 3), ("global", "LIMIT", 9, 9)]);
+        assert_eq!(units[1].content, "def total(self):\n        return 1");
+        assert_eq!(units[1].start_byte, 16);
+        assert_eq!(units[1].language, "Python");
+        assert_eq!(units[1].metadata["scope"], "Cart");
+        assert_eq!((units[1].parent_name.as_deref(), units[1].qualified_name.as_str()), (Some("Cart"), "Cart.total"));
+    }
+}
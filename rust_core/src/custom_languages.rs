@@ -0,0 +1,164 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use tree_sitter::{Language, Parser, Query};
+
+/// A tree-sitter grammar registered at runtime, with its unit queries
+#[derive(Debug)]
+pub struct CustomLanguage {
+    /// Unique per registration, so parsers cached for a replaced grammar
+    /// aren't reused
+    pub id: usize,
+    /// As registered; reported in `ParseResult.language`
+    pub name: String,
+    /// Without the leading dot
+    pub extensions: Vec<String>,
+    pub language: Language,
+    pub function_query: Query,
+    pub class_query: Query,
+}
+
+/// Registered languages by lowercased name
+fn registry() -> &'static RwLock<HashMap<String, Arc<CustomLanguage>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<CustomLanguage>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// The registered language named `name` (case-insensitive)
+pub(crate) fn by_name(name: &str) -> Option<Arc<CustomLanguage>> {
+    registry().read().ok()?.get(&name.trim().to_ascii_lowercase()).cloned()
+}
+
+/// The registered language claiming `extension`, the latest registration
+/// when several do
+pub(crate) fn by_extension(extension: &str) -> Option<Arc<CustomLanguage>> {
+    registry()
+        .read()
+        .ok()?
+        .values()
+        .filter(|custom| custom.extensions.iter().any(|e| e == extension))
+        .max_by_key(|custom| custom.id)
+        .cloned()
+}
+
+/// Check a grammar and its queries, then register it under `name`,
+/// replacing any earlier registration of the name
+pub fn add_language(
+    name: &str,
+    extensions: &[String],
+    language: Language,
+    function_query: &str,
+    class_query: &str,
+) -> Result<(), String> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    let key = name.trim().to_ascii_lowercase();
+    if key.is_empty() {
+        return Err("Language name must not be empty".to_string());
+    }
+    Parser::new()
+        .set_language(&language)
+        .map_err(|e| format!("Grammar for {} is incompatible with this build: {}", name, e))?;
+    let function_query =
+        Query::new(&language, function_query).map_err(|e| format!("Invalid function query for {}: {}", name, e))?;
+    let class_query =
+        Query::new(&language, class_query).map_err(|e| format!("Invalid class query for {}: {}", name, e))?;
+    let custom = CustomLanguage {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        name: name.trim().to_string(),
+        extensions: extensions
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_string())
+            .filter(|e| !e.is_empty())
+            .collect(),
+        language,
+        function_query,
+        class_query,
+    };
+    registry().write().map_err(|e| e.to_string())?.insert(key, Arc::new(custom));
+    Ok(())
+}
+
+/// Load the grammar a shared library exports as `symbol`.
+///
+/// The library is never unloaded: parsers, trees and queries built from
+/// the grammar point into it for the rest of the process.
+pub fn load_grammar(path: &str, symbol: &str) -> Result<Language, String> {
+    // SAFETY: loading runs the library's initializers and `symbol` is
+    // trusted to be a tree-sitter language function; both are the caller's
+    // responsibility, as with any native extension
+    unsafe {
+        let library = libloading::Library::new(path).map_err(|e| format!("Failed to load grammar {}: {}", path, e))?;
+        let constructor: libloading::Symbol<unsafe extern "C" fn() -> *const tree_sitter::ffi::TSLanguage> =
+            library.get(symbol.as_bytes()).map_err(|e| format!("{} does not export {}: {}", path, symbol, e))?;
+        let raw = constructor();
+        if raw.is_null() {
+            return Err(format!("{} in {} returned no language", symbol, path));
+        }
+        let language = Language::from_raw(raw);
+        std::mem::forget(library);
+        Ok(language)
+    }
+}
+
+/// Register a tree-sitter grammar compiled as a shared library.
+///
+/// Files with one of `extensions` (with or without the dot) are then parsed
+/// with the grammar, ahead of any built-in language for the extension, and
+/// `parse_source` accepts `name`. Queries capture the whole construct as
+/// `@function` / `@class` and may capture `@name` and `@params`; optional
+/// unit kinds aren't extracted for registered languages. Registering a
+/// name again replaces it. Raises ValueError if the library can't be
+/// loaded, doesn't export the grammar, or a query doesn't compile.
+///
+/// Args:
+///     name: Language name, e.g. "zig"
+///     extensions: File extensions to claim, e.g. ["zig"]
+///     path_to_grammar_so: Path of the compiled grammar (.so, .dylib, .dll)
+///     function_query: Query matching functions
+///     class_query: Query matching classes or similar type declarations
+///     symbol: Exported language function (default `tree_sitter_<name>`)
+#[pyfunction]
+#[pyo3(signature = (name, extensions, path_to_grammar_so, function_query, class_query, symbol=None))]
+pub fn register_language(
+    name: &str,
+    extensions: Vec<String>,
+    path_to_grammar_so: &str,
+    function_query: &str,
+    class_query: &str,
+    symbol: Option<&str>,
+) -> PyResult<()> {
+    let symbol = match symbol {
+        Some(symbol) => symbol.to_string(),
+        None => format!("tree_sitter_{}", name.trim().to_ascii_lowercase().replace('-', "_")),
+    };
+    load_grammar(path_to_grammar_so, &symbol)
+        .and_then(|language| add_language(name, &extensions, language, function_query, class_query))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    #[test]
+    fn test_registered_language_parses_its_extensions() {
+        let functions = "(function_definition name: (identifier) @name) @function";
+        let classes = "(class_definition name: (identifier) @name) @class";
+        let extensions = vec![".pyish".to_string()];
+        add_language("PyIsh", &extensions, tree_sitter_python::LANGUAGE.into(), functions, classes).unwrap();
+
+        let source = "class A:\n    def f(self):\n        pass\n";
+        let result = parse_any_file("lib.pyish", source, &ParseOptions::default()).unwrap();
+        assert_eq!(result.language, "PyIsh");
+        let names: Vec<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["f", "A"]);
+        assert_eq!(by_name("pyish").unwrap().extensions, vec!["pyish"]);
+
+        let python = tree_sitter_python::LANGUAGE.into();
+        assert!(add_language("broken", &extensions, python, "(no_such_node) @function", classes).is_err());
+        assert!(load_grammar("/nonexistent/grammar.so", "tree_sitter_x").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Words per shingle
+const SHINGLE_WORDS: usize = 3;
+/// MinHash signature length
+const PERMUTATIONS: usize = 64;
+
+/// FNV-1a, stable across platforms and releases so stored fingerprints stay
+/// comparable
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Finalizer of SplitMix64, spreading a hash over all 64 bits
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes of the overlapping word triples of `text`, lowercased; a text
+/// shorter than a shingle is one shingle
+fn shingles(text: &str) -> HashSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return HashSet::new();
+    }
+    words
+        .windows(SHINGLE_WORDS.min(words.len()))
+        .map(|window| fnv1a(window.join(" ").as_bytes()))
+        .collect()
+}
+
+/// 64-bit SimHash of the shingles: each bit is the majority vote of the
+/// corresponding shingle hash bits, so similar texts differ in few bits
+pub fn simhash_of(text: &str) -> u64 {
+    let mut votes = [0i64; 64];
+    for shingle in shingles(text) {
+        let hash = mix(shingle);
+        for (bit, vote) in votes.iter_mut().enumerate() {
+            *vote += if (hash >> bit) & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    votes.iter().enumerate().filter(|(_, &v)| v > 0).fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+fn minhash(shingles: &HashSet<u64>) -> [u64; PERMUTATIONS] {
+    let mut signature = [u64::MAX; PERMUTATIONS];
+    for &shingle in shingles {
+        for (i, slot) in signature.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(shingle ^ mix(i as u64 + 1)));
+        }
+    }
+    signature
+}
+
+/// Rows per LSH band: the most rows (fewest false positives) whose
+/// detection threshold `(1 / bands)^(1 / rows)` stays at or below `threshold`
+fn rows_per_band(threshold: f64) -> usize {
+    [64, 32, 16, 8, 4, 2, 1]
+        .into_iter()
+        .find(|&rows| (rows as f64 / PERMUTATIONS as f64).powf(1.0 / rows as f64) <= threshold)
+        .unwrap_or(1)
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let shared = a.intersection(b).count();
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        1.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Pairs `(i, j, similarity)` with `i < j` whose shingle Jaccard similarity
+/// is at least `threshold`, in index order. MinHash banding proposes the
+/// pairs; each is verified exactly. Texts without words are skipped.
+pub fn near_duplicates(texts: &[String], threshold: f64) -> Result<Vec<(usize, usize, f64)>, String> {
+    if threshold.is_nan() || threshold <= 0.0 || threshold > 1.0 {
+        return Err("threshold must be in (0, 1]".to_string());
+    }
+    let sets: Vec<HashSet<u64>> = texts.par_iter().map(|t| shingles(t)).collect();
+    let signatures: Vec<[u64; PERMUTATIONS]> = sets.par_iter().map(minhash).collect();
+
+    let rows = rows_per_band(threshold);
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for band in 0..PERMUTATIONS / rows {
+        let mut buckets: HashMap<&[u64], Vec<usize>> = HashMap::new();
+        for (i, signature) in signatures.iter().enumerate().filter(|(i, _)| !sets[*i].is_empty()) {
+            buckets.entry(&signature[band * rows..(band + 1) * rows]).or_default().push(i);
+        }
+        for members in buckets.values().filter(|m| m.len() > 1) {
+            for (a, &i) in members.iter().enumerate() {
+                candidates.extend(members[a + 1..].iter().map(|&j| (i, j)));
+            }
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize, f64)> = candidates
+        .into_par_iter()
+        .map(|(i, j)| (i, j, jaccard(&sets[i], &sets[j])))
+        .filter(|&(_, _, similarity)| similarity >= threshold)
+        .collect();
+    pairs.sort_by_key(|p| (p.0, p.1));
+    Ok(pairs)
+}
+
+/// 64-bit SimHash fingerprint of a text.
+///
+/// Computed over lowercased word triples; near-identical texts have
+/// fingerprints a small Hamming distance apart (`bin(a ^ b).count("1")`).
+/// The hash is stable across runs and platforms, so it can be stored.
+///
+/// Args:
+///     text: Text to fingerprint
+///
+/// Returns:
+///     Unsigned 64-bit fingerprint (0 for text without words)
+#[pyfunction]
+pub fn simhash(text: &str) -> u64 {
+    simhash_of(text)
+}
+
+/// Find pairs of near-duplicate texts.
+///
+/// Similarity is the Jaccard index of the texts' word-triple shingles.
+/// Candidate pairs come from MinHash locality-sensitive hashing and are
+/// verified exactly, so no reported pair is below the threshold.
+///
+/// Args:
+///     texts: Texts to compare
+///     threshold: Minimum similarity in (0, 1]
+///
+/// Returns:
+///     List of (i, j, similarity) with i < j, ordered by i then j
+#[pyfunction]
+#[pyo3(signature = (texts, threshold=0.8))]
+pub fn find_near_duplicates(py: Python<'_>, texts: Vec<String>, threshold: f64) -> PyResult<Vec<(usize, usize, f64)>> {
+    py.detach(|| near_duplicates(&texts, threshold)).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "The ingestion worker retries failed uploads three times with exponential backoff before giving up";
+
+    #[test]
+    fn test_simhash_is_stable_and_locality_sensitive() {
+        let edited = NOTE.replace("three", "five");
+        let unrelated = simhash_of("Weekly planning meeting moved to Thursday afternoon in room B");
+        assert_eq!(simhash_of(NOTE), simhash_of(&NOTE.to_uppercase()));
+        assert!((simhash_of(NOTE) ^ simhash_of(&edited)).count_ones() < (simhash_of(NOTE) ^ unrelated).count_ones());
+        assert_eq!(simhash_of("  ...  "), 0);
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let texts = vec![
+            NOTE.to_string(),
+            "Weekly planning meeting moved to Thursday afternoon in room B".to_string(),
+            format!("{} entirely", NOTE),
+            String::new(),
+            String::new(),
+            NOTE.to_string(),
+        ];
+        let pairs = near_duplicates(&texts, 0.8).unwrap();
+        let indices: Vec<(usize, usize)> = pairs.iter().map(|&(i, j, _)| (i, j)).collect();
+        assert_eq!(indices, vec![(0, 2), (0, 5), (2, 5)]);
+        assert_eq!(pairs[1].2, 1.0);
+        // 12 shingles shared of 13: the note has 14 words, the edit one more
+        assert!((pairs[0].2 - 12.0 / 13.0).abs() < 1e-9);
+        assert!(near_duplicates(&texts, 0.0).is_err());
+        assert!(near_duplicates(&[], 0.5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_band_rows_track_threshold() {
+        assert_eq!(rows_per_band(1.0), 64);
+        assert!(rows_per_band(0.5) < rows_per_band(0.9));
+        assert_eq!(rows_per_band(0.01), 1);
+    }
+}
@@ -0,0 +1,351 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::parsing::{parse_any_file, ParseOptions, SemanticUnit};
+
+/// One hunk of a unified diff, with the semantic units its changes touch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct DiffHunk {
+    #[pyo3(get)]
+    pub old_path: Option<String>, // None for added files
+    #[pyo3(get)]
+    pub new_path: Option<String>, // None for deleted files
+    #[pyo3(get)]
+    pub old_start: usize,
+    #[pyo3(get)]
+    pub old_lines: usize,
+    #[pyo3(get)]
+    pub new_start: usize,
+    #[pyo3(get)]
+    pub new_lines: usize,
+    #[pyo3(get)]
+    pub section: String, // Text after the closing `@@` (git's enclosing-function hint)
+    #[pyo3(get)]
+    pub added: usize,
+    #[pyo3(get)]
+    pub removed: usize,
+    #[pyo3(get)]
+    pub content: String,
+    #[pyo3(get)]
+    pub units: Vec<SemanticUnit>, // Units overlapping the changed lines, in file order
+}
+
+#[pymethods]
+impl DiffHunk {
+    fn __repr__(&self) -> String {
+        format!(
+            "DiffHunk(file={}, old={},{}, new={},{}, units={})",
+            self.path().unwrap_or(""),
+            self.old_start,
+            self.old_lines,
+            self.new_start,
+            self.new_lines,
+            self.units.len()
+        )
+    }
+}
+
+impl DiffHunk {
+    /// Path of the side the hunk is attributed to: the new file, or the old
+    /// one when the file was deleted
+    fn path(&self) -> Option<&str> {
+        self.new_path.as_deref().or(self.old_path.as_deref())
+    }
+}
+
+/// A hunk as read from the patch, before attribution
+#[derive(Debug)]
+struct RawHunk {
+    hunk: DiffHunk,
+    /// Changed lines, in the coordinates of the attributed side
+    changed_lines: Vec<usize>,
+    /// Text of the attributed side covered by the hunk (context plus changes)
+    side_text: String,
+}
+
+/// Strip the `a/` / `b/` prefixes git adds and map `/dev/null` to `None`
+fn diff_path(raw: &str) -> Option<String> {
+    // Anything after a tab is a timestamp (`diff -u` output)
+    let raw = raw.split('\t').next().unwrap_or("").trim_end();
+    if raw == "/dev/null" {
+        return None;
+    }
+    let path = raw.strip_prefix("a/").or_else(|| raw.strip_prefix("b/")).unwrap_or(raw);
+    Some(path.to_string())
+}
+
+/// Parse `-l,s` / `+l,s` ranges; the count defaults to 1 when omitted
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = match range.split_once(',') {
+        Some((start, count)) => (start, count.parse().ok()?),
+        None => (range, 1),
+    };
+    Some((start.parse().ok()?, count))
+}
+
+/// Parse an `@@ -a,b +c,d @@ section` header
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize, usize, String)> {
+    let rest = line.strip_prefix("@@ ")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(' ')?;
+    let (old_start, old_lines) = parse_range(old.strip_prefix('-')?)?;
+    let (new_start, new_lines) = parse_range(new.strip_prefix('+')?)?;
+    Some((old_start, old_lines, new_start, new_lines, section.trim().to_string()))
+}
+
+/// Split a unified diff into hunks. Hunk bodies are consumed by their line
+/// counts, so removed lines that look like headers (`--- x`) are not misread.
+fn parse_hunks(diff_text: &str) -> Result<Vec<RawHunk>, String> {
+    let lines: Vec<&str> = diff_text.lines().collect();
+    let mut hunks = Vec::new();
+    let mut old_path = None;
+    let mut new_path = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        if let Some(path) = line.strip_prefix("--- ") {
+            old_path = diff_path(path);
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("+++ ") {
+            new_path = diff_path(path);
+            continue;
+        }
+        if !line.starts_with("@@ ") {
+            // `diff --git`, `index`, mode and rename lines carry nothing we attribute
+            continue;
+        }
+
+        let (old_start, old_lines, new_start, new_lines, section) =
+            parse_hunk_header(line).ok_or(format!("Malformed hunk header on line {}: {}", i, line))?;
+
+        let deleted_file = new_path.is_none();
+        let mut old_line = old_start;
+        let mut new_line = new_start;
+        let mut old_left = old_lines;
+        let mut new_left = new_lines;
+        let mut body = vec![line];
+        let mut changed_lines = Vec::new();
+        let mut side_text = String::new();
+        let (mut added, mut removed) = (0, 0);
+
+        while (old_left > 0 || new_left > 0) && i < lines.len() {
+            let body_line = lines[i];
+            let (marker, text) = match body_line.chars().next() {
+                Some(c @ ('+' | '-' | ' ')) => (c, &body_line[1..]),
+                // `\ No newline at end of file` annotates the previous line
+                Some('\\') => {
+                    body.push(body_line);
+                    i += 1;
+                    continue;
+                }
+                // Some tools drop the space of empty context lines
+                None => (' ', ""),
+                Some(_) => break,
+            };
+            i += 1;
+            body.push(body_line);
+
+            match marker {
+                '+' => {
+                    added += 1;
+                    new_left = new_left.saturating_sub(1);
+                    if !deleted_file {
+                        changed_lines.push(new_line);
+                        side_text.push_str(text);
+                        side_text.push('\n');
+                    }
+                    new_line += 1;
+                }
+                '-' => {
+                    removed += 1;
+                    old_left = old_left.saturating_sub(1);
+                    if deleted_file {
+                        changed_lines.push(old_line);
+                        side_text.push_str(text);
+                        side_text.push('\n');
+                    } else {
+                        // A pure removal touches whatever now sits at that position
+                        changed_lines.push(new_line.max(1));
+                    }
+                    old_line += 1;
+                }
+                _ => {
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                    side_text.push_str(text);
+                    side_text.push('\n');
+                    old_line += 1;
+                    new_line += 1;
+                }
+            }
+        }
+
+        // Trailing `\ No newline` after the last counted line
+        if i < lines.len() && lines[i].starts_with('\\') {
+            body.push(lines[i]);
+            i += 1;
+        }
+
+        changed_lines.dedup();
+        hunks.push(RawHunk {
+            hunk: DiffHunk {
+                old_path: old_path.clone(),
+                new_path: new_path.clone(),
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                section,
+                added,
+                removed,
+                content: body.join("\n"),
+                units: Vec::new(),
+            },
+            changed_lines,
+            side_text,
+        });
+    }
+
+    Ok(hunks)
+}
+
+/// Units covering any of `changed_lines`, in file order
+fn touched_units(units: &[SemanticUnit], changed_lines: &[usize]) -> Vec<SemanticUnit> {
+    let mut touched: Vec<SemanticUnit> = units
+        .iter()
+        .filter(|u| changed_lines.iter().any(|&line| u.start_line <= line && line <= u.end_line))
+        .cloned()
+        .collect();
+    touched.sort_by_key(|u| (u.start_byte, std::cmp::Reverse(u.end_byte)));
+    touched
+}
+
+/// Parse a unified diff and attribute each hunk to the semantic units it touches.
+///
+/// `parse_context` maps file paths to full file contents (the new version, or
+/// the old one for deleted files); those files are parsed once and hunks are
+/// attributed against the complete symbol table. Files without context are
+/// attributed by parsing the hunk's own text, which finds units whose
+/// definitions lie inside the hunk. Files in unsupported formats get no units.
+pub fn attribute_diff(
+    diff_text: &str,
+    parse_context: &HashMap<String, String>,
+) -> Result<Vec<DiffHunk>, String> {
+    let options = ParseOptions::default();
+    let mut parsed: HashMap<String, Vec<SemanticUnit>> = HashMap::new();
+    let mut hunks = Vec::new();
+
+    for raw in parse_hunks(diff_text)? {
+        let mut hunk = raw.hunk;
+        let Some(path) = hunk.path().map(str::to_string) else {
+            hunks.push(hunk);
+            continue;
+        };
+
+        hunk.units = match parse_context.get(&path) {
+            Some(source) => {
+                let units = parsed.entry(path.clone()).or_insert_with(|| {
+                    parse_any_file(&path, source, &options).map(|r| r.units).unwrap_or_default()
+                });
+                touched_units(units, &raw.changed_lines)
+            }
+            None => {
+                let base_line = if hunk.new_path.is_some() { hunk.new_start } else { hunk.old_start };
+                let mut units = parse_any_file(&path, &raw.side_text, &options)
+                    .map(|r| r.units)
+                    .unwrap_or_default();
+                // Byte offsets are unknown without the file, so only lines are remapped
+                for unit in units.iter_mut() {
+                    unit.remap(base_line.max(1), 0);
+                }
+                touched_units(&units, &raw.changed_lines)
+            }
+        };
+        hunks.push(hunk);
+    }
+
+    Ok(hunks)
+}
+
+/// Parse a unified diff into hunks attributed to the functions and classes they touch
+///
+/// `parse_context` optionally maps file paths (as they appear in the diff,
+/// without `a/`/`b/` prefixes) to full file contents for precise attribution.
+/// Raises ValueError for a malformed hunk header.
+#[pyfunction]
+#[pyo3(signature = (diff_text, parse_context=None))]
+pub fn parse_unified_diff(
+    diff_text: String,
+    parse_context: Option<HashMap<String, String>>,
+) -> PyResult<Vec<DiffHunk>> {
+    attribute_diff(&diff_text, &parse_context.unwrap_or_default())
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW_FILE: &str = "import os\n\n\ndef keep():\n    return 1\n\n\ndef changed(x):\n    y = x + 1\n    return y\n";
+
+    const DIFF: &str = "diff --git a/app.py b/app.py\nindex 1111111..2222222 100644\n--- a/app.py\n+++ b/app.py\n@@ -8,3 +8,3 @@ def keep():\n def changed(x):\n-    y = x\n+    y = x + 1\n     return y\n";
+
+    #[test]
+    fn test_parse_hunks_reads_header_and_counts() {
+        let hunks = parse_hunks(DIFF).unwrap();
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0].hunk;
+        assert_eq!(hunk.old_path.as_deref(), Some("app.py"));
+        assert_eq!(hunk.new_path.as_deref(), Some("app.py"));
+        assert_eq!((hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines), (8, 3, 8, 3));
+        assert_eq!(hunk.section, "def keep():");
+        assert_eq!((hunk.added, hunk.removed), (1, 1));
+        assert_eq!(hunks[0].changed_lines, vec![9]);
+    }
+
+    #[test]
+    fn test_attribution_with_context_names_enclosing_function() {
+        let context = HashMap::from([("app.py".to_string(), NEW_FILE.to_string())]);
+        let hunks = attribute_diff(DIFF, &context).unwrap();
+        let names: Vec<&str> = hunks[0].units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["changed"]);
+    }
+
+    #[test]
+    fn test_attribution_without_context_parses_hunk_text() {
+        let hunks = attribute_diff(DIFF, &HashMap::new()).unwrap();
+        let unit = &hunks[0].units[0];
+        assert_eq!(unit.name, "changed");
+        assert_eq!((unit.start_line, unit.end_line), (8, 10));
+    }
+
+    #[test]
+    fn test_removed_lines_resembling_headers_stay_in_hunk() {
+        let diff = "--- a/q.sql\n+++ b/q.sql\n@@ -1,2 +1,1 @@\n--- a comment\n SELECT 1;\n@@ -10,1 +9,1 @@\n-x\n+y\n";
+        let hunks = parse_hunks(diff).unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].hunk.removed, 1);
+        assert_eq!(hunks[0].hunk.new_path.as_deref(), Some("q.sql"));
+        assert_eq!(hunks[1].changed_lines, vec![9]);
+    }
+
+    #[test]
+    fn test_deleted_file_attributes_old_side() {
+        let diff = "--- a/gone.rs\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-fn gone() {\n-}\n";
+        let hunks = attribute_diff(diff, &HashMap::new()).unwrap();
+        assert_eq!(hunks[0].new_path, None);
+        assert_eq!(hunks[0].units[0].name, "gone");
+        assert_eq!(hunks[0].units[0].start_line, 1);
+    }
+
+    #[test]
+    fn test_malformed_header_is_an_error() {
+        assert!(attribute_diff("--- a/x\n+++ b/x\n@@ bogus @@\n", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use tree_sitter::{Node, Tree};
+
+use crate::parsing::{line_starts, with_thread_parser, ParseOptions, SemanticUnit, SupportedLanguage};
+
+/// A reference from a document or code comment to another page, file or URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct DocLink {
+    #[pyo3(get)]
+    pub kind: String, // "markdown" (`[text](target)`, `[ref]: target`), "wiki" (`[[Page]]`) or "url"
+    #[pyo3(get)]
+    pub target: String, // As written, e.g. "../api.md#auth"
+    #[pyo3(get)]
+    pub resolved: Option<String>, // Graph node linked to; None for links within the same document
+    #[pyo3(get)]
+    pub text: String, // Link text, wiki label, or the URL itself
+    #[pyo3(get)]
+    pub line: usize,
+    #[pyo3(get)]
+    pub unit: Option<String>, // Qualified name of the innermost section or function holding the link
+}
+
+#[pymethods]
+impl DocLink {
+    fn __repr__(&self) -> String {
+        format!("DocLink(kind={}, target={}, line={})", self.kind, self.target, self.line)
+    }
+}
+
+/// The links of one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct LinkGraph {
+    #[pyo3(get)]
+    pub file_path: String,
+    #[pyo3(get)]
+    pub links: Vec<DocLink>,
+}
+
+#[pymethods]
+impl LinkGraph {
+    /// `(file_path, resolved target)` edges, once each in order of first
+    /// link, in the form `pagerank` and `betweenness` take
+    pub fn edges(&self) -> Vec<(String, String)> {
+        let mut edges: Vec<(String, String)> = Vec::new();
+        for target in self.links.iter().filter_map(|link| link.resolved.as_ref()) {
+            if !edges.iter().any(|(_, seen)| seen == target) {
+                edges.push((self.file_path.clone(), target.clone()));
+            }
+        }
+        edges
+    }
+
+    fn __repr__(&self) -> String {
+        format!("LinkGraph(file={}, links={})", self.file_path, self.links.len())
+    }
+}
+
+/// A link found in text: its kind, target, text and byte offset
+pub(crate) type Found = (&'static str, String, String, usize);
+
+/// `[text](target "title")` starting at `open`, or a reference definition
+/// `[text]: target` when `open` starts a line; returns the text, target
+/// and the end of the link
+fn markdown_link(text: &str, open: usize) -> Option<(String, String, usize)> {
+    let label_end = open + 1 + text[open + 1..].find(']')?;
+    let label = &text[open + 1..label_end];
+    if label.contains(['[', '\n']) {
+        return None;
+    }
+    let rest = &text[label_end + 1..];
+    if let Some(inner) = rest.strip_prefix('(') {
+        let close = inner.find(')')?;
+        let destination = inner[..close].trim();
+        let target = match destination.strip_prefix('<') {
+            Some(angled) => angled.split('>').next()?,
+            None => destination.split_whitespace().next()?,
+        };
+        return Some((label.to_string(), target.to_string(), label_end + close + 3));
+    }
+    let at_line_start = text[..open].rsplit('\n').next().unwrap_or("").trim().is_empty();
+    let definition = rest.strip_prefix(':').filter(|_| at_line_start)?;
+    let target = definition.split_whitespace().next()?;
+    let target_end = label_end + 2 + (definition.len() - definition.trim_start().len()) + target.len();
+    Some((label.to_string(), target.to_string(), target_end))
+}
+
+/// End of the bare URL starting at `start`, trailing punctuation excluded
+fn url_end(text: &str, start: usize) -> usize {
+    let end = text[start..]
+        .find(|c: char| c.is_whitespace() || "<>\"'`)]|".contains(c))
+        .map_or(text.len(), |n| start + n);
+    start + text[start..end].trim_end_matches(['.', ',', ';', ':', '!', '?']).len()
+}
+
+/// Markdown links, wiki links and bare URLs in `text`, which starts at byte
+/// `base` of the file
+pub(crate) fn scan_links(text: &str, base: usize, found: &mut Vec<Found>) {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"[[") {
+            let inner = text[i + 2..].find("]]").map(|close| &text[i + 2..i + 2 + close]);
+            if let Some(inner) = inner.filter(|inner| !inner.trim().is_empty() && !inner.contains(['\n', '['])) {
+                let (page, label) = inner.split_once('|').unwrap_or((inner, inner));
+                found.push(("wiki", page.trim().to_string(), label.trim().to_string(), base + i));
+                i += inner.len() + 4;
+                continue;
+            }
+        }
+        if bytes[i] == b'[' && (i == 0 || bytes[i - 1] != b'!') {
+            if let Some((label, target, end)) = markdown_link(text, i) {
+                found.push(("markdown", target, label, base + i));
+                i = end;
+                continue;
+            }
+        }
+        if bytes[i..].starts_with(b"http://") || bytes[i..].starts_with(b"https://") {
+            let end = url_end(text, i);
+            found.push(("url", text[i..end].to_string(), text[i..end].to_string(), base + i));
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+}
+
+/// The graph node a link points to: URLs as written, wiki links by page
+/// name, and paths resolved against the linking file's directory, all
+/// without their `#anchor`. None for links within the same document.
+pub(crate) fn resolve(file_path: &str, kind: &str, target: &str) -> Option<String> {
+    if target.contains("://") || target.starts_with("mailto:") {
+        return Some(target.to_string());
+    }
+    let path = target.split('#').next().unwrap_or("").trim();
+    if path.is_empty() {
+        return None;
+    }
+    if kind == "wiki" {
+        return Some(path.to_string());
+    }
+    let path = path.split('?').next().unwrap_or(path);
+    let mut segments: Vec<&str> = match path.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => file_path.split('/').collect(),
+    };
+    // The linking file's own name
+    segments.pop();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Byte ranges of comments, and of docstrings (a string opening a block),
+/// in a syntax tree
+fn comment_ranges(tree: &Tree) -> Vec<Range<usize>> {
+    let is_docstring = |node: &Node| {
+        node.kind() == "string"
+            && node.parent().is_some_and(|parent| {
+                parent.kind() == "expression_statement" && parent.prev_named_sibling().is_none()
+            })
+    };
+    let mut ranges = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let is_text = node.kind().contains("comment") || is_docstring(&node);
+        if is_text {
+            ranges.push(node.byte_range());
+        }
+        if !is_text && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return ranges;
+            }
+        }
+    }
+}
+
+/// Byte ranges of a Markdown document outside inline code and code blocks
+pub(crate) fn markdown_prose_ranges(source: &str) -> Vec<Range<usize>> {
+    let mut prose = Vec::new();
+    let mut start = 0;
+    for (event, range) in Parser::new(source).into_offset_iter() {
+        if matches!(event, Event::Code(_) | Event::Start(Tag::CodeBlock(_))) && range.start >= start {
+            prose.push(start..range.start);
+            start = range.end;
+        }
+    }
+    prose.push(start..source.len());
+    prose
+}
+
+/// Extract the links of a file. Markdown (and other text) is scanned
+/// outside code; source files supported by tree-sitter are scanned in
+/// their comments and docstrings only.
+pub(crate) fn extract_links(file_path: &str, source_code: &str) -> Result<LinkGraph, String> {
+    let extension = std::path::Path::new(file_path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let language = SupportedLanguage::from_extension(extension);
+    let (ranges, units): (Vec<Range<usize>>, Vec<SemanticUnit>) = match language {
+        Some(lang) => {
+            let (result, tree) = with_thread_parser(|parser| {
+                parser.parse_tree_with_language(file_path, source_code, lang, &ParseOptions::default())
+            })?;
+            (comment_ranges(&tree), result.units)
+        }
+        None if matches!(extension, "md" | "markdown") => {
+            let result = crate::markdown_parsing::parse_markdown(file_path, source_code)?;
+            let sections = result.units.into_iter().filter(|u| u.unit_type == "section").collect();
+            (markdown_prose_ranges(source_code), sections)
+        }
+        None => (std::iter::once(0..source_code.len()).collect(), Vec::new()),
+    };
+
+    let mut found = Vec::new();
+    for range in ranges {
+        scan_links(&source_code[range.clone()], range.start, &mut found);
+    }
+    let line_starts = line_starts(source_code);
+    let links = found
+        .into_iter()
+        .map(|(kind, target, text, at)| {
+            let unit = units
+                .iter()
+                .filter(|u| u.start_byte <= at && at < u.end_byte)
+                .min_by_key(|u| u.end_byte - u.start_byte)
+                .map(|u| u.qualified_name.clone());
+            DocLink {
+                kind: kind.to_string(),
+                resolved: resolve(file_path, kind, &target),
+                target,
+                text,
+                line: line_starts.partition_point(|&start| start <= at),
+                unit,
+            }
+        })
+        .collect();
+    Ok(LinkGraph { file_path: file_path.to_string(), links })
+}
+
+/// Extract the cross-references of a document or source file: Markdown
+/// links and reference definitions, wiki links (`[[Page]]`,
+/// `[[Page#Heading|label]]`) and bare URLs
+///
+/// Markdown is scanned outside code spans and blocks; source files are
+/// scanned in comments and docstrings only; other files as plain text.
+/// `LinkGraph.edges()` gives `(file_path, target)` pairs, relative paths
+/// resolved against the file's directory, ready to rank with `pagerank` or
+/// record as "references" relations between the memories of both ends.
+/// Raises RuntimeError when a source file can't be parsed.
+#[pyfunction]
+pub fn extract_doc_links(py: Python<'_>, file_path: String, source_code: String) -> PyResult<LinkGraph> {
+    py.detach(|| extract_links(&file_path, &source_code)).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A link's kind, target, resolved path, text, line and unit
+    type Row<'a> = (&'a str, &'a str, Option<&'a str>, &'a str, usize, Option<&'a str>);
+
+    fn links(graph: &LinkGraph) -> Vec<Row<'_>> {
+        graph
+            .links
+            .iter()
+            .map(|l| {
+                let (resolved, unit) = (l.resolved.as_deref(), l.unit.as_deref());
+                (l.kind.as_str(), l.target.as_str(), resolved, l.text.as_str(), l.line, unit)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_markdown_links_outside_code() {
+        let source = "# Guide\nSee [the API](../api/README.md#auth) and [[Deploy Notes|deploy]].\n\
+            Docs at https://example.com/docs.\n\n## Code\n`[not](a link)` and ![logo](logo.png)\n\
+            ```\nfetch https://skip.me\n```\n[ref]: ./ref.md\n[top](#guide)\n";
+        let graph = extract_links("docs/guide.md", source).unwrap();
+        let docs = "https://example.com/docs";
+        assert_eq!(
+            links(&graph),
+            vec![
+                ("markdown", "../api/README.md#auth", Some("api/README.md"), "the API", 2, Some("Guide")),
+                ("wiki", "Deploy Notes", Some("Deploy Notes"), "deploy", 2, Some("Guide")),
+                ("url", docs, Some(docs), docs, 3, Some("Guide")),
+                ("markdown", "./ref.md", Some("docs/ref.md"), "ref", 10, Some("Guide > Code")),
+                ("markdown", "#guide", None, "top", 11, Some("Guide > Code")),
+            ]
+        );
+        let targets: Vec<String> = graph.edges().into_iter().map(|(_, target)| target).collect();
+        assert_eq!(targets, vec!["api/README.md", "Deploy Notes", "https://example.com/docs", "docs/ref.md"]);
+    }
+
+    #[test]
+    fn test_code_links_come_from_comments_and_docstrings() {
+        let source = "def load():\n    \"\"\"Loads data; see https://pandas.pydata.org/docs.\"\"\"\n    \
+            # Format described in [[Data Format]]\n    url = \"https://not-a-comment.example\"\n    return url\n";
+        let graph = extract_links("etl/load.py", source).unwrap();
+        let pandas = "https://pandas.pydata.org/docs";
+        assert_eq!(
+            links(&graph),
+            vec![
+                ("url", pandas, Some(pandas), pandas, 2, Some("load")),
+                ("wiki", "Data Format", Some("Data Format"), "Data Format", 3, Some("load")),
+            ]
+        );
+    }
+}
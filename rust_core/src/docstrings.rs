@@ -0,0 +1,203 @@
+use tree_sitter::Node;
+
+/// Node kinds that wrap a declaration without documenting it themselves;
+/// a comment above the wrapper documents the declaration inside
+const WRAPPERS: &[&str] = &[
+    "decorated_definition", // Python decorators
+    "export_statement",     // JS/TS `export`
+    "type_declaration",     // Go `type X ...`
+    "const_declaration",
+    "var_declaration",
+    "template_declaration", // C++ templates
+];
+
+/// Statement lists whose leading comments are parsed outside the list, as
+/// siblings of the list rather than of its first statement
+const BLOCKS: &[&str] = &["block", "body_statement"];
+
+/// Documentation attached to a unit's node: its Python docstring, or else
+/// the comment block directly above it (JSDoc, Javadoc, `///`, `#`...).
+/// Comment markers and common indentation are removed.
+pub(crate) fn docstring(node: Node, source: &[u8]) -> Option<String> {
+    python_docstring(node, source)
+        .or_else(|| leading_comments(node, source))
+        .filter(|text| !text.is_empty())
+}
+
+/// The string literal opening a Python function or class body
+fn python_docstring(node: Node, source: &[u8]) -> Option<String> {
+    if !matches!(node.kind(), "function_definition" | "class_definition") {
+        return None;
+    }
+    let body = node.child_by_field_name("body").filter(|b| b.kind() == "block")?;
+    let statement = body.named_child(0).filter(|s| s.kind() == "expression_statement")?;
+    let string = statement.named_child(0).filter(|s| s.kind() == "string")?;
+    // String prefixes (r, u...) come before the quotes
+    let text = string.utf8_text(source).ok()?.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let quote = ["\"\"\"", "'''", "\"", "'"].into_iter().find(|q| text.starts_with(q))?;
+    let inner = &text[quote.len()..];
+    let inner = inner.strip_suffix(quote).unwrap_or(inner);
+    Some(cleandoc(&inner.lines().collect::<Vec<_>>()))
+}
+
+/// The comments directly above the node, or above the declaration wrapping
+/// it; a blank line or trailing code ends the block
+fn leading_comments(node: Node, source: &[u8]) -> Option<String> {
+    let mut anchor = node;
+    while let Some(parent) = anchor.parent() {
+        let wrapped = WRAPPERS.contains(&parent.kind()) && !anchor.prev_named_sibling().is_some_and(is_comment);
+        let opens_block = BLOCKS.contains(&parent.kind()) && anchor.prev_sibling().is_none();
+        if !(wrapped || opens_block) {
+            break;
+        }
+        anchor = parent;
+    }
+
+    let mut comments = Vec::new();
+    let mut below = anchor.start_position().row;
+    let mut current = anchor.prev_sibling();
+    while let Some(sibling) = current {
+        current = sibling.prev_sibling();
+        // Rust attributes sit between doc comments and the item
+        if sibling.kind() == "attribute_item" {
+            below = sibling.start_position().row;
+            continue;
+        }
+        if !is_comment(sibling) || last_row(sibling) + 1 < below {
+            break;
+        }
+        // A comment sharing its line with code documents that code
+        if current.is_some_and(|code| !is_comment(code) && last_row(code) == sibling.start_position().row) {
+            break;
+        }
+        comments.push(sibling.utf8_text(source).ok()?);
+        below = sibling.start_position().row;
+    }
+
+    let lines: Vec<String> = comments.into_iter().rev().flat_map(strip_markers).collect();
+    Some(cleandoc(&lines))
+}
+
+fn is_comment(node: Node) -> bool {
+    node.kind().contains("comment")
+}
+
+/// Last line the node has text on; comments may end with their newline
+pub(crate) fn last_row(node: Node) -> usize {
+    let end = node.end_position();
+    if end.column == 0 && end.row > node.start_position().row {
+        end.row - 1
+    } else {
+        end.row
+    }
+}
+
+/// Lines of a comment without its markers (`//`, `#`, `/** */`, leading `*`...)
+fn strip_markers(comment: &str) -> Vec<String> {
+    let comment = comment.trim();
+    if let Some(block) = comment.strip_prefix("/*") {
+        let block = block.strip_suffix("*/").unwrap_or(block).trim_start_matches(['*', '!']);
+        return block
+            .lines()
+            .map(|line| match line.trim_start().strip_prefix('*') {
+                Some(rest) => rest.strip_prefix(' ').unwrap_or(rest).to_string(),
+                None => line.to_string(),
+            })
+            .collect();
+    }
+    // Ruby block comments
+    if let Some(block) = comment.strip_prefix("=begin") {
+        return block.strip_suffix("=end").unwrap_or(block).lines().map(str::to_string).collect();
+    }
+    comment
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let marker = ["///", "//!", "//", "#", "--"].into_iter().find(|m| line.starts_with(m)).unwrap_or("");
+            let rest = &line[marker.len()..];
+            rest.strip_prefix(' ').unwrap_or(rest).to_string()
+        })
+        .collect()
+}
+
+/// Lines joined with their common indentation removed, as Python's
+/// `inspect.cleandoc` does: the first line is only trimmed, and blank lines
+/// at either end are dropped
+fn cleandoc<S: AsRef<str>>(lines: &[S]) -> String {
+    let indent = lines
+        .iter()
+        .skip(1)
+        .map(AsRef::as_ref)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let line = line.as_ref();
+            match index {
+                0 => line.trim(),
+                _ => line.get(indent..).unwrap_or(line.trim_start()).trim_end(),
+            }
+        })
+        .collect();
+    while cleaned.last().is_some_and(|line| line.is_empty()) {
+        cleaned.pop();
+    }
+    let start = cleaned.iter().position(|line| !line.is_empty()).unwrap_or(cleaned.len());
+    cleaned[start..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    fn docstrings(file_path: &str, source: &str) -> Vec<(String, Option<String>)> {
+        let result = parse_any_file(file_path, source, &ParseOptions::default()).unwrap();
+        result.units.into_iter().map(|u| (u.name, u.docstring)).collect()
+    }
+
+    fn expected(pairs: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        pairs.iter().map(|(name, doc)| (name.to_string(), doc.map(str::to_string))).collect()
+    }
+
+    #[test]
+    fn test_python_docstrings_and_comments() {
+        let source = "x = 1  # not documentation\ndef load(path):\n    \"\"\"Load a file.\n\n    \
+            Returns its text.\n    \"\"\"\n\n# Shapes\n\n# Geometry helpers\n@dataclass\nclass Circle:\n    \
+            r'''Round.'''\n\n# Unattached\n\ndef bare():\n    pass\n";
+        assert_eq!(
+            docstrings("a.py", source),
+            expected(&[
+                ("load", Some("Load a file.\n\nReturns its text.")),
+                ("bare", None),
+                ("Circle", Some("Round.")),
+            ])
+        );
+        let decorated = "# Geometry helpers\n@dataclass\nclass Circle:\n    pass\n";
+        assert_eq!(docstrings("b.py", decorated), expected(&[("Circle", Some("Geometry helpers"))]));
+    }
+
+    #[test]
+    fn test_comment_styles() {
+        let jsdoc = "/**\n * Add two numbers.\n * @param a first\n */\nexport function add(a, b) { return a + b; }\n";
+        assert_eq!(docstrings("a.js", jsdoc), expected(&[("add", Some("Add two numbers.\n@param a first"))]));
+
+        let rust = "/// Parse input.\n///\n/// Example:\n///     parse()\n#[inline]\nfn parse() {}\n";
+        assert_eq!(docstrings("a.rs", rust), expected(&[("parse", Some("Parse input.\n\nExample:\n    parse()"))]));
+
+        let java = "class A {\n    /** Total price. */\n    int total() { return 0; }\n}\n";
+        assert_eq!(docstrings("A.java", java), expected(&[("total", Some("Total price.")), ("A", None)]));
+
+        let go = "package main\n\n// Run starts the server.\n// It blocks.\nfunc Run() {}\n";
+        assert_eq!(docstrings("a.go", go), expected(&[("Run", Some("Run starts the server.\nIt blocks."))]));
+
+        let ruby = "# Greets people\nclass Greeter\n  # Say hello\n  def greet; end\nend\n";
+        assert_eq!(
+            docstrings("a.rb", ruby),
+            expected(&[("greet", Some("Say hello")), ("Greeter", Some("Greets people"))])
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What a destructive store operation would change. Operations taking
+/// `dry_run` select the records they affect the same way either way and
+/// return this plan instead of applying it, so a dry run reports exactly
+/// what a real run would do and changes nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct ChangePlan {
+    #[pyo3(get)]
+    pub operation: String, // The method planned, e.g. "sweep_expired"
+    #[pyo3(get)]
+    pub ids: Vec<String>, // Ids that would be deleted or rewritten
+    #[pyo3(get)]
+    pub count: usize, // Records affected, including any without an id (reclaimed tombstones)
+    #[pyo3(get)]
+    pub bytes: u64, // Bytes that would be freed or rewritten
+}
+
+#[pymethods]
+impl ChangePlan {
+    fn __repr__(&self) -> String {
+        format!("ChangePlan(operation={}, count={}, bytes={})", self.operation, self.count, self.bytes)
+    }
+}
+
+impl ChangePlan {
+    pub fn new(operation: &str, ids: Vec<String>, count: usize, bytes: u64) -> Self {
+        Self { operation: operation.to_string(), ids, count, bytes }
+    }
+}
+
+/// A destructive operation's result, or with `dry_run` its plan
+#[derive(Debug, IntoPyObject)]
+pub enum Outcome<T> {
+    Applied(T),
+    Planned(ChangePlan),
+}
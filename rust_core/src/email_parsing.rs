@@ -0,0 +1,406 @@
+use mailparse::{DispositionType, MailAddr, MailHeaderMap, ParsedMail};
+use std::collections::HashMap;
+
+use crate::parsing::{ParseResult, SemanticUnit};
+
+/// Separator between a thread's subject and a message in its qualified name
+const PATH_SEPARATOR: &str = " > ";
+
+/// Longest chunk of an HTML-only message kept when rendering it as text
+const HTML_CHUNK_CHARS: usize = 4000;
+
+/// Whether a file holds email, by its extension: "eml" for one message,
+/// "mbox" for many
+pub fn email_format(file_path: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(file_path).extension().and_then(|e| e.to_str())?;
+    match extension.to_ascii_lowercase().as_str() {
+        "eml" => Some("eml"),
+        "mbox" => Some("mbox"),
+        _ => None,
+    }
+}
+
+/// One message's place in its file
+struct Span {
+    line: usize, // 1-based
+    start: usize,
+    end: usize,
+}
+
+/// Whether a line looks like a mail header (`Name: value`)
+fn is_header_line(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(name, _)| {
+        !name.is_empty() && name.bytes().all(|c| c.is_ascii_graphic() && c != b':')
+    })
+}
+
+/// The messages of an mbox file. A message starts at a `From ` envelope
+/// line at the top of the file or after a blank line, followed by a header;
+/// a file without one is a single message.
+fn split_mbox(source: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut offset = 0;
+    let mut lines = Vec::new();
+    for line in source.split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+    let mut after_blank = true;
+    for (index, &(offset, line)) in lines.iter().enumerate() {
+        let next_is_header = lines.get(index + 1).is_some_and(|(_, next)| is_header_line(next));
+        if after_blank && line.starts_with("From ") && next_is_header {
+            if let Some(last) = spans.last_mut() {
+                last.end = offset;
+            }
+            spans.push(Span { line: index + 1, start: offset, end: source.len() });
+        }
+        after_blank = line.trim().is_empty();
+    }
+    if spans.is_empty() && !source.trim().is_empty() {
+        spans.push(Span { line: 1, start: 0, end: source.len() });
+    }
+    spans
+}
+
+/// A message's raw text without its envelope line, with mboxrd `>From `
+/// escapes undone
+fn unescape_message(text: &str) -> String {
+    let text = if text.starts_with("From ") { text.split_once('\n').map_or("", |(_, rest)| rest) } else { text };
+    let mut raw = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let escaped = line.starts_with('>') && line.trim_start_matches('>').starts_with("From ");
+        raw.push_str(if escaped { &line[1..] } else { line });
+    }
+    raw
+}
+
+/// The readable body of a message: its first inline text/plain part, or
+/// the main content of its first text/html part
+fn body_text(mail: &ParsedMail) -> Result<Option<String>, String> {
+    fn find<'a>(mail: &'a ParsedMail<'a>, mimetype: &str) -> Option<&'a ParsedMail<'a>> {
+        if mail.get_content_disposition().disposition == DispositionType::Attachment {
+            return None;
+        }
+        if mail.subparts.is_empty() {
+            return (mail.ctype.mimetype == mimetype).then_some(mail);
+        }
+        mail.subparts.iter().find_map(|part| find(part, mimetype))
+    }
+
+    if let Some(part) = find(mail, "text/plain") {
+        return part.get_body().map(Some).map_err(|e| e.to_string());
+    }
+    if let Some(part) = find(mail, "text/html") {
+        let html = part.get_body().map_err(|e| e.to_string())?;
+        return Ok(Some(crate::html_ingest::extract_page(&html, "", HTML_CHUNK_CHARS).text));
+    }
+    Ok(None)
+}
+
+/// Whether a line opens the quoted history of a reply: a Gmail-style
+/// attribution ("On <date>, <name> wrote:", possibly wrapped onto a second
+/// line) or an Outlook separator
+fn starts_history(line: &str, next: Option<&str>) -> bool {
+    let line = line.trim();
+    let attribution = line.starts_with("On ")
+        && (line.ends_with("wrote:") || next.is_some_and(|next| next.trim().ends_with("wrote:")));
+    let outlook = line.contains("-----Original Message-----") || (line.len() >= 10 && line.bytes().all(|c| c == b'_'));
+    attribution || outlook
+}
+
+/// Whether a line opens a signature: the `-- ` delimiter or a mobile
+/// client's sign-off
+fn starts_signature(line: &str) -> bool {
+    matches!(line.trim_end_matches(['\r', '\n']), "-- " | "--") || line.starts_with("Sent from my ")
+}
+
+/// A message's own text: quoted lines, the quoted history of a reply and
+/// the signature cut off
+fn strip_quotes_and_signature(body: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut kept = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if starts_signature(line) || starts_history(line, lines.get(index + 1).copied()) {
+            break;
+        }
+        if !line.trim_start().starts_with('>') {
+            kept.push(line.trim_end());
+        }
+    }
+    kept.join("\n").trim().to_string()
+}
+
+/// A subject without reply and forward prefixes or a leading list tag, so
+/// every message of a thread has the same one
+fn thread_subject(subject: &str) -> String {
+    let mut subject = subject.trim();
+    loop {
+        let lower = subject.to_ascii_lowercase();
+        let prefix = ["re:", "fwd:", "fw:", "aw:"].iter().find(|prefix| lower.starts_with(*prefix));
+        if let Some(prefix) = prefix {
+            subject = subject[prefix.len()..].trim_start();
+        } else if let Some(rest) = subject.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            subject = rest.1.trim_start();
+        } else {
+            return subject.to_string();
+        }
+    }
+}
+
+/// Message ids in a Message-ID, In-Reply-To or References header, without
+/// their angle brackets
+fn message_ids(header: &str) -> Vec<String> {
+    header
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|id| id.trim_matches(|c| c == '<' || c == '>'))
+        .filter(|id| id.contains('@'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Addresses in an address header, lowercased
+fn addresses(header: Option<String>) -> Vec<String> {
+    let Some(list) = header.and_then(|header| mailparse::addrparse(&header).ok()) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for address in list.iter() {
+        match address {
+            MailAddr::Single(single) => found.push(single.addr.to_lowercase()),
+            MailAddr::Group(group) => found.extend(group.addrs.iter().map(|single| single.addr.to_lowercase())),
+        }
+    }
+    found
+}
+
+/// A parsed message, before it's placed in a thread
+struct Message {
+    span: Span,
+    subject: String,
+    sender: String,
+    id: Option<String>,
+    thread_key: String,
+    body: String,
+    metadata: HashMap<String, String>,
+}
+
+fn read_message(span: Span, text: &str, index: usize) -> Result<Message, String> {
+    let raw = unescape_message(text);
+    let mail = mailparse::parse_mail(raw.as_bytes()).map_err(|e| e.to_string())?;
+    let header = |name: &str| mail.headers.get_first_value(name);
+
+    let subject = header("Subject").map(|subject| subject.trim().to_string()).unwrap_or_default();
+    let sender = header("From").unwrap_or_default().trim().to_string();
+    let id = header("Message-ID").and_then(|id| message_ids(&id).into_iter().next());
+    let in_reply_to = header("In-Reply-To").and_then(|id| message_ids(&id).into_iter().next());
+    let references = header("References").map(|ids| message_ids(&ids)).unwrap_or_default();
+    // Threads are keyed by their root's id, or by subject when nothing links them
+    let thread_key = references
+        .first()
+        .or(in_reply_to.as_ref())
+        .or(id.as_ref())
+        .cloned()
+        .unwrap_or_else(|| format!("subject:{}", thread_subject(&subject).to_lowercase()));
+
+    let (from, to, cc) = (addresses(header("From")), addresses(header("To")), addresses(header("Cc")));
+    let mut participants: Vec<String> = from.iter().chain(&to).chain(&cc).cloned().collect();
+    participants.sort();
+    participants.dedup();
+
+    let mut metadata = HashMap::new();
+    metadata.insert("from".to_string(), from.join(", "));
+    metadata.insert("to".to_string(), to.join(", "));
+    metadata.insert("participants".to_string(), participants.join(", "));
+    if !cc.is_empty() {
+        metadata.insert("cc".to_string(), cc.join(", "));
+    }
+    if let Some(date) = header("Date") {
+        if let Ok(timestamp) = mailparse::dateparse(&date) {
+            metadata.insert("timestamp".to_string(), timestamp.to_string());
+        }
+        metadata.insert("date".to_string(), date.trim().to_string());
+    }
+    if let Some(id) = &id {
+        metadata.insert("message_id".to_string(), id.clone());
+    }
+    if let Some(parent) = &in_reply_to {
+        metadata.insert("in_reply_to".to_string(), parent.clone());
+    }
+    let body = body_text(&mail)?.map(|body| strip_quotes_and_signature(&body)).unwrap_or_default();
+    if body.is_empty() && subject.is_empty() {
+        return Err(format!("message {} has no subject or text", index + 1));
+    }
+    Ok(Message { span, subject, sender, id, thread_key, body, metadata })
+}
+
+/// Parse an mbox file or a single `.eml` message into one "message" unit
+/// per message, with quoted history and signatures stripped from its text.
+///
+/// Messages are threaded by `References` and `In-Reply-To`, falling back to
+/// the subject without `Re:`/`Fwd:` prefixes: `parent_name` and
+/// `metadata["thread"]` are the thread's subject, `metadata["thread_id"]`
+/// the id of its first message. Addresses (`from`, `to`, `cc` and the
+/// sorted `participants`) are lowercased and comma-joined; `date` is the raw
+/// header and `timestamp` its Unix time. Messages that can't be parsed are
+/// reported as warnings.
+pub fn parse_email(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let spans = match email_format(file_path) {
+        Some("eml") => vec![Span { line: 1, start: 0, end: source_code.len() }],
+        _ => split_mbox(source_code),
+    };
+
+    let mut messages = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, span) in spans.into_iter().enumerate() {
+        let (line, text) = (span.line, &source_code[span.start..span.end]);
+        match read_message(span, text, index) {
+            Ok(message) => messages.push(message),
+            Err(e) => warnings.push(format!("Skipped message at line {}: {}", line, e)),
+        }
+    }
+
+    // A reply whose headers name only its parent joins the parent's thread
+    let by_id: HashMap<&str, usize> =
+        messages.iter().enumerate().filter_map(|(index, message)| Some((message.id.as_deref()?, index))).collect();
+    let thread_keys: Vec<String> = messages
+        .iter()
+        .map(|message| {
+            let mut key = message.thread_key.as_str();
+            for _ in 0..messages.len() {
+                match by_id.get(key).map(|&index| messages[index].thread_key.as_str()) {
+                    Some(next) if next != key => key = next,
+                    _ => break,
+                }
+            }
+            key.to_string()
+        })
+        .collect();
+    let mut subjects: HashMap<&str, String> = HashMap::new();
+    for (message, key) in messages.iter().zip(&thread_keys) {
+        let subject = by_id.get(key.as_str()).map_or(&message.subject, |&root| &messages[root].subject);
+        subjects.entry(key.as_str()).or_insert_with(|| thread_subject(subject));
+    }
+
+    let mut units = Vec::new();
+    for (index, (message, key)) in messages.iter().zip(&thread_keys).enumerate() {
+        let thread = subjects[key.as_str()].clone();
+        let name = if message.subject.is_empty() { "(no subject)".to_string() } else { message.subject.clone() };
+        let label = message.id.clone().unwrap_or_else(|| format!("message {}", index + 1));
+        let mut metadata = message.metadata.clone();
+        metadata.insert("thread".to_string(), thread.clone());
+        if !key.starts_with("subject:") {
+            metadata.insert("thread_id".to_string(), key.clone());
+        }
+        let text = &source_code[message.span.start..message.span.end];
+        units.push(SemanticUnit {
+            unit_type: "message".into(),
+            name: name.clone(),
+            parent_name: Some(thread.clone()),
+            qualified_name: format!("{}{}{}", thread, PATH_SEPARATOR, label),
+            start_line: message.span.line,
+            end_line: message.span.line + text.trim_end().lines().count().max(1) - 1,
+            start_byte: message.span.start,
+            end_byte: message.span.end,
+            signature: format!("From: {} | Subject: {}", message.sender, name),
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: message.body.clone(),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: "Email".into(),
+            metadata,
+        });
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Email".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MBOX: &str = "\
+From alice@example.com Mon Mar  4 10:00:00 2024
+From: Alice <Alice@Example.com>
+To: dev@lists.example.com
+Subject: [dev] Drop Python 3.8?
+Date: Mon, 4 Mar 2024 10:00:00 +0000
+Message-ID: <root@example.com>
+
+I think we should drop 3.8 in the next release.
+>From what I can tell nobody uses it.
+
+--
+Alice, Release Manager
+
+From bob@example.com Mon Mar  4 11:00:00 2024
+From: Bob <bob@example.com>
+To: dev@lists.example.com
+Cc: Alice <alice@example.com>
+Subject: Re: [dev] Drop Python 3.8?
+Date: Mon, 4 Mar 2024 11:00:00 +0000
+Message-ID: <reply@example.com>
+In-Reply-To: <root@example.com>
+
+Agreed, decided: 3.8 goes in 2.0.
+
+On Mon, Mar 4, 2024 at 10:00 AM Alice <alice@example.com>
+wrote:
+> I think we should drop 3.8 in the next release.
+
+From carol@example.com Mon Mar  4 12:00:00 2024
+From: carol@example.com
+To: dev@lists.example.com
+Subject: Re: Re: [dev] Drop Python 3.8?
+Message-ID: <late@example.com>
+In-Reply-To: <reply@example.com>
+
+> Agreed
++1
+Sent from my phone
+";
+
+    #[test]
+    fn test_mbox_messages_are_threaded_and_cleaned() {
+        let result = parse_email("lists/dev.mbox", MBOX).unwrap();
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        let bodies: Vec<&str> = result.units.iter().map(|u| u.content.as_str()).collect();
+        assert_eq!(
+            bodies,
+            vec![
+                "I think we should drop 3.8 in the next release.\nFrom what I can tell nobody uses it.",
+                "Agreed, decided: 3.8 goes in 2.0.",
+                "+1",
+            ]
+        );
+        for unit in &result.units {
+            assert_eq!(unit.parent_name.as_deref(), Some("Drop Python 3.8?"));
+            assert_eq!(unit.metadata["thread_id"], "root@example.com");
+        }
+        let reply = &result.units[1];
+        assert_eq!(reply.metadata["participants"], "alice@example.com, bob@example.com, dev@lists.example.com");
+        assert_eq!(reply.metadata["timestamp"], "1709550000");
+        assert_eq!(reply.start_line, 14);
+        assert_eq!(reply.qualified_name, "Drop Python 3.8? > reply@example.com");
+    }
+
+    #[test]
+    fn test_eml_is_one_message() {
+        let eml = "From: Dana <dana@example.com>\nSubject: Notes\nContent-Type: text/plain\n\nFrom now on, \
+                   we deploy on Tuesdays.\n\nFrom the ops team\n";
+        let result = parse_email("inbox/notes.eml", eml).unwrap();
+        assert_eq!(result.units.len(), 1);
+        assert_eq!(result.units[0].content, "From now on, we deploy on Tuesdays.\n\nFrom the ops team");
+        assert!(!result.units[0].metadata.contains_key("thread_id"));
+    }
+}
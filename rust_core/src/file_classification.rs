@@ -0,0 +1,144 @@
+/// Whether a file is hand-written, the output of a code generator, or
+/// minified; the latter two rarely hold anything worth remembering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileClass {
+    Source,
+    Generated,
+    Minified,
+}
+
+impl FileClass {
+    /// The value reported in `ParseResult.file_classification`
+    pub fn name(self) -> &'static str {
+        match self {
+            FileClass::Source => "source",
+            FileClass::Generated => "generated",
+            FileClass::Minified => "minified",
+        }
+    }
+}
+
+/// Suffixes of files protoc, Flutter, WinForms and friends write
+const GENERATED_SUFFIXES: &[&str] = &[
+    "_pb2.py",
+    "_pb2.pyi",
+    "_pb2_grpc.py",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    ".pb.swift",
+    ".g.dart",
+    ".freezed.dart",
+    ".designer.cs",
+    ".generated.cs",
+    "_generated.go",
+    ".gen.go",
+];
+
+const MINIFIED_SUFFIXES: &[&str] = &[".min.js", ".min.mjs", ".min.css", "-min.js"];
+
+/// Header comments generators leave, matched case-insensitively
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "code generated by",
+    "generated by the protocol buffer compiler",
+    "auto-generated",
+    "autogenerated",
+    "this file was automatically generated",
+];
+
+/// Lines searched for a generated marker
+const HEADER_LINES: usize = 10;
+
+/// How comment lines start in the languages a header can be written in
+const COMMENT_PREFIXES: &[&str] = &["//", "#", "/*", "*", "--", "<!--", ";", "%"];
+
+/// Bytes at the end of the file searched for a source map reference
+const TAIL_BYTES: usize = 512;
+
+/// Text shorter than this is never called minified
+const MIN_MINIFIED_BYTES: usize = 1024;
+
+/// Average line length above which text is minified
+const MINIFIED_AVERAGE_LINE: usize = 200;
+
+/// Lines at least this long are counted as "single-line" code
+const LONG_LINE: usize = 500;
+
+/// Whether typical minifier output: long lines on average, or nearly all
+/// of the text on a handful of very long lines
+fn looks_minified(source: &str) -> bool {
+    if source.len() < MIN_MINIFIED_BYTES {
+        return false;
+    }
+    let lines: Vec<usize> = source.lines().map(|line| line.trim().len()).filter(|&len| len > 0).collect();
+    let total: usize = lines.iter().sum();
+    let long: usize = lines.iter().filter(|&&len| len >= LONG_LINE).sum();
+    total / lines.len().max(1) > MINIFIED_AVERAGE_LINE || long * 10 > total * 8
+}
+
+/// Classify a file by its name, its header comments, a source map
+/// reference, and the shape of its lines
+pub fn classify(file_path: &str, source: &str) -> FileClass {
+    let name = file_path.to_ascii_lowercase();
+    if MINIFIED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+        return FileClass::Minified;
+    }
+    if looks_minified(source) {
+        return FileClass::Minified;
+    }
+    if GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+        return FileClass::Generated;
+    }
+    // Only the comments the file opens with, so a "do not edit" inside
+    // hand-written code isn't taken for a generator's banner
+    let header = source
+        .lines()
+        .take(HEADER_LINES)
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || COMMENT_PREFIXES.iter().any(|prefix| line.starts_with(prefix)))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase();
+    if GENERATED_MARKERS.iter().any(|marker| header.contains(marker)) {
+        return FileClass::Generated;
+    }
+    // Transpiler output that wasn't minified still points at its source
+    let mut start = source.len().saturating_sub(TAIL_BYTES);
+    while !source.is_char_boundary(start) {
+        start += 1;
+    }
+    let tail = &source[start..];
+    if tail.contains("# sourceMappingURL=") {
+        return FileClass::Generated;
+    }
+    FileClass::Source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_generated_and_minified_files() {
+        assert_eq!(classify("app.py", "def main():\n    # Do not edit lightly\n    pass\n"), FileClass::Source);
+        assert_eq!(classify("dist/vendor.min.js", "var a=1;"), FileClass::Minified);
+        let bundle = format!("!function(){{{}}}();\n//# sourceMappingURL=app.js.map\n", "var a=b+c;".repeat(200));
+        assert_eq!(classify("dist/app.js", &bundle), FileClass::Minified);
+        let transpiled = "\"use strict\";\nexports.x = 1;\n//# sourceMappingURL=x.js.map\n";
+        assert_eq!(classify("lib/x.js", transpiled), FileClass::Generated);
+        assert_eq!(classify("api/user_pb2.py", "import sys\n"), FileClass::Generated);
+        let header = "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n";
+        assert_eq!(classify("api/user.go", header), FileClass::Generated);
+        let python = "#!/usr/bin/env python\n# -*- coding: utf-8 -*-\n# @generated by make_tables.py\nTABLE = {}\n";
+        assert_eq!(classify("tables.py", python), FileClass::Generated);
+    }
+
+    #[test]
+    fn test_classify_multibyte_tail() {
+        // The last 512 bytes start halfway through an "é"
+        let source = format!("x = 1\n{}\n", "é".repeat(300));
+        assert_eq!(classify("notes.py", &source), FileClass::Source);
+    }
+}
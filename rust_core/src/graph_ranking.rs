@@ -0,0 +1,219 @@
+use pyo3::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// A directed edge `(from, to, weight)` between node indices
+pub type WeightedEdge = (usize, usize, f64);
+
+/// Weighted PageRank over nodes `0..node_count`.
+///
+/// `edges` are `(from, to, weight)`; edges touching nodes outside the range
+/// are ignored. Rank from nodes without outgoing edges is spread uniformly,
+/// so scores always sum to 1. Iteration stops early once the total change
+/// drops below 1e-9.
+pub fn pagerank(node_count: usize, edges: &[WeightedEdge], damping: f64, iterations: usize) -> Vec<f64> {
+    if node_count == 0 {
+        return Vec::new();
+    }
+    let uniform = 1.0 / node_count as f64;
+
+    let mut out_weight = vec![0.0; node_count];
+    let edges: Vec<WeightedEdge> = edges
+        .iter()
+        .copied()
+        .filter(|&(from, to, weight)| from < node_count && to < node_count && weight > 0.0)
+        .collect();
+    for &(from, _, weight) in &edges {
+        out_weight[from] += weight;
+    }
+
+    let mut ranks = vec![uniform; node_count];
+    for _ in 0..iterations {
+        let dangling: f64 = (0..node_count).filter(|&n| out_weight[n] == 0.0).map(|n| ranks[n]).sum();
+        let base = (1.0 - damping) * uniform + damping * dangling * uniform;
+        let mut next = vec![base; node_count];
+        for &(from, to, weight) in &edges {
+            next[to] += damping * ranks[from] * weight / out_weight[from];
+        }
+        let delta: f64 = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+        ranks = next;
+        if delta < 1e-9 {
+            break;
+        }
+    }
+    ranks
+}
+
+/// Approximate betweenness centrality over a directed, unweighted graph.
+///
+/// Runs Brandes' algorithm from `samples` source nodes spread evenly over
+/// the node range (all nodes when `samples >= node_count`) and scales the
+/// result by `node_count / samples`, so sampled scores estimate the exact
+/// ones. With `normalized`, scores are divided by `(n - 1)(n - 2)`.
+pub fn betweenness(node_count: usize, edges: &[WeightedEdge], samples: usize, normalized: bool) -> Vec<f64> {
+    let mut scores = vec![0.0; node_count];
+    if node_count < 3 || samples == 0 {
+        return scores;
+    }
+
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+    for &(from, to, _) in edges {
+        if from < node_count && to < node_count && from != to && !adjacency[from].contains(&to) {
+            adjacency[from].push(to);
+        }
+    }
+
+    let samples = samples.min(node_count);
+    let sources = (0..samples).map(|i| i * node_count / samples);
+    for source in sources {
+        // Shortest-path counts and predecessors from a BFS
+        let mut order = Vec::with_capacity(node_count);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+        let mut paths = vec![0.0; node_count];
+        let mut distance: Vec<Option<usize>> = vec![None; node_count];
+        paths[source] = 1.0;
+        distance[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            let next_distance = distance[node].map(|d| d + 1);
+            for &next in &adjacency[node] {
+                if distance[next].is_none() {
+                    distance[next] = next_distance;
+                    queue.push_back(next);
+                }
+                if distance[next] == next_distance {
+                    paths[next] += paths[node];
+                    predecessors[next].push(node);
+                }
+            }
+        }
+
+        // Accumulate dependencies in order of decreasing distance
+        let mut dependency = vec![0.0; node_count];
+        for &node in order.iter().rev() {
+            for &previous in &predecessors[node] {
+                dependency[previous] += paths[previous] / paths[node] * (1.0 + dependency[node]);
+            }
+            if node != source {
+                scores[node] += dependency[node];
+            }
+        }
+    }
+
+    let mut scale = node_count as f64 / samples as f64;
+    if normalized {
+        scale /= ((node_count - 1) * (node_count - 2)) as f64;
+    }
+    scores.iter_mut().for_each(|s| *s *= scale);
+    scores
+}
+
+/// Assign indices to the nodes of a labelled edge list, in order of first
+/// appearance. Missing weights default to 1.
+fn index_edges<'a>(edges: &'a [(String, String)], weights: Option<&[f64]>) -> PyResult<(Vec<String>, Vec<WeightedEdge>)> {
+    if let Some(weights) = weights {
+        if weights.len() != edges.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "weights has {} entries but there are {} edges",
+                weights.len(),
+                edges.len()
+            )));
+        }
+    }
+    let mut nodes: Vec<String> = Vec::new();
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut indexed = Vec::with_capacity(edges.len());
+    for (i, (from, to)) in edges.iter().enumerate() {
+        let mut id = |name: &'a str| {
+            *ids.entry(name).or_insert_with(|| {
+                nodes.push(name.to_string());
+                nodes.len() - 1
+            })
+        };
+        let (from, to) = (id(from), id(to));
+        indexed.push((from, to, weights.map_or(1.0, |w| w[i])));
+    }
+    Ok((nodes, indexed))
+}
+
+/// PageRank over a directed graph given as `(source, target)` edges
+///
+/// `weights` optionally gives one weight per edge. Returns a score per node
+/// (summing to 1); the same implementation ranks files in `build_repo_map`.
+#[pyfunction]
+#[pyo3(name = "pagerank", signature = (edges, damping=0.85, iterations=100, weights=None))]
+pub fn pagerank_scores(
+    edges: Vec<(String, String)>,
+    damping: f64,
+    iterations: usize,
+    weights: Option<Vec<f64>>,
+) -> PyResult<HashMap<String, f64>> {
+    if !(0.0..=1.0).contains(&damping) {
+        return Err(pyo3::exceptions::PyValueError::new_err("damping must be between 0 and 1"));
+    }
+    let (nodes, indexed) = index_edges(&edges, weights.as_deref())?;
+    let ranks = pagerank(nodes.len(), &indexed, damping, iterations);
+    Ok(nodes.into_iter().zip(ranks).collect())
+}
+
+/// Betweenness centrality over a directed graph given as `(source, target)` edges
+///
+/// With `samples` set, shortest paths are computed from that many source
+/// nodes only and scaled up, trading accuracy for speed on large graphs.
+#[pyfunction]
+#[pyo3(name = "betweenness", signature = (edges, samples=None, normalized=true))]
+pub fn betweenness_scores(
+    edges: Vec<(String, String)>,
+    samples: Option<usize>,
+    normalized: bool,
+) -> PyResult<HashMap<String, f64>> {
+    let (nodes, indexed) = index_edges(&edges, None)?;
+    let scores = betweenness(nodes.len(), &indexed, samples.unwrap_or(nodes.len()), normalized);
+    Ok(nodes.into_iter().zip(scores).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagerank_favors_linked_nodes() {
+        // 0 and 1 both point at 2
+        let ranks = pagerank(3, &[(0, 2, 1.0), (1, 2, 1.0)], 0.85, 100);
+        assert!(ranks[2] > ranks[0]);
+        assert!((ranks[0] - ranks[1]).abs() < 1e-12);
+        assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pagerank_ignores_out_of_range_edges() {
+        let ranks = pagerank(2, &[(0, 5, 1.0)], 0.85, 50);
+        assert!((ranks[0] - ranks[1]).abs() < 1e-12);
+        assert!(pagerank(0, &[], 0.85, 10).is_empty());
+    }
+
+    #[test]
+    fn test_betweenness_of_path_center() {
+        // 0 -> 1 -> 2: only node 1 lies between others
+        let scores = betweenness(3, &[(0, 1, 1.0), (1, 2, 1.0)], 3, false);
+        assert_eq!(scores, vec![0.0, 1.0, 0.0]);
+        let normalized = betweenness(3, &[(0, 1, 1.0), (1, 2, 1.0)], 3, true);
+        assert!((normalized[1] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_betweenness_splits_equal_paths() {
+        // Two shortest paths 0 -> {1, 2} -> 3
+        let edges = [(0, 1, 1.0), (0, 2, 1.0), (1, 3, 1.0), (2, 3, 1.0)];
+        let scores = betweenness(4, &edges, 4, false);
+        assert_eq!(scores, vec![0.0, 0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_index_edges_in_first_appearance_order() {
+        let edges = vec![("b".to_string(), "a".to_string()), ("a".to_string(), "c".to_string())];
+        let (nodes, indexed) = index_edges(&edges, Some(&[2.0, 3.0])).unwrap();
+        assert_eq!(nodes, vec!["b", "a", "c"]);
+        assert_eq!(indexed, vec![(0, 1, 2.0), (1, 2, 3.0)]);
+    }
+}
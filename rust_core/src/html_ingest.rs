@@ -0,0 +1,551 @@
+use pyo3::prelude::*;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Elements whose subtrees are never page content
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "form", "button", "input",
+    "select", "textarea", "nav", "header", "footer", "aside", "menu", "dialog",
+];
+
+/// Elements that start a block of their own when rendered
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "pre", "blockquote", "ul", "ol", "li", "dl", "table", "thead", "tbody", "tfoot", "tr", "h1", "h2",
+    "h3", "h4", "h5", "h6", "section", "article", "main", "figure", "hr", "header", "footer", "aside", "nav", "form",
+];
+
+/// Class and id words marking boilerplate: comments, sidebars, share bars...
+const UNLIKELY_NAMES: &[&str] = &[
+    "comment", "sidebar", "footer", "nav", "menu", "share", "social", "advert", "sponsor", "promo", "related",
+    "cookie", "banner", "popup", "modal", "subscribe", "newsletter", "breadcrumb", "pagination", "widget",
+];
+
+/// Class and id words marking the main content
+const LIKELY_NAMES: &[&str] = &["article", "content", "main", "post", "entry", "story", "blog", "text", "body"];
+
+/// Paragraphs shorter than this many characters don't vote for a container
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// A piece of a page, sized to be stored as one memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct PageChunk {
+    #[pyo3(get)]
+    pub heading: Option<String>, // Enclosing headings, e.g. "Install > Linux"
+    #[pyo3(get)]
+    pub text: String,
+}
+
+#[pymethods]
+impl PageChunk {
+    fn __repr__(&self) -> String {
+        format!("PageChunk(heading={:?}, chars={})", self.heading, self.text.chars().count())
+    }
+}
+
+/// The main content of a web page as clean text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct IngestedPage {
+    #[pyo3(get)]
+    pub url: String,
+    #[pyo3(get)]
+    pub title: Option<String>,
+    #[pyo3(get)]
+    pub text: String, // Blocks separated by blank lines; list items as "- item"
+    #[pyo3(get)]
+    pub chunks: Vec<PageChunk>,
+    #[pyo3(get)]
+    pub metadata: HashMap<String, String>, // site_name, description, author, published, language when given
+}
+
+#[pymethods]
+impl IngestedPage {
+    fn __repr__(&self) -> String {
+        format!("IngestedPage(url={}, title={:?}, chunks={})", self.url, self.title, self.chunks.len())
+    }
+}
+
+/// A rendered block of content: a heading (with its level) or a paragraph
+struct Block {
+    level: Option<usize>,
+    text: String,
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some((level - b'0') as usize),
+        _ => None,
+    }
+}
+
+/// +25 for class/id names like "article" or "content", -25 for names like
+/// "sidebar" or "comments" (both may apply)
+fn class_weight(element: ElementRef) -> f64 {
+    let element = element.value();
+    let names = format!("{} {}", element.attr("class").unwrap_or(""), element.id().unwrap_or("")).to_lowercase();
+    let mut weight = 0.0;
+    if UNLIKELY_NAMES.iter().any(|name| names.contains(name)) {
+        weight -= 25.0;
+    }
+    if LIKELY_NAMES.iter().any(|name| names.contains(name)) {
+        weight += 25.0;
+    }
+    weight
+}
+
+/// Whether an element is hidden, navigation or other boilerplate
+fn is_skipped(element: ElementRef) -> bool {
+    let value = element.value();
+    let name = value.name();
+    let hidden = value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || value.attr("style").is_some_and(|style| {
+            let style = style.replace(' ', "").to_lowercase();
+            style.contains("display:none") || style.contains("visibility:hidden")
+        });
+    let boilerplate_role = value
+        .attr("role")
+        .is_some_and(|role| matches!(role, "navigation" | "banner" | "complementary" | "contentinfo" | "dialog"));
+    let containers = matches!(name, "html" | "body" | "article" | "main");
+    SKIPPED_TAGS.contains(&name) || hidden || boilerplate_role || (!containers && class_weight(element) < 0.0)
+}
+
+/// Append the text under `element` to `text`, skipped subtrees left out
+fn push_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(fragment) => text.push_str(fragment),
+            Node::Element(child_element) if child_element.name() == "br" => text.push('\n'),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child).filter(|child| !is_skipped(*child)) {
+                    push_text(child, text);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The visible text of an element with whitespace collapsed
+fn visible_text(element: ElementRef) -> String {
+    let mut text = String::new();
+    push_text(element, &mut text);
+    collapse_whitespace(&text)
+}
+
+/// Characters of visible link text under `element`
+fn link_text_length(element: ElementRef) -> usize {
+    element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| !is_skipped(*child))
+        .map(|child| match child.value().name() {
+            "a" => visible_text(child).chars().count(),
+            _ => link_text_length(child),
+        })
+        .sum()
+}
+
+/// Share of an element's visible text that is link text
+fn link_density(element: ElementRef) -> f64 {
+    let length = visible_text(element).chars().count();
+    if length == 0 {
+        return 0.0;
+    }
+    link_text_length(element) as f64 / length as f64
+}
+
+fn has_block_children(element: ElementRef) -> bool {
+    element.children().filter_map(ElementRef::wrap).any(|child| BLOCK_TAGS.contains(&child.value().name()))
+}
+
+/// Paragraph-like elements under `element`: paragraphs, preformatted text,
+/// table cells, quotes and divs holding only inline content
+fn collect_paragraphs<'a>(element: ElementRef<'a>, paragraphs: &mut Vec<ElementRef<'a>>) {
+    for child in element.children().filter_map(ElementRef::wrap).filter(|child| !is_skipped(*child)) {
+        let name = child.value().name();
+        let is_paragraph =
+            matches!(name, "p" | "pre" | "td" | "blockquote") || (name == "div" && !has_block_children(child));
+        if is_paragraph {
+            paragraphs.push(child);
+        } else {
+            collect_paragraphs(child, paragraphs);
+        }
+    }
+}
+
+/// Starting score of a container, before its paragraphs vote
+fn initial_score(element: ElementRef) -> f64 {
+    let tag_score = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(element)
+}
+
+/// The elements holding the page's main content, in document order:
+/// the best scoring container and those of its siblings that score close
+/// to it or are substantial paragraphs. Each paragraph scores its length
+/// and commas to its parent, half to its grandparent and a third to the
+/// level above; scores are discounted by link density.
+fn main_content(body: ElementRef) -> Vec<ElementRef> {
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(body, &mut paragraphs);
+
+    let mut scores = HashMap::new();
+    let mut candidates = Vec::new();
+    for paragraph in paragraphs {
+        let text = visible_text(paragraph);
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+        for (level, ancestor) in paragraph.ancestors().filter_map(ElementRef::wrap).take(3).enumerate() {
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                initial_score(ancestor)
+            });
+            *entry += score / (level + 1) as f64;
+        }
+    }
+    let final_score =
+        |element: ElementRef| scores.get(&element.id()).map(|score| score * (1.0 - link_density(element)));
+
+    let mut best: Option<(ElementRef, f64)> = None;
+    for candidate in candidates {
+        let score = final_score(candidate).unwrap_or(0.0);
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((candidate, score));
+        }
+    }
+    let Some((best, best_score)) = best else {
+        return vec![body];
+    };
+    let Some(parent) = best.parent().and_then(ElementRef::wrap) else {
+        return vec![best];
+    };
+    let threshold = (best_score * 0.2).max(10.0);
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == best.id() {
+                return true;
+            }
+            if is_skipped(*sibling) {
+                return false;
+            }
+            let substantial_paragraph = sibling.value().name() == "p"
+                && visible_text(*sibling).chars().count() > 80
+                && link_density(*sibling) < 0.25;
+            substantial_paragraph || final_score(*sibling).is_some_and(|score| score >= threshold)
+        })
+        .collect()
+}
+
+/// Render an element's content as blocks: headings, paragraphs, list items
+/// ("- item"), table rows (cells joined by " | ") and preformatted text.
+/// Link lists (menus, tag clouds) are left out.
+fn render(element: ElementRef, blocks: &mut Vec<Block>) {
+    if is_skipped(element) {
+        return;
+    }
+    let name = element.value().name();
+    let mut push = |level, text: String| {
+        if !text.trim().is_empty() {
+            blocks.push(Block { level, text });
+        }
+    };
+    if let Some(level) = heading_level(name) {
+        push(Some(level), visible_text(element));
+        return;
+    }
+    match name {
+        "pre" => push(None, element.text().collect::<String>().trim_end().to_string()),
+        "ul" | "ol" if link_density(element) > 0.5 => {}
+        "tr" => {
+            let cells = element.children().filter_map(ElementRef::wrap);
+            let cells = cells.filter(|cell| matches!(cell.value().name(), "td" | "th")).map(visible_text);
+            push(None, cells.collect::<Vec<_>>().join(" | "));
+        }
+        "li" => {
+            // The item's own text, then any nested lists
+            let mut text = String::new();
+            for child in element.children() {
+                match ElementRef::wrap(child) {
+                    Some(nested) if matches!(nested.value().name(), "ul" | "ol") => {}
+                    Some(inline) if !is_skipped(inline) => push_text(inline, &mut text),
+                    Some(_) => {}
+                    None => {
+                        if let Node::Text(fragment) = child.value() {
+                            text.push_str(fragment);
+                        }
+                    }
+                }
+            }
+            push(None, format!("- {}", collapse_whitespace(&text)));
+            for nested in element.children().filter_map(ElementRef::wrap) {
+                if matches!(nested.value().name(), "ul" | "ol") {
+                    render(nested, blocks);
+                }
+            }
+        }
+        _ if has_block_children(element) => {
+            // Runs of inline content between blocks become paragraphs
+            let mut inline = String::new();
+            for child in element.children() {
+                match ElementRef::wrap(child) {
+                    Some(block) if BLOCK_TAGS.contains(&block.value().name()) => {
+                        blocks.extend(paragraph(&inline));
+                        inline.clear();
+                        render(block, blocks);
+                    }
+                    Some(inline_element) if !is_skipped(inline_element) => push_text(inline_element, &mut inline),
+                    Some(_) => {}
+                    None => {
+                        if let Node::Text(fragment) = child.value() {
+                            inline.push_str(fragment);
+                        }
+                    }
+                }
+            }
+            blocks.extend(paragraph(&inline));
+        }
+        _ => push(None, visible_text(element)),
+    }
+}
+
+fn paragraph(text: &str) -> Option<Block> {
+    let text = collapse_whitespace(text);
+    (!text.is_empty()).then_some(Block { level: None, text })
+}
+
+/// `text` split into pieces of at most `max_chars` characters, at sentence
+/// ends where possible and otherwise at whitespace
+fn split_long(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max_chars {
+        let limit = rest.char_indices().nth(max_chars).map_or(rest.len(), |(i, _)| i);
+        let window = &rest[..limit];
+        let cut = window
+            .rfind(". ")
+            .map(|i| i + 1)
+            .filter(|&i| i > limit / 2)
+            .or_else(|| window.rfind(char::is_whitespace).filter(|&i| i > 0))
+            .unwrap_or(limit);
+        pieces.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Group blocks into chunks of at most `max_chars` characters. A heading
+/// starts a new chunk and is repeated as its first line; each chunk
+/// records the headings it sits under.
+fn chunk_blocks(blocks: &[Block], max_chars: usize) -> Vec<PageChunk> {
+    let mut chunks = Vec::new();
+    let mut headings: Vec<(usize, &str)> = Vec::new();
+    let mut current = String::new();
+    let flush = |current: &mut String, headings: &[(usize, &str)], chunks: &mut Vec<PageChunk>| {
+        if !current.is_empty() {
+            let path = headings.iter().map(|(_, text)| *text).collect::<Vec<_>>().join(" > ");
+            chunks.push(PageChunk { heading: (!path.is_empty()).then_some(path), text: std::mem::take(current) });
+        }
+    };
+    for block in blocks {
+        if let Some(level) = block.level {
+            flush(&mut current, &headings, &mut chunks);
+            while headings.last().is_some_and(|(open, _)| *open >= level) {
+                headings.pop();
+            }
+            headings.push((level, &block.text));
+        }
+        for piece in split_long(&block.text, max_chars) {
+            let length = current.chars().count();
+            if length > 0 && length + 2 + piece.chars().count() > max_chars {
+                flush(&mut current, &headings, &mut chunks);
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(piece);
+        }
+    }
+    flush(&mut current, &headings, &mut chunks);
+    chunks
+}
+
+/// `<meta>` contents by lowercase `property` or `name`, first one winning
+fn meta_tags(document: &Html) -> HashMap<String, String> {
+    let selector = Selector::parse("meta").expect("valid selector");
+    let mut tags = HashMap::new();
+    for meta in document.select(&selector) {
+        let element = meta.value();
+        let (Some(key), Some(content)) = (element.attr("property").or(element.attr("name")), element.attr("content"))
+        else {
+            continue;
+        };
+        tags.entry(key.to_lowercase()).or_insert_with(|| content.trim().to_string());
+    }
+    tags
+}
+
+/// Extract the main content of an HTML page as clean, chunked text
+pub(crate) fn extract_page(html: &str, url: &str, max_chunk_chars: usize) -> IngestedPage {
+    let document = Html::parse_document(html);
+    let meta = meta_tags(&document);
+    let select = |selector: &str| {
+        let selector = Selector::parse(selector).expect("valid selector");
+        document.select(&selector).next()
+    };
+
+    let site_name = meta.get("og:site_name").cloned();
+    let title = meta.get("og:title").cloned().or_else(|| {
+        let title = collapse_whitespace(&select("title")?.text().collect::<String>());
+        // "Article | Site" is the article's title
+        let site = site_name.as_deref().unwrap_or("\u{0}");
+        let title = [" | ", " - ", " — "]
+            .iter()
+            .find_map(|separator| title.strip_suffix(site)?.strip_suffix(separator))
+            .map_or(title.clone(), str::to_string);
+        (!title.is_empty()).then_some(title)
+    });
+
+    let mut blocks = Vec::new();
+    let body = select("body").unwrap_or_else(|| document.root_element());
+    for element in main_content(body) {
+        render(element, &mut blocks);
+    }
+    let title = title.or_else(|| blocks.iter().find(|block| block.level == Some(1)).map(|block| block.text.clone()));
+
+    let mut metadata = HashMap::new();
+    let fields = [
+        ("site_name", "og:site_name"),
+        ("description", "og:description"),
+        ("description", "description"),
+        ("author", "author"),
+        ("author", "article:author"),
+        ("published", "article:published_time"),
+    ];
+    for (field, key) in fields {
+        if let Some(value) = meta.get(key).filter(|value| !value.is_empty()) {
+            metadata.entry(field.to_string()).or_insert_with(|| value.clone());
+        }
+    }
+    if let Some(language) = document.root_element().value().attr("lang") {
+        metadata.insert("language".to_string(), language.to_string());
+    }
+
+    IngestedPage {
+        url: url.to_string(),
+        title,
+        text: blocks.iter().map(|block| block.text.as_str()).collect::<Vec<_>>().join("\n\n"),
+        chunks: chunk_blocks(&blocks, max_chunk_chars),
+        metadata,
+    }
+}
+
+/// Ingest a web page as clean, chunked text for memories
+///
+/// The page's main content is found the way Readability does: paragraphs
+/// vote for the containers holding them, class and id names like
+/// "article" or "sidebar" weigh in, and link-heavy blocks score low.
+/// Navigation, headers, footers, comments, scripts and hidden elements are
+/// dropped.
+///
+/// Args:
+///     html: The page's HTML
+///     url: Where the page came from, kept on the result
+///     max_chunk_chars: Longest chunk in characters; each heading starts a
+///         new chunk and long paragraphs are split at sentence ends
+///
+/// Returns:
+///     IngestedPage with the page title (Open Graph, then `<title>`, then
+///     the first `<h1>`), the content text, its chunks with their enclosing
+///     headings, and metadata from `<meta>` tags
+#[pyfunction]
+#[pyo3(signature = (html, url, max_chunk_chars=1500))]
+pub fn ingest_html(py: Python<'_>, html: &str, url: &str, max_chunk_chars: usize) -> PyResult<IngestedPage> {
+    if max_chunk_chars == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err("max_chunk_chars must be at least 1"));
+    }
+    Ok(py.detach(|| extract_page(html, url, max_chunk_chars)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <title>Tuning the cache | Ops Notes</title>
+  <meta property="og:site_name" content="Ops Notes">
+  <meta name="description" content="How we sized the cache.">
+  <script>var tracking = "Sign up now";</script>
+</head>
+<body>
+  <header><a href="/">Ops Notes</a> <a href="/about">About</a></header>
+  <nav><ul><li><a href="/a">Archive</a></li><li><a href="/b">Tags</a></li></ul></nav>
+  <div class="layout">
+    <div id="post-body" class="article-content">
+      <h1>Tuning the cache</h1>
+      <p>The cache sat in front of the <b>orders</b> service, and its hit rate dropped to 40% after the last deploy.</p>
+      <p>We measured key sizes, eviction counts, and latency percentiles against last week.</p>
+      <h2>Findings</h2>
+      <ul><li>Most evictions came from one tenant</li><li>TTLs were too short</li></ul>
+      <pre>maxmemory 4gb
+maxmemory-policy allkeys-lfu</pre>
+      <div class="share-buttons"><a href="/tw">Share on Twitter</a></div>
+    </div>
+    <div class="sidebar"><p>Subscribe to our newsletter for more posts like this one, every week.</p></div>
+  </div>
+  <div id="comments"><p>Great post, thanks for sharing this with everyone, really useful!</p></div>
+  <footer>Copyright 2024</footer>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_extracts_main_content() {
+        let page = extract_page(ARTICLE, "https://ops.example/cache", 1500);
+        assert_eq!(page.title.as_deref(), Some("Tuning the cache"));
+        assert_eq!(page.metadata["site_name"], "Ops Notes");
+        assert_eq!(page.metadata["description"], "How we sized the cache.");
+        assert_eq!(page.metadata["language"], "en");
+        let expected = "Tuning the cache\n\n\
+            The cache sat in front of the orders service, and its hit rate dropped to 40% after the last deploy.\n\n\
+            We measured key sizes, eviction counts, and latency percentiles against last week.\n\n\
+            Findings\n\n- Most evictions came from one tenant\n\n- TTLs were too short\n\n\
+            maxmemory 4gb\nmaxmemory-policy allkeys-lfu";
+        assert_eq!(page.text, expected);
+        let headings: Vec<Option<&str>> = page.chunks.iter().map(|chunk| chunk.heading.as_deref()).collect();
+        assert_eq!(headings, vec![Some("Tuning the cache"), Some("Tuning the cache > Findings")]);
+        assert!(page.chunks[1].text.starts_with("Findings\n\n- Most evictions"));
+    }
+
+    #[test]
+    fn test_long_paragraphs_are_split_at_sentences() {
+        let sentence = "Each replica keeps its own copy of the index, so reads never wait on the primary. ";
+        let html = format!("<html><body><article><p>{}</p></article></body></html>", sentence.repeat(4));
+        let page = extract_page(&html, "https://example.com", 200);
+        assert_eq!(page.title, None);
+        assert_eq!(page.chunks.len(), 2);
+        assert!(page.chunks.iter().all(|chunk| chunk.text.chars().count() <= 200 && chunk.text.ends_with('.')));
+        assert_eq!(page.chunks[0].heading, None);
+    }
+}
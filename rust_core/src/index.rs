@@ -0,0 +1,448 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::Write;
+
+use crate::dry_run::{ChangePlan, Outcome};
+use crate::simd::{dot, normalized};
+
+/// A node distance, ordered by distance then node index so heaps are total
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    /// Unit length, so cosine distance is `1 - dot`
+    vector: Vec<f32>,
+    /// Neighbor lists, one per layer from 0 up to the node's level
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - dot(a, b)
+}
+
+/// Hierarchical navigable small world graph over cosine distance
+///
+/// Deleted vectors are tombstoned: they keep routing searches but never
+/// appear in results. Re-inserting an id tombstones its previous vector.
+/// Levels are drawn from a seeded generator, so the same insertion order
+/// always builds the same graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct VectorIndex {
+    dimension: usize,
+    m: usize,
+    ef_construction: usize,
+    #[pyo3(get, set)]
+    ef_search: usize,
+    nodes: Vec<Node>,
+    ids: HashMap<String, usize>,
+    entry_point: Option<usize>,
+    rng_state: u64,
+}
+
+impl VectorIndex {
+    pub fn with_params(dimension: usize, m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Self {
+            dimension,
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry_point: None,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// An empty index with the same parameters
+    pub fn empty_like(&self) -> Self {
+        Self::with_params(self.dimension, self.m, self.ef_construction, self.ef_search)
+    }
+
+    /// Live `(id, unit vector)` pairs, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[f32])> {
+        self.ids.iter().map(|(id, &node)| (id.as_str(), self.nodes[node].vector.as_slice()))
+    }
+
+    fn check_dimension(&self, vector: &[f32]) -> Result<(), String> {
+        if vector.len() != self.dimension {
+            return Err(format!("Vector has {} dimensions, index expects {}", vector.len(), self.dimension));
+        }
+        Ok(())
+    }
+
+    /// Uniform draw in (0, 1] from a splitmix64 stream
+    fn next_uniform(&mut self) -> f64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    fn random_level(&mut self) -> usize {
+        let scale = 1.0 / (self.m as f64).ln();
+        (-self.next_uniform().ln() * scale).floor() as usize
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn top_layer(&self) -> usize {
+        self.entry_point.map_or(0, |e| self.nodes[e].neighbors.len() - 1)
+    }
+
+    /// Best-first search of one layer from `entry`, keeping the `ef` closest
+    /// nodes found; returned closest first
+    fn search_layer(&self, query: &[f32], entry: &[usize], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry.iter().copied().collect();
+        let mut frontier: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &node in entry {
+            let candidate = Candidate { distance: distance(query, &self.nodes[node].vector), node };
+            frontier.push(Reverse(candidate));
+            found.push(candidate);
+        }
+        while let Some(Reverse(current)) = frontier.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|worst| current.distance > worst.distance) {
+                break;
+            }
+            for &next in &self.nodes[current.node].neighbors[layer] {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let candidate = Candidate { distance: distance(query, &self.nodes[next].vector), node: next };
+                if found.len() < ef || found.peek().is_some_and(|worst| candidate < *worst) {
+                    frontier.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    /// Neighbor selection heuristic: take candidates closest first, skipping
+    /// any that is closer to an already selected neighbor than to the base,
+    /// which keeps links spread across directions
+    fn select_neighbors(&self, candidates: &[Candidate], limit: usize) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::with_capacity(limit);
+        for candidate in candidates {
+            if selected.len() == limit {
+                break;
+            }
+            let vector = &self.nodes[candidate.node].vector;
+            if selected.iter().all(|&s| distance(vector, &self.nodes[s].vector) > candidate.distance) {
+                selected.push(candidate.node);
+            }
+        }
+        // Top up with the closest skipped candidates so sparse regions stay connected
+        for candidate in candidates {
+            if selected.len() == limit {
+                break;
+            }
+            if !selected.contains(&candidate.node) {
+                selected.push(candidate.node);
+            }
+        }
+        selected
+    }
+
+    fn connect(&mut self, from: usize, to: usize, layer: usize) {
+        self.nodes[from].neighbors[layer].push(to);
+        let limit = self.max_neighbors(layer);
+        if self.nodes[from].neighbors[layer].len() <= limit {
+            return;
+        }
+        let base = &self.nodes[from].vector;
+        let mut candidates: Vec<Candidate> = self.nodes[from].neighbors[layer]
+            .iter()
+            .map(|&node| Candidate { distance: distance(base, &self.nodes[node].vector), node })
+            .collect();
+        candidates.sort();
+        self.nodes[from].neighbors[layer] = self.select_neighbors(&candidates, limit);
+    }
+
+    pub fn insert(&mut self, id: String, vector: &[f32]) -> Result<(), String> {
+        self.check_dimension(vector)?;
+        self.delete(&id);
+
+        let vector = normalized(vector);
+        let level = self.random_level();
+        let node = self.nodes.len();
+        self.nodes.push(Node { id: id.clone(), vector, neighbors: vec![Vec::new(); level + 1], deleted: false });
+        self.ids.insert(id, node);
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            return Ok(());
+        };
+
+        let query = self.nodes[node].vector.clone();
+        let top_layer = self.top_layer();
+        let mut entry = vec![entry_point];
+        for layer in (level + 1..=top_layer).rev() {
+            entry = vec![self.search_layer(&query, &entry, 1, layer)[0].node];
+        }
+        for layer in (0..=level.min(top_layer)).rev() {
+            let candidates = self.search_layer(&query, &entry, self.ef_construction, layer);
+            let neighbors = self.select_neighbors(&candidates, self.m);
+            for &neighbor in &neighbors {
+                self.nodes[node].neighbors[layer].push(neighbor);
+                self.connect(neighbor, node, layer);
+            }
+            entry = candidates.iter().map(|c| c.node).collect();
+        }
+        if level > top_layer {
+            self.entry_point = Some(node);
+        }
+        Ok(())
+    }
+
+    /// Tombstone an id; returns whether it was present
+    pub fn delete(&mut self, id: &str) -> bool {
+        let Some(node) = self.ids.remove(id) else { return false };
+        self.nodes[node].deleted = true;
+        true
+    }
+
+    /// What `delete` would remove: the id if present, with its vector's bytes
+    pub fn plan_delete(&self, id: &str) -> ChangePlan {
+        let ids: Vec<String> = self.ids.contains_key(id).then(|| id.to_string()).into_iter().collect();
+        let bytes = (ids.len() * self.dimension * 4) as u64;
+        ChangePlan::new("delete", ids.clone(), ids.len(), bytes)
+    }
+
+    /// The `k` live vectors nearest to `vector`, as `(id, cosine similarity)`
+    /// pairs, most similar first
+    pub fn search(&self, vector: &[f32], k: usize, ef: Option<usize>) -> Result<Vec<(String, f32)>, String> {
+        self.check_dimension(vector)?;
+        let Some(entry_point) = self.entry_point else { return Ok(Vec::new()) };
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let query = normalized(vector);
+        let mut entry = vec![entry_point];
+        for layer in (1..=self.top_layer()).rev() {
+            entry = vec![self.search_layer(&query, &entry, 1, layer)[0].node];
+        }
+        // Widen the beam (at most twofold) by the tombstone count so deletions
+        // don't starve results
+        let ef = ef.unwrap_or(self.ef_search).max(k);
+        let ef = ef + (self.nodes.len() - self.ids.len()).min(ef);
+        Ok(self
+            .search_layer(&query, &entry, ef, 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.node].deleted)
+            .take(k)
+            .map(|c| (self.nodes[c.node].id.clone(), 1.0 - c.distance))
+            .collect())
+    }
+
+    /// Write the index next to `path` and rename it into place, so a crash
+    /// mid-write leaves the previous index intact
+    pub fn save_to(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        let json = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        let mut file = std::fs::File::create(&tmp_path).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        file.write_all(&json).and_then(|_| file.sync_all()).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        std::fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+    }
+
+    pub fn load_from(path: &str) -> Result<Self, String> {
+        let json = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid vector index {}: {}", path, e))
+    }
+}
+
+#[pymethods]
+impl VectorIndex {
+    /// Empty index for vectors of `dimension`. `m` is the number of links per
+    /// node (twice that on the bottom layer); `ef_construction` and
+    /// `ef_search` are the beam widths used when inserting and searching.
+    #[new]
+    #[pyo3(signature = (dimension, m=16, ef_construction=200, ef_search=50))]
+    fn new(dimension: usize, m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Self::with_params(dimension, m, ef_construction, ef_search)
+    }
+
+    /// Add or replace a vector. Raises ValueError on a dimension mismatch.
+    #[pyo3(name = "insert")]
+    fn py_insert(&mut self, id: String, vector: Vec<f32>) -> PyResult<()> {
+        self.insert(id, &vector).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Add or replace `(id, vector)` pairs in order
+    fn insert_batch(&mut self, items: Vec<(String, Vec<f32>)>) -> PyResult<()> {
+        for (id, vector) in items {
+            self.insert(id, &vector).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        }
+        Ok(())
+    }
+
+    /// Remove a vector; returns whether it was present. With `dry_run`,
+    /// nothing is removed and a ChangePlan of what would be is returned.
+    #[pyo3(name = "delete", signature = (id, dry_run=false))]
+    fn py_delete(&mut self, id: &str, dry_run: bool) -> Outcome<bool> {
+        if dry_run {
+            return Outcome::Planned(self.plan_delete(id));
+        }
+        Outcome::Applied(self.delete(id))
+    }
+
+    /// Approximate `k` nearest neighbors as `(id, cosine similarity)` pairs,
+    /// most similar first. `ef` overrides `ef_search` for this query.
+    #[pyo3(name = "search", signature = (vector, k=10, ef=None))]
+    fn py_search(&self, py: Python<'_>, vector: Vec<f32>, k: usize, ef: Option<usize>) -> PyResult<Vec<(String, f32)>> {
+        py.detach(|| self.search(&vector, k, ef))
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Persist the index as JSON, atomically replacing `path`
+    fn save(&self, path: &str) -> PyResult<()> {
+        self.save_to(path).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    /// Load an index written by `save`
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        Self::load_from(path).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    fn __contains__(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    fn __len__(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("VectorIndex(dimension={}, vectors={}, m={})", self.dimension, self.ids.len(), self.m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random vectors
+    fn vectors(count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                (0..dimension)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn exact(data: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let query = normalized(query);
+        let mut scored: Vec<(usize, f32)> =
+            data.iter().enumerate().map(|(i, v)| (i, distance(&query, &normalized(v)))).collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+        scored.iter().take(k).map(|(i, _)| i.to_string()).collect()
+    }
+
+    fn build(data: &[Vec<f32>]) -> VectorIndex {
+        let mut index = VectorIndex::with_params(data[0].len(), 8, 64, 32);
+        for (i, vector) in data.iter().enumerate() {
+            index.insert(i.to_string(), vector).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_recall_against_exact_search() {
+        let data = vectors(500, 16);
+        let index = build(&data);
+        let mut hits = 0;
+        for query in vectors(20, 16).iter() {
+            let expected = exact(&data, query, 10);
+            let found = index.search(query, 10, None).unwrap();
+            hits += found.iter().filter(|(id, _)| expected.contains(id)).count();
+        }
+        assert!(hits >= 180, "recall {}/200", hits);
+    }
+
+    #[test]
+    fn test_exact_match_ranks_first() {
+        let data = vectors(200, 8);
+        let index = build(&data);
+        let found = index.search(&data[17], 1, None).unwrap();
+        assert_eq!(found[0].0, "17");
+        assert!((found[0].1 - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_delete_and_replace() {
+        let data = vectors(100, 8);
+        let mut index = build(&data);
+        let plan = index.plan_delete("17");
+        assert_eq!((plan.ids, plan.bytes), (vec!["17".to_string()], 32));
+        assert!(matches!(index.py_delete("17", true), Outcome::Planned(_)));
+        assert!(index.delete("17"));
+        assert!(!index.delete("17"));
+        assert_eq!(index.plan_delete("17").count, 0);
+        assert!(index.search(&data[17], 5, None).unwrap().iter().all(|(id, _)| id != "17"));
+        assert_eq!(index.ids.len(), 99);
+
+        index.insert("3".to_string(), &data[17]).unwrap();
+        assert_eq!(index.search(&data[17], 1, None).unwrap()[0].0, "3");
+        assert_eq!(index.ids.len(), 99);
+        assert!(index.insert("x".to_string(), &[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let data = vectors(50, 4);
+        let index = build(&data);
+        let path = std::env::temp_dir().join(format!("vector_index_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        index.save_to(path).unwrap();
+        let loaded = VectorIndex::load_from(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.search(&data[5], 3, None).unwrap(), index.search(&data[5], 3, None).unwrap());
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let index = VectorIndex::with_params(3, 16, 200, 50);
+        assert!(index.search(&[1.0, 0.0, 0.0], 5, None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::index::VectorIndex;
+use crate::keyword_index::KeywordIndex;
+use crate::vector_store::VectorStore;
+
+/// How an auxiliary index compared with its rebuild from the vector store
+#[derive(Debug, Clone, PartialEq)]
+#[pyclass]
+pub struct IndexCheck {
+    #[pyo3(get)]
+    pub index: String, // "ann" or "lexical"
+    #[pyo3(get)]
+    pub old_count: usize,
+    #[pyo3(get)]
+    pub new_count: usize,
+    #[pyo3(get)]
+    pub old_checksum: String,
+    #[pyo3(get)]
+    pub new_checksum: String,
+    #[pyo3(get)]
+    pub repaired: bool, // Whether the old index differed from the rebuild
+}
+
+#[pymethods]
+impl IndexCheck {
+    fn __repr__(&self) -> String {
+        format!(
+            "IndexCheck(index={}, old_count={}, new_count={}, repaired={})",
+            self.index, self.old_count, self.new_count, self.repaired
+        )
+    }
+}
+
+impl IndexCheck {
+    fn new(index: &str, old: (usize, String), new: (usize, String)) -> Self {
+        Self {
+            index: index.to_string(),
+            repaired: old != new,
+            old_count: old.0,
+            new_count: new.0,
+            old_checksum: old.1,
+            new_checksum: new.1,
+        }
+    }
+}
+
+/// Count and order-independent xxh3 checksum of an index's entries
+fn fingerprint(mut entries: Vec<Vec<u8>>) -> (usize, String) {
+    entries.sort();
+    let mut hasher = Xxh3::new();
+    for entry in &entries {
+        hasher.update(&(entry.len() as u64).to_le_bytes());
+        hasher.update(entry);
+    }
+    (entries.len(), format!("{:016x}", hasher.digest()))
+}
+
+/// Fingerprint of an ANN index's ids and vectors
+pub fn ann_fingerprint(index: &VectorIndex) -> (usize, String) {
+    let entries = index.entries().map(|(id, vector)| {
+        let mut entry = id.as_bytes().to_vec();
+        entry.extend(vector.iter().flat_map(|value| value.to_le_bytes()));
+        entry
+    });
+    fingerprint(entries.collect())
+}
+
+/// Fingerprint of a keyword index's ids, terms and lengths
+pub fn lexical_fingerprint(index: &KeywordIndex) -> (usize, String) {
+    let entries = index.documents().map(|(id, terms, length)| {
+        let mut terms = terms.to_vec();
+        terms.sort();
+        format!("{}\0{}\0{}", id, length, terms.join("\0")).into_bytes()
+    });
+    fingerprint(entries.collect())
+}
+
+/// Check a rebuilt index holds exactly the store's ids
+fn verify<'a>(name: &str, store: &VectorStore, ids: impl Iterator<Item = &'a str>) -> Result<(), String> {
+    let mut ids: Vec<&str> = ids.collect();
+    ids.sort_unstable();
+    let mut expected = store.ordered_ids();
+    expected.sort_unstable();
+    if ids != expected {
+        return Err(format!("Rebuilt {} index holds {} ids, store holds {}", name, ids.len(), expected.len()));
+    }
+    Ok(())
+}
+
+/// A fresh ANN index with `old`'s parameters over the store's records, in
+/// write order so the graph is reproducible
+pub fn rebuild_ann(store: &VectorStore, old: &VectorIndex) -> Result<VectorIndex, String> {
+    let mut index = old.empty_like();
+    for id in store.ordered_ids() {
+        let vector = store.vector(&id).expect("live id");
+        index.insert(id, &vector)?;
+    }
+    verify("ann", store, index.entries().map(|(id, _)| id))?;
+    Ok(index)
+}
+
+/// A fresh keyword index with `old`'s parameters over the text of every
+/// stored record; text for ids the store doesn't hold is ignored
+pub fn rebuild_lexical(
+    store: &VectorStore,
+    old: &KeywordIndex,
+    texts: &HashMap<String, String>,
+) -> Result<KeywordIndex, String> {
+    let mut index = old.empty_like();
+    for id in store.ordered_ids() {
+        let text = texts.get(&id).ok_or_else(|| format!("No text for stored id: {}", id))?;
+        index.insert(&id, text);
+    }
+    verify("lexical", store, index.documents().map(|(id, _, _)| id))?;
+    Ok(index)
+}
+
+/// Rebuild auxiliary indexes from the vector store, the primary record
+/// store, and swap them in
+///
+/// Every requested index is rebuilt and verified against the store before
+/// any is replaced, so a failure leaves them all as they were. The old and
+/// new counts and checksums are returned; an index whose checksum changed
+/// had drifted from the store and is now repaired. The core keeps no
+/// bitmap indexes, so there are none to rebuild.
+///
+/// Args:
+///     store: VectorStore holding the primary records
+///     which: Indexes to rebuild, "ann" and/or "lexical" (default: those passed)
+///     ann: VectorIndex to rebuild in place, keeping its parameters
+///     keywords: KeywordIndex to rebuild in place, keeping its parameters
+///     texts: Text of each stored id, required to rebuild "lexical"
+///
+/// Returns:
+///     IndexCheck for each rebuilt index
+#[pyfunction]
+#[pyo3(signature = (store, which=None, ann=None, keywords=None, texts=None))]
+pub fn rebuild_indexes(
+    store: PyRef<'_, VectorStore>,
+    which: Option<Vec<String>>,
+    mut ann: Option<PyRefMut<'_, VectorIndex>>,
+    mut keywords: Option<PyRefMut<'_, KeywordIndex>>,
+    texts: Option<HashMap<String, String>>,
+) -> PyResult<Vec<IndexCheck>> {
+    let which = which.unwrap_or_else(|| {
+        let given = [("ann", ann.is_some()), ("lexical", keywords.is_some())];
+        given.into_iter().filter(|(_, given)| *given).map(|(name, _)| name.to_string()).collect()
+    });
+    let missing = |what: &str| pyo3::exceptions::PyValueError::new_err(format!("Rebuilding needs {}", what));
+
+    let (mut rebuilt_ann, mut rebuilt_lexical) = (None, None);
+    for name in &which {
+        match name.to_ascii_lowercase().as_str() {
+            "ann" => {
+                let old = ann.as_deref().ok_or_else(|| missing("ann"))?;
+                let index = rebuild_ann(&store, old).map_err(pyo3::exceptions::PyValueError::new_err)?;
+                rebuilt_ann = Some(index);
+            }
+            "lexical" => {
+                let old = keywords.as_deref().ok_or_else(|| missing("keywords"))?;
+                let texts = texts.as_ref().ok_or_else(|| missing("texts"))?;
+                let index = rebuild_lexical(&store, old, texts).map_err(pyo3::exceptions::PyValueError::new_err)?;
+                rebuilt_lexical = Some(index);
+            }
+            _ => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!("Unknown index: {}", name)));
+            }
+        }
+    }
+
+    let mut checks = Vec::new();
+    if let (Some(old), Some(index)) = (ann.as_deref_mut(), rebuilt_ann) {
+        checks.push(IndexCheck::new("ann", ann_fingerprint(old), ann_fingerprint(&index)));
+        *old = index;
+    }
+    if let (Some(old), Some(index)) = (keywords.as_deref_mut(), rebuilt_lexical) {
+        checks.push(IndexCheck::new("lexical", lexical_fingerprint(old), lexical_fingerprint(&index)));
+        *old = index;
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuild_repairs_drifted_indexes() {
+        let path = std::env::temp_dir().join(format!("index_rebuild_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut store = VectorStore::open(path, 2, 8).unwrap();
+        let records = [("a", [1.0, 0.0]), ("b", [0.0, 1.0]), ("c", [1.0, 1.0])];
+        store.write(&records.map(|(id, vector)| (id.to_string(), vector.to_vec()))).unwrap();
+        let texts: HashMap<String, String> =
+            [("a", "parse python"), ("b", "tokenize text"), ("c", "merge results")]
+                .into_iter()
+                .map(|(id, text)| (id.to_string(), text.to_string()))
+                .collect();
+
+        // The ANN index lost "c"; the keyword index kept a deleted "ghost"
+        let mut ann = VectorIndex::with_params(2, 4, 16, 8);
+        ann.insert("a".to_string(), &[1.0, 0.0]).unwrap();
+        ann.insert("b".to_string(), &[0.0, 1.0]).unwrap();
+        let mut keywords = KeywordIndex::default();
+        for (id, text) in &texts {
+            keywords.insert(id, text);
+        }
+        keywords.insert("ghost", "stale");
+
+        let rebuilt = rebuild_ann(&store, &ann).unwrap();
+        let check = IndexCheck::new("ann", ann_fingerprint(&ann), ann_fingerprint(&rebuilt));
+        assert!(check.repaired && (check.old_count, check.new_count) == (2, 3));
+        assert_eq!(rebuilt.search(&[1.0, 1.0], 1, None).unwrap()[0].0, "c");
+        assert_eq!(ann_fingerprint(&rebuild_ann(&store, &rebuilt).unwrap()), ann_fingerprint(&rebuilt));
+
+        let rebuilt = rebuild_lexical(&store, &keywords, &texts).unwrap();
+        assert_eq!(lexical_fingerprint(&rebuilt).0, 3);
+        assert!(rebuilt.search("stale", 1).is_empty());
+        let partial: HashMap<String, String> = texts.into_iter().filter(|(id, _)| id != "b").collect();
+        assert!(rebuild_lexical(&store, &keywords, &partial).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+use crate::parsing::{line_starts, ParseResult, SemanticUnit};
+
+/// Separator between a Dockerfile stage and its instructions in qualified names
+const STAGE_SEPARATOR: &str = " > ";
+
+/// Whether a file is a Dockerfile (`Dockerfile`, `Dockerfile.dev`,
+/// `api.dockerfile`, `Containerfile`)
+pub fn is_dockerfile(file_path: &str) -> bool {
+    let name = std::path::Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    matches!(name, "Dockerfile" | "Containerfile")
+        || name.starts_with("Dockerfile.")
+        || name.to_ascii_lowercase().ends_with(".dockerfile")
+}
+
+/// The `#` or `//` comment lines directly above line `row` (0-based), without markers
+fn leading_comments(lines: &[&str], row: usize) -> Option<String> {
+    let comments: Vec<&str> = lines[..row]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with('#') || line.starts_with("//"))
+        .map(|line| line.trim_start_matches(['#', '/']).trim())
+        .collect();
+    let text = comments.into_iter().rev().collect::<Vec<_>>().join("\n");
+    (!text.trim().is_empty()).then(|| text.trim().to_string())
+}
+
+/// End of the HCL construct starting at `from`. For a block (`from` at its
+/// `{`) that is just past the matching `}`; with `to_newline`, an attribute
+/// ends at the first newline outside brackets. Strings (with their `${}`
+/// interpolations), comments and heredocs are skipped.
+fn hcl_scan(source: &str, from: usize, to_newline: bool) -> usize {
+    let bytes = source.as_bytes();
+    let line_end = |at: usize| source[at..].find('\n').map_or(bytes.len(), |n| at + n);
+    // Open brackets, and `"` while inside a string
+    let mut stack: Vec<u8> = Vec::new();
+    let mut i = from;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        if stack.last() == Some(&b'"') {
+            match c {
+                b'\\' => i += 1,
+                b'"' => {
+                    stack.pop();
+                }
+                b'$' | b'%' if next == Some(b'{') => {
+                    stack.push(b'{');
+                    i += 1;
+                }
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        match c {
+            b'"' => stack.push(c),
+            b'{' | b'[' | b'(' => stack.push(c),
+            b'}' | b']' | b')' => {
+                stack.pop();
+                if stack.is_empty() && !to_newline {
+                    return i + 1;
+                }
+            }
+            b'#' => {
+                i = line_end(i);
+                continue;
+            }
+            b'/' if next == Some(b'/') => {
+                i = line_end(i);
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                i = source[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+                continue;
+            }
+            b'<' if next == Some(b'<') => {
+                // Heredoc: `<<EOF` or `<<-EOF`, closed by a line holding only the marker
+                let marker_start = i + 2 + usize::from(bytes.get(i + 2) == Some(&b'-'));
+                let marker: String = source[marker_start..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_')
+                    .collect();
+                if !marker.is_empty() {
+                    let mut at = line_end(i);
+                    while at < bytes.len() {
+                        let end = line_end(at + 1);
+                        if source[at + 1..end].trim() == marker {
+                            i = end;
+                            break;
+                        }
+                        at = end;
+                    }
+                    if at >= bytes.len() {
+                        return bytes.len();
+                    }
+                    continue;
+                }
+            }
+            b'\n' if to_newline && stack.is_empty() => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Parse Terraform (`.tf`) or other HCL into one unit per top-level block.
+///
+/// The unit type is the block type (`resource`, `data`, `module`,
+/// `variable`, `output`, `provider`, `locals`, `terraform`...) and the name
+/// its labels joined by dots (`aws_s3_bucket.logs`), or the block type for
+/// unlabelled blocks. Qualified names are Terraform addresses
+/// (`data.aws_ami.ubuntu`, `module.vpc`, `var.region`). Metadata holds the
+/// `block` type, the `resource_type` of resources and data sources, and
+/// the `source` of modules. Top-level attributes (`.tfvars`-style) are skipped.
+pub fn parse_hcl(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let language = if file_path.ends_with(".tf") { "Terraform" } else { "HCL" };
+    let lines: Vec<&str> = source_code.lines().collect();
+    let line_starts = line_starts(source_code);
+
+    let mut units = Vec::new();
+    let mut i = 0;
+    while i < source_code.len() {
+        let rest = &source_code[i..];
+        let trimmed = rest.trim_start();
+        i += rest.len() - trimmed.len();
+        if trimmed.is_empty() {
+            break;
+        }
+        if trimmed.starts_with('#') || trimmed.starts_with("//") || trimmed.starts_with("/*") {
+            i = hcl_scan(source_code, i, true);
+            continue;
+        }
+
+        let header_end = trimmed.find(['{', '=', '\n']).map_or(source_code.len(), |n| i + n);
+        let header: Vec<&str> = source_code[i..header_end]
+            .split_whitespace()
+            .map(|token| token.trim_matches('"'))
+            .collect();
+        let is_block = source_code.as_bytes().get(header_end) == Some(&b'{')
+            && header.first().is_some_and(|t| t.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-'));
+        if !is_block {
+            i = hcl_scan(source_code, i, true).max(i + 1);
+            continue;
+        }
+
+        let end = hcl_scan(source_code, header_end, false);
+        let (block, labels) = (header[0], &header[1..]);
+        let name = if labels.is_empty() { block.to_string() } else { labels.join(".") };
+        let mut unit = SemanticUnit::spanning(block, name.clone(), source_code, &line_starts, (i, end), language);
+        unit.qualified_name = match block {
+            "resource" => name,
+            "variable" => format!("var.{}", name),
+            _ if labels.is_empty() => name,
+            _ => format!("{}.{}", block, name),
+        };
+        unit.docstring = leading_comments(&lines, line_starts.partition_point(|&line| line <= i) - 1);
+        unit.metadata.insert("block".to_string(), block.to_string());
+        if matches!(block, "resource" | "data") && labels.len() == 2 {
+            unit.metadata.insert("resource_type".to_string(), labels[0].to_string());
+        }
+        if block == "module" {
+            let source = unit.content.lines().skip(1).find_map(|line| {
+                let value = line.trim().strip_prefix("source")?.trim_start().strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            });
+            if let Some(source) = source {
+                unit.metadata.insert("source".to_string(), source);
+            }
+        }
+        units.push(unit);
+        i = end;
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: language.to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings: Vec::new(),
+        file_classification: String::new(),
+    })
+}
+
+/// Heredoc markers opened on an instruction line (`RUN <<EOF`, `COPY <<-"EOT" /a`)
+fn heredoc_markers(line: &str) -> Vec<String> {
+    line.match_indices("<<")
+        .map(|(at, _)| {
+            let marker = line[at + 2..].trim_start_matches('-').trim_start_matches(['"', '\'']);
+            marker.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect::<String>()
+        })
+        .filter(|marker| !marker.is_empty())
+        .collect()
+}
+
+/// Parse a Dockerfile into one "stage" unit per `FROM` and one
+/// "instruction" unit per instruction, grouped under its stage.
+///
+/// A stage runs from its `FROM` to its last instruction and is named after
+/// its alias (`FROM golang:1.22 AS build`), else its image; metadata holds
+/// the `image` and the 0-based `stage` index. Instructions are named by
+/// their keyword (`RUN`, `COPY`...) with the stage as parent, and span line
+/// continuations and heredocs. Instructions before the first `FROM` (global
+/// `ARG`s) have no parent.
+pub fn parse_dockerfile(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let lines: Vec<&str> = source_code.lines().collect();
+    let line_starts = line_starts(source_code);
+    let line_end = |row: usize| line_starts[row] + lines[row].len();
+    // A `# escape=` parser directive at the top replaces the backslash
+    let escape = lines
+        .iter()
+        .take_while(|line| line.trim_start().starts_with('#'))
+        .find_map(|line| line.trim_start_matches('#').trim().strip_prefix("escape="))
+        .and_then(|value| value.trim().chars().next())
+        .unwrap_or('\\');
+
+    let mut units: Vec<SemanticUnit> = Vec::new();
+    let mut stage: Option<usize> = None;
+    let mut stage_count = 0;
+    let mut row = 0;
+    while row < lines.len() {
+        let line = lines[row].trim();
+        if line.is_empty() || line.starts_with('#') {
+            row += 1;
+            continue;
+        }
+        let first = row;
+        while lines[row].trim_end().ends_with(escape) && row + 1 < lines.len() {
+            row += 1;
+        }
+        for marker in heredoc_markers(lines[first]) {
+            while row + 1 < lines.len() && lines[row].trim() != marker {
+                row += 1;
+            }
+        }
+        let span = (line_starts[first], line_end(row));
+        row += 1;
+
+        let keyword = line.split_whitespace().next().unwrap_or("").to_ascii_uppercase();
+        if keyword == "FROM" {
+            let words: Vec<&str> = line.split_whitespace().skip(1).filter(|w| !w.starts_with("--")).collect();
+            let image = words.first().copied().unwrap_or("").to_string();
+            let alias = (words.len() >= 3 && words[1].eq_ignore_ascii_case("as")).then(|| words[2]);
+            let name = alias.unwrap_or(&image).to_string();
+            let mut unit = SemanticUnit::spanning("stage", name, source_code, &line_starts, span, "Dockerfile");
+            unit.docstring = leading_comments(&lines, first);
+            unit.metadata.insert("image".to_string(), image);
+            unit.metadata.insert("stage".to_string(), stage_count.to_string());
+            stage = Some(units.len());
+            stage_count += 1;
+            units.push(unit);
+            continue;
+        }
+
+        let mut unit =
+            SemanticUnit::spanning("instruction", keyword.clone(), source_code, &line_starts, span, "Dockerfile");
+        unit.docstring = leading_comments(&lines, first);
+        unit.metadata.insert("instruction".to_string(), keyword.clone());
+        if let Some(index) = stage {
+            let stage = &mut units[index];
+            // The stage grows to cover each of its instructions
+            stage.end_line = unit.end_line;
+            stage.end_byte = unit.end_byte;
+            stage.content = source_code[stage.start_byte..stage.end_byte].to_string();
+            unit.parent_name = Some(stage.name.clone());
+            unit.qualified_name = format!("{}{}{}", stage.name, STAGE_SEPARATOR, keyword);
+            unit.metadata.insert("stage".to_string(), stage.metadata["stage"].clone());
+        }
+        units.push(unit);
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Dockerfile".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings: Vec::new(),
+        file_classification: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terraform_blocks() {
+        let source = r#"terraform {
+  required_version = ">= 1.5"
+}
+
+# Access logs for every bucket
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs-${var.env}"
+  policy = <<EOF
+{ "Version": "2012-10-17" }
+EOF
+}
+
+data "aws_ami" "ubuntu" {
+  most_recent = true
+}
+
+module "vpc" {
+  source = "terraform-aws-modules/vpc/aws"
+  tags   = { Name = "main" }
+}
+
+variable "env" {}
+"#;
+        let result = parse_hcl("infra/main.tf", source).unwrap();
+        assert_eq!(result.language, "Terraform");
+        let units: Vec<(&str, &str, &str, usize, usize)> = result
+            .units
+            .iter()
+            .map(|u| (u.unit_type.as_str(), u.name.as_str(), u.qualified_name.as_str(), u.start_line, u.end_line))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("terraform", "terraform", "terraform", 1, 3),
+                ("resource", "aws_s3_bucket.logs", "aws_s3_bucket.logs", 6, 11),
+                ("data", "aws_ami.ubuntu", "data.aws_ami.ubuntu", 13, 15),
+                ("module", "vpc", "module.vpc", 17, 20),
+                ("variable", "env", "var.env", 22, 22),
+            ]
+        );
+        assert_eq!(result.units[1].docstring.as_deref(), Some("Access logs for every bucket"));
+        assert_eq!(result.units[1].metadata["resource_type"], "aws_s3_bucket");
+        assert_eq!(result.units[1].signature, "resource \"aws_s3_bucket\" \"logs\"");
+        assert_eq!(result.units[3].metadata["source"], "terraform-aws-modules/vpc/aws");
+    }
+
+    #[test]
+    fn test_dockerfile_stages_group_instructions() {
+        let source = "ARG GO_VERSION=1.22\n\
+            FROM --platform=$BUILDPLATFORM golang:${GO_VERSION} AS build\n\
+            WORKDIR /src\n\
+            RUN go mod download && \\\n    go build -o /app .\n\
+            \n\
+            # Minimal runtime image\n\
+            FROM gcr.io/distroless/static\n\
+            COPY --from=build /app /app\n\
+            COPY <<EOF /etc/app.conf\nport = 8080\nEOF\n\
+            ENTRYPOINT [\"/app\"]\n";
+        let result = parse_dockerfile("Dockerfile", source).unwrap();
+        let units: Vec<(&str, &str, Option<&str>, usize, usize)> = result
+            .units
+            .iter()
+            .map(|u| (u.unit_type.as_str(), u.name.as_str(), u.parent_name.as_deref(), u.start_line, u.end_line))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("instruction", "ARG", None, 1, 1),
+                ("stage", "build", None, 2, 5),
+                ("instruction", "WORKDIR", Some("build"), 3, 3),
+                ("instruction", "RUN", Some("build"), 4, 5),
+                ("stage", "gcr.io/distroless/static", None, 8, 13),
+                ("instruction", "COPY", Some("gcr.io/distroless/static"), 9, 9),
+                ("instruction", "COPY", Some("gcr.io/distroless/static"), 10, 12),
+                ("instruction", "ENTRYPOINT", Some("gcr.io/distroless/static"), 13, 13),
+            ]
+        );
+        assert_eq!(result.units[1].metadata["image"], "golang:${GO_VERSION}");
+        assert_eq!(result.units[4].metadata["stage"], "1");
+        assert_eq!(result.units[4].docstring.as_deref(), Some("Minimal runtime image"));
+        assert_eq!(result.units[3].qualified_name, "build > RUN");
+        assert!(is_dockerfile("services/api/Dockerfile.dev"));
+        assert!(is_dockerfile("api.Dockerfile"));
+        assert!(!is_dockerfile("docs/dockerfile.md"));
+    }
+}
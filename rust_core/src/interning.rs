@@ -0,0 +1,141 @@
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// A shared immutable string, for the few values repeated across millions
+/// of units (`unit_type`, `language`). Equal values share one allocation,
+/// so cloning and creating them never allocates after the first time.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interned(Arc<str>);
+
+/// Every string interned so far; the set stays small (unit types and
+/// language names), so entries are never evicted
+fn table() -> &'static RwLock<HashSet<Arc<str>>> {
+    static TABLE: OnceLock<RwLock<HashSet<Arc<str>>>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+impl Interned {
+    pub fn new(value: &str) -> Self {
+        if let Some(found) = table().read().ok().and_then(|table| table.get(value).cloned()) {
+            return Interned(found);
+        }
+        match table().write() {
+            Ok(mut table) => match table.get(value) {
+                Some(found) => Interned(found.clone()),
+                None => {
+                    let shared: Arc<str> = Arc::from(value);
+                    table.insert(shared.clone());
+                    Interned(shared)
+                }
+            },
+            // A poisoned table still hands out correct, if unshared, strings
+            Err(_) => Interned(Arc::from(value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(value: &str) -> Self {
+        Interned::new(value)
+    }
+}
+
+impl From<String> for Interned {
+    fn from(value: String) -> Self {
+        Interned::new(&value)
+    }
+}
+
+impl PartialEq<str> for Interned {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Interned {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Debug for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        Ok(Interned::new(&value))
+    }
+}
+
+/// Exposed to Python as an interned `str`, shared between units there too
+impl<'py> IntoPyObject<'py> for &Interned {
+    type Target = PyString;
+    type Output = Bound<'py, PyString>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(PyString::intern(py, &self.0))
+    }
+}
+
+impl<'py> IntoPyObject<'py> for Interned {
+    type Target = PyString;
+    type Output = Bound<'py, PyString>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        (&self).into_pyobject(py)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_values_share_one_allocation() {
+        let a = Interned::new("function");
+        let b = Interned::from("function".to_string());
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert_eq!(a, "function");
+        assert_ne!(a, Interned::new("class"));
+
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, "\"function\"");
+        let back: Interned = serde_json::from_str(&json).unwrap();
+        assert!(Arc::ptr_eq(&a.0, &back.0));
+    }
+}
@@ -0,0 +1,381 @@
+use pyo3::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+use crate::dry_run::{ChangePlan, Outcome};
+use crate::query_expansion::split_identifier;
+
+/// Whether `c` is Chinese, Japanese or Korean (Han ideographs, Hiragana,
+/// Katakana, Hangul), which the index splits into bigrams rather than words
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}' // Han extension A
+        | '\u{4E00}'..='\u{9FFF}' // Han
+        | '\u{F900}'..='\u{FAFF}' // Han compatibility
+        | '\u{20000}'..='\u{2FA1F}' // Han extensions B onwards
+        | '\u{1100}'..='\u{11FF}' // Hangul Jamo
+        | '\u{3130}'..='\u{318F}' // Hangul compatibility Jamo
+        | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+    )
+}
+
+/// Overlapping character bigrams of a CJK run (`配置文件` gives `配置`,
+/// `置文`, `文件`), or the run itself when it is a single character.
+/// Bigrams stand in for word segmentation: a query word of two or more
+/// characters shares all its bigrams with any text containing it.
+fn cjk_bigrams(run: &str, terms: &mut Vec<String>) {
+    let chars: Vec<char> = run.chars().collect();
+    if chars.len() == 1 {
+        terms.push(run.to_string());
+    }
+    terms.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+}
+
+/// Latin letters that don't decompose into a base letter and accents
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ø' => "o",
+        'Ø' => "O",
+        'ł' => "l",
+        'Ł' => "L",
+        'đ' | 'ð' => "d",
+        'Đ' | 'Ð' => "D",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ı' => "i",
+        _ => return None,
+    })
+}
+
+/// `text` with Latin letters folded to ASCII: accents dropped (`café`
+/// gives `cafe`) and ligatures and special letters spelled out (`straße`
+/// gives `strasse`). Other scripts are left alone, so folding never turns
+/// a Cyrillic or Hangul letter into a different one.
+pub fn fold_to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            folded.push(c);
+        } else if let Some(spelled) = transliterate(c) {
+            folded.push_str(spelled);
+        } else {
+            let mut base = String::new();
+            decompose_canonical(c, |part| {
+                if !is_combining_mark(part) {
+                    base.push(part);
+                }
+            });
+            if !base.is_empty() && base.is_ascii() {
+                folded.push_str(&base);
+            } else {
+                folded.push(c);
+            }
+        }
+    }
+    Cow::Owned(folded)
+}
+
+/// Lowercased terms of a text, repeated as often as they occur: each
+/// identifier whole, plus its parts when it has several (`parseConfig`
+/// gives `parseconfig`, `parse`, `config`). Chinese, Japanese and Korean
+/// runs become character bigrams, see `cjk_bigrams`.
+pub fn keyword_terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for token in text.split(|c: char| !(c.is_alphanumeric() || c == '_')).filter(|t| !t.is_empty()) {
+        let mut rest = token;
+        while let Some(first) = rest.chars().next() {
+            let cjk = is_cjk(first);
+            let end = rest.find(|c: char| is_cjk(c) != cjk).unwrap_or(rest.len());
+            let (run, next) = rest.split_at(end);
+            rest = next;
+            if cjk {
+                cjk_bigrams(run, &mut terms);
+                continue;
+            }
+            terms.push(run.to_lowercase());
+            let parts = split_identifier(run);
+            if parts.len() > 1 {
+                terms.extend(parts.into_iter().map(str::to_lowercase));
+            }
+        }
+    }
+    terms
+}
+
+/// Inverted index over document text with Okapi BM25 scoring
+///
+/// Each term maps to the documents containing it and how often. Documents
+/// can be added, replaced and removed at any time; collection statistics
+/// (document count, average length) are kept current incrementally.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct KeywordIndex {
+    k1: f32,
+    b: f32,
+    /// Whether text is folded to ASCII before splitting, so `café` matches `cafe`
+    fold_accents: bool,
+    /// Occurrences of each term per document id
+    postings: HashMap<String, HashMap<String, u32>>,
+    /// Distinct terms and term count of each document
+    documents: HashMap<String, (Vec<String>, usize)>,
+    total_length: usize,
+}
+
+impl Default for KeywordIndex {
+    fn default() -> Self {
+        Self::with_params(1.2, 0.75)
+    }
+}
+
+impl KeywordIndex {
+    pub fn with_params(k1: f32, b: f32) -> Self {
+        Self { k1, b, fold_accents: true, postings: HashMap::new(), documents: HashMap::new(), total_length: 0 }
+    }
+
+    /// An empty index analyzing and scoring text the same way
+    pub fn empty_like(&self) -> Self {
+        Self { fold_accents: self.fold_accents, ..Self::with_params(self.k1, self.b) }
+    }
+
+    /// `(id, distinct terms, term count)` of each document, in no particular order
+    pub fn documents(&self) -> impl Iterator<Item = (&str, &[String], usize)> {
+        self.documents.iter().map(|(id, (terms, length))| (id.as_str(), terms.as_slice(), *length))
+    }
+
+    /// Terms of a document or query, as this index analyzes text
+    fn terms(&self, text: &str) -> Vec<String> {
+        if self.fold_accents {
+            keyword_terms(&fold_to_ascii(text))
+        } else {
+            keyword_terms(text)
+        }
+    }
+
+    /// Index `text` under `id`, replacing any previous text
+    pub fn insert(&mut self, id: &str, text: &str) {
+        self.delete(id);
+        let terms = self.terms(text);
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for term in &terms {
+            *counts.entry(term.clone()).or_default() += 1;
+        }
+        for (term, &count) in &counts {
+            self.postings.entry(term.clone()).or_default().insert(id.to_string(), count);
+        }
+        self.total_length += terms.len();
+        self.documents.insert(id.to_string(), (counts.into_keys().collect(), terms.len()));
+    }
+
+    /// Drop a document; returns whether it was indexed
+    pub fn delete(&mut self, id: &str) -> bool {
+        let Some((terms, length)) = self.documents.remove(id) else { return false };
+        for term in terms {
+            if let Some(documents) = self.postings.get_mut(&term) {
+                documents.remove(id);
+                if documents.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        self.total_length -= length;
+        true
+    }
+
+    /// What `delete` would remove: the id if present, with the bytes of
+    /// its distinct terms
+    pub fn plan_delete(&self, id: &str) -> ChangePlan {
+        let Some((terms, _)) = self.documents.get(id) else { return ChangePlan::new("remove", Vec::new(), 0, 0) };
+        let bytes = terms.iter().map(String::len).sum::<usize>() as u64;
+        ChangePlan::new("remove", vec![id.to_string()], 1, bytes)
+    }
+
+    /// The `k` documents scoring highest for `query` as `(id, score)`, best
+    /// first (ties by id). Documents sharing no term with the query are left out.
+    pub fn search(&self, query: &str, k: usize) -> Vec<(String, f32)> {
+        let mut terms = self.terms(query);
+        terms.sort();
+        terms.dedup();
+        let count = self.documents.len() as f32;
+        let average_length = self.total_length as f32 / count.max(1.0);
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+        for documents in terms.iter().filter_map(|term| self.postings.get(term)) {
+            let frequency = documents.len() as f32;
+            let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+            for (id, &occurrences) in documents {
+                let length = self.documents[id].1 as f32;
+                let norm = self.k1 * (1.0 - self.b + self.b * length / average_length.max(f32::EPSILON));
+                let tf = occurrences as f32;
+                *scores.entry(id).or_default() += idf * tf * (self.k1 + 1.0) / (tf + norm);
+            }
+        }
+        let mut scored: Vec<(&str, f32)> = scores.into_iter().collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        scored.into_iter().take(k).map(|(id, score)| (id.to_string(), score)).collect()
+    }
+}
+
+#[pymethods]
+impl KeywordIndex {
+    /// Empty index. `k1` controls term-frequency saturation and `b` length
+    /// normalization (0 for none, 1 for full). With `fold_accents`, accents
+    /// and special Latin letters are folded to ASCII (`café` matches `cafe`,
+    /// `straße` matches `strasse`). Raises ValueError for a negative `k1` or
+    /// `b` outside [0, 1].
+    #[new]
+    #[pyo3(signature = (k1=1.2, b=0.75, fold_accents=true))]
+    fn new(k1: f32, b: f32, fold_accents: bool) -> PyResult<Self> {
+        if !(k1 >= 0.0 && (0.0..=1.0).contains(&b)) {
+            return Err(pyo3::exceptions::PyValueError::new_err("k1 must be >= 0 and b between 0 and 1"));
+        }
+        Ok(Self { fold_accents, ..Self::with_params(k1, b) })
+    }
+
+    /// Add or replace a document
+    fn add(&mut self, id: &str, text: &str) {
+        self.insert(id, text);
+    }
+
+    /// Add or replace `(id, text)` pairs
+    fn add_batch(&mut self, documents: Vec<(String, String)>) {
+        for (id, text) in &documents {
+            self.insert(id, text);
+        }
+    }
+
+    /// Remove a document; returns whether it was present. With `dry_run`,
+    /// nothing is removed and a ChangePlan of what would be is returned.
+    #[pyo3(signature = (id, dry_run=false))]
+    fn remove(&mut self, id: &str, dry_run: bool) -> Outcome<bool> {
+        if dry_run {
+            return Outcome::Planned(self.plan_delete(id));
+        }
+        Outcome::Applied(self.delete(id))
+    }
+
+    /// BM25 search
+    ///
+    /// Query and documents are split into lowercased identifiers and their
+    /// snake/camel case parts, so exact symbol names score highest.
+    /// Chinese, Japanese and Korean text is matched by character bigrams.
+    /// Scores are unbounded; normalize them before fusing with vector
+    /// similarities.
+    ///
+    /// Args:
+    ///     query: Query text
+    ///     k: Maximum number of results
+    ///
+    /// Returns:
+    ///     List of (id, score) tuples, best first
+    #[pyo3(name = "search", signature = (query, k=10))]
+    fn py_search(&self, py: Python<'_>, query: &str, k: usize) -> Vec<(String, f32)> {
+        py.detach(|| self.search(query, k))
+    }
+
+    fn __contains__(&self, id: &str) -> bool {
+        self.documents.contains_key(id)
+    }
+
+    fn __len__(&self) -> usize {
+        self.documents.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "KeywordIndex(documents={}, terms={}, k1={}, b={}, fold_accents={})",
+            self.documents.len(),
+            self.postings.len(),
+            self.k1,
+            self.b,
+            self.fold_accents
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> KeywordIndex {
+        let mut index = KeywordIndex::default();
+        index.insert("config", "def parseConfig(path): load the yaml config file");
+        index.insert("parser", "parse source files into semantic units");
+        index.insert("notes", "weekly meeting notes about the release");
+        index
+    }
+
+    #[test]
+    fn test_terms_keep_identifiers_and_parts() {
+        assert_eq!(keyword_terms("parseConfig(x_y)"), vec!["parseconfig", "parse", "config", "x_y", "x", "y"]);
+    }
+
+    #[test]
+    fn test_cjk_text_matches_by_bigrams() {
+        assert_eq!(
+            keyword_terms("加载配置文件loadConfig"),
+            vec!["加载", "载配", "配置", "置文", "文件", "loadconfig", "load", "config"]
+        );
+        assert_eq!(keyword_terms("設定 を"), vec!["設定", "を"]);
+
+        let mut index = index();
+        index.insert("zh", "读取 YAML 配置文件并解析");
+        index.insert("ko", "설정 파일을 읽습니다");
+        assert_eq!(index.search("配置文件", 10)[0].0, "zh");
+        assert_eq!(index.search("설정 파일", 10)[0].0, "ko");
+        assert!(index.search("数据库", 10).is_empty());
+    }
+
+    #[test]
+    fn test_accents_fold_to_ascii() {
+        assert_eq!(fold_to_ascii("Café naïve Straße Łódź Æsir"), "Cafe naive Strasse Lodz AEsir");
+        assert_eq!(fold_to_ascii("Привет 한국어 日本"), "Привет 한국어 日本");
+        assert!(matches!(fold_to_ascii("plain"), Cow::Borrowed(_)));
+
+        let mut index = index();
+        index.insert("menu", "Crème brûlée at the café");
+        assert_eq!(index.search("creme cafe", 10)[0].0, "menu");
+        assert_eq!(index.search("CAFÉ", 10)[0].0, "menu");
+
+        index.fold_accents = false;
+        index.insert("menu", "Crème brûlée at the café");
+        assert!(index.search("cafe", 10).is_empty());
+    }
+
+    #[test]
+    fn test_exact_identifier_ranks_first() {
+        let index = index();
+        let hits = index.search("parseConfig", 10);
+        assert_eq!(hits[0].0, "config");
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].1 > hits[1].1);
+        assert!(index.search("kubernetes", 10).is_empty());
+        assert_eq!(index.search("parse", 1).len(), 1);
+    }
+
+    #[test]
+    fn test_replace_and_remove_keep_statistics() {
+        let mut index = index();
+        let before = index.search("meeting", 10);
+        index.insert("notes", "release checklist");
+        assert!(index.search("meeting", 10).is_empty());
+        assert!(matches!(index.remove("notes", true), Outcome::Planned(plan) if plan.ids == ["notes"]));
+        assert_eq!(index.plan_delete("missing").count, 0);
+        assert!(index.delete("notes"));
+        assert!(!index.delete("notes"));
+        assert!(!index.postings.contains_key("release"));
+        index.insert("notes", "weekly meeting notes about the release");
+        assert_eq!(index.search("meeting", 10), before);
+    }
+}
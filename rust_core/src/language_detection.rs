@@ -0,0 +1,103 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use whatlang::{Detector, Lang};
+
+/// The natural language a text is most likely written in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct LanguageGuess {
+    #[pyo3(get)]
+    pub language: String, // ISO 639-3 code, e.g. "eng", "deu", "cmn"
+    #[pyo3(get)]
+    pub name: String, // English name, e.g. "German"
+    #[pyo3(get)]
+    pub script: String, // Writing system, e.g. "Latin", "Cyrillic"
+    #[pyo3(get)]
+    pub confidence: f64, // 0.0 to 1.0
+    #[pyo3(get)]
+    pub reliable: bool, // Whether the text was long and distinctive enough to trust the guess
+}
+
+#[pymethods]
+impl LanguageGuess {
+    fn __repr__(&self) -> String {
+        format!("LanguageGuess(language={}, confidence={:.2})", self.language, self.confidence)
+    }
+}
+
+/// Guess the language of `text` by script and trigram frequencies,
+/// considering only `candidates` (ISO 639-3 codes) when given. None for
+/// text with no letters to go on (empty, numbers, symbols).
+pub fn detect_language(text: &str, candidates: Option<&[String]>) -> Result<Option<LanguageGuess>, String> {
+    let detector = match candidates {
+        Some(codes) => {
+            let lang = |code: &String| {
+                Lang::from_code(code.to_ascii_lowercase()).ok_or(format!("Unknown language code: {}", code))
+            };
+            Detector::with_allowlist(codes.iter().map(lang).collect::<Result<Vec<_>, _>>()?)
+        }
+        None => Detector::new(),
+    };
+    Ok(detector.detect(text).map(|info| LanguageGuess {
+        language: info.lang().code().to_string(),
+        name: info.lang().eng_name().to_string(),
+        script: info.script().name().to_string(),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    }))
+}
+
+/// Detect the natural language of a text, e.g. to tag non-English memories,
+/// route them to a multilingual embedding model, or pick a stemmer
+///
+/// Short texts (a few words) are often ambiguous; check `reliable` before
+/// acting on the guess.
+///
+/// Args:
+///     text: Text to classify
+///     candidates: Optional ISO 639-3 codes to choose between, e.g.
+///         ["eng", "deu", "fra"] for the languages a deployment supports
+///
+/// Returns:
+///     The most likely language, or None when the text has no letters.
+///     Raises ValueError for an unknown candidate code.
+#[pyfunction]
+#[pyo3(signature = (text, candidates=None))]
+pub fn detect_natural_language(text: &str, candidates: Option<Vec<String>>) -> PyResult<Option<LanguageGuess>> {
+    detect_language(text, candidates.as_deref()).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(text: &str) -> Option<String> {
+        detect_language(text, None).unwrap().map(|guess| guess.language)
+    }
+
+    #[test]
+    fn test_detects_language_and_script() {
+        let english = "The memory server stores notes about every project and finds them again when you ask.";
+        assert_eq!(language(english).as_deref(), Some("eng"));
+        let german = "Der Speicher legt Notizen zu jedem Projekt ab und findet sie wieder, wenn man danach fragt.";
+        assert_eq!(language(german).as_deref(), Some("deu"));
+
+        let russian = detect_language("Сервер памяти хранит заметки о каждом проекте и находит их снова.", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!((russian.language.as_str(), russian.script.as_str()), ("rus", "Cyrillic"));
+
+        assert_eq!(language(""), None);
+        assert_eq!(language("12345 !!!"), None);
+    }
+
+    #[test]
+    fn test_candidates_limit_the_choice() {
+        let spanish = "El servidor guarda notas sobre cada proyecto y las encuentra de nuevo.";
+        let codes = ["eng".to_string(), "SPA".to_string()];
+        let guess = detect_language(spanish, Some(&codes[..])).unwrap().unwrap();
+        assert_eq!(guess.language, "spa");
+        assert_eq!(guess.name, "Spanish");
+        assert!(detect_language(spanish, Some(&["xx".to_string()])).is_err());
+    }
+}
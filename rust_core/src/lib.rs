@@ -1,7 +1,79 @@
+use ndarray::{Array2, ArrayView1, ArrayView2, Axis};
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
 use pyo3::prelude::*;
+use rayon::prelude::*;
+use simd::cosine;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 mod parsing;
+mod parse_diff;
+mod batch_stream;
+mod build_parsing;
+mod calendar_parsing;
+mod call_graph;
+mod chat_import;
+pub mod cli;
+mod clustering;
+mod code_intel;
 mod config_parsing;
+mod conflict_parsing;
+mod contradiction;
+mod core_config;
+mod coverage_parsing;
+mod custom_languages;
+mod ctags;
+mod dedup;
+mod doc_links;
+mod docstrings;
+mod dry_run;
+mod email_parsing;
+mod diff_parsing;
+mod file_classification;
+mod graph_ranking;
+mod html_ingest;
+mod index;
+mod index_rebuild;
+mod infra_parsing;
+mod interning;
+mod keyword_index;
+mod language_detection;
+mod log_parsing;
+mod lsp;
+mod markdown_parsing;
+mod migrations;
+mod note_import;
+mod notebook_parsing;
+mod pdf_parsing;
+mod quantization;
+mod query_packs;
+mod query_trace;
+mod query_expansion;
+mod relations;
+mod repo_map;
+mod repo_scan;
+mod retrieval;
+mod scheduler;
+mod scopes;
+mod signatures;
+mod source_encoding;
+pub mod simd;
+mod spelling;
+mod sql_parsing;
+mod symbol_index;
+mod template_parsing;
+mod test_report_parsing;
+mod text_cleaning;
+mod token_counting;
+mod transaction;
+mod trace_parsing;
+mod truncation;
+mod vector_store;
+
+/// Each embedding scaled to unit length, in parallel; all-zero embeddings stay zero
+fn normalize_all(embeddings: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    embeddings.par_iter().map(|emb| simd::normalized(emb)).collect()
+}
 
 /// Normalize a batch of embeddings to unit length.
 ///
@@ -11,18 +83,8 @@ mod config_parsing;
 /// Returns:
 ///     List of normalized embedding vectors
 #[pyfunction]
-fn batch_normalize_embeddings(embeddings: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
-    Ok(embeddings
-        .iter()
-        .map(|emb| {
-            let norm: f32 = emb.iter().map(|x| x * x).sum::<f32>().sqrt();
-            if norm > 0.0 {
-                emb.iter().map(|x| x / norm).collect()
-            } else {
-                vec![0.0; emb.len()]
-            }
-        })
-        .collect())
+fn batch_normalize_embeddings(py: Python<'_>, embeddings: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
+    Ok(py.detach(|| normalize_all(&embeddings)))
 }
 
 /// Calculate cosine similarity between two vectors.
@@ -41,16 +103,304 @@ fn cosine_similarity(vec_a: Vec<f32>, vec_b: Vec<f32>) -> PyResult<f32> {
         ));
     }
 
-    let dot_product: f32 = vec_a.iter().zip(vec_b.iter()).map(|(a, b)| a * b).sum();
+    Ok(cosine(&vec_a, &vec_b))
+}
+
+/// The `k` corpus vectors most similar to `query`, as `(index, similarity)`
+/// pairs, most similar first (ties by lower index)
+fn top_k_cosine(query: &[f32], corpus: &[Vec<f32>], k: usize) -> Result<Vec<(usize, f32)>, String> {
+    if let Some(index) = corpus.iter().position(|v| v.len() != query.len()) {
+        return Err(format!(
+            "Corpus vector {} has length {}, query has length {}",
+            index,
+            corpus[index].len(),
+            query.len()
+        ));
+    }
+
+    let mut scores: Vec<(usize, f32)> = corpus
+        .par_iter()
+        .enumerate()
+        .map(|(index, vector)| (index, cosine(query, vector)))
+        .collect();
+    let order = |a: &(usize, f32), b: &(usize, f32)| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0));
+    if k < scores.len() {
+        scores.select_nth_unstable_by(k, order);
+        scores.truncate(k);
+    }
+    scores.sort_by(order);
+    Ok(scores)
+}
+
+/// Find the corpus vectors most similar to a query in one call.
+///
+/// Similarities are computed in parallel.
+///
+/// Args:
+///     query: Query vector
+///     corpus: List of vectors to search, each the query's length
+///     k: Number of results to return
+///
+/// Returns:
+///     List of (index, cosine similarity) pairs, most similar first
+#[pyfunction]
+fn top_k_similar(py: Python<'_>, query: Vec<f32>, corpus: Vec<Vec<f32>>, k: usize) -> PyResult<Vec<(usize, f32)>> {
+    py.detach(|| top_k_cosine(&query, &corpus, k)).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Candidate indices in maximal marginal relevance order: each pick
+/// maximizes `lambda * similarity to the query - (1 - lambda) * highest
+/// similarity to an earlier pick`
+fn mmr_order(query: &[f32], candidates: &[Vec<f32>], lambda: f32, k: usize) -> Result<Vec<usize>, String> {
+    if !(0.0..=1.0).contains(&lambda) {
+        return Err("lambda must be between 0 and 1".to_string());
+    }
+    if let Some(index) = candidates.iter().position(|v| v.len() != query.len()) {
+        return Err(format!(
+            "Candidate {} has length {}, query has length {}",
+            index,
+            candidates[index].len(),
+            query.len()
+        ));
+    }
+    let query = simd::normalized(query);
+    let candidates: Vec<Vec<f32>> = candidates.par_iter().map(|v| simd::normalized(v)).collect();
+    let relevance: Vec<(usize, f32)> =
+        candidates.par_iter().enumerate().map(|(i, v)| (i, simd::dot(&query, v))).collect();
+    let similarity = |a: usize, b: usize| simd::dot(&candidates[a], &candidates[b]);
+    Ok(retrieval::mmr_select(&relevance, similarity, lambda, k)
+        .into_iter()
+        .map(|(index, _)| index)
+        .collect())
+}
+
+/// Diversify candidates with maximal marginal relevance.
+///
+/// Runs the full greedy MMR loop natively: each step picks the candidate
+/// best balancing similarity to the query against similarity to the
+/// candidates already picked.
+///
+/// Args:
+///     query_emb: Query vector
+///     candidate_embs: Candidate vectors, each the query's length
+///     lambda: Trade-off between relevance (1.0) and diversity (0.0)
+///     k: Number of candidates to pick
+///
+/// Returns:
+///     Indices into candidate_embs in pick order
+#[pyfunction]
+#[pyo3(signature = (query_emb, candidate_embs, lambda, k))]
+fn mmr_rerank(
+    py: Python<'_>,
+    query_emb: Vec<f32>,
+    candidate_embs: Vec<Vec<f32>>,
+    lambda: f32,
+    k: usize,
+) -> PyResult<Vec<usize>> {
+    py.detach(|| mmr_order(&query_emb, &candidate_embs, lambda, k))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Reciprocal rank fusion: each id scores `1 / (k + rank)` (rank from 1)
+/// in every ranking it appears in, counting only its first appearance per
+/// ranking. Fused `(id, score)` pairs, best first (ties by id).
+fn reciprocal_rank_fusion(rankings: &[Vec<String>], k: f64) -> Result<Vec<(String, f64)>, String> {
+    if !(k >= 0.0 && k.is_finite()) {
+        return Err("k must be a non-negative number".to_string());
+    }
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for ranking in rankings {
+        let mut seen = HashSet::new();
+        for (rank, id) in ranking.iter().filter(|id| seen.insert(id.as_str())).enumerate() {
+            *scores.entry(id).or_default() += 1.0 / (k + rank as f64 + 1.0);
+        }
+    }
+    let mut fused: Vec<(String, f64)> = scores.into_iter().map(|(id, score)| (id.to_string(), score)).collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(fused)
+}
+
+/// How `normalize_scores` rescales a list of scores
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScoreNormalization {
+    /// Linearly onto [0, 1]
+    MinMax,
+    /// Zero mean, unit (population) standard deviation
+    ZScore,
+    /// Positive and summing to 1
+    Softmax,
+}
+
+impl ScoreNormalization {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('-', "_").as_str() {
+            "min_max" | "minmax" => Some(ScoreNormalization::MinMax),
+            "z_score" | "zscore" => Some(ScoreNormalization::ZScore),
+            "softmax" => Some(ScoreNormalization::Softmax),
+            _ => None,
+        }
+    }
+
+    /// Rescaled `scores`. Constant scores map to 1.0 under min-max and 0.0
+    /// under z-score, since neither range nor deviation can be divided by.
+    fn apply(self, scores: &[f64]) -> Result<Vec<f64>, String> {
+        if scores.iter().any(|s| !s.is_finite()) {
+            return Err("scores must be finite".to_string());
+        }
+        if scores.is_empty() {
+            return Ok(Vec::new());
+        }
+        let count = scores.len() as f64;
+        let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Ok(match self {
+            ScoreNormalization::MinMax => {
+                let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+                let range = max - min;
+                scores.iter().map(|s| if range > 0.0 { (s - min) / range } else { 1.0 }).collect()
+            }
+            ScoreNormalization::ZScore => {
+                let mean = scores.iter().sum::<f64>() / count;
+                let deviation = (scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / count).sqrt();
+                scores.iter().map(|s| if deviation > 0.0 { (s - mean) / deviation } else { 0.0 }).collect()
+            }
+            ScoreNormalization::Softmax => {
+                // Shifted by the maximum so large scores don't overflow
+                let exponentials: Vec<f64> = scores.iter().map(|s| (s - max).exp()).collect();
+                let total: f64 = exponentials.iter().sum();
+                exponentials.into_iter().map(|e| e / total).collect()
+            }
+        })
+    }
+}
+
+/// Fuse several rankings of the same candidates with reciprocal rank fusion.
+///
+/// Only ranks matter, so rankings from incomparable scorers (vector
+/// similarity, BM25, recency) combine without normalization.
+///
+/// Args:
+///     rankings: Lists of ids, each ordered best first
+///     k: Rank offset damping the weight of top ranks (60 is customary)
+///
+/// Returns:
+///     List of (id, fused score) pairs covering every id, best first
+#[pyfunction]
+#[pyo3(signature = (rankings, k=60.0))]
+fn rrf_fuse(rankings: Vec<Vec<String>>, k: f64) -> PyResult<Vec<(String, f64)>> {
+    reciprocal_rank_fusion(&rankings, k).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Rescale scores so different scorers can be blended.
+///
+/// Args:
+///     scores: Scores of one scorer's candidates
+///     method: "min_max" (onto [0, 1]; constant scores become 1.0),
+///         "z_score" (zero mean, unit deviation; constant scores become 0.0)
+///         or "softmax" (positive, summing to 1)
+///
+/// Returns:
+///     Normalized scores in input order
+#[pyfunction]
+#[pyo3(signature = (scores, method="min_max"))]
+fn normalize_scores(scores: Vec<f64>, method: &str) -> PyResult<Vec<f64>> {
+    let method = ScoreNormalization::from_name(method).ok_or_else(|| {
+        pyo3::exceptions::PyValueError::new_err(format!("Unknown normalization method: {}", method))
+    })?;
+    method.apply(&scores).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Rows of a matrix as slices, borrowed from the buffer when each row is
+/// contiguous and copied otherwise (e.g. a transposed or strided array)
+fn matrix_rows<T: Clone>(matrix: ArrayView2<'_, T>) -> Vec<Cow<'_, [T]>> {
+    (0..matrix.nrows())
+        .map(|i| {
+            let row: ArrayView1<'_, T> = matrix.index_axis_move(Axis(0), i);
+            row.to_slice().map_or_else(|| Cow::Owned(row.to_vec()), Cow::Borrowed)
+        })
+        .collect()
+}
 
-    let norm_a: f32 = vec_a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = vec_b.iter().map(|x| x * x).sum::<f32>().sqrt();
+/// Each row scaled to unit length; all-zero rows stay zero
+fn normalize_rows(embeddings: ArrayView2<'_, f32>) -> Array2<f32> {
+    let (count, dimension) = embeddings.dim();
+    let rows = matrix_rows(embeddings);
+    let mut out = vec![0.0; count * dimension];
+    if dimension > 0 {
+        out.par_chunks_mut(dimension)
+            .zip(rows.par_iter())
+            .for_each(|(slot, row)| simd::normalize_into(row, slot));
+    }
+    Array2::from_shape_vec((count, dimension), out).expect("count * dimension elements")
+}
 
-    if norm_a == 0.0 || norm_b == 0.0 {
-        return Ok(0.0);
+/// Cosine similarity of every query row to every corpus row, as a
+/// `(queries, corpus)` matrix
+fn cosine_matrix(queries: ArrayView2<'_, f32>, corpus: ArrayView2<'_, f32>) -> Result<Array2<f32>, String> {
+    if queries.ncols() != corpus.ncols() {
+        return Err(format!(
+            "Queries have {} dimensions, corpus has {}",
+            queries.ncols(),
+            corpus.ncols()
+        ));
+    }
+    let (queries, corpus) = (matrix_rows(queries), matrix_rows(corpus));
+    let corpus_norms: Vec<f32> = corpus.par_iter().map(|v| simd::norm(v)).collect();
+    let mut out = vec![0.0; queries.len() * corpus.len()];
+    if !corpus.is_empty() {
+        out.par_chunks_mut(corpus.len()).zip(queries.par_iter()).for_each(|(slot, query)| {
+            let query_norm = simd::norm(query);
+            for ((similarity, vector), &norm) in slot.iter_mut().zip(&corpus).zip(&corpus_norms) {
+                if query_norm > 0.0 && norm > 0.0 {
+                    *similarity = simd::dot(query, vector) / (query_norm * norm);
+                }
+            }
+        });
     }
+    Ok(Array2::from_shape_vec((queries.len(), corpus.len()), out).expect("queries * corpus elements"))
+}
 
-    Ok(dot_product / (norm_a * norm_b))
+/// Normalize a float32 numpy matrix of embeddings, one per row.
+///
+/// Reads the array's buffer directly instead of converting it to lists,
+/// and returns a new array of the same shape.
+///
+/// Args:
+///     embeddings: 2-D float32 array
+///
+/// Returns:
+///     2-D float32 array of unit-length (or all-zero) rows
+#[pyfunction]
+fn batch_normalize_embeddings_np<'py>(
+    py: Python<'py>,
+    embeddings: PyReadonlyArray2<'py, f32>,
+) -> Bound<'py, PyArray2<f32>> {
+    let embeddings = embeddings.as_array();
+    py.detach(|| normalize_rows(embeddings)).into_pyarray(py)
+}
+
+/// Cosine similarity between every query and every corpus embedding.
+///
+/// Both inputs are float32 numpy matrices read in place; similarities are
+/// computed in parallel straight into the result's buffer.
+///
+/// Args:
+///     queries: 2-D float32 array, one query per row
+///     corpus: 2-D float32 array with the same number of columns
+///
+/// Returns:
+///     2-D float32 array where `[i, j]` is the similarity of query i to
+///     corpus row j (0.0 when either is all zeros)
+#[pyfunction]
+fn similarity_matrix_np<'py>(
+    py: Python<'py>,
+    queries: PyReadonlyArray2<'py, f32>,
+    corpus: PyReadonlyArray2<'py, f32>,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let (queries, corpus) = (queries.as_array(), corpus.as_array());
+    let matrix = py
+        .detach(|| cosine_matrix(queries, corpus))
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    Ok(matrix.into_pyarray(py))
 }
 
 /// Python module for high-performance operations.
@@ -59,12 +409,146 @@ fn mcp_performance_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Embedding operations
     m.add_function(wrap_pyfunction!(batch_normalize_embeddings, m)?)?;
     m.add_function(wrap_pyfunction!(cosine_similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(top_k_similar, m)?)?;
+    m.add_function(wrap_pyfunction!(mmr_rerank, m)?)?;
+    m.add_function(wrap_pyfunction!(rrf_fuse, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_scores, m)?)?;
+    m.add_function(wrap_pyfunction!(clustering::cluster_embeddings, m)?)?;
+    m.add_class::<clustering::Clustering>()?;
+    m.add_function(wrap_pyfunction!(dedup::simhash, m)?)?;
+    m.add_function(wrap_pyfunction!(dedup::find_near_duplicates, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_normalize_embeddings_np, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity_matrix_np, m)?)?;
+    m.add_function(wrap_pyfunction!(quantization::quantize_int8, m)?)?;
+    m.add_function(wrap_pyfunction!(quantization::quantize_binary, m)?)?;
+    m.add_function(wrap_pyfunction!(quantization::hamming_top_k, m)?)?;
+    m.add_function(wrap_pyfunction!(quantization::int8_dot_top_k, m)?)?;
+
+    // Vector index operations
+    m.add_class::<index::VectorIndex>()?;
+    m.add_class::<vector_store::VectorStore>()?;
+    m.add_class::<vector_store::VectorStoreStats>()?;
+    m.add_class::<dry_run::ChangePlan>()?;
+    m.add_class::<keyword_index::KeywordIndex>()?;
+    m.add_class::<transaction::Transaction>()?;
+    m.add_function(wrap_pyfunction!(transaction::begin, m)?)?;
+    m.add_function(wrap_pyfunction!(index_rebuild::rebuild_indexes, m)?)?;
+    m.add_class::<index_rebuild::IndexCheck>()?;
+
+    // Retrieval operations
+    m.add_class::<retrieval::PipelineConfig>()?;
+    m.add_class::<retrieval::RetrievalPipeline>()?;
+    m.add_class::<retrieval::RetrievalHit>()?;
+    m.add_class::<retrieval::ScoringProfile>()?;
+    m.add_class::<retrieval::PipelineStats>()?;
+    m.add_class::<query_trace::QueryTrace>()?;
+    m.add_class::<query_trace::TraceStage>()?;
+    m.add_class::<relations::MemoryRelation>()?;
+    m.add_function(wrap_pyfunction!(contradiction::find_contradiction_candidates, m)?)?;
+    m.add_class::<contradiction::ContradictionCandidate>()?;
+    m.add_function(wrap_pyfunction!(query_expansion::expand_query, m)?)?;
+    m.add_class::<query_expansion::ExpandedQuery>()?;
+    m.add_class::<spelling::SpellCorrector>()?;
+    m.add_class::<spelling::Suggestion>()?;
 
     // Parsing operations
     m.add_function(wrap_pyfunction!(parsing::parse_source_file, m)?)?;
+    m.add_function(wrap_pyfunction!(source_encoding::parse_source_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(source_encoding::detect_source_encoding, m)?)?;
+    m.add_function(wrap_pyfunction!(parsing::parse_source, m)?)?;
+    m.add_function(wrap_pyfunction!(parsing::parse_snippet, m)?)?;
+    m.add_function(wrap_pyfunction!(config_parsing::parse_config_sections, m)?)?;
+    m.add_function(wrap_pyfunction!(custom_languages::register_language, m)?)?;
+    m.add_function(wrap_pyfunction!(query_packs::load_query_pack, m)?)?;
+    m.add_function(wrap_pyfunction!(query_packs::reset_query_packs, m)?)?;
+    m.add_function(wrap_pyfunction!(query_packs::default_query, m)?)?;
+    m.add_function(wrap_pyfunction!(query_packs::register_custom_query, m)?)?;
+    m.add_function(wrap_pyfunction!(query_packs::unregister_custom_query, m)?)?;
     m.add_function(wrap_pyfunction!(parsing::batch_parse_files, m)?)?;
+    m.add_function(wrap_pyfunction!(parsing::batch_parse_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_stream::batch_parse_stream, m)?)?;
+    m.add_class::<batch_stream::ParseProgress>()?;
+    m.add_function(wrap_pyfunction!(parse_diff::diff_parse_results, m)?)?;
+    m.add_class::<parse_diff::ParseDiff>()?;
+    m.add_function(wrap_pyfunction!(repo_scan::scan_repository, m)?)?;
+    m.add_class::<repo_scan::RepositoryScan>()?;
+    m.add_function(wrap_pyfunction!(pdf_parsing::parse_pdf_file, m)?)?;
     m.add_class::<parsing::SemanticUnit>()?;
     m.add_class::<parsing::ParseResult>()?;
+    m.add_class::<symbol_index::SymbolIndex>()?;
+    m.add_class::<symbol_index::OutlineNode>()?;
+    m.add_class::<lsp::LspClient>()?;
+    m.add_class::<lsp::SymbolLocation>()?;
+    m.add_function(wrap_pyfunction!(repo_map::build_repo_map, m)?)?;
+    m.add_function(wrap_pyfunction!(call_graph::parse_call_graph, m)?)?;
+    m.add_class::<call_graph::CallGraph>()?;
+    m.add_class::<call_graph::FunctionCalls>()?;
+    m.add_function(wrap_pyfunction!(doc_links::extract_doc_links, m)?)?;
+    m.add_class::<doc_links::LinkGraph>()?;
+    m.add_class::<doc_links::DocLink>()?;
+    m.add_function(wrap_pyfunction!(note_import::import_obsidian_vault, m)?)?;
+    m.add_function(wrap_pyfunction!(note_import::import_notion_export, m)?)?;
+    m.add_class::<note_import::NoteImport>()?;
+    m.add_class::<note_import::ImportedNote>()?;
+    m.add_function(wrap_pyfunction!(chat_import::import_slack_export, m)?)?;
+    m.add_function(wrap_pyfunction!(chat_import::import_discord_package, m)?)?;
+    m.add_class::<chat_import::ChatImport>()?;
+    m.add_class::<chat_import::ChatThread>()?;
+    m.add_class::<chat_import::ChatMessage>()?;
+    m.add_function(wrap_pyfunction!(html_ingest::ingest_html, m)?)?;
+    m.add_class::<html_ingest::IngestedPage>()?;
+    m.add_class::<html_ingest::PageChunk>()?;
+
+    // Graph operations
+    m.add_function(wrap_pyfunction!(graph_ranking::pagerank_scores, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_ranking::betweenness_scores, m)?)?;
+
+    // Diff operations
+    m.add_function(wrap_pyfunction!(diff_parsing::parse_unified_diff, m)?)?;
+    m.add_class::<diff_parsing::DiffHunk>()?;
+
+    // Stack trace operations
+    m.add_function(wrap_pyfunction!(trace_parsing::parse_stack_trace, m)?)?;
+    m.add_class::<trace_parsing::StackFrame>()?;
+    m.add_class::<trace_parsing::StackTrace>()?;
+
+    // Log operations
+    m.add_function(wrap_pyfunction!(log_parsing::cluster_log_lines, m)?)?;
+    m.add_class::<log_parsing::LogCluster>()?;
+
+    // Test report operations
+    m.add_function(wrap_pyfunction!(test_report_parsing::parse_junit_xml, m)?)?;
+    m.add_function(wrap_pyfunction!(test_report_parsing::parse_pytest_json, m)?)?;
+    m.add_class::<test_report_parsing::TestFailure>()?;
+
+    // Coverage operations
+    m.add_function(wrap_pyfunction!(coverage_parsing::map_coverage_to_units, m)?)?;
+    m.add_class::<coverage_parsing::UnitCoverage>()?;
+
+    // Build graph operations
+    m.add_function(wrap_pyfunction!(build_parsing::extract_build_graph, m)?)?;
+    m.add_class::<build_parsing::BuildEdge>()?;
+
+    // Text operations
+    m.add_function(wrap_pyfunction!(truncation::truncate_to_chars, m)?)?;
+    m.add_function(wrap_pyfunction!(truncation::truncate_to_graphemes, m)?)?;
+    m.add_function(wrap_pyfunction!(truncation::truncate_to_width, m)?)?;
+    m.add_function(wrap_pyfunction!(truncation::grapheme_count, m)?)?;
+    m.add_function(wrap_pyfunction!(truncation::display_width, m)?)?;
+    m.add_function(wrap_pyfunction!(token_counting::count_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(token_counting::batch_count_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(language_detection::detect_natural_language, m)?)?;
+    m.add_class::<language_detection::LanguageGuess>()?;
+    m.add_function(wrap_pyfunction!(text_cleaning::clean_for_embedding, m)?)?;
+
+    // Configuration
+    m.add_class::<core_config::CoreConfig>()?;
+    m.add_function(wrap_pyfunction!(core_config::reload_config, m)?)?;
+    m.add_function(wrap_pyfunction!(core_config::core_config, m)?)?;
+
+    // Maintenance operations
+    m.add_class::<scheduler::Scheduler>()?;
+    m.add_class::<scheduler::JobStatus>()?;
 
     Ok(())
 }
@@ -76,7 +560,7 @@ mod tests {
     #[test]
     fn test_batch_normalize() {
         let input = vec![vec![3.0, 4.0], vec![5.0, 12.0]];
-        let result = batch_normalize_embeddings(input).unwrap();
+        let result = normalize_all(&input);
 
         // First vector: [3, 4] -> norm = 5 -> [0.6, 0.8]
         assert!((result[0][0] - 0.6).abs() < 0.001);
@@ -101,4 +585,171 @@ mod tests {
         let similarity = cosine_similarity(vec_a, vec_b).unwrap();
         assert!((similarity - 0.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_top_k_similar_orders_by_similarity() {
+        let corpus = vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 0.0]];
+        let top = top_k_cosine(&[1.0, 0.0], &corpus, 2).unwrap();
+        assert_eq!(top[0], (1, 1.0));
+        assert_eq!(top[1].0, 2);
+        assert_eq!(top_k_cosine(&[1.0, 0.0], &corpus, 10).unwrap().len(), 4);
+        assert!(top_k_cosine(&[1.0], &corpus, 1).is_err());
+    }
+
+    #[test]
+    fn test_mmr_rerank_skips_near_duplicates() {
+        let candidates = vec![vec![1.0, 0.1], vec![1.0, 0.12], vec![0.6, 0.8], vec![0.0, 1.0]];
+        assert_eq!(mmr_order(&[1.0, 0.0], &candidates, 1.0, 2).unwrap(), vec![0, 1]);
+        assert_eq!(mmr_order(&[1.0, 0.0], &candidates, 0.3, 2).unwrap(), vec![0, 3]);
+        assert_eq!(mmr_order(&[1.0, 0.0], &candidates, 0.5, 10).unwrap().len(), 4);
+        assert!(mmr_order(&[1.0, 0.0], &candidates, 1.5, 2).is_err());
+        assert!(mmr_order(&[1.0], &candidates, 0.5, 2).is_err());
+    }
+
+    #[test]
+    fn test_rrf_fuse() {
+        let ranking = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<String>>();
+        let rankings = vec![ranking(&["a", "b", "c"]), ranking(&["b", "d", "b"]), Vec::new()];
+        let fused = reciprocal_rank_fusion(&rankings, 60.0).unwrap();
+        let ids: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "d", "c"]);
+        assert!((fused[0].1 - (1.0 / 62.0 + 1.0 / 61.0)).abs() < 1e-12);
+        assert!(reciprocal_rank_fusion(&rankings, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_normalize_scores() {
+        let scores = [1.0, 3.0, 2.0];
+        assert_eq!(ScoreNormalization::MinMax.apply(&scores).unwrap(), vec![0.0, 1.0, 0.5]);
+        let z = ScoreNormalization::ZScore.apply(&scores).unwrap();
+        assert!((z[0] + 1.224744871).abs() < 1e-6 && z[2].abs() < 1e-12);
+        let softmax = ScoreNormalization::Softmax.apply(&[1000.0, 1000.0]).unwrap();
+        assert_eq!(softmax, vec![0.5, 0.5]);
+        assert_eq!(ScoreNormalization::MinMax.apply(&[2.0, 2.0]).unwrap(), vec![1.0, 1.0]);
+        assert_eq!(ScoreNormalization::ZScore.apply(&[2.0]).unwrap(), vec![0.0]);
+        assert!(ScoreNormalization::Softmax.apply(&[f64::NAN]).is_err());
+        assert_eq!(ScoreNormalization::from_name("Z-Score"), Some(ScoreNormalization::ZScore));
+        assert_eq!(ScoreNormalization::from_name("l2"), None);
+    }
+
+    #[test]
+    fn test_matrix_kernels_accept_strided_rows() {
+        let embeddings = ndarray::array![[3.0, 5.0], [4.0, 12.0]];
+        // Transposed, so rows are [3, 4] and [5, 12] and not contiguous
+        let normalized = normalize_rows(embeddings.t());
+        assert!((normalized[[0, 0]] - 0.6).abs() < 1e-6);
+        assert!((normalized[[1, 1]] - 12.0 / 13.0).abs() < 1e-6);
+
+        let queries = ndarray::array![[1.0, 0.0], [0.0, 0.0]];
+        let corpus = ndarray::array![[2.0, 0.0], [1.0, 1.0], [0.0, -1.0]];
+        let matrix = cosine_matrix(queries.view(), corpus.view()).unwrap();
+        assert_eq!(matrix.dim(), (2, 3));
+        assert!((matrix[[0, 0]] - 1.0).abs() < 1e-6);
+        assert!((matrix[[0, 1]] - cosine(&[1.0, 0.0], &[1.0, 1.0])).abs() < 1e-6);
+        assert_eq!(matrix.row(1).to_vec(), vec![0.0; 3]);
+        assert!(cosine_matrix(queries.view(), ndarray::array![[1.0]].view()).is_err());
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Embedding-like vectors: bounded components so norms stay finite in f32.
+        fn embedding(dim: std::ops::Range<usize>) -> impl Strategy<Value = Vec<f32>> {
+            prop::collection::vec(-100.0f32..100.0, dim)
+        }
+
+        /// Pairs of vectors with matching dimensionality.
+        fn embedding_pair() -> impl Strategy<Value = (Vec<f32>, Vec<f32>)> {
+            (1usize..64).prop_flat_map(|dim| {
+                (
+                    prop::collection::vec(-100.0f32..100.0, dim),
+                    prop::collection::vec(-100.0f32..100.0, dim),
+                )
+            })
+        }
+
+        fn norm(v: &[f32]) -> f32 {
+            v.iter().map(|x| x * x).sum::<f32>().sqrt()
+        }
+
+        proptest! {
+            #[test]
+            fn normalize_produces_unit_or_zero_vectors(batch in prop::collection::vec(embedding(1..64), 0..16)) {
+                let result = normalize_all(&batch);
+                prop_assert_eq!(result.len(), batch.len());
+                for (input, output) in batch.iter().zip(result.iter()) {
+                    prop_assert_eq!(input.len(), output.len());
+                    if norm(input) > 0.0 {
+                        prop_assert!((norm(output) - 1.0).abs() < 1e-4);
+                    } else {
+                        prop_assert!(output.iter().all(|x| *x == 0.0));
+                    }
+                }
+            }
+
+            #[test]
+            fn normalize_is_idempotent(batch in prop::collection::vec(embedding(1..64), 1..16)) {
+                let once = normalize_all(&batch);
+                let twice = normalize_all(&once);
+                for (a, b) in once.iter().zip(twice.iter()) {
+                    for (x, y) in a.iter().zip(b.iter()) {
+                        prop_assert!((x - y).abs() < 1e-5);
+                    }
+                }
+            }
+
+            #[test]
+            fn cosine_is_symmetric((a, b) in embedding_pair()) {
+                let ab = cosine_similarity(a.clone(), b.clone()).unwrap();
+                let ba = cosine_similarity(b, a).unwrap();
+                prop_assert!((ab - ba).abs() < 1e-6);
+            }
+
+            #[test]
+            fn cosine_is_bounded((a, b) in embedding_pair()) {
+                let sim = cosine_similarity(a, b).unwrap();
+                prop_assert!(sim.is_finite());
+                prop_assert!((-1.0 - 1e-4..=1.0 + 1e-4).contains(&sim));
+            }
+
+            #[test]
+            fn cosine_is_scale_invariant((a, b) in embedding_pair(), scale in 0.01f32..100.0) {
+                let scaled: Vec<f32> = a.iter().map(|x| x * scale).collect();
+                let base = cosine_similarity(a, b.clone()).unwrap();
+                let rescaled = cosine_similarity(scaled, b).unwrap();
+                prop_assert!((base - rescaled).abs() < 1e-3);
+            }
+
+            #[test]
+            fn top_k_matches_pairwise_cosine(
+                (query, corpus) in (1usize..32).prop_flat_map(|dim| (
+                    prop::collection::vec(-100.0f32..100.0, dim),
+                    prop::collection::vec(prop::collection::vec(-100.0f32..100.0, dim), 0..32),
+                )),
+                k in 0usize..40,
+            ) {
+                let top = top_k_cosine(&query, &corpus, k).unwrap();
+                prop_assert_eq!(top.len(), k.min(corpus.len()));
+                for pair in top.windows(2) {
+                    prop_assert!(pair[0].1 >= pair[1].1);
+                }
+                for &(index, score) in &top {
+                    prop_assert_eq!(score, cosine_similarity(query.clone(), corpus[index].clone()).unwrap());
+                }
+                // Nothing left out scores higher than the last result
+                if let Some(&(_, lowest)) = top.last() {
+                    let chosen: Vec<usize> = top.iter().map(|&(i, _)| i).collect();
+                    for (_, vector) in corpus.iter().enumerate().filter(|(i, _)| !chosen.contains(i)) {
+                        prop_assert!(cosine(&query, vector) <= lowest);
+                    }
+                }
+            }
+
+            #[test]
+            fn cosine_rejects_mismatched_lengths(a in embedding(1..32), b in embedding(32..64)) {
+                prop_assert!(cosine_similarity(a, b).is_err());
+            }
+        }
+    }
 }
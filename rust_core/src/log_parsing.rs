@@ -0,0 +1,249 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Placeholder for a token that varies between lines of one cluster
+const WILDCARD: &str = "<*>";
+
+/// A group of log lines sharing one message template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct LogCluster {
+    #[pyo3(get)]
+    pub template: String, // Masked message, e.g. "Fetched <NUM> rows in <NUM>ms"
+    #[pyo3(get)]
+    pub count: usize,
+    #[pyo3(get)]
+    pub level: Option<String>, // Most severe level seen: "error", "warning", "info", "debug", "trace"
+    #[pyo3(get)]
+    pub example: String, // First matching line, unmasked
+    #[pyo3(get)]
+    pub first_line: usize, // 0-based index of `example` in the input
+}
+
+#[pymethods]
+impl LogCluster {
+    fn __repr__(&self) -> String {
+        format!("LogCluster(count={}, level={}, template={})", self.count, self.level.as_deref().unwrap_or("-"), self.template)
+    }
+}
+
+/// Remove ANSI color/style escapes (`\x1b[31m`), common in build output
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Identifiers that vary per line: UUIDs and long hex strings (hashes, addresses)
+fn is_id(token: &str) -> bool {
+    let hex = token.strip_prefix("0x").unwrap_or(token);
+    let hex_digits = hex.chars().filter(|c| *c != '-').count();
+    hex_digits >= 8
+        && hex.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        && hex.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Replace ids with `<ID>` and runs of digits (including `1.5`, `10:42:01`,
+/// `2024-01-02`) with `<NUM>`, keeping surrounding words and punctuation
+fn mask_token(token: &str) -> String {
+    let core = token.trim_matches(|c: char| !c.is_alphanumeric());
+    if !core.is_empty() && is_id(core) {
+        return token.replacen(core, "<ID>", 1);
+    }
+
+    let chars: Vec<char> = token.chars().collect();
+    let mut out = String::with_capacity(token.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_ascii_digit() {
+            // Separators only continue a number when another digit follows
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || (matches!(chars[i], '.' | ':' | '-' | ',' | '_')
+                        && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())))
+            {
+                i += 1;
+            }
+            out.push_str("<NUM>");
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Severity rank of a level keyword (higher is more severe)
+fn level_of(token: &str) -> Option<(u8, &'static str)> {
+    let word = token.trim_matches(|c: char| !c.is_ascii_alphabetic());
+    match word.to_ascii_uppercase().as_str() {
+        "FATAL" | "CRITICAL" | "PANIC" | "ERROR" | "ERR" | "FAILED" | "FAIL" => Some((4, "error")),
+        "WARN" | "WARNING" => Some((3, "warning")),
+        "INFO" => Some((2, "info")),
+        "DEBUG" => Some((1, "debug")),
+        "TRACE" => Some((0, "trace")),
+        _ => None,
+    }
+}
+
+/// Level of a line: the first level keyword among its leading tokens
+/// (`2024-01-02 ERROR ...`, `[warn] ...`, `error[E0308]: ...`)
+fn line_level(tokens: &[String]) -> Option<(u8, &'static str)> {
+    tokens.iter().take(4).find_map(|t| {
+        // `error[E0308]:` style prefixes, then bracketed `[warn]` levels
+        let head = t.split(['[', ':']).next().unwrap_or(t);
+        level_of(head).or_else(|| level_of(t))
+    })
+}
+
+struct Cluster {
+    tokens: Vec<String>,
+    count: usize,
+    level: Option<(u8, &'static str)>,
+    example: String,
+    first_line: usize,
+}
+
+/// Fraction of positions where two equal-length token lists agree
+fn similarity(a: &[String], b: &[String]) -> f64 {
+    let same = a.iter().zip(b).filter(|(x, y)| x == y || *x == WILDCARD).count();
+    same as f64 / a.len().max(1) as f64
+}
+
+/// Template and cluster log lines.
+///
+/// Each line is masked (ids, numbers) and tokenized on whitespace; a line
+/// joins the first cluster with the same token count whose template agrees
+/// on at least `similarity_threshold` of the positions, and disagreeing
+/// positions become `<*>`. Blank lines are skipped. Clusters are returned in
+/// order of first appearance.
+pub fn cluster_lines(lines: &[String], similarity_threshold: f64) -> Vec<LogCluster> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+
+    for (index, raw) in lines.iter().enumerate() {
+        let line = strip_ansi(raw);
+        let tokens: Vec<String> = line.split_whitespace().map(mask_token).collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let level = line_level(&tokens);
+
+        let existing = clusters
+            .iter_mut()
+            .filter(|c| c.tokens.len() == tokens.len())
+            .find(|c| similarity(&c.tokens, &tokens) >= similarity_threshold);
+        match existing {
+            Some(cluster) => {
+                for (template_token, token) in cluster.tokens.iter_mut().zip(&tokens) {
+                    if template_token != token {
+                        *template_token = WILDCARD.to_string();
+                    }
+                }
+                cluster.count += 1;
+                if level.map(|l| l.0) > cluster.level.map(|l| l.0) {
+                    cluster.level = level;
+                }
+            }
+            None => clusters.push(Cluster {
+                tokens,
+                count: 1,
+                level,
+                example: line.trim_end().to_string(),
+                first_line: index,
+            }),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|c| LogCluster {
+            template: c.tokens.join(" "),
+            count: c.count,
+            level: c.level.map(|(_, name)| name.to_string()),
+            example: c.example,
+            first_line: c.first_line,
+        })
+        .collect()
+}
+
+/// Cluster log lines into message templates with counts
+///
+/// Numbers and ids are masked (`<NUM>`, `<ID>`), and lines whose templates
+/// agree on at least `similarity_threshold` of their tokens are merged, with
+/// varying tokens shown as `<*>`. Clusters come back in order of first
+/// appearance, each with a count, its most severe log level, and an example.
+#[pyfunction]
+#[pyo3(signature = (lines, similarity_threshold=0.7))]
+pub fn cluster_log_lines(lines: Vec<String>, similarity_threshold: f64) -> PyResult<Vec<LogCluster>> {
+    if !(0.0..=1.0).contains(&similarity_threshold) {
+        return Err(pyo3::exceptions::PyValueError::new_err("similarity_threshold must be between 0 and 1"));
+    }
+    Ok(cluster_lines(&lines, similarity_threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_mask_token_numbers_and_ids() {
+        assert_eq!(mask_token("12.5ms"), "<NUM>ms");
+        assert_eq!(mask_token("2024-01-02T10:42:01Z"), "<NUM>T<NUM>Z");
+        assert_eq!(mask_token("user=42,"), "user=<NUM>,");
+        assert_eq!(mask_token("(550e8400-e29b-41d4-a716-446655440000)"), "(<ID>)");
+        assert_eq!(mask_token("0xdeadbeef1"), "<ID>");
+        assert_eq!(mask_token("v1"), "v<NUM>");
+        assert_eq!(mask_token("deadline"), "deadline");
+    }
+
+    #[test]
+    fn test_clusters_group_masked_lines() {
+        let log = lines("INFO fetched 10 rows in 3ms\nINFO fetched 250 rows in 41ms\n\nERROR connection to db-1 refused\nINFO fetched 7 rows in 1ms\nERROR connection to db-2 refused\n");
+        let clusters = cluster_lines(&log, 0.7);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].template, "INFO fetched <NUM> rows in <NUM>ms");
+        assert_eq!(clusters[0].count, 3);
+        assert_eq!(clusters[0].level.as_deref(), Some("info"));
+        assert_eq!(clusters[1].template, "ERROR connection to db-<NUM> refused");
+        assert_eq!((clusters[1].count, clusters[1].first_line), (2, 3));
+        assert_eq!(clusters[1].example, "ERROR connection to db-1 refused");
+    }
+
+    #[test]
+    fn test_similar_templates_merge_with_wildcards() {
+        let log = lines("cache miss for key users\ncache miss for key orders\ncache miss for key sessions\n");
+        let clusters = cluster_lines(&log, 0.7);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].template, "cache miss for key <*>");
+        assert_eq!(clusters[0].count, 3);
+
+        // A strict threshold keeps them apart
+        assert_eq!(cluster_lines(&log, 1.0).len(), 3);
+    }
+
+    #[test]
+    fn test_levels_and_ansi_codes() {
+        let log = lines("\x1b[31merror[E0308]\x1b[0m: mismatched types\n[warn] slow query\nplain line\n");
+        let clusters = cluster_lines(&log, 0.7);
+        assert_eq!(clusters[0].example, "error[E0308]: mismatched types");
+        assert_eq!(clusters[0].level.as_deref(), Some("error"));
+        assert_eq!(clusters[1].level.as_deref(), Some("warning"));
+        assert_eq!(clusters[2].level, None);
+    }
+}
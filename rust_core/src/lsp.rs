@@ -0,0 +1,491 @@
+use pyo3::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A position-based query a language server can answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LspRequest {
+    Definition,
+    References,
+}
+
+impl LspRequest {
+    fn method(self) -> &'static str {
+        match self {
+            LspRequest::Definition => "textDocument/definition",
+            LspRequest::References => "textDocument/references",
+        }
+    }
+}
+
+/// Where a symbol is defined or used
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[pyclass]
+pub struct SymbolLocation {
+    #[pyo3(get)]
+    pub path: String, // Relative to the client root when inside it
+    #[pyo3(get)]
+    pub line: usize, // 1-based
+    #[pyo3(get)]
+    pub column: usize, // 0-based UTF-8 byte column
+    #[pyo3(get)]
+    pub end_line: usize,
+    #[pyo3(get)]
+    pub end_column: usize,
+    #[pyo3(get)]
+    pub precise: bool, // Reported by a language server rather than matched by name
+}
+
+#[pymethods]
+impl SymbolLocation {
+    fn __repr__(&self) -> String {
+        format!("SymbolLocation({}:{}:{}, precise={})", self.path, self.line, self.column, self.precise)
+    }
+}
+
+/// LSP `languageId` for a file extension, "plaintext" when unknown
+fn language_id(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "py" | "bzl" => "python",
+        "js" | "mjs" => "javascript",
+        "jsx" => "javascriptreact",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "java" => "java",
+        "go" => "go",
+        "rs" => "rust",
+        "rb" => "ruby",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hxx" | "hh" => "cpp",
+        "cs" => "csharp",
+        "php" => "php",
+        "sql" => "sql",
+        _ => "plaintext",
+    }
+}
+
+/// `file://` URI of an absolute path, percent-encoding all but unreserved
+/// characters and separators
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI, None for other schemes
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = match encoded[i] {
+            b'%' => encoded
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/x` names `C:/x` on Windows
+    let path = match path.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest.to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// UTF-8 byte column of the UTF-16 offset `character` in `line` (LSP's
+/// default position encoding)
+fn byte_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= character {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// UTF-16 offset of the UTF-8 byte column `column` in `line`
+fn utf16_column(line: &str, column: usize) -> usize {
+    line.char_indices().take_while(|&(i, _)| i < column).map(|(_, c)| c.len_utf16()).sum()
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Next `Content-Length`-framed JSON-RPC message, None at end of stream
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':').filter(|(n, _)| n.eq_ignore_ascii_case("content-length")) {
+            length = Some(value.trim().parse::<usize>().map_err(|_| format!("Invalid header: {}", name))?);
+        }
+    }
+    let mut body = vec![0u8; length.ok_or("Message without Content-Length")?];
+    input.read_exact(&mut body).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| format!("Invalid message: {}", e))
+}
+
+/// A location's `(uri, start, end)`, positions as `(line, character)`
+type LocationRange = (String, (usize, usize), (usize, usize));
+
+/// The range of each location in a definition or references result, which
+/// may be null, a Location, or a list of Locations or LocationLinks
+fn result_ranges(result: &Value) -> Vec<LocationRange> {
+    let items: Vec<&Value> = match result {
+        Value::Null => Vec::new(),
+        Value::Array(items) => items.iter().collect(),
+        item => vec![item],
+    };
+    let position = |position: &Value| {
+        let field = |name: &str| position.get(name).and_then(Value::as_u64).map(|n| n as usize);
+        Some((field("line")?, field("character")?))
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let uri = item.get("uri").or_else(|| item.get("targetUri"))?.as_str()?;
+            let range = item.get("range").or_else(|| item.get("targetSelectionRange"))?;
+            Some((uri.to_string(), position(range.get("start")?)?, position(range.get("end")?)?))
+        })
+        .collect()
+}
+
+/// Client for a language server speaking LSP over stdio
+///
+/// The server is started in `root` and initialized on construction. A
+/// background thread reads its messages; each request waits at most
+/// `timeout` for the answer. Files are opened with the server on first use
+/// and re-sent when their text on disk changes. Columns are UTF-8 byte
+/// offsets on both sides and converted from LSP's UTF-16 ones.
+#[pyclass]
+pub struct LspClient {
+    root: PathBuf,
+    child: Child,
+    stdin: ChildStdin,
+    messages: Mutex<Receiver<Result<Value, String>>>,
+    next_id: i64,
+    timeout: Duration,
+    /// Version and text of each opened document, by URI
+    opened: HashMap<String, (i64, String)>,
+}
+
+impl LspClient {
+    pub fn start(command: &[String], root: &str, timeout: Duration) -> Result<Self, String> {
+        let program = command.first().ok_or("Language server command is empty")?;
+        let root = std::fs::canonicalize(root).map_err(|e| format!("Invalid root {}: {}", root, e))?;
+        let mut child = Command::new(program)
+            .args(&command[1..])
+            .current_dir(&root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut reader).transpose() {
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Self {
+            root,
+            child,
+            stdin,
+            messages: Mutex::new(receiver),
+            next_id: 1,
+            timeout,
+            opened: HashMap::new(),
+        };
+        let root_uri = path_to_uri(&client.root);
+        let name = client.root.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        client.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root_uri,
+                "workspaceFolders": [{"uri": root_uri, "name": name}],
+                "capabilities": {"textDocument": {"definition": {"linkSupport": true}, "references": {}}},
+            }),
+        )?;
+        client.notify("initialized", json!({}))?;
+        Ok(client)
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), String> {
+        write_message(&mut self.stdin, &message).map_err(|e| format!("Language server closed its input: {}", e))
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}))
+    }
+
+    /// Send a request and wait for its result, answering requests the
+    /// server makes meanwhile with null and skipping its notifications
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))?;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let message = match self.messages.lock().unwrap().recv_timeout(remaining) {
+                Ok(message) => message?,
+                Err(RecvTimeoutError::Timeout) => return Err(format!("{} timed out", method)),
+                Err(RecvTimeoutError::Disconnected) => return Err("Language server exited".to_string()),
+            };
+            if message.get("method").is_some() {
+                if let Some(request_id) = message.get("id") {
+                    self.send(json!({"jsonrpc": "2.0", "id": request_id, "result": null}))?;
+                }
+                continue;
+            }
+            if message.get("id").and_then(Value::as_i64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let reason = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+                return Err(format!("{} failed: {}", method, reason));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn absolute(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// `path` relative to the root when inside it, else as given
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root).unwrap_or(path).to_string_lossy().into_owned()
+    }
+
+    /// Open `path` with the server, or send its new text if it changed on
+    /// disk since; returns its URI and text
+    fn sync(&mut self, path: &str) -> Result<(String, String), String> {
+        let absolute = self.absolute(path);
+        let text = std::fs::read_to_string(&absolute).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let uri = path_to_uri(&absolute);
+        match self.opened.get(&uri) {
+            Some((_, opened)) if *opened == text => {}
+            Some(&(version, _)) => {
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": {"uri": uri, "version": version + 1},
+                        "contentChanges": [{"text": text}],
+                    }),
+                )?;
+                self.opened.insert(uri.clone(), (version + 1, text.clone()));
+            }
+            None => {
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {"uri": uri, "languageId": language_id(path), "version": 1, "text": text},
+                    }),
+                )?;
+                self.opened.insert(uri.clone(), (1, text.clone()));
+            }
+        }
+        Ok((uri, text))
+    }
+
+    /// Answer `request` at a 1-based line and 0-based byte column of `path`
+    /// (relative to the root, or absolute)
+    pub fn locations(
+        &mut self,
+        request: LspRequest,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) -> Result<Vec<SymbolLocation>, String> {
+        let (uri, text) = self.sync(path)?;
+        let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+        let mut params = json!({
+            "textDocument": {"uri": uri},
+            "position": {"line": line.saturating_sub(1), "character": utf16_column(line_text, column)},
+        });
+        if request == LspRequest::References {
+            params["context"] = json!({"includeDeclaration": true});
+        }
+        let result = self.request(request.method(), params)?;
+
+        let mut lines: HashMap<String, Vec<String>> = HashMap::new();
+        let mut locations = Vec::new();
+        for (uri, start, end) in result_ranges(&result) {
+            let Some(path) = uri_to_path(&uri) else { continue };
+            let file_lines = lines.entry(uri.clone()).or_insert_with(|| {
+                let text = self.opened.get(&uri).map(|(_, t)| t.clone());
+                let text = text.or_else(|| std::fs::read_to_string(&path).ok()).unwrap_or_default();
+                text.lines().map(str::to_string).collect()
+            });
+            let column = |(line, character): (usize, usize)| {
+                file_lines.get(line).map_or(character, |text| byte_column(text, character))
+            };
+            locations.push(SymbolLocation {
+                path: self.relative(&path),
+                line: start.0 + 1,
+                column: column(start),
+                end_line: end.0 + 1,
+                end_column: column(end),
+                precise: true,
+            });
+        }
+        locations.sort();
+        locations.dedup();
+        Ok(locations)
+    }
+
+    /// Ask the server to shut down and exit, then make sure it has
+    pub fn shutdown(&mut self) {
+        if self.child.try_wait().ok().flatten().is_none() {
+            let _ = self.request("shutdown", Value::Null);
+            let _ = self.notify("exit", Value::Null);
+            let deadline = Instant::now() + Duration::from_millis(500);
+            while self.child.try_wait().ok().flatten().is_none() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[pymethods]
+impl LspClient {
+    /// Start a language server (e.g. `["pyright-langserver", "--stdio"]`)
+    /// for the project at `root` and initialize it, waiting up to `timeout`
+    /// seconds per request. Raises RuntimeError if the server cannot be
+    /// started or does not initialize.
+    #[new]
+    #[pyo3(signature = (command, root, timeout=10.0))]
+    fn new(py: Python<'_>, command: Vec<String>, root: &str, timeout: f64) -> PyResult<Self> {
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|_| pyo3::exceptions::PyValueError::new_err("timeout must be a non-negative number of seconds"))?;
+        py.detach(|| Self::start(&command, root, timeout)).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+    }
+
+    /// Where the symbol at a 1-based line and 0-based column of `path` is
+    /// defined. Raises RuntimeError if the server fails or times out.
+    fn definition(&mut self, py: Python<'_>, path: &str, line: usize, column: usize) -> PyResult<Vec<SymbolLocation>> {
+        py.detach(|| self.locations(LspRequest::Definition, path, line, column))
+            .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+    }
+
+    /// Every use of the symbol at a 1-based line and 0-based column of
+    /// `path`, declaration included. Raises RuntimeError if the server
+    /// fails or times out.
+    fn references(&mut self, py: Python<'_>, path: &str, line: usize, column: usize) -> PyResult<Vec<SymbolLocation>> {
+        py.detach(|| self.locations(LspRequest::References, path, line, column))
+            .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+    }
+
+    /// Shut the server down; the client is unusable afterwards
+    fn close(&mut self, py: Python<'_>) {
+        py.detach(|| self.shutdown());
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "LspClient(root={}, pid={}, open_documents={})",
+            self.root.display(),
+            self.child.id(),
+            self.opened.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_message_framing_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({"id": 1, "result": "é"})).unwrap();
+        write_message(&mut buffer, &json!({"method": "exit"})).unwrap();
+        let mut input = Cursor::new(buffer);
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({"id": 1, "result": "é"})));
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({"method": "exit"})));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+        assert!(read_message(&mut Cursor::new(b"X-Other: 1\r\n\r\n{}".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_uris_and_columns() {
+        let path = Path::new("/src/my project/ü.py");
+        assert_eq!(path_to_uri(path), "file:///src/my%20project/%C3%BC.py");
+        assert_eq!(uri_to_path(&path_to_uri(path)).unwrap(), path);
+        assert!(uri_to_path("untitled:1").is_none());
+        // "é" is two UTF-8 bytes but one UTF-16 unit; "𝄞" is four and two
+        let line = "é𝄞x";
+        assert_eq!(utf16_column(line, 6), 3);
+        assert_eq!(byte_column(line, 3), 6);
+        assert_eq!(byte_column(line, 99), line.len());
+    }
+
+    #[test]
+    fn test_result_shapes() {
+        let range = json!({"start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 9}});
+        let location = json!({"uri": "file:///a.py", "range": range});
+        let link = json!({"targetUri": "file:///b.py", "targetRange": range, "targetSelectionRange": range});
+        assert!(result_ranges(&Value::Null).is_empty());
+        assert_eq!(result_ranges(&location), vec![("file:///a.py".to_string(), (2, 4), (2, 9))]);
+        let ranges = result_ranges(&json!([location, link]));
+        assert_eq!(ranges[1].0, "file:///b.py");
+        assert_eq!(language_id("src/app.tsx"), "typescriptreact");
+    }
+}
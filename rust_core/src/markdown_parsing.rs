@@ -0,0 +1,280 @@
+use pulldown_cmark::{CodeBlockKind, Event, MetadataBlockKind, Options, Parser, Tag, TagEnd};
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::ops::Range;
+use toml::Value as TomlValue;
+
+use crate::parsing::{line_starts, ParseResult, SemanticUnit};
+
+/// Separator between headings in a section's qualified name
+const PATH_SEPARATOR: &str = " > ";
+
+/// A heading whose section is still open: later headings of a deeper
+/// level nest inside it
+struct OpenSection {
+    level: usize,
+    unit: usize,
+}
+
+/// Parse a Markdown document into one "section" unit per heading and one
+/// "code_block" unit per fenced code block.
+///
+/// A section runs from its heading to the next heading of the same or a
+/// higher level, so it contains its subsections; `parent_name` is the
+/// enclosing heading. Code blocks record their fence language in
+/// `metadata["fence_language"]` and are named after it ("code" without one).
+/// Headings inside code blocks are not sections.
+///
+/// The `title`, `tags` and `date` of a YAML (`---`) or TOML (`+++`)
+/// frontmatter block are copied into every unit's metadata, tags joined
+/// with commas; unparseable frontmatter is reported as a warning.
+pub fn parse_markdown(file_path: &str, source_code: &str) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let line_starts = line_starts(source_code);
+    let line_of = |byte: usize| line_starts.partition_point(|&start| start <= byte);
+
+    let mut units: Vec<SemanticUnit> = Vec::new();
+    let mut open: Vec<OpenSection> = Vec::new();
+    let mut heading: Option<(usize, Range<usize>, String)> = None;
+    let mut frontmatter_block: Option<String> = None;
+    let mut frontmatter = HashMap::new();
+    let mut warnings = Vec::new();
+
+    let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    for (event, range) in Parser::new_ext(source_code, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => heading = Some((level as usize, range, String::new())),
+            Event::Start(Tag::MetadataBlock(_)) => frontmatter_block = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(block) = frontmatter_block.as_mut() {
+                    block.push_str(&text);
+                } else if let Some((_, _, name)) = heading.as_mut() {
+                    name.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::MetadataBlock(kind)) => {
+                let block = frontmatter_block.take().unwrap_or_default();
+                match frontmatter_metadata(&block, kind) {
+                    Ok(metadata) => frontmatter = metadata,
+                    Err(e) => warnings.push(format!("Invalid frontmatter: {}", e)),
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, range, name)) = heading.take() else { continue };
+                while open.last().is_some_and(|section| section.level >= level) {
+                    if let Some(section) = open.pop() {
+                        close(&mut units[section.unit], source_code, range.start, &line_of);
+                    }
+                }
+                let parent = open.last().map(|section| &units[section.unit]);
+                let name = name.trim().to_string();
+                let mut metadata = HashMap::new();
+                metadata.insert("level".to_string(), level.to_string());
+                // Spans only the heading until `close` extends it
+                let section = unit("section", name, parent, source_code, range, &line_starts, metadata);
+                open.push(OpenSection { level, unit: units.len() });
+                units.push(section);
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                // Info strings may carry attributes after the language (`rust,ignore`, `python {.lineno}`)
+                let fence_language = info.split(|c: char| c.is_whitespace() || c == ',' || c == '{').next();
+                let fence_language = fence_language.unwrap_or("");
+                let mut metadata = HashMap::new();
+                if !fence_language.is_empty() {
+                    metadata.insert("fence_language".to_string(), fence_language.to_string());
+                }
+                let name = if fence_language.is_empty() { "code" } else { fence_language }.to_string();
+                let parent = open.last().map(|section| &units[section.unit]);
+                let block = unit("code_block", name, parent, source_code, range, &line_starts, metadata);
+                units.push(block);
+            }
+            _ => {}
+        }
+    }
+    for section in open {
+        close(&mut units[section.unit], source_code, source_code.len(), &line_of);
+    }
+    for unit in &mut units {
+        unit.metadata.extend(frontmatter.clone());
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Markdown".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+fn yaml_text(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::String(text) => Some(text.clone()),
+        YamlValue::Number(number) => Some(number.to_string()),
+        YamlValue::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+fn toml_text(value: &TomlValue) -> Option<String> {
+    match value {
+        TomlValue::String(text) => Some(text.clone()),
+        TomlValue::Datetime(datetime) => Some(datetime.to_string()),
+        TomlValue::Integer(_) | TomlValue::Float(_) | TomlValue::Boolean(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Top-level fields of a frontmatter block as text, lists item by item
+pub(crate) fn frontmatter_fields(block: &str, kind: MetadataBlockKind) -> Result<HashMap<String, Vec<String>>, String> {
+    match kind {
+        MetadataBlockKind::YamlStyle => {
+            let YamlValue::Mapping(map) = serde_yaml::from_str::<YamlValue>(block).map_err(|e| e.to_string())? else {
+                return Ok(HashMap::new());
+            };
+            Ok(map
+                .iter()
+                .filter_map(|(key, value)| {
+                    let items = match value {
+                        YamlValue::Sequence(items) => items.iter().filter_map(yaml_text).collect(),
+                        value => yaml_text(value).into_iter().collect(),
+                    };
+                    Some((key.as_str()?.to_string(), items))
+                })
+                .collect())
+        }
+        MetadataBlockKind::PlusesStyle => {
+            let table: toml::Table = block.parse().map_err(|e: toml::de::Error| e.to_string())?;
+            Ok(table
+                .iter()
+                .map(|(key, value)| {
+                    let items = match value {
+                        TomlValue::Array(items) => items.iter().filter_map(toml_text).collect(),
+                        value => toml_text(value).into_iter().collect(),
+                    };
+                    (key.clone(), items)
+                })
+                .collect())
+        }
+    }
+}
+
+/// The title, date and tags of a frontmatter block as unit metadata. Tags
+/// may be a list or a comma-separated string.
+fn frontmatter_metadata(block: &str, kind: MetadataBlockKind) -> Result<HashMap<String, String>, String> {
+    let fields = frontmatter_fields(block, kind)?;
+    let mut metadata = HashMap::new();
+    for field in ["title", "date"] {
+        if let Some(text) = fields.get(field).and_then(|items| items.first()) {
+            metadata.insert(field.to_string(), text.trim().to_string());
+        }
+    }
+    let tags: Vec<&str> = fields
+        .get("tags")
+        .into_iter()
+        .flatten()
+        .flat_map(|tag| tag.split(','))
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    if !tags.is_empty() {
+        metadata.insert("tags".to_string(), tags.join(","));
+    }
+    Ok(metadata)
+}
+
+/// A unit spanning `range`, trailing whitespace excluded, nested in `parent`
+fn unit(
+    unit_type: &str,
+    name: String,
+    parent: Option<&SemanticUnit>,
+    source: &str,
+    range: Range<usize>,
+    line_starts: &[usize],
+    metadata: HashMap<String, String>,
+) -> SemanticUnit {
+    let qualified_name = match parent {
+        Some(parent) => format!("{}{}{}", parent.qualified_name, PATH_SEPARATOR, name),
+        None => name.clone(),
+    };
+    SemanticUnit {
+        parent_name: parent.map(|p| p.name.clone()),
+        qualified_name,
+        metadata,
+        ..SemanticUnit::spanning(unit_type, name, source, line_starts, (range.start, range.end), "Markdown")
+    }
+}
+
+/// Extend a section from its heading to `end`, the start of the next
+/// heading at its level or above
+fn close(section: &mut SemanticUnit, source: &str, end: usize, line_of: &impl Fn(usize) -> usize) {
+    let content = source[section.start_byte..end].trim_end();
+    section.end_byte = section.start_byte + content.len();
+    section.end_line = line_of(section.end_byte.saturating_sub(1).max(section.start_byte));
+    section.content = content.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_nest_by_heading_level() {
+        let source = "# Guide\nIntro.\n\n## Install\nRun it.\n\n```bash\n# not a heading\npip install x\n```\n\n\
+            ### Windows\nUse WSL.\n\n## Usage\nCall it.\n";
+        let result = parse_markdown("README.md", source).unwrap();
+        let units: Vec<(&str, &str, Option<&str>, usize, usize)> = result
+            .units
+            .iter()
+            .map(|u| {
+                let parent = u.parent_name.as_deref();
+                (u.unit_type.as_str(), u.qualified_name.as_str(), parent, u.start_line, u.end_line)
+            })
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("section", "Guide", None, 1, 16),
+                ("section", "Guide > Install", Some("Guide"), 4, 13),
+                ("code_block", "Guide > Install > bash", Some("Install"), 7, 10),
+                ("section", "Guide > Install > Windows", Some("Install"), 12, 13),
+                ("section", "Guide > Usage", Some("Guide"), 15, 16),
+            ]
+        );
+        assert_eq!(result.units[1].signature, "## Install");
+        assert_eq!(result.units[1].metadata["level"], "2");
+        assert_eq!(result.units[2].metadata["fence_language"], "bash");
+        assert_eq!(result.units[4].content, "## Usage\nCall it.");
+    }
+
+    #[test]
+    fn test_setext_headings_and_unlabelled_fences() {
+        let source = "Title\n=====\n\n```\nplain\n```\n";
+        let result = parse_markdown("a.md", source).unwrap();
+        assert_eq!(result.units[0].name, "Title");
+        assert_eq!((result.units[0].start_line, result.units[0].end_line), (1, 6));
+        assert_eq!(result.units[1].name, "code");
+        assert!(!result.units[1].metadata.contains_key("fence_language"));
+    }
+
+    #[test]
+    fn test_frontmatter_becomes_metadata() {
+        let source = "---\ntitle: Deploy guide\ntags: [ops, k8s]\ndate: 2024-03-01\n---\n# Deploy\nSteps.\n";
+        let result = parse_markdown("deploy.md", source).unwrap();
+        // The closing `---` doesn't make the frontmatter a setext heading
+        assert_eq!(result.units.len(), 1);
+        let metadata = &result.units[0].metadata;
+        assert_eq!((metadata["title"].as_str(), metadata["date"].as_str()), ("Deploy guide", "2024-03-01"));
+        assert_eq!(metadata["tags"], "ops,k8s");
+
+        let source = "+++\ntitle = \"Notes\"\ntags = \"rust, parsing\"\ndate = 2024-03-01T10:00:00Z\n+++\n# Notes\n";
+        let metadata = &parse_markdown("notes.md", source).unwrap().units[0].metadata;
+        assert_eq!(metadata["tags"], "rust,parsing");
+        assert_eq!(metadata["date"], "2024-03-01T10:00:00Z");
+
+        let result = parse_markdown("bad.md", "---\ntitle: [unclosed\n---\n# A\n").unwrap();
+        assert!(result.warnings[0].starts_with("Invalid frontmatter"));
+        assert!(!result.units[0].metadata.contains_key("title"));
+    }
+}
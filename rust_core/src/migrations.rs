@@ -0,0 +1,471 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::parsing::SemanticUnit;
+use crate::sql_parsing::{self, SqlDialect, StatementKind};
+
+/// Migration tools whose file layouts and DSLs are recognized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationFramework {
+    Alembic,
+    Flyway,
+    Rails,
+    /// Plain SQL migrations (`001_init.up.sql`, goose/sql-migrate annotated files)
+    Sql,
+}
+
+impl MigrationFramework {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationFramework::Alembic => "alembic",
+            MigrationFramework::Flyway => "flyway",
+            MigrationFramework::Rails => "rails",
+            MigrationFramework::Sql => "sql",
+        }
+    }
+}
+
+/// Identity of a migration file, derived from its path and header
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationInfo {
+    pub framework: MigrationFramework,
+    pub version: String,
+    pub description: String,
+    /// Fixed direction for files holding a single direction (Flyway `U`, `.down.sql`)
+    pub direction: Option<&'static str>,
+}
+
+/// Detect whether a file is a migration, and which framework wrote it
+pub fn detect_migration(file_path: &str, source: &str) -> Option<MigrationInfo> {
+    let path = Path::new(file_path);
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let stem = file_name.split('.').next().unwrap_or("").to_string();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    match extension {
+        "py" if source.contains("def upgrade(") && assigned_string(source, "revision").is_some() => {
+            let version = assigned_string(source, "revision").unwrap_or_default();
+            let description = stem
+                .strip_prefix(&version)
+                .unwrap_or(&stem)
+                .trim_start_matches('_')
+                .to_string();
+            Some(MigrationInfo { framework: MigrationFramework::Alembic, version, description, direction: None })
+        }
+        "rb" if file_path.contains("db/migrate/") || source.contains("ActiveRecord::Migration") => {
+            let (version, description) = split_leading_digits(&stem);
+            Some(MigrationInfo { framework: MigrationFramework::Rails, version, description, direction: None })
+        }
+        "sql" => {
+            if let Some((prefix, description)) = stem.split_once("__") {
+                let kind = prefix.chars().next()?;
+                let version = prefix[1..].replace('_', ".");
+                let direction = match kind {
+                    'V' => "up",
+                    'U' => "down",
+                    'R' => "repeatable",
+                    _ => return None,
+                };
+                if kind != 'R' && version.is_empty() {
+                    return None;
+                }
+                return Some(MigrationInfo {
+                    framework: MigrationFramework::Flyway,
+                    version,
+                    description: description.replace('_', " "),
+                    direction: Some(direction),
+                });
+            }
+            if !sql_parsing::is_migration_path(file_path) {
+                return None;
+            }
+            let (version, description) = split_leading_digits(&stem);
+            let direction = if file_name.ends_with(".up.sql") {
+                Some("up")
+            } else if file_name.ends_with(".down.sql") {
+                Some("down")
+            } else {
+                None
+            };
+            Some(MigrationInfo { framework: MigrationFramework::Sql, version, description, direction })
+        }
+        _ => None,
+    }
+}
+
+/// Emit one `migration` unit per direction of a migration file.
+///
+/// Directions come from the framework's conventions: Alembic `upgrade` /
+/// `downgrade` and Rails `up` / `down` / `change` methods (found among the
+/// already-extracted function `units`), goose-style `-- +goose Up` sections,
+/// or the file name. Each unit's metadata records the framework, version,
+/// direction and the tables the section touches.
+pub fn extract_migration_units(
+    file_path: &str,
+    source: &str,
+    language: &str,
+    units: &[SemanticUnit],
+) -> Vec<SemanticUnit> {
+    let Some(info) = detect_migration(file_path, source) else {
+        return Vec::new();
+    };
+
+    let sections: Vec<(&'static str, usize, usize)> = match info.framework {
+        MigrationFramework::Alembic | MigrationFramework::Rails => units
+            .iter()
+            .filter(|u| u.unit_type == "function")
+            .filter_map(|u| {
+                let direction = match (info.framework, u.name.as_str()) {
+                    (MigrationFramework::Alembic, "upgrade") => "up",
+                    (MigrationFramework::Alembic, "downgrade") => "down",
+                    (MigrationFramework::Rails, "up" | "change") => "up",
+                    (MigrationFramework::Rails, "down") => "down",
+                    _ => return None,
+                };
+                Some((direction, u.start_byte, u.end_byte))
+            })
+            .collect(),
+        MigrationFramework::Flyway | MigrationFramework::Sql => {
+            let annotated = annotated_sql_sections(source);
+            if annotated.is_empty() {
+                vec![(info.direction.unwrap_or("up"), 0, source.len())]
+            } else {
+                annotated
+            }
+        }
+    };
+
+    sections
+        .into_iter()
+        .map(|(direction, start_byte, end_byte)| {
+            let content = &source[start_byte..end_byte];
+            let tables = match info.framework {
+                MigrationFramework::Alembic => alembic_tables(content),
+                MigrationFramework::Rails => rails_tables(content),
+                MigrationFramework::Flyway | MigrationFramework::Sql => sql_tables(content),
+            };
+
+            let mut metadata = HashMap::new();
+            metadata.insert("framework".to_string(), info.framework.as_str().to_string());
+            metadata.insert("version".to_string(), info.version.clone());
+            metadata.insert("direction".to_string(), direction.to_string());
+            metadata.insert("tables".to_string(), tables.into_iter().collect::<Vec<_>>().join(","));
+            if !info.description.is_empty() {
+                metadata.insert("description".to_string(), info.description.clone());
+            }
+            if let Some(down_revision) = assigned_string(source, "down_revision") {
+                metadata.insert("down_revision".to_string(), down_revision);
+            }
+
+            let start_line = source[..start_byte].matches('\n').count() + 1;
+            let name = if info.version.is_empty() {
+                format!("{} ({})", info.description, direction)
+            } else {
+                format!("{} ({})", info.version, direction)
+            };
+
+            SemanticUnit {
+                unit_type: "migration".into(),
+                name: name.clone(),
+                parent_name: None,
+                qualified_name: name,
+                start_line,
+                end_line: start_line + content.trim_end().matches('\n').count(),
+                start_byte,
+                end_byte,
+                signature: content.lines().next().unwrap_or("").trim().to_string(),
+                parameters: None,
+                docstring: None,
+                decorators: Vec::new(),
+                content: content.to_string(),
+                content_ref: None,
+                content_hash: String::new(),
+                unit_id: String::new(),
+                language: language.into(),
+                metadata,
+            }
+        })
+        .collect()
+}
+
+/// Find `name = '...'` / `name = "..."` at the start of a line
+fn assigned_string(source: &str, name: &str) -> Option<String> {
+    source.lines().find_map(|line| {
+        let rest = line.strip_prefix(name)?.trim_start();
+        // Allow a type annotation (`revision: str = ...`)
+        let rest = match rest.strip_prefix(':') {
+            Some(annotated) => annotated.split_once('=')?.1,
+            None => rest.strip_prefix('=')?,
+        };
+        let value = rest.trim();
+        let quote = value.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+        value[1..].split(quote).next().map(str::to_string)
+    })
+}
+
+/// Split `20240101_create_users` into (`20240101`, `create_users`)
+fn split_leading_digits(stem: &str) -> (String, String) {
+    let digits: String = stem.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let description = stem[digits.len()..].trim_start_matches(['_', '-']).to_string();
+    (digits, description)
+}
+
+/// Split SQL annotated with goose / sql-migrate markers into up and down sections
+fn annotated_sql_sections(source: &str) -> Vec<(&'static str, usize, usize)> {
+    let mut markers = Vec::new();
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let lower = line.trim().to_ascii_lowercase();
+        if lower.starts_with("-- +goose") || lower.starts_with("-- +migrate") {
+            if lower.ends_with(" up") {
+                markers.push(("up", offset));
+            } else if lower.ends_with(" down") {
+                markers.push(("down", offset));
+            }
+        }
+        offset += line.len();
+    }
+
+    markers
+        .iter()
+        .enumerate()
+        .map(|(i, (direction, start))| {
+            let end = markers.get(i + 1).map_or(source.len(), |(_, next)| *next);
+            (*direction, *start, end)
+        })
+        .collect()
+}
+
+/// Tables created, altered, dropped or written by SQL statements
+fn sql_tables(sql: &str) -> BTreeSet<String> {
+    let mut tables = BTreeSet::new();
+    for statement in sql_parsing::split_statements(sql, SqlDialect::detect(sql)) {
+        let info = sql_parsing::classify_statement(&statement.text);
+        let words: Vec<&str> = statement.text.split_whitespace().collect();
+        let upper: Vec<String> = words.iter().map(|w| w.to_ascii_uppercase()).collect();
+        let word_after = |keyword: &str| {
+            upper
+                .iter()
+                .position(|w| w == keyword)
+                .and_then(|i| words.get(i + 1))
+                .map(|w| sql_parsing::unquote_identifier(w.split('(').next().unwrap_or(w)))
+        };
+
+        let table = match (info.kind, upper.first().map(String::as_str)) {
+            (StatementKind::Table, _) => info.name,
+            (StatementKind::Index | StatementKind::Trigger, _) => word_after("ON"),
+            (_, Some("INSERT")) => word_after("INTO"),
+            (_, Some("UPDATE")) => words.get(1).map(|w| sql_parsing::unquote_identifier(w)),
+            (_, Some("DELETE")) => word_after("FROM"),
+            _ => None,
+        };
+        tables.extend(table.filter(|t| !t.is_empty()));
+    }
+    tables
+}
+
+/// Tables touched by Alembic `op.*` calls (and raw SQL in `op.execute`)
+fn alembic_tables(code: &str) -> BTreeSet<String> {
+    // Operations whose first string argument is a table name
+    const FIRST_ARG: [&str; 10] = [
+        "create_table", "drop_table", "add_column", "drop_column", "alter_column",
+        "rename_table", "create_foreign_key", "bulk_insert", "create_unique_constraint",
+        "batch_alter_table",
+    ];
+    let mut tables = BTreeSet::new();
+
+    for (op, args) in calls(code, "op.") {
+        let strings = string_args(args);
+        let table = match op {
+            "create_index" | "drop_index" => strings.get(1),
+            "execute" => {
+                if let Some(sql) = strings.first() {
+                    tables.extend(sql_tables(sql));
+                }
+                None
+            }
+            op if FIRST_ARG.contains(&op) => strings.first(),
+            _ => None,
+        };
+        tables.extend(table.cloned());
+    }
+    tables
+}
+
+/// Tables touched by Rails schema statements (`create_table :users`, `add_column :users, ...`)
+fn rails_tables(code: &str) -> BTreeSet<String> {
+    const STATEMENTS: [&str; 12] = [
+        "create_table", "drop_table", "change_table", "rename_table", "add_column",
+        "remove_column", "rename_column", "change_column", "add_index", "remove_index",
+        "add_reference", "remove_reference",
+    ];
+    code.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let keyword = STATEMENTS.iter().find(|s| {
+                line.strip_prefix(**s)
+                    .is_some_and(|rest| rest.starts_with([' ', '(']))
+            })?;
+            let arg = line[keyword.len()..].trim_start_matches([' ', '(']);
+            let table = arg
+                .trim_start_matches(':')
+                .trim_start_matches(['"', '\''])
+                .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .next()?;
+            (!table.is_empty()).then(|| table.to_string())
+        })
+        .collect()
+}
+
+/// Find `prefix<name>(<args>)` calls, returning the name and raw argument text
+fn calls<'a>(code: &'a str, prefix: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found = Vec::new();
+    let mut rest = code;
+    while let Some(pos) = rest.find(prefix) {
+        let after = &rest[pos + prefix.len()..];
+        let name_len = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        let (name, tail) = after.split_at(name_len);
+        if let Some(args) = tail.strip_prefix('(') {
+            let mut depth = 1;
+            let end = args
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(args.len(), |(i, _)| i);
+            found.push((name, &args[..end]));
+        }
+        rest = tail;
+    }
+    found
+}
+
+/// Quoted string literals appearing in call arguments, in order
+fn string_args(args: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut i = 0;
+    while let Some(offset) = args[i..].find(['\'', '"']) {
+        let start = i + offset;
+        let quote = &args[start..start + 1];
+        // Triple-quoted strings are common for op.execute
+        let delimiter = if args[start..].starts_with(&quote.repeat(3)) { quote.repeat(3) } else { quote.to_string() };
+        let body_start = start + delimiter.len();
+        match args[body_start..].find(&delimiter) {
+            Some(len) => {
+                strings.push(args[body_start..body_start + len].to_string());
+                i = body_start + len + delimiter.len();
+            }
+            None => break,
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{CodeParser, ParseOptions};
+
+    fn migration_units(file_path: &str, source: &str) -> Vec<SemanticUnit> {
+        CodeParser::new()
+            .parse_file(file_path, source, &ParseOptions::default())
+            .unwrap()
+            .units
+            .into_iter()
+            .filter(|u| u.unit_type == "migration")
+            .collect()
+    }
+
+    #[test]
+    fn test_alembic_revision_directions_and_tables() {
+        let source = r#""""add users
+
+Revision ID: ae1027a6acf
+"""
+from alembic import op
+
+revision = 'ae1027a6acf'
+down_revision = '1975ea83b712'
+
+def upgrade():
+    op.create_table('users', sa.Column('id', sa.Integer))
+    op.create_index('ix_users_email', 'users', ['email'])
+    op.execute("UPDATE accounts SET active = 1")
+
+def downgrade():
+    op.drop_table('users')
+"#;
+        let units = migration_units("alembic/versions/ae1027a6acf_add_users.py", source);
+        assert_eq!(units.len(), 2);
+
+        let up = &units[0];
+        assert_eq!(up.name, "ae1027a6acf (up)");
+        assert_eq!(up.language, "Python");
+        assert_eq!(up.metadata["framework"], "alembic");
+        assert_eq!(up.metadata["direction"], "up");
+        assert_eq!(up.metadata["tables"], "accounts,users");
+        assert_eq!(up.metadata["down_revision"], "1975ea83b712");
+        assert_eq!(up.metadata["description"], "add_users");
+        assert!(up.content.starts_with("def upgrade():"));
+
+        assert_eq!(units[1].metadata["direction"], "down");
+        assert_eq!(units[1].metadata["tables"], "users");
+    }
+
+    #[test]
+    fn test_rails_change_and_up_down() {
+        let source = "class CreateOrders < ActiveRecord::Migration[7.0]\n  def up\n    create_table :orders do |t|\n      t.string :sku\n    end\n    add_index :orders, :sku\n    add_reference :line_items, :order\n  end\n\n  def down\n    drop_table :orders\n  end\nend\n";
+        let units = migration_units("db/migrate/20240101120000_create_orders.rb", source);
+        let summary: Vec<(&str, &str, &str)> = units
+            .iter()
+            .map(|u| (u.metadata["version"].as_str(), u.metadata["direction"].as_str(), u.metadata["tables"].as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("20240101120000", "up", "line_items,orders"), ("20240101120000", "down", "orders")]
+        );
+        assert_eq!(units[0].metadata["description"], "create_orders");
+    }
+
+    #[test]
+    fn test_flyway_versions_and_directions() {
+        let source = "CREATE TABLE users (id INT);\nCREATE INDEX idx_users ON users (id);\nINSERT INTO audit VALUES (1);\n";
+        let units = migration_units("sql/V1_2__create_users.sql", source);
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].name, "1.2 (up)");
+        assert_eq!(units[0].metadata["framework"], "flyway");
+        assert_eq!(units[0].metadata["description"], "create users");
+        assert_eq!(units[0].metadata["tables"], "audit,users");
+
+        let undo = migration_units("sql/U1_2__create_users.sql", "DROP TABLE users;");
+        assert_eq!(undo[0].metadata["direction"], "down");
+
+        let repeatable = migration_units("sql/R__views.sql", "CREATE VIEW v AS SELECT 1;");
+        assert_eq!(repeatable[0].name, "views (repeatable)");
+    }
+
+    #[test]
+    fn test_goose_annotated_sql_sections() {
+        let source = "-- +goose Up\nCREATE TABLE posts (id INT);\n\n-- +goose Down\nDROP TABLE posts;\n";
+        let units = migration_units("db/migrations/00003_posts.sql", source);
+        let directions: Vec<&str> = units.iter().map(|u| u.metadata["direction"].as_str()).collect();
+        assert_eq!(directions, vec!["up", "down"]);
+        assert_eq!(units[1].start_line, 4);
+        assert_eq!(units[1].content, "-- +goose Down\nDROP TABLE posts;\n");
+        assert_eq!(units[0].metadata["version"], "00003");
+    }
+
+    #[test]
+    fn test_non_migrations_are_ignored() {
+        assert!(detect_migration("app/models/user.rb", "class User\nend\n").is_none());
+        assert!(detect_migration("src/app.py", "def upgrade():\n    pass\n").is_none());
+        assert!(detect_migration("sql/schema.sql", "CREATE TABLE t (id INT);").is_none());
+    }
+}
@@ -0,0 +1,494 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::doc_links::{markdown_prose_ranges, resolve, scan_links};
+use crate::markdown_parsing::frontmatter_fields;
+
+/// A note from a personal knowledge base export, ready to store as a memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct ImportedNote {
+    #[pyo3(get)]
+    pub id: String, // Export-relative path without extension or Notion page ids, e.g. "Projects/Alpha"
+    #[pyo3(get)]
+    pub title: String,
+    #[pyo3(get)]
+    pub file_path: String,
+    #[pyo3(get)]
+    pub content: String, // Body without frontmatter (Obsidian) or title and property lines (Notion)
+    #[pyo3(get)]
+    pub tags: Vec<String>, // Without `#`, once each: frontmatter or property tags first, then inline `#tags`
+    #[pyo3(get)]
+    pub aliases: Vec<String>,
+    #[pyo3(get)]
+    pub metadata: HashMap<String, String>, // Other frontmatter fields or page properties, lists comma-joined
+}
+
+#[pymethods]
+impl ImportedNote {
+    fn __repr__(&self) -> String {
+        format!("ImportedNote(id={}, tags={})", self.id, self.tags.len())
+    }
+}
+
+/// The notes of an export and the links between them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct NoteImport {
+    #[pyo3(get)]
+    pub notes: Vec<ImportedNote>,
+    #[pyo3(get)]
+    pub edges: Vec<(String, String)>, // (note id, linked note id), once each, self links omitted
+    #[pyo3(get)]
+    pub unresolved: Vec<(String, String)>, // (note id, link target) for notes missing from the export
+    #[pyo3(get)]
+    pub warnings: Vec<String>, // Problems that cost a note its frontmatter
+}
+
+#[pymethods]
+impl NoteImport {
+    fn __repr__(&self) -> String {
+        format!("NoteImport(notes={}, edges={})", self.notes.len(), self.edges.len())
+    }
+}
+
+/// A note and the links still to resolve against the rest of the export
+struct Draft {
+    note: ImportedNote,
+    links: Vec<(&'static str, String)>,
+}
+
+/// Where a link leads once resolved
+enum Link {
+    Note(usize),
+    /// A note that isn't in the export
+    Missing,
+    /// Within the same note, an attachment, or a web page
+    Ignored,
+}
+
+fn push_unique<T: PartialEq>(items: &mut Vec<T>, item: T) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+/// Wiki and Markdown links in the prose of a Markdown body, code excluded
+fn body_links(body: &str) -> Vec<(&'static str, String)> {
+    let mut found = Vec::new();
+    for range in markdown_prose_ranges(body) {
+        scan_links(&body[range.clone()], range.start, &mut found);
+    }
+    found.into_iter().map(|(kind, target, _, _)| (kind, target)).collect()
+}
+
+/// Inline `#tags` (`#idea`, `#project/alpha`) in the prose of a Markdown
+/// body; headings, code, anchors and numbers like `#42` are not tags
+fn inline_tags(body: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for range in markdown_prose_ranges(body) {
+        let text = &body[range];
+        for (i, _) in text.match_indices('#') {
+            if i > 0 && !text[..i].ends_with(char::is_whitespace) {
+                continue;
+            }
+            let tag = text[i + 1..].split(|c: char| !(c.is_alphanumeric() || "_-/".contains(c))).next();
+            let tag = tag.unwrap_or("").trim_end_matches('/');
+            if tag.chars().any(|c| !c.is_ascii_digit()) {
+                push_unique(&mut tags, tag.to_string());
+            }
+        }
+    }
+    tags
+}
+
+/// Items of the first of `keys` a frontmatter has, split on commas (and
+/// whitespace, for tags)
+fn field_items(fields: &HashMap<String, Vec<String>>, keys: &[&str], split_whitespace: bool) -> Vec<String> {
+    let items = keys.iter().find_map(|key| fields.get(*key)).into_iter().flatten();
+    let mut split = Vec::new();
+    for item in items.flat_map(|item| item.split(',')) {
+        let words = if split_whitespace { item.split_whitespace().collect() } else { vec![item.trim()] };
+        for word in words.into_iter().map(|word| word.trim_start_matches('#')).filter(|word| !word.is_empty()) {
+            push_unique(&mut split, word.to_string());
+        }
+    }
+    split
+}
+
+/// The frontmatter fields of a note and where its body starts
+fn split_frontmatter(source: &str) -> (Result<HashMap<String, Vec<String>>, String>, usize) {
+    let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let mut block = String::new();
+    for (event, range) in Parser::new_ext(source, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => {}
+            Event::Text(text) => block.push_str(&text),
+            Event::End(TagEnd::MetadataBlock(kind)) => return (frontmatter_fields(&block, kind), range.end),
+            _ => break,
+        }
+    }
+    (Ok(HashMap::new()), 0)
+}
+
+/// Whether a link target names an attachment (`diagram.png`, `spec.pdf`)
+/// rather than a note
+fn is_attachment(target: &str) -> bool {
+    let name = target.split('#').next().unwrap_or("").rsplit('/').next().unwrap_or("");
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        extension != "md" && (1..=5).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphabetic())
+    })
+}
+
+/// `%XX` escapes in a link target decoded (`Other%20Note.md`)
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| text.get(i + 1..i + 3)).flatten();
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+/// Resolve every draft's links, collecting edges between notes and the
+/// targets missing from the export
+fn link_notes(drafts: Vec<Draft>, warnings: Vec<String>, resolve: impl Fn(&Draft, &str, &str) -> Link) -> NoteImport {
+    let mut edges = Vec::new();
+    let mut unresolved = Vec::new();
+    for (from, draft) in drafts.iter().enumerate() {
+        for (kind, target) in &draft.links {
+            match resolve(draft, kind, target) {
+                Link::Note(to) if to != from => {
+                    push_unique(&mut edges, (draft.note.id.clone(), drafts[to].note.id.clone()))
+                }
+                Link::Missing => push_unique(&mut unresolved, (draft.note.id.clone(), target.clone())),
+                _ => {}
+            }
+        }
+    }
+    NoteImport { notes: drafts.into_iter().map(|draft| draft.note).collect(), edges, unresolved, warnings }
+}
+
+/// Import the Markdown notes of an Obsidian vault. Hidden folders
+/// (`.obsidian`, `.trash`) and attachments are skipped.
+pub(crate) fn import_obsidian(files: &[(String, String)]) -> NoteImport {
+    let mut drafts = Vec::new();
+    let mut warnings = Vec::new();
+    for (file_path, source) in files {
+        let file_path = file_path.replace('\\', "/");
+        let Some(id) = file_path.strip_suffix(".md") else { continue };
+        if id.split('/').any(|segment| segment.starts_with('.')) {
+            continue;
+        }
+        let (fields, body_start) = split_frontmatter(source);
+        let fields = fields.unwrap_or_else(|e| {
+            warnings.push(format!("{}: invalid frontmatter: {}", file_path, e));
+            HashMap::new()
+        });
+        let body = &source[body_start..];
+
+        let mut tags = field_items(&fields, &["tags", "tag"], true);
+        for tag in inline_tags(body) {
+            push_unique(&mut tags, tag);
+        }
+        let title = fields.get("title").and_then(|items| items.first()).cloned();
+        let metadata = fields
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "title" | "tags" | "tag" | "aliases" | "alias"))
+            .map(|(key, items)| (key.clone(), items.join(",")))
+            .chain([("source".to_string(), "obsidian".to_string())])
+            .collect();
+        let note = ImportedNote {
+            id: id.to_string(),
+            title: title.unwrap_or_else(|| id.rsplit('/').next().unwrap_or(id).to_string()),
+            content: body.trim().to_string(),
+            tags,
+            aliases: field_items(&fields, &["aliases", "alias"], false),
+            metadata,
+            file_path,
+        };
+        drafts.push(Draft { note, links: body_links(body) });
+    }
+
+    // Notes by lowercase id, and by lowercase file name and alias
+    let ids: Vec<String> = drafts.iter().map(|draft| draft.note.id.to_lowercase()).collect();
+    let by_id: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, draft) in drafts.iter().enumerate() {
+        let name = ids[i].rsplit('/').next().unwrap_or(&ids[i]).to_string();
+        by_name.entry(name).or_default().push(i);
+        for alias in &draft.note.aliases {
+            by_name.entry(alias.to_lowercase()).or_default().push(i);
+        }
+    }
+    let folder = |id: &str| id.rsplit_once('/').map_or("", |(folder, _)| folder).to_string();
+
+    link_notes(drafts, warnings, |draft, kind, target| {
+        let page = match kind {
+            "wiki" => target.split('#').next().unwrap_or("").trim().to_lowercase(),
+            "markdown" if !target.contains("://") && !target.starts_with("mailto:") => {
+                match resolve(&draft.note.file_path, kind, &percent_decode(target)) {
+                    Some(path) => path.to_lowercase(),
+                    None => return Link::Ignored,
+                }
+            }
+            _ => return Link::Ignored,
+        };
+        let page = page.strip_suffix(".md").unwrap_or(&page);
+        if page.is_empty() {
+            return Link::Ignored;
+        }
+        // Paths match the end of an id; bare names prefer the linking
+        // note's folder, then the shortest path, as Obsidian does
+        let found = if page.contains('/') {
+            by_id.get(page).copied().or_else(|| ids.iter().position(|id| id.ends_with(&format!("/{}", page))))
+        } else {
+            let from_folder = folder(&draft.note.id.to_lowercase());
+            by_name.get(page).and_then(|candidates| {
+                candidates.iter().copied().min_by_key(|&i| (folder(&ids[i]) != from_folder, ids[i].len()))
+            })
+        };
+        match found {
+            Some(i) => Link::Note(i),
+            None if is_attachment(target) => Link::Ignored,
+            None => Link::Missing,
+        }
+    })
+}
+
+/// A Notion export file or folder name split into its name and the 32
+/// hex digit page id Notion appends: "Alpha 1a2b…" is "Alpha"
+fn notion_name(segment: &str) -> (&str, Option<&str>) {
+    let stem = segment.strip_suffix(".md").unwrap_or(segment);
+    match stem.rsplit_once(' ') {
+        Some((name, id)) if id.len() == 32 && id.bytes().all(|c| c.is_ascii_hexdigit()) => (name, Some(id)),
+        _ => (stem, None),
+    }
+}
+
+/// The page id in a notion.so URL (`https://www.notion.so/Beta-5678…`)
+fn notion_url_id(url: &str) -> Option<&str> {
+    url.split(|c: char| !c.is_ascii_hexdigit()).find(|run| run.len() == 32)
+}
+
+/// The `# Title` a Notion page starts with, the `Property: value` lines
+/// under it, and where the page content starts
+fn notion_header(source: &str) -> (Option<&str>, Vec<(&str, &str)>, usize) {
+    let mut lines = source.split_inclusive('\n');
+    let title = lines.next().and_then(|line| line.trim().strip_prefix("# "));
+    let Some(title) = title else {
+        return (None, Vec::new(), 0);
+    };
+    let mut properties = Vec::new();
+    let mut offset = source.split_inclusive('\n').next().map_or(0, str::len);
+    let mut end = offset;
+    for line in lines {
+        offset += line.len();
+        let text = line.trim();
+        if text.is_empty() {
+            if !properties.is_empty() {
+                break;
+            }
+            end = offset;
+            continue;
+        }
+        let Some((key, value)) = text.split_once(": ") else { break };
+        if key.len() > 40 || key.starts_with(['#', '-', '*', '>', '[', '!', '|']) {
+            break;
+        }
+        properties.push((key, value.trim()));
+        end = offset;
+    }
+    (Some(title.trim()), properties, end)
+}
+
+/// Import the Markdown pages of a Notion export ("Markdown & CSV"). Page
+/// ids are stripped from names, property lines under the title become
+/// metadata (`Tags` become tags), and links between pages, relative or as
+/// notion.so URLs, become edges. Database CSVs are skipped.
+pub(crate) fn import_notion(files: &[(String, String)]) -> NoteImport {
+    let mut drafts = Vec::new();
+    for (file_path, source) in files {
+        let file_path = file_path.replace('\\', "/");
+        if !file_path.ends_with(".md") {
+            continue;
+        }
+        let segments: Vec<(&str, Option<&str>)> = file_path.split('/').map(notion_name).collect();
+        let id = segments.iter().map(|(name, _)| *name).collect::<Vec<_>>().join("/");
+        let (name, page_id) = segments.last().copied().unwrap_or(("", None));
+        let (title, properties, content_start) = notion_header(source);
+
+        let mut tags = Vec::new();
+        let mut metadata = HashMap::new();
+        for (key, value) in properties {
+            let key = key.to_lowercase().replace(' ', "_");
+            if key == "tags" {
+                value.split(',').map(str::trim).filter(|tag| !tag.is_empty()).for_each(|tag| {
+                    push_unique(&mut tags, tag.to_string());
+                });
+            } else {
+                metadata.insert(key, value.to_string());
+            }
+        }
+        metadata.insert("source".to_string(), "notion".to_string());
+        if let Some(page_id) = page_id {
+            metadata.insert("notion_id".to_string(), page_id.to_string());
+        }
+        let content = &source[content_start..];
+        let note = ImportedNote {
+            title: title.unwrap_or(name).to_string(),
+            content: content.trim().to_string(),
+            tags,
+            aliases: Vec::new(),
+            metadata,
+            file_path: file_path.clone(),
+            id,
+        };
+        drafts.push(Draft { note, links: body_links(content) });
+    }
+
+    let by_path: HashMap<String, usize> =
+        drafts.iter().enumerate().map(|(i, draft)| (draft.note.file_path.to_lowercase(), i)).collect();
+    let by_page_id: HashMap<String, usize> = drafts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, draft)| Some((draft.note.metadata.get("notion_id")?.clone(), i)))
+        .collect();
+
+    link_notes(drafts, Vec::new(), |draft, kind, target| {
+        if target.contains("notion.so/") {
+            return notion_url_id(target).and_then(|id| by_page_id.get(id)).map_or(Link::Missing, |&i| Link::Note(i));
+        }
+        if kind != "markdown" || target.contains("://") || target.starts_with("mailto:") {
+            return Link::Ignored;
+        }
+        match resolve(&draft.note.file_path, kind, &percent_decode(target)) {
+            Some(path) if path.ends_with(".md") => {
+                by_path.get(&path.to_lowercase()).map_or(Link::Missing, |&i| Link::Note(i))
+            }
+            _ => Link::Ignored,
+        }
+    })
+}
+
+/// Import an Obsidian vault as memory records and link edges
+///
+/// Args:
+///     files: (vault-relative path, content) pairs; non-Markdown files and
+///         hidden folders such as `.obsidian` are skipped
+///
+/// Returns:
+///     NoteImport with one ImportedNote per note (frontmatter title,
+///     aliases and tags plus inline `#tags` collected, other frontmatter
+///     fields in metadata) and `(note id, note id)` edges for wiki links
+///     and Markdown links between notes, resolved the way Obsidian does.
+///     The edges are ready for `pagerank` or "references" relations.
+#[pyfunction]
+pub fn import_obsidian_vault(py: Python<'_>, files: Vec<(String, String)>) -> NoteImport {
+    py.detach(|| import_obsidian(&files))
+}
+
+/// Import a Notion Markdown export as memory records and link edges
+///
+/// Args:
+///     files: (export-relative path, content) pairs; non-Markdown files
+///         (database CSVs, attachments) are skipped
+///
+/// Returns:
+///     NoteImport with one ImportedNote per page, named without Notion's
+///     page ids (kept in `metadata["notion_id"]`), page properties in
+///     metadata, and `(note id, note id)` edges for links between pages
+#[pyfunction]
+pub fn import_notion_export(py: Python<'_>, files: Vec<(String, String)>) -> NoteImport {
+    py.detach(|| import_notion(&files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(path, content)| (path.to_string(), content.to_string())).collect()
+    }
+
+    fn pairs(edges: &[(String, String)]) -> Vec<(&str, &str)> {
+        edges.iter().map(|(from, to)| (from.as_str(), to.as_str())).collect()
+    }
+
+    #[test]
+    fn test_obsidian_vault() {
+        let vault = files(&[
+            (
+                "Projects/Alpha.md",
+                "---\ntags: [project, active]\naliases: [A1]\nstatus: draft\n---\n# Alpha\nSee [[Notes|the notes]], \
+                 [[Beta#Plan]] and ![[diagram.png]] #idea #42.\n\n```\n[[Not A Link]] #not-a-tag\n```\n[[Missing]]\n",
+            ),
+            ("Projects/Notes.md", "Back to [Alpha](Alpha.md) and [[A1]]; also [[Archive/Notes]]."),
+            ("Notes.md", "Top-level notes"),
+            ("Archive/Notes.md", "Old notes"),
+            ("Beta.md", "---\ntitle: Beta plan\n---\nLinks to [[Alpha]].\n"),
+            (".obsidian/workspace.md", "[[Alpha]]"),
+            ("diagram.png", ""),
+        ]);
+        let import = import_obsidian(&vault);
+        let alpha = &import.notes[0];
+        assert_eq!(import.notes.len(), 5);
+        assert_eq!((alpha.id.as_str(), alpha.title.as_str()), ("Projects/Alpha", "Alpha"));
+        assert_eq!(alpha.tags, vec!["project", "active", "idea"]);
+        assert_eq!(alpha.aliases, vec!["A1"]);
+        assert_eq!(alpha.metadata["status"], "draft");
+        assert!(alpha.content.starts_with("# Alpha"));
+        assert_eq!(import.notes[4].title, "Beta plan");
+        assert_eq!(
+            pairs(&import.edges),
+            vec![
+                ("Projects/Alpha", "Projects/Notes"),
+                ("Projects/Alpha", "Beta"),
+                ("Projects/Notes", "Projects/Alpha"),
+                ("Projects/Notes", "Archive/Notes"),
+                ("Beta", "Projects/Alpha"),
+            ]
+        );
+        assert_eq!(pairs(&import.unresolved), vec![("Projects/Alpha", "Missing")]);
+    }
+
+    #[test]
+    fn test_notion_export() {
+        let alpha_id = "1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d";
+        let beta_id = "0f0e0d0c0b0a09080706050403020100";
+        let alpha = format!(
+            "# Alpha\n\nTags: roadmap, q3\nCreated: May 1, 2024\n\n\
+             Plan: see [Beta](Alpha%20{alpha_id}/Beta%20{beta_id}.md) \
+             and [again](https://www.notion.so/Beta-{beta_id}).\n"
+        );
+        let export = files(&[
+            (format!("Workspace/Alpha {alpha_id}.md").as_str(), alpha.as_str()),
+            (format!("Workspace/Alpha {alpha_id}/Beta {beta_id}.md").as_str(), "# Beta\n\nJust text, no properties.\n"),
+            ("Workspace/Tasks.csv", "Name,Status\n"),
+        ]);
+        let import = import_notion(&export);
+        assert_eq!(import.notes.len(), 2);
+        let alpha = &import.notes[0];
+        assert_eq!((alpha.id.as_str(), alpha.title.as_str()), ("Workspace/Alpha", "Alpha"));
+        assert_eq!(alpha.tags, vec!["roadmap", "q3"]);
+        assert_eq!(alpha.metadata["created"], "May 1, 2024");
+        assert_eq!(alpha.metadata["notion_id"], alpha_id);
+        assert!(alpha.content.starts_with("Plan: see"));
+        assert_eq!(import.notes[1].id, "Workspace/Alpha/Beta");
+        assert_eq!(import.notes[1].content, "Just text, no properties.");
+        assert_eq!(pairs(&import.edges), vec![("Workspace/Alpha", "Workspace/Alpha/Beta")]);
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use crate::parsing::{parse_as_language, NamedLanguage, ParseOptions, ParseResult, SemanticUnit};
+use crate::truncation::{truncate, Measure};
+
+/// Longest name of a documentation unit, in characters
+const MAX_DOC_NAME: usize = 80;
+
+/// Source of a notebook cell, stored either as one string or as a list of lines
+fn cell_source(cell: &JsonValue) -> String {
+    match cell.get("source") {
+        Some(JsonValue::String(source)) => source.clone(),
+        Some(JsonValue::Array(lines)) => lines.iter().filter_map(JsonValue::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Language of the notebook's kernel, Python when unrecorded
+fn kernel_language(notebook: &JsonValue) -> &str {
+    let metadata = &notebook["metadata"];
+    metadata["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| metadata["language_info"]["name"].as_str())
+        .unwrap_or("python")
+}
+
+/// IPython magics (`%matplotlib inline`, `!pip install`) and shell escapes
+/// blanked out so the cell parses as plain code; offsets are unchanged
+fn blank_magics(source: &str) -> String {
+    source
+        .split_inclusive('\n')
+        .map(|line| match line.trim_start().chars().next() {
+            Some('%' | '!') => line.chars().map(|c| if c == '\n' { c } else { ' ' }).collect(),
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Parse a Jupyter notebook: code cells run through the parser for the
+/// kernel's language, and each markdown cell becomes a "documentation" unit
+/// named after its first line.
+///
+/// Positions refer to the notebook's script view, its cells' sources joined
+/// in order with a blank line after each cell. Every unit also records its
+/// cell in `metadata["cell"]` (0-based index) and its lines within the cell
+/// in `metadata["cell_lines"]` ("start,end").
+pub fn parse_notebook(file_path: &str, source_code: &str, options: &ParseOptions) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let notebook: JsonValue =
+        serde_json::from_str(source_code).map_err(|e| format!("Invalid notebook JSON: {}", e))?;
+    let cells = notebook["cells"].as_array().ok_or("Notebook has no cells array")?;
+    let language = kernel_language(&notebook);
+    let named = NamedLanguage::from_name(language);
+
+    let mut units = Vec::new();
+    let mut warnings = Vec::new();
+    let (mut base_line, mut base_byte) = (1, 0);
+    for (index, cell) in cells.iter().enumerate() {
+        let source = cell_source(cell);
+        let mut cell_units = Vec::new();
+        match (cell["cell_type"].as_str(), &named) {
+            (Some("code"), Some(named)) => {
+                let code = blank_magics(&source);
+                match parse_as_language(file_path, &code, named.clone(), options) {
+                    Ok(result) => {
+                        cell_units = result.units;
+                        warnings.extend(result.warnings.into_iter().map(|w| format!("cell {}: {}", index, w)));
+                    }
+                    Err(e) => warnings.push(format!("cell {} skipped: {}", index, e)),
+                }
+            }
+            (Some("code"), None) => {
+                warnings.push(format!("cell {} skipped: unsupported language {}", index, language))
+            }
+            (Some("markdown"), _) if !source.trim().is_empty() => cell_units.push(documentation_unit(&source)),
+            _ => {}
+        }
+        for mut unit in cell_units {
+            unit.metadata.insert("cell".to_string(), index.to_string());
+            unit.metadata.insert("cell_lines".to_string(), format!("{},{}", unit.start_line, unit.end_line));
+            unit.remap(base_line, base_byte);
+            units.push(unit);
+        }
+
+        // The script view ends each cell with a newline, then a blank line
+        let cell_len = source.len() + usize::from(!source.ends_with('\n') && !source.is_empty());
+        base_line += source.lines().count() + 1;
+        base_byte += cell_len + 1;
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Jupyter".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+/// A markdown cell as one unit, named after its first line without heading marks
+fn documentation_unit(source: &str) -> SemanticUnit {
+    let content = source.trim_end();
+    let first_line = content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    let title = first_line.trim_start_matches('#').trim();
+    let name = truncate(title, MAX_DOC_NAME, Measure::Chars, "…").into_owned();
+    SemanticUnit {
+        unit_type: "documentation".into(),
+        qualified_name: name.clone(),
+        name,
+        parent_name: None,
+        start_line: 1,
+        end_line: content.lines().count().max(1),
+        start_byte: 0,
+        end_byte: content.len(),
+        signature: first_line.to_string(),
+        parameters: None,
+        docstring: None,
+        decorators: Vec::new(),
+        content: content.to_string(),
+        content_ref: None,
+        content_hash: String::new(),
+        unit_id: String::new(),
+        language: "Markdown".into(),
+        metadata: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_map_to_script_lines() {
+        let notebook = r##"{
+            "metadata": {"kernelspec": {"language": "python"}},
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Loading data\n", "Reads the CSV."]},
+                {"cell_type": "code", "source": ["%matplotlib inline\n", "import pandas as pd\n"]},
+                {"cell_type": "code", "source": "def load(path):\n    return pd.read_csv(path)\n"}
+            ]
+        }"##;
+        let result = parse_notebook("analysis.ipynb", notebook, &ParseOptions::default()).unwrap();
+        assert_eq!(result.language, "Jupyter");
+        let units: Vec<(&str, &str, usize, usize, &str, &str)> = result
+            .units
+            .iter()
+            .map(|u| {
+                let (cell, lines) = (u.metadata["cell"].as_str(), u.metadata["cell_lines"].as_str());
+                (u.unit_type.as_str(), u.name.as_str(), u.start_line, u.end_line, cell, lines)
+            })
+            .collect();
+        assert_eq!(
+            units,
+            vec![("documentation", "Loading data", 1, 2, "0", "1,2"), ("function", "load", 7, 8, "2", "1,2")]
+        );
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_invalid_notebooks_are_errors() {
+        assert!(parse_notebook("a.ipynb", "not json", &ParseOptions::default()).is_err());
+        assert!(parse_notebook("a.ipynb", "{\"metadata\": {}}", &ParseOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::parsing::{assign_unit_ids, ParseResult, SemanticUnit};
+
+/// What changed between two parses of a file, unit by unit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[pyclass]
+pub struct ParseDiff {
+    #[pyo3(get)]
+    pub added: Vec<SemanticUnit>,
+    #[pyo3(get)]
+    pub removed: Vec<SemanticUnit>,
+    #[pyo3(get)]
+    pub modified: Vec<(SemanticUnit, SemanticUnit)>, // (old, new): same unit, new content
+    #[pyo3(get)]
+    pub renamed: Vec<(SemanticUnit, SemanticUnit)>, // (old, new): same content under a new unit id
+    #[pyo3(get)]
+    pub moved: Vec<SemanticUnit>, // New units whose content is unchanged but whose lines shifted
+    #[pyo3(get)]
+    pub unchanged: usize,
+}
+
+#[pymethods]
+impl ParseDiff {
+    fn __repr__(&self) -> String {
+        format!(
+            "ParseDiff(added={}, removed={}, modified={}, renamed={}, moved={}, unchanged={})",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len(),
+            self.renamed.len(),
+            self.moved.len(),
+            self.unchanged
+        )
+    }
+
+    /// Whether any unit needs re-embedding or deleting
+    fn has_changes(&self) -> bool {
+        !(self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty() && self.renamed.is_empty())
+    }
+}
+
+/// A result's units, identified if they weren't already (e.g. results
+/// saved before units carried ids)
+fn identified(result: &ParseResult) -> Vec<SemanticUnit> {
+    let mut units = result.units.clone();
+    if units.iter().any(|unit| unit.unit_id.is_empty() || unit.content_hash.is_empty()) {
+        assign_unit_ids(&result.file_path, &mut units, "");
+    }
+    units
+}
+
+/// Compare two parses by unit id and content hash. Units only in `new` are
+/// added and units only in `old` removed, except that a removed and an
+/// added unit of the same type and content are paired up as a rename (a
+/// method moved to another class, say).
+pub(crate) fn diff_results(old: &ParseResult, new: &ParseResult) -> ParseDiff {
+    let (old_units, new_units) = (identified(old), identified(new));
+    let mut old_by_id: HashMap<&str, &SemanticUnit> =
+        old_units.iter().map(|unit| (unit.unit_id.as_str(), unit)).collect();
+
+    let mut diff = ParseDiff::default();
+    let mut added = Vec::new();
+    for unit in &new_units {
+        match old_by_id.remove(unit.unit_id.as_str()) {
+            Some(before) if before.content_hash != unit.content_hash => {
+                diff.modified.push((before.clone(), unit.clone()))
+            }
+            Some(before) if (before.start_line, before.end_line) != (unit.start_line, unit.end_line) => {
+                diff.moved.push(unit.clone())
+            }
+            Some(_) => diff.unchanged += 1,
+            None => added.push(unit),
+        }
+    }
+
+    // Whatever is left of the old units was removed, or renamed if an added
+    // unit has its content
+    let mut removed: Vec<&SemanticUnit> =
+        old_units.iter().filter(|unit| old_by_id.contains_key(unit.unit_id.as_str())).collect();
+    for unit in added {
+        let same_content = removed
+            .iter()
+            .position(|before| before.content_hash == unit.content_hash && before.unit_type == unit.unit_type);
+        match same_content {
+            Some(index) => diff.renamed.push((removed.remove(index).clone(), unit.clone())),
+            None => diff.added.push(unit.clone()),
+        }
+    }
+    diff.removed = removed.into_iter().cloned().collect();
+    diff
+}
+
+/// Compare two parses of a file to find the units that need re-embedding
+///
+/// Units are matched by `unit_id`, which survives edits, and compared by
+/// `content_hash`. After a file edit only `added`, `modified` and `renamed`
+/// units need new embeddings, `removed` ones deleting and `moved` ones
+/// their line numbers updating.
+///
+/// Args:
+///     old: The file's previous ParseResult
+///     new: Its ParseResult after the edit
+///
+/// Returns:
+///     ParseDiff with added and removed units, (old, new) pairs of
+///     modified units and of units whose content moved to a new name,
+///     units that only moved, and the count of units left as they were
+#[pyfunction]
+pub fn diff_parse_results(py: Python<'_>, old: ParseResult, new: ParseResult) -> ParseDiff {
+    py.detach(|| diff_results(&old, &new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    #[test]
+    fn test_diff_classifies_units() {
+        let parse = |source: &str| parse_any_file("shop.py", source, &ParseOptions::default()).unwrap();
+        let old = parse(
+            "def total():\n    return 1\n\ndef tax():\n    return 2\n\ndef gone():\n    return 4\n\n\
+             class A:\n    def helper(self):\n        return 3\n\nclass B:\n    pass\n",
+        );
+        let new = parse(
+            "\ndef total():\n    return 10\n\ndef tax():\n    return 2\n\ndef fresh():\n    return 5\n\n\
+             class A:\n    pass\n\nclass B:\n    def helper(self):\n        return 3\n",
+        );
+        let diff = diff_results(&old, &new);
+        let names = |units: &[SemanticUnit]| units.iter().map(|u| u.qualified_name.clone()).collect::<Vec<_>>();
+        let pairs = |pairs: &[(SemanticUnit, SemanticUnit)]| {
+            pairs.iter().map(|(a, b)| format!("{} -> {}", a.qualified_name, b.qualified_name)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(pairs(&diff.modified), vec!["total -> total", "A -> A", "B -> B"]);
+        assert_eq!(names(&diff.moved), vec!["tax"]);
+        assert_eq!(pairs(&diff.renamed), vec!["A.helper -> B.helper"]);
+        assert_eq!(names(&diff.added), vec!["fresh"]);
+        assert_eq!(names(&diff.removed), vec!["gone"]);
+        assert!(diff.has_changes());
+        let same = diff_results(&new, &new);
+        assert!(!same.has_changes() && same.moved.is_empty());
+        assert_eq!(same.unchanged, new.units.len());
+    }
+}
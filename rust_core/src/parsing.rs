@@ -1,11 +1,28 @@
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use tree_sitter::{Language, Parser, Query, QueryCursor};
+use std::hash::Hash;
+use std::sync::Arc;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, Tree};
 use streaming_iterator::StreamingIterator;
 
+use crate::config_parsing::ConfigNesting;
+use crate::conflict_parsing;
+use crate::core_config;
+use crate::custom_languages::{self, CustomLanguage};
+use crate::file_classification::{self, FileClass};
+use crate::docstrings;
+use crate::interning::Interned;
+use crate::query_packs;
+use crate::scopes;
+use crate::signatures;
+use crate::sql_parsing::{self, SqlDialect};
+use crate::template_parsing::TemplateLanguage;
+
 /// Supported programming languages for parsing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SupportedLanguage {
     Python,
     JavaScript,
@@ -19,12 +36,18 @@ pub enum SupportedLanguage {
     CSharp,
     Sql,
     Php,
+    Kotlin,
+    Swift,
+    Scala,
+    Lua,
+    Bash,
+    Zig,
 }
 
 impl SupportedLanguage {
-    fn from_extension(ext: &str) -> Option<Self> {
+    pub(crate) fn from_extension(ext: &str) -> Option<Self> {
         match ext {
-            "py" => Some(SupportedLanguage::Python),
+            "py" | "bzl" => Some(SupportedLanguage::Python),
             "js" | "jsx" | "mjs" => Some(SupportedLanguage::JavaScript),
             "ts" | "tsx" => Some(SupportedLanguage::TypeScript),
             "java" => Some(SupportedLanguage::Java),
@@ -36,11 +59,35 @@ impl SupportedLanguage {
             "cs" => Some(SupportedLanguage::CSharp),
             "sql" => Some(SupportedLanguage::Sql),
             "php" => Some(SupportedLanguage::Php),
+            "kt" | "kts" => Some(SupportedLanguage::Kotlin),
+            "swift" => Some(SupportedLanguage::Swift),
+            "scala" | "sc" => Some(SupportedLanguage::Scala),
+            "lua" => Some(SupportedLanguage::Lua),
+            "sh" | "bash" => Some(SupportedLanguage::Bash),
+            "zig" => Some(SupportedLanguage::Zig),
             _ => None,
         }
     }
 
-    fn get_language(&self) -> Language {
+    /// Look up a language by name (case-insensitive). Accepts the names
+    /// reported in `ParseResult.language`, common aliases, and file extensions.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "python" => Some(SupportedLanguage::Python),
+            "javascript" => Some(SupportedLanguage::JavaScript),
+            "typescript" => Some(SupportedLanguage::TypeScript),
+            "golang" => Some(SupportedLanguage::Go),
+            "rust" => Some(SupportedLanguage::Rust),
+            "ruby" => Some(SupportedLanguage::Ruby),
+            "c++" => Some(SupportedLanguage::Cpp),
+            "csharp" | "c#" => Some(SupportedLanguage::CSharp),
+            "kotlin" => Some(SupportedLanguage::Kotlin),
+            "shell" | "zsh" => Some(SupportedLanguage::Bash),
+            other => Self::from_extension(other),
+        }
+    }
+
+    pub(crate) fn get_language(&self) -> Language {
         match self {
             SupportedLanguage::Python => tree_sitter_python::LANGUAGE.into(),
             SupportedLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
@@ -54,207 +101,129 @@ impl SupportedLanguage {
             SupportedLanguage::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
             SupportedLanguage::Sql => tree_sitter_sequel::LANGUAGE.into(),
             SupportedLanguage::Php => tree_sitter_php::LANGUAGE_PHP.into(),
+            SupportedLanguage::Kotlin => tree_sitter_kotlin_sg::LANGUAGE.into(),
+            SupportedLanguage::Swift => tree_sitter_swift::LANGUAGE.into(),
+            SupportedLanguage::Scala => tree_sitter_scala::LANGUAGE.into(),
+            SupportedLanguage::Lua => tree_sitter_lua::LANGUAGE.into(),
+            SupportedLanguage::Bash => tree_sitter_bash::LANGUAGE.into(),
+            SupportedLanguage::Zig => tree_sitter_zig::LANGUAGE.into(),
         }
     }
+}
 
-    fn function_query(&self) -> &str {
-        match self {
-            SupportedLanguage::Python => {
-                r#"
-                (function_definition
-                  name: (identifier) @name
-                  parameters: (parameters) @params
-                  body: (block) @body) @function
-                "#
-            }
-            SupportedLanguage::JavaScript => {
-                r#"
-                (function_declaration
-                  name: (identifier) @name
-                  parameters: (formal_parameters) @params
-                  body: (statement_block) @body) @function
-                "#
-            }
-            SupportedLanguage::TypeScript => {
-                // TypeScript functions can have type annotations
-                r#"
-                (function_declaration
-                  name: (identifier) @name
-                  parameters: (formal_parameters) @params
-                  body: (statement_block) @body) @function
-                "#
-            }
-            SupportedLanguage::Java => {
-                r#"
-                (method_declaration
-                  name: (identifier) @name
-                  parameters: (formal_parameters) @params
-                  body: (block) @body) @function
-                "#
-            }
-            SupportedLanguage::Go => {
-                r#"
-                (function_declaration
-                  name: (identifier) @name
-                  parameters: (parameter_list) @params
-                  body: (block) @body) @function
-                "#
-            }
-            SupportedLanguage::Rust => {
-                r#"
-                (function_item
-                  name: (identifier) @name
-                  parameters: (parameters) @params
-                  body: (block) @body) @function
-                "#
-            }
-            SupportedLanguage::Ruby => {
-                r#"
-                (method
-                  name: (_) @name
-                  parameters: (method_parameters)? @params) @function
-                "#
-            }
-            SupportedLanguage::C | SupportedLanguage::Cpp => {
-                r#"
-                (function_definition
-                  declarator: (function_declarator
-                    declarator: (_) @name)
-                  body: (compound_statement) @body) @function
-                "#
-            }
-            SupportedLanguage::CSharp => {
-                r#"
-                (method_declaration
-                  name: (identifier) @name) @function
-                "#
-            }
-            SupportedLanguage::Sql => {
-                // SQL functions and procedures
-                r#"
-                (create_function) @function
-                "#
-            }
-            SupportedLanguage::Php => {
-                r#"
-                (function_definition
-                  name: (name) @name
-                  parameters: (formal_parameters) @params
-                  body: (compound_statement) @body) @function
-                "#
-            }
+/// Unit kinds beyond functions and classes; enums, type aliases, interfaces
+/// and traits are always extracted, the rest only on request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+    /// Module-level constants (`DEFAULT_TIMEOUT = 30`, `export const`,
+    /// `readonly`), whole declaration with its initializer as content
+    Constant,
+    Enum,
+    TypeAlias,
+    /// Interfaces (TypeScript, Go, Java)
+    Interface,
+    /// Rust traits
+    Trait,
+    /// Module-level mutable variables
+    Global,
+    /// Imported modules, one unit per module (`import`, `use`, `#include`,
+    /// `require`...)
+    Import,
+}
+
+impl UnitKind {
+    pub const ALL: [UnitKind; 7] = [
+        UnitKind::Constant,
+        UnitKind::Enum,
+        UnitKind::TypeAlias,
+        UnitKind::Interface,
+        UnitKind::Trait,
+        UnitKind::Global,
+        UnitKind::Import,
+    ];
+
+    /// Kinds extracted whether or not they are requested
+    pub const DEFAULT: [UnitKind; 4] = [UnitKind::Enum, UnitKind::TypeAlias, UnitKind::Interface, UnitKind::Trait];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "constant" => Some(UnitKind::Constant),
+            "enum" => Some(UnitKind::Enum),
+            "type_alias" => Some(UnitKind::TypeAlias),
+            "interface" => Some(UnitKind::Interface),
+            "trait" => Some(UnitKind::Trait),
+            "global" => Some(UnitKind::Global),
+            "import" => Some(UnitKind::Import),
+            _ => None,
         }
     }
 
-    fn class_query(&self) -> &str {
+    /// The `unit_type` string emitted for units of this kind
+    pub fn unit_type(&self) -> &'static str {
         match self {
-            SupportedLanguage::Python => {
-                r#"
-                (class_definition
-                  name: (identifier) @name
-                  body: (block) @body) @class
-                "#
-            }
-            SupportedLanguage::JavaScript => {
-                r#"
-                (class_declaration
-                  name: (identifier) @name
-                  body: (class_body) @body) @class
-                "#
-            }
-            SupportedLanguage::TypeScript => {
-                // TypeScript can use both identifier and type_identifier for class names
-                r#"
-                (class_declaration
-                  name: (_) @name
-                  body: (class_body) @body) @class
-                "#
-            }
-            SupportedLanguage::Java => {
-                r#"
-                (class_declaration
-                  name: (identifier) @name
-                  body: (class_body) @body) @class
-                "#
-            }
-            SupportedLanguage::Go => {
-                r#"
-                (type_declaration
-                  (type_spec
-                    name: (type_identifier) @name
-                    type: (struct_type) @body)) @class
-                "#
-            }
-            SupportedLanguage::Rust => {
-                r#"
-                (struct_item
-                  name: (type_identifier) @name
-                  body: (field_declaration_list) @body) @class
-                "#
-            }
-            SupportedLanguage::Ruby => {
-                // Ruby has both classes and modules
-                r#"
-                [(class
-                  name: (constant) @name)
-                 (module
-                  name: (constant) @name)] @class
-                "#
-            }
-            SupportedLanguage::C => {
-                // C only has structs, not classes
-                r#"
-                (struct_specifier
-                  name: (type_identifier) @name
-                  body: (field_declaration_list) @body) @class
-                "#
-            }
-            SupportedLanguage::Cpp => {
-                // C++ has both classes and structs - use alternation to capture both
-                r#"
-                [(class_specifier
-                  name: (type_identifier) @name
-                  body: (field_declaration_list) @body)
-                 (struct_specifier
-                  name: (type_identifier) @name
-                  body: (field_declaration_list) @body)] @class
-                "#
-            }
-            SupportedLanguage::CSharp => {
-                r#"
-                [(class_declaration
-                  name: (identifier) @name)
-                 (interface_declaration
-                  name: (identifier) @name)
-                 (struct_declaration
-                  name: (identifier) @name)] @class
-                "#
-            }
-            SupportedLanguage::Sql => {
-                // SQL tables and views as "class" equivalents
-                r#"
-                [
-                  (create_table) @class
-                  (create_view) @class
-                ]
-                "#
-            }
-            SupportedLanguage::Php => {
-                // PHP classes, interfaces, and traits
-                r#"
-                [(class_declaration
-                  name: (name) @name
-                  body: (declaration_list) @body)
-                 (interface_declaration
-                  name: (name) @name
-                  body: (declaration_list) @body)
-                 (trait_declaration
-                  name: (name) @name
-                  body: (declaration_list) @body)] @class
-                "#
-            }
+            UnitKind::Constant => "constant",
+            UnitKind::Enum => "enum",
+            UnitKind::TypeAlias => "type_alias",
+            UnitKind::Interface => "interface",
+            UnitKind::Trait => "trait",
+            UnitKind::Global => "global",
+            UnitKind::Import => "import",
         }
     }
+
+    /// Parse the `unit_kinds` option passed from Python
+    pub fn parse_list(names: &[String]) -> Result<Vec<Self>, String> {
+        names
+            .iter()
+            .map(|name| {
+                Self::from_name(name).ok_or_else(|| {
+                    let expected: Vec<&str> = Self::ALL.iter().map(|k| k.unit_type()).collect();
+                    format!("Unknown unit kind: {} (expected one of: {})", name, expected.join(", "))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Per-call parsing options shared by the single-file and batch entry points
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub unit_kinds: Vec<UnitKind>,
+    /// SQL dialect for `.sql` files; detected from the source when `None`
+    pub sql_dialect: Option<SqlDialect>,
+    /// Also parse the host document of templates (`config.yaml.j2` as YAML)
+    pub parse_template_host: bool,
+    /// Leave unit content out, reporting where it is in the source instead
+    pub content_refs: bool,
+    /// How far JSON/YAML/TOML files are split below their top-level keys
+    pub config_nesting: ConfigNesting,
+    /// Return generated and minified files without parsing them
+    pub skip_generated: bool,
+}
+
+impl ParseOptions {
+    /// Build options from the optional keyword arguments of the Python entry points
+    pub(crate) fn from_args(
+        unit_kinds: Option<Vec<String>>,
+        sql_dialect: Option<String>,
+        parse_template_host: bool,
+        content_refs: bool,
+    ) -> PyResult<Self> {
+        let unit_kinds = UnitKind::parse_list(&unit_kinds.unwrap_or_default())
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let sql_dialect = sql_dialect
+            .map(|name| {
+                SqlDialect::from_name(&name).ok_or_else(|| {
+                    pyo3::exceptions::PyValueError::new_err(format!(
+                        "Unknown SQL dialect: {} (expected one of: generic, postgres, mysql, sqlite, tsql)",
+                        name
+                    ))
+                })
+            })
+            .transpose()?;
+        let config_nesting = ConfigNesting::default();
+        Ok(Self { unit_kinds, sql_dialect, parse_template_host, content_refs, config_nesting, skip_generated: false })
+    }
 }
 
 /// Represents a parsed semantic unit (function, class, etc.)
@@ -262,10 +231,16 @@ impl SupportedLanguage {
 #[pyclass]
 pub struct SemanticUnit {
     #[pyo3(get)]
-    pub unit_type: String, // "function", "class", "import"
+    pub unit_type: Interned, // "function", "class", "import"
     #[pyo3(get)]
     pub name: String,
     #[pyo3(get)]
+    #[serde(default)]
+    pub parent_name: Option<String>, // Innermost enclosing class, impl, namespace or function
+    #[pyo3(get)]
+    #[serde(default)]
+    pub qualified_name: String, // Name prefixed with its enclosing scopes (`Cart.total`, `Foo::bar`)
+    #[pyo3(get)]
     pub start_line: usize,
     #[pyo3(get)]
     pub end_line: usize,
@@ -276,9 +251,28 @@ pub struct SemanticUnit {
     #[pyo3(get)]
     pub signature: String,
     #[pyo3(get)]
+    pub parameters: Option<String>, // Parameter list text, when the language query captures it
+    #[pyo3(get)]
+    #[serde(default)]
+    pub docstring: Option<String>, // Docstring or comment block documenting the unit
+    #[pyo3(get)]
+    #[serde(default)]
+    pub decorators: Vec<String>, // Decorators, annotations and attributes, as written
+    #[pyo3(get)]
     pub content: String,
     #[pyo3(get)]
-    pub language: String,
+    #[serde(default)]
+    pub content_ref: Option<(usize, usize)>, // (offset, length) in the source when content was left out
+    #[pyo3(get)]
+    #[serde(default)]
+    pub content_hash: String, // xxh3 of the unit's text, as 16 hex digits
+    #[pyo3(get)]
+    #[serde(default)]
+    pub unit_id: String, // xxh3 of file path, unit type and qualified name; survives edits to the unit
+    #[pyo3(get)]
+    pub language: Interned,
+    #[pyo3(get)]
+    pub metadata: HashMap<String, String>, // Format-specific details (e.g. migration version)
 }
 
 #[pymethods]
@@ -291,6 +285,83 @@ impl SemanticUnit {
     }
 }
 
+impl SemanticUnit {
+    /// A top-level unit spanning `start..end` of `source`, trailing
+    /// whitespace excluded, for formats parsed without tree-sitter; its
+    /// signature is its first line up to an opening brace. `line_starts`
+    /// are the source's, as `line_starts` returns them.
+    pub(crate) fn spanning(
+        unit_type: &str,
+        name: String,
+        source: &str,
+        line_starts: &[usize],
+        (start, end): (usize, usize),
+        language: &str,
+    ) -> Self {
+        let content = source[start..end].trim_end();
+        let line_of = |byte: usize| line_starts.partition_point(|&line| line <= byte);
+        SemanticUnit {
+            unit_type: unit_type.into(),
+            qualified_name: name.clone(),
+            name,
+            parent_name: None,
+            start_line: line_of(start),
+            end_line: line_of(start + content.len().saturating_sub(1)),
+            start_byte: start,
+            end_byte: start + content.len(),
+            signature: content.lines().next().unwrap_or("").trim().trim_end_matches(['{', ' ']).to_string(),
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: content.to_string(),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: language.into(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Rename the unit, keeping its qualified name in step
+    pub(crate) fn rename(&mut self, name: String) {
+        self.qualified_name = match self.qualified_name.strip_suffix(self.name.as_str()) {
+            Some(scopes) => format!("{}{}", scopes, name),
+            None => name.clone(),
+        };
+        self.name = name;
+    }
+
+    /// Replace the content with its `(offset, length)` in the source
+    fn release_content(&mut self) {
+        self.content_ref = Some((self.start_byte, self.end_byte - self.start_byte));
+        self.content = String::new();
+    }
+
+    /// Move the unit from snippet coordinates into the coordinates of the file
+    /// the snippet was taken from. `base_line` is the 1-based line the snippet
+    /// starts on and `base_byte` its byte offset in that file.
+    pub(crate) fn remap(&mut self, base_line: usize, base_byte: usize) {
+        let line_shift = base_line.saturating_sub(1);
+        self.start_line += line_shift;
+        self.end_line += line_shift;
+        self.start_byte += base_byte;
+        self.end_byte += base_byte;
+        if let Some((offset, _)) = &mut self.content_ref {
+            *offset += base_byte;
+        }
+        if let Some(lines) = self.metadata.get_mut("template_lines") {
+            *lines = lines
+                .split(',')
+                .map(|line| match line.parse::<usize>() {
+                    Ok(line) => (line + line_shift).to_string(),
+                    Err(_) => line.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+        }
+    }
+}
+
 /// Parse result containing all extracted semantic units
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[pyclass]
@@ -303,6 +374,12 @@ pub struct ParseResult {
     pub units: Vec<SemanticUnit>,
     #[pyo3(get)]
     pub parse_time_ms: f64,
+    #[pyo3(get)]
+    #[serde(default)]
+    pub warnings: Vec<String>, // Problems that cost units without failing the parse
+    #[pyo3(get)]
+    #[serde(default)]
+    pub file_classification: String, // "source", "generated" or "minified"
 }
 
 #[pymethods]
@@ -318,47 +395,158 @@ impl ParseResult {
     }
 }
 
+/// Capture indices of a query mapped to the roles they play in a unit.
+///
+/// Queries tag the whole construct with a capture named after the unit type
+/// (`@function`, `@class`) and may tag its parts with `@name`, `@params` and
+/// `@body`. Roles a query doesn't declare are `None`.
+struct CaptureRoles {
+    unit: u32,
+    name: Option<u32>,
+    params: Option<u32>,
+    body: Option<u32>,
+}
+
+impl CaptureRoles {
+    fn new(query: &Query, unit_type: &str) -> Self {
+        let index_of = |role: &str| query.capture_index_for_name(role);
+        Self {
+            // Fall back to the last capture when the unit capture is unnamed
+            unit: index_of(unit_type)
+                .unwrap_or(query.capture_names().len().saturating_sub(1) as u32),
+            name: index_of("name"),
+            params: index_of("params"),
+            body: index_of("body"),
+        }
+    }
+}
+
+/// Run `query` over `root` and append one unit per match of its unit capture.
+/// With `content_refs` the node text isn't copied into the unit.
+fn extract_units(
+    query: &Query,
+    unit_type: &str,
+    root: Node,
+    source: &[u8],
+    language: &str,
+    content_refs: bool,
+    units: &mut Vec<SemanticUnit>,
+) {
+    let roles = CaptureRoles::new(query, unit_type);
+    let (unit_type, language) = (Interned::new(unit_type), Interned::new(language));
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(query, root, source);
+
+    while let Some(match_) = matches.next() {
+        let node_for = |index: Option<u32>| {
+            index.and_then(|i| match_.captures.iter().find(|c| c.index == i).map(|c| c.node))
+        };
+
+        let Some(node) = node_for(Some(roles.unit)) else {
+            continue;
+        };
+        let content = node.utf8_text(source).unwrap_or("");
+        let first_line = content.lines().next().unwrap_or("").trim();
+
+        // Prefer the @name capture; fall back to the first line for queries
+        // without one (e.g. SQL statements)
+        let name = node_for(roles.name)
+            .and_then(|n| n.utf8_text(source).ok())
+            .map(str::trim)
+            .unwrap_or(first_line);
+        let parameters = node_for(roles.params)
+            .and_then(|n| n.utf8_text(source).ok())
+            .map(str::to_string);
+        // Queries without a @name (SQL statements) keep the first line
+        let signature = node_for(roles.name)
+            .and_then(|_| signatures::signature(node, node_for(roles.body), node_for(roles.params), source))
+            .unwrap_or_else(|| first_line.to_string());
+        let mut scopes = scopes::enclosing_scopes(node, source);
+        let parent_name = scopes.last().cloned();
+        scopes.push(name.to_string());
+
+        units.push(SemanticUnit {
+            unit_type: unit_type.clone(),
+            name: name.to_string(),
+            parent_name,
+            qualified_name: scopes.join(scopes::separator(&language)),
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            signature,
+            parameters,
+            docstring: docstrings::docstring(node, source),
+            decorators: signatures::decorators(node, source),
+            content: if content_refs { String::new() } else { content.to_string() },
+            content_ref: content_refs.then(|| (node.start_byte(), node.byte_range().len())),
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: language.clone(),
+            metadata: HashMap::new(),
+        });
+    }
+}
+
 /// Code parser using tree-sitter
+///
+/// Parsers and queries are created the first time a language is parsed and
+/// reused afterwards, so keep one `CodeParser` around (see
+/// `with_thread_parser`) rather than building one per file.
+#[derive(Default)]
 pub struct CodeParser {
-    parsers: HashMap<String, Parser>,
+    parsers: HashMap<SupportedLanguage, Parser>,
+    /// Parsers for registered grammars by registration id
+    custom_parsers: HashMap<usize, Parser>,
 }
 
-impl CodeParser {
-    pub fn new() -> Self {
-        let mut parsers = HashMap::new();
-
-        // Initialize parsers for each language
-        for lang in [
-            SupportedLanguage::Python,
-            SupportedLanguage::JavaScript,
-            SupportedLanguage::TypeScript,
-            SupportedLanguage::Java,
-            SupportedLanguage::Go,
-            SupportedLanguage::Rust,
-            SupportedLanguage::Ruby,
-            SupportedLanguage::C,
-            SupportedLanguage::Cpp,
-            SupportedLanguage::CSharp,
-            SupportedLanguage::Sql,
-            SupportedLanguage::Php,
-        ] {
+/// The parser cached under `key`, created for `language` on first use
+fn cached_parser<'a, K: Hash + Eq>(
+    parsers: &'a mut HashMap<K, Parser>,
+    key: K,
+    language: &Language,
+) -> Result<&'a mut Parser, String> {
+    let limit = core_config::current().parser_cache_size;
+    if limit > 0 && parsers.len() >= limit && !parsers.contains_key(&key) {
+        parsers.clear();
+    }
+    match parsers.entry(key) {
+        std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.into_mut()),
+        std::collections::hash_map::Entry::Vacant(entry) => {
             let mut parser = Parser::new();
-            parser
-                .set_language(&lang.get_language())
-                .expect("Error loading language");
-            parsers.insert(format!("{:?}", lang), parser);
+            parser.set_language(language).map_err(|e| format!("Error loading language: {}", e))?;
+            Ok(entry.insert(parser))
         }
+    }
+}
+
+thread_local! {
+    /// Parser shared by every parse on this thread, including rayon workers
+    static THREAD_PARSER: RefCell<CodeParser> = RefCell::new(CodeParser::new());
+}
+
+/// Run `f` with this thread's cached `CodeParser`. A nested call on the same
+/// thread gets a fresh parser instead of the busy one.
+pub(crate) fn with_thread_parser<R>(f: impl FnOnce(&mut CodeParser) -> R) -> R {
+    THREAD_PARSER.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut parser) => f(&mut parser),
+        Err(_) => f(&mut CodeParser::new()),
+    })
+}
 
-        Self { parsers }
+impl CodeParser {
+    pub fn new() -> Self {
+        Self::default()
     }
 
+    /// Parse a file, extracting functions, classes, the default unit kinds
+    /// and those requested in `options`
     pub fn parse_file(
         &mut self,
         file_path: &str,
         source_code: &str,
+        options: &ParseOptions,
     ) -> Result<ParseResult, String> {
-        let start = std::time::Instant::now();
-
         // Detect language from file extension
         let extension = std::path::Path::new(file_path)
             .extension()
@@ -368,162 +556,1324 @@ impl CodeParser {
         let lang = SupportedLanguage::from_extension(extension)
             .ok_or(format!("Unsupported file extension: {}", extension))?;
 
+        self.parse_with_language(file_path, source_code, lang, options)
+    }
+
+    /// Parse source in an explicit language; `file_path` is only used for
+    /// labelling and path-based detection (e.g. migrations) and may be empty.
+    pub fn parse_with_language(
+        &mut self,
+        file_path: &str,
+        source_code: &str,
+        lang: SupportedLanguage,
+        options: &ParseOptions,
+    ) -> Result<ParseResult, String> {
+        self.parse_tree_with_language(file_path, source_code, lang, options).map(|(result, _)| result)
+    }
+
+    /// `parse_with_language`, also returning the syntax tree the units came from
+    pub(crate) fn parse_tree_with_language(
+        &mut self,
+        file_path: &str,
+        source_code: &str,
+        lang: SupportedLanguage,
+        options: &ParseOptions,
+    ) -> Result<(ParseResult, Tree), String> {
+        let start = std::time::Instant::now();
+
         let lang_name = format!("{:?}", lang);
 
-        // Get parser for this language
-        let parser = self
-            .parsers
-            .get_mut(&lang_name)
-            .ok_or("Parser not found")?;
+        // Get (or create) the parser for this language
+        let parser = cached_parser(&mut self.parsers, lang, &lang.get_language())?;
+
+        // SQL dialect quirks are rewritten in place (offsets unchanged) so the
+        // generic grammar can parse them; unit text still comes from the original
+        let sql_dialect = matches!(lang, SupportedLanguage::Sql)
+            .then(|| options.sql_dialect.unwrap_or_else(|| SqlDialect::detect(source_code)));
+        let grammar_source = match sql_dialect {
+            Some(dialect) => Cow::Owned(sql_parsing::normalize_for_grammar(source_code, dialect)),
+            None => Cow::Borrowed(source_code),
+        };
+
+        // Unresolved merge conflicts are parsed as "ours" (offsets unchanged),
+        // so the surrounding code still yields units
+        let conflicts = conflict_parsing::find_conflicts(source_code);
+        let grammar_source = if conflicts.is_empty() {
+            grammar_source
+        } else {
+            Cow::Owned(conflict_parsing::keep_ours(&grammar_source, &conflicts))
+        };
 
         // Parse the source code
         let tree = parser
-            .parse(source_code, None)
+            .parse(grammar_source.as_ref(), None)
             .ok_or("Failed to parse file")?;
 
         let mut units = Vec::new();
+        let mut warnings = Vec::new();
+        let source_bytes = source_code.as_bytes();
 
-        // Extract functions (with error recovery)
-        match Query::new(&lang.get_language(), lang.function_query()) {
-            Ok(function_query) => {
-                let mut cursor = QueryCursor::new();
-                let mut matches = cursor.matches(&function_query, tree.root_node(), source_code.as_bytes());
-
-                // Find the capture index for "@function" (last capture in the query)
-                let function_capture_idx = function_query.capture_names().iter()
-                    .position(|name| *name == "function")
-                    .unwrap_or(function_query.capture_names().len().saturating_sub(1));
-
-                while let Some(match_) = matches.next() {
-                    // Only process the @function capture, not @name/@params/@body
-                    if let Some(capture) = match_.captures.iter().find(|c| c.index as usize == function_capture_idx) {
-                        let node = capture.node;
-                        let name = node
-                            .utf8_text(source_code.as_bytes())
-                            .unwrap_or("<unknown>")
-                            .lines()
-                            .next()
-                            .unwrap_or("")
-                            .trim();
-
-                        units.push(SemanticUnit {
-                            unit_type: "function".to_string(),
-                            name: name.to_string(),
-                            start_line: node.start_position().row + 1,
-                            end_line: node.end_position().row + 1,
-                            start_byte: node.start_byte(),
-                            end_byte: node.end_byte(),
-                            signature: name.to_string(),
-                            content: node.utf8_text(source_code.as_bytes()).unwrap_or("").to_string(),
-                            language: lang_name.clone(),
-                        });
-                    }
-                }
-            }
-            Err(e) => {
-                // Log error but continue parsing (skip function extraction for this file)
-                eprintln!("Warning: Function query failed for {}: {}. Continuing without function extraction.", file_path, e);
+        // Extract functions, the requested and default kinds and classes
+        // (languages without a construct have no query for it); a query
+        // that fails to compile is skipped with a warning. Kinds run before
+        // classes so a node both match (a Ruby module as an enum) keeps
+        // the kind the caller asked for.
+        let defaults = UnitKind::DEFAULT.iter().filter(|k| !options.unit_kinds.contains(k));
+        let kinds = options.unit_kinds.iter().chain(defaults).map(|k| k.unit_type());
+        let unit_types = std::iter::once("function").chain(kinds).chain(std::iter::once("class"));
+        for unit_type in unit_types {
+            match query_packs::query(lang, unit_type) {
+                Some(Ok(query)) => extract_units(
+                    &query,
+                    unit_type,
+                    tree.root_node(),
+                    source_bytes,
+                    &lang_name,
+                    options.content_refs,
+                    &mut units,
+                ),
+                Some(Err(e)) => warnings.push(format!("{} extraction skipped: {}", unit_type, e)),
+                None => {}
             }
         }
 
-        // Extract classes (with error recovery)
-        match Query::new(&lang.get_language(), lang.class_query()) {
-            Ok(class_query) => {
-                let mut cursor = QueryCursor::new();
-                let mut matches = cursor.matches(&class_query, tree.root_node(), source_code.as_bytes());
-
-                // Find the capture index for "@class" (last capture in the query)
-                let class_capture_idx = class_query.capture_names().iter()
-                    .position(|name| *name == "class")
-                    .unwrap_or(class_query.capture_names().len().saturating_sub(1));
-
-                while let Some(match_) = matches.next() {
-                    // Only process the @class capture, not @name/@body
-                    if let Some(capture) = match_.captures.iter().find(|c| c.index as usize == class_capture_idx) {
-                        let node = capture.node;
-                        let name = node
-                            .utf8_text(source_code.as_bytes())
-                            .unwrap_or("<unknown>")
-                            .lines()
-                            .next()
-                            .unwrap_or("")
-                            .trim();
-
-                        units.push(SemanticUnit {
-                            unit_type: "class".to_string(),
-                            name: name.to_string(),
-                            start_line: node.start_position().row + 1,
-                            end_line: node.end_position().row + 1,
-                            start_byte: node.start_byte(),
-                            end_byte: node.end_byte(),
-                            signature: name.to_string(),
-                            content: node.utf8_text(source_code.as_bytes()).unwrap_or("").to_string(),
-                            language: lang_name.clone(),
-                        });
-                    }
+        // Imports are named after the module; drop the quotes or angle
+        // brackets of string paths (`"./util"`, `<stdio.h>`)
+        for unit in units.iter_mut().filter(|u| u.unit_type == "import") {
+            let name = unit.name.trim_matches(|c| matches!(c, '"' | '\'' | '`' | '<' | '>')).to_string();
+            unit.rename(name);
+        }
+
+        // Statement splitting covers what the grammar doesn't model (indexes,
+        // triggers, procedures, migration statements) and statements it failed
+        // to parse; units the queries already found are kept as-is
+        if let Some(dialect) = sql_dialect {
+            // The SQL queries have no @name capture; name units after the object they create
+            for unit in units.iter_mut() {
+                let statement = &source_code[unit.start_byte..unit.end_byte];
+                if let Some(name) = sql_parsing::classify_statement(statement).name {
+                    unit.rename(name);
                 }
             }
-            Err(e) => {
-                // Log error but continue parsing (skip class extraction for this file)
-                eprintln!("Warning: Class query failed for {}: {}. Continuing without class extraction.", file_path, e);
-            }
+            let found: std::collections::HashSet<(Interned, usize)> =
+                units.iter().map(|u| (u.unit_type.clone(), u.start_byte)).collect();
+            units.extend(
+                sql_parsing::extract_statement_units(file_path, source_code, dialect)
+                    .into_iter()
+                    .filter(|u| !found.contains(&(u.unit_type.clone(), u.start_byte))),
+            );
         }
 
+        // Migration files get one unit per direction, built on the function units above
+        let migration_units = crate::migrations::extract_migration_units(file_path, source_code, &lang_name, &units);
+        units.extend(migration_units);
+
+        let conflict_units = conflict_parsing::conflict_units(source_code, &conflicts, &units, &lang_name);
+        units.extend(conflict_units);
+
+        // Registered custom queries run last and are deduplicated among
+        // themselves, so a custom unit on the same node as a function survives
+        let mut units = dedup_units(units);
+        let mut custom_units = Vec::new();
+        for (unit_type, query) in query_packs::custom_queries(lang) {
+            let root = tree.root_node();
+            extract_units(&query, &unit_type, root, source_bytes, &lang_name, options.content_refs, &mut custom_units);
+        }
+        units.extend(dedup_units(custom_units));
+
         let elapsed = start.elapsed();
 
-        Ok(ParseResult {
+        let result = ParseResult {
             file_path: file_path.to_string(),
             language: lang_name,
             units,
             parse_time_ms: elapsed.as_secs_f64() * 1000.0,
+            warnings,
+            file_classification: String::new(),
+        };
+        Ok((result, tree))
+    }
+
+    /// Parse source in a grammar registered with `register_language`,
+    /// extracting its functions and classes
+    pub fn parse_custom(
+        &mut self,
+        file_path: &str,
+        source_code: &str,
+        custom: &CustomLanguage,
+    ) -> Result<ParseResult, String> {
+        let start = std::time::Instant::now();
+        let parser = cached_parser(&mut self.custom_parsers, custom.id, &custom.language)?;
+
+        let conflicts = conflict_parsing::find_conflicts(source_code);
+        let grammar_source = if conflicts.is_empty() {
+            Cow::Borrowed(source_code)
+        } else {
+            Cow::Owned(conflict_parsing::keep_ours(source_code, &conflicts))
+        };
+        let tree = parser
+            .parse(grammar_source.as_ref(), None)
+            .ok_or("Failed to parse file")?;
+
+        let mut units = Vec::new();
+        let root = tree.root_node();
+        let source = source_code.as_bytes();
+        extract_units(&custom.function_query, "function", root, source, &custom.name, false, &mut units);
+        extract_units(&custom.class_query, "class", root, source, &custom.name, false, &mut units);
+        let conflict_units = conflict_parsing::conflict_units(source_code, &conflicts, &units, &custom.name);
+        units.extend(conflict_units);
+
+        Ok(ParseResult {
+            file_path: file_path.to_string(),
+            language: custom.name.clone(),
+            units: dedup_units(units),
+            parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+            warnings: Vec::new(),
+            file_classification: String::new(),
         })
     }
 }
 
-/// Parse a source file and extract semantic units
-#[pyfunction]
-pub fn parse_source_file(file_path: String, source_code: String) -> PyResult<ParseResult> {
-    // Check if this is a configuration file first
-    let extension = std::path::Path::new(&file_path)
+/// Byte offset at which each line of `source` starts
+pub(crate) fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect()
+}
+
+/// Remove units emitted more than once by overlapping query passes.
+///
+/// Two units are duplicates when they cover the same byte span with the same
+/// name (e.g. one node matched by several alternation branches, or by both the
+/// function and class pass); the first occurrence wins, so function units take
+/// precedence over optional kinds, and both over class units. A unit is also
+/// dropped when it is contained in another unit of the same type and name,
+/// which happens when a query matches both a wrapper node and the declaration
+/// inside it. Order is preserved.
+pub fn dedup_units(units: Vec<SemanticUnit>) -> Vec<SemanticUnit> {
+    let mut seen_spans = std::collections::HashSet::new();
+    let unique: Vec<SemanticUnit> = units
+        .into_iter()
+        .filter(|u| seen_spans.insert((u.start_byte, u.end_byte, u.name.clone())))
+        .collect();
+
+    let is_shadowed = |unit: &SemanticUnit| {
+        unique.iter().any(|outer| {
+            outer.unit_type == unit.unit_type
+                && outer.name == unit.name
+                && outer.start_byte <= unit.start_byte
+                && unit.end_byte <= outer.end_byte
+                && (outer.start_byte, outer.end_byte) != (unit.start_byte, unit.end_byte)
+        })
+    };
+
+    unique.iter().filter(|u| !is_shadowed(u)).cloned().collect()
+}
+
+/// Parse a single file, dispatching config formats to the native config parsers
+/// and everything else to tree-sitter. With `skip_generated`, generated and
+/// minified files come back classified but without units.
+pub(crate) fn parse_any_file(
+    file_path: &str,
+    source_code: &str,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    if options.skip_generated {
+        let class = file_classification::classify(file_path, source_code);
+        if class != FileClass::Source {
+            return Ok(ParseResult {
+                file_path: file_path.to_string(),
+                language: String::new(),
+                units: Vec::new(),
+                parse_time_ms: 0.0,
+                warnings: vec![format!("Skipped {} file", class.name())],
+                file_classification: class.name().to_string(),
+            });
+        }
+    }
+    parse_by_extension(file_path, source_code, options).map(|result| finish(result, source_code, options))
+}
+
+/// Set each unit's `content_hash` from its text (its `content`, or the span
+/// of `source` its `content_ref` points to) and its `unit_id` from where it
+/// lives. Units sharing a type and qualified name (overloads, repeated
+/// headings) are told apart by their order in the file.
+pub(crate) fn assign_unit_ids(file_path: &str, units: &mut [SemanticUnit], source: &str) {
+    use xxhash_rust::xxh3::xxh3_64;
+
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for unit in units {
+        let text = unit.content_ref.and_then(|(offset, length)| source.get(offset..offset + length));
+        unit.content_hash = format!("{:016x}", xxh3_64(text.unwrap_or(&unit.content).as_bytes()));
+        let occurrence = seen.entry((unit.unit_type.to_string(), unit.qualified_name.clone())).or_default();
+        let mut key = format!("{}\0{}\0{}", file_path, unit.unit_type, unit.qualified_name);
+        if *occurrence > 0 {
+            key.push_str(&format!("\0{}", occurrence));
+        }
+        *occurrence += 1;
+        unit.unit_id = format!("{:016x}", xxh3_64(key.as_bytes()));
+    }
+}
+
+/// Classify the file and identify every unit, then leave content out when
+/// the options ask for content refs; the tree-sitter path never copies it,
+/// the other parsers drop it here
+fn finish(mut result: ParseResult, source: &str, options: &ParseOptions) -> ParseResult {
+    result.file_classification = file_classification::classify(&result.file_path, source).name().to_string();
+    assign_unit_ids(&result.file_path, &mut result.units, source);
+    if options.content_refs {
+        result.units.iter_mut().filter(|u| u.content_ref.is_none()).for_each(SemanticUnit::release_content);
+    }
+    result
+}
+
+fn is_config_extension(extension: &str) -> bool {
+    matches!(extension, "json" | "jsonc" | "json5" | "yaml" | "yml" | "toml" | "ini" | "cfg" | "properties" | "xml")
+}
+
+/// Whether `parse_any_file` has a parser for a file, by its name or extension
+pub(crate) fn is_parseable(file_path: &str) -> bool {
+    let extension = std::path::Path::new(file_path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    crate::build_parsing::is_build_file(file_path)
+        || crate::infra_parsing::is_dockerfile(file_path)
+        || crate::config_parsing::is_env_file(file_path)
+        || is_config_extension(extension)
+        || matches!(extension, "tf" | "hcl" | "md" | "markdown" | "ipynb")
+        || crate::email_parsing::email_format(file_path).is_some()
+        || extension == "ics"
+        || TemplateLanguage::from_extension(extension).is_some()
+        || custom_languages::by_extension(extension).is_some()
+        || SupportedLanguage::from_extension(extension).is_some()
+        || (cfg!(feature = "pdf") && crate::pdf_parsing::is_pdf(file_path))
+}
+
+fn parse_by_extension(file_path: &str, source_code: &str, options: &ParseOptions) -> Result<ParseResult, String> {
+    let extension = std::path::Path::new(file_path)
         .extension()
         .and_then(|e| e.to_str())
         .unwrap_or("");
 
+    // Handle Bazel/Buck build files, which are recognized by name
+    if crate::build_parsing::is_build_file(file_path) {
+        return crate::build_parsing::parse_build_file(file_path, source_code);
+    }
+
+    // Handle Dockerfiles, which are recognized by name
+    if crate::infra_parsing::is_dockerfile(file_path) {
+        return crate::infra_parsing::parse_dockerfile(file_path, source_code);
+    }
+
+    // Handle Terraform and other HCL by top-level block
+    if matches!(extension, "tf" | "hcl") {
+        return crate::infra_parsing::parse_hcl(file_path, source_code);
+    }
+
     // Handle config files with native parsers
-    if matches!(extension, "json" | "yaml" | "yml" | "toml") {
-        return crate::config_parsing::parse_config_file(&file_path, &source_code)
-            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e));
+    if is_config_extension(extension) || crate::config_parsing::is_env_file(file_path) {
+        return crate::config_parsing::parse_config_file(file_path, source_code, &options.config_nesting);
+    }
+
+    // Handle Markdown documents by heading
+    if matches!(extension, "md" | "markdown") {
+        return crate::markdown_parsing::parse_markdown(file_path, source_code);
     }
 
-    // Handle code files with tree-sitter
-    let mut parser = CodeParser::new();
-    parser
-        .parse_file(&file_path, &source_code)
-        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e))
+    // Handle mailing list archives and saved messages message by message
+    if crate::email_parsing::email_format(file_path).is_some() {
+        return crate::email_parsing::parse_email(file_path, source_code);
+    }
+
+    // Handle iCalendar files event by event
+    if extension == "ics" {
+        return crate::calendar_parsing::parse_calendar(file_path, source_code);
+    }
+
+    // Handle Jupyter notebooks cell by cell
+    if extension == "ipynb" {
+        return crate::notebook_parsing::parse_notebook(file_path, source_code, options);
+    }
+
+    // Handle Jinja/ERB/Handlebars templates
+    if TemplateLanguage::from_extension(extension).is_some() {
+        return crate::template_parsing::parse_template_file(file_path, source_code, options);
+    }
+
+    // Registered grammars take precedence over the built-in code languages
+    if let Some(custom) = custom_languages::by_extension(extension) {
+        return with_thread_parser(|parser| parser.parse_custom(file_path, source_code, &custom));
+    }
+
+    // Handle code files with tree-sitter, reusing this thread's parser
+    with_thread_parser(|parser| parser.parse_file(file_path, source_code, options))
+}
+
+/// A language chosen by name rather than detected from a file extension
+#[derive(Debug, Clone)]
+pub(crate) enum NamedLanguage {
+    Code(SupportedLanguage),
+    Config(&'static str),
+    Template(TemplateLanguage),
+    Build,
+    Markdown,
+    Email,
+    Calendar,
+    Hcl,
+    Dockerfile,
+    Custom(Arc<CustomLanguage>),
+}
+
+impl NamedLanguage {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        if let Some(custom) = custom_languages::by_name(&name) {
+            return Some(NamedLanguage::Custom(custom));
+        }
+        match name.as_str() {
+            "json" | "jsonc" | "json5" => Some(NamedLanguage::Config("json")),
+            "yaml" | "yml" => Some(NamedLanguage::Config("yaml")),
+            "toml" => Some(NamedLanguage::Config("toml")),
+            "ini" | "cfg" => Some(NamedLanguage::Config("ini")),
+            "env" | "dotenv" => Some(NamedLanguage::Config("env")),
+            "properties" => Some(NamedLanguage::Config("properties")),
+            "xml" => Some(NamedLanguage::Config("xml")),
+            "starlark" | "bazel" | "buck" => Some(NamedLanguage::Build),
+            "markdown" | "md" => Some(NamedLanguage::Markdown),
+            "email" | "eml" | "mbox" => Some(NamedLanguage::Email),
+            "calendar" | "icalendar" | "ical" | "ics" => Some(NamedLanguage::Calendar),
+            "terraform" | "tf" | "hcl" => Some(NamedLanguage::Hcl),
+            "dockerfile" | "docker" | "containerfile" => Some(NamedLanguage::Dockerfile),
+            _ => TemplateLanguage::from_extension(&name)
+                .map(NamedLanguage::Template)
+                .or_else(|| SupportedLanguage::from_name(&name).map(NamedLanguage::Code)),
+        }
+    }
+}
+
+/// Parse source in an explicit language, bypassing extension detection.
+pub(crate) fn parse_as_language(
+    file_path: &str,
+    source_code: &str,
+    language: NamedLanguage,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    let result = match language {
+        NamedLanguage::Config(format) => {
+            crate::config_parsing::parse_config_source(file_path, source_code, format, &options.config_nesting)
+        }
+        NamedLanguage::Template(template) => {
+            crate::template_parsing::parse_template_source(file_path, source_code, template, options)
+        }
+        NamedLanguage::Code(lang) => {
+            with_thread_parser(|parser| parser.parse_with_language(file_path, source_code, lang, options))
+        }
+        NamedLanguage::Build => crate::build_parsing::parse_build_file(file_path, source_code),
+        NamedLanguage::Markdown => crate::markdown_parsing::parse_markdown(file_path, source_code),
+        NamedLanguage::Email => crate::email_parsing::parse_email(file_path, source_code),
+        NamedLanguage::Calendar => crate::calendar_parsing::parse_calendar(file_path, source_code),
+        NamedLanguage::Hcl => crate::infra_parsing::parse_hcl(file_path, source_code),
+        NamedLanguage::Dockerfile => crate::infra_parsing::parse_dockerfile(file_path, source_code),
+        NamedLanguage::Custom(custom) => {
+            with_thread_parser(|parser| parser.parse_custom(file_path, source_code, &custom))
+        }
+    };
+    result.map(|result| finish(result, source_code, options))
+}
+
+/// Parse files in parallel, returning one result per input in input order.
+///
+/// Rayon schedules work out of order, but collecting an indexed parallel
+/// iterator into a `Vec` places each result at its input's position, so
+/// `results[i]` always belongs to `files[i]`.
+pub(crate) fn parse_files_ordered(
+    files: &[(String, String)],
+    options: &ParseOptions,
+) -> Vec<Result<ParseResult, String>> {
+    use rayon::prelude::*;
+
+    core_config::install(|| {
+        files
+            .par_iter()
+            .map(|(path, content)| parse_any_file(path, content, options))
+            .collect()
+    })
+}
+
+/// Parse files in parallel, failing with the first error in input order
+pub(crate) fn parse_batch(files: &[(String, String)], options: &ParseOptions) -> Result<Vec<ParseResult>, String> {
+    parse_files_ordered(files, options).into_iter().collect()
+}
+
+/// Files at least this large are memory-mapped rather than read into a buffer
+const MMAP_THRESHOLD: u64 = 1 << 20;
+
+/// Read and parse a file; large files are parsed straight from a memory map,
+/// PDFs from their bytes
+pub(crate) fn parse_path(path: &str, options: &ParseOptions) -> Result<ParseResult, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {}", path, e);
+    if crate::pdf_parsing::is_pdf(path) {
+        let mut result = crate::pdf_parsing::parse_pdf(path, &std::fs::read(path).map_err(read_error)?)?;
+        assign_unit_ids(path, &mut result.units, "");
+        result.file_classification = FileClass::Source.name().to_string();
+        return Ok(result);
+    }
+    let mut file = std::fs::File::open(path).map_err(read_error)?;
+    if file.metadata().map_err(read_error)?.len() < MMAP_THRESHOLD {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut bytes).map_err(read_error)?;
+        return parse_file_bytes(path, &bytes, options);
+    }
+    // SAFETY: the map is read-only and dropped before returning; as with any
+    // mapped file, truncating it concurrently is the caller's responsibility
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(read_error)?;
+    parse_file_bytes(path, &map, options)
+}
+
+/// Parse UTF-8 bytes in place, and anything else after transcoding
+fn parse_file_bytes(path: &str, bytes: &[u8], options: &ParseOptions) -> Result<ParseResult, String> {
+    match std::str::from_utf8(bytes) {
+        Ok(source) => parse_any_file(path, source, options),
+        Err(_) => crate::source_encoding::parse_bytes(path, bytes, None, options),
+    }
+}
+
+/// Read and parse files in parallel, failing with the first error in input order
+pub(crate) fn parse_paths(paths: &[String], options: &ParseOptions) -> Result<Vec<ParseResult>, String> {
+    use rayon::prelude::*;
+
+    let results: Vec<_> = core_config::install(|| paths.par_iter().map(|path| parse_path(path, options)).collect());
+    results.into_iter().collect()
+}
+
+/// Parse an excerpt in an explicit language and shift its units so they
+/// point into the file it was taken from
+pub(crate) fn parse_excerpt(
+    path_hint: &str,
+    source: &str,
+    language: NamedLanguage,
+    base_line: usize,
+    base_byte: usize,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    let mut result = parse_as_language(path_hint, source, language, options)?;
+    for unit in result.units.iter_mut() {
+        unit.remap(base_line, base_byte);
+    }
+    Ok(result)
+}
+
+/// Language named by a Python caller, or ValueError
+fn named_language(language: &str) -> PyResult<NamedLanguage> {
+    NamedLanguage::from_name(language)
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown language: {}", language)))
+}
+
+/// Parse a source file and extract semantic units
+///
+/// Functions, classes, enums, type aliases, interfaces and traits are
+/// always extracted; `unit_kinds` optionally requests more: "constant",
+/// "global", and "import" (one unit per imported module, named after it).
+/// `sql_dialect` ("postgres", "mysql", "sqlite", "tsql") overrides dialect
+/// detection for `.sql` files. `parse_template_host` additionally parses
+/// the document a Jinja/ERB/Handlebars template renders to (e.g.
+/// `settings.py.j2`).
+///
+/// With `content_refs`, units leave `content` empty and instead carry
+/// `content_ref`, the `(offset, length)` of their text in the source, so
+/// huge files don't copy every unit's text into Python.
+#[pyfunction]
+#[pyo3(signature = (file_path, source_code, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false))]
+pub fn parse_source_file(
+    py: Python<'_>,
+    file_path: String,
+    source_code: String,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+) -> PyResult<ParseResult> {
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    py.detach(|| parse_any_file(&file_path, &source_code, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// Parse source in an explicitly chosen language, without a file name
+///
+/// `language` is matched case-insensitively against language names (as
+/// reported in `ParseResult.language`), common aliases, and file extensions,
+/// e.g. "python", "py", "TypeScript", "yaml", "jinja". `path_hint`, when
+/// given, is used as the result's `file_path` and for path-based detection
+/// such as migrations and template host formats; it never selects the
+/// language. Raises ValueError for an unknown language.
+#[pyfunction]
+#[pyo3(signature = (source, language, path_hint=None, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn parse_source(
+    py: Python<'_>,
+    source: String,
+    language: String,
+    path_hint: Option<String>,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+) -> PyResult<ParseResult> {
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let named = named_language(&language)?;
+    py.detach(|| parse_as_language(path_hint.as_deref().unwrap_or(""), &source, named, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// Parse an excerpt of a file (e.g. a diff hunk) in an explicitly chosen language
+///
+/// Works like `parse_source`, then shifts every unit's lines and byte offsets
+/// so they point into the original file: `base_line` is the 1-based line the
+/// excerpt starts on and `base_byte` the byte offset of its first character.
+#[pyfunction]
+#[pyo3(signature = (source, language, base_line=1, base_byte=0, path_hint=None, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn parse_snippet(
+    py: Python<'_>,
+    source: String,
+    language: String,
+    base_line: usize,
+    base_byte: usize,
+    path_hint: Option<String>,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+) -> PyResult<ParseResult> {
+    if base_line == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err("base_line is 1-based and must be at least 1"));
+    }
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let named = named_language(&language)?;
+    let path_hint = path_hint.as_deref().unwrap_or("");
+    py.detach(|| parse_excerpt(path_hint, &source, named, base_line, base_byte, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
 }
 
 /// Batch parse multiple files in parallel
+///
+/// Results are returned in the same order as `files`, so callers may zip them
+/// against their inputs. If any file fails, the error for the first failing
+/// file (in input order) is raised.
+///
+/// Every result reports its `file_classification`: "source", or
+/// "generated" (protobuf output, `@generated`/"DO NOT EDIT" headers,
+/// transpiled code with a source map) or "minified" (`.min.js`, very long
+/// lines). With `skip_generated`, those two are returned without units
+/// instead of being parsed.
 #[pyfunction]
-pub fn batch_parse_files(files: Vec<(String, String)>) -> PyResult<Vec<ParseResult>> {
-    use rayon::prelude::*;
+#[pyo3(signature = (files, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false, skip_generated=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_parse_files(
+    py: Python<'_>,
+    files: Vec<(String, String)>,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+    skip_generated: bool,
+) -> PyResult<Vec<ParseResult>> {
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let options = ParseOptions { skip_generated, ..options };
+    py.detach(|| parse_batch(&files, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// Batch parse files read from disk in parallel
+///
+/// Like `batch_parse_files`, but the files are read natively instead of
+/// being passed in, and files of 1 MiB or more are memory-mapped. Files
+/// that aren't UTF-8 are transcoded as by `parse_source_bytes`. Results are
+/// in the order of `paths`; if any file can't be read or parsed, the error
+/// for the first one (in input order) is raised.
+/// Combine with `content_refs` to slice unit text lazily from the file, and
+/// with `skip_generated` to leave generated and minified files unparsed.
+#[pyfunction]
+#[pyo3(signature = (paths, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false, skip_generated=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_parse_paths(
+    py: Python<'_>,
+    paths: Vec<String>,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+    skip_generated: bool,
+) -> PyResult<Vec<ParseResult>> {
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let options = ParseOptions { skip_generated, ..options };
+    py.detach(|| parse_paths(&paths, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(unit_type: &str, name: &str, start_byte: usize, end_byte: usize) -> SemanticUnit {
+        SemanticUnit {
+            unit_type: unit_type.into(),
+            name: name.to_string(),
+            parent_name: None,
+            qualified_name: name.to_string(),
+            start_line: 1,
+            end_line: 1,
+            start_byte,
+            end_byte,
+            signature: name.to_string(),
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: String::new(),
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: "Test".into(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn assert_no_duplicate_spans(file_path: &str, source: &str) {
+        let result = CodeParser::new().parse_file(file_path, source, &ParseOptions::default()).unwrap();
+        let mut spans: Vec<(usize, usize)> =
+            result.units.iter().map(|u| (u.start_byte, u.end_byte)).collect();
+        let total = spans.len();
+        spans.sort();
+        spans.dedup();
+        assert_eq!(spans.len(), total, "duplicate spans in {}: {:?}", file_path, result.units);
+    }
+
+    #[test]
+    fn test_dedup_drops_identical_spans() {
+        let units = vec![
+            unit("class", "Foo", 0, 20),
+            unit("class", "Foo", 0, 20),
+            unit("class", "Bar", 30, 40),
+        ];
+        let names: Vec<String> = dedup_units(units).into_iter().map(|u| u.name).collect();
+        assert_eq!(names, vec!["Foo", "Bar"]);
+    }
 
-    let results: Result<Vec<ParseResult>, String> = files
-        .par_iter()
-        .map(|(path, content)| {
-            // Check if this is a configuration file
-            let extension = std::path::Path::new(path)
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("");
-
-            if matches!(extension, "json" | "yaml" | "yml" | "toml") {
-                crate::config_parsing::parse_config_file(path, content)
-            } else {
-                let mut parser = CodeParser::new();
-                parser.parse_file(path, content)
+    #[test]
+    fn test_dedup_prefers_first_pass_for_same_span() {
+        let units = vec![unit("function", "f", 0, 20), unit("class", "f", 0, 20)];
+        let deduped = dedup_units(units);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].unit_type, "function");
+    }
+
+    #[test]
+    fn test_dedup_resolves_containment_of_same_unit() {
+        let units = vec![unit("function", "f", 5, 15), unit("function", "f", 0, 20)];
+        let deduped = dedup_units(units);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!((deduped[0].start_byte, deduped[0].end_byte), (0, 20));
+    }
+
+    #[test]
+    fn test_dedup_keeps_nested_distinct_units() {
+        // A method inside a class is a separate unit, not a duplicate
+        let units = vec![unit("function", "m", 10, 20), unit("class", "A", 0, 30)];
+        assert_eq!(dedup_units(units).len(), 2);
+    }
+
+    #[test]
+    fn test_names_come_from_name_capture() {
+        let cases = [
+            ("a.py", "def greet(name, greeting='hi'):\n    pass\n", "greet", Some("(name, greeting='hi')")),
+            ("a.js", "function add(a, b) { return a + b; }\n", "add", Some("(a, b)")),
+            ("a.go", "package main\nfunc Run(ctx context.Context) error { return nil }\n", "Run", Some("(ctx context.Context)")),
+            ("a.rs", "pub fn parse(input: &str) -> u32 { 0 }\n", "parse", Some("(input: &str)")),
+            ("a.java", "class A { int size(int x) { return x; } }\n", "size", Some("(int x)")),
+            ("a.c", "int main(void) { return 0; }\n", "main", None),
+            ("a.cs", "class A { void Save() {} }\n", "Save", None),
+            ("a.php", "<?php\nfunction render($view) { return 1; }\n", "render", Some("($view)")),
+            ("a.kt", "fun greet(name: String) { println(name) }\n", "greet", Some("(name: String)")),
+            ("a.swift", "func greet(name: String) { print(name) }\n", "greet", None),
+            ("a.scala", "object A { def greet(name: String): Unit = println(name) }\n", "greet", None),
+            ("a.lua", "local function greet(name) print(name) end\n", "greet", Some("(name)")),
+            ("a.sh", "greet() { echo \"$1\"; }\n", "greet", None),
+            ("a.zig", "fn add(a: i32, b: i32) i32 { return a + b; }\n", "add", Some("(a: i32, b: i32)")),
+        ];
+
+        for (path, source, name, params) in cases {
+            let result = CodeParser::new().parse_file(path, source, &ParseOptions::default()).unwrap();
+            let function = result.units.iter().find(|u| u.unit_type == "function").unwrap();
+            assert_eq!(function.name, name, "{}", path);
+            assert_eq!(function.parameters.as_deref(), params, "{}", path);
+            assert!(function.signature.contains(name), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_class_names_come_from_name_capture() {
+        let source = "package main\ntype (\n A struct{}\n B struct{}\n)\n";
+        let result = CodeParser::new().parse_file("a.go", source, &ParseOptions::default()).unwrap();
+        let names: Vec<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B"]);
+
+        let result = CodeParser::new()
+            .parse_file("a.rb", "module Billing\n  class Invoice\n  end\nend\n", &ParseOptions::default())
+            .unwrap();
+        let names: Vec<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["Billing", "Invoice"]);
+    }
+
+    #[test]
+    fn test_sql_units_are_named_after_created_object() {
+        let result = CodeParser::new()
+            .parse_file("a.sql", "CREATE TABLE users (\n  id INT\n);\n", &ParseOptions::default())
+            .unwrap();
+        assert_eq!(result.units[0].name, "users");
+        assert_eq!(result.units[0].signature, "CREATE TABLE users (");
+        assert!(result.units[0].parameters.is_none());
+    }
+
+    #[test]
+    fn test_sql_dialects_extract_indexes_triggers_and_procedures() {
+        let cases = [
+            (
+                SqlDialect::MySql,
+                "CREATE TABLE `users` (`id` INT) ENGINE=InnoDB;\nDELIMITER //\nCREATE TRIGGER trg BEFORE INSERT ON users FOR EACH ROW BEGIN SET NEW.id = 1; END//\nDELIMITER ;\nCREATE INDEX idx_users ON users (id);\n",
+                vec![("class", "users"), ("trigger", "trg"), ("index", "idx_users")],
+            ),
+            (
+                SqlDialect::Postgres,
+                "CREATE TABLE t (id SERIAL PRIMARY KEY);\nCREATE UNIQUE INDEX CONCURRENTLY idx ON t (id);\nCREATE TRIGGER trg AFTER INSERT ON t FOR EACH ROW EXECUTE FUNCTION f();\n",
+                vec![("class", "t"), ("index", "idx"), ("trigger", "trg")],
+            ),
+            (
+                SqlDialect::Sqlite,
+                "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT);\nCREATE TRIGGER tr AFTER UPDATE ON t BEGIN UPDATE t SET id = 1; DELETE FROM t; END;\nCREATE TABLE u (x int);\n",
+                vec![("class", "t"), ("trigger", "tr"), ("class", "u")],
+            ),
+            (
+                SqlDialect::TSql,
+                "CREATE TABLE [dbo].[Users] ([Id] INT)\nGO\nCREATE PROCEDURE GetUsers AS SELECT * FROM Users\nGO\n",
+                vec![("class", "dbo.Users"), ("function", "GetUsers")],
+            ),
+        ];
+
+        for (dialect, source, expected) in cases {
+            let options = ParseOptions { sql_dialect: Some(dialect), ..Default::default() };
+            let result = CodeParser::new().parse_file("schema.sql", source, &options).unwrap();
+            let mut found: Vec<(&str, &str)> =
+                result.units.iter().map(|u| (u.unit_type.as_str(), u.name.as_str())).collect();
+            let mut expected = expected;
+            found.sort();
+            expected.sort();
+            assert_eq!(found, expected, "{:?}", dialect);
+            for unit in &result.units {
+                assert_eq!(&source[unit.start_byte..unit.end_byte], unit.content, "{:?}", dialect);
             }
-        })
-        .collect();
+        }
+    }
+
+    #[test]
+    fn test_sql_migration_files_emit_one_unit_per_statement() {
+        let source = "CREATE TABLE a (id int);\nALTER TABLE a ADD COLUMN x int;\nINSERT INTO a VALUES (1);\n";
+        let result = CodeParser::new()
+            .parse_file("db/migrations/001_init.sql", source, &ParseOptions::default())
+            .unwrap();
+        let statements: Vec<&str> = result
+            .units
+            .iter()
+            .filter(|u| u.unit_type == "migration_statement")
+            .map(|u| u.name.as_str())
+            .collect();
+        assert_eq!(statements, vec!["CREATE TABLE a", "ALTER TABLE a", "INSERT INTO a"]);
 
-    results.map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e))
+        let plain = CodeParser::new().parse_file("schema.sql", source, &ParseOptions::default()).unwrap();
+        assert!(plain.units.iter().all(|u| u.unit_type != "migration_statement"));
+    }
+
+    fn kind_names(file_path: &str, source: &str, kinds: &[UnitKind]) -> Vec<(String, String)> {
+        let options = ParseOptions { unit_kinds: kinds.to_vec(), ..Default::default() };
+        CodeParser::new()
+            .parse_file(file_path, source, &options)
+            .unwrap()
+            .units
+            .into_iter()
+            .filter(|u| u.unit_type != "function" && u.unit_type != "class")
+            .map(|u| (u.unit_type.to_string(), u.name))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(t, n)| (t.to_string(), n.to_string())).collect()
+    }
+
+    #[test]
+    fn test_unit_kinds_are_opt_in() {
+        let source = "MAX_RETRIES = 3\n";
+        assert!(kind_names("a.py", source, &[]).is_empty());
+        assert_eq!(kind_names("a.py", source, &[UnitKind::Constant]), pairs(&[("constant", "MAX_RETRIES")]));
+    }
+
+    #[test]
+    fn test_unit_kinds_python() {
+        let source = "from typing import TypeAlias\nDEFAULT_TIMEOUT = 30\ncache = {}\nUserId: TypeAlias = int\ndef f():\n    LOCAL = 1\n";
+        assert_eq!(
+            kind_names("a.py", source, &UnitKind::ALL),
+            pairs(&[
+                ("constant", "DEFAULT_TIMEOUT"),
+                ("type_alias", "UserId"),
+                ("global", "cache"),
+                ("import", "typing"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_typescript() {
+        let source = "export const API_URL = 'x';\nlet counter = 0;\nenum Color { Red }\ntype Id = string;\nfunction f() { const inner = 1; }\n";
+        assert_eq!(
+            kind_names("a.ts", source, &UnitKind::ALL),
+            pairs(&[("constant", "API_URL"), ("enum", "Color"), ("type_alias", "Id"), ("global", "counter")])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_rust() {
+        let source = "const MAX: u32 = 1;\nstatic NAME: &str = \"x\";\nenum Mode { A }\ntype Result<T> = std::result::Result<T, ()>;\n\
+            impl Mode {\n    const DEFAULT: u32 = 0;\n}\nfn f() {\n    static COUNT: u32 = 0;\n    const STEP: u32 = 1;\n}\n";
+        assert_eq!(
+            kind_names("a.rs", source, &UnitKind::ALL),
+            pairs(&[("constant", "MAX"), ("enum", "Mode"), ("type_alias", "Result"), ("global", "NAME")])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_go() {
+        let source = "package main\nconst (\n A = 1\n B = 2\n)\nvar debug = false\ntype ID = string\ntype Handler func()\n";
+        assert_eq!(
+            kind_names("a.go", source, &UnitKind::ALL),
+            pairs(&[
+                ("constant", "A"),
+                ("constant", "B"),
+                ("type_alias", "ID"),
+                ("type_alias", "Handler"),
+                ("global", "debug"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_java_and_csharp() {
+        let java = "class A {\n  static final int LIMIT = 5;\n  int count;\n}\nenum Level { LOW }\n";
+        assert_eq!(
+            kind_names("A.java", java, &UnitKind::ALL),
+            pairs(&[("constant", "LIMIT"), ("enum", "Level")])
+        );
+
+        let csharp = "class A {\n  const int Limit = 5;\n  int count;\n}\nenum Level { Low }\n";
+        assert_eq!(
+            kind_names("A.cs", csharp, &UnitKind::ALL),
+            pairs(&[("constant", "Limit"), ("enum", "Level")])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_c_family() {
+        let source = "#define BUFFER_SIZE 64\nint verbose = 0;\nenum color { RED };\ntypedef unsigned int uint;\n";
+        assert_eq!(
+            kind_names("a.c", source, &UnitKind::ALL),
+            pairs(&[("constant", "BUFFER_SIZE"), ("enum", "color"), ("type_alias", "uint"), ("global", "verbose")])
+        );
+        let cpp = "using Id = long;\n";
+        assert_eq!(kind_names("a.cpp", cpp, &[UnitKind::TypeAlias]), pairs(&[("type_alias", "Id")]));
+    }
+
+    #[test]
+    fn test_unit_kinds_scripting_languages() {
+        let constants = [UnitKind::Constant, UnitKind::Global];
+        let lua = "local MAX_RETRIES = 3\nlocal cache = {}\nfunction f()\n  local inner = 1\nend\n";
+        assert_eq!(kind_names("a.lua", lua, &constants), pairs(&[("constant", "MAX_RETRIES"), ("global", "cache")]));
+        let bash = "readonly ROOT=/srv\nTIMEOUT=30\nlog_file=out.log\nf() {\n  local tmp=1\n}\n";
+        assert_eq!(
+            kind_names("a.sh", bash, &constants),
+            pairs(&[("constant", "ROOT"), ("constant", "TIMEOUT"), ("global", "log_file")])
+        );
+        let zig = "const std = @import(\"std\");\nconst Point = struct { x: i32 };\nconst MAX: u32 = 8;\nvar counter: u32 = 0;\n";
+        assert_eq!(kind_names("a.zig", zig, &constants), pairs(&[("constant", "MAX"), ("global", "counter")]));
+        let options = ParseOptions { unit_kinds: constants.to_vec(), ..Default::default() };
+        let result = CodeParser::new().parse_file("a.zig", zig, &options).unwrap();
+        assert!(result.units.iter().any(|u| u.unit_type == "class" && u.name == "Point"));
+        let kotlin = "const val DEFAULT_TIMEOUT = 30\nvar verbose = false\n";
+        assert_eq!(
+            kind_names("a.kt", kotlin, &constants),
+            pairs(&[("constant", "DEFAULT_TIMEOUT"), ("global", "verbose")])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_interfaces_and_traits() {
+        let rust = "trait Store {\n    fn get(&self) -> u32;\n}\nstruct Memory {}\n";
+        assert_eq!(kind_names("a.rs", rust, &UnitKind::ALL), pairs(&[("trait", "Store")]));
+        let typescript = "interface User { id: string }\nclass Admin {}\n";
+        assert_eq!(kind_names("a.ts", typescript, &UnitKind::ALL), pairs(&[("interface", "User")]));
+        let go = "package main\ntype Reader interface {\n\tRead() error\n}\ntype File struct{}\n";
+        assert_eq!(kind_names("a.go", go, &UnitKind::ALL), pairs(&[("interface", "Reader")]));
+        let java = "interface Shape {\n  double area();\n}\n";
+        assert_eq!(kind_names("Shape.java", java, &[UnitKind::Interface]), pairs(&[("interface", "Shape")]));
+        assert_eq!(kind_names("Shape.java", java, &[]), pairs(&[("interface", "Shape")]));
+        assert_eq!(kind_names("a.rs", rust, &[]), pairs(&[("trait", "Store")]));
+        let types = "pub enum Color { Red }\ntype Id = u64;\n";
+        assert_eq!(kind_names("a.rs", types, &[]), pairs(&[("enum", "Color"), ("type_alias", "Id")]));
+        let typescript = "type Id = string;\nenum Color { Red }\n";
+        assert_eq!(kind_names("a.ts", typescript, &[]), pairs(&[("enum", "Color"), ("type_alias", "Id")]));
+        let c = "typedef unsigned long size;\nenum color { RED };\n";
+        assert_eq!(kind_names("a.c", c, &[]), pairs(&[("enum", "color"), ("type_alias", "size")]));
+    }
+
+    #[test]
+    fn test_unit_kinds_ruby_and_php() {
+        let ruby = "VERSION = '1.0'\n$debug = true\n";
+        assert_eq!(
+            kind_names("a.rb", ruby, &UnitKind::ALL),
+            pairs(&[("constant", "VERSION"), ("global", "$debug")])
+        );
+        let php = "<?php\nconst LIMIT = 10;\nenum Suit { case Hearts; }\n";
+        assert_eq!(
+            kind_names("a.php", php, &UnitKind::ALL),
+            pairs(&[("constant", "LIMIT"), ("enum", "Suit")])
+        );
+    }
+
+    #[test]
+    fn test_unit_kinds_imports() {
+        let imports = |file_path: &str, source: &str| kind_names(file_path, source, &[UnitKind::Import]);
+        assert_eq!(
+            imports("a.py", "import os.path, json as j\nfrom .models import User\n"),
+            pairs(&[("import", "os.path"), ("import", "json"), ("import", ".models")])
+        );
+        assert_eq!(
+            imports("a.ts", "import { x } from './util';\nexport * from \"lib\";\nconst fs = require('fs');\n"),
+            pairs(&[("import", "./util"), ("import", "lib"), ("import", "fs")])
+        );
+        assert_eq!(
+            imports("a.go", "package main\nimport (\n\t\"fmt\"\n\tlog \"github.com/x/log\"\n)\n"),
+            pairs(&[("import", "fmt"), ("import", "github.com/x/log")])
+        );
+        assert_eq!(
+            imports("a.rs", "use std::collections::HashMap;\n"),
+            pairs(&[("import", "std::collections::HashMap")])
+        );
+        assert_eq!(imports("A.java", "import java.util.List;\nclass A {}\n"), pairs(&[("import", "java.util.List")]));
+        assert_eq!(
+            imports("a.c", "#include <stdio.h>\n#include \"util.h\"\n"),
+            pairs(&[("import", "stdio.h"), ("import", "util.h")])
+        );
+        assert_eq!(imports("a.rb", "require 'json'\nputs 1\n"), pairs(&[("import", "json")]));
+        assert!(imports("a.py", "x = 1\n").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_unit_kind_is_rejected() {
+        assert!(UnitKind::parse_list(&["constant".to_string(), "macro".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_no_duplicate_units_per_language() {
+        assert_no_duplicate_spans("a.py", "class A:\n    def m(self):\n        pass\ndef f():\n    pass\n");
+        assert_no_duplicate_spans("a.js", "class A { m() {} }\nfunction f() {}\n");
+        assert_no_duplicate_spans("a.ts", "class A { m() {} }\nexport function f(a: number): number { return a; }\n");
+        assert_no_duplicate_spans("a.java", "class A { void m() {} }\n");
+        assert_no_duplicate_spans("a.go", "package main\ntype (\n A struct{}\n B struct{}\n)\nfunc f() {}\n");
+        assert_no_duplicate_spans("a.rs", "struct S { x: i32 }\nimpl S { fn m(&self) {} }\nfn f() {}\n");
+        assert_no_duplicate_spans("a.rb", "class A\n  def m(a, b)\n  end\nend\nmodule M\nend\n");
+        assert_no_duplicate_spans("a.c", "struct S { int x; };\nint f(void) { return 0; }\n");
+        assert_no_duplicate_spans("a.cpp", "struct Foo { int x; };\nclass Bar { void m() {} };\n");
+        assert_no_duplicate_spans("a.cs", "class A { void M() {} }\ninterface I {}\nstruct S {}\n");
+        assert_no_duplicate_spans("a.sql", "CREATE TABLE t (id int);\nCREATE VIEW v AS SELECT 1;\n");
+        assert_no_duplicate_spans("a.php", "<?php\nclass A { function m() {} }\nfunction f($a) { return 1; }\n");
+        assert_no_duplicate_spans("a.kt", "class A { fun m() {} }\nobject O\nfun f(a: Int) = a\n");
+        assert_no_duplicate_spans("a.swift", "class A { func m() {} }\nstruct S {}\nprotocol P {}\n");
+        assert_no_duplicate_spans("a.scala", "class A { def m(): Int = 1 }\nobject O\ntrait T\n");
+        assert_no_duplicate_spans("a.zig", "const S = struct { x: i32 };\nfn f() void {}\n");
+    }
+
+    #[test]
+    fn test_batch_results_follow_input_order() {
+        // Mix cheap and expensive inputs so rayon finishes them out of order
+        let files: Vec<(String, String)> = (0..200)
+            .map(|i| {
+                let body = "    x = 1\n".repeat(if i % 7 == 0 { 500 } else { 1 });
+                (format!("file_{}.py", i), format!("def func_{}():\n{}", i, body))
+            })
+            .collect();
+
+        let results = parse_batch(&files, &ParseOptions::default()).unwrap();
+
+        assert_eq!(results.len(), files.len());
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.file_path, files[i].0);
+            assert!(result.units[0].content.starts_with(&format!("def func_{}()", i)));
+        }
+    }
+
+    #[test]
+    fn test_batch_mixed_languages_follow_input_order() {
+        let files = vec![
+            ("a.rs".to_string(), "fn a() {}".to_string()),
+            ("b.json".to_string(), r#"{"b": 1}"#.to_string()),
+            ("c.go".to_string(), "package main\nfunc c() {}".to_string()),
+            ("d.yaml".to_string(), "d: 1".to_string()),
+            ("e.py".to_string(), "def e():\n    pass".to_string()),
+        ];
+
+        let results = parse_batch(&files, &ParseOptions::default()).unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["a.rs", "b.json", "c.go", "d.yaml", "e.py"]);
+        let languages: Vec<&str> = results.iter().map(|r| r.language.as_str()).collect();
+        assert_eq!(languages, vec!["Rust", "Json", "Go", "Yaml", "Python"]);
+    }
+
+    #[test]
+    fn test_batch_reports_first_failure_in_input_order() {
+        let files = vec![
+            ("ok.py".to_string(), "def ok():\n    pass".to_string()),
+            ("first.unknown".to_string(), String::new()),
+            ("second.json".to_string(), "{ not json".to_string()),
+        ];
+
+        let results = parse_files_ordered(&files, &ParseOptions::default());
+        assert!(results[0].is_ok());
+        let first_error = results.iter().find_map(|r| r.as_ref().err()).unwrap();
+        assert!(first_error.contains("Unsupported file extension"));
+        assert_eq!(parse_batch(&files, &ParseOptions::default()).unwrap_err(), *first_error);
+    }
+
+    #[test]
+    fn test_named_language_accepts_names_aliases_and_extensions() {
+        for name in ["python", "Python", "py", " PY "] {
+            assert!(matches!(NamedLanguage::from_name(name), Some(NamedLanguage::Code(SupportedLanguage::Python))));
+        }
+        assert!(matches!(NamedLanguage::from_name("CSharp"), Some(NamedLanguage::Code(SupportedLanguage::CSharp))));
+        assert!(matches!(NamedLanguage::from_name("c++"), Some(NamedLanguage::Code(SupportedLanguage::Cpp))));
+        assert!(matches!(NamedLanguage::from_name("yml"), Some(NamedLanguage::Config("yaml"))));
+        assert!(matches!(NamedLanguage::from_name("jinja"), Some(NamedLanguage::Template(TemplateLanguage::Jinja))));
+        assert!(NamedLanguage::from_name("cobol").is_none());
+    }
+
+    #[test]
+    fn test_parse_as_language_ignores_path_extension() {
+        let named = NamedLanguage::from_name("python").unwrap();
+        let result = parse_as_language("", "def greet(name):\n    return name", named, &ParseOptions::default()).unwrap();
+        assert_eq!(result.file_path, "");
+        assert_eq!(result.language, "Python");
+        assert_eq!(result.units[0].name, "greet");
+
+        // The hint labels the result but never selects the language
+        let named = NamedLanguage::from_name("rust").unwrap();
+        let result = parse_as_language("notes.txt", "fn f() {}", named, &ParseOptions::default()).unwrap();
+        assert_eq!(result.file_path, "notes.txt");
+        assert_eq!(result.language, "Rust");
+        assert_eq!(result.units.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_as_language_handles_config_and_templates() {
+        let named = NamedLanguage::from_name("toml").unwrap();
+        let result = parse_as_language("", "[server]\nport = 80\n", named, &ParseOptions::default()).unwrap();
+        assert_eq!(result.language, "Toml");
+        assert!(!result.units.is_empty());
+
+        let named = NamedLanguage::from_name("jinja").unwrap();
+        let result = parse_as_language("", "{% block body %}hi{% endblock %}", named, &ParseOptions::default()).unwrap();
+        assert_eq!(result.language, "Jinja");
+        assert_eq!(result.units[0].name, "body");
+    }
+
+    #[test]
+    fn test_parse_snippet_remaps_into_file_coordinates() {
+        let file = "import os\n\n\ndef helper():\n    return os.getcwd()\n";
+        let base_byte = file.find("def helper").unwrap();
+        let snippet = file[base_byte..].to_string();
+
+        let python = NamedLanguage::from_name("python").unwrap();
+        let result = parse_excerpt("", &snippet, python, 4, base_byte, &ParseOptions::default()).unwrap();
+        let unit = &result.units[0];
+        assert_eq!(unit.name, "helper");
+        assert_eq!((unit.start_line, unit.end_line), (4, 5));
+        assert_eq!(&file[unit.start_byte..unit.end_byte], unit.content);
+    }
+
+    #[test]
+    fn test_remap_shifts_template_lines() {
+        let mut shifted = unit("class", "spec", 0, 10);
+        shifted.metadata.insert("template_lines".to_string(), "2,5".to_string());
+        shifted.remap(10, 100);
+        assert_eq!((shifted.start_line, shifted.start_byte, shifted.end_byte), (10, 100, 110));
+        assert_eq!(shifted.metadata["template_lines"], "11,14");
+    }
+
+    #[test]
+    fn test_content_refs_point_into_source() {
+        let options = ParseOptions { content_refs: true, ..Default::default() };
+        let source = "def load():\n    pass\n\nclass Store:\n    pass\n";
+        let result = parse_any_file("store.py", source, &options).unwrap();
+        let texts: Vec<&str> = result
+            .units
+            .iter()
+            .map(|unit| {
+                assert!(unit.content.is_empty());
+                let (offset, len) = unit.content_ref.unwrap();
+                &source[offset..offset + len]
+            })
+            .collect();
+        assert_eq!(texts, ["def load():\n    pass", "class Store:\n    pass"]);
+
+        // Parsers that build content themselves drop it afterwards
+        let template = "{% block body %}hi{% endblock %}";
+        let result = parse_any_file("page.html.j2", template, &options).unwrap();
+        assert!(result.units[0].content.is_empty());
+        assert_eq!(result.units[0].content_ref, Some((0, template.len())));
+    }
+
+    /// Golden-file view of a parse: the kind, name, scope and lines of each
+    /// unit, and its docstring
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Golden {
+        language: String,
+        units: Vec<GoldenUnit>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct GoldenUnit {
+        unit_type: String,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parent_name: Option<String>,
+        start_line: usize,
+        end_line: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        docstring: Option<String>,
+    }
+
+    /// Every `tests/fixtures/<lang>/<file>` parses (with all unit kinds) to
+    /// the units in `<file stem>.expected.json`, listed by line. After an
+    /// intended change, rewrite the expectations with `UPDATE_FIXTURES=1`.
+    #[test]
+    fn test_golden_fixtures() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+        let options = ParseOptions { unit_kinds: UnitKind::ALL.to_vec(), ..ParseOptions::default() };
+        let sorted_entries = |dir: &std::path::Path| {
+            let mut paths: Vec<_> = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+            paths.sort();
+            paths
+        };
+
+        let mut failures = Vec::new();
+        let mut languages = std::collections::HashSet::new();
+        for dir in sorted_entries(&root).into_iter().filter(|p| p.is_dir()) {
+            for path in sorted_entries(&dir) {
+                let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+                if file_name.ends_with(".expected.json") {
+                    continue;
+                }
+                let relative = path.strip_prefix(&root).unwrap().to_string_lossy().to_string();
+                let source = std::fs::read_to_string(&path).unwrap();
+                let result = parse_any_file(&relative, &source, &options).unwrap();
+                let mut units: Vec<GoldenUnit> = result
+                    .units
+                    .into_iter()
+                    .map(|u| GoldenUnit {
+                        unit_type: u.unit_type.to_string(),
+                        name: u.name,
+                        parent_name: u.parent_name,
+                        start_line: u.start_line,
+                        end_line: u.end_line,
+                        docstring: u.docstring,
+                    })
+                    .collect();
+                units.sort_by_key(|u| (u.start_line, u.end_line, u.unit_type.clone(), u.name.clone()));
+                let actual = Golden { language: result.language, units };
+                languages.insert(actual.language.clone());
+
+                let stem = file_name.split('.').next().unwrap();
+                let expected_path = dir.join(format!("{}.expected.json", stem));
+                if update {
+                    std::fs::write(&expected_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+                    continue;
+                }
+                let expected: Golden = std::fs::read_to_string(&expected_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| panic!("{}: {}", expected_path.display(), e));
+                if actual != expected {
+                    failures.push(format!("{}\n  expected: {:?}\n  actual:   {:?}", relative, expected, actual));
+                }
+            }
+        }
+        assert!(languages.len() >= 18, "fixtures cover only {:?}", languages);
+        assert!(failures.is_empty(), "fixtures differ (UPDATE_FIXTURES=1 rewrites them):\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_parse_paths_reads_and_maps_files() {
+        let directory = std::env::temp_dir().join(format!("parse_paths_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let small = directory.join("small.py");
+        let large = directory.join("large.rs");
+        std::fs::write(&small, "def f():\n    pass\n").unwrap();
+        let padding = "// padding\n".repeat(MMAP_THRESHOLD as usize / 10);
+        std::fs::write(&large, format!("{}\nfn g() {{}}\n", padding)).unwrap();
+        let paths: Vec<String> = [&small, &large].iter().map(|p| p.to_string_lossy().into_owned()).collect();
+
+        let results = parse_paths(&paths, &ParseOptions::default());
+        let missing = directory.join("missing.py").to_string_lossy().into_owned();
+        let missing = parse_paths(&[missing], &ParseOptions::default());
+        std::fs::remove_dir_all(&directory).unwrap();
+        let results = results.unwrap();
+        assert_eq!((results[0].units[0].name.as_str(), results[1].units[0].name.as_str()), ("f", "g"));
+        assert_eq!(results[1].units[0].start_byte, padding.len() + 1);
+        assert!(missing.unwrap_err().contains("missing.py"));
+    }
+
+    #[test]
+    fn test_unit_ids_survive_edits_and_hashes_follow_content() {
+        let options = ParseOptions::default();
+        let before = parse_any_file("cart.py", "def total():\n    return 1\n\ndef total():\n    pass\n", &options);
+        let after = parse_any_file("cart.py", "\n\ndef total():\n    return 2\n\ndef total():\n    pass\n", &options);
+        let refs = ParseOptions { content_refs: true, ..ParseOptions::default() };
+        let by_ref = parse_any_file("cart.py", "def total():\n    return 1\n\ndef total():\n    pass\n", &refs);
+        let (before, after, by_ref) = (before.unwrap().units, after.unwrap().units, by_ref.unwrap().units);
+
+        assert_ne!(before[0].unit_id, before[1].unit_id);
+        assert_eq!((&before[0].unit_id, &before[1].unit_id), (&after[0].unit_id, &after[1].unit_id));
+        assert_ne!(before[0].content_hash, after[0].content_hash);
+        assert_eq!(before[1].content_hash, after[1].content_hash);
+        assert_eq!(before[0].content_hash, by_ref[0].content_hash);
+        assert_eq!(before[0].content_hash.len(), 16);
+    }
+
+    #[test]
+    fn test_parsers_are_reused() {
+        let mut parser = CodeParser::new();
+        assert!(parser.parsers.is_empty());
+        let first = parser.parse_file("a.py", "def f():\n    pass\n", &ParseOptions::default()).unwrap();
+        let second = parser.parse_file("b.py", "class C:\n    pass\n", &ParseOptions::default()).unwrap();
+        assert_eq!((first.units[0].name.as_str(), second.units[0].name.as_str()), ("f", "C"));
+        assert_eq!(parser.parsers.len(), 1);
+
+        // A nested call while the thread's parser is busy gets its own parser
+        let nested = with_thread_parser(|_| parse_any_file("c.rs", "fn g() {}\n", &ParseOptions::default()));
+        assert_eq!(nested.unwrap().units[0].name, "g");
+    }
+
+    #[test]
+    fn test_skip_generated_leaves_units_out() {
+        let generated = "# Generated by the protocol buffer compiler.  DO NOT EDIT!\ndef f():\n    pass\n";
+        let parsed = parse_any_file("user_pb2.py", generated, &ParseOptions::default()).unwrap();
+        assert_eq!((parsed.file_classification.as_str(), parsed.units.len()), ("generated", 1));
+
+        let options = ParseOptions { skip_generated: true, ..Default::default() };
+        let skipped = parse_any_file("user_pb2.py", generated, &options).unwrap();
+        assert_eq!((skipped.file_classification.as_str(), skipped.units.len()), ("generated", 0));
+        let kept = parse_any_file("user.py", "def f():\n    pass\n", &options).unwrap();
+        assert_eq!((kept.file_classification.as_str(), kept.units.len()), ("source", 1));
+    }
 }
@@ -0,0 +1,129 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use crate::parsing::{ParseResult, SemanticUnit};
+use crate::text_cleaning::normalize_whitespace;
+
+/// Whether a file is a PDF, by its extension
+pub fn is_pdf(file_path: &str) -> bool {
+    std::path::Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+/// The text of each page of a PDF, in order
+#[cfg(feature = "pdf")]
+fn page_texts(data: &[u8]) -> Result<Vec<String>, String> {
+    // pdf-extract panics on some malformed documents rather than erroring
+    match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(data)) {
+        Ok(Ok(pages)) => Ok(pages),
+        Ok(Err(e)) => Err(format!("PDF parse error: {}", e)),
+        Err(_) => Err("PDF parse error: malformed document".to_string()),
+    }
+}
+
+#[cfg(not(feature = "pdf"))]
+fn page_texts(_data: &[u8]) -> Result<Vec<String>, String> {
+    Err("PDF support is not enabled; build with the `pdf` feature".to_string())
+}
+
+/// One "page" unit per page with text. Spans index the document text: the
+/// pages' text, whitespace tidied, joined by blank lines.
+fn page_units(pages: &[String]) -> Vec<SemanticUnit> {
+    let mut units = Vec::new();
+    let (mut line, mut byte) = (1, 0);
+    for (index, page) in pages.iter().enumerate() {
+        let text = normalize_whitespace(page);
+        if text.is_empty() {
+            continue;
+        }
+        let (lines, length) = (text.lines().count(), text.len());
+        let name = format!("page {}", index + 1);
+        let mut metadata = HashMap::new();
+        metadata.insert("page".to_string(), (index + 1).to_string());
+        units.push(SemanticUnit {
+            unit_type: "page".into(),
+            name: name.clone(),
+            parent_name: None,
+            qualified_name: name,
+            start_line: line,
+            end_line: line + lines - 1,
+            start_byte: byte,
+            end_byte: byte + length,
+            signature: text.lines().next().unwrap_or("").to_string(),
+            parameters: None,
+            docstring: None,
+            decorators: Vec::new(),
+            content: text,
+            content_ref: None,
+            content_hash: String::new(),
+            unit_id: String::new(),
+            language: "Pdf".into(),
+            metadata,
+        });
+        line += lines + 1;
+        byte += length + 2;
+    }
+    units
+}
+
+/// Parse a PDF into one "page" unit per page with a text layer, numbered
+/// from 1 in `metadata["page"]`. Pages without text (scans) are left out,
+/// with a warning when the whole document has none.
+pub fn parse_pdf(file_path: &str, data: &[u8]) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+    let units = page_units(&page_texts(data)?);
+    let mut warnings = Vec::new();
+    if units.is_empty() {
+        warnings.push("No text layer found; scanned PDFs need OCR".to_string());
+    }
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: "Pdf".to_string(),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+/// Extract the text of a PDF as page-anchored units
+///
+/// Each page with a text layer becomes a "page" unit named "page N" with
+/// `metadata["page"]`, so search hits can cite the page. Requires the
+/// extension to be built with the `pdf` feature.
+///
+/// Args:
+///     file_path: Path used to label the result
+///     data: The PDF file's bytes
+///
+/// Returns:
+///     ParseResult with one unit per page. Raises RuntimeError for a
+///     malformed PDF, or when PDF support isn't built in.
+#[pyfunction]
+pub fn parse_pdf_file(py: Python<'_>, file_path: String, data: &[u8]) -> PyResult<ParseResult> {
+    py.detach(|| parse_pdf(&file_path, data)).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_become_units() {
+        let pages = vec![
+            "Architecture   Overview\n\nThe ingest tier   writes to Kafka.  \n\n\n".to_string(),
+            "  \n".to_string(),
+            "Storage\nShards are replicated three ways.\n".to_string(),
+        ];
+        let units = page_units(&pages);
+        let spans: Vec<(&str, usize, usize, &str)> =
+            units.iter().map(|u| (u.name.as_str(), u.start_line, u.end_line, u.metadata["page"].as_str())).collect();
+        assert_eq!(spans, vec![("page 1", 1, 3, "1"), ("page 3", 5, 6, "3")]);
+        assert_eq!(units[0].content, "Architecture Overview\n\nThe ingest tier writes to Kafka.");
+        assert_eq!(units[1].signature, "Storage");
+        assert_eq!(units[1].start_byte, units[0].end_byte + 2);
+        assert!(is_pdf("docs/Design.PDF"));
+    }
+}
@@ -0,0 +1,315 @@
+use ndarray::{Array1, Array2, ArrayView2};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+use crate::matrix_rows;
+
+/// Scale `row` into `code` so that `row ≈ code * scale`; returns the scale,
+/// 0.0 for an all-zero row
+fn quantize_row(row: &[f32], code: &mut [i8]) -> f32 {
+    let max = row.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+    if max == 0.0 {
+        code.fill(0);
+        return 0.0;
+    }
+    let scale = max / 127.0;
+    for (slot, x) in code.iter_mut().zip(row) {
+        *slot = (x / scale).round().clamp(-127.0, 127.0) as i8;
+    }
+    scale
+}
+
+/// Symmetric int8 codes with one scale per row
+pub fn int8_codes(embeddings: ArrayView2<'_, f32>) -> (Array2<i8>, Array1<f32>) {
+    let (count, dimension) = embeddings.dim();
+    let rows = matrix_rows(embeddings);
+    let mut codes = vec![0i8; count * dimension];
+    let mut scales = vec![0.0f32; count];
+    if dimension > 0 {
+        codes
+            .par_chunks_mut(dimension)
+            .zip(scales.par_iter_mut())
+            .zip(rows.par_iter())
+            .for_each(|((code, scale), row)| *scale = quantize_row(row, code));
+    }
+    let codes = Array2::from_shape_vec((count, dimension), codes).expect("count * dimension codes");
+    (codes, Array1::from(scales))
+}
+
+/// One bit per dimension, set for positive components, packed most
+/// significant bit first like `numpy.packbits`
+pub fn binary_codes(embeddings: ArrayView2<'_, f32>) -> Array2<u8> {
+    let (count, dimension) = embeddings.dim();
+    let width = dimension.div_ceil(8);
+    let rows = matrix_rows(embeddings);
+    let mut codes = vec![0u8; count * width];
+    if width > 0 {
+        codes.par_chunks_mut(width).zip(rows.par_iter()).for_each(|(code, row)| {
+            for (i, _) in row.iter().enumerate().filter(|(_, &x)| x > 0.0) {
+                code[i / 8] |= 0x80 >> (i % 8);
+            }
+        });
+    }
+    Array2::from_shape_vec((count, width), codes).expect("count * width bytes")
+}
+
+/// Differing bits of two equal-length codes, eight bytes at a time
+fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    let (a_words, b_words) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: u32 = a_words.remainder().iter().zip(b_words.remainder()).map(|(x, y)| (x ^ y).count_ones()).sum();
+    let word = |bytes: &[u8]| u64::from_ne_bytes(bytes.try_into().expect("eight bytes"));
+    a_words.zip(b_words).map(|(x, y)| (word(x) ^ word(y)).count_ones()).sum::<u32>() + tail
+}
+
+/// The first `k` scores under `order`, sorted
+fn best_k<T>(
+    mut scores: Vec<(usize, T)>,
+    k: usize,
+    order: impl Fn(&(usize, T), &(usize, T)) -> Ordering,
+) -> Vec<(usize, T)> {
+    if k < scores.len() {
+        scores.select_nth_unstable_by(k, &order);
+        scores.truncate(k);
+    }
+    scores.sort_by(order);
+    scores
+}
+
+/// The `k` codes nearest `query` by Hamming distance, as `(index,
+/// distance)` pairs, nearest first (ties by lower index)
+pub fn hamming_nearest(query: &[u8], codes: ArrayView2<'_, u8>, k: usize) -> Result<Vec<(usize, u32)>, String> {
+    if codes.ncols() != query.len() {
+        return Err(format!("Codes have {} bytes, query has {}", codes.ncols(), query.len()));
+    }
+    let rows = matrix_rows(codes);
+    let distances: Vec<(usize, u32)> = rows.par_iter().enumerate().map(|(i, code)| (i, hamming(query, code))).collect();
+    Ok(best_k(distances, k, |a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0))))
+}
+
+/// The `k` int8 codes with the largest approximate dot product with
+/// `query`, as `(index, dot product)` pairs, largest first (ties by lower
+/// index). The query is quantized the same way and products are summed in
+/// integers before scaling.
+pub fn int8_nearest(
+    query: &[f32],
+    codes: ArrayView2<'_, i8>,
+    scales: &[f32],
+    k: usize,
+) -> Result<Vec<(usize, f32)>, String> {
+    if codes.ncols() != query.len() {
+        return Err(format!("Codes have {} dimensions, query has {}", codes.ncols(), query.len()));
+    }
+    if scales.len() != codes.nrows() {
+        return Err(format!("{} scales given for {} codes", scales.len(), codes.nrows()));
+    }
+    let mut query_code = vec![0i8; query.len()];
+    let query_scale = quantize_row(query, &mut query_code);
+    let rows = matrix_rows(codes);
+    let products: Vec<(usize, f32)> = rows
+        .par_iter()
+        .zip(scales.par_iter())
+        .enumerate()
+        .map(|(i, (code, &scale))| {
+            let dot: i32 = query_code.iter().zip(code.iter()).map(|(&x, &y)| i32::from(x) * i32::from(y)).sum();
+            (i, dot as f32 * query_scale * scale)
+        })
+        .collect();
+    Ok(best_k(products, k, |a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0))))
+}
+
+/// Quantize float32 embeddings to int8, one scale per row.
+///
+/// Each row is scaled so its largest magnitude maps to 127, so it keeps a
+/// quarter of the float32 size.
+///
+/// Args:
+///     embeddings: 2-D float32 array, one embedding per row
+///
+/// Returns:
+///     Tuple of (int8 codes with the input's shape, float32 scales per row);
+///     `codes[i] * scales[i]` approximates row i
+#[pyfunction]
+pub fn quantize_int8<'py>(
+    py: Python<'py>,
+    embeddings: PyReadonlyArray2<'py, f32>,
+) -> (Bound<'py, PyArray2<i8>>, Bound<'py, PyArray1<f32>>) {
+    let embeddings = embeddings.as_array();
+    let (codes, scales) = py.detach(|| int8_codes(embeddings));
+    (codes.into_pyarray(py), scales.into_pyarray(py))
+}
+
+/// Quantize float32 embeddings to packed sign bits (1/32 of the size).
+///
+/// Args:
+///     embeddings: 2-D float32 array, one embedding per row
+///
+/// Returns:
+///     2-D uint8 array of ceil(dimension / 8) bytes per row, bit order as
+///     `numpy.packbits`
+#[pyfunction]
+pub fn quantize_binary<'py>(py: Python<'py>, embeddings: PyReadonlyArray2<'py, f32>) -> Bound<'py, PyArray2<u8>> {
+    let embeddings = embeddings.as_array();
+    py.detach(|| binary_codes(embeddings)).into_pyarray(py)
+}
+
+/// Find the binary codes nearest a query code by Hamming distance.
+///
+/// Args:
+///     query_code: 1-D uint8 array from `quantize_binary`
+///     codes: 2-D uint8 array from `quantize_binary`
+///     k: Number of results to return
+///
+/// Returns:
+///     List of (index, Hamming distance) pairs, nearest first
+#[pyfunction]
+pub fn hamming_top_k(
+    py: Python<'_>,
+    query_code: PyReadonlyArray1<'_, u8>,
+    codes: PyReadonlyArray2<'_, u8>,
+    k: usize,
+) -> PyResult<Vec<(usize, u32)>> {
+    let query_code = query_code.as_array().to_vec();
+    let codes = codes.as_array();
+    py.detach(|| hamming_nearest(&query_code, codes, k))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Find the int8 codes with the largest dot product with a float32 query.
+///
+/// Args:
+///     query: 1-D float32 array
+///     codes: 2-D int8 array from `quantize_int8`
+///     scales: 1-D float32 array of per-row scales from `quantize_int8`
+///     k: Number of results to return
+///
+/// Returns:
+///     List of (index, approximate dot product) pairs, largest first; for
+///     unit-length embeddings this approximates cosine similarity
+#[pyfunction]
+pub fn int8_dot_top_k(
+    py: Python<'_>,
+    query: PyReadonlyArray1<'_, f32>,
+    codes: PyReadonlyArray2<'_, i8>,
+    scales: PyReadonlyArray1<'_, f32>,
+    k: usize,
+) -> PyResult<Vec<(usize, f32)>> {
+    let (query, scales) = (query.as_array().to_vec(), scales.as_array().to_vec());
+    let codes = codes.as_array();
+    py.detach(|| int8_nearest(&query, codes, &scales, k))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_int8_round_trip_and_search() {
+        let embeddings = array![[0.5, -1.0, 0.25], [0.0, 0.0, 0.0], [-0.6, 0.8, 0.0]];
+        let (codes, scales) = int8_codes(embeddings.view());
+        assert_eq!(codes.row(0).to_vec(), vec![64, -127, 32]);
+        assert_eq!(scales[1], 0.0);
+        for ((i, j), &x) in embeddings.indexed_iter() {
+            assert!((f32::from(codes[[i, j]]) * scales[i] - x).abs() <= scales[i] / 2.0 + 1e-6);
+        }
+
+        let top = int8_nearest(&[-0.6, 0.8, 0.0], codes.view(), scales.as_slice().unwrap(), 2).unwrap();
+        assert_eq!(top[0].0, 2);
+        assert!((top[0].1 - 1.0).abs() < 0.02);
+        assert_eq!(top[1].0, 1);
+        assert!(int8_nearest(&[1.0], codes.view(), scales.as_slice().unwrap(), 1).is_err());
+        assert!(int8_nearest(&[1.0, 0.0, 0.0], codes.view(), &[1.0], 1).is_err());
+    }
+
+    #[test]
+    fn test_binary_codes_and_hamming_search() {
+        let mut row = vec![-1.0f32; 70];
+        row[0] = 1.0;
+        row[9] = 1.0;
+        row[69] = 1.0;
+        let embeddings = Array2::from_shape_vec((2, 70), [row.clone(), vec![1.0; 70]].concat()).unwrap();
+        let codes = binary_codes(embeddings.view());
+        assert_eq!(codes.dim(), (2, 9));
+        assert_eq!(codes[[0, 0]], 0x80);
+        assert_eq!(codes[[0, 1]], 0x40);
+        assert_eq!(codes[[0, 8]], 0x04);
+        assert_eq!(codes[[1, 8]], 0xfc);
+
+        let query = codes.row(0).to_vec();
+        let top = hamming_nearest(&query, codes.view(), 5).unwrap();
+        assert_eq!(top, vec![(0, 0), (1, 67)]);
+        assert!(hamming_nearest(&query[..4], codes.view(), 1).is_err());
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// A query and a matrix of rows with its dimensionality
+        fn query_and_rows() -> impl Strategy<Value = (Vec<f32>, Array2<f32>)> {
+            (1usize..32, 1usize..20).prop_flat_map(|(dim, count)| {
+                (
+                    prop::collection::vec(-1.0f32..1.0, dim),
+                    prop::collection::vec(-1.0f32..1.0, dim * count)
+                        .prop_map(move |values| Array2::from_shape_vec((count, dim), values).unwrap()),
+                )
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn int8_round_trip_is_within_half_a_step((_, rows) in query_and_rows()) {
+                let (codes, scales) = int8_codes(rows.view());
+                for ((i, j), &x) in rows.indexed_iter() {
+                    let error = (f32::from(codes[[i, j]]) * scales[i] - x).abs();
+                    prop_assert!(error <= scales[i] / 2.0 + 1e-6, "row {} error {} scale {}", i, error, scales[i]);
+                }
+            }
+
+            #[test]
+            fn hamming_top_k_matches_brute_force((query, rows) in query_and_rows(), k in 1usize..25) {
+                let codes = binary_codes(rows.view());
+                let query = binary_codes(Array2::from_shape_vec((1, query.len()), query).unwrap().view());
+                let query = query.row(0).to_vec();
+                let mut expected: Vec<(usize, u32)> = codes
+                    .rows()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, code)| (i, code.iter().zip(&query).map(|(x, y)| (x ^ y).count_ones()).sum()))
+                    .collect();
+                expected.sort_by_key(|&(i, distance)| (distance, i));
+                expected.truncate(k);
+                prop_assert_eq!(hamming_nearest(&query, codes.view(), k).unwrap(), expected);
+            }
+
+            #[test]
+            fn int8_top_k_matches_brute_force_within_tolerance((query, rows) in query_and_rows(), k in 1usize..25) {
+                let (codes, scales) = int8_codes(rows.view());
+                let top = int8_nearest(&query, codes.view(), scales.as_slice().unwrap(), k).unwrap();
+                let query_scale = query.iter().fold(0.0f32, |max, x| max.max(x.abs())) / 127.0;
+                let dot = |row: usize| rows.row(row).iter().zip(&query).map(|(&x, &y)| f64::from(x * y)).sum::<f64>();
+                // Each component is off by at most half a step of its scale
+                let tolerance = |row: usize| {
+                    let (row_l1, query_l1): (f32, f32) =
+                        (rows.row(row).iter().map(|x| x.abs()).sum(), query.iter().map(|x| x.abs()).sum());
+                    let both = query_scale * scales[row] * query.len() as f32;
+                    f64::from((query_scale * row_l1 + scales[row] * query_l1 + both) / 2.0) + 1e-4
+                };
+                for &(row, approx) in &top {
+                    prop_assert!((f64::from(approx) - dot(row)).abs() <= tolerance(row));
+                }
+
+                let mut exact: Vec<f64> = (0..rows.nrows()).map(dot).collect();
+                exact.sort_by(|a, b| b.total_cmp(a));
+                let slack = 2.0 * (0..rows.nrows()).map(tolerance).fold(0.0, f64::max);
+                prop_assert_eq!(top.len(), k.min(rows.nrows()));
+                for (rank, &(row, _)) in top.iter().enumerate() {
+                    prop_assert!(dot(row) >= exact[rank] - slack, "rank {} row {}", rank, row);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::spelling::SpellCorrector;
+
+/// Weight of a term as written in the query
+const WORD_WEIGHT: f32 = 1.0;
+/// Weight of snake/camel spellings of a multi-part identifier
+const VARIANT_WEIGHT: f32 = 0.8;
+/// Weight of a stemmed term
+const STEM_WEIGHT: f32 = 0.5;
+/// Weight of a symbol (path, call, identifier) extracted from the query
+const SYMBOL_WEIGHT: f32 = 2.0;
+
+/// Words that carry no meaning in a code search query
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how", "i", "in", "is", "it",
+    "of", "on", "or", "that", "the", "this", "to", "what", "when", "where", "which", "why", "with",
+];
+
+/// A query rewritten into weighted terms for lexical matching
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[pyclass]
+pub struct ExpandedQuery {
+    #[pyo3(get)]
+    pub query: String,
+    #[pyo3(get)]
+    pub terms: Vec<(String, f32)>, // Lowercased terms with weights, in first-appearance order
+    #[pyo3(get)]
+    pub symbols: Vec<String>, // Paths, called functions and identifiers found in the query, as written
+}
+
+#[pymethods]
+impl ExpandedQuery {
+    fn __repr__(&self) -> String {
+        format!("ExpandedQuery(terms={}, symbols={:?})", self.terms.len(), self.symbols)
+    }
+}
+
+impl ExpandedQuery {
+    fn add(&mut self, term: &str, weight: f32) {
+        if term.is_empty() {
+            return;
+        }
+        let term = term.to_lowercase();
+        match self.terms.iter_mut().find(|(t, _)| *t == term) {
+            Some((_, existing)) => *existing = existing.max(weight),
+            None => self.terms.push((term, weight)),
+        }
+    }
+
+    /// Respell words and symbols missing from the corrector's vocabulary as
+    /// their closest known term (one edit for short words, two otherwise).
+    /// Variants and stems are left alone.
+    pub fn correct(&mut self, corrector: &SpellCorrector) {
+        let terms = std::mem::take(&mut self.terms);
+        for (term, weight) in terms {
+            let is_word = term.chars().all(|c| c.is_alphanumeric() || c == '_');
+            let replacement = if weight >= WORD_WEIGHT && is_word && !corrector.contains(&term) {
+                let distance = if term.chars().count() <= 5 { 1 } else { 2 };
+                corrector
+                    .suggest(&term, distance.min(corrector.max_edit_distance()))
+                    .ok()
+                    .and_then(|suggestions| suggestions.into_iter().next())
+                    .map(|s| s.term)
+            } else {
+                None
+            };
+            self.add(&replacement.unwrap_or(term), weight);
+        }
+    }
+
+    /// Weighted share of the query terms present in `terms`, from 0 to 1
+    pub fn overlap(&self, terms: &HashSet<String>) -> f32 {
+        let total: f32 = self.terms.iter().map(|(_, w)| w).sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+        let matched: f32 = self.terms.iter().filter(|(t, _)| terms.contains(t)).map(|(_, w)| w).sum();
+        matched / total
+    }
+}
+
+/// Split an identifier into its words: `parseHTTPRequest_v2` becomes
+/// `parse`, `HTTP`, `Request`, `v`, `2`
+pub fn split_identifier(identifier: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    for segment in identifier.split(|c: char| !c.is_alphanumeric()).filter(|s| !s.is_empty()) {
+        let chars: Vec<(usize, char)> = segment.char_indices().collect();
+        let mut start = 0;
+        for window in 1..chars.len() {
+            let (index, current) = chars[window];
+            let previous = chars[window - 1].1;
+            let next = chars.get(window + 1).map(|&(_, c)| c);
+            let boundary = (previous.is_lowercase() && current.is_uppercase())
+                || (previous.is_uppercase() && current.is_uppercase() && next.is_some_and(char::is_lowercase))
+                || (previous.is_alphabetic() != current.is_alphabetic());
+            if boundary {
+                parts.push(&segment[start..index]);
+                start = index;
+            }
+        }
+        parts.push(&segment[start..]);
+    }
+    parts
+}
+
+/// Light suffix-stripping stemmer, enough to conflate `parse`, `parser`,
+/// `parsing`, `parsed` and `parses`
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.len() <= 3 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return word;
+    }
+    let strip = |suffix: &str| word.strip_suffix(suffix).filter(|rest| rest.len() >= 3);
+    let stemmed = if let Some(rest) = strip("ies") {
+        format!("{}y", rest)
+    } else if let Some(rest) = strip("sses") {
+        format!("{}ss", rest)
+    } else if let Some(rest) = strip("es").filter(|r| ["s", "x", "z", "ch", "sh"].iter().any(|s| r.ends_with(s))) {
+        rest.to_string()
+    } else if let Some(rest) = strip("s").filter(|r| !(r.ends_with('s') || r.ends_with('u') || r.ends_with('i'))) {
+        rest.to_string()
+    } else if let Some(rest) = strip("ing").or_else(|| strip("ed")).or_else(|| strip("er")) {
+        rest.to_string()
+    } else {
+        word.clone()
+    };
+    match stemmed.strip_suffix('e') {
+        Some(rest) if rest.len() >= 3 => rest.to_string(),
+        _ => stemmed,
+    }
+}
+
+/// A path, call, or code-style identifier in a query
+struct Symbol<'a> {
+    text: &'a str,
+    is_path: bool,
+}
+
+impl Symbol<'_> {
+    /// File name of a path, or member name of a qualified identifier
+    fn last(&self) -> Option<&str> {
+        let last = if self.is_path {
+            self.text.rsplit('/').next()
+        } else {
+            self.text.rsplit(['.', ':']).next()
+        };
+        last.filter(|l| !l.is_empty() && *l != self.text)
+    }
+}
+
+/// Whether a query word names a symbol: a path, a call, or an identifier
+/// written in code style (snake/camel case, qualified with `.` or `::`)
+fn symbol_of(word: &str) -> Option<Symbol<'_>> {
+    let word = word.trim_matches(|c: char| matches!(c, '`' | '"' | '\'' | ',' | ';' | '?' | '!'));
+    let word = word.split('(').next().unwrap_or("").trim_end_matches('.');
+    if word.is_empty() || !word.chars().next().is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '/')) {
+        return None;
+    }
+    let extension = word.rsplit_once('.').map(|(_, ext)| ext);
+    let is_path = word.contains('/')
+        || extension.is_some_and(|ext| (1..=4).contains(&ext.len()) && ext.chars().all(|c| c.is_ascii_lowercase()));
+    let code_style = word.contains('_')
+        || word.contains("::")
+        || word.contains('.')
+        || word.chars().skip(1).any(char::is_uppercase) && word.chars().any(char::is_lowercase);
+    (is_path || code_style).then_some(Symbol { text: word, is_path })
+}
+
+/// Identifier tokens of a word, splitting on everything but `_`
+fn tokens(word: &str) -> impl Iterator<Item = &str> {
+    word.split(|c: char| !(c.is_alphanumeric() || c == '_')).filter(|t| !t.is_empty())
+}
+
+/// Expand a search query for lexical matching.
+///
+/// Each word contributes its lowercased parts and, for identifiers, their
+/// snake_case and concatenated (camelCase, lowercased) spellings; stems are
+/// added at lower weight. Paths, called functions and code-style identifiers
+/// are kept whole as boosted symbols, along with their file or member name.
+/// Stopwords are dropped.
+pub fn expand(query: &str) -> ExpandedQuery {
+    let mut expanded = ExpandedQuery { query: query.to_string(), ..Default::default() };
+    for word in query.split_whitespace() {
+        let symbol = symbol_of(word);
+        if let Some(symbol) = &symbol {
+            if !expanded.symbols.iter().any(|s| s == symbol.text) {
+                expanded.symbols.push(symbol.text.to_string());
+            }
+            expanded.add(symbol.text, SYMBOL_WEIGHT);
+            if let Some(last) = symbol.last() {
+                expanded.add(last, SYMBOL_WEIGHT);
+            }
+        }
+
+        for token in tokens(word) {
+            let parts: Vec<String> = split_identifier(token)
+                .into_iter()
+                .map(str::to_lowercase)
+                .filter(|p| !STOPWORDS.contains(&p.as_str()))
+                .collect();
+            for part in &parts {
+                expanded.add(part, WORD_WEIGHT);
+            }
+            if parts.len() > 1 && symbol.is_some() {
+                expanded.add(&parts.join("_"), VARIANT_WEIGHT);
+                expanded.add(&parts.concat(), VARIANT_WEIGHT);
+            }
+            for part in &parts {
+                expanded.add(&stem(part), STEM_WEIGHT);
+            }
+        }
+    }
+    expanded
+}
+
+/// Terms under which a document's text is matched against expanded queries:
+/// whole identifiers, paths, concatenated identifier parts, single parts, and
+/// stems, all lowercased
+pub fn document_terms(text: &str) -> HashSet<String> {
+    let mut terms = HashSet::new();
+    for word in text.split_whitespace() {
+        if let Some(symbol) = symbol_of(word) {
+            terms.insert(symbol.text.to_lowercase());
+            if let Some(last) = symbol.last() {
+                terms.insert(last.to_lowercase());
+            }
+        }
+        for token in tokens(word) {
+            terms.insert(token.to_lowercase());
+            let parts = split_identifier(token);
+            // `parse_config` also matches queries spelled `parseConfig`
+            terms.insert(parts.concat().to_lowercase());
+            for part in parts {
+                terms.insert(part.to_lowercase());
+                terms.insert(stem(part));
+            }
+        }
+    }
+    terms
+}
+
+/// Distinct lowercased words and identifiers of a text, plus the parts of
+/// multi-part identifiers: the vocabulary spelling correction draws from
+pub fn vocabulary(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for token in tokens(text) {
+        let parts = split_identifier(token);
+        let whole = (parts.len() > 1).then_some(token);
+        for word in whole.into_iter().chain(parts) {
+            let word = word.to_lowercase();
+            if word.chars().any(char::is_alphabetic) && seen.insert(word.clone()) {
+                words.push(word);
+            }
+        }
+    }
+    words
+}
+
+/// Expand a code search query into weighted lexical terms
+///
+/// Identifiers are split (`parseFile` → `parse`, `file`) and respelled
+/// (`parse_file`, `parsefile`), words are stemmed, and paths, calls and
+/// identifiers in the query are returned as `symbols` and boosted.
+#[pyfunction]
+pub fn expand_query(query: &str) -> ExpandedQuery {
+    expand(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight(expanded: &ExpandedQuery, term: &str) -> Option<f32> {
+        expanded.terms.iter().find(|(t, _)| t == term).map(|&(_, w)| w)
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        assert_eq!(split_identifier("parseHTTPRequest_v2"), vec!["parse", "HTTP", "Request", "v", "2"]);
+        assert_eq!(split_identifier("snake_case"), vec!["snake", "case"]);
+        assert_eq!(split_identifier("Foo::bar"), vec!["Foo", "bar"]);
+    }
+
+    #[test]
+    fn test_stem_conflates_forms() {
+        for word in ["parse", "parser", "parsing", "parsed", "parses"] {
+            assert_eq!(stem(word), "pars", "{}", word);
+        }
+        assert_eq!(stem("classes"), "class");
+        assert_eq!(stem("queries"), "query");
+        assert_eq!(stem("user"), "user");
+    }
+
+    #[test]
+    fn test_expand_identifiers_and_variants() {
+        let expanded = expand("where is parseConfig called");
+        assert_eq!(expanded.symbols, vec!["parseConfig"]);
+        assert_eq!(weight(&expanded, "parseconfig"), Some(SYMBOL_WEIGHT));
+        assert_eq!(weight(&expanded, "parse_config"), Some(VARIANT_WEIGHT));
+        assert_eq!(weight(&expanded, "config"), Some(WORD_WEIGHT));
+        assert_eq!(weight(&expanded, "call"), Some(STEM_WEIGHT));
+        assert_eq!(weight(&expanded, "where"), None);
+    }
+
+    #[test]
+    fn test_expand_extracts_paths_and_calls() {
+        let expanded = expand("error in src/core/loader.py from load_all()");
+        assert_eq!(expanded.symbols, vec!["src/core/loader.py", "load_all"]);
+        assert_eq!(weight(&expanded, "loader.py"), Some(SYMBOL_WEIGHT));
+        assert_eq!(weight(&expanded, "loadall"), Some(VARIANT_WEIGHT));
+
+        let qualified = expand("HttpClient.send_request failing");
+        assert_eq!(weight(&qualified, "send_request"), Some(SYMBOL_WEIGHT));
+        assert_eq!(weight(&qualified, "httpclient"), Some(VARIANT_WEIGHT));
+        assert_eq!(weight(&qualified, "fail"), Some(STEM_WEIGHT));
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        assert_eq!(vocabulary("fn parseConfig(x: u8) -> 42"), vec!["fn", "parseconfig", "parse", "config", "x", "u8", "u"]);
+    }
+
+    #[test]
+    fn test_correct_respells_unknown_words() {
+        let mut corrector = SpellCorrector::default();
+        for word in vocabulary("def tokenize_source(text): return lexer.run(text)") {
+            corrector.add(&word);
+        }
+        let mut expanded = expand("tokenze_source lexr");
+        expanded.correct(&corrector);
+        assert_eq!(weight(&expanded, "tokenize_source"), Some(SYMBOL_WEIGHT));
+        assert_eq!(weight(&expanded, "lexer"), Some(WORD_WEIGHT));
+        assert_eq!(weight(&expanded, "lexr"), None);
+        assert_eq!(weight(&expanded, "tokenz"), Some(STEM_WEIGHT));
+    }
+
+    #[test]
+    fn test_overlap_with_document_terms() {
+        let document = document_terms("def parse_config(path):\n    return load(path)");
+        assert!((expand("parseConfig").overlap(&document) - 1.0).abs() < 1e-6);
+        assert!(expand("parsing configs").overlap(&document) > 0.0);
+        assert_eq!(expand("network socket").overlap(&document), 0.0);
+    }
+}
@@ -0,0 +1,408 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use tree_sitter::Query;
+
+use crate::parsing::{SupportedLanguage, UnitKind};
+
+/// Default extraction queries, embedded from `queries/<language>/<unit type>.scm`
+const DEFAULT_PACK: &[(SupportedLanguage, &str, &str)] = &[
+    (SupportedLanguage::Python, "function", include_str!("../queries/python/function.scm")),
+    (SupportedLanguage::Python, "class", include_str!("../queries/python/class.scm")),
+    (SupportedLanguage::Python, "constant", include_str!("../queries/python/constant.scm")),
+    (SupportedLanguage::Python, "type_alias", include_str!("../queries/python/type_alias.scm")),
+    (SupportedLanguage::Python, "global", include_str!("../queries/python/global.scm")),
+    (SupportedLanguage::Python, "import", include_str!("../queries/python/import.scm")),
+    (SupportedLanguage::Python, "call", include_str!("../queries/python/call.scm")),
+    (SupportedLanguage::JavaScript, "function", include_str!("../queries/javascript/function.scm")),
+    (SupportedLanguage::JavaScript, "class", include_str!("../queries/javascript/class.scm")),
+    (SupportedLanguage::JavaScript, "constant", include_str!("../queries/javascript/constant.scm")),
+    (SupportedLanguage::JavaScript, "global", include_str!("../queries/javascript/global.scm")),
+    (SupportedLanguage::JavaScript, "import", include_str!("../queries/javascript/import.scm")),
+    (SupportedLanguage::JavaScript, "call", include_str!("../queries/javascript/call.scm")),
+    (SupportedLanguage::TypeScript, "function", include_str!("../queries/typescript/function.scm")),
+    (SupportedLanguage::TypeScript, "class", include_str!("../queries/typescript/class.scm")),
+    (SupportedLanguage::TypeScript, "constant", include_str!("../queries/typescript/constant.scm")),
+    (SupportedLanguage::TypeScript, "enum", include_str!("../queries/typescript/enum.scm")),
+    (SupportedLanguage::TypeScript, "type_alias", include_str!("../queries/typescript/type_alias.scm")),
+    (SupportedLanguage::TypeScript, "interface", include_str!("../queries/typescript/interface.scm")),
+    (SupportedLanguage::TypeScript, "global", include_str!("../queries/typescript/global.scm")),
+    (SupportedLanguage::TypeScript, "import", include_str!("../queries/typescript/import.scm")),
+    (SupportedLanguage::TypeScript, "call", include_str!("../queries/typescript/call.scm")),
+    (SupportedLanguage::Java, "function", include_str!("../queries/java/function.scm")),
+    (SupportedLanguage::Java, "class", include_str!("../queries/java/class.scm")),
+    (SupportedLanguage::Java, "constant", include_str!("../queries/java/constant.scm")),
+    (SupportedLanguage::Java, "enum", include_str!("../queries/java/enum.scm")),
+    (SupportedLanguage::Java, "interface", include_str!("../queries/java/interface.scm")),
+    (SupportedLanguage::Java, "import", include_str!("../queries/java/import.scm")),
+    (SupportedLanguage::Java, "call", include_str!("../queries/java/call.scm")),
+    (SupportedLanguage::Go, "function", include_str!("../queries/go/function.scm")),
+    (SupportedLanguage::Go, "class", include_str!("../queries/go/class.scm")),
+    (SupportedLanguage::Go, "constant", include_str!("../queries/go/constant.scm")),
+    (SupportedLanguage::Go, "type_alias", include_str!("../queries/go/type_alias.scm")),
+    (SupportedLanguage::Go, "interface", include_str!("../queries/go/interface.scm")),
+    (SupportedLanguage::Go, "global", include_str!("../queries/go/global.scm")),
+    (SupportedLanguage::Go, "import", include_str!("../queries/go/import.scm")),
+    (SupportedLanguage::Go, "call", include_str!("../queries/go/call.scm")),
+    (SupportedLanguage::Rust, "function", include_str!("../queries/rust/function.scm")),
+    (SupportedLanguage::Rust, "class", include_str!("../queries/rust/class.scm")),
+    (SupportedLanguage::Rust, "constant", include_str!("../queries/rust/constant.scm")),
+    (SupportedLanguage::Rust, "enum", include_str!("../queries/rust/enum.scm")),
+    (SupportedLanguage::Rust, "type_alias", include_str!("../queries/rust/type_alias.scm")),
+    (SupportedLanguage::Rust, "trait", include_str!("../queries/rust/trait.scm")),
+    (SupportedLanguage::Rust, "global", include_str!("../queries/rust/global.scm")),
+    (SupportedLanguage::Rust, "import", include_str!("../queries/rust/import.scm")),
+    (SupportedLanguage::Rust, "call", include_str!("../queries/rust/call.scm")),
+    (SupportedLanguage::Ruby, "function", include_str!("../queries/ruby/function.scm")),
+    (SupportedLanguage::Ruby, "class", include_str!("../queries/ruby/class.scm")),
+    (SupportedLanguage::Ruby, "constant", include_str!("../queries/ruby/constant.scm")),
+    (SupportedLanguage::Ruby, "global", include_str!("../queries/ruby/global.scm")),
+    (SupportedLanguage::Ruby, "import", include_str!("../queries/ruby/import.scm")),
+    (SupportedLanguage::Ruby, "call", include_str!("../queries/ruby/call.scm")),
+    (SupportedLanguage::C, "function", include_str!("../queries/c/function.scm")),
+    (SupportedLanguage::C, "class", include_str!("../queries/c/class.scm")),
+    (SupportedLanguage::C, "constant", include_str!("../queries/c/constant.scm")),
+    (SupportedLanguage::C, "enum", include_str!("../queries/c/enum.scm")),
+    (SupportedLanguage::C, "type_alias", include_str!("../queries/c/type_alias.scm")),
+    (SupportedLanguage::C, "global", include_str!("../queries/c/global.scm")),
+    (SupportedLanguage::C, "import", include_str!("../queries/c/import.scm")),
+    (SupportedLanguage::C, "call", include_str!("../queries/c/call.scm")),
+    (SupportedLanguage::Cpp, "function", include_str!("../queries/cpp/function.scm")),
+    (SupportedLanguage::Cpp, "class", include_str!("../queries/cpp/class.scm")),
+    (SupportedLanguage::Cpp, "constant", include_str!("../queries/cpp/constant.scm")),
+    (SupportedLanguage::Cpp, "enum", include_str!("../queries/cpp/enum.scm")),
+    (SupportedLanguage::Cpp, "type_alias", include_str!("../queries/cpp/type_alias.scm")),
+    (SupportedLanguage::Cpp, "global", include_str!("../queries/cpp/global.scm")),
+    (SupportedLanguage::Cpp, "import", include_str!("../queries/cpp/import.scm")),
+    (SupportedLanguage::Cpp, "call", include_str!("../queries/cpp/call.scm")),
+    (SupportedLanguage::CSharp, "function", include_str!("../queries/csharp/function.scm")),
+    (SupportedLanguage::CSharp, "class", include_str!("../queries/csharp/class.scm")),
+    (SupportedLanguage::CSharp, "constant", include_str!("../queries/csharp/constant.scm")),
+    (SupportedLanguage::CSharp, "enum", include_str!("../queries/csharp/enum.scm")),
+    (SupportedLanguage::CSharp, "import", include_str!("../queries/csharp/import.scm")),
+    (SupportedLanguage::CSharp, "call", include_str!("../queries/csharp/call.scm")),
+    (SupportedLanguage::Sql, "function", include_str!("../queries/sql/function.scm")),
+    (SupportedLanguage::Sql, "class", include_str!("../queries/sql/class.scm")),
+    (SupportedLanguage::Php, "function", include_str!("../queries/php/function.scm")),
+    (SupportedLanguage::Php, "class", include_str!("../queries/php/class.scm")),
+    (SupportedLanguage::Php, "constant", include_str!("../queries/php/constant.scm")),
+    (SupportedLanguage::Php, "enum", include_str!("../queries/php/enum.scm")),
+    (SupportedLanguage::Php, "import", include_str!("../queries/php/import.scm")),
+    (SupportedLanguage::Php, "call", include_str!("../queries/php/call.scm")),
+    (SupportedLanguage::Kotlin, "function", include_str!("../queries/kotlin/function.scm")),
+    (SupportedLanguage::Kotlin, "class", include_str!("../queries/kotlin/class.scm")),
+    (SupportedLanguage::Kotlin, "constant", include_str!("../queries/kotlin/constant.scm")),
+    (SupportedLanguage::Kotlin, "global", include_str!("../queries/kotlin/global.scm")),
+    (SupportedLanguage::Kotlin, "call", include_str!("../queries/kotlin/call.scm")),
+    (SupportedLanguage::Swift, "function", include_str!("../queries/swift/function.scm")),
+    (SupportedLanguage::Swift, "class", include_str!("../queries/swift/class.scm")),
+    (SupportedLanguage::Swift, "call", include_str!("../queries/swift/call.scm")),
+    (SupportedLanguage::Scala, "function", include_str!("../queries/scala/function.scm")),
+    (SupportedLanguage::Scala, "class", include_str!("../queries/scala/class.scm")),
+    (SupportedLanguage::Scala, "call", include_str!("../queries/scala/call.scm")),
+    (SupportedLanguage::Lua, "function", include_str!("../queries/lua/function.scm")),
+    (SupportedLanguage::Lua, "constant", include_str!("../queries/lua/constant.scm")),
+    (SupportedLanguage::Lua, "global", include_str!("../queries/lua/global.scm")),
+    (SupportedLanguage::Lua, "call", include_str!("../queries/lua/call.scm")),
+    (SupportedLanguage::Bash, "function", include_str!("../queries/bash/function.scm")),
+    (SupportedLanguage::Bash, "constant", include_str!("../queries/bash/constant.scm")),
+    (SupportedLanguage::Bash, "global", include_str!("../queries/bash/global.scm")),
+    (SupportedLanguage::Bash, "call", include_str!("../queries/bash/call.scm")),
+    (SupportedLanguage::Zig, "function", include_str!("../queries/zig/function.scm")),
+    (SupportedLanguage::Zig, "class", include_str!("../queries/zig/class.scm")),
+    (SupportedLanguage::Zig, "constant", include_str!("../queries/zig/constant.scm")),
+    (SupportedLanguage::Zig, "global", include_str!("../queries/zig/global.scm")),
+    (SupportedLanguage::Zig, "call", include_str!("../queries/zig/call.scm")),
+];
+
+type CompiledQuery = Result<Arc<Query>, String>;
+
+/// Unit types a query pack can define, and `call` for call graphs
+fn unit_types() -> impl Iterator<Item = &'static str> {
+    ["function", "class"].into_iter().chain(UnitKind::ALL.iter().map(|kind| kind.unit_type())).chain(["call"])
+}
+
+/// Compiled queries by language and unit type: loaded overrides, and
+/// defaults compiled on first use
+fn compiled() -> &'static RwLock<HashMap<(SupportedLanguage, &'static str), CompiledQuery>> {
+    static COMPILED: OnceLock<RwLock<HashMap<(SupportedLanguage, &'static str), CompiledQuery>>> = OnceLock::new();
+    COMPILED.get_or_init(Default::default)
+}
+
+fn default_source(lang: SupportedLanguage, unit_type: &str) -> Option<&'static str> {
+    DEFAULT_PACK.iter().find(|(l, t, _)| *l == lang && *t == unit_type).map(|(_, _, source)| *source)
+}
+
+/// Compile a query, which must mark each unit with a capture named after its type
+fn compile(lang: SupportedLanguage, unit_type: &str, source: &str) -> CompiledQuery {
+    let query = Query::new(&lang.get_language(), source).map_err(|e| e.to_string())?;
+    if query.capture_index_for_name(unit_type).is_none() {
+        return Err(format!("no @{} capture marks the unit", unit_type));
+    }
+    Ok(Arc::new(query))
+}
+
+/// The query extracting `unit_type` units of `lang`: the loaded override,
+/// else the default. None when neither exists (the language has no such
+/// construct); an error when the default fails to compile.
+pub(crate) fn query(lang: SupportedLanguage, unit_type: &'static str) -> Option<CompiledQuery> {
+    if let Some(found) = compiled().read().ok()?.get(&(lang, unit_type)) {
+        return Some(found.clone());
+    }
+    let query = compile(lang, unit_type, default_source(lang, unit_type)?);
+    Some(compiled().write().ok()?.entry((lang, unit_type)).or_insert(query).clone())
+}
+
+/// Queries registered with `register_custom_query`, by language and the
+/// unit type they introduce
+type CustomQueries = HashMap<(SupportedLanguage, String), Arc<Query>>;
+
+fn custom() -> &'static RwLock<CustomQueries> {
+    static CUSTOM: OnceLock<RwLock<CustomQueries>> = OnceLock::new();
+    CUSTOM.get_or_init(Default::default)
+}
+
+/// Compile and install a query emitting units of a new `unit_type` for
+/// `lang`, replacing any query registered under the same type. Built-in
+/// unit types are changed through query packs instead.
+pub fn register_custom(lang: SupportedLanguage, unit_type: &str, source: &str) -> Result<(), String> {
+    if unit_types().any(|builtin| builtin == unit_type) {
+        return Err(format!("{} is a built-in unit type; override it with load_query_pack", unit_type));
+    }
+    if unit_type.is_empty() || !unit_type.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid unit type {:?}: use letters, digits and underscores", unit_type));
+    }
+    let query = compile(lang, unit_type, source)?;
+    custom().write().map_err(|e| e.to_string())?.insert((lang, unit_type.to_string()), query);
+    Ok(())
+}
+
+/// Remove a registered query; returns whether there was one
+pub fn unregister_custom(lang: SupportedLanguage, unit_type: &str) -> bool {
+    custom().write().is_ok_and(|mut custom| custom.remove(&(lang, unit_type.to_string())).is_some())
+}
+
+/// Registered queries for `lang` with their unit types, sorted by type so
+/// units come out in a stable order
+pub(crate) fn custom_queries(lang: SupportedLanguage) -> Vec<(String, Arc<Query>)> {
+    let Ok(custom) = custom().read() else {
+        return Vec::new();
+    };
+    let mut queries: Vec<(String, Arc<Query>)> = custom
+        .iter()
+        .filter(|((l, _), _)| *l == lang)
+        .map(|((_, unit_type), query)| (unit_type.clone(), query.clone()))
+        .collect();
+    queries.sort_by(|a, b| a.0.cmp(&b.0));
+    queries
+}
+
+fn sorted_entries(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {}", directory.display(), e);
+    let mut paths = std::fs::read_dir(directory)
+        .map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Compile every `<language>/<unit type>.scm` under `directory` and, if all
+/// are valid, use them in place of the defaults. Other files are ignored.
+/// Returns the loaded queries as `language/unit_type`; on error nothing is
+/// loaded and each problem is reported on its own line.
+pub fn load_overrides(directory: &Path) -> Result<Vec<String>, String> {
+    let mut loaded = Vec::new();
+    let mut problems = Vec::new();
+    for language_dir in sorted_entries(directory)?.into_iter().filter(|path| path.is_dir()) {
+        let name = language_dir.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let Some(lang) = SupportedLanguage::from_name(&name) else {
+            problems.push(format!("{}: unknown language {}", language_dir.display(), name));
+            continue;
+        };
+        for file in sorted_entries(&language_dir)? {
+            if file.extension().and_then(|e| e.to_str()) != Some("scm") {
+                continue;
+            }
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            let Some(unit_type) = unit_types().find(|unit_type| *unit_type == stem) else {
+                let expected: Vec<&str> = unit_types().collect();
+                problems.push(format!(
+                    "{}: unknown unit type {} (expected one of: {})",
+                    file.display(),
+                    stem,
+                    expected.join(", ")
+                ));
+                continue;
+            };
+            let compiled = std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|source| compile(lang, unit_type, &source));
+            match compiled {
+                Ok(query) => loaded.push((lang, unit_type, query)),
+                Err(e) => problems.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    let names = loaded
+        .iter()
+        .map(|(lang, unit_type, _)| format!("{}/{}", format!("{:?}", lang).to_lowercase(), unit_type))
+        .collect();
+    let mut compiled = compiled().write().map_err(|e| e.to_string())?;
+    for (lang, unit_type, query) in loaded {
+        compiled.insert((lang, unit_type), Ok(query));
+    }
+    Ok(names)
+}
+
+/// Forget loaded overrides, so the defaults apply again
+pub fn reset_overrides() {
+    if let Ok(mut compiled) = compiled().write() {
+        compiled.clear();
+    }
+}
+
+/// Override extraction queries with a directory of query packs.
+///
+/// The layout matches the embedded defaults: `<language>/<unit type>.scm`,
+/// e.g. `ruby/function.scm` or `python/constant.scm` (see `default_query`).
+/// Each query must capture the whole unit as `@<unit type>` and may
+/// capture its `@name` and `@params`; `call.scm` captures each call as
+/// `@call` and the callee as `@name`. Overrides apply to every later
+/// parse. If any file is invalid nothing is loaded, and ValueError lists
+/// each problem with its file and, for query errors, the position.
+///
+/// Args:
+///     directory: Query pack directory
+///
+/// Returns:
+///     Loaded queries as "language/unit_type", sorted
+#[pyfunction]
+pub fn load_query_pack(py: Python<'_>, directory: &str) -> PyResult<Vec<String>> {
+    py.detach(|| load_overrides(Path::new(directory))).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Discard loaded query pack overrides and use the embedded defaults again
+#[pyfunction]
+pub fn reset_query_packs() {
+    reset_overrides();
+}
+
+fn language(name: &str) -> PyResult<SupportedLanguage> {
+    SupportedLanguage::from_name(name)
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown language: {}", name)))
+}
+
+/// The embedded default query for a language and unit type, as a starting
+/// point for an override; None when the language has no such construct.
+/// Raises ValueError for an unknown language.
+#[pyfunction]
+pub fn default_query(language: &str, unit_type: &str) -> PyResult<Option<String>> {
+    Ok(default_source(self::language(language)?, unit_type).map(str::to_string))
+}
+
+/// Extract an extra kind of unit (React hooks, pytest fixtures, Django
+/// models...) from every later parse of a language
+///
+/// The query marks each unit with a capture named after `unit_type` and may
+/// capture its `@name` and `@params`, as in a query pack; predicates such
+/// as `#match?` narrow it down. Its units carry `unit_type` and are emitted
+/// in addition to the built-in ones, even when they span the same node as a
+/// function or class. Registering the same language and unit type again
+/// replaces the query. Raises ValueError for an unknown language, a
+/// built-in or malformed unit type, or a query that doesn't compile or
+/// lacks the `@<unit_type>` capture.
+///
+/// Args:
+///     language: Language name, e.g. "python" or "tsx"
+///     unit_type: Type of the new units; built-in types ("function",
+///         "constant"...) are overridden with `load_query_pack` instead
+///     query_src: Tree-sitter query source
+#[pyfunction]
+pub fn register_custom_query(language: &str, unit_type: &str, query_src: &str) -> PyResult<()> {
+    register_custom(self::language(language)?, unit_type, query_src).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Stop extracting a unit type registered with `register_custom_query`;
+/// returns whether it was registered
+#[pyfunction]
+pub fn unregister_custom_query(language: &str, unit_type: &str) -> PyResult<bool> {
+    Ok(unregister_custom(self::language(language)?, unit_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{CodeParser, ParseOptions};
+
+    #[test]
+    fn test_default_pack_compiles() {
+        for &(lang, unit_type, source) in DEFAULT_PACK {
+            assert!(unit_types().any(|t| t == unit_type), "{:?} {}", lang, unit_type);
+            compile(lang, unit_type, source).unwrap_or_else(|e| panic!("{:?} {} query: {}", lang, unit_type, e));
+        }
+        assert!(query(SupportedLanguage::Sql, "enum").is_none());
+    }
+
+    #[test]
+    fn test_overrides_are_validated_then_used() {
+        let directory = std::env::temp_dir().join(format!("query_pack_{}", std::process::id()));
+        let ruby = directory.join("ruby");
+        std::fs::create_dir_all(&ruby).unwrap();
+        std::fs::write(directory.join("README.md"), "notes").unwrap();
+        std::fs::write(ruby.join("enum.scm"), "(module name: (constant) @name) @enum").unwrap();
+        std::fs::write(ruby.join("widget.scm"), "(module) @widget").unwrap();
+        std::fs::write(ruby.join("global.scm"), "(no_such_node) @global").unwrap();
+
+        let problems = load_overrides(&directory).unwrap_err();
+        assert_eq!(problems.lines().count(), 2, "{}", problems);
+        assert!(problems.contains("global.scm") && problems.contains("unknown unit type widget"));
+        assert!(query(SupportedLanguage::Ruby, "enum").is_none());
+
+        std::fs::remove_file(ruby.join("widget.scm")).unwrap();
+        std::fs::remove_file(ruby.join("global.scm")).unwrap();
+        let loaded = load_overrides(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(loaded.unwrap(), vec!["ruby/enum"]);
+        let options = ParseOptions { unit_kinds: vec![UnitKind::Enum], ..ParseOptions::default() };
+        let result = CodeParser::new().parse_file("color.rb", "module Color\nend\n", &options).unwrap();
+        assert!(result.units.iter().any(|u| u.unit_type == "enum" && u.name == "Color"));
+
+        // A query that doesn't compile only costs its units, with a warning
+        let broken = (SupportedLanguage::Sql, "constant");
+        compiled().write().unwrap().insert(broken, Err("Invalid node type".to_string()));
+        let options = ParseOptions { unit_kinds: vec![UnitKind::Constant], ..ParseOptions::default() };
+        let result = CodeParser::new().parse_file("schema.sql", "CREATE TABLE t (id INT);\n", &options).unwrap();
+        assert_eq!(result.warnings, vec!["constant extraction skipped: Invalid node type"]);
+        assert_eq!(result.units[0].name, "t");
+        reset_overrides();
+        assert!(query(SupportedLanguage::Ruby, "enum").is_none());
+    }
+
+    #[test]
+    fn test_custom_queries_add_unit_types() {
+        let python = SupportedLanguage::Python;
+        let fixture = r#"(decorated_definition
+            (decorator) @marker (#match? @marker "custom_query_fixture")
+            definition: (function_definition name: (identifier) @name)) @fixture"#;
+        assert!(register_custom(python, "function", fixture).unwrap_err().contains("built-in"));
+        assert!(register_custom(python, "fixture", "(function_definition) @function").is_err());
+        register_custom(python, "fixture", fixture).unwrap();
+
+        let source = "@custom_query_fixture\ndef db():\n    pass\n\n@other\ndef helper():\n    pass\n";
+        let result = CodeParser::new().parse_file("conftest.py", source, &ParseOptions::default()).unwrap();
+        let fixtures: Vec<&str> = result.units.iter().filter(|u| u.unit_type == "fixture").map(|u| &*u.name).collect();
+        assert_eq!(fixtures, vec!["db"]);
+        assert!(result.units.iter().any(|u| u.unit_type == "function" && u.name == "db"));
+
+        assert!(unregister_custom(python, "fixture"));
+        let result = CodeParser::new().parse_file("conftest.py", source, &ParseOptions::default()).unwrap();
+        assert!(result.units.iter().all(|u| u.unit_type != "fixture"));
+    }
+}
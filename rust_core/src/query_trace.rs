@@ -0,0 +1,136 @@
+use pyo3::prelude::*;
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::retrieval::RetrievalHit;
+
+/// What one pipeline stage of a traced query kept and removed
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct TraceStage {
+    #[pyo3(get)]
+    pub stage: String,
+    #[pyo3(get)]
+    pub elapsed_ms: f64,
+    #[pyo3(get)]
+    pub candidates: Vec<(String, f32)>, // Survivors in stage order, with the score the stage ranked by
+    #[pyo3(get)]
+    pub dropped: Vec<String>, // Candidates of the previous stage that this one removed
+}
+
+#[pymethods]
+impl TraceStage {
+    fn __repr__(&self) -> String {
+        format!(
+            "TraceStage(stage={}, candidates={}, dropped={}, time={:.3}ms)",
+            self.stage,
+            self.candidates.len(),
+            self.dropped.len(),
+            self.elapsed_ms
+        )
+    }
+}
+
+/// Every stage of one query, for working out why a document did or didn't
+/// come back
+///
+/// Stages are recorded in order: "prefilter" (expired documents and the
+/// binary prefilter, scored by Hamming distance), "ann" (cosine
+/// similarity), "supersedes" (only when relations exist), "filter"
+/// (metadata), "mmr" or "top_k", and "rerank" (final scores, pinned
+/// documents forced in).
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct QueryTrace {
+    #[pyo3(get)]
+    pub profile: String, // Scoring profile, or "blend" for the `lexical_weight` blend
+    #[pyo3(get)]
+    pub terms: Vec<(String, f32)>, // Expanded, spell-corrected query terms the rerank matched
+    #[pyo3(get)]
+    pub stages: Vec<TraceStage>,
+    #[pyo3(get)]
+    pub total_ms: f64,
+    started: Instant,
+    last: Instant,
+    /// Ids of the last stage's candidates, to tell what the next one drops
+    previous: Vec<String>,
+}
+
+#[pymethods]
+impl QueryTrace {
+    /// Where a document left the pipeline: "dropped by <stage>",
+    /// "returned at rank <n>", or "not a document" for an unknown id
+    pub(crate) fn explain(&self, id: &str) -> String {
+        if let Some(stage) = self.stages.iter().find(|stage| stage.dropped.iter().any(|d| d == id)) {
+            return format!("dropped by {}", stage.stage);
+        }
+        let rank = self.stages.last().and_then(|stage| stage.candidates.iter().position(|(c, _)| c == id));
+        match rank {
+            Some(rank) => format!("returned at rank {}", rank + 1),
+            None => "not a document".to_string(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("QueryTrace(profile={}, stages={}, time={:.3}ms)", self.profile, self.stages.len(), self.total_ms)
+    }
+}
+
+impl QueryTrace {
+    /// Start tracing a query over the documents `ids`
+    pub fn new(ids: Vec<String>) -> Self {
+        let now = Instant::now();
+        Self {
+            profile: String::new(),
+            terms: Vec::new(),
+            stages: Vec::new(),
+            total_ms: 0.0,
+            started: now,
+            last: now,
+            previous: ids,
+        }
+    }
+
+    /// Record a stage that ran since the last one, leaving `candidates`
+    pub fn record(&mut self, stage: &str, candidates: Vec<(String, f32)>) {
+        let elapsed_ms = self.last.elapsed().as_secs_f64() * 1000.0;
+        let kept: HashSet<&str> = candidates.iter().map(|(id, _)| id.as_str()).collect();
+        let dropped = self.previous.iter().filter(|id| !kept.contains(id.as_str())).cloned().collect();
+        self.previous = candidates.iter().map(|(id, _)| id.clone()).collect();
+        self.stages.push(TraceStage { stage: stage.to_string(), elapsed_ms, candidates, dropped });
+        self.total_ms = self.started.elapsed().as_secs_f64() * 1000.0;
+        // Building the lists above isn't charged to the next stage
+        self.last = Instant::now();
+    }
+}
+
+/// Record a stage if the query is traced; `candidates` is only built then
+pub fn record(trace: &mut Option<&mut QueryTrace>, stage: &str, candidates: impl FnOnce() -> Vec<(String, f32)>) {
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.record(stage, candidates());
+    }
+}
+
+/// A search's hits, or with `trace` the hits and how the query reached them
+#[derive(Debug, IntoPyObject)]
+pub enum SearchOutput {
+    Hits(Vec<RetrievalHit>),
+    Traced(Vec<RetrievalHit>, QueryTrace),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_record_what_they_drop() {
+        let mut trace = QueryTrace::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        trace.record("ann", vec![("b".to_string(), 0.9), ("a".to_string(), 0.5)]);
+        trace.record("filter", vec![("a".to_string(), 0.5)]);
+        assert_eq!(trace.stages[0].dropped, vec!["c"]);
+        assert_eq!(trace.stages[1].dropped, vec!["b"]);
+        assert_eq!(trace.explain("a"), "returned at rank 1");
+        assert_eq!(trace.explain("b"), "dropped by filter");
+        assert_eq!(trace.explain("z"), "not a document");
+    }
+}
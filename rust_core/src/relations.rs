@@ -0,0 +1,244 @@
+use pyo3::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// How one memory relates to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelationKind {
+    /// The source replaces the (older) target
+    Supersedes,
+    /// The two memories disagree; symmetric
+    Contradicts,
+    /// The source was produced from the target
+    DerivesFrom,
+    /// The source links to or cites the target
+    References,
+}
+
+impl RelationKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('-', "_").as_str() {
+            "supersedes" => Some(RelationKind::Supersedes),
+            "contradicts" => Some(RelationKind::Contradicts),
+            "derives_from" => Some(RelationKind::DerivesFrom),
+            "references" => Some(RelationKind::References),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RelationKind::Supersedes => "supersedes",
+            RelationKind::Contradicts => "contradicts",
+            RelationKind::DerivesFrom => "derives_from",
+            RelationKind::References => "references",
+        }
+    }
+
+    /// `(source, target)` as stored: symmetric kinds keep the smaller id first
+    fn oriented<'a>(self, source: &'a str, target: &'a str) -> (&'a str, &'a str) {
+        if self == RelationKind::Contradicts && target < source {
+            (target, source)
+        } else {
+            (source, target)
+        }
+    }
+}
+
+/// `(kind, other id)` edges of each memory id, in one direction
+type EdgeIndex = HashMap<String, BTreeSet<(RelationKind, String)>>;
+
+/// A typed relation between two memories
+#[derive(Debug, Clone, PartialEq)]
+#[pyclass]
+pub struct MemoryRelation {
+    #[pyo3(get)]
+    pub source: String,
+    #[pyo3(get)]
+    pub kind: String, // "supersedes", "contradicts", "derives_from" or "references"
+    #[pyo3(get)]
+    pub target: String,
+}
+
+#[pymethods]
+impl MemoryRelation {
+    fn __repr__(&self) -> String {
+        format!("MemoryRelation({} -[{}]-> {})", self.source, self.kind, self.target)
+    }
+}
+
+/// Typed edges between memory ids, indexed in both directions
+///
+/// Supersedes edges never form a cycle, so every chain has a newest end.
+/// Detaching a memory removes its edges and links each memory that
+/// superseded it to each memory it superseded, keeping chains connected.
+#[derive(Debug, Clone, Default)]
+pub struct Relations {
+    /// `(kind, target)` of each source's edges
+    outgoing: EdgeIndex,
+    /// `(kind, source)` of each target's edges
+    incoming: EdgeIndex,
+}
+
+impl Relations {
+    pub fn is_empty(&self) -> bool {
+        self.outgoing.is_empty()
+    }
+
+    /// Number of relations
+    pub fn len(&self) -> usize {
+        self.outgoing.values().map(BTreeSet::len).sum()
+    }
+
+    /// Record `source -[kind]-> target`; returns whether it is new
+    pub fn add(&mut self, source: &str, kind: RelationKind, target: &str) -> Result<bool, String> {
+        if source == target {
+            return Err(format!("{} cannot relate to itself", source));
+        }
+        let (source, target) = kind.oriented(source, target);
+        if kind == RelationKind::Supersedes && self.supersedes(target, source) {
+            return Err(format!("{} already supersedes {}", target, source));
+        }
+        Ok(self.link(source, kind, target))
+    }
+
+    /// Drop `source -[kind]-> target`; returns whether it existed
+    pub fn remove(&mut self, source: &str, kind: RelationKind, target: &str) -> bool {
+        let (source, target) = kind.oriented(source, target);
+        let removed = remove_edge(&mut self.outgoing, source, kind, target);
+        if removed {
+            remove_edge(&mut self.incoming, target, kind, source);
+        }
+        removed
+    }
+
+    /// Remove every edge touching `id`, bridging its supersedes chain
+    pub fn detach(&mut self, id: &str) {
+        let newer: Vec<String> = self.sources(id, RelationKind::Supersedes).map(String::from).collect();
+        let older: Vec<String> = self.targets(id, RelationKind::Supersedes).map(String::from).collect();
+        for (kind, target) in self.outgoing.remove(id).unwrap_or_default() {
+            remove_edge(&mut self.incoming, &target, kind, id);
+        }
+        for (kind, source) in self.incoming.remove(id).unwrap_or_default() {
+            remove_edge(&mut self.outgoing, &source, kind, id);
+        }
+        for source in &newer {
+            for target in &older {
+                self.link(source, RelationKind::Supersedes, target);
+            }
+        }
+    }
+
+    /// Ids `id` points to with `kind`
+    pub fn targets<'a>(&'a self, id: &str, kind: RelationKind) -> impl Iterator<Item = &'a str> {
+        edges_of(&self.outgoing, id, kind)
+    }
+
+    /// Ids pointing to `id` with `kind`
+    pub fn sources<'a>(&'a self, id: &str, kind: RelationKind) -> impl Iterator<Item = &'a str> {
+        edges_of(&self.incoming, id, kind)
+    }
+
+    /// Every relation with `id` as source or target, outgoing first
+    pub fn of(&self, id: &str) -> Vec<MemoryRelation> {
+        let relation = |source: &str, kind: RelationKind, target: &str| MemoryRelation {
+            source: source.to_string(),
+            kind: kind.name().to_string(),
+            target: target.to_string(),
+        };
+        let outgoing = self.outgoing.get(id).into_iter().flatten().map(|(kind, target)| relation(id, *kind, target));
+        let incoming = self.incoming.get(id).into_iter().flatten().map(|(kind, source)| relation(source, *kind, id));
+        outgoing.chain(incoming).collect()
+    }
+
+    fn link(&mut self, source: &str, kind: RelationKind, target: &str) -> bool {
+        self.incoming.entry(target.to_string()).or_default().insert((kind, source.to_string()));
+        self.outgoing.entry(source.to_string()).or_default().insert((kind, target.to_string()))
+    }
+
+    /// Whether `newer` transitively supersedes `older`
+    fn supersedes(&self, newer: &str, older: &str) -> bool {
+        let mut stack = vec![newer];
+        let mut seen: HashSet<&str> = HashSet::from([newer]);
+        while let Some(id) = stack.pop() {
+            for next in self.targets(id, RelationKind::Supersedes) {
+                if next == older {
+                    return true;
+                }
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        false
+    }
+}
+
+fn edges_of<'a>(index: &'a EdgeIndex, id: &str, kind: RelationKind) -> impl Iterator<Item = &'a str> {
+    index
+        .get(id)
+        .into_iter()
+        .flatten()
+        .filter(move |(k, _)| *k == kind)
+        .map(|(_, other)| other.as_str())
+}
+
+fn remove_edge(index: &mut EdgeIndex, id: &str, kind: RelationKind, other: &str) -> bool {
+    let Some(edges) = index.get_mut(id) else { return false };
+    let removed = edges.remove(&(kind, other.to_string()));
+    if edges.is_empty() {
+        index.remove(id);
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(relations: &Relations, id: &str) -> Vec<String> {
+        relations.of(id).iter().map(|r| format!("{} {} {}", r.source, r.kind, r.target)).collect()
+    }
+
+    #[test]
+    fn test_kind_names() {
+        assert_eq!(RelationKind::from_name("derives-from"), Some(RelationKind::DerivesFrom));
+        assert_eq!(RelationKind::from_name("Supersedes"), Some(RelationKind::Supersedes));
+        assert_eq!(RelationKind::from_name("replaces"), None);
+    }
+
+    #[test]
+    fn test_add_remove_and_symmetric_contradicts() {
+        let mut relations = Relations::default();
+        assert!(relations.add("b", RelationKind::Contradicts, "a").unwrap());
+        assert!(!relations.add("a", RelationKind::Contradicts, "b").unwrap());
+        assert!(relations.add("c", RelationKind::DerivesFrom, "a").unwrap());
+        assert_eq!(relations.len(), 2);
+        assert_eq!(names(&relations, "a"), vec!["a contradicts b", "c derives_from a"]);
+        assert!(relations.remove("b", RelationKind::Contradicts, "a"));
+        assert!(!relations.remove("a", RelationKind::Contradicts, "b"));
+        assert!(relations.remove("c", RelationKind::DerivesFrom, "a"));
+        assert!(relations.is_empty());
+        assert!(relations.add("a", RelationKind::Supersedes, "a").is_err());
+    }
+
+    #[test]
+    fn test_supersedes_cycles_are_rejected() {
+        let mut relations = Relations::default();
+        relations.add("v3", RelationKind::Supersedes, "v2").unwrap();
+        relations.add("v2", RelationKind::Supersedes, "v1").unwrap();
+        assert!(relations.add("v1", RelationKind::Supersedes, "v3").is_err());
+        assert!(relations.add("v1", RelationKind::Contradicts, "v3").is_ok());
+    }
+
+    #[test]
+    fn test_detach_relinks_supersedes_chain() {
+        let mut relations = Relations::default();
+        relations.add("v3", RelationKind::Supersedes, "v2").unwrap();
+        relations.add("v2", RelationKind::Supersedes, "v1").unwrap();
+        relations.add("note", RelationKind::DerivesFrom, "v2").unwrap();
+        relations.detach("v2");
+        assert_eq!(names(&relations, "v3"), vec!["v3 supersedes v1"]);
+        assert!(relations.of("v2").is_empty());
+        assert!(relations.of("note").is_empty());
+    }
+}
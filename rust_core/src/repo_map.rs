@@ -0,0 +1,212 @@
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::graph_ranking::{pagerank, WeightedEdge};
+use crate::parsing::{ParseResult, SemanticUnit};
+use crate::symbol_index::{build_outline, OutlineNode};
+
+/// Rough token count of map text (about four characters per token)
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// How files are ordered in the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankBy {
+    /// PageRank over the cross-file reference graph
+    PageRank,
+    /// Number of references from other files
+    References,
+    /// Input order
+    Input,
+}
+
+impl RankBy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pagerank" => Some(RankBy::PageRank),
+            "references" => Some(RankBy::References),
+            "input" | "none" => Some(RankBy::Input),
+            _ => None,
+        }
+    }
+}
+
+/// Identifier-like words in `text`
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| w.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_'))
+}
+
+/// Units not contained in another unit of the same file
+fn top_level_units(units: &[SemanticUnit]) -> Vec<&SemanticUnit> {
+    units
+        .iter()
+        .filter(|unit| {
+            !units.iter().any(|outer| {
+                outer.start_byte <= unit.start_byte
+                    && unit.end_byte <= outer.end_byte
+                    && (outer.start_byte, outer.end_byte) != (unit.start_byte, unit.end_byte)
+            })
+        })
+        .collect()
+}
+
+/// Cross-file reference edges `(from, to, weight)`: file `from` mentions a
+/// name that file `to` defines. A name defined in several files splits its
+/// weight between them; references within a file are ignored.
+pub fn reference_edges(results: &[ParseResult]) -> Vec<WeightedEdge> {
+    let mut definers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, result) in results.iter().enumerate() {
+        let names: HashSet<&str> = result.units.iter().map(|u| u.name.as_str()).collect();
+        for name in names {
+            definers.entry(name).or_default().push(index);
+        }
+    }
+
+    let mut weights: HashMap<(usize, usize), f64> = HashMap::new();
+    for (from, result) in results.iter().enumerate() {
+        for unit in top_level_units(&result.units) {
+            for word in identifiers(&unit.content) {
+                let Some(files) = definers.get(word) else { continue };
+                let share = 1.0 / files.len() as f64;
+                for &to in files.iter().filter(|&&to| to != from) {
+                    *weights.entry((from, to)).or_insert(0.0) += share;
+                }
+            }
+        }
+    }
+
+    let mut edges: Vec<WeightedEdge> = weights.into_iter().map(|((from, to), w)| (from, to, w)).collect();
+    edges.sort_by_key(|&(from, to, _)| (from, to));
+    edges
+}
+
+/// File indices in map order, highest ranked first (ties keep input order)
+pub fn rank_files(results: &[ParseResult], rank_by: RankBy) -> Vec<usize> {
+    let scores: Vec<f64> = match rank_by {
+        RankBy::Input => return (0..results.len()).collect(),
+        RankBy::PageRank => pagerank(results.len(), &reference_edges(results), 0.85, 100),
+        RankBy::References => {
+            let mut incoming = vec![0.0; results.len()];
+            for (_, to, weight) in reference_edges(results) {
+                incoming[to] += weight;
+            }
+            incoming
+        }
+    };
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+    order
+}
+
+fn render_nodes(nodes: &[OutlineNode], depth: Option<usize>, indent: usize, out: &mut String) {
+    for node in nodes {
+        out.push_str(&"  ".repeat(indent));
+        out.push_str(&node.signature);
+        out.push('\n');
+        if depth.is_none_or(|d| d > 1) {
+            render_nodes(&node.children, depth.map(|d| d - 1), indent + 1, out);
+        }
+    }
+}
+
+/// Map block for one file, optionally limited to `depth` outline levels
+fn file_block(result: &ParseResult, depth: Option<usize>) -> String {
+    let mut block = format!("{}:\n", result.file_path);
+    render_nodes(&build_outline(&result.units), depth, 1, &mut block);
+    block
+}
+
+/// Render a ranked, token-budgeted repository map.
+///
+/// Files are visited in rank order; each gets its full outline if that fits
+/// the remaining budget, otherwise only its top-level symbols, otherwise it
+/// is left out (smaller files further down may still fit). Files without
+/// units are skipped.
+pub fn render_repo_map(results: &[ParseResult], token_budget: usize, rank_by: RankBy) -> String {
+    let mut map = String::new();
+    let mut remaining = token_budget;
+    for index in rank_files(results, rank_by) {
+        let result = &results[index];
+        if result.units.is_empty() {
+            continue;
+        }
+        let block = [file_block(result, None), file_block(result, Some(1))]
+            .into_iter()
+            .find(|block| estimate_tokens(block) <= remaining);
+        if let Some(block) = block {
+            remaining -= estimate_tokens(&block);
+            map.push_str(&block);
+        }
+    }
+    map
+}
+
+/// Build a token-budgeted map of the repository from parse results
+///
+/// Each file is listed with an outline of its symbols (classes with their
+/// methods, compact signatures). `rank_by` orders files: "pagerank" (over
+/// cross-file name references), "references" (incoming reference count), or
+/// "input". Files that don't fit `token_budget` (estimated at ~4 characters
+/// per token) are shortened to top-level symbols or dropped.
+#[pyfunction]
+#[pyo3(signature = (parse_results, token_budget=1024, rank_by="pagerank"))]
+pub fn build_repo_map(parse_results: Vec<ParseResult>, token_budget: usize, rank_by: &str) -> PyResult<String> {
+    let rank_by = RankBy::from_name(rank_by)
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown rank_by: {}", rank_by)))?;
+    Ok(render_repo_map(&parse_results, token_budget, rank_by))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    fn parse(files: &[(&str, &str)]) -> Vec<ParseResult> {
+        files
+            .iter()
+            .map(|(path, source)| parse_any_file(path, source, &ParseOptions::default()).unwrap())
+            .collect()
+    }
+
+    fn sample() -> Vec<ParseResult> {
+        parse(&[
+            ("app.py", "def main():\n    cart = Cart()\n    return format_total(cart.total())\n"),
+            ("cart.py", "class Cart:\n    def total(self):\n        return 1\n"),
+            ("fmt.py", "def format_total(value):\n    return str(Cart and value)\n"),
+        ])
+    }
+
+    #[test]
+    fn test_reference_edges_point_at_definers() {
+        let edges = reference_edges(&sample());
+        let pairs: Vec<(usize, usize)> = edges.iter().map(|&(from, to, _)| (from, to)).collect();
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_most_referenced_file_ranks_first() {
+        assert_eq!(rank_files(&sample(), RankBy::PageRank)[0], 1);
+        assert_eq!(rank_files(&sample(), RankBy::References)[0], 1);
+        assert_eq!(rank_files(&sample(), RankBy::Input), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_repo_map_renders_outlines_in_rank_order() {
+        let map = render_repo_map(&sample(), 1000, RankBy::PageRank);
+        assert!(map.starts_with("cart.py:\n  class Cart\n    def total(self)\n"));
+        assert!(map.contains("app.py:\n  def main()\n"));
+    }
+
+    #[test]
+    fn test_repo_map_respects_budget() {
+        let results = sample();
+        let full = render_repo_map(&results, 1000, RankBy::PageRank);
+        let budget = estimate_tokens(&full) - 1;
+        let trimmed = render_repo_map(&results, budget, RankBy::PageRank);
+        assert!(estimate_tokens(&trimmed) <= budget);
+        assert!(trimmed.len() < full.len());
+        assert!(render_repo_map(&results, 0, RankBy::PageRank).is_empty());
+    }
+}
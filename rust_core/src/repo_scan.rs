@@ -0,0 +1,288 @@
+use ignore::gitignore::GitignoreBuilder;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+use crate::core_config;
+use crate::parsing::{is_parseable, parse_path, ParseOptions, ParseResult};
+use crate::pdf_parsing::is_pdf;
+
+/// Directories never indexed, whether or not they're ignored
+const EXCLUDED_DIRS: &[&str] = &[
+    ".git",
+    ".venv",
+    "venv",
+    ".virtualenv",
+    "__pycache__",
+    "node_modules",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".tox",
+    ".worktrees",
+];
+
+/// Files larger than this are skipped unless the caller says otherwise
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Leading bytes checked for a NUL byte, which marks a file as binary
+const SNIFF_BYTES: u64 = 8192;
+
+/// The parsed files of a repository, and those left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct RepositoryScan {
+    #[pyo3(get)]
+    pub root: String,
+    #[pyo3(get)]
+    pub results: Vec<ParseResult>, // In path order
+    #[pyo3(get)]
+    pub skipped: Vec<(String, String)>, // (path, "binary", "too large", "generated" or "minified")
+    #[pyo3(get)]
+    pub errors: Vec<(String, String)>, // (path, message) for files that couldn't be read or parsed
+}
+
+#[pymethods]
+impl RepositoryScan {
+    fn __repr__(&self) -> String {
+        format!(
+            "RepositoryScan(root={}, parsed={}, skipped={}, errors={})",
+            self.root,
+            self.results.len(),
+            self.skipped.len(),
+            self.errors.len()
+        )
+    }
+}
+
+/// A path and why it failed
+type Failure = (String, String);
+
+/// Paths of the files a walk found with their sizes, and its errors
+type Walked = (Vec<(String, u64)>, Vec<Failure>);
+
+/// What became of one file
+enum Outcome {
+    Parsed(ParseResult),
+    Skipped(&'static str),
+    Failed(String),
+}
+
+/// Files under `root` worth parsing, in path order, with their sizes:
+/// not ignored by `.gitignore`/`.ignore` files, not hidden, not in
+/// `EXCLUDED_DIRS`, in a supported language and matching the globs. Walk
+/// errors (unreadable directories) are returned alongside.
+fn walk(root: &str, include: &[String], exclude: &[String]) -> Result<Walked, String> {
+    if !std::path::Path::new(root).is_dir() {
+        return Err(format!("Not a directory: {}", root));
+    }
+    // Includes are matched after the walk: as whitelist overrides they'd
+    // take priority over .gitignore and bring ignored files back
+    let mut includes = GitignoreBuilder::new(root);
+    for glob in include {
+        includes.add_line(None, glob).map_err(|e| format!("Invalid include glob {}: {}", glob, e))?;
+    }
+    let includes = includes.build().map_err(|e| e.to_string())?;
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in exclude {
+        overrides.add(&format!("!{}", glob)).map_err(|e| format!("Invalid exclude glob {}: {}", glob, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| e.to_string())?;
+
+    // Hidden directories (.github, .config) are walked; hidden files, which
+    // include secrets like .env, are not
+    let walker = WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            !(is_dir && entry.file_name().to_str().is_some_and(|name| EXCLUDED_DIRS.contains(&name)))
+        })
+        .build();
+
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push((root.to_string(), e.to_string()));
+                continue;
+            }
+        };
+        let is_file = entry.file_type().is_some_and(|kind| kind.is_file());
+        let hidden = entry.file_name().to_str().is_none_or(|name| name.starts_with('.'));
+        let Some(path) = entry.path().to_str().filter(|path| is_file && !hidden && is_parseable(path)) else {
+            continue;
+        };
+        // The matcher reports a path matching one of its lines as "ignore"
+        if !include.is_empty() && !includes.matched_path_or_any_parents(path, false).is_ignore() {
+            continue;
+        }
+        match entry.metadata() {
+            Ok(metadata) => files.push((path.to_string(), metadata.len())),
+            Err(e) => errors.push((path.to_string(), e.to_string())),
+        }
+    }
+    files.sort();
+    Ok((files, errors))
+}
+
+/// Whether a file starts with a NUL byte within its first `SNIFF_BYTES`
+fn is_binary(path: &str) -> std::io::Result<bool> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)?.take(SNIFF_BYTES).read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+fn scan_file(path: &str, size: u64, max_file_bytes: u64, options: &ParseOptions) -> Outcome {
+    if size > max_file_bytes {
+        return Outcome::Skipped("too large");
+    }
+    // PDFs are binary but have a parser of their own
+    match is_binary(path).map(|binary| binary && !is_pdf(path)) {
+        Ok(true) => Outcome::Skipped("binary"),
+        Ok(false) => match parse_path(path, options) {
+            Ok(result) if options.skip_generated => match result.file_classification.as_str() {
+                "generated" => Outcome::Skipped("generated"),
+                "minified" => Outcome::Skipped("minified"),
+                _ => Outcome::Parsed(result),
+            },
+            Ok(result) => Outcome::Parsed(result),
+            Err(e) => Outcome::Failed(e),
+        },
+        Err(e) => Outcome::Failed(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Walk a repository and parse its files in parallel. Unlike
+/// `parse_paths`, a file that fails is recorded and the scan goes on.
+pub(crate) fn scan_repository_files(
+    root: &str,
+    include: &[String],
+    exclude: &[String],
+    max_file_bytes: u64,
+    options: &ParseOptions,
+) -> Result<RepositoryScan, String> {
+    let (files, mut errors) = walk(root, include, exclude)?;
+    let outcomes: Vec<Outcome> = core_config::install(|| {
+        files.par_iter().map(|(path, size)| scan_file(path, *size, max_file_bytes, options)).collect()
+    });
+
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+    for ((path, _), outcome) in files.into_iter().zip(outcomes) {
+        match outcome {
+            Outcome::Parsed(result) => results.push(result),
+            Outcome::Skipped(reason) => skipped.push((path, reason.to_string())),
+            Outcome::Failed(message) => errors.push((path, message)),
+        }
+    }
+    Ok(RepositoryScan { root: root.to_string(), results, skipped, errors })
+}
+
+/// Walk a repository natively and parse every supported file in parallel
+///
+/// `.gitignore`, `.ignore` and `.git/info/exclude` rules are respected
+/// (also outside a git checkout), as are global git excludes. Hidden files,
+/// dependency and cache directories (`node_modules`, `.venv`,
+/// `__pycache__`...), binary files and files over `max_file_bytes` are
+/// skipped, as are files no parser handles. PDFs are parsed when the
+/// `pdf` feature is built in. With `skip_generated`, generated and minified
+/// files are skipped too.
+///
+/// Args:
+///     root: Directory to scan
+///     include_globs: Only files matching one of these gitignore-style
+///         globs, relative to `root` (e.g. `src/**/*.py`), are scanned
+///     exclude_globs: Files matching these globs are not scanned
+///     max_file_bytes: Largest file parsed, in bytes
+///     unit_kinds, sql_dialect, parse_template_host, content_refs,
+///         skip_generated: As for `batch_parse_files`
+///
+/// Returns:
+///     RepositoryScan with a ParseResult per parsed file in path order,
+///     the skipped files with why, and the files that failed with their
+///     errors. Raises ValueError for a missing root or an invalid glob.
+#[pyfunction]
+#[pyo3(signature = (
+    root,
+    include_globs=None,
+    exclude_globs=None,
+    max_file_bytes=DEFAULT_MAX_FILE_BYTES,
+    unit_kinds=None,
+    sql_dialect=None,
+    parse_template_host=false,
+    content_refs=false,
+    skip_generated=false
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn scan_repository(
+    py: Python<'_>,
+    root: String,
+    include_globs: Option<Vec<String>>,
+    exclude_globs: Option<Vec<String>>,
+    max_file_bytes: u64,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+    skip_generated: bool,
+) -> PyResult<RepositoryScan> {
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    let options = ParseOptions { skip_generated, ..options };
+    let (include, exclude) = (include_globs.unwrap_or_default(), exclude_globs.unwrap_or_default());
+    py.detach(|| scan_repository_files(&root, &include, &exclude, max_file_bytes, &options))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_respects_gitignore_and_skips_binaries() {
+        let root = std::env::temp_dir().join(format!("scan_repository_{}", std::process::id()));
+        let files: &[(&str, &[u8])] = &[
+            (".gitignore", b"build/\n*.gen.py\n"),
+            ("src/app.py", b"def main():\n    pass\n"),
+            ("src/schema.gen.py", b"def generated():\n    pass\n"),
+            ("src/blob.py", b"\x00\x01binary"),
+            ("src/big.rs", b"fn big() {}\n// padding padding padding\n"),
+            ("src/notes.txt", b"not parsed"),
+            ("build/out.py", b"def built():\n    pass\n"),
+            ("node_modules/pkg/index.js", b"function f() {}\n"),
+            (".github/workflows/ci.yml", b"on: push\njobs: {}\n"),
+            ("tests/test_app.py", b"def test_main():\n    pass\n"),
+            (".env", b"TOKEN=secret\n"),
+        ];
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let root_path = root.to_string_lossy().into_owned();
+        let relative = |path: &str| path.strip_prefix(root_path.as_str()).unwrap().trim_start_matches('/').to_string();
+
+        let scan = scan_repository_files(&root_path, &[], &["tests/**".to_string()], 30, &ParseOptions::default());
+        let include = ["src/*.py".to_string()];
+        let narrowed = scan_repository_files(&root_path, &include, &[], 1024, &ParseOptions::default());
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let scan = scan.unwrap();
+        let parsed: Vec<String> = scan.results.iter().map(|result| relative(&result.file_path)).collect();
+        assert_eq!(parsed, vec![".github/workflows/ci.yml", "src/app.py"]);
+        let skipped: Vec<(String, &str)> =
+            scan.skipped.iter().map(|(path, reason)| (relative(path), reason.as_str())).collect();
+        assert_eq!(skipped, vec![("src/big.rs".to_string(), "too large"), ("src/blob.py".to_string(), "binary")]);
+        assert!(scan.errors.is_empty());
+
+        let narrowed = narrowed.unwrap();
+        let parsed: Vec<String> = narrowed.results.iter().map(|result| relative(&result.file_path)).collect();
+        assert_eq!(parsed, vec!["src/app.py"]);
+        assert!(scan_repository_files("/no/such/root", &[], &[], 1024, &ParseOptions::default()).is_err());
+    }
+}
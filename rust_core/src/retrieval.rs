@@ -0,0 +1,1618 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::contradiction::{Claims, ContradictionCandidate};
+use crate::core_config;
+use crate::dry_run::{ChangePlan, Outcome};
+use crate::index::VectorIndex;
+use crate::keyword_index::KeywordIndex;
+use crate::query_expansion::{document_terms, expand, vocabulary, ExpandedQuery};
+use crate::query_trace::{self, QueryTrace, SearchOutput};
+use crate::relations::{MemoryRelation, RelationKind, Relations};
+use crate::simd::{dot, normalized};
+use crate::spelling::{SpellCorrector, Suggestion};
+use crate::transaction::{self, Failure, Op};
+use crate::vector_store::VectorStore;
+
+/// Stage settings of a `RetrievalPipeline`
+///
+/// Stages run in order: binary prefilter (Hamming distance over sign bits),
+/// ANN scoring (cosine over the survivors), metadata filter, MMR
+/// diversification, lexical rerank. A stage is skipped when its setting is
+/// None or zero. Named scoring `profiles` replace the lexical blend with a
+/// weighted mix of signals when a search selects one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[pyclass(get_all, set_all)]
+pub struct PipelineConfig {
+    /// Candidates kept by the binary prefilter
+    pub prefilter_candidates: Option<usize>,
+    /// Candidates kept after cosine scoring
+    pub ann_candidates: usize,
+    /// MMR trade-off between relevance (1.0) and diversity (0.0)
+    pub mmr_lambda: Option<f32>,
+    /// Share of the final score taken from query term overlap
+    pub lexical_weight: f32,
+    /// Results returned per query
+    pub top_k: usize,
+    /// Scoring profiles selectable by name per search
+    pub profiles: HashMap<String, ScoringProfile>,
+    /// Pinned documents guaranteed a place in every result set, displacing
+    /// the lowest-ranked unpinned hits
+    pub pinned_budget: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            prefilter_candidates: Some(1000),
+            ann_candidates: 100,
+            mmr_lambda: None,
+            lexical_weight: 0.0,
+            top_k: 10,
+            profiles: HashMap::new(),
+            pinned_budget: 0,
+        }
+    }
+}
+
+#[pymethods]
+impl PipelineConfig {
+    #[new]
+    #[pyo3(signature = (prefilter_candidates=Some(1000), ann_candidates=100, mmr_lambda=None, lexical_weight=0.0, top_k=10, profiles=None, pinned_budget=0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        prefilter_candidates: Option<usize>,
+        ann_candidates: usize,
+        mmr_lambda: Option<f32>,
+        lexical_weight: f32,
+        top_k: usize,
+        profiles: Option<HashMap<String, ScoringProfile>>,
+        pinned_budget: usize,
+    ) -> PyResult<Self> {
+        let profiles = profiles.unwrap_or_default();
+        let config =
+            Self { prefilter_candidates, ann_candidates, mmr_lambda, lexical_weight, top_k, profiles, pinned_budget };
+        config.validate().map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(config)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PipelineConfig(prefilter_candidates={}, ann_candidates={}, mmr_lambda={}, lexical_weight={}, top_k={}, profiles={}, pinned_budget={})",
+            python_repr(self.prefilter_candidates),
+            self.ann_candidates,
+            python_repr(self.mmr_lambda),
+            self.lexical_weight,
+            self.top_k,
+            self.profiles.len(),
+            self.pinned_budget
+        )
+    }
+}
+
+impl PipelineConfig {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.mmr_lambda.is_some_and(|l| !(0.0..=1.0).contains(&l)) {
+            return Err("mmr_lambda must be between 0 and 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.lexical_weight) {
+            return Err("lexical_weight must be between 0 and 1".to_string());
+        }
+        for (name, profile) in &self.profiles {
+            profile.validate().map_err(|e| format!("Profile {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Weights of the signals blended into a hit's final score
+///
+/// Each signal lies in [0, 1]: cosine similarity, lexical overlap, recency
+/// (halving every `recency_half_life_days`), the document's importance, and
+/// graph proximity supplied per search. The score is the weighted mean, so
+/// only the weights' ratios matter. `trust` in [0, 1] then scales the mean
+/// toward the hit's trust score, so low-confidence and long-unverified
+/// documents sink; at 0 trust is ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[pyclass(get_all, set_all)]
+pub struct ScoringProfile {
+    pub vector: f32,
+    pub lexical: f32,
+    pub recency: f32,
+    pub importance: f32,
+    pub graph: f32,
+    pub recency_half_life_days: f64,
+    pub trust: f32,
+    pub verification_half_life_days: f64,
+}
+
+impl Default for ScoringProfile {
+    fn default() -> Self {
+        Self {
+            vector: 1.0,
+            lexical: 0.0,
+            recency: 0.0,
+            importance: 0.0,
+            graph: 0.0,
+            recency_half_life_days: 7.0,
+            trust: 0.0,
+            verification_half_life_days: 30.0,
+        }
+    }
+}
+
+#[pymethods]
+impl ScoringProfile {
+    #[new]
+    #[pyo3(signature = (vector=1.0, lexical=0.0, recency=0.0, importance=0.0, graph=0.0, recency_half_life_days=7.0, trust=0.0, verification_half_life_days=30.0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        vector: f32,
+        lexical: f32,
+        recency: f32,
+        importance: f32,
+        graph: f32,
+        recency_half_life_days: f64,
+        trust: f32,
+        verification_half_life_days: f64,
+    ) -> PyResult<Self> {
+        let profile = Self {
+            vector,
+            lexical,
+            recency,
+            importance,
+            graph,
+            recency_half_life_days,
+            trust,
+            verification_half_life_days,
+        };
+        profile.validate().map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(profile)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ScoringProfile(vector={}, lexical={}, recency={}, importance={}, graph={}, recency_half_life_days={}, trust={}, verification_half_life_days={})",
+            self.vector,
+            self.lexical,
+            self.recency,
+            self.importance,
+            self.graph,
+            self.recency_half_life_days,
+            self.trust,
+            self.verification_half_life_days
+        )
+    }
+}
+
+impl ScoringProfile {
+    fn validate(&self) -> Result<(), String> {
+        let weights = [self.vector, self.lexical, self.recency, self.importance, self.graph];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("weights must be non-negative".to_string());
+        }
+        if self.recency_half_life_days.is_nan() || self.recency_half_life_days <= 0.0 {
+            return Err("recency_half_life_days must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.trust) {
+            return Err("trust must be between 0 and 1".to_string());
+        }
+        if self.verification_half_life_days.is_nan() || self.verification_half_life_days <= 0.0 {
+            return Err("verification_half_life_days must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// The profile equivalent to a plain `lexical_weight` blend
+    fn blend(lexical_weight: f32) -> Self {
+        Self { vector: 1.0 - lexical_weight, lexical: lexical_weight, ..Self::default() }
+    }
+
+    /// Whether scores can order hits differently from vector similarity
+    fn reorders(&self) -> bool {
+        self.lexical > 0.0 || self.recency > 0.0 || self.importance > 0.0 || self.graph > 0.0 || self.trust > 0.0
+    }
+
+    fn score(&self, hit: &RetrievalHit) -> f32 {
+        let weighted = [
+            (self.vector, hit.vector_score),
+            (self.lexical, hit.lexical_score),
+            (self.recency, hit.recency_score),
+            (self.importance, hit.importance),
+            (self.graph, hit.graph_score),
+        ];
+        let total: f32 = weighted.iter().map(|(w, _)| w).sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+        let mean = weighted.iter().map(|(w, s)| w * s).sum::<f32>() / total;
+        mean * (1.0 - self.trust * (1.0 - hit.trust_score))
+    }
+}
+
+fn python_repr<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("None".to_string(), |v| v.to_string())
+}
+
+/// One result of a pipeline query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct RetrievalHit {
+    #[pyo3(get)]
+    pub id: String,
+    #[pyo3(get)]
+    pub score: f32, // Final score: `lexical_weight` blend, or the selected profile's weighted mean
+    #[pyo3(get)]
+    pub vector_score: f32, // Cosine similarity to the query
+    #[pyo3(get)]
+    pub lexical_score: f32, // Weighted share of the expanded query terms found in the document text
+    #[pyo3(get)]
+    pub recency_score: f32, // 1.0 when just stored, halving every profile half-life; 0.0 without a timestamp
+    #[pyo3(get)]
+    pub importance: f32, // As given when the document was added
+    #[pyo3(get)]
+    pub graph_score: f32, // Proximity passed to the search, 0.0 when absent
+    #[pyo3(get)]
+    pub pinned: bool,
+    #[pyo3(get)]
+    pub trust_score: f32, // Confidence times verification freshness, see `Signals::trust`
+    #[pyo3(get)]
+    pub source: Option<String>, // "file", "conversation", or "tool" when given
+    #[pyo3(get)]
+    pub resolved_from: Option<String>, // Superseded document this hit stands in for
+}
+
+#[pymethods]
+impl RetrievalHit {
+    fn __repr__(&self) -> String {
+        format!("RetrievalHit(id={}, score={:.4})", self.id, self.score)
+    }
+}
+
+/// Where a document's content came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provenance {
+    File,
+    Conversation,
+    Tool,
+}
+
+impl Provenance {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "file" => Some(Provenance::File),
+            "conversation" => Some(Provenance::Conversation),
+            "tool" => Some(Provenance::Tool),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provenance::File => "file",
+            Provenance::Conversation => "conversation",
+            Provenance::Tool => "tool",
+        }
+    }
+}
+
+/// Per-document inputs to scoring profiles and expiry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Signals {
+    /// Seconds since the Unix epoch
+    pub timestamp: Option<f64>,
+    /// Caller-assigned importance in [0, 1]; 0.5 when absent
+    pub importance: Option<f32>,
+    /// Seconds since the Unix epoch after which the document is dropped
+    pub expires_at: Option<f64>,
+    /// Pinned documents never expire, keep full recency, and may be forced
+    /// into results by `pinned_budget`
+    pub pinned: bool,
+    /// Belief in the content, in [0, 1]; 1.0 when absent
+    pub confidence: Option<f32>,
+    pub source: Option<Provenance>,
+    /// Seconds since the Unix epoch when a person last confirmed the content
+    pub verified_at: Option<f64>,
+}
+
+impl Signals {
+    fn expired(&self, now: f64) -> bool {
+        !self.pinned && self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    fn recency(&self, now: f64, half_life: f64) -> f32 {
+        if self.pinned {
+            return 1.0;
+        }
+        self.timestamp.map_or(0.0, |stored| {
+            let age = (now - stored).max(0.0);
+            0.5f64.powf(age / half_life) as f32
+        })
+    }
+
+    /// Confidence scaled by verification freshness: 1.0 just after
+    /// verification, falling toward half as the verification ages (halving
+    /// its excess every `half_life` seconds), and half when never verified
+    fn trust(&self, now: f64, half_life: f64) -> f32 {
+        let freshness = self.verified_at.map_or(0.0, |verified| {
+            let age = (now - verified).max(0.0);
+            0.5f64.powf(age / half_life) as f32
+        });
+        self.confidence.unwrap_or(1.0) * (0.5 + 0.5 * freshness)
+    }
+}
+
+/// Search inputs besides the query embedding
+#[derive(Debug, Clone, Copy)]
+pub struct QueryOptions<'a> {
+    pub text: Option<&'a str>,
+    pub filter: Option<&'a HashMap<String, String>>,
+    pub top_k: usize,
+    /// Name of a configured scoring profile
+    pub profile: Option<&'a str>,
+    /// Graph proximity of document ids to the query context, in [0, 1]
+    pub graph_scores: Option<&'a HashMap<String, f32>>,
+    /// Reference time for recency, seconds since the Unix epoch
+    pub now: f64,
+}
+
+impl QueryOptions<'_> {
+    pub fn top_k(top_k: usize) -> Self {
+        Self { text: None, filter: None, top_k, profile: None, graph_scores: None, now: unix_now() }
+    }
+}
+
+fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// `(id, embedding, text, metadata)` as passed to `add_batch`
+type DocumentTuple = (String, Vec<f32>, String, HashMap<String, String>);
+
+/// A stored document: unit-length embedding, its sign bits, and the fields
+/// used by the filter and rerank stages
+#[derive(Debug, Clone)]
+struct Document {
+    id: String,
+    embedding: Vec<f32>,
+    code: Vec<u64>,
+    terms: HashSet<String>,
+    vocabulary: Vec<String>,
+    claims: Claims,
+    metadata: HashMap<String, String>,
+    signals: Signals,
+}
+
+impl Document {
+    /// Whether the metadata has every key/value of `filter`
+    fn matches(&self, filter: Option<&HashMap<String, String>>) -> bool {
+        filter.is_none_or(|filter| filter.iter().all(|(key, value)| self.metadata.get(key) == Some(value)))
+    }
+
+    /// Approximate bytes the document holds in memory
+    fn footprint(&self) -> u64 {
+        let text: usize = self.terms.iter().chain(&self.vocabulary).map(String::len).sum();
+        let metadata: usize = self.metadata.iter().map(|(key, value)| key.len() + value.len()).sum();
+        (self.id.len() + self.embedding.len() * 4 + self.code.len() * 8 + text + metadata) as u64
+    }
+}
+
+/// Metadata key holding a document's comma-separated tags
+const TAGS_KEY: &str = "tags";
+
+/// Metadata changes applied together to every matching document
+#[derive(Debug, Clone, Default)]
+pub struct MetadataOps {
+    pub set: HashMap<String, String>,
+    pub unset: Vec<String>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+impl MetadataOps {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(key) = self.unset.iter().find(|key| self.set.contains_key(*key)) {
+            return Err(format!("Key {} is both set and unset", key));
+        }
+        let touches_tags = self.set.contains_key(TAGS_KEY) || self.unset.iter().any(|key| key == TAGS_KEY);
+        if touches_tags && !(self.add_tags.is_empty() && self.remove_tags.is_empty()) {
+            return Err(format!("Key {} cannot be set or unset alongside tag operations", TAGS_KEY));
+        }
+        let invalid = |tag: &&String| tag.trim().is_empty() || tag.contains(',');
+        if let Some(tag) = self.add_tags.iter().chain(&self.remove_tags).find(invalid) {
+            return Err(format!("Invalid tag: {:?}", tag));
+        }
+        Ok(())
+    }
+
+    /// Apply to one document's metadata; returns whether it changed
+    fn apply(&self, metadata: &mut HashMap<String, String>) -> bool {
+        let before = metadata.clone();
+        for (key, value) in &self.set {
+            metadata.insert(key.clone(), value.clone());
+        }
+        for key in &self.unset {
+            metadata.remove(key);
+        }
+        if !self.add_tags.is_empty() || !self.remove_tags.is_empty() {
+            let mut tags: Vec<String> = metadata
+                .get(TAGS_KEY)
+                .map(|tags| tags.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+                .unwrap_or_default();
+            for tag in &self.add_tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            tags.retain(|tag| !self.remove_tags.contains(tag));
+            if tags.is_empty() {
+                metadata.remove(TAGS_KEY);
+            } else {
+                metadata.insert(TAGS_KEY.to_string(), tags.join(","));
+            }
+        }
+        *metadata != before
+    }
+}
+
+/// Document counts of a `RetrievalPipeline`, overall and per metadata value
+#[derive(Debug, Clone, Default)]
+#[pyclass]
+pub struct PipelineStats {
+    #[pyo3(get)]
+    pub documents: usize,
+    #[pyo3(get)]
+    pub dimension: Option<usize>,
+    #[pyo3(get)]
+    pub pinned: usize,
+    #[pyo3(get)]
+    pub expired: usize, // Expired but not yet swept
+    #[pyo3(get)]
+    pub relations: usize,
+    #[pyo3(get)]
+    pub by_namespace: HashMap<String, usize>,
+    #[pyo3(get)]
+    pub by_tag: HashMap<String, usize>,
+    #[pyo3(get)]
+    pub by_language: HashMap<String, usize>,
+}
+
+#[pymethods]
+impl PipelineStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "PipelineStats(documents={}, pinned={}, expired={}, relations={}, namespaces={}, tags={}, languages={})",
+            self.documents,
+            self.pinned,
+            self.expired,
+            self.relations,
+            self.by_namespace.len(),
+            self.by_tag.len(),
+            self.by_language.len()
+        )
+    }
+}
+
+/// One bit per dimension, set for positive components
+fn sign_bits(embedding: &[f32]) -> Vec<u64> {
+    let mut code = vec![0u64; embedding.len().div_ceil(64)];
+    for (i, _) in embedding.iter().enumerate().filter(|(_, &x)| x > 0.0) {
+        code[i / 64] |= 1 << (i % 64);
+    }
+    code
+}
+
+fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Greedy maximal marginal relevance: repeatedly pick the candidate with the
+/// best `lambda * relevance - (1 - lambda) * max similarity to the picks`.
+/// `candidates` are `(index, relevance)` and `similarity` compares two
+/// indices; returns up to `k` candidates in pick order.
+pub fn mmr_select(
+    candidates: &[(usize, f32)],
+    similarity: impl Fn(usize, usize) -> f32,
+    lambda: f32,
+    k: usize,
+) -> Vec<(usize, f32)> {
+    let mut remaining: Vec<(usize, f32)> = candidates.to_vec();
+    let mut picked: Vec<(usize, f32)> = Vec::with_capacity(k.min(remaining.len()));
+    // Highest similarity of each remaining candidate to anything picked so far
+    let mut redundancy = vec![f32::NEG_INFINITY; remaining.len()];
+    while picked.len() < k && !remaining.is_empty() {
+        let marginal = |i: usize| {
+            let penalty = if picked.is_empty() { 0.0 } else { redundancy[i] };
+            lambda * remaining[i].1 - (1.0 - lambda) * penalty
+        };
+        let best = (0..remaining.len()).max_by(|&a, &b| marginal(a).total_cmp(&marginal(b)).then(b.cmp(&a))).unwrap();
+        let choice = remaining.swap_remove(best);
+        redundancy.swap_remove(best);
+        for (slot, &(index, _)) in redundancy.iter_mut().zip(&remaining) {
+            *slot = slot.max(similarity(choice.0, index));
+        }
+        picked.push(choice);
+    }
+    picked
+}
+
+/// Multi-stage vector retrieval executed natively, one call per query
+///
+/// Documents are an id, an embedding, optional text (for the lexical stage)
+/// and string metadata (for `filter`). Re-adding an id replaces it.
+/// Documents may be related by id; a superseded document is never returned
+/// itself, only through the newest live version of its chain.
+#[derive(Debug, Clone, Default)]
+#[pyclass]
+pub struct RetrievalPipeline {
+    config: PipelineConfig,
+    documents: Vec<Document>,
+    positions: HashMap<String, usize>,
+    /// Document frequency of each vocabulary word, for query spelling correction
+    corrector: SpellCorrector,
+    pub(crate) relations: Relations,
+}
+
+impl RetrievalPipeline {
+    pub(crate) fn dimension(&self) -> Option<usize> {
+        self.documents.first().map(|d| d.embedding.len())
+    }
+
+    pub fn insert(
+        &mut self,
+        id: String,
+        embedding: &[f32],
+        text: &str,
+        metadata: HashMap<String, String>,
+        signals: Signals,
+    ) -> Result<(), String> {
+        if let Some(dimension) = self.dimension().filter(|&d| d != embedding.len()) {
+            return Err(format!("Embedding has {} dimensions, pipeline holds {}", embedding.len(), dimension));
+        }
+        let document = Document {
+            id: id.clone(),
+            code: sign_bits(embedding),
+            embedding: normalized(embedding),
+            terms: document_terms(text),
+            vocabulary: vocabulary(text),
+            claims: Claims::of(text),
+            metadata,
+            signals,
+        };
+        for word in &document.vocabulary {
+            self.corrector.add(word);
+        }
+        match self.positions.get(&id) {
+            Some(&position) => {
+                let replaced = std::mem::replace(&mut self.documents[position], document);
+                for word in &replaced.vocabulary {
+                    self.corrector.remove(word);
+                }
+            }
+            None => {
+                self.positions.insert(id, self.documents.len());
+                self.documents.push(document);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.positions.contains_key(id)
+    }
+
+    pub fn is_pinned(&self, id: &str) -> bool {
+        self.positions.get(id).is_some_and(|&p| self.documents[p].signals.pinned)
+    }
+
+    pub fn delete(&mut self, id: &str) -> bool {
+        let Some(position) = self.positions.remove(id) else { return false };
+        let removed = self.documents.swap_remove(position);
+        for word in &removed.vocabulary {
+            self.corrector.remove(word);
+        }
+        self.relations.detach(id);
+        if let Some(moved) = self.documents.get(position) {
+            self.positions.insert(moved.id.clone(), position);
+        }
+        true
+    }
+
+    /// Apply `ops` to every document whose metadata matches `filter`. All
+    /// operations are checked before any document changes, so an invalid
+    /// request leaves the store untouched. Returns how many documents changed.
+    pub fn update_where(
+        &mut self,
+        filter: Option<&HashMap<String, String>>,
+        ops: &MetadataOps,
+    ) -> Result<usize, String> {
+        ops.validate()?;
+        let mut changed = 0;
+        for document in self.documents.iter_mut().filter(|d| d.matches(filter)) {
+            if ops.apply(&mut document.metadata) {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Counts of documents, grouped by the values of `namespace_key` and
+    /// `language_key` and by tag; documents without a key are not grouped
+    pub fn stats(&self, namespace_key: &str, language_key: &str, now: f64) -> PipelineStats {
+        let mut stats = PipelineStats {
+            documents: self.documents.len(),
+            dimension: self.dimension(),
+            relations: self.relations.len(),
+            ..PipelineStats::default()
+        };
+        for document in &self.documents {
+            stats.pinned += usize::from(document.signals.pinned);
+            stats.expired += usize::from(document.signals.expired(now));
+            if let Some(namespace) = document.metadata.get(namespace_key) {
+                *stats.by_namespace.entry(namespace.clone()).or_default() += 1;
+            }
+            if let Some(language) = document.metadata.get(language_key) {
+                *stats.by_language.entry(language.clone()).or_default() += 1;
+            }
+            let tags = document.metadata.get(TAGS_KEY).into_iter().flat_map(|tags| tags.split(','));
+            for tag in tags.map(str::trim).filter(|t| !t.is_empty()).collect::<HashSet<_>>() {
+                *stats.by_tag.entry(tag.to_string()).or_default() += 1;
+            }
+        }
+        stats
+    }
+
+    /// The newest live document superseding the one at `position`: the
+    /// furthest along its chain, then the latest timestamp, then the lowest
+    /// id. The document itself when no live document supersedes it.
+    fn newest(&self, position: usize, now: f64) -> usize {
+        let key = |depth: usize, position: usize| {
+            let document = &self.documents[position];
+            (depth, document.signals.timestamp.unwrap_or(f64::NEG_INFINITY), &document.id)
+        };
+        let mut best = key(0, position);
+        let mut newest = position;
+        let mut frontier = vec![position];
+        let mut seen = HashSet::from([position]);
+        let mut depth = 0;
+        while !frontier.is_empty() {
+            depth += 1;
+            let mut next = Vec::new();
+            for &current in &frontier {
+                for id in self.relations.sources(&self.documents[current].id, RelationKind::Supersedes) {
+                    if let Some(&position) = self.positions.get(id).filter(|&&p| seen.insert(p)) {
+                        next.push(position);
+                    }
+                }
+            }
+            for &position in next.iter().filter(|&&p| !self.documents[p].signals.expired(now)) {
+                let candidate = key(depth, position);
+                let newer = candidate.0.cmp(&best.0).then(candidate.1.total_cmp(&best.1)).then(best.2.cmp(candidate.2));
+                if newer.is_gt() {
+                    best = candidate;
+                    newest = position;
+                }
+            }
+            frontier = next;
+        }
+        newest
+    }
+
+    /// What deleting `ids` would remove; ids not stored are left out
+    pub fn plan_delete(&self, operation: &str, ids: &[String]) -> ChangePlan {
+        let stored: Vec<&Document> =
+            ids.iter().filter_map(|id| self.positions.get(id)).map(|&p| &self.documents[p]).collect();
+        let bytes = stored.iter().map(|document| document.footprint()).sum();
+        let ids: Vec<String> = stored.iter().map(|document| document.id.clone()).collect();
+        ChangePlan::new(operation, ids, stored.len(), bytes)
+    }
+
+    fn expired_ids(&self, now: f64) -> Vec<String> {
+        self.documents.iter().filter(|d| d.signals.expired(now)).map(|d| d.id.clone()).collect()
+    }
+
+    /// Remove every document expired at `now` from the pipeline and, in the
+    /// same transaction, from `store` and `keywords`; returns their ids. The
+    /// ANN index can't fail, so `ann` is tombstoned once the rest commits.
+    pub fn sweep(
+        &mut self,
+        now: f64,
+        store: Option<&mut VectorStore>,
+        keywords: Option<&mut KeywordIndex>,
+        ann: Option<&mut VectorIndex>,
+    ) -> Result<Vec<String>, Failure> {
+        let expired = self.expired_ids(now);
+        let ops: Vec<Op> = expired.iter().cloned().map(Op::Delete).collect();
+        transaction::apply(store, keywords, Some(self), &ops)?;
+        if let Some(ann) = ann {
+            for id in &expired {
+                ann.delete(id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Live documents with cosine similarity to `embedding` inside the
+    /// inclusive `band` whose text differs lexically from `claims`, most
+    /// similar first
+    pub fn contradiction_candidates(
+        &self,
+        embedding: &[f32],
+        claims: &Claims,
+        band: (f32, f32),
+        limit: usize,
+    ) -> Result<Vec<ContradictionCandidate>, String> {
+        if let Some(dimension) = self.dimension().filter(|&d| d != embedding.len()) {
+            return Err(format!("Embedding has {} dimensions, pipeline holds {}", embedding.len(), dimension));
+        }
+        let query = normalized(embedding);
+        let now = unix_now();
+        let mut candidates: Vec<ContradictionCandidate> = self
+            .documents
+            .par_iter()
+            .filter(|d| !d.signals.expired(now))
+            .filter_map(|d| {
+                let similarity = dot(&query, &d.embedding);
+                if !(band.0..=band.1).contains(&similarity) {
+                    return None;
+                }
+                let reasons = claims.differences(&d.claims);
+                (!reasons.is_empty()).then(|| ContradictionCandidate { id: d.id.clone(), similarity, reasons })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then(a.id.cmp(&b.id)));
+        candidates.truncate(limit);
+        Ok(candidates)
+    }
+
+    /// Run every stage for one query
+    pub fn query(&self, embedding: &[f32], options: &QueryOptions) -> Result<Vec<RetrievalHit>, String> {
+        self.query_traced(embedding, options, None)
+    }
+
+    /// `query`, recording each stage's candidates and timing in `trace`
+    pub fn query_traced(
+        &self,
+        embedding: &[f32],
+        options: &QueryOptions,
+        mut trace: Option<&mut QueryTrace>,
+    ) -> Result<Vec<RetrievalHit>, String> {
+        if let Some(dimension) = self.dimension().filter(|&d| d != embedding.len()) {
+            return Err(format!("Query has {} dimensions, pipeline holds {}", embedding.len(), dimension));
+        }
+        let config = &self.config;
+        let mut profile = match options.profile {
+            Some(name) => config
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Unknown scoring profile: {}", name))?,
+            None => ScoringProfile::blend(config.lexical_weight),
+        };
+        let top_k = options.top_k;
+        let query = normalized(embedding);
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.profile = options.profile.unwrap_or("blend").to_string();
+        }
+        let with_ids = |scored: &[(usize, f32)]| -> Vec<(String, f32)> {
+            scored.iter().map(|&(i, score)| (self.documents[i].id.clone(), score)).collect()
+        };
+
+        // Binary prefilter: nearest sign codes by Hamming distance. Expired
+        // documents not yet swept are never candidates.
+        let mut candidates: Vec<usize> =
+            (0..self.documents.len()).filter(|&i| !self.documents[i].signals.expired(options.now)).collect();
+        if let Some(limit) = config.prefilter_candidates.filter(|&n| n > 0 && n < candidates.len()) {
+            let code = sign_bits(embedding);
+            candidates.select_nth_unstable_by_key(limit, |&i| (hamming(&code, &self.documents[i].code), i));
+            candidates.truncate(limit);
+        }
+        query_trace::record(&mut trace, "prefilter", || {
+            let code = sign_bits(embedding);
+            let distance = |i: usize| hamming(&code, &self.documents[i].code) as f32;
+            candidates.iter().map(|&i| (self.documents[i].id.clone(), distance(i))).collect()
+        });
+
+        // ANN scoring over the survivors
+        let mut scored: Vec<(usize, f32)> = candidates
+            .par_iter()
+            .map(|&i| (i, dot(&query, &self.documents[i].embedding)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        if config.ann_candidates > 0 {
+            scored.truncate(config.ann_candidates);
+        }
+        query_trace::record(&mut trace, "ann", || with_ids(&scored));
+
+        // Supersedes chains: a stale document gives way to its newest live
+        // version, which takes its rank with its own similarity
+        let mut resolved_from: HashMap<usize, usize> = HashMap::new();
+        if !self.relations.is_empty() {
+            let mut seen: HashSet<usize> = HashSet::new();
+            scored = scored
+                .into_iter()
+                .filter_map(|(i, score)| {
+                    let newest = self.newest(i, options.now);
+                    if !seen.insert(newest) {
+                        return None;
+                    }
+                    if newest == i {
+                        return Some((i, score));
+                    }
+                    resolved_from.insert(newest, i);
+                    Some((newest, dot(&query, &self.documents[newest].embedding)))
+                })
+                .collect();
+            query_trace::record(&mut trace, "supersedes", || with_ids(&scored));
+        }
+
+        // Metadata filter: every key must match exactly
+        scored.retain(|&(i, _)| self.documents[i].matches(options.filter));
+        query_trace::record(&mut trace, "filter", || with_ids(&scored));
+
+        // MMR diversification, otherwise plain truncation
+        let selected = match config.mmr_lambda {
+            Some(lambda) => {
+                let similarity = |a: usize, b: usize| dot(&self.documents[a].embedding, &self.documents[b].embedding);
+                mmr_select(&scored, similarity, lambda, top_k)
+            }
+            None => {
+                scored.truncate(top_k);
+                scored
+            }
+        };
+        let stage = if config.mmr_lambda.is_some() { "mmr" } else { "top_k" };
+        query_trace::record(&mut trace, stage, || with_ids(&selected));
+
+        // Lexical and profile rerank
+        let mut expanded = options.text.map(expand).unwrap_or_default();
+        expanded.correct(&self.corrector);
+        if expanded.terms.is_empty() {
+            profile.lexical = 0.0;
+        }
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.terms = expanded.terms.clone();
+        }
+        let scorer = Scorer { profile: &profile, query: &expanded, options };
+        let mut hits: Vec<RetrievalHit> = selected
+            .into_iter()
+            .map(|(i, vector_score)| {
+                let mut hit = scorer.hit(&self.documents[i], vector_score);
+                hit.resolved_from = resolved_from.get(&i).map(|&stale| self.documents[stale].id.clone());
+                hit
+            })
+            .collect();
+
+        // Pinned documents the ranking left out displace the lowest unpinned hits
+        let missing = config.pinned_budget.saturating_sub(hits.iter().filter(|h| h.pinned).count());
+        let mut forced: Vec<RetrievalHit> = Vec::new();
+        if missing > 0 {
+            let included: HashSet<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+            forced = self
+                .documents
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| d.signals.pinned && !included.contains(d.id.as_str()) && d.matches(options.filter))
+                .filter(|&(i, _)| self.newest(i, options.now) == i)
+                .map(|(_, d)| scorer.hit(d, dot(&query, &d.embedding)))
+                .collect();
+            forced.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+            forced.truncate(missing);
+        }
+        let reorder = profile.reorders() || !forced.is_empty();
+        if !forced.is_empty() {
+            let keep = top_k.saturating_sub(forced.len());
+            let mut unpinned_kept = 0;
+            let pinned_in_hits = hits.iter().filter(|h| h.pinned).count();
+            hits.retain(|h| {
+                if h.pinned {
+                    return true;
+                }
+                unpinned_kept += 1;
+                unpinned_kept + pinned_in_hits <= keep
+            });
+            hits.extend(forced);
+        }
+        if reorder {
+            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        query_trace::record(&mut trace, "rerank", || hits.iter().map(|h| (h.id.clone(), h.score)).collect());
+        Ok(hits)
+    }
+}
+
+/// Builds scored hits for one query
+struct Scorer<'a> {
+    profile: &'a ScoringProfile,
+    query: &'a ExpandedQuery,
+    options: &'a QueryOptions<'a>,
+}
+
+impl Scorer<'_> {
+    fn hit(&self, document: &Document, vector_score: f32) -> RetrievalHit {
+        let half_life = self.profile.recency_half_life_days * 86400.0;
+        let verification_half_life = self.profile.verification_half_life_days * 86400.0;
+        let mut hit = RetrievalHit {
+            id: document.id.clone(),
+            score: 0.0,
+            vector_score,
+            lexical_score: self.query.overlap(&document.terms),
+            recency_score: document.signals.recency(self.options.now, half_life),
+            importance: document.signals.importance.unwrap_or(0.5),
+            graph_score: self.options.graph_scores.and_then(|g| g.get(&document.id)).copied().unwrap_or(0.0),
+            pinned: document.signals.pinned,
+            trust_score: document.signals.trust(self.options.now, verification_half_life),
+            source: document.signals.source.map(|s| s.name().to_string()),
+            resolved_from: None,
+        };
+        hit.score = self.profile.score(&hit);
+        hit
+    }
+}
+
+#[pymethods]
+impl RetrievalPipeline {
+    /// Empty pipeline; without `config`, the search defaults of the
+    /// CoreConfig in force
+    #[new]
+    #[pyo3(signature = (config=None))]
+    fn new(config: Option<PipelineConfig>) -> Self {
+        let config = config.unwrap_or_else(|| core_config::current().search.clone());
+        Self { config, ..Self::default() }
+    }
+
+    #[getter]
+    fn config(&self) -> PipelineConfig {
+        self.config.clone()
+    }
+
+    #[setter]
+    fn set_config(&mut self, config: PipelineConfig) -> PyResult<()> {
+        config.validate().map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Add or replace a document. `timestamp` (seconds since the epoch) and
+    /// `importance` (0 to 1) feed scoring profiles. With `ttl` (seconds), the
+    /// document expires that long after `timestamp` (default now): searches
+    /// skip it and `sweep_expired` removes it. `pinned` documents never
+    /// expire or lose recency, and fill `pinned_budget`. `confidence` (0 to
+    /// 1), `source` ("file", "conversation" or "tool") and `verified_at`
+    /// (seconds since the epoch) feed the profiles' trust. Raises ValueError
+    /// if the embedding's dimension differs from the stored ones, or for an
+    /// out-of-range confidence or unknown source.
+    #[pyo3(signature = (id, embedding, text="", metadata=None, timestamp=None, importance=None, ttl=None, pinned=false, confidence=None, source=None, verified_at=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn add(
+        &mut self,
+        id: String,
+        embedding: Vec<f32>,
+        text: &str,
+        metadata: Option<HashMap<String, String>>,
+        timestamp: Option<f64>,
+        importance: Option<f32>,
+        ttl: Option<f64>,
+        pinned: bool,
+        confidence: Option<f32>,
+        source: Option<&str>,
+        verified_at: Option<f64>,
+    ) -> PyResult<()> {
+        let confidence = confidence.map(validate_confidence).transpose()?;
+        let source = source
+            .map(|name| {
+                Provenance::from_name(name)
+                    .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown source: {}", name)))
+            })
+            .transpose()?;
+        let expires_at = ttl.map(|ttl| timestamp.unwrap_or_else(unix_now) + ttl);
+        let signals = Signals { timestamp, importance, expires_at, pinned, confidence, source, verified_at };
+        self.insert(id, &embedding, text, metadata.unwrap_or_default(), signals)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Add or replace documents given as `(id, embedding, text, metadata)`
+    fn add_batch(&mut self, documents: Vec<DocumentTuple>) -> PyResult<()> {
+        for (id, embedding, text, metadata) in documents {
+            self.insert(id, &embedding, &text, metadata, Signals::default())
+                .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        }
+        Ok(())
+    }
+
+    /// Remove a document; returns whether it was present. With `dry_run`,
+    /// nothing is removed and a ChangePlan of what would be is returned.
+    #[pyo3(signature = (id, dry_run=false))]
+    fn remove(&mut self, id: &str, dry_run: bool) -> Outcome<bool> {
+        if dry_run {
+            return Outcome::Planned(self.plan_delete("remove", &[id.to_string()]));
+        }
+        Outcome::Applied(self.delete(id))
+    }
+
+    /// Set a document's time to live from `now` (default the current time),
+    /// or clear it with None. Raises KeyError for an unknown id.
+    #[pyo3(signature = (id, ttl, now=None))]
+    fn set_ttl(&mut self, id: &str, ttl: Option<f64>, now: Option<f64>) -> PyResult<()> {
+        let position = *self
+            .positions
+            .get(id)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(id.to_string()))?;
+        let now = now.unwrap_or_else(unix_now);
+        self.documents[position].signals.expires_at = ttl.map(|ttl| now + ttl);
+        Ok(())
+    }
+
+    /// Pin or unpin a document. Raises KeyError for an unknown id.
+    ///
+    /// Pins live in the pipeline: a transaction or sweep that includes it
+    /// refuses to delete a pinned id, but a VectorStore or KeywordIndex
+    /// used on its own doesn't know about them.
+    fn set_pinned(&mut self, id: &str, pinned: bool) -> PyResult<()> {
+        let position = *self
+            .positions
+            .get(id)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(id.to_string()))?;
+        self.documents[position].signals.pinned = pinned;
+        Ok(())
+    }
+
+    /// Record that a person confirmed a document at `now` (default the
+    /// current time), optionally updating its confidence. Raises KeyError
+    /// for an unknown id and ValueError for an out-of-range confidence.
+    #[pyo3(signature = (id, confidence=None, now=None))]
+    fn mark_verified(&mut self, id: &str, confidence: Option<f32>, now: Option<f64>) -> PyResult<()> {
+        let position = *self
+            .positions
+            .get(id)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(id.to_string()))?;
+        let signals = &mut self.documents[position].signals;
+        if let Some(confidence) = confidence {
+            signals.confidence = Some(validate_confidence(confidence)?);
+        }
+        signals.verified_at = Some(now.unwrap_or_else(unix_now));
+        Ok(())
+    }
+
+    /// Update metadata in bulk on every document matching `filter` (all
+    /// documents when None). `set_ops` may hold "set" (dict of fields to
+    /// assign), "unset" (fields to drop), and "add_tags" / "remove_tags"
+    /// (tags kept comma-separated under the "tags" field). Nothing changes
+    /// unless every operation is valid. Returns the number of documents
+    /// changed. Raises ValueError for an unknown or conflicting operation.
+    #[pyo3(signature = (filter, set_ops))]
+    fn update_metadata_where(
+        &mut self,
+        filter: Option<HashMap<String, String>>,
+        set_ops: HashMap<String, Bound<'_, PyAny>>,
+    ) -> PyResult<usize> {
+        let mut ops = MetadataOps::default();
+        for (name, value) in set_ops {
+            match name.as_str() {
+                "set" => ops.set = value.extract()?,
+                "unset" => ops.unset = value.extract()?,
+                "add_tags" => ops.add_tags = value.extract()?,
+                "remove_tags" => ops.remove_tags = value.extract()?,
+                _ => {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!("Unknown metadata operation: {}", name)))
+                }
+            }
+        }
+        self.update_where(filter.as_ref(), &ops)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Document counts per namespace, tag and language, plus pinned, expired
+    /// (not yet swept) and related documents at `now` (default the current
+    /// time), as reported by the `memory_status` tool. `namespace_key` and
+    /// `language_key` name the metadata fields to group by; tags come from
+    /// the comma-separated "tags" field.
+    #[pyo3(signature = (namespace_key="project_name", language_key="language", now=None))]
+    fn store_stats(&self, namespace_key: &str, language_key: &str, now: Option<f64>) -> PipelineStats {
+        self.stats(namespace_key, language_key, now.unwrap_or_else(unix_now))
+    }
+
+    /// Ids of pinned documents, sorted
+    fn pinned_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.documents.iter().filter(|d| d.signals.pinned).map(|d| d.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    /// Record that `source` supersedes, contradicts, derives from or
+    /// references `target` (`kind` is "supersedes", "contradicts",
+    /// "derives_from" or "references"); returns
+    /// whether the relation is new. Removing a document links whatever
+    /// superseded it to whatever it superseded. Raises KeyError for an
+    /// unknown id and ValueError for an unknown kind, a self relation, or a
+    /// supersedes cycle.
+    fn relate(&mut self, source: &str, kind: &str, target: &str) -> PyResult<bool> {
+        let kind = relation_kind(kind)?;
+        for id in [source, target] {
+            if !self.positions.contains_key(id) {
+                return Err(pyo3::exceptions::PyKeyError::new_err(id.to_string()));
+            }
+        }
+        self.relations
+            .add(source, kind, target)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Remove a relation; returns whether it existed
+    fn unrelate(&mut self, source: &str, kind: &str, target: &str) -> PyResult<bool> {
+        Ok(self.relations.remove(source, relation_kind(kind)?, target))
+    }
+
+    /// Relations with `id` as source or target
+    fn relations(&self, id: &str) -> Vec<MemoryRelation> {
+        self.relations.of(id)
+    }
+
+    /// Id of the newest live document superseding `id` at `now` (default the
+    /// current time), or `id` itself. Raises KeyError for an unknown id.
+    #[pyo3(signature = (id, now=None))]
+    fn newest_version(&self, id: &str, now: Option<f64>) -> PyResult<String> {
+        let position = *self
+            .positions
+            .get(id)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(id.to_string()))?;
+        Ok(self.documents[self.newest(position, now.unwrap_or_else(unix_now))].id.clone())
+    }
+
+    /// Remove all documents expired at `now` (default the current time) and
+    /// return their ids. Call periodically to reclaim memory; searches
+    /// already ignore expired documents. Pass the `store`, `keywords` and
+    /// `ann` indexes holding the same ids so they are removed there too,
+    /// all or nothing, and don't come back after a restart or rebuild. With
+    /// `dry_run`, nothing is removed and a ChangePlan of what would be is
+    /// returned. Raises IOError if the vector store can't be written, in
+    /// which case nothing is removed.
+    #[pyo3(signature = (now=None, dry_run=false, store=None, keywords=None, ann=None))]
+    fn sweep_expired(
+        &mut self,
+        now: Option<f64>,
+        dry_run: bool,
+        mut store: Option<PyRefMut<'_, VectorStore>>,
+        mut keywords: Option<PyRefMut<'_, KeywordIndex>>,
+        mut ann: Option<PyRefMut<'_, VectorIndex>>,
+    ) -> PyResult<Outcome<Vec<String>>> {
+        let now = now.unwrap_or_else(unix_now);
+        if dry_run {
+            return Ok(Outcome::Planned(self.plan_delete("sweep_expired", &self.expired_ids(now))));
+        }
+        let swept = self.sweep(now, store.as_deref_mut(), keywords.as_deref_mut(), ann.as_deref_mut())?;
+        Ok(Outcome::Applied(swept))
+    }
+
+    /// Run the pipeline for one query
+    ///
+    /// `query_text` feeds the lexical rerank (expanded as by `expand_query`),
+    /// `filter` keeps only documents whose metadata has every given
+    /// key/value, and `top_k` overrides the configured result count.
+    /// `profile` names a configured scoring profile; `graph_scores` gives
+    /// its graph proximity per document id and `now` (seconds since the
+    /// epoch, default the current time) anchors recency. Hits are ordered
+    /// best first. Raises ValueError for an unknown profile.
+    ///
+    /// With `trace`, returns `(hits, QueryTrace)`: every stage's surviving
+    /// candidates with their scores, what it dropped and how long it took,
+    /// so `trace.explain(id)` tells where a missing document fell out.
+    #[pyo3(signature = (query_embedding, query_text=None, filter=None, top_k=None, profile=None, graph_scores=None, now=None, trace=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        &self,
+        py: Python<'_>,
+        query_embedding: Vec<f32>,
+        query_text: Option<&str>,
+        filter: Option<HashMap<String, String>>,
+        top_k: Option<usize>,
+        profile: Option<&str>,
+        graph_scores: Option<HashMap<String, f32>>,
+        now: Option<f64>,
+        trace: bool,
+    ) -> PyResult<SearchOutput> {
+        let options = QueryOptions {
+            text: query_text,
+            filter: filter.as_ref(),
+            top_k: top_k.unwrap_or(self.config.top_k),
+            profile,
+            graph_scores: graph_scores.as_ref(),
+            now: now.unwrap_or_else(unix_now),
+        };
+        let output = if trace {
+            py.detach(|| {
+                let mut trace = QueryTrace::new(self.documents.iter().map(|d| d.id.clone()).collect());
+                let hits = self.query_traced(&query_embedding, &options, Some(&mut trace));
+                hits.map(|hits| SearchOutput::Traced(hits, trace))
+            })
+        } else {
+            py.detach(|| self.query(&query_embedding, &options).map(SearchOutput::Hits))
+        };
+        output.map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Add or replace a named scoring profile
+    fn set_profile(&mut self, name: String, profile: ScoringProfile) -> PyResult<()> {
+        profile.validate().map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.config.profiles.insert(name, profile);
+        Ok(())
+    }
+
+    /// Remove a scoring profile; returns whether it existed
+    fn remove_profile(&mut self, name: &str) -> bool {
+        self.config.profiles.remove(name).is_some()
+    }
+
+    /// Words from the stored documents' text close to `term`, closest and
+    /// most common first. Query text is corrected the same way before the
+    /// lexical rerank, so misspelled symbol names still match.
+    #[pyo3(signature = (term, max_edit_distance=2))]
+    fn suggest_corrections(&self, term: &str, max_edit_distance: usize) -> PyResult<Vec<Suggestion>> {
+        self.corrector
+            .suggest(term, max_edit_distance)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.documents.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("RetrievalPipeline(documents={}, dimension={})", self.documents.len(), python_repr(self.dimension()))
+    }
+}
+
+fn validate_confidence(confidence: f32) -> PyResult<f32> {
+    if (0.0..=1.0).contains(&confidence) {
+        Ok(confidence)
+    } else {
+        Err(pyo3::exceptions::PyValueError::new_err("confidence must be between 0 and 1"))
+    }
+}
+
+pub(crate) fn relation_kind(name: &str) -> PyResult<RelationKind> {
+    RelationKind::from_name(name)
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown relation kind: {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(config: PipelineConfig) -> RetrievalPipeline {
+        let mut pipeline = RetrievalPipeline { config, ..Default::default() };
+        let docs = [
+            ("parser", [1.0, 0.1, 0.0], "parse python source files", "code"),
+            ("parser_copy", [1.0, 0.12, 0.0], "parse python source files", "code"),
+            ("lexer", [0.8, 0.6, 0.0], "tokenize source text", "code"),
+            ("meeting", [0.0, 0.2, 1.0], "weekly meeting notes", "notes"),
+        ];
+        for (id, embedding, text, kind) in docs {
+            let metadata = HashMap::from([("kind".to_string(), kind.to_string())]);
+            pipeline.insert(id.to_string(), &embedding, text, metadata, Signals::default()).unwrap();
+        }
+        pipeline
+    }
+
+    fn ids(hits: &[RetrievalHit]) -> Vec<&str> {
+        hits.iter().map(|h| h.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_vector_stage_orders_by_similarity() {
+        let pipeline = pipeline(PipelineConfig::default());
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions::top_k(3)).unwrap();
+        assert_eq!(ids(&hits), vec!["parser", "parser_copy", "lexer"]);
+        assert!((hits[0].vector_score - hits[0].score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_metadata_filter() {
+        let pipeline = pipeline(PipelineConfig::default());
+        let filter = HashMap::from([("kind".to_string(), "notes".to_string())]);
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { filter: Some(&filter), ..QueryOptions::top_k(3) }).unwrap();
+        assert_eq!(ids(&hits), vec!["meeting"]);
+    }
+
+    #[test]
+    fn test_mmr_skips_near_duplicates() {
+        let config = PipelineConfig { mmr_lambda: Some(0.3), ..Default::default() };
+        let filter = HashMap::from([("kind".to_string(), "code".to_string())]);
+        let hits = pipeline(config).query(&[1.0, 0.0, 0.0], &QueryOptions { filter: Some(&filter), ..QueryOptions::top_k(2) }).unwrap();
+        assert_eq!(ids(&hits), vec!["parser", "lexer"]);
+    }
+
+    #[test]
+    fn test_lexical_rerank() {
+        let config = PipelineConfig { lexical_weight: 0.5, ..Default::default() };
+        let hits = pipeline(config).query(&[1.0, 0.0, 0.0], &QueryOptions { text: Some("tokenize text"), ..QueryOptions::top_k(3) }).unwrap();
+        assert_eq!(hits[0].id, "lexer");
+        assert_eq!(hits[0].lexical_score, 1.0);
+    }
+
+    #[test]
+    fn test_lexical_rerank_corrects_typos() {
+        let config = PipelineConfig { lexical_weight: 0.5, ..Default::default() };
+        let pipeline = pipeline(config);
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { text: Some("tokenzie txet"), ..QueryOptions::top_k(3) }).unwrap();
+        assert_eq!(hits[0].id, "lexer");
+        assert_eq!(pipeline.corrector.suggest("tokenzie", 2).unwrap()[0].term, "tokenize");
+    }
+
+    #[test]
+    fn test_traced_query_records_each_stage() {
+        let pipeline = pipeline(PipelineConfig::default());
+        let filter = HashMap::from([("kind".to_string(), "code".to_string())]);
+        let options = QueryOptions { filter: Some(&filter), text: Some("tokenize"), ..QueryOptions::top_k(2) };
+        let mut trace = QueryTrace::new(pipeline.documents.iter().map(|d| d.id.clone()).collect());
+        let hits = pipeline.query_traced(&[1.0, 0.0, 0.0], &options, Some(&mut trace)).unwrap();
+        assert_eq!(ids(&hits), ids(&pipeline.query(&[1.0, 0.0, 0.0], &options).unwrap()));
+
+        let stages: Vec<&str> = trace.stages.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(stages, vec!["prefilter", "ann", "filter", "top_k", "rerank"]);
+        assert_eq!(trace.stages[2].dropped, vec!["meeting"]);
+        assert_eq!(trace.explain("lexer"), "dropped by top_k");
+        assert_eq!(trace.explain(&hits[0].id), "returned at rank 1");
+        assert_eq!((trace.profile.as_str(), trace.terms[0].0.as_str()), ("blend", "tokenize"));
+    }
+
+    #[test]
+    fn test_binary_prefilter_limits_candidates() {
+        let config = PipelineConfig { prefilter_candidates: Some(1), ..Default::default() };
+        let hits = pipeline(config).query(&[0.0, 0.1, 1.0], &QueryOptions::top_k(3)).unwrap();
+        assert_eq!(ids(&hits), vec!["meeting"]);
+    }
+
+    #[test]
+    fn test_replace_remove_and_dimension_checks() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        pipeline.insert("lexer".to_string(), &[0.0, 0.0, 1.0], "", HashMap::new(), Signals::default()).unwrap();
+        assert_eq!(pipeline.documents.len(), 4);
+        assert!(pipeline.delete("parser"));
+        assert!(!pipeline.delete("parser"));
+        assert!(!pipeline.corrector.contains("tokenize"));
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions::top_k(10)).unwrap();
+        assert_eq!(ids(&hits)[0], "parser_copy");
+        assert!(pipeline.insert("x".to_string(), &[1.0], "", HashMap::new(), Signals::default()).is_err());
+        assert!(pipeline.query(&[1.0], &QueryOptions::top_k(1)).is_err());
+    }
+
+    #[test]
+    fn test_scoring_profiles() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        let now = 100.0 * 86400.0;
+        let recent = Signals { timestamp: Some(now), importance: Some(1.0), ..Default::default() };
+        pipeline.insert("recent".to_string(), &[0.5, 0.5, 0.0], "", HashMap::new(), recent).unwrap();
+        pipeline.config.profiles.insert(
+            "debug".to_string(),
+            ScoringProfile { vector: 1.0, recency: 1.0, ..Default::default() },
+        );
+        pipeline.config.profiles.insert(
+            "architecture".to_string(),
+            ScoringProfile { vector: 0.0, graph: 1.0, ..Default::default() },
+        );
+
+        let plain = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions::top_k(5)).unwrap();
+        assert_eq!(plain[0].id, "parser");
+
+        let debug = QueryOptions { profile: Some("debug"), now, ..QueryOptions::top_k(5) };
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &debug).unwrap();
+        assert_eq!(hits[0].id, "recent");
+        assert_eq!(hits[0].recency_score, 1.0);
+        assert_eq!(hits[1].recency_score, 0.0);
+
+        let graph = HashMap::from([("meeting".to_string(), 0.9)]);
+        let architecture = QueryOptions { profile: Some("architecture"), graph_scores: Some(&graph), ..QueryOptions::top_k(5) };
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &architecture).unwrap();
+        assert_eq!(hits[0].id, "meeting");
+        assert!((hits[0].score - 0.9).abs() < 1e-6);
+
+        let unknown = QueryOptions { profile: Some("missing"), ..QueryOptions::top_k(5) };
+        assert!(pipeline.query(&[1.0, 0.0, 0.0], &unknown).is_err());
+    }
+
+    #[test]
+    fn test_profile_validation() {
+        assert!(ScoringProfile { graph: -1.0, ..Default::default() }.validate().is_err());
+        assert!(ScoringProfile { recency_half_life_days: 0.0, ..Default::default() }.validate().is_err());
+        let profile = ScoringProfile { vector: 1.0, importance: 1.0, ..Default::default() };
+        let hit = RetrievalHit {
+            id: "x".to_string(),
+            score: 0.0,
+            vector_score: 0.5,
+            lexical_score: 0.0,
+            recency_score: 0.0,
+            importance: 1.0,
+            graph_score: 0.0,
+            pinned: false,
+            trust_score: 1.0,
+            source: None,
+            resolved_from: None,
+        };
+        assert!((profile.score(&hit) - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_expired_documents_are_skipped_and_swept() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        let temporary = Signals { expires_at: Some(50.0), ..Default::default() };
+        pipeline.insert("weekly".to_string(), &[1.0, 0.0, 0.0], "sprint goals", HashMap::new(), temporary).unwrap();
+
+        let before = QueryOptions { now: 10.0, ..QueryOptions::top_k(1) };
+        assert_eq!(pipeline.query(&[1.0, 0.0, 0.0], &before).unwrap()[0].id, "weekly");
+        let after = QueryOptions { now: 50.0, ..QueryOptions::top_k(1) };
+        assert_eq!(pipeline.query(&[1.0, 0.0, 0.0], &after).unwrap()[0].id, "parser");
+
+        assert!(pipeline.sweep(10.0, None, None, None).unwrap().is_empty());
+        let plan = pipeline.plan_delete("sweep_expired", &pipeline.expired_ids(60.0));
+        assert_eq!((plan.ids.clone(), plan.count), (vec!["weekly".to_string()], 1));
+        assert!(plan.bytes > 0);
+        assert_eq!(pipeline.documents.len(), 5);
+        assert_eq!(pipeline.sweep(60.0, None, None, None).unwrap(), vec!["weekly"]);
+        assert_eq!(pipeline.documents.len(), 4);
+        assert!(!pipeline.corrector.contains("sprint"));
+    }
+
+    #[test]
+    fn test_sweep_removes_expired_ids_from_every_index() {
+        let path = std::env::temp_dir().join(format!("retrieval_sweep_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut store = VectorStore::open(path, 3, 8).unwrap();
+        let mut keywords = KeywordIndex::default();
+        let mut ann = VectorIndex::with_params(3, 4, 16, 8);
+        let mut pipeline = RetrievalPipeline::default();
+        let temporary = Signals { expires_at: Some(50.0), ..Default::default() };
+        let embedding = [1.0, 0.0, 0.0];
+        pipeline.insert("weekly".to_string(), &embedding, "sprint goals", HashMap::new(), temporary).unwrap();
+        store.write(&[("weekly".to_string(), embedding.to_vec())]).unwrap();
+        keywords.insert("weekly", "sprint goals");
+        ann.insert("weekly".to_string(), &embedding).unwrap();
+
+        let swept = pipeline.sweep(60.0, Some(&mut store), Some(&mut keywords), Some(&mut ann)).unwrap();
+        assert_eq!(swept, vec!["weekly"]);
+        assert!(pipeline.documents.is_empty());
+        assert!(store.vector("weekly").is_none());
+        assert!(keywords.search("sprint", 1).is_empty());
+        assert_eq!(ann.entries().count(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_pinned_documents_are_forced_in_and_never_expire() {
+        let config = PipelineConfig { pinned_budget: 1, ..Default::default() };
+        let mut pipeline = pipeline(config);
+        let invariant = Signals { expires_at: Some(1.0), pinned: true, ..Default::default() };
+        pipeline.insert("invariant".to_string(), &[0.0, -1.0, 0.0], "never push to main", HashMap::new(), invariant).unwrap();
+
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { now: 10.0, ..QueryOptions::top_k(2) }).unwrap();
+        assert_eq!(ids(&hits), vec!["parser", "invariant"]);
+        assert!(hits[1].pinned);
+        assert_eq!(hits[1].recency_score, 1.0);
+
+        assert!(pipeline.sweep(10.0, None, None, None).unwrap().is_empty());
+        pipeline.config.pinned_budget = 0;
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { now: 10.0, ..QueryOptions::top_k(2) }).unwrap();
+        assert_eq!(ids(&hits), vec!["parser", "parser_copy"]);
+    }
+
+    #[test]
+    fn test_superseded_documents_resolve_to_newest_version() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        for (id, timestamp) in [("tabs_v2", 20.0), ("tabs_v3", 30.0)] {
+            let signals = Signals { timestamp: Some(timestamp), ..Default::default() };
+            pipeline.insert(id.to_string(), &[0.0, 1.0, 0.0], "", HashMap::new(), signals).unwrap();
+        }
+        pipeline.relations.add("tabs_v2", RelationKind::Supersedes, "parser").unwrap();
+        pipeline.relations.add("tabs_v3", RelationKind::Supersedes, "tabs_v2").unwrap();
+
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { now: 40.0, ..QueryOptions::top_k(2) }).unwrap();
+        assert_eq!(ids(&hits), vec!["tabs_v3", "parser_copy"]);
+        assert_eq!(hits[0].resolved_from.as_deref(), Some("parser"));
+        assert!(hits[0].vector_score.abs() < 1e-6);
+
+        // The chain stays linked when its middle is removed
+        assert!(pipeline.delete("tabs_v2"));
+        let position = pipeline.positions["parser"];
+        assert_eq!(pipeline.documents[pipeline.newest(position, 40.0)].id, "tabs_v3");
+
+        // An expired newest version falls back to the document itself
+        let position = pipeline.positions["tabs_v3"];
+        pipeline.documents[position].signals.expires_at = Some(35.0);
+        let hits = pipeline.query(&[1.0, 0.0, 0.0], &QueryOptions { now: 40.0, ..QueryOptions::top_k(1) }).unwrap();
+        assert_eq!(ids(&hits), vec!["parser"]);
+        assert_eq!(hits[0].resolved_from, None);
+    }
+
+    #[test]
+    fn test_contradiction_candidates() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        pipeline.insert("port".to_string(), &[0.0, 1.0, 0.1], "dev server runs on port 8080", HashMap::new(), Signals::default()).unwrap();
+        let claims = Claims::of("dev server runs on port 3000");
+        let candidates = pipeline.contradiction_candidates(&[0.0, 1.0, 0.0], &claims, (0.9, 1.0), 10).unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].id, "port");
+        assert_eq!(candidates[0].reasons, vec!["number"]);
+        assert!(pipeline.contradiction_candidates(&[0.0, 1.0, 0.0], &claims, (0.999, 1.0), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trust_down_weights_unverified_low_confidence() {
+        let mut pipeline = RetrievalPipeline::default();
+        let now = 100.0 * 86400.0;
+        let extracted = Signals { confidence: Some(0.4), source: Some(Provenance::Tool), ..Default::default() };
+        let confirmed = Signals { verified_at: Some(now), source: Some(Provenance::Conversation), ..Default::default() };
+        pipeline.insert("extracted".to_string(), &[1.0, 0.0], "", HashMap::new(), extracted).unwrap();
+        pipeline.insert("confirmed".to_string(), &[0.9, 0.3], "", HashMap::new(), confirmed).unwrap();
+        pipeline.config.profiles.insert("trusted".to_string(), ScoringProfile { trust: 1.0, ..Default::default() });
+
+        let plain = pipeline.query(&[1.0, 0.0], &QueryOptions { now, ..QueryOptions::top_k(2) }).unwrap();
+        assert_eq!(ids(&plain), vec!["extracted", "confirmed"]);
+
+        let trusted = QueryOptions { profile: Some("trusted"), now, ..QueryOptions::top_k(2) };
+        let hits = pipeline.query(&[1.0, 0.0], &trusted).unwrap();
+        assert_eq!(ids(&hits), vec!["confirmed", "extracted"]);
+        assert_eq!(hits[0].trust_score, 1.0);
+        assert!((hits[1].trust_score - 0.2).abs() < 1e-6);
+        assert_eq!(hits[1].source.as_deref(), Some("tool"));
+
+        // A verification a month old keeps three quarters of the trust
+        let later = QueryOptions { now: now + 30.0 * 86400.0, ..trusted };
+        let hits = pipeline.query(&[1.0, 0.0], &later).unwrap();
+        assert!((hits[0].trust_score - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_update_metadata_where() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        let code = HashMap::from([("kind".to_string(), "code".to_string())]);
+        let ops = MetadataOps {
+            set: HashMap::from([("reviewed".to_string(), "yes".to_string())]),
+            add_tags: vec!["rust".to_string(), "parser".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(pipeline.update_where(Some(&code), &ops).unwrap(), 3);
+        assert_eq!(pipeline.update_where(Some(&code), &ops).unwrap(), 0);
+        let lexer = &pipeline.documents[pipeline.positions["lexer"]].metadata;
+        assert_eq!(lexer["tags"], "rust,parser");
+        assert_eq!(lexer["reviewed"], "yes");
+        assert!(!pipeline.documents[pipeline.positions["meeting"]].metadata.contains_key("tags"));
+
+        let prune = MetadataOps {
+            unset: vec!["reviewed".to_string()],
+            remove_tags: vec!["rust".to_string(), "parser".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(pipeline.update_where(None, &prune).unwrap(), 3);
+        assert_eq!(pipeline.documents[pipeline.positions["lexer"]].metadata, code);
+
+        // Invalid requests change nothing
+        let invalid = MetadataOps { add_tags: vec!["a,b".to_string()], set: ops.set.clone(), ..Default::default() };
+        assert!(pipeline.update_where(None, &invalid).is_err());
+        assert_eq!(pipeline.documents[pipeline.positions["lexer"]].metadata, code);
+        let conflicting = MetadataOps { set: ops.set.clone(), unset: vec!["reviewed".to_string()], ..Default::default() };
+        assert!(pipeline.update_where(None, &conflicting).is_err());
+    }
+
+    #[test]
+    fn test_store_stats() {
+        let mut pipeline = pipeline(PipelineConfig::default());
+        let add_tags = MetadataOps { add_tags: vec!["rust".to_string(), "parser".to_string()], ..Default::default() };
+        pipeline.update_where(Some(&HashMap::from([("kind".to_string(), "code".to_string())])), &add_tags).unwrap();
+        let signals = Signals { expires_at: Some(5.0), ..Default::default() };
+        pipeline.insert("stale".to_string(), &[0.0, 1.0, 0.0], "", HashMap::new(), signals).unwrap();
+        pipeline.relations.add("parser", RelationKind::Supersedes, "parser_copy").unwrap();
+
+        let stats = pipeline.stats("kind", "language", 10.0);
+        assert_eq!((stats.documents, stats.dimension, stats.expired, stats.relations), (5, Some(3), 1, 1));
+        assert_eq!(stats.by_namespace, HashMap::from([("code".to_string(), 3), ("notes".to_string(), 1)]));
+        assert_eq!(stats.by_tag, HashMap::from([("rust".to_string(), 3), ("parser".to_string(), 3)]));
+        assert!(stats.by_language.is_empty());
+    }
+}
@@ -0,0 +1,413 @@
+use chrono::{DateTime, Datelike, Days, Timelike, Utc};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Seconds since the epoch
+fn now() -> f64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// How far ahead a cron spec is searched: Feb 29 comes at least every
+/// eight years (2096, then 2104)
+const HORIZON_DAYS: u64 = 8 * 366;
+
+/// Allowed values of one cron field, as a bit per value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    bits: u64,
+    /// Written as `*`, which matters for the day-of-month/day-of-week rule
+    any: bool,
+}
+
+impl Field {
+    fn parse(text: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut bits = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    (range, step.parse::<u32>().ok().filter(|&s| s > 0).ok_or_else(|| format!("Bad step: {}", part))?)
+                }
+                None => (part, 1),
+            };
+            let number = |n: &str| {
+                let n = n.parse::<u32>().ok().filter(|n| (min..=max).contains(n));
+                n.ok_or_else(|| format!("Bad cron field: {}", part))
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            };
+            if start > end {
+                return Err(format!("Bad cron field: {}", part));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(Self { bits, any: text == "*" })
+    }
+
+    fn has(self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Spec {
+    /// A fixed interval, counted from the end of the previous run
+    Every(Duration),
+    /// Minute, hour, day of month, month and day of week, in UTC
+    Cron([Field; 5]),
+}
+
+impl Spec {
+    /// Parse `@every 30m` (s, m, h or d), `@hourly`, `@daily`, `@weekly`,
+    /// `@monthly`, or five cron fields with `*`, lists, ranges and steps
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        if let Some(interval) = spec.strip_prefix("@every") {
+            let interval = interval.trim();
+            let split = interval.find(|c: char| !c.is_ascii_digit()).unwrap_or(interval.len());
+            let count: u64 = interval[..split].parse().map_err(|_| format!("Bad interval: {}", interval))?;
+            let unit = match &interval[split..] {
+                "s" => 1,
+                "m" => 60,
+                "h" => 3600,
+                "d" => 86400,
+                _ => return Err(format!("Bad interval: {}", interval)),
+            };
+            if count == 0 {
+                return Err("Interval must be positive".to_string());
+            }
+            return Ok(Spec::Every(Duration::from_secs(count * unit)));
+        }
+        let cron = match spec {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            cron => cron,
+        };
+        let fields: Vec<&str> = cron.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("Expected five cron fields: {}", spec));
+        };
+        let mut weekday = Field::parse(weekday, 0, 7)?;
+        if weekday.has(7) {
+            weekday.bits = (weekday.bits | 1) & !(1 << 7);
+        }
+        Ok(Spec::Cron([
+            Field::parse(minute, 0, 59)?,
+            Field::parse(hour, 0, 23)?,
+            Field::parse(day, 1, 31)?,
+            Field::parse(month, 1, 12)?,
+            weekday,
+        ]))
+    }
+
+    /// The first time after `after` (seconds since the epoch) the job is
+    /// due, or None if a cron spec never matches (`0 0 30 2 *`)
+    pub fn next_after(&self, after: f64) -> Option<f64> {
+        let [minute, hour, day, month, weekday] = match self {
+            Spec::Every(interval) => return Some(after + interval.as_secs_f64()),
+            Spec::Cron(fields) => *fields,
+        };
+        // Cron's rule: when both day fields are restricted, either may match
+        let day_matches = |d: u32, w: u32| match (day.any, weekday.any) {
+            (false, false) => day.has(d) || weekday.has(w),
+            _ => day.has(d) && weekday.has(w),
+        };
+        let first = DateTime::<Utc>::from_timestamp(((after / 60.0).floor() as i64 + 1) * 60, 0)?;
+        // Whole days that can't match are skipped, then the day's minutes
+        // are searched from the first candidate
+        (0..HORIZON_DAYS).find_map(|offset| {
+            let date = first.date_naive().checked_add_days(Days::new(offset))?;
+            if !month.has(date.month()) || !day_matches(date.day(), date.weekday().num_days_from_sunday()) {
+                return None;
+            }
+            let from = if offset == 0 { first.hour() * 60 + first.minute() } else { 0 };
+            let minutes = (from..24 * 60).find(|m| hour.has(m / 60) && minute.has(m % 60))?;
+            let midnight = date.and_hms_opt(0, 0, 0)?.and_utc().timestamp();
+            Some((midnight + i64::from(minutes) * 60) as f64)
+        })
+    }
+}
+
+/// Delay in [0, `jitter`) seconds for one run of a job, varying by job and
+/// run so jobs on the same schedule don't all start at once
+fn jitter_for(name: &str, due: f64, jitter: f64) -> f64 {
+    if jitter <= 0.0 {
+        return 0.0;
+    }
+    let hash = xxh3_64(format!("{}:{}", name, due as i64).as_bytes());
+    (hash as f64 / u64::MAX as f64) * jitter
+}
+
+/// A scheduled job and how its runs went
+#[derive(Debug, Clone, Default)]
+#[pyclass(get_all)]
+pub struct JobStatus {
+    pub name: String,
+    pub spec: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub skipped: usize, // Runs skipped because the previous one hadn't finished
+    pub running: bool,
+    pub last_run: Option<f64>, // Start, in seconds since the epoch
+    pub last_duration: Option<f64>,
+    pub last_error: Option<String>,
+    pub next_run: Option<f64>,
+}
+
+#[pymethods]
+impl JobStatus {
+    fn __repr__(&self) -> String {
+        format!("JobStatus(name={}, spec={}, runs={}, failures={})", self.name, self.spec, self.runs, self.failures)
+    }
+}
+
+struct Job {
+    spec: Spec,
+    jitter: f64,
+    callback: Py<PyAny>,
+    status: Mutex<JobStatus>,
+    cancelled: AtomicBool,
+}
+
+impl Job {
+    /// Run the callback unless a run is already in progress; returns whether
+    /// it ran
+    fn run(&self) -> bool {
+        {
+            let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+            if status.running {
+                status.skipped += 1;
+                return false;
+            }
+            status.running = true;
+        }
+        let started = now();
+        let outcome = Python::attach(|py| self.callback.call0(py).map(drop).map_err(|e| e.to_string()));
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.running = false;
+        status.runs += 1;
+        status.last_run = Some(started);
+        status.last_duration = Some(now() - started);
+        if let Err(error) = outcome {
+            status.failures += 1;
+            status.last_error = Some(error);
+        }
+        true
+    }
+}
+
+/// Wakes job threads for shutdown
+#[derive(Default)]
+struct Signal {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+/// Wait on `job`'s thread until each due time, run it, and repeat until the
+/// job is cancelled or the scheduler stops
+fn job_loop(name: String, job: Arc<Job>, signal: Arc<Signal>) {
+    loop {
+        let Some(due) = job.spec.next_after(now()) else {
+            let mut status = job.status.lock().unwrap_or_else(|e| e.into_inner());
+            status.next_run = None;
+            status.last_error = Some("Spec has no upcoming run".to_string());
+            return;
+        };
+        let due = due + jitter_for(&name, due, job.jitter);
+        job.status.lock().unwrap_or_else(|e| e.into_inner()).next_run = Some(due);
+
+        let mut stopped = signal.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if *stopped || job.cancelled.load(Ordering::SeqCst) {
+                return;
+            }
+            let remaining = due - now();
+            if remaining <= 0.0 {
+                break;
+            }
+            let waited = signal.wake.wait_timeout(stopped, Duration::from_secs_f64(remaining));
+            stopped = waited.unwrap_or_else(|e| e.into_inner()).0;
+        }
+        drop(stopped);
+        job.run();
+    }
+}
+
+/// Runs maintenance jobs (compaction, decay scoring, consolidation,
+/// snapshots) on background threads
+///
+/// Each job gets its own thread, so a slow job never delays another. A run
+/// that comes due while the previous run of the same job is still going is
+/// skipped rather than stacked. Jobs are Python callables, called with the
+/// GIL held; the wait between runs doesn't hold it.
+#[pyclass]
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: HashMap<String, (Arc<Job>, JoinHandle<()>)>,
+    signal: Arc<Signal>,
+}
+
+impl Scheduler {
+    /// Stop `name`'s thread, without waiting for it
+    fn cancel(&mut self, name: &str) -> Option<JoinHandle<()>> {
+        let (job, handle) = self.jobs.remove(name)?;
+        job.cancelled.store(true, Ordering::SeqCst);
+        let _guard = self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        self.signal.wake.notify_all();
+        Some(handle)
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        *self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.signal.wake.notify_all();
+    }
+}
+
+#[pymethods]
+impl Scheduler {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `job` (a callable taking no arguments) on `spec`, replacing any
+    /// job of the same name
+    ///
+    /// Args:
+    ///     name: Job name, e.g. "compact"
+    ///     job: Callable to run, e.g. `store.compact`
+    ///     spec: "@every 30m" (s, m, h or d; counted from the end of the
+    ///         previous run), "@hourly", "@daily", "@weekly", "@monthly",
+    ///         or five cron fields ("15 3 * * 1-5"), in UTC
+    ///     jitter: Up to this many seconds are added to each due time
+    ///
+    /// Raises:
+    ///     ValueError: For a malformed spec, a cron spec that never comes
+    ///         due (`0 0 30 2 *`), or negative jitter
+    #[pyo3(signature = (name, job, spec, jitter=0.0))]
+    fn schedule(&mut self, py: Python<'_>, name: String, job: Py<PyAny>, spec: &str, jitter: f64) -> PyResult<()> {
+        let parsed = Spec::parse(spec).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        if parsed.next_after(now()).is_none() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!("Spec never comes due: {}", spec)));
+        }
+        if jitter.is_nan() || jitter < 0.0 {
+            return Err(pyo3::exceptions::PyValueError::new_err("jitter must be >= 0"));
+        }
+        if let Some(handle) = self.cancel(&name) {
+            py.detach(|| handle.join()).ok();
+        }
+        let status = JobStatus { name: name.clone(), spec: spec.to_string(), ..JobStatus::default() };
+        let job = Arc::new(Job {
+            spec: parsed,
+            jitter,
+            callback: job,
+            status: Mutex::new(status),
+            cancelled: AtomicBool::new(false),
+        });
+        let (thread_name, thread_job, signal) = (name.clone(), job.clone(), self.signal.clone());
+        let handle = std::thread::Builder::new()
+            .name(format!("memory-job-{}", name))
+            .spawn(move || job_loop(thread_name, thread_job, signal))
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+        self.jobs.insert(name, (job, handle));
+        Ok(())
+    }
+
+    /// Stop scheduling a job, waiting for a run in progress to finish;
+    /// returns whether it was scheduled
+    fn unschedule(&mut self, py: Python<'_>, name: &str) -> bool {
+        match self.cancel(name) {
+            Some(handle) => {
+                py.detach(|| handle.join()).ok();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run a job now, on the calling thread; returns False (and counts it
+    /// skipped) if a run is already in progress. Raises KeyError for an
+    /// unknown job.
+    fn run_now(&self, py: Python<'_>, name: &str) -> PyResult<bool> {
+        let (job, _) = self.jobs.get(name).ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(name.to_string()))?;
+        let job = job.clone();
+        Ok(py.detach(|| job.run()))
+    }
+
+    /// Status of every job, by name
+    fn jobs(&self) -> Vec<JobStatus> {
+        let mut jobs: Vec<JobStatus> = self
+            .jobs
+            .values()
+            .map(|(job, _)| job.status.lock().unwrap_or_else(|e| e.into_inner()).clone())
+            .collect();
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        jobs
+    }
+
+    /// Stop every job, waiting for runs in progress to finish
+    fn shutdown(&mut self, py: Python<'_>) {
+        *self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.signal.wake.notify_all();
+        let handles: Vec<JoinHandle<()>> = self.jobs.drain().map(|(_, (_, handle))| handle).collect();
+        py.detach(|| handles.into_iter().for_each(|handle| drop(handle.join())));
+        *self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner()) = false;
+    }
+
+    fn __len__(&self) -> usize {
+        self.jobs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> f64 {
+        DateTime::parse_from_rfc3339(text).unwrap().timestamp() as f64
+    }
+
+    #[test]
+    fn test_next_after_follows_cron_rules() {
+        let next = |spec: &str, after: &str| {
+            let due = Spec::parse(spec).unwrap().next_after(at(after)).unwrap();
+            DateTime::<Utc>::from_timestamp(due as i64, 0).unwrap().format("%Y-%m-%d %H:%M").to_string()
+        };
+        // 2024-03-01 was a Friday
+        assert_eq!(next("15 3 * * *", "2024-03-01T03:15:00Z"), "2024-03-02 03:15");
+        assert_eq!(next("*/20 * * * *", "2024-03-01T10:41:30Z"), "2024-03-01 11:00");
+        assert_eq!(next("0 9 * * 1-5", "2024-03-01T10:00:00Z"), "2024-03-04 09:00");
+        assert_eq!(next("@monthly", "2024-03-01T10:00:00Z"), "2024-04-01 00:00");
+        assert_eq!(next("0 0 * * 7", "2024-03-01T10:00:00Z"), "2024-03-03 00:00");
+        // Both day fields restricted: the 10th or any Monday
+        assert_eq!(next("0 0 10 * 1", "2024-03-05T00:00:00Z"), "2024-03-10 00:00");
+        assert_eq!(next("@every 90m", "2024-03-01T10:00:00Z"), "2024-03-01 11:30");
+        assert_eq!(next("0 0 29 2 *", "2024-03-01T00:00:00Z"), "2028-02-29 00:00");
+        assert_eq!(next("0 0 29 2 *", "2097-03-01T00:00:00Z"), "2104-02-29 00:00");
+        assert_eq!(next("30 23 * * *", "2024-12-31T23:30:00Z"), "2025-01-01 23:30");
+        assert!(Spec::parse("0 0 30 2 *").unwrap().next_after(at("2024-03-01T00:00:00Z")).is_none());
+
+        for bad in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "@every 0s", "@every 5w"] {
+            assert!(Spec::parse(bad).is_err(), "{}", bad);
+        }
+        let jitter = jitter_for("compact", at("2024-03-01T00:00:00Z"), 300.0);
+        assert!((0.0..300.0).contains(&jitter));
+        assert_eq!(jitter_for("compact", 0.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,141 @@
+use tree_sitter::Node;
+
+/// Declarations whose `name` scopes the declarations inside them: types,
+/// namespaces, and functions (for nested functions)
+const SCOPE_KINDS: &[&str] = &[
+    "class_definition",
+    "object_definition",  // Scala
+    "object_declaration", // Kotlin
+    "trait_definition",   // Scala
+    "class_declaration",
+    "abstract_class_declaration",
+    "class_specifier",
+    "struct_specifier",
+    "struct_declaration",
+    "record_declaration",
+    "interface_declaration",
+    "protocol_declaration", // Swift
+    "trait_declaration",
+    "trait_item",
+    "enum_declaration",
+    "class",  // Ruby
+    "module", // Ruby
+    "namespace_definition",
+    "namespace_declaration",
+    "file_scoped_namespace_declaration",
+    "mod_item",
+    "function_definition",
+    "function_declaration",
+    "function_item",
+    "method_definition",
+    "method_declaration",
+    "method",
+    "singleton_method",
+];
+
+/// Zig container expressions, which scope the functions declared inside
+const ZIG_CONTAINERS: &[&str] = &["struct_declaration", "union_declaration", "enum_declaration"];
+
+/// Names of the declarations enclosing `node`, outermost first. Rust
+/// `impl` blocks scope their items under the implementing type, and Go
+/// methods under their receiver's type.
+pub(crate) fn enclosing_scopes(node: Node, source: &[u8]) -> Vec<String> {
+    let mut scopes: Vec<String> = receiver_type(node, source).into_iter().collect();
+    let mut current = node.parent();
+    while let Some(ancestor) = current {
+        scopes.extend(scope_name(ancestor, source));
+        current = ancestor.parent();
+    }
+    scopes.reverse();
+    scopes
+}
+
+/// Separator between scopes in a qualified name, as the language writes it
+pub(crate) fn separator(language: &str) -> &'static str {
+    match language {
+        "Rust" | "C" | "Cpp" | "Php" | "Ruby" => "::",
+        _ => ".",
+    }
+}
+
+fn scope_name(node: Node, source: &[u8]) -> Option<String> {
+    let mut cursor = node.walk();
+    let mut children = node.named_children(&mut cursor);
+    let name = match node.kind() {
+        // `impl Foo` and `impl Trait for Foo` both scope under Foo
+        "impl_item" => node.child_by_field_name("type")?,
+        // Zig types are constants bound to a container (`const Stack = struct { ... };`)
+        "variable_declaration" => {
+            let children: Vec<Node> = children.collect();
+            children.iter().find(|c| ZIG_CONTAINERS.contains(&c.kind()))?;
+            *children.iter().find(|c| c.kind() == "identifier")?
+        }
+        // Kotlin declarations name themselves with an unlabelled child
+        kind if SCOPE_KINDS.contains(&kind) => node
+            .child_by_field_name("name")
+            .or_else(|| children.find(|c| matches!(c.kind(), "type_identifier" | "simple_identifier")))?,
+        _ => return None,
+    };
+    let text = name.utf8_text(source).ok()?;
+    // Generic arguments aren't part of the scope (`Foo<T>`)
+    Some(text.split('<').next().unwrap_or(text).trim().to_string())
+}
+
+/// Type of a Go method's receiver (`func (s *Server[T]) Addr()` gives Server)
+fn receiver_type(node: Node, source: &[u8]) -> Option<String> {
+    if node.kind() != "method_declaration" {
+        return None;
+    }
+    let receiver = node.child_by_field_name("receiver")?.named_child(0)?;
+    let text = receiver.child_by_field_name("type")?.utf8_text(source).ok()?;
+    Some(text.trim_start_matches('*').split('[').next()?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    fn qualified(file_path: &str, source: &str) -> Vec<(String, Option<String>)> {
+        let result = parse_any_file(file_path, source, &ParseOptions::default()).unwrap();
+        result.units.into_iter().map(|u| (u.qualified_name, u.parent_name)).collect()
+    }
+
+    fn expected(pairs: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        pairs.iter().map(|(name, parent)| (name.to_string(), parent.map(str::to_string))).collect()
+    }
+
+    #[test]
+    fn test_methods_are_qualified_by_their_scopes() {
+        let python = "class Outer:\n    class Inner:\n        def run(self):\n            def step():\n                pass\n";
+        assert_eq!(
+            qualified("a.py", python),
+            expected(&[
+                ("Outer.Inner.run", Some("Inner")),
+                ("Outer.Inner.run.step", Some("run")),
+                ("Outer", None),
+                ("Outer.Inner", Some("Outer")),
+            ])
+        );
+
+        let rust = "mod shapes {\n    impl<T> Display for Square<T> {\n        fn fmt(&self) {}\n    }\n}\nfn main() {}\n";
+        assert_eq!(qualified("a.rs", rust), expected(&[("shapes::Square::fmt", Some("Square")), ("main", None)]));
+
+        let go = "package main\nfunc (s *Server) Addr() string { return \"\" }\n";
+        assert_eq!(qualified("a.go", go), expected(&[("Server.Addr", Some("Server"))]));
+
+        let csharp = "namespace Shop {\n    class Order {\n        int Total() { return 0; }\n    }\n}\n";
+        assert_eq!(
+            qualified("a.cs", csharp),
+            expected(&[("Shop.Order.Total", Some("Order")), ("Shop.Order", Some("Shop"))])
+        );
+    }
+
+    #[test]
+    fn test_class_methods_are_units() {
+        let js = "class Cart {\n  total() { return 0; }\n}\n";
+        assert_eq!(qualified("a.js", js), expected(&[("Cart.total", Some("Cart")), ("Cart", None)]));
+
+        let php = "<?php\nclass Mailer {\n    public function send($to) { return true; }\n}\n";
+        assert_eq!(qualified("a.php", php), expected(&[("Mailer::send", Some("Mailer")), ("Mailer", None)]));
+    }
+}
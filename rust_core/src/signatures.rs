@@ -0,0 +1,158 @@
+use tree_sitter::Node;
+
+use crate::docstrings::last_row;
+
+/// Decorators, annotations and attributes attached to a declaration
+const DECORATOR_KINDS: &[&str] = &[
+    "decorator",             // Python, JS/TS
+    "annotation",            // Java `@Table(name = "x")`
+    "marker_annotation",     // Java `@Override`
+    "attribute_list",        // C# `[HttpGet]`, PHP `#[Route]`
+    "attribute_item",        // Rust `#[derive(Debug)]`
+    "attribute_declaration", // C++ `[[nodiscard]]`
+];
+
+/// Declaration text from its start up to its body: modifiers, name,
+/// parameters and return type, on one line with whitespace collapsed.
+/// Decorators are left out (see `decorators`). Without a body the
+/// signature ends after the parameters; `None` when neither exists.
+pub(crate) fn signature(node: Node, body: Option<Node>, params: Option<Node>, source: &[u8]) -> Option<String> {
+    let body = body.or_else(|| node.child_by_field_name("body"));
+    // Go structs are captured with `struct {...}` as their body; keep the keyword
+    let body = body.map(|b| if b.kind() == "struct_type" { b.named_child(0).unwrap_or(b) } else { b });
+    let end = match (body, params) {
+        (Some(body), _) if body.start_byte() > node.start_byte() => body.start_byte(),
+        (_, Some(params)) => params.end_byte(),
+        _ => return None,
+    };
+
+    let mut text = Vec::new();
+    let mut from = node.start_byte();
+    for decorator in own_decorators(node).into_iter().filter(|d| d.start_byte() < end) {
+        text.extend_from_slice(source.get(from..decorator.start_byte())?);
+        from = decorator.end_byte();
+    }
+    text.extend_from_slice(source.get(from.min(end)..end)?);
+    let text = String::from_utf8_lossy(&text);
+
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let tidied = collapsed.replace("( ", "(").replace(" )", ")").replace(",)", ")");
+    Some(tidied.trim_end_matches([':', ' ']).to_string())
+}
+
+/// Decorators, annotations and attributes of the declaration, in source
+/// order, as written (`@dataclass`, `@Override`, `#[derive(Debug)]`...)
+pub(crate) fn decorators(node: Node, source: &[u8]) -> Vec<String> {
+    let mut found = outer_decorators(node);
+    found.extend(own_decorators(node));
+    found
+        .into_iter()
+        .filter_map(|decorator| decorator.utf8_text(source).ok())
+        .map(|text| text.trim().to_string())
+        .collect()
+}
+
+/// Decorators inside the declaration's own span, directly or in its
+/// modifier list (Java)
+fn own_decorators(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    let mut found = Vec::new();
+    for child in node.children(&mut cursor) {
+        if DECORATOR_KINDS.contains(&child.kind()) {
+            found.push(child);
+        } else if child.kind() == "modifiers" {
+            let mut inner = child.walk();
+            found.extend(child.children(&mut inner).filter(|c| DECORATOR_KINDS.contains(&c.kind())));
+        }
+    }
+    found
+}
+
+/// Decorators written before the declaration: the decorators of a Python
+/// `decorated_definition`, or the attributes above a Rust item
+fn outer_decorators(node: Node) -> Vec<Node> {
+    if let Some(parent) = node.parent().filter(|p| p.kind() == "decorated_definition") {
+        let mut cursor = parent.walk();
+        return parent.children(&mut cursor).filter(|c| c.kind() == "decorator").collect();
+    }
+    let mut found = Vec::new();
+    let mut below = node.start_position().row;
+    let mut current = node.prev_named_sibling();
+    while let Some(sibling) = current {
+        // A blank line ends the item's attributes, and stops the walk
+        // before it steps through every comment above
+        if last_row(sibling) + 1 < below {
+            break;
+        }
+        match sibling.kind() {
+            "attribute_item" => found.push(sibling),
+            // Doc comments may sit between attributes
+            kind if kind.contains("comment") => {}
+            _ => break,
+        }
+        below = sibling.start_position().row;
+        current = sibling.prev_named_sibling();
+    }
+    found.reverse();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::{parse_any_file, ParseOptions};
+
+    fn signatures(file_path: &str, source: &str) -> Vec<(String, Vec<String>)> {
+        let result = parse_any_file(file_path, source, &ParseOptions::default()).unwrap();
+        result.units.into_iter().map(|u| (u.signature, u.decorators)).collect()
+    }
+
+    fn expected(pairs: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        pairs
+            .iter()
+            .map(|(signature, decorators)| (signature.to_string(), decorators.iter().map(|d| d.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_line_signatures_with_decorators() {
+        let python = "@app.route('/')\n@login_required\ndef index(\n    request,\n    page: int = 1,\n) -> str:\n    \
+            return ''\n\n@dataclass\nclass Point(Base):\n    x: int\n";
+        assert_eq!(
+            signatures("a.py", python),
+            expected(&[
+                ("def index(request, page: int = 1) -> str", &["@app.route('/')", "@login_required"]),
+                ("class Point(Base)", &["@dataclass"]),
+            ])
+        );
+
+        let java = "class A {\n    @Override\n    public String toString()\n        throws IOException {\n        \
+            return \"\";\n    }\n}\n";
+        assert_eq!(
+            signatures("A.java", java),
+            expected(&[("public String toString() throws IOException", &["@Override"]), ("class A", &[])])
+        );
+    }
+
+    #[test]
+    fn test_signatures_keep_return_types() {
+        let rust = "/// Pops.\n#[inline]\n#[must_use]\npub fn pop<T>(&mut self) -> Option<T> where T: Clone {\n    \
+            None\n}\n";
+        assert_eq!(
+            signatures("a.rs", rust),
+            expected(&[("pub fn pop<T>(&mut self) -> Option<T> where T: Clone", &["#[inline]", "#[must_use]"])])
+        );
+
+        let go = "package main\ntype Server struct {\n    addr string\n}\n\
+            func (s *Server) Addr() (string, error) {\n    return s.addr, nil\n}\n";
+        assert_eq!(
+            signatures("a.go", go),
+            expected(&[("func (s *Server) Addr() (string, error)", &[]), ("Server struct", &[])])
+        );
+
+        let csharp = "class Orders {\n    [HttpGet]\n    public int Count() => 0;\n}\n";
+        assert_eq!(
+            signatures("a.cs", csharp),
+            expected(&[("public int Count()", &["[HttpGet]"]), ("class Orders", &[])])
+        );
+    }
+}
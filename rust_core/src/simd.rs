@@ -0,0 +1,239 @@
+//! Explicit SIMD kernels for embedding similarity and normalization
+//!
+//! x86_64 CPUs with AVX2 and FMA take a hand-written 256-bit path chosen at
+//! runtime. Every other CPU falls back to portable 8-lane `wide` vectors,
+//! which lower to SSE2 on x86_64 and NEON on aarch64.
+
+use wide::f32x8;
+
+const LANES: usize = 8;
+
+/// Dot product of two equal-length vectors
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    #[cfg(target_arch = "x86_64")]
+    if avx2::available() {
+        // SAFETY: the CPU supports AVX2 and FMA
+        return unsafe { avx2::dot(a, b) };
+    }
+    portable::dot(a, b)
+}
+
+/// `(a·b, |a|², |b|²)` in a single pass over both vectors
+pub fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    debug_assert_eq!(a.len(), b.len());
+    #[cfg(target_arch = "x86_64")]
+    if avx2::available() {
+        // SAFETY: the CPU supports AVX2 and FMA
+        return unsafe { avx2::dot_and_norms(a, b) };
+    }
+    portable::dot_and_norms(a, b)
+}
+
+/// Euclidean length of a vector
+pub fn norm(a: &[f32]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Cosine similarity of two equal-length vectors; 0.0 if either is all zeros
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let (dot, norm_a, norm_b) = dot_and_norms(a, b);
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// `vector` scaled to unit length, or all zeros if it has no length
+pub fn normalized(vector: &[f32]) -> Vec<f32> {
+    let mut out = vec![0.0; vector.len()];
+    normalize_into(vector, &mut out);
+    out
+}
+
+/// Write `vector` scaled to unit length into the equal-length `out`
+pub fn normalize_into(vector: &[f32], out: &mut [f32]) {
+    debug_assert_eq!(vector.len(), out.len());
+    let norm = norm(vector);
+    if norm > 0.0 {
+        portable::scale_into(vector, 1.0 / norm, out);
+    } else {
+        out.fill(0.0);
+    }
+}
+
+/// Reference scalar kernels, kept for benchmarks and tests
+pub mod scalar {
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let norm_a = dot(a, a).sqrt();
+        let norm_b = dot(b, b).sqrt();
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 0.0;
+        }
+        dot(a, b) / (norm_a * norm_b)
+    }
+
+    pub fn normalized(vector: &[f32]) -> Vec<f32> {
+        let norm = dot(vector, vector).sqrt();
+        if norm > 0.0 {
+            vector.iter().map(|x| x / norm).collect()
+        } else {
+            vec![0.0; vector.len()]
+        }
+    }
+}
+
+mod portable {
+    use super::{f32x8, LANES};
+
+    fn lanes(chunk: &[f32]) -> f32x8 {
+        f32x8::from(<[f32; LANES]>::try_from(chunk).expect("chunk of LANES floats"))
+    }
+
+    pub(super) fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = f32x8::ZERO;
+        let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+        let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, y)| x * y).sum();
+        for (x, y) in a_chunks.zip(b_chunks) {
+            acc = lanes(x).mul_add(lanes(y), acc);
+        }
+        acc.reduce_add() + tail
+    }
+
+    pub(super) fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let (mut ab, mut aa, mut bb) = (f32x8::ZERO, f32x8::ZERO, f32x8::ZERO);
+        let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+        let (mut tail_ab, mut tail_aa, mut tail_bb) = (0.0, 0.0, 0.0);
+        for (x, y) in a_chunks.remainder().iter().zip(b_chunks.remainder()) {
+            tail_ab += x * y;
+            tail_aa += x * x;
+            tail_bb += y * y;
+        }
+        for (x, y) in a_chunks.zip(b_chunks) {
+            let (x, y) = (lanes(x), lanes(y));
+            ab = x.mul_add(y, ab);
+            aa = x.mul_add(x, aa);
+            bb = y.mul_add(y, bb);
+        }
+        (ab.reduce_add() + tail_ab, aa.reduce_add() + tail_aa, bb.reduce_add() + tail_bb)
+    }
+
+    pub(super) fn scale_into(vector: &[f32], factor: f32, out: &mut [f32]) {
+        let chunks = vector.chunks_exact(LANES);
+        let tail = chunks.remainder();
+        let mut out_chunks = out.chunks_exact_mut(LANES);
+        let factor_lanes = f32x8::splat(factor);
+        for (chunk, slot) in chunks.zip(out_chunks.by_ref()) {
+            slot.copy_from_slice(&(lanes(chunk) * factor_lanes).to_array());
+        }
+        for (x, slot) in tail.iter().zip(out_chunks.into_remainder()) {
+            *slot = x * factor;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    pub(super) fn available() -> bool {
+        is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma")
+    }
+
+    /// Sum of the eight lanes
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn horizontal_sum(v: __m256) -> f32 {
+        let sum = _mm_add_ps(_mm256_castps256_ps128(v), _mm256_extractf128_ps(v, 1));
+        let sum = _mm_add_ps(sum, _mm_movehl_ps(sum, sum));
+        let sum = _mm_add_ss(sum, _mm_shuffle_ps(sum, sum, 1));
+        _mm_cvtss_f32(sum)
+    }
+
+    /// Two accumulators per sum hide the FMA latency. Callers must check
+    /// `available()` first.
+    #[target_feature(enable = "avx2,fma")]
+    pub(super) unsafe fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let (mut acc0, mut acc1) = (_mm256_setzero_ps(), _mm256_setzero_ps());
+        let mut i = 0;
+        while i + 16 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)), acc0);
+            acc1 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i + 8)), _mm256_loadu_ps(pb.add(i + 8)), acc1);
+            i += 16;
+        }
+        if i + 8 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)), acc0);
+            i += 8;
+        }
+        let tail: f32 = a[i..n].iter().zip(&b[i..n]).map(|(x, y)| x * y).sum();
+        horizontal_sum(_mm256_add_ps(acc0, acc1)) + tail
+    }
+
+    /// Callers must check `available()` first.
+    #[target_feature(enable = "avx2,fma")]
+    pub(super) unsafe fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let (mut ab, mut aa, mut bb) = (_mm256_setzero_ps(), _mm256_setzero_ps(), _mm256_setzero_ps());
+        let mut i = 0;
+        while i + 8 <= n {
+            let x = _mm256_loadu_ps(pa.add(i));
+            let y = _mm256_loadu_ps(pb.add(i));
+            ab = _mm256_fmadd_ps(x, y, ab);
+            aa = _mm256_fmadd_ps(x, x, aa);
+            bb = _mm256_fmadd_ps(y, y, bb);
+            i += 8;
+        }
+        let (mut tail_ab, mut tail_aa, mut tail_bb) = (0.0, 0.0, 0.0);
+        for (x, y) in a[i..n].iter().zip(&b[i..n]) {
+            tail_ab += x * y;
+            tail_aa += x * x;
+            tail_bb += y * y;
+        }
+        (horizontal_sum(ab) + tail_ab, horizontal_sum(aa) + tail_aa, horizontal_sum(bb) + tail_bb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn close(a: f32, b: f32, scale: f32) -> bool {
+        (a - b).abs() <= 1e-4 * scale.max(1.0)
+    }
+
+    #[test]
+    fn test_zero_vectors() {
+        assert_eq!(cosine(&[0.0; 19], &[1.0; 19]), 0.0);
+        assert_eq!(normalized(&[0.0; 19]), vec![0.0; 19]);
+        assert_eq!(dot(&[], &[]), 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn kernels_match_scalar(
+            (a, b) in (0usize..100).prop_flat_map(|dim| (
+                prop::collection::vec(-100.0f32..100.0, dim),
+                prop::collection::vec(-100.0f32..100.0, dim),
+            )),
+        ) {
+            let magnitude = scalar::dot(&a, &a).sqrt() * scalar::dot(&b, &b).sqrt();
+            prop_assert!(close(dot(&a, &b), scalar::dot(&a, &b), magnitude));
+            prop_assert!(close(portable::dot(&a, &b), scalar::dot(&a, &b), magnitude));
+            let (ab, aa, bb) = dot_and_norms(&a, &b);
+            prop_assert!(close(ab, scalar::dot(&a, &b), magnitude));
+            prop_assert!(close(aa, scalar::dot(&a, &a), aa));
+            prop_assert!(close(bb, scalar::dot(&b, &b), bb));
+            prop_assert!((cosine(&a, &b) - scalar::cosine(&a, &b)).abs() < 1e-4);
+            for (x, y) in normalized(&a).iter().zip(scalar::normalized(&a)) {
+                prop_assert!((x - y).abs() < 1e-5);
+            }
+        }
+    }
+}
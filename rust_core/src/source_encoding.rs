@@ -0,0 +1,214 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use pyo3::prelude::*;
+
+use crate::parsing::{parse_any_file, ParseOptions, ParseResult};
+
+/// The encoding named by a PEP 263 / Emacs / Vim declaration in the first
+/// two lines, e.g. `# -*- coding: latin-1 -*-`
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = bytes.split(|&b| b == b'\n').take(2);
+    head.filter_map(|line| {
+        let line = String::from_utf8_lossy(line);
+        let at = line.find("coding")? + "coding".len();
+        let rest = line[at..].strip_prefix([':', '='])?.trim_start();
+        let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || "-_.".contains(c))).unwrap_or(rest.len());
+        Encoding::for_label(&rest.as_bytes()[..end])
+    })
+    .next()
+}
+
+/// Guess the encoding of `bytes` and the length of its byte order mark:
+/// a BOM wins, then valid UTF-8, then an encoding declaration, then
+/// BOM-less UTF-16 (ASCII-range text has a NUL in every other byte), and
+/// otherwise Windows-1252, the superset of Latin-1 that legacy files use
+pub fn detect(bytes: &[u8]) -> (&'static Encoding, usize) {
+    if let Some(found) = Encoding::for_bom(bytes) {
+        return found;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return (UTF_8, 0);
+    }
+    if let Some(declared) = declared_encoding(bytes) {
+        return (declared, 0);
+    }
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    let nuls = |parity: usize| sample.iter().skip(parity).step_by(2).filter(|&&b| b == 0).count();
+    let (even, odd) = (nuls(0), nuls(1));
+    let half = sample.len() / 2;
+    if odd > half / 2 && even < odd / 8 {
+        (UTF_16LE, 0)
+    } else if even > half / 2 && odd < even / 8 {
+        (UTF_16BE, 0)
+    } else {
+        (WINDOWS_1252, 0)
+    }
+}
+
+/// Source bytes decoded to UTF-8, with what's needed to map offsets back
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    /// Whether malformed sequences were replaced with U+FFFD
+    pub had_errors: bool,
+    /// `(UTF-8 offset, source offset)` at every character boundary and the
+    /// end, or None when the two always differ by just `bom`
+    boundaries: Option<Vec<(usize, usize)>>,
+    bom: usize,
+}
+
+impl Decoded {
+    /// Decode `bytes`, as `encoding` or else as detected
+    pub fn new(bytes: &[u8], encoding: Option<&'static Encoding>) -> Self {
+        let (encoding, bom) = match encoding {
+            Some(encoding) => (encoding, Encoding::for_bom(bytes).filter(|(e, _)| *e == encoding).map_or(0, |f| f.1)),
+            None => detect(bytes),
+        };
+        let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom..]);
+        let text = text.into_owned();
+        let width: Option<Box<dyn Fn(char) -> usize>> = if encoding == UTF_8 {
+            None
+        } else if encoding == UTF_16LE || encoding == UTF_16BE {
+            Some(Box::new(|c: char| c.len_utf16() * 2))
+        } else if encoding.is_single_byte() {
+            Some(Box::new(|_| 1))
+        } else {
+            Some(Box::new(move |c: char| encoding.encode(c.encode_utf8(&mut [0; 4])).0.len()))
+        };
+        let boundaries = width.map(|width| {
+            let mut source = bom;
+            let mut boundaries: Vec<(usize, usize)> = text
+                .char_indices()
+                .map(|(offset, c)| {
+                    let boundary = (offset, source);
+                    source += width(c);
+                    boundary
+                })
+                .collect();
+            boundaries.push((text.len(), source));
+            boundaries
+        });
+        Self { text, encoding, had_errors, boundaries, bom }
+    }
+
+    /// The offset in the source bytes of UTF-8 offset `offset`
+    pub fn source_offset(&self, offset: usize) -> usize {
+        match &self.boundaries {
+            None => offset + self.bom,
+            Some(boundaries) => match boundaries.binary_search_by_key(&offset, |&(utf8, _)| utf8) {
+                Ok(i) => boundaries[i].1,
+                // Inside a character: round down to its start
+                Err(i) => boundaries[i.saturating_sub(1)].1,
+            },
+        }
+    }
+}
+
+/// Parse source bytes in any encoding. Units keep their lines and text;
+/// their byte offsets and content refs point into `bytes`.
+pub(crate) fn parse_bytes(
+    file_path: &str,
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    let decoded = Decoded::new(bytes, encoding);
+    let mut result = parse_any_file(file_path, &decoded.text, options)?;
+    if decoded.boundaries.is_some() || decoded.bom > 0 {
+        for unit in &mut result.units {
+            if let Some((offset, length)) = unit.content_ref {
+                let start = decoded.source_offset(offset);
+                unit.content_ref = Some((start, decoded.source_offset(offset + length) - start));
+            }
+            unit.start_byte = decoded.source_offset(unit.start_byte);
+            unit.end_byte = decoded.source_offset(unit.end_byte);
+        }
+    }
+    if decoded.had_errors {
+        result.warnings.push(format!("Malformed {} sequences replaced with U+FFFD", decoded.encoding.name()));
+    }
+    Ok(result)
+}
+
+/// Parse a source file given as raw bytes, in whatever encoding it has
+///
+/// The encoding is taken from a byte order mark, else detected: UTF-8 if
+/// the bytes are valid UTF-8, then a `coding:` declaration in the first two
+/// lines, then BOM-less UTF-16, falling back to Windows-1252 (Latin-1).
+/// Units' `start_byte`, `end_byte` and `content_ref` are offsets into
+/// `source_bytes`, not into the decoded text.
+///
+/// Args:
+///     file_path: Path used to pick the parser, as in `parse_source_file`
+///     source_bytes: The file's contents
+///     encoding: WHATWG encoding label (e.g. "latin1", "utf-16le",
+///         "shift_jis") overriding detection
+///     unit_kinds, sql_dialect, parse_template_host, content_refs: As for
+///         `parse_source_file`
+///
+/// Returns:
+///     ParseResult, with a warning if malformed sequences were replaced
+#[pyfunction]
+#[pyo3(signature = (file_path, source_bytes, encoding=None, unit_kinds=None, sql_dialect=None, parse_template_host=false, content_refs=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn parse_source_bytes(
+    py: Python<'_>,
+    file_path: String,
+    source_bytes: &[u8],
+    encoding: Option<String>,
+    unit_kinds: Option<Vec<String>>,
+    sql_dialect: Option<String>,
+    parse_template_host: bool,
+    content_refs: bool,
+) -> PyResult<ParseResult> {
+    let encoding = encoding
+        .map(|label| {
+            Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown encoding: {}", label)))
+        })
+        .transpose()?;
+    let options = ParseOptions::from_args(unit_kinds, sql_dialect, parse_template_host, content_refs)?;
+    py.detach(|| parse_bytes(&file_path, source_bytes, encoding, &options))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// Name of the encoding `parse_source_bytes` would decode bytes as, e.g.
+/// "UTF-8", "UTF-16LE" or "windows-1252"
+#[pyfunction]
+pub fn detect_source_encoding(source_bytes: &[u8]) -> &'static str {
+    detect(source_bytes).0.name()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(result: &ParseResult, name: &str) -> (usize, usize) {
+        let unit = result.units.iter().find(|u| u.name == name).unwrap();
+        (unit.start_byte, unit.end_byte)
+    }
+
+    #[test]
+    fn test_legacy_encodings_parse_with_source_offsets() {
+        let source = "# Café\ndef größe():\n    return 'é'\n\ndef after():\n    pass\n";
+        let latin1: Vec<u8> = source.chars().map(|c| c as u8).collect();
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(source.encode_utf16().flat_map(u16::to_le_bytes));
+        let options = ParseOptions::default();
+
+        assert_eq!(detect(&latin1).0, WINDOWS_1252);
+        assert_eq!(detect(&utf16), (UTF_16LE, 2));
+        assert_eq!(detect(&utf16[2..]).0, UTF_16LE);
+        assert_eq!(detect(b"# -*- coding: shift_jis -*-\n\x82\xa0").0.name(), "Shift_JIS");
+
+        let from_latin1 = parse_bytes("legacy.py", &latin1, None, &options).unwrap();
+        let (start, end) = find(&from_latin1, "after");
+        assert_eq!(&latin1[start..end], b"def after():\n    pass");
+        assert!(from_latin1.units.iter().any(|u| u.name == "größe"));
+        assert!(from_latin1.warnings.is_empty());
+
+        let from_utf16 = parse_bytes("legacy.py", &utf16, None, &options).unwrap();
+        let (start, end) = find(&from_utf16, "after");
+        let text: Vec<u16> = utf16[start..end].chunks(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+        assert_eq!(String::from_utf16(&text).unwrap(), "def after():\n    pass");
+    }
+}
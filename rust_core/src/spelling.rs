@@ -0,0 +1,253 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A vocabulary term close to a misspelled one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Suggestion {
+    #[pyo3(get)]
+    pub term: String,
+    #[pyo3(get)]
+    pub distance: usize, // Damerau-Levenshtein (optimal string alignment) distance
+    #[pyo3(get)]
+    pub count: u64, // Occurrences of the term in the vocabulary
+}
+
+#[pymethods]
+impl Suggestion {
+    fn __repr__(&self) -> String {
+        format!("Suggestion(term={}, distance={}, count={})", self.term, self.distance, self.count)
+    }
+}
+
+/// `word` and every string reachable from it by deleting up to
+/// `max_distance` characters
+fn deletes(word: &str, max_distance: usize) -> HashSet<String> {
+    let mut all = HashSet::from([word.to_string()]);
+    let mut frontier = vec![word.to_string()];
+    for _ in 0..max_distance {
+        let mut next = Vec::new();
+        for current in &frontier {
+            let chars: Vec<char> = current.chars().collect();
+            for skip in 0..chars.len() {
+                let deleted: String = chars.iter().enumerate().filter(|&(i, _)| i != skip).map(|(_, c)| c).collect();
+                if all.insert(deleted.clone()) {
+                    next.push(deleted);
+                }
+            }
+        }
+        frontier = next;
+    }
+    all
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// SymSpell-style spelling corrector over a counted vocabulary
+///
+/// Every vocabulary word is indexed under all of its deletions up to
+/// `max_edit_distance`, so a lookup only computes edit distances against words
+/// sharing a deletion with the query term instead of scanning the vocabulary.
+/// Terms are lowercased.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct SpellCorrector {
+    max_edit_distance: usize,
+    counts: HashMap<String, u64>,
+    deletes: HashMap<String, Vec<String>>,
+}
+
+impl Default for SpellCorrector {
+    fn default() -> Self {
+        Self::with_distance(2)
+    }
+}
+
+impl SpellCorrector {
+    pub fn with_distance(max_edit_distance: usize) -> Self {
+        Self { max_edit_distance, counts: HashMap::new(), deletes: HashMap::new() }
+    }
+
+    pub fn max_edit_distance(&self) -> usize {
+        self.max_edit_distance
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.counts.contains_key(word)
+    }
+
+    /// Count one occurrence of `word`
+    pub fn add(&mut self, word: &str) {
+        let word = word.to_lowercase();
+        let count = self.counts.entry(word.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            for deleted in deletes(&word, self.max_edit_distance) {
+                self.deletes.entry(deleted).or_default().push(word.clone());
+            }
+        }
+    }
+
+    /// Forget one occurrence of `word`, dropping it once none remain
+    pub fn remove(&mut self, word: &str) {
+        let word = word.to_lowercase();
+        let Some(count) = self.counts.get_mut(&word) else { return };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        self.counts.remove(&word);
+        for deleted in deletes(&word, self.max_edit_distance) {
+            if let Some(words) = self.deletes.get_mut(&deleted) {
+                words.retain(|w| *w != word);
+                if words.is_empty() {
+                    self.deletes.remove(&deleted);
+                }
+            }
+        }
+    }
+
+    /// Vocabulary terms within `max_edit_distance` of `term`, closest first,
+    /// then most frequent
+    pub fn suggest(&self, term: &str, max_edit_distance: usize) -> Result<Vec<Suggestion>, String> {
+        if max_edit_distance > self.max_edit_distance {
+            return Err(format!(
+                "max_edit_distance {} exceeds the corrector's {}",
+                max_edit_distance, self.max_edit_distance
+            ));
+        }
+        let term = term.to_lowercase();
+        let length = term.chars().count();
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+        for deleted in deletes(&term, max_edit_distance) {
+            let Some(words) = self.deletes.get(&deleted) else { continue };
+            for word in words {
+                if !seen.insert(word) || word.chars().count().abs_diff(length) > max_edit_distance {
+                    continue;
+                }
+                let distance = edit_distance(&term, word);
+                if distance <= max_edit_distance {
+                    suggestions.push(Suggestion { term: word.clone(), distance, count: self.counts[word] });
+                }
+            }
+        }
+        suggestions.sort_by(|a, b| a.distance.cmp(&b.distance).then(b.count.cmp(&a.count)).then(a.term.cmp(&b.term)));
+        Ok(suggestions)
+    }
+}
+
+#[pymethods]
+impl SpellCorrector {
+    /// Empty corrector; lookups may use edit distances up to `max_edit_distance`
+    #[new]
+    #[pyo3(signature = (max_edit_distance=2))]
+    fn new(max_edit_distance: usize) -> Self {
+        Self::with_distance(max_edit_distance)
+    }
+
+    /// Count occurrences of each word
+    fn add_words(&mut self, words: Vec<String>) {
+        for word in &words {
+            self.add(word);
+        }
+    }
+
+    /// Count the identifiers and words of a text (identifiers also contribute
+    /// their snake/camel case parts)
+    fn add_text(&mut self, text: &str) {
+        for word in crate::query_expansion::vocabulary(text) {
+            self.add(&word);
+        }
+    }
+
+    /// Vocabulary terms close to `term`, closest and most frequent first.
+    /// An exact match comes back with distance 0. Raises ValueError when
+    /// `max_edit_distance` exceeds the corrector's.
+    #[pyo3(signature = (term, max_edit_distance=2))]
+    fn suggest_corrections(&self, term: &str, max_edit_distance: usize) -> PyResult<Vec<Suggestion>> {
+        self.suggest(term, max_edit_distance).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.counts.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SpellCorrector(terms={}, max_edit_distance={})", self.counts.len(), self.max_edit_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrector(words: &[&str]) -> SpellCorrector {
+        let mut corrector = SpellCorrector::default();
+        for word in words {
+            corrector.add(word);
+        }
+        corrector
+    }
+
+    fn terms(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.term.as_str()).collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("parse", "parse"), 0);
+        assert_eq!(edit_distance("prase", "parse"), 1);
+        assert_eq!(edit_distance("pars", "parse"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggestions_ordered_by_distance_then_count() {
+        let corrector = corrector(&["config", "config", "confog", "configure", "connect"]);
+        let suggestions = corrector.suggest("confgi", 2).unwrap();
+        assert_eq!(terms(&suggestions), vec!["config", "confog"]);
+        assert_eq!(suggestions[0].count, 2);
+        assert_eq!(corrector.suggest("CONFIG", 0).unwrap()[0].distance, 0);
+    }
+
+    #[test]
+    fn test_remove_drops_words_after_last_occurrence() {
+        let mut corrector = corrector(&["parser", "parser"]);
+        corrector.remove("parser");
+        assert_eq!(corrector.suggest("parsr", 1).unwrap()[0].count, 1);
+        corrector.remove("parser");
+        assert!(corrector.suggest("parsr", 1).unwrap().is_empty());
+        assert!(corrector.deletes.is_empty());
+    }
+
+    #[test]
+    fn test_distance_limit() {
+        let corrector = corrector(&["tokenizer"]);
+        assert!(corrector.suggest("tokenzier", 3).is_err());
+        assert!(corrector.suggest("tkenzier", 1).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use crate::parsing::SemanticUnit;
+
+/// SQL dialects with syntax the shared tree-sitter grammar doesn't understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    Generic,
+    Postgres,
+    MySql,
+    Sqlite,
+    TSql,
+}
+
+impl SqlDialect {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "generic" | "ansi" => Some(SqlDialect::Generic),
+            "postgres" | "postgresql" => Some(SqlDialect::Postgres),
+            "mysql" | "mariadb" => Some(SqlDialect::MySql),
+            "sqlite" => Some(SqlDialect::Sqlite),
+            "tsql" | "mssql" | "sqlserver" => Some(SqlDialect::TSql),
+            _ => None,
+        }
+    }
+
+    /// Guess the dialect from syntax only one dialect uses
+    pub fn detect(source: &str) -> Self {
+        let upper = source.to_ascii_uppercase();
+        if upper.lines().any(|l| l.trim() == "GO") || source.contains("[dbo]") {
+            SqlDialect::TSql
+        } else if source.contains('`') || upper.contains("DELIMITER ") || upper.contains("ENGINE=") {
+            SqlDialect::MySql
+        } else if source.contains("$$") || upper.contains("SERIAL") || upper.contains("JSONB") {
+            SqlDialect::Postgres
+        } else if upper.contains("AUTOINCREMENT") || upper.contains("PRAGMA ") {
+            SqlDialect::Sqlite
+        } else {
+            SqlDialect::Generic
+        }
+    }
+}
+
+/// A top-level statement with its byte span in the original source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub text: String,
+}
+
+/// Rewrite dialect-specific syntax into the generic form the tree-sitter
+/// grammar accepts, without changing any byte offsets.
+///
+/// MySQL backtick and T-SQL bracket identifiers become double-quoted, T-SQL
+/// `GO` batch separators and MySQL custom `DELIMITER`s become semicolons, and
+/// `DELIMITER` directives are blanked out.
+pub fn normalize_for_grammar(source: &str, dialect: SqlDialect) -> String {
+    let mut out = source.as_bytes().to_vec();
+
+    match dialect {
+        SqlDialect::MySql => {
+            for b in out.iter_mut() {
+                if *b == b'`' {
+                    *b = b'"';
+                }
+            }
+            let mut offset = 0;
+            let mut delimiter: Option<String> = None;
+            for line in source.split_inclusive('\n') {
+                let trimmed = line.trim();
+                if let Some(rest) = strip_keyword(trimmed, "DELIMITER") {
+                    let rest = rest.trim();
+                    delimiter = if rest == ";" { None } else { Some(rest.to_string()) };
+                    blank(&mut out[offset..offset + line.trim_end_matches(['\r', '\n']).len()]);
+                } else if let Some(delim) = &delimiter {
+                    let mut search = 0;
+                    while let Some(pos) = line[search..].find(delim.as_str()) {
+                        let at = offset + search + pos;
+                        out[at] = b';';
+                        blank(&mut out[at + 1..at + delim.len()]);
+                        search += pos + delim.len();
+                    }
+                }
+                offset += line.len();
+            }
+        }
+        SqlDialect::TSql => {
+            let mut depth = 0usize;
+            for b in out.iter_mut() {
+                match *b {
+                    b'[' => {
+                        depth += 1;
+                        *b = b'"';
+                    }
+                    b']' if depth > 0 => {
+                        depth -= 1;
+                        *b = b'"';
+                    }
+                    _ => {}
+                }
+            }
+            let mut offset = 0;
+            for line in source.split_inclusive('\n') {
+                let trimmed = line.trim();
+                if trimmed.eq_ignore_ascii_case("GO") {
+                    let at = offset + line.find(trimmed).unwrap_or(0);
+                    out[at] = b';';
+                    out[at + 1] = b' ';
+                }
+                offset += line.len();
+            }
+        }
+        SqlDialect::Generic | SqlDialect::Postgres | SqlDialect::Sqlite => {}
+    }
+
+    // Only ASCII bytes were substituted for ASCII bytes, so this stays UTF-8
+    String::from_utf8(out).unwrap_or_else(|_| source.to_string())
+}
+
+fn blank(bytes: &mut [u8]) {
+    for b in bytes {
+        *b = b' ';
+    }
+}
+
+/// Strip a leading case-insensitive keyword followed by whitespace or end of input
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = text.get(..keyword.len())?;
+    let rest = &text[keyword.len()..];
+    if head.eq_ignore_ascii_case(keyword) && rest.chars().next().is_none_or(char::is_whitespace) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// Split a script into top-level statements.
+///
+/// Respects quoted strings and identifiers, comments, Postgres dollar quoting,
+/// `BEGIN ... END` bodies of triggers and procedures, MySQL `DELIMITER`
+/// directives and T-SQL `GO` separators. Statement text excludes the
+/// terminator; empty statements are dropped.
+pub fn split_statements(source: &str, dialect: SqlDialect) -> Vec<SqlStatement> {
+    let normalized = normalize_for_grammar(source, dialect);
+    let bytes = normalized.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    let mut block_depth = 0usize;
+
+    // Trim against the normalized text so blanked directives are excluded,
+    // but report the original text
+    let mut push = |from: usize, to: usize| {
+        let raw = &normalized[from..to];
+        let trimmed = raw.trim();
+        if !trimmed.is_empty() && !is_comment_only(trimmed) {
+            let start_byte = from + (raw.len() - raw.trim_start().len());
+            let end_byte = start_byte + trimmed.len();
+            statements.push(SqlStatement {
+                start_byte,
+                end_byte,
+                text: source[start_byte..end_byte].to_string(),
+            });
+        }
+    };
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => i = skip_quoted(bytes, i, bytes[i]),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| i + p);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = normalized[i + 2..].find("*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+            }
+            b'$' if dialect != SqlDialect::TSql => match dollar_tag(&normalized[i..]) {
+                Some(tag) => {
+                    let body = i + tag.len();
+                    i = normalized[body..].find(tag).map_or(bytes.len(), |p| body + p + tag.len());
+                }
+                None => i += 1,
+            },
+            b';' if block_depth == 0 => {
+                push(start, i);
+                i += 1;
+                start = i;
+            }
+            b if b.is_ascii_alphabetic() && (i == 0 || !is_word_byte(bytes[i - 1])) => {
+                let end = i + bytes[i..].iter().take_while(|b| is_word_byte(**b)).count();
+                let word = &normalized[i..end];
+                if word.eq_ignore_ascii_case("BEGIN") || word.eq_ignore_ascii_case("CASE") {
+                    if opens_block(&normalized[start..i]) {
+                        block_depth += 1;
+                    }
+                } else if word.eq_ignore_ascii_case("END") && block_depth > 0 {
+                    block_depth -= 1;
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    push(start, bytes.len());
+
+    statements
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn is_comment_only(text: &str) -> bool {
+    text.lines().all(|l| {
+        let l = l.trim();
+        l.is_empty() || l.starts_with("--")
+    }) || (text.starts_with("/*") && text.ends_with("*/"))
+}
+
+fn skip_quoted(bytes: &[u8], open: usize, quote: u8) -> usize {
+    let mut i = open + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            // Doubled quotes are escapes
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Return the `$tag$` opening a dollar-quoted string at the start of `text`
+fn dollar_tag(text: &str) -> Option<&str> {
+    let close = text[1..].find('$')? + 1;
+    let tag = &text[..=close];
+    tag[1..close]
+        .bytes()
+        .all(is_word_byte)
+        .then_some(tag)
+}
+
+/// Whether a `BEGIN`/`CASE` keyword opens a compound block whose inner
+/// semicolons must not end the statement. Plain `BEGIN;` transactions don't.
+fn opens_block(statement_prefix: &str) -> bool {
+    let upper = statement_prefix.trim_start().to_ascii_uppercase();
+    upper.starts_with("CREATE")
+        && ["TRIGGER", "PROCEDURE", "PROC ", "FUNCTION"]
+            .iter()
+            .any(|kw| upper.contains(kw))
+}
+
+/// Kinds of statement that carry a named schema object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Table,
+    View,
+    Function,
+    Procedure,
+    Index,
+    Trigger,
+    Other,
+}
+
+impl StatementKind {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            StatementKind::Table => "TABLE",
+            StatementKind::View => "VIEW",
+            StatementKind::Function => "FUNCTION",
+            StatementKind::Procedure => "PROCEDURE",
+            StatementKind::Index => "INDEX",
+            StatementKind::Trigger => "TRIGGER",
+            StatementKind::Other => "",
+        }
+    }
+}
+
+/// The verb, object kind and object name of a DDL statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementInfo {
+    pub verb: String,
+    pub kind: StatementKind,
+    pub name: Option<String>,
+}
+
+/// Classify a statement and extract the name of the object it creates, alters or drops
+pub fn classify_statement(text: &str) -> StatementInfo {
+    let words = leading_words(text, 12);
+    let upper: Vec<String> = words.iter().map(|w| w.to_ascii_uppercase()).collect();
+    let verb = upper.first().cloned().unwrap_or_default();
+    let other = |verb: String| StatementInfo { verb, kind: StatementKind::Other, name: None };
+
+    if !matches!(verb.as_str(), "CREATE" | "ALTER" | "DROP") {
+        return other(verb);
+    }
+
+    // Skip modifiers between the verb and the object keyword
+    let modifiers = [
+        "OR", "REPLACE", "ALTER", "TEMP", "TEMPORARY", "UNIQUE", "CLUSTERED", "NONCLUSTERED",
+        "MATERIALIZED", "CONSTRAINT", "RECURSIVE", "UNLOGGED", "VIRTUAL",
+    ];
+    let mut idx = 1;
+    while idx < upper.len() && (modifiers.contains(&upper[idx].as_str()) || upper[idx].starts_with("DEFINER=")) {
+        idx += 1;
+    }
+
+    let kind = match upper.get(idx).map(String::as_str) {
+        Some("TABLE") => StatementKind::Table,
+        Some("VIEW") => StatementKind::View,
+        Some("FUNCTION") => StatementKind::Function,
+        Some("PROCEDURE") | Some("PROC") => StatementKind::Procedure,
+        Some("INDEX") => StatementKind::Index,
+        Some("TRIGGER") => StatementKind::Trigger,
+        _ => return other(verb),
+    };
+    idx += 1;
+
+    // Skip options between the object keyword and its name
+    let options = ["IF", "NOT", "EXISTS", "CONCURRENTLY", "ONLY"];
+    while idx < upper.len() && options.contains(&upper[idx].as_str()) {
+        idx += 1;
+    }
+
+    StatementInfo {
+        verb,
+        kind,
+        name: words.get(idx).map(|w| unquote_identifier(w)),
+    }
+}
+
+/// Split the first `limit` whitespace-separated words, cutting names at `(`
+fn leading_words(text: &str, limit: usize) -> Vec<String> {
+    let mut words = Vec::new();
+    for line in text.lines() {
+        let line = line.split("--").next().unwrap_or("");
+        for word in line.split_whitespace() {
+            let word = word.split('(').next().unwrap_or("");
+            if !word.is_empty() {
+                words.push(word.to_string());
+            }
+            if words.len() >= limit {
+                return words;
+            }
+        }
+    }
+    words
+}
+
+/// Strip dialect quoting from a (possibly schema-qualified) identifier
+pub fn unquote_identifier(identifier: &str) -> String {
+    identifier
+        .trim_end_matches([';', ','])
+        .split('.')
+        .map(|part| part.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']')))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Whether a path looks like a schema migration rather than a plain script
+pub fn is_migration_path(file_path: &str) -> bool {
+    let path = std::path::Path::new(file_path);
+    let in_migration_dir = path.components().any(|c| {
+        let part = c.as_os_str().to_string_lossy().to_ascii_lowercase();
+        matches!(part.as_str(), "migrations" | "migration" | "migrate" | "flyway")
+    });
+    let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    // Flyway (V1__init.sql, U1__undo.sql, R__views.sql) and up/down pairs
+    let flyway = file_name.contains("__")
+        && matches!(file_name.chars().next(), Some('V' | 'U' | 'R'));
+    let up_down = file_name.ends_with(".up.sql") || file_name.ends_with(".down.sql");
+
+    in_migration_dir || flyway || up_down
+}
+
+/// Extract units for `CREATE` statements found by statement splitting.
+///
+/// Tables and views become "class" units and functions and procedures
+/// "function" units, mirroring the tree-sitter queries (which callers prefer
+/// when both find a statement); indexes and triggers, which the grammar doesn't
+/// model, become "index" and "trigger" units. In migration files every
+/// statement also becomes a `migration_statement` unit, so each schema change
+/// is addressable.
+pub fn extract_statement_units(
+    file_path: &str,
+    source: &str,
+    dialect: SqlDialect,
+) -> Vec<SemanticUnit> {
+    let migration = is_migration_path(file_path);
+    let mut units = Vec::new();
+
+    for statement in split_statements(source, dialect) {
+        let info = classify_statement(&statement.text);
+        let unit_type = match (info.verb.as_str(), info.kind) {
+            ("CREATE", StatementKind::Table | StatementKind::View) => Some("class"),
+            ("CREATE", StatementKind::Function | StatementKind::Procedure) => Some("function"),
+            ("CREATE", StatementKind::Index) => Some("index"),
+            ("CREATE", StatementKind::Trigger) => Some("trigger"),
+            _ => None,
+        };
+
+        if let (Some(unit_type), Some(name)) = (unit_type, &info.name) {
+            units.push(statement_unit(source, &statement, unit_type, name));
+        }
+        if migration {
+            units.push(statement_unit(source, &statement, "migration_statement", &statement_summary(&statement.text)));
+        }
+    }
+
+    units
+}
+
+/// Short human-readable label such as `CREATE TABLE users` or `INSERT INTO orders`
+pub fn statement_summary(text: &str) -> String {
+    let info = classify_statement(text);
+    if let Some(name) = info.name {
+        return format!("{} {} {}", info.verb, info.kind.keyword(), name);
+    }
+
+    let words = leading_words(text, 3);
+    let take = match info.verb.as_str() {
+        "INSERT" | "DELETE" => 3, // INSERT INTO <table>, DELETE FROM <table>
+        _ => 2,
+    };
+    words
+        .iter()
+        .take(take)
+        .enumerate()
+        .map(|(i, w)| if i == 0 { w.to_ascii_uppercase() } else { unquote_identifier(w) })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn statement_unit(source: &str, statement: &SqlStatement, unit_type: &str, name: &str) -> SemanticUnit {
+    let start_line = source[..statement.start_byte].matches('\n').count() + 1;
+    let end_line = start_line + statement.text.matches('\n').count();
+
+    SemanticUnit {
+        unit_type: unit_type.into(),
+        name: name.to_string(),
+        parent_name: None,
+        qualified_name: name.to_string(),
+        start_line,
+        end_line,
+        start_byte: statement.start_byte,
+        end_byte: statement.end_byte,
+        signature: statement.text.lines().next().unwrap_or("").trim().to_string(),
+        parameters: None,
+        docstring: None,
+        decorators: Vec::new(),
+        content: statement.text.clone(),
+        content_ref: None,
+        content_hash: String::new(),
+        unit_id: String::new(),
+        language: "Sql".into(),
+        metadata: HashMap::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str, dialect: SqlDialect) -> Vec<String> {
+        split_statements(source, dialect).into_iter().map(|s| s.text).collect()
+    }
+
+    #[test]
+    fn test_split_respects_strings_and_comments() {
+        let source = "INSERT INTO t VALUES ('a;b');\n-- comment; here\nSELECT 1 /* ; */;\n";
+        assert_eq!(
+            texts(source, SqlDialect::Generic),
+            vec!["INSERT INTO t VALUES ('a;b')", "-- comment; here\nSELECT 1 /* ; */"]
+        );
+    }
+
+    #[test]
+    fn test_split_postgres_dollar_quoting() {
+        let source = "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql;\nSELECT 2;";
+        let statements = texts(source, SqlDialect::Postgres);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("LANGUAGE plpgsql"));
+    }
+
+    #[test]
+    fn test_split_mysql_delimiter_blocks() {
+        let source = "DELIMITER $$\nCREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END$$\nDELIMITER ;\nSELECT 3;\n";
+        assert_eq!(
+            texts(source, SqlDialect::MySql),
+            vec!["CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END", "SELECT 3"]
+        );
+    }
+
+    #[test]
+    fn test_split_tsql_go_batches() {
+        let source = "CREATE TABLE [t] ([id] INT)\nGO\nSELECT 1\ngo\n";
+        let statements = split_statements(source, SqlDialect::TSql);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(&source[statements[1].start_byte..statements[1].end_byte], "SELECT 1");
+    }
+
+    #[test]
+    fn test_transaction_begin_does_not_open_block() {
+        let source = "BEGIN;\nUPDATE t SET x = 1;\nCOMMIT;";
+        assert_eq!(texts(source, SqlDialect::Sqlite), vec!["BEGIN", "UPDATE t SET x = 1", "COMMIT"]);
+    }
+
+    #[test]
+    fn test_normalization_preserves_offsets() {
+        for (source, dialect) in [
+            ("CREATE TABLE `a` (`id` INT);", SqlDialect::MySql),
+            ("CREATE TABLE [dbo].[a] ([id] INT)\nGO\n", SqlDialect::TSql),
+            ("DELIMITER //\nSELECT 1//\nDELIMITER ;\n", SqlDialect::MySql),
+        ] {
+            assert_eq!(normalize_for_grammar(source, dialect).len(), source.len());
+        }
+    }
+
+    #[test]
+    fn test_classify_statement_names() {
+        let cases = [
+            ("CREATE OR REPLACE VIEW active_users AS SELECT 1", StatementKind::View, "active_users"),
+            ("CREATE UNIQUE INDEX IF NOT EXISTS idx_email ON users (email)", StatementKind::Index, "idx_email"),
+            ("CREATE TABLE IF NOT EXISTS `shop`.`orders` (id INT)", StatementKind::Table, "shop.orders"),
+            ("CREATE DEFINER=`root`@`%` TRIGGER audit AFTER INSERT ON t", StatementKind::Trigger, "audit"),
+            ("CREATE OR ALTER PROC [dbo].[Sync] AS SELECT 1", StatementKind::Procedure, "dbo.Sync"),
+        ];
+        for (text, kind, name) in cases {
+            let info = classify_statement(text);
+            assert_eq!(info.kind, kind, "{}", text);
+            assert_eq!(info.name.as_deref(), Some(name), "{}", text);
+        }
+        assert_eq!(classify_statement("SELECT * FROM t").kind, StatementKind::Other);
+    }
+
+    #[test]
+    fn test_detect_dialect() {
+        assert_eq!(SqlDialect::detect("CREATE TABLE `a` (id INT)"), SqlDialect::MySql);
+        assert_eq!(SqlDialect::detect("CREATE TABLE a (id SERIAL)"), SqlDialect::Postgres);
+        assert_eq!(SqlDialect::detect("SELECT 1\nGO\n"), SqlDialect::TSql);
+        assert_eq!(SqlDialect::detect("CREATE TABLE a (id INTEGER PRIMARY KEY AUTOINCREMENT)"), SqlDialect::Sqlite);
+        assert_eq!(SqlDialect::detect("SELECT 1"), SqlDialect::Generic);
+    }
+
+    #[test]
+    fn test_migration_paths() {
+        assert!(is_migration_path("db/migrations/001_init.sql"));
+        assert!(is_migration_path("sql/V2__add_users.sql"));
+        assert!(is_migration_path("schema/20240101_users.up.sql"));
+        assert!(!is_migration_path("sql/schema.sql"));
+    }
+}
@@ -0,0 +1,711 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::code_intel::{parse_dump, Occurrences};
+use crate::ctags::{parse_tags, tag_units, Tag};
+use crate::lsp::{LspClient, LspRequest, SymbolLocation};
+use crate::parsing::{line_starts, parse_any_file, parse_files_ordered, ParseOptions, ParseResult, SemanticUnit};
+
+/// Units of one indexed file, plus the line table needed to turn editor
+/// positions into byte offsets without keeping the source around
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub language: String,
+    pub units: Vec<SemanticUnit>,
+    /// Byte offset at which each line starts (`line_starts[0] == 0`)
+    pub line_starts: Vec<usize>,
+    pub len: usize,
+}
+
+impl IndexedFile {
+    pub fn new(source: &str, result: ParseResult) -> Self {
+        Self { language: result.language, units: result.units, line_starts: line_starts(source), len: source.len() }
+    }
+
+    /// Byte offset of a 1-based line and 0-based byte column, clamped to the
+    /// line (and file) end
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map(|next| next - 1).unwrap_or(self.len);
+        Some((start + column).min(end))
+    }
+
+    /// Units containing `line`, outermost first
+    pub fn enclosing(&self, line: usize) -> Vec<&SemanticUnit> {
+        let mut units: Vec<&SemanticUnit> = self
+            .units
+            .iter()
+            .filter(|u| u.start_line <= line && line <= u.end_line)
+            .collect();
+        units.sort_by_key(|u| (u.start_byte, std::cmp::Reverse(u.end_byte)));
+        units
+    }
+
+    /// Innermost unit containing the byte offset
+    pub fn unit_at(&self, offset: usize) -> Option<&SemanticUnit> {
+        self.units
+            .iter()
+            .filter(|u| u.start_byte <= offset && offset < u.end_byte.max(u.start_byte + 1))
+            .min_by_key(|u| u.end_byte - u.start_byte)
+    }
+
+    /// 1-based line and 0-based byte column of a byte offset
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset).max(1);
+        (line, offset - self.line_starts[line - 1])
+    }
+
+    /// Identifier at (or just before) a byte offset, read from the innermost
+    /// unit's content
+    pub fn word_at(&self, offset: usize) -> Option<&str> {
+        let unit = self.unit_at(offset)?;
+        let bytes = unit.content.as_bytes();
+        let at = offset.checked_sub(unit.start_byte)?.min(bytes.len());
+        let at = if bytes.get(at).copied().is_some_and(is_identifier_byte) { at + 1 } else { at };
+        let start = at - bytes[..at].iter().rev().take_while(|&&b| is_identifier_byte(b)).count();
+        let end = at + bytes[at..].iter().take_while(|&&b| is_identifier_byte(b)).count();
+        let word = &unit.content[start..end];
+        word.starts_with(|c: char| !c.is_ascii_digit()).then_some(word)
+    }
+}
+
+/// Bytes of identifiers; non-ASCII bytes count so words never split a character
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+/// Byte offsets of whole-word occurrences of `word` in `text`
+fn occurrences<'a>(text: &'a str, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let bytes = text.as_bytes();
+    text.match_indices(word).map(|(i, _)| i).filter(move |&i| {
+        let before = i.checked_sub(1).map(|j| bytes[j]);
+        let after = bytes.get(i + word.len()).copied();
+        !before.is_some_and(is_identifier_byte) && !after.is_some_and(is_identifier_byte)
+    })
+}
+
+/// Longest signature kept in an outline, in characters
+const MAX_OUTLINE_SIGNATURE: usize = 120;
+
+/// A unit in a file outline, with the units nested inside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct OutlineNode {
+    #[pyo3(get)]
+    pub unit_type: String,
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub signature: String, // Single line, whitespace collapsed, body opener dropped
+    #[pyo3(get)]
+    pub start_line: usize,
+    #[pyo3(get)]
+    pub end_line: usize,
+    #[pyo3(get)]
+    pub children: Vec<OutlineNode>,
+}
+
+#[pymethods]
+impl OutlineNode {
+    /// Indented text form of this node and its descendants, one line per unit
+    #[pyo3(signature = (indent=0))]
+    pub fn render(&self, indent: usize) -> String {
+        let mut out = format!("{}{}\n", "  ".repeat(indent), self.signature);
+        for child in &self.children {
+            out.push_str(&child.render(indent + 1));
+        }
+        out
+    }
+
+    fn __repr__(&self) -> String {
+        format!("OutlineNode(type={}, name={}, children={})", self.unit_type, self.name, self.children.len())
+    }
+}
+
+/// Signature compacted for an outline: whitespace runs collapsed, a trailing
+/// `{` or `:` dropped, and long signatures truncated
+fn compact_signature(signature: &str) -> String {
+    let collapsed = signature.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = collapsed.trim_end_matches('{').trim_end_matches(':').trim_end();
+    if trimmed.chars().count() > MAX_OUTLINE_SIGNATURE {
+        let cut: String = trimmed.chars().take(MAX_OUTLINE_SIGNATURE - 1).collect();
+        format!("{}…", cut)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Nest units by byte containment: a unit's parent is the innermost unit
+/// whose span contains it. Siblings keep source order.
+pub fn build_outline(units: &[SemanticUnit]) -> Vec<OutlineNode> {
+    let mut sorted: Vec<&SemanticUnit> = units.iter().collect();
+    sorted.sort_by_key(|u| (u.start_byte, std::cmp::Reverse(u.end_byte)));
+
+    // Open ancestors, innermost last, each with its end byte
+    let mut stack: Vec<(usize, OutlineNode)> = Vec::new();
+    let mut roots = Vec::new();
+    let close = |stack: &mut Vec<(usize, OutlineNode)>, roots: &mut Vec<OutlineNode>| {
+        let (_, node) = stack.pop().expect("stack is non-empty");
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    };
+
+    for unit in sorted {
+        while stack.last().is_some_and(|(end, _)| unit.end_byte > *end || unit.start_byte >= *end) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((
+            unit.end_byte,
+            OutlineNode {
+                unit_type: unit.unit_type.to_string(),
+                name: unit.name.clone(),
+                signature: compact_signature(&unit.signature),
+                start_line: unit.start_line,
+                end_line: unit.end_line,
+                children: Vec::new(),
+            },
+        ));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// In-memory symbol index over parsed files, persisted as JSON
+///
+/// Files are keyed by the path they were indexed under; re-indexing a path
+/// replaces its units. Lookups answer editor questions ("which function is
+/// at this cursor") without re-parsing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[pyclass]
+pub struct SymbolIndex {
+    pub files: HashMap<String, IndexedFile>,
+    /// Language server answers by request, file, line and column; dropped
+    /// for any file they mention once it is re-indexed
+    #[serde(skip)]
+    precise: HashMap<PreciseKey, Vec<SymbolLocation>>,
+    /// Occurrences imported from SCIP/LSIF dumps by file; dropped for a
+    /// file once it is re-indexed
+    #[serde(default)]
+    code_intel: Occurrences,
+}
+
+type PreciseKey = (LspRequest, String, usize, usize);
+
+impl SymbolIndex {
+    fn file(&self, path: &str) -> PyResult<&IndexedFile> {
+        self.files
+            .get(path)
+            .ok_or_else(|| pyo3::exceptions::PyKeyError::new_err(format!("File not indexed: {}", path)))
+    }
+
+    /// Write the index next to `path` and rename it into place, so a crash
+    /// mid-write leaves the previous index intact
+    pub fn save_to(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        let json = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        let mut file = std::fs::File::create(&tmp_path).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        file.write_all(&json).and_then(|_| file.sync_all()).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        std::fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+    }
+
+    pub fn load_from(path: &str) -> Result<Self, String> {
+        let json = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid symbol index {}: {}", path, e))
+    }
+
+    /// Index the tags of a universal-ctags file, replacing the units of each
+    /// tagged file. Tag paths are read relative to `root`. Returns the
+    /// number of units imported and the tagged paths that couldn't be read.
+    pub fn import_tags(&mut self, text: &str, root: &Path) -> (usize, Vec<String>) {
+        let tags = parse_tags(text);
+        let mut by_path: HashMap<&str, Vec<&Tag>> = HashMap::new();
+        for tag in &tags {
+            by_path.entry(tag.path.strip_prefix("./").unwrap_or(&tag.path)).or_default().push(tag);
+        }
+        let mut paths: Vec<&str> = by_path.keys().copied().collect();
+        paths.sort();
+        let sources: Vec<Option<String>> =
+            paths.par_iter().map(|path| std::fs::read_to_string(root.join(path)).ok()).collect();
+
+        let (mut imported, mut unreadable) = (0, Vec::new());
+        for (path, source) in paths.into_iter().zip(sources) {
+            let Some(source) = source else {
+                unreadable.push(path.to_string());
+                continue;
+            };
+            let starts = line_starts(&source);
+            let units = tag_units(&by_path[path], &source, &starts);
+            let language = units.first().map_or_else(|| "unknown".to_string(), |u| u.language.to_string());
+            imported += units.len();
+            self.forget_precise(path);
+            let file = IndexedFile { language, units, line_starts: starts, len: source.len() };
+            self.files.insert(path.to_string(), file);
+        }
+        (imported, unreadable)
+    }
+
+    /// Drop language server answers asked in or pointing into `path`, and
+    /// the dump occurrences recorded in it
+    fn forget_precise(&mut self, path: &str) {
+        self.precise
+            .retain(|(_, file, _, _), locations| file != path && locations.iter().all(|l| l.path != path));
+        self.code_intel.remove(path);
+    }
+
+    /// Keep the occurrences of a SCIP/LSIF dump, replacing those of every
+    /// file it covers; returns how many were imported
+    pub fn import_occurrences(&mut self, occurrences: Occurrences) -> usize {
+        let count = occurrences.values().map(Vec::len).sum();
+        self.code_intel.extend(occurrences);
+        count
+    }
+
+    /// Answer `request` from imported dumps: the innermost occurrence at the
+    /// position, then every occurrence of its symbol (definitions only for
+    /// `Definition`). None when no occurrence is there or, for definitions,
+    /// the symbol is defined outside the dump.
+    pub fn dump_matches(
+        &self,
+        request: LspRequest,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) -> Option<Vec<SymbolLocation>> {
+        let symbol = &self.code_intel.get(path)?.iter().rfind(|o| o.contains(line, column))?.symbol;
+        let mut locations: Vec<SymbolLocation> = self
+            .code_intel
+            .iter()
+            .flat_map(|(path, found)| found.iter().map(move |o| (path, o)))
+            .filter(|(_, o)| &o.symbol == symbol && (o.definition || request == LspRequest::References))
+            .map(|(path, o)| o.location(path))
+            .collect();
+        locations.sort();
+        (!locations.is_empty()).then_some(locations)
+    }
+
+    /// `(reference, definition)` pairs of every imported occurrence whose
+    /// symbol is defined in the dumps, sorted
+    pub fn dump_edges(&self) -> Vec<(SymbolLocation, SymbolLocation)> {
+        let mut definitions: HashMap<&str, Vec<SymbolLocation>> = HashMap::new();
+        for (path, found) in &self.code_intel {
+            for occurrence in found.iter().filter(|o| o.definition) {
+                definitions.entry(occurrence.symbol.as_str()).or_default().push(occurrence.location(path));
+            }
+        }
+        let mut edges: Vec<(SymbolLocation, SymbolLocation)> = self
+            .code_intel
+            .iter()
+            .flat_map(|(path, found)| found.iter().map(move |o| (path, o)))
+            .filter(|(_, o)| !o.definition)
+            .flat_map(|(path, o)| {
+                let targets = definitions.get(o.symbol.as_str()).map(Vec::as_slice).unwrap_or_default();
+                targets.iter().map(move |target| (o.location(path), target.clone()))
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// Tree-sitter fallback: units named `word` for definitions, whole-word
+    /// occurrences of `word` in any unit for references
+    pub fn name_matches(&self, request: LspRequest, word: &str) -> Vec<SymbolLocation> {
+        let mut locations = BTreeSet::new();
+        for (path, file) in &self.files {
+            for unit in &file.units {
+                let named = unit.name == word || unit.name.rsplit(['.', ':']).next() == Some(word);
+                if request == LspRequest::Definition && !(named && unit.unit_type != "import") {
+                    continue;
+                }
+                let mut found = occurrences(&unit.content, word).peekable();
+                if request == LspRequest::Definition && found.peek().is_none() {
+                    locations.insert(location(path, file, unit.start_byte, 0));
+                }
+                for offset in found {
+                    locations.insert(location(path, file, unit.start_byte + offset, word.len()));
+                    if request == LspRequest::Definition {
+                        break;
+                    }
+                }
+            }
+        }
+        locations.into_iter().collect()
+    }
+
+    /// Answer `request` for the symbol at a position: from the language
+    /// server when one is given and answers, else from its last answer for
+    /// the position, else from imported dumps, else by name
+    pub fn locate(
+        &mut self,
+        request: LspRequest,
+        path: &str,
+        line: usize,
+        column: usize,
+        client: Option<&mut LspClient>,
+    ) -> Result<Vec<SymbolLocation>, String> {
+        let file = self.files.get(path).ok_or_else(|| format!("File not indexed: {}", path))?;
+        let word = file.offset(line, column).and_then(|offset| file.word_at(offset)).map(str::to_string);
+        let key = (request, path.to_string(), line, column);
+        if let Some(Ok(locations)) = client.map(|client| client.locations(request, path, line, column)) {
+            self.precise.insert(key, locations.clone());
+            return Ok(locations);
+        }
+        if let Some(locations) = self.precise.get(&key) {
+            return Ok(locations.clone());
+        }
+        if let Some(locations) = self.dump_matches(request, path, line, column) {
+            return Ok(locations);
+        }
+        Ok(word.map(|word| self.name_matches(request, &word)).unwrap_or_default())
+    }
+}
+
+fn location(path: &str, file: &IndexedFile, offset: usize, length: usize) -> SymbolLocation {
+    let (line, column) = file.position(offset);
+    let (end_line, end_column) = file.position(offset + length);
+    SymbolLocation { path: path.to_string(), line, column, end_line, end_column, precise: false }
+}
+
+#[pymethods]
+impl SymbolIndex {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse and index one file, returning the number of units indexed
+    fn index_file(&mut self, file_path: String, source_code: String) -> PyResult<usize> {
+        let result = parse_any_file(&file_path, &source_code, &ParseOptions::default())
+            .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+        let count = result.units.len();
+        self.forget_precise(&file_path);
+        self.files.insert(file_path, IndexedFile::new(&source_code, result));
+        Ok(count)
+    }
+
+    /// Parse and index files in parallel. Files that fail to parse are
+    /// skipped; their paths are returned.
+    fn index_files(&mut self, files: Vec<(String, String)>) -> Vec<String> {
+        let results = parse_files_ordered(&files, &ParseOptions::default());
+        let mut failed = Vec::new();
+        for ((path, source), result) in files.into_iter().zip(results) {
+            match result {
+                Ok(result) => {
+                    self.forget_precise(&path);
+                    self.files.insert(path, IndexedFile::new(&source, result));
+                }
+                Err(_) => failed.push(path),
+            }
+        }
+        failed
+    }
+
+    /// Drop a file from the index; returns whether it was indexed
+    fn remove_file(&mut self, file_path: &str) -> bool {
+        self.forget_precise(file_path);
+        self.files.remove(file_path).is_some()
+    }
+
+    /// Indexed file paths, sorted
+    fn indexed_files(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.files.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Innermost unit at a 1-based line and 0-based (UTF-8 byte) column, or
+    /// None outside any unit. Raises KeyError for a file that isn't indexed.
+    fn unit_at_position(&self, file_path: &str, line: usize, column: usize) -> PyResult<Option<SemanticUnit>> {
+        let file = self.file(file_path)?;
+        Ok(file.offset(line, column).and_then(|offset| file.unit_at(offset)).cloned())
+    }
+
+    /// All units containing a 1-based line, outermost first (e.g. class, then
+    /// method). Raises KeyError for a file that isn't indexed.
+    fn enclosing_units(&self, file_path: &str, line: usize) -> PyResult<Vec<SemanticUnit>> {
+        Ok(self.file(file_path)?.enclosing(line).into_iter().cloned().collect())
+    }
+
+    /// Nested outline of a file: classes contain their methods, modules their
+    /// functions. Raises KeyError for a file that isn't indexed.
+    fn file_outline(&self, file_path: &str) -> PyResult<Vec<OutlineNode>> {
+        Ok(build_outline(&self.file(file_path)?.units))
+    }
+
+    /// Bootstrap the index from a universal-ctags file (classic or
+    /// `--output-format=json`) instead of parsing. Each tagged file's units
+    /// are replaced by its tags, spanning whole lines, or to the `end` line
+    /// when tags carry `--fields=+ne`. Tag paths are read relative to `root`
+    /// (default the tags file's directory) and indexed as written. Returns
+    /// (units imported, tagged paths that couldn't be read). Raises IOError
+    /// if the tags file can't be read.
+    #[pyo3(signature = (tags_file, root=None))]
+    fn import_ctags(&mut self, py: Python<'_>, tags_file: &str, root: Option<&str>) -> PyResult<(usize, Vec<String>)> {
+        let text = std::fs::read_to_string(tags_file)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Failed to read {}: {}", tags_file, e)))?;
+        let root = match root {
+            Some(root) => PathBuf::from(root),
+            None => Path::new(tags_file).parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        Ok(py.detach(|| self.import_tags(&text, &root)))
+    }
+
+    /// Where the symbol at a 1-based line and 0-based (UTF-8 byte) column is
+    /// defined. With a `client`, the language server answers and its answer
+    /// is kept for the position until a file it mentions is re-indexed.
+    /// Without one, or if the server fails, a kept answer is reused, else
+    /// imported SCIP/LSIF occurrences, else units named like the symbol are
+    /// returned; `precise` tells them apart. Raises KeyError for a file that
+    /// isn't indexed.
+    #[pyo3(signature = (file_path, line, column, client=None))]
+    fn find_definitions(
+        &mut self,
+        py: Python<'_>,
+        file_path: &str,
+        line: usize,
+        column: usize,
+        mut client: Option<PyRefMut<'_, LspClient>>,
+    ) -> PyResult<Vec<SymbolLocation>> {
+        self.file(file_path)?;
+        let client = client.as_deref_mut();
+        py.detach(|| self.locate(LspRequest::Definition, file_path, line, column, client))
+            .map_err(pyo3::exceptions::PyKeyError::new_err)
+    }
+
+    /// Every use of the symbol at a position, declaration included, resolved
+    /// like `find_definitions`. The fallback matches the name as a whole word
+    /// in all indexed units, so it may include unrelated namesakes. Raises
+    /// KeyError for a file that isn't indexed.
+    #[pyo3(signature = (file_path, line, column, client=None))]
+    fn find_references(
+        &mut self,
+        py: Python<'_>,
+        file_path: &str,
+        line: usize,
+        column: usize,
+        mut client: Option<PyRefMut<'_, LspClient>>,
+    ) -> PyResult<Vec<SymbolLocation>> {
+        self.file(file_path)?;
+        let client = client.as_deref_mut();
+        py.detach(|| self.locate(LspRequest::References, file_path, line, column, client))
+            .map_err(pyo3::exceptions::PyKeyError::new_err)
+    }
+
+    /// Import compiler-accurate cross-references from a SCIP index
+    /// (`index.scip`) or an LSIF dump (JSON lines or array), detected from
+    /// the content. Paths are taken relative to the dump's project root and
+    /// should match the indexed paths. Each covered file's occurrences
+    /// replace earlier ones and are dropped when the file is re-indexed.
+    /// Returns the number of occurrences imported. Raises IOError if the
+    /// file can't be read and ValueError if it isn't a valid dump.
+    fn import_code_intel(&mut self, py: Python<'_>, dump_file: &str) -> PyResult<usize> {
+        let bytes = std::fs::read(dump_file)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Failed to read {}: {}", dump_file, e)))?;
+        let occurrences = py.detach(|| parse_dump(&bytes)).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(self.import_occurrences(occurrences))
+    }
+
+    /// Precise (reference, definition) edges from imported SCIP/LSIF
+    /// occurrences, for loading into the code graph. A reference to a
+    /// symbol defined outside the dumps has no edge.
+    fn precise_edges(&self, py: Python<'_>) -> Vec<(SymbolLocation, SymbolLocation)> {
+        py.detach(|| self.dump_edges())
+    }
+
+    /// Persist the index as JSON, atomically replacing `path`
+    fn save(&self, path: &str) -> PyResult<()> {
+        self.save_to(path).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    /// Load an index written by `save`
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        Self::load_from(path).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.files.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let units: usize = self.files.values().map(|f| f.units.len()).sum();
+        format!("SymbolIndex(files={}, units={})", self.files.len(), units)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "class Cart:\n    def total(self):\n        return 1\n\n    def empty(self):\n        return 0\n\n\nx = 1\n";
+
+    fn index() -> SymbolIndex {
+        let mut index = SymbolIndex::new();
+        index.index_file("cart.py".to_string(), SOURCE.to_string()).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_unit_at_position_returns_innermost() {
+        let index = index();
+        let unit = index.unit_at_position("cart.py", 3, 10).unwrap().unwrap();
+        assert_eq!(unit.name, "total");
+        let unit = index.unit_at_position("cart.py", 4, 0).unwrap().unwrap();
+        assert_eq!(unit.name, "Cart");
+        assert!(index.unit_at_position("cart.py", 9, 0).unwrap().is_none());
+        // Columns past the end of the line clamp to it
+        assert_eq!(index.unit_at_position("cart.py", 5, 500).unwrap().unwrap().name, "empty");
+    }
+
+    #[test]
+    fn test_enclosing_units_outermost_first() {
+        let index = index();
+        let names: Vec<String> = index.enclosing_units("cart.py", 6).unwrap().into_iter().map(|u| u.name).collect();
+        assert_eq!(names, vec!["Cart", "empty"]);
+        assert!(index.enclosing_units("cart.py", 9).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_offset_bounds() {
+        let file = &index().files["cart.py"];
+        assert_eq!(file.offset(1, 0), Some(0));
+        assert_eq!(file.offset(2, 4), Some(16));
+        assert_eq!(file.offset(0, 0), None);
+        assert_eq!(file.offset(100, 0), None);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let index = index();
+        let path = std::env::temp_dir().join(format!("symbol_index_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        index.save_to(path).unwrap();
+        let loaded = SymbolIndex::load_from(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.files["cart.py"].units.len(), index.files["cart.py"].units.len());
+        assert_eq!(loaded.files["cart.py"].line_starts, index.files["cart.py"].line_starts);
+    }
+
+    #[test]
+    fn test_outline_nests_methods_in_classes() {
+        let outline = build_outline(&index().files["cart.py"].units);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].signature, "class Cart");
+        let children: Vec<&str> = outline[0].children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(children, vec!["total", "empty"]);
+        assert_eq!(outline[0].render(0), "class Cart\n  def total(self)\n  def empty(self)\n");
+    }
+
+    #[test]
+    fn test_outline_keeps_disjoint_units_as_siblings() {
+        let mut index = SymbolIndex::new();
+        index
+            .index_file("A.java".to_string(), "class A {\n    int get() {\n        return 1;\n    }\n}\n\nclass B {}\n".to_string())
+            .unwrap();
+        let outline = index.file_outline("A.java").unwrap();
+        let top: Vec<&str> = outline.iter().map(|n| n.signature.as_str()).collect();
+        assert_eq!(top, vec!["class A", "class B"]);
+        assert_eq!(outline[0].children[0].signature, "int get()");
+        assert!(outline[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_compact_signature() {
+        assert_eq!(compact_signature("def  f(a,\tb):"), "def f(a, b)");
+        let long = format!("fn f({})", "a".repeat(200));
+        assert_eq!(compact_signature(&long).chars().count(), MAX_OUTLINE_SIGNATURE);
+    }
+
+    #[test]
+    fn test_definitions_and_references_fall_back_to_names() {
+        let mut index = index();
+        let caller = "from cart import Cart\n\ndef checkout(cart):\n    return cart.total() + Cart.total_fee\n";
+        index.index_file("shop.py".to_string(), caller.to_string()).unwrap();
+        let positions = |locations: Vec<SymbolLocation>| {
+            locations.into_iter().map(|l| (l.path, l.line, l.column, l.precise)).collect::<Vec<_>>()
+        };
+
+        // Cursor on `total` in `cart.total()`
+        let definitions = index.locate(LspRequest::Definition, "shop.py", 4, 16, None).unwrap();
+        assert_eq!(positions(definitions), vec![("cart.py".to_string(), 2, 8, false)]);
+        let references = index.locate(LspRequest::References, "shop.py", 4, 16, None).unwrap();
+        assert_eq!(
+            positions(references),
+            vec![("cart.py".to_string(), 2, 8, false), ("shop.py".to_string(), 4, 16, false)]
+        );
+        assert!(index.locate(LspRequest::Definition, "shop.py", 2, 0, None).unwrap().is_empty());
+        assert!(index.locate(LspRequest::Definition, "missing.py", 1, 0, None).is_err());
+
+        // A kept server answer wins until a file it mentions is re-indexed
+        let precise = SymbolLocation {
+            path: "cart.py".to_string(),
+            line: 2,
+            column: 8,
+            end_line: 2,
+            end_column: 13,
+            precise: true,
+        };
+        index.precise.insert((LspRequest::Definition, "shop.py".to_string(), 4, 16), vec![precise]);
+        assert!(index.locate(LspRequest::Definition, "shop.py", 4, 16, None).unwrap()[0].precise);
+        index.index_file("cart.py".to_string(), SOURCE.to_string()).unwrap();
+        assert!(!index.locate(LspRequest::Definition, "shop.py", 4, 16, None).unwrap()[0].precise);
+    }
+
+    #[test]
+    fn test_import_ctags_replaces_tagged_files() {
+        let root = std::env::temp_dir().join(format!("symbol_index_ctags_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("cart.py"), SOURCE).unwrap();
+        let tags = "Cart\t./cart.py\t/^class Cart:$/;\"\tkind:class\tline:1\tend:6\n\
+            total\t./cart.py\t/^    def total(self):$/;\"\tkind:member\tclass:Cart\tline:2\tend:3\n\
+            empty\t./cart.py\t/^    def empty(self):$/;\"\tkind:function\tclass:Cart\tline:5\tend:6\n\
+            gone\tgone.py\t1;\"\tf\n";
+
+        let mut index = SymbolIndex::new();
+        let (imported, unreadable) = index.import_tags(tags, &root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!((imported, unreadable), (2, vec!["gone.py".to_string()]));
+        assert_eq!(index.files["cart.py"].language, "Python");
+        let outline = build_outline(&index.files["cart.py"].units);
+        assert_eq!(outline[0].render(0), "class Cart\n  def empty(self)\n");
+        assert_eq!(index.unit_at_position("cart.py", 6, 8).unwrap().unwrap().name, "empty");
+    }
+
+    #[test]
+    fn test_imported_occurrences_answer_and_give_edges() {
+        let mut index = index();
+        index.index_file("shop.py".to_string(), "def checkout(cart):\n    return cart.total()\n".to_string()).unwrap();
+        let occurrence = |symbol: &str, line: usize, column: usize, definition: bool| crate::code_intel::Occurrence {
+            symbol: symbol.to_string(),
+            line,
+            column,
+            end_line: line,
+            end_column: column + 5,
+            definition,
+        };
+        let dump = Occurrences::from([
+            ("cart.py".to_string(), vec![occurrence("total", 2, 8, true)]),
+            ("shop.py".to_string(), vec![occurrence("total", 2, 16, false), occurrence("std", 2, 4, false)]),
+        ]);
+        assert_eq!(index.import_occurrences(dump), 3);
+
+        let definitions = index.locate(LspRequest::Definition, "shop.py", 2, 18, None).unwrap();
+        assert_eq!((definitions[0].path.as_str(), definitions[0].line, definitions[0].precise), ("cart.py", 2, true));
+        assert_eq!(index.locate(LspRequest::References, "shop.py", 2, 18, None).unwrap().len(), 2);
+        // An externally defined symbol falls back to name matching
+        assert!(!index.locate(LspRequest::Definition, "shop.py", 2, 5, None).unwrap().iter().any(|l| l.precise));
+
+        let edges = index.dump_edges();
+        assert_eq!(edges.len(), 1);
+        assert_eq!((edges[0].0.path.as_str(), edges[0].1.path.as_str()), ("shop.py", "cart.py"));
+        index.index_file("cart.py".to_string(), SOURCE.to_string()).unwrap();
+        assert!(index.dump_edges().is_empty());
+    }
+}
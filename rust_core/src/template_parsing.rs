@@ -0,0 +1,420 @@
+use crate::parsing::{line_starts, ParseOptions, ParseResult, SemanticUnit};
+
+/// Server-side template languages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateLanguage {
+    Jinja,
+    Erb,
+    Handlebars,
+}
+
+impl TemplateLanguage {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "j2" | "jinja" | "jinja2" => Some(TemplateLanguage::Jinja),
+            "erb" => Some(TemplateLanguage::Erb),
+            "hbs" | "handlebars" | "mustache" => Some(TemplateLanguage::Handlebars),
+            _ => None,
+        }
+    }
+
+    /// Opening and closing delimiters of the language's tags
+    fn delimiters(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            TemplateLanguage::Jinja => &[("{%", "%}"), ("{{", "}}"), ("{#", "#}")],
+            TemplateLanguage::Erb => &[("<%", "%>")],
+            TemplateLanguage::Handlebars => &[("{{", "}}")],
+        }
+    }
+}
+
+/// A template tag with its byte span and trimmed inner text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateTag {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// Opening delimiter including any marker (`{%`, `{{`, `<%=`, `{{#`, ...)
+    pub open: String,
+    pub body: String,
+}
+
+impl TemplateTag {
+    /// Tags that print a value, as opposed to control statements and comments
+    fn is_expression(&self) -> bool {
+        let marker = self.open.trim_start_matches(['{', '<', '%']);
+        if self.open.starts_with("<%") {
+            marker.starts_with('=')
+        } else {
+            self.open.starts_with("{{") && marker.chars().all(|c| matches!(c, '-' | '~'))
+        }
+    }
+}
+
+/// Find every tag in a template, in source order
+pub fn scan_tags(source: &str, language: TemplateLanguage) -> Vec<TemplateTag> {
+    let mut tags = Vec::new();
+    let mut pos = 0;
+
+    while pos < source.len() {
+        let next = language
+            .delimiters()
+            .iter()
+            .filter_map(|(open, close)| source[pos..].find(open).map(|at| (pos + at, *open, *close)))
+            .min_by_key(|(at, _, _)| *at);
+        let Some((start, open, close)) = next else {
+            break;
+        };
+        let inner_start = start + open.len();
+        let Some(close_at) = source[inner_start..].find(close) else {
+            break;
+        };
+        let end = inner_start + close_at + close.len();
+
+        // Keep the marker characters that select the tag flavour
+        let raw_inner = &source[inner_start..inner_start + close_at];
+        let marker_len = raw_inner
+            .find(|c: char| !matches!(c, '-' | '=' | '#' | '/' | '>' | '!' | '~' | '*'))
+            .unwrap_or(raw_inner.len());
+        tags.push(TemplateTag {
+            start_byte: start,
+            end_byte: end,
+            open: format!("{}{}", open, &raw_inner[..marker_len]),
+            body: raw_inner[marker_len..]
+                .trim()
+                .trim_end_matches(['-', '~'])
+                .trim()
+                .to_string(),
+        });
+        pos = end;
+    }
+
+    tags
+}
+
+/// First word of a tag body and the remainder (`block content` -> ("block", "content"))
+fn keyword(body: &str) -> (&str, &str) {
+    let body = body.trim();
+    match body.find(char::is_whitespace) {
+        Some(at) => (&body[..at], body[at..].trim()),
+        None => (body, ""),
+    }
+}
+
+/// First quoted string or bare word in a tag argument
+fn first_argument(args: &str) -> String {
+    let args = args.trim().trim_start_matches(['(', ':']);
+    match args.chars().next() {
+        Some(quote @ ('"' | '\'')) => args[1..].split(quote).next().unwrap_or("").to_string(),
+        _ => args
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ',' | '%'))
+            .next()
+            .unwrap_or("")
+            .to_string(),
+    }
+}
+
+/// An open block waiting for its closing tag
+struct OpenBlock {
+    unit_type: &'static str,
+    name: String,
+    parameters: Option<String>,
+    start_byte: usize,
+    /// Closing keyword (`endblock`, `end`, `/inline`) the block waits for
+    closer: &'static str,
+}
+
+/// Extract blocks, macros and includes from a template.
+///
+/// - Jinja: `{% block %}` -> "block", `{% macro %}` -> "macro", and
+///   `{% include/extends/import/from %}` -> "include".
+/// - ERB: `<% content_for :name do %>` -> "block" and `render` calls -> "include".
+/// - Handlebars: `{{#*inline "x"}}` -> "macro", `{{#block "x"}}` /
+///   `{{#content "x"}}` -> "block" and `{{> partial}}` -> "include".
+pub fn extract_template_units(source: &str, language: TemplateLanguage) -> Vec<SemanticUnit> {
+    let language_name = format!("{:?}", language);
+    let line_starts = line_starts(source);
+    let unit = |unit_type: &str, name: String, span: (usize, usize)| {
+        SemanticUnit::spanning(unit_type, name, source, &line_starts, span, &language_name)
+    };
+    let mut units = Vec::new();
+    let mut stack: Vec<OpenBlock> = Vec::new();
+    // ERB closes every Ruby block with `end`, so track unrelated blocks too
+    let mut erb_depth: Vec<Option<OpenBlock>> = Vec::new();
+
+    let close = |block: OpenBlock, end_byte: usize, units: &mut Vec<SemanticUnit>| {
+        let span = (block.start_byte, end_byte);
+        units.push(SemanticUnit { parameters: block.parameters, ..unit(block.unit_type, block.name, span) });
+    };
+
+    for tag in scan_tags(source, language) {
+        let (word, rest) = keyword(&tag.body);
+        match language {
+            TemplateLanguage::Jinja if tag.open.starts_with("{%") => match word {
+                "block" | "macro" => {
+                    let (name, parameters) = match word {
+                        "macro" => match rest.split_once('(') {
+                            Some((name, params)) => (name.trim().to_string(), Some(format!("({}", params))),
+                            None => (rest.to_string(), None),
+                        },
+                        _ => (first_argument(rest), None),
+                    };
+                    stack.push(OpenBlock {
+                        unit_type: if word == "block" { "block" } else { "macro" },
+                        name,
+                        parameters,
+                        start_byte: tag.start_byte,
+                        closer: if word == "block" { "endblock" } else { "endmacro" },
+                    });
+                }
+                "endblock" | "endmacro" => {
+                    if let Some(idx) = stack.iter().rposition(|b| b.closer == word) {
+                        let block = stack.remove(idx);
+                        close(block, tag.end_byte, &mut units);
+                    }
+                }
+                "include" | "extends" | "import" | "from" => {
+                    let target = first_argument(rest);
+                    units.push(unit("include", target, (tag.start_byte, tag.end_byte)));
+                }
+                _ => {}
+            },
+            TemplateLanguage::Erb => {
+                let code = tag.body.as_str();
+                if let Some(args) = code.strip_prefix("render") {
+                    let args = args.trim_start_matches(['(', ' ']);
+                    let target = match args.split_once("partial:") {
+                        Some((_, partial)) => first_argument(partial),
+                        None => first_argument(args),
+                    };
+                    units.push(unit("include", target, (tag.start_byte, tag.end_byte)));
+                }
+                if code == "end" || code.starts_with("end ") || code.starts_with("end;") {
+                    if let Some(Some(block)) = erb_depth.pop() {
+                        close(block, tag.end_byte, &mut units);
+                    }
+                } else if code.ends_with(" do") || code.contains(" do |") || starts_ruby_block(code) {
+                    let block = code.strip_prefix("content_for").map(|args| OpenBlock {
+                        unit_type: "block",
+                        name: first_argument(args),
+                        parameters: None,
+                        start_byte: tag.start_byte,
+                        closer: "end",
+                    });
+                    erb_depth.push(block);
+                }
+            }
+            TemplateLanguage::Handlebars => match tag.open.as_str() {
+                "{{#*" | "{{#" => {
+                    let (unit_type, closer) = match word {
+                        "inline" if tag.open == "{{#*" => ("macro", "inline"),
+                        "block" => ("block", "block"),
+                        "content" => ("block", "content"),
+                        _ => continue,
+                    };
+                    stack.push(OpenBlock {
+                        unit_type,
+                        name: first_argument(rest),
+                        parameters: None,
+                        start_byte: tag.start_byte,
+                        closer,
+                    });
+                }
+                "{{/" => {
+                    if let Some(idx) = stack.iter().rposition(|b| b.closer == word) {
+                        let block = stack.remove(idx);
+                        close(block, tag.end_byte, &mut units);
+                    }
+                }
+                open if open.starts_with("{{>") => {
+                    units.push(unit("include", first_argument(&tag.body), (tag.start_byte, tag.end_byte)));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    units.sort_by_key(|u| (u.start_byte, std::cmp::Reverse(u.end_byte)));
+    units
+}
+
+/// Ruby statements that open a block closed by `end`
+fn starts_ruby_block(code: &str) -> bool {
+    let (word, _) = keyword(code);
+    matches!(word, "if" | "unless" | "case" | "while" | "until" | "for" | "begin" | "def")
+}
+
+/// Blank out template tags so the host document can be parsed.
+///
+/// Expression tags become runs of `_` (a valid identifier or scalar in most
+/// host languages) and statements and comments become spaces, each the same
+/// length as the tag, so byte offsets and line numbers are unchanged.
+pub fn strip_template_tags(source: &str, language: TemplateLanguage) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    for tag in scan_tags(source, language) {
+        out.push_str(&source[pos..tag.start_byte]);
+        let fill = if tag.is_expression() { '_' } else { ' ' };
+        for c in source[tag.start_byte..tag.end_byte].chars() {
+            if c == '\n' {
+                out.push('\n');
+            } else {
+                // Pad multi-byte characters to keep byte offsets stable
+                out.extend(std::iter::repeat_n(fill, c.len_utf8()));
+            }
+        }
+        pos = tag.end_byte;
+    }
+    out.push_str(&source[pos..]);
+    out
+}
+
+/// Parse a template file into template units, optionally followed by units
+/// of the host document (e.g. `config.yaml.j2` -> YAML, `model.py.j2` ->
+/// Python) parsed after stripping the template tags. Host units keep their
+/// own language; host formats that aren't supported are skipped.
+pub fn parse_template_file(
+    file_path: &str,
+    source_code: &str,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    let language = std::path::Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(TemplateLanguage::from_extension)
+        .ok_or(format!("Unsupported template file: {}", file_path))?;
+
+    parse_template_source(file_path, source_code, language, options)
+}
+
+/// Parse template source in an explicit template language. The host document
+/// format is taken from `file_path`, which may carry the template extension
+/// (`model.py.j2`), omit it (`model.py`), or be empty (no host parsing).
+pub fn parse_template_source(
+    file_path: &str,
+    source_code: &str,
+    language: TemplateLanguage,
+    options: &ParseOptions,
+) -> Result<ParseResult, String> {
+    let start = std::time::Instant::now();
+
+    let mut units = extract_template_units(source_code, language);
+    let mut warnings = Vec::new();
+
+    if options.parse_template_host {
+        let path = std::path::Path::new(file_path);
+        let has_template_extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(TemplateLanguage::from_extension)
+            .is_some();
+        let host_path = if has_template_extension { path.with_extension("") } else { path.to_path_buf() };
+        let has_host_extension = host_path.extension().is_some();
+        if has_host_extension {
+            let host_path = host_path.to_string_lossy();
+            let stripped = strip_template_tags(source_code, language);
+            if let Ok(host) = crate::parsing::parse_any_file(&host_path, &stripped, options) {
+                warnings.extend(host.warnings);
+                units.extend(host.units.into_iter().map(|mut unit| {
+                    // Offsets are preserved, so show the original template text
+                    let span = unit.start_byte..unit.end_byte;
+                    if let (Some(original), Some(host_text)) = (source_code.get(span.clone()), stripped.get(span)) {
+                        if unit.content == host_text {
+                            unit.content = original.to_string();
+                        }
+                    }
+                    unit
+                }));
+            }
+        }
+    }
+
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        language: format!("{:?}", language),
+        units,
+        parse_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        warnings,
+        file_classification: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(units: &[SemanticUnit]) -> Vec<(&str, &str)> {
+        units.iter().map(|u| (u.unit_type.as_str(), u.name.as_str())).collect()
+    }
+
+    #[test]
+    fn test_jinja_blocks_macros_and_includes() {
+        let source = "{% extends \"base.html\" %}\n{% import 'forms.html' as forms %}\n{% macro input(name, value='') -%}\n  <input name=\"{{ name }}\">\n{%- endmacro %}\n{% block content %}\n  {% block inner %}x{% endblock %}\n  {% include \"footer.html\" %}\n{% endblock content %}\n";
+        let units = extract_template_units(source, TemplateLanguage::Jinja);
+        assert_eq!(
+            summary(&units),
+            vec![
+                ("include", "base.html"),
+                ("include", "forms.html"),
+                ("macro", "input"),
+                ("block", "content"),
+                ("block", "inner"),
+                ("include", "footer.html"),
+            ]
+        );
+        let input = &units[2];
+        assert_eq!(input.parameters.as_deref(), Some("(name, value='')"));
+        assert_eq!((input.start_line, input.end_line), (3, 5));
+        assert_eq!(&source[units[3].start_byte..units[3].end_byte], units[3].content);
+        assert!(units[3].content.ends_with("{% endblock content %}"));
+    }
+
+    #[test]
+    fn test_erb_content_for_and_render() {
+        let source = "<% content_for :sidebar do %>\n  <% if admin? %>\n    <%= render 'admin/menu' %>\n  <% end %>\n<% end %>\n<%= render partial: \"shared/footer\", locals: {} %>\n";
+        let units = extract_template_units(source, TemplateLanguage::Erb);
+        assert_eq!(
+            summary(&units),
+            vec![("block", "sidebar"), ("include", "admin/menu"), ("include", "shared/footer")]
+        );
+        assert_eq!((units[0].start_line, units[0].end_line), (1, 5));
+    }
+
+    #[test]
+    fn test_handlebars_inline_partials_and_blocks() {
+        let source = "{{#*inline \"row\"}}<tr>{{name}}</tr>{{/inline}}\n{{#block \"body\"}}\n  {{#each items}}{{> row}}{{/each}}\n{{/block}}\n";
+        let units = extract_template_units(source, TemplateLanguage::Handlebars);
+        assert_eq!(summary(&units), vec![("macro", "row"), ("block", "body"), ("include", "row")]);
+    }
+
+    #[test]
+    fn test_strip_template_tags_preserves_offsets() {
+        let source = "name: {{ app_name }}\n{% if debug %}\ndebug: true\n{% endif %}\n";
+        let stripped = strip_template_tags(source, TemplateLanguage::Jinja);
+        assert_eq!(stripped.len(), source.len());
+        assert_eq!(stripped.lines().next(), Some("name: ______________"));
+        assert!(stripped.lines().nth(1).unwrap().trim().is_empty());
+    }
+
+    #[test]
+    fn test_host_language_is_parsed_on_request() {
+        let source = "{% macro header() %}# generated{% endmacro %}\nTIMEOUT = {{ timeout }}\n\ndef handler(event):\n    return {{ body }}\n";
+
+        let plain = parse_template_file("lambda.py.j2", source, &ParseOptions::default()).unwrap();
+        assert_eq!(plain.language, "Jinja");
+        assert_eq!(summary(&plain.units), vec![("macro", "header")]);
+
+        let options = ParseOptions { parse_template_host: true, ..Default::default() };
+        let with_host = parse_template_file("lambda.py.j2", source, &options).unwrap();
+        let handler = with_host.units.iter().find(|u| u.name == "handler").unwrap();
+        assert_eq!(handler.language, "Python");
+        assert!(handler.content.contains("return {{ body }}"));
+    }
+
+    #[test]
+    fn test_host_parsing_skips_unsupported_hosts() {
+        let options = ParseOptions { parse_template_host: true, ..Default::default() };
+        let result = parse_template_file("index.html.erb", "<%= render 'nav' %>\n<p>hi</p>", &options).unwrap();
+        assert_eq!(summary(&result.units), vec![("include", "nav")]);
+    }
+}
@@ -0,0 +1,325 @@
+use pyo3::prelude::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::parsing::{SemanticUnit, SupportedLanguage};
+use crate::trace_parsing::{detect_trace_language, resolve_stack_trace, LocationResolver};
+
+/// A failed or errored test from a test report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct TestFailure {
+    #[pyo3(get)]
+    pub test_id: String, // pytest node id, or `classname.name` for JUnit
+    #[pyo3(get)]
+    pub outcome: String, // "failed" (assertion) or "error" (setup/teardown, unexpected exception)
+    #[pyo3(get)]
+    pub message: Option<String>,
+    #[pyo3(get)]
+    pub details: Option<String>, // Full failure text, usually a stack trace
+    #[pyo3(get)]
+    pub duration: Option<f64>, // Seconds
+    #[pyo3(get)]
+    pub file_path: Option<String>, // Where the failure was raised, when the report says
+    #[pyo3(get)]
+    pub line: Option<usize>,
+    #[pyo3(get)]
+    pub unit: Option<SemanticUnit>, // Innermost resolvable function/class implicated by the failure
+}
+
+#[pymethods]
+impl TestFailure {
+    fn __repr__(&self) -> String {
+        format!(
+            "TestFailure(test_id={}, outcome={}, unit={})",
+            self.test_id,
+            self.outcome,
+            self.unit.as_ref().map(|u| u.name.as_str()).unwrap_or("?")
+        )
+    }
+}
+
+impl TestFailure {
+    /// Resolve the failure's stack trace, taking location and unit from the
+    /// innermost frame that resolves (or the innermost frame with a location)
+    fn attribute(&mut self, language: Option<SupportedLanguage>, resolver: &mut LocationResolver) {
+        let Some(details) = self.details.as_deref() else { return };
+        let Some(language) = language.or_else(|| detect_trace_language(details)) else { return };
+        let Ok(trace) = resolve_stack_trace(details, language, resolver) else { return };
+
+        let located = trace.frames.iter().find(|f| f.unit.is_some()).or_else(|| trace.frames.iter().find(|f| f.line.is_some()));
+        if let Some(frame) = located {
+            if self.file_path.is_none() {
+                self.file_path = frame.file_path.clone();
+                self.line = frame.line;
+            }
+            self.unit = frame.unit.clone();
+        }
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>, String> {
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| e.to_string())?;
+        if attr.key.as_ref() == name {
+            return Ok(Some(attr.unescape_value().map_err(|e| e.to_string())?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Read failures and errors from a JUnit XML report (`<testsuites>` or a
+/// single `<testsuite>`); passing and skipped test cases are omitted
+pub fn parse_junit(xml: &str) -> Result<Vec<TestFailure>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut failures = Vec::new();
+    // The enclosing <testcase> (as a failure template) and the failure being read inside it
+    let mut case: Option<TestFailure> = None;
+    let mut current: Option<TestFailure> = None;
+
+    loop {
+        let event = reader.read_event().map_err(|e| format!("Invalid JUnit XML at byte {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match e.name().as_ref() {
+                    b"testcase" => {
+                        let name = attribute(e, b"name")?.unwrap_or_default();
+                        let test_id = match attribute(e, b"classname")? {
+                            Some(class) if !class.is_empty() => format!("{}.{}", class, name),
+                            _ => name,
+                        };
+                        let duration = attribute(e, b"time")?.and_then(|t| t.parse().ok());
+                        let file = attribute(e, b"file")?;
+                        let line = attribute(e, b"line")?.and_then(|l| l.parse().ok());
+                        case = (!is_empty).then_some(TestFailure {
+                            test_id,
+                            outcome: String::new(),
+                            message: None,
+                            details: None,
+                            duration,
+                            file_path: file,
+                            line,
+                            unit: None,
+                        });
+                    }
+                    tag @ (b"failure" | b"error") => {
+                        let Some(mut failure) = case.clone() else { continue };
+                        failure.outcome = if tag == b"failure" { "failed" } else { "error" }.to_string();
+                        failure.message = attribute(e, b"message")?.filter(|m| !m.is_empty());
+                        if is_empty {
+                            failures.push(failure);
+                        } else {
+                            current = Some(failure);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(text) => {
+                if let Some(failure) = current.as_mut() {
+                    let text = text.unescape().map_err(|e| e.to_string())?;
+                    failure.details.get_or_insert_with(String::new).push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(failure) = current.as_mut() {
+                    let text = String::from_utf8_lossy(&data.into_inner()).into_owned();
+                    failure.details.get_or_insert_with(String::new).push_str(&text);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"failure" | b"error" => {
+                    if let Some(mut failure) = current.take() {
+                        failure.details = failure.details.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+                        failures.push(failure);
+                    }
+                }
+                b"testcase" => case = None,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Read failures and errors from a pytest-json-report (`--json-report`) file
+pub fn parse_pytest_report(json: &str) -> Result<Vec<TestFailure>, String> {
+    let report: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Invalid pytest JSON report: {}", e))?;
+    let tests = report
+        .get("tests")
+        .and_then(|t| t.as_array())
+        .ok_or("pytest JSON report has no \"tests\" array")?;
+
+    let mut failures = Vec::new();
+    for test in tests {
+        let outcome = test.get("outcome").and_then(|o| o.as_str()).unwrap_or("");
+        if !matches!(outcome, "failed" | "error") {
+            continue;
+        }
+        let phases: Vec<&serde_json::Value> =
+            ["setup", "call", "teardown"].iter().filter_map(|phase| test.get(*phase)).collect();
+        let duration: f64 = phases.iter().filter_map(|p| p.get("duration").and_then(|d| d.as_f64())).sum();
+        // The phase that failed carries the crash location and traceback
+        let failed = phases
+            .iter()
+            .find(|p| matches!(p.get("outcome").and_then(|o| o.as_str()), Some("failed" | "error")));
+        let crash = failed.and_then(|p| p.get("crash"));
+
+        failures.push(TestFailure {
+            test_id: test.get("nodeid").and_then(|n| n.as_str()).unwrap_or_default().to_string(),
+            outcome: outcome.to_string(),
+            message: crash.and_then(|c| c.get("message")).and_then(|m| m.as_str()).map(str::to_string),
+            details: failed.and_then(|p| p.get("longrepr")).and_then(|l| l.as_str()).map(str::to_string),
+            duration: (!phases.is_empty()).then_some(duration),
+            file_path: crash.and_then(|c| c.get("path")).and_then(|p| p.as_str()).map(str::to_string),
+            line: crash.and_then(|c| c.get("lineno")).and_then(|l| l.as_u64()).map(|l| l as usize),
+            unit: None,
+        });
+    }
+
+    Ok(failures)
+}
+
+/// Attribute each failure to a unit: the crash location when the report has
+/// one (pytest), otherwise the failure's stack trace
+fn attribute_failures(
+    failures: &mut [TestFailure],
+    language: Option<SupportedLanguage>,
+    parse_context: &HashMap<String, String>,
+) {
+    let mut resolver = LocationResolver::new(parse_context);
+    for failure in failures.iter_mut() {
+        if let (Some(path), Some(line)) = (failure.file_path.as_deref(), failure.line) {
+            failure.unit = resolver.resolve(path, line);
+        }
+        if failure.unit.is_none() {
+            failure.attribute(language, &mut resolver);
+        }
+    }
+}
+
+fn language_arg(language: Option<String>) -> PyResult<Option<SupportedLanguage>> {
+    language
+        .map(|name| {
+            SupportedLanguage::from_name(name.trim())
+                .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown language: {}", name)))
+        })
+        .transpose()
+}
+
+/// Parse a JUnit XML report into failure records
+///
+/// Only failed and errored test cases are returned. Each failure's text is
+/// parsed as a stack trace (in `language`, or detected from the trace) and
+/// resolved against `parse_context` (file path to contents) to find the
+/// implicated unit. Raises ValueError for malformed XML.
+#[pyfunction]
+#[pyo3(signature = (xml, language=None, parse_context=None))]
+pub fn parse_junit_xml(
+    xml: String,
+    language: Option<String>,
+    parse_context: Option<HashMap<String, String>>,
+) -> PyResult<Vec<TestFailure>> {
+    let language = language_arg(language)?;
+    let mut failures = parse_junit(&xml).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    attribute_failures(&mut failures, language, &parse_context.unwrap_or_default());
+    Ok(failures)
+}
+
+/// Parse a pytest-json-report file into failure records
+///
+/// Only failed and errored tests are returned, with durations summed over
+/// setup, call and teardown. The crash location is resolved against
+/// `parse_context` (file path to contents) to find the implicated unit.
+/// Raises ValueError for malformed JSON.
+#[pyfunction]
+#[pyo3(signature = (report_json, parse_context=None))]
+pub fn parse_pytest_json(
+    report_json: String,
+    parse_context: Option<HashMap<String, String>>,
+) -> PyResult<Vec<TestFailure>> {
+    let mut failures = parse_pytest_report(&report_json).map_err(pyo3::exceptions::PyValueError::new_err)?;
+    attribute_failures(&mut failures, Some(SupportedLanguage::Python), &parse_context.unwrap_or_default());
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CART: &str = "def total(items):\n    return sum(items) + 1\n";
+
+    #[test]
+    fn test_junit_failures_and_errors() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites><testsuite name="pytest" tests="3">
+  <testcase classname="tests.test_cart" name="test_ok" time="0.001"/>
+  <testcase classname="tests.test_cart" name="test_total" time="0.012">
+    <failure message="assert 3 == 2">Traceback (most recent call last):
+  File "/ci/src/cart.py", line 2, in total
+    return sum(items) + 1
+AssertionError: assert 3 == 2</failure>
+  </testcase>
+  <testcase classname="tests.test_db" name="test_connect" time="0.5"><error message="timeout &amp; retry"/></testcase>
+  <testcase classname="tests.test_db" name="test_skip"><skipped/></testcase>
+</testsuite></testsuites>"#;
+
+        let mut failures = parse_junit(xml).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].test_id, "tests.test_cart.test_total");
+        assert_eq!(failures[0].outcome, "failed");
+        assert_eq!(failures[0].duration, Some(0.012));
+        assert!(failures[0].details.as_deref().unwrap().starts_with("Traceback"));
+        assert_eq!((failures[1].outcome.as_str(), failures[1].message.as_deref()), ("error", Some("timeout & retry")));
+
+        let context = HashMap::from([("src/cart.py".to_string(), CART.to_string())]);
+        attribute_failures(&mut failures, None, &context);
+        assert_eq!(failures[0].unit.as_ref().unwrap().name, "total");
+        assert_eq!((failures[0].file_path.as_deref(), failures[0].line), (Some("/ci/src/cart.py"), Some(2)));
+        assert!(failures[1].unit.is_none());
+    }
+
+    #[test]
+    fn test_junit_cdata_details() {
+        let xml = "<testsuite><testcase classname=\"CartTest\" name=\"total\"><failure><![CDATA[java.lang.AssertionError\n\tat com.shop.Cart.total(Cart.java:3)]]></failure></testcase></testsuite>";
+        let mut failures = parse_junit(xml).unwrap();
+        attribute_failures(&mut failures, None, &HashMap::new());
+        assert_eq!(failures[0].file_path.as_deref(), Some("com/shop/Cart.java"));
+        assert_eq!(failures[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_pytest_report_failures() {
+        let json = r#"{"tests": [
+            {"nodeid": "tests/test_cart.py::test_ok", "outcome": "passed", "call": {"duration": 0.1, "outcome": "passed"}},
+            {"nodeid": "tests/test_cart.py::test_total", "outcome": "failed",
+             "setup": {"duration": 0.25, "outcome": "passed"},
+             "call": {"duration": 0.5, "outcome": "failed",
+                      "crash": {"path": "/ci/src/cart.py", "lineno": 2, "message": "AssertionError: assert 3 == 2"},
+                      "longrepr": "def total(items):\n>       return sum(items) + 1\nE       AssertionError"},
+             "teardown": {"duration": 0.25, "outcome": "passed"}}
+        ]}"#;
+
+        let mut failures = parse_pytest_report(json).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].test_id, "tests/test_cart.py::test_total");
+        assert_eq!(failures[0].duration, Some(1.0));
+        assert_eq!(failures[0].message.as_deref(), Some("AssertionError: assert 3 == 2"));
+
+        let context = HashMap::from([("src/cart.py".to_string(), CART.to_string())]);
+        attribute_failures(&mut failures, Some(SupportedLanguage::Python), &context);
+        assert_eq!(failures[0].unit.as_ref().unwrap().name, "total");
+    }
+
+    #[test]
+    fn test_malformed_reports_are_errors() {
+        assert!(parse_junit("<testsuite><testcase></testsuite>").is_err());
+        assert!(parse_pytest_report("{}").is_err());
+    }
+}
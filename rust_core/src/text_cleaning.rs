@@ -0,0 +1,253 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use pyo3::prelude::*;
+
+/// Which noise `clean_text` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanOptions {
+    /// Markdown markup (emphasis, headings, links, fences...), keeping its text
+    pub strip_markdown: bool,
+    /// Emoji, emoji sequences and `:shortcode:`s
+    pub strip_emoji: bool,
+    /// Greetings opening the text and sign-offs closing it
+    pub strip_greetings: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        Self { strip_markdown: true, strip_emoji: true, strip_greetings: true }
+    }
+}
+
+/// Openers dropped from the start of a text, with the clause they begin
+const GREETINGS: &[&str] = &[
+    "hi",
+    "hello",
+    "hey",
+    "hiya",
+    "greetings",
+    "good morning",
+    "good afternoon",
+    "good evening",
+    "sure",
+    "absolutely",
+    "great question",
+    "thanks",
+    "thank you",
+];
+
+/// Closers dropped from the end of a text, with the line they begin
+const SIGN_OFFS: &[&str] = &[
+    "thanks",
+    "thank you",
+    "thx",
+    "cheers",
+    "best regards",
+    "best wishes",
+    "kind regards",
+    "regards",
+    "hope this helps",
+    "hope that helps",
+    "let me know if",
+    "happy coding",
+];
+
+/// Words a greeting or sign-off clause may have before it's taken for content
+const MAX_BOILERPLATE_WORDS: usize = 6;
+
+/// Text of a Markdown document without its markup: link and image text are
+/// kept (not URLs), code is kept verbatim, HTML is dropped, and blocks end
+/// with a line break
+fn strip_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut plain = String::with_capacity(text.len());
+    let newline = |plain: &mut String| {
+        if !plain.is_empty() && !plain.ends_with('\n') {
+            plain.push('\n');
+        }
+    };
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Text(text) | Event::Code(text) => plain.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => plain.push('\n'),
+            Event::Start(Tag::Item) | Event::End(TagEnd::Item | TagEnd::TableRow | TagEnd::TableHead) => {
+                newline(&mut plain)
+            }
+            Event::End(TagEnd::TableCell) => plain.push(' '),
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::BlockQuote(_) | TagEnd::List(_),
+            ) => {
+                newline(&mut plain);
+                plain.push('\n');
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// Whether `c` is an emoji or part of an emoji sequence (modifiers,
+/// joiners, variation selectors, keycaps, flags)
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F000}'..='\u{1FAFF}' // Pictographs, emoticons, transport, flags, supplemental symbols
+        | '\u{2600}'..='\u{27BF}' // Miscellaneous symbols, dingbats
+        | '\u{2B50}' | '\u{2B55}' | '\u{2B1B}' | '\u{2B1C}'
+        | '\u{231A}' | '\u{231B}' | '\u{23E9}'..='\u{23F3}' | '\u{23F8}'..='\u{23FA}'
+        | '\u{200D}' // Zero-width joiner
+        | '\u{FE0E}' | '\u{FE0F}' // Variation selectors
+        | '\u{20E3}' // Combining keycap
+        | '\u{E0020}'..='\u{E007F}' // Tag sequences (subdivision flags)
+    )
+}
+
+/// Whether `word` is a `:shortcode:` such as `:tada:` or `:+1:`
+fn is_shortcode(word: &str) -> bool {
+    let word = word.trim_end_matches([',', '.', '!', '?']);
+    word.len() > 2
+        && word.starts_with(':')
+        && word.ends_with(':')
+        && word[1..word.len() - 1].chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_+-".contains(c))
+}
+
+fn strip_emoji(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line: String = line.chars().filter(|&c| !is_emoji(c)).collect();
+            if line.contains(':') {
+                line.split(' ').filter(|word| !is_shortcode(word)).collect::<Vec<_>>().join(" ")
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether `clause` is boilerplate starting with one of `phrases`
+fn is_boilerplate(clause: &str, phrases: &[&str]) -> bool {
+    let clause = clause.trim().to_lowercase();
+    let words = clause.split_whitespace().count();
+    words > 0
+        && words <= MAX_BOILERPLATE_WORDS
+        && phrases.iter().any(|phrase| {
+            clause.strip_prefix(phrase).is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+        })
+}
+
+fn strip_greetings(text: &str) -> String {
+    // Opening clauses, up to their punctuation: "Hi Claude! Thanks, the fix..."
+    let mut rest = text.trim_start();
+    while let Some(end) = rest.find(['.', '!', '?', ',', ':', '\n']) {
+        if !is_boilerplate(&rest[..end], GREETINGS) {
+            break;
+        }
+        rest = rest[end + 1..].trim_start();
+    }
+
+    // Closing lines, including a short name signed under a sign-off
+    let mut lines: Vec<&str> = rest.trim_end().lines().collect();
+    loop {
+        let count = lines.len();
+        if count > 0 && is_boilerplate(lines[count - 1], SIGN_OFFS) {
+            lines.pop();
+        } else if count > 1
+            && lines[count - 1].split_whitespace().count() <= 2
+            && is_boilerplate(lines[count - 2], SIGN_OFFS)
+        {
+            lines.truncate(count - 2);
+        } else {
+            break;
+        }
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+    }
+    lines.join("\n")
+}
+
+/// Trailing spaces and repeated spaces dropped, and runs of blank lines
+/// collapsed to one
+pub(crate) fn normalize_whitespace(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut blank_run = false;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_run = !normalized.is_empty();
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_run { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(&line);
+        blank_run = false;
+    }
+    normalized
+}
+
+/// `text` with the noise `options` selects removed and whitespace tidied,
+/// for embedding conversation-derived memories
+pub fn clean_text(text: &str, options: &CleanOptions) -> String {
+    let mut text = if options.strip_markdown { strip_markdown(text) } else { text.to_string() };
+    if options.strip_emoji {
+        text = strip_emoji(&text);
+    }
+    if options.strip_greetings {
+        text = strip_greetings(&text);
+    }
+    normalize_whitespace(&text)
+}
+
+/// Clean text before embedding it: strip Markdown markup, emoji, and
+/// boilerplate greetings and sign-offs that dilute the embedding
+///
+/// Args:
+///     text: Text to clean, e.g. a conversation turn
+///     strip_markdown: Remove markup, keeping link text and code
+///     strip_emoji: Remove emoji, emoji sequences and `:shortcode:`s
+///     strip_greetings: Remove opening greetings ("Hi! Sure,") and closing
+///         sign-offs ("Hope this helps!", "Thanks,\nSam")
+///
+/// Returns:
+///     The cleaned text, with repeated spaces and blank lines collapsed
+#[pyfunction]
+#[pyo3(signature = (text, strip_markdown=true, strip_emoji=true, strip_greetings=true))]
+pub fn clean_for_embedding(
+    py: Python<'_>,
+    text: &str,
+    strip_markdown: bool,
+    strip_emoji: bool,
+    strip_greetings: bool,
+) -> String {
+    let options = CleanOptions { strip_markdown, strip_emoji, strip_greetings };
+    py.detach(|| clean_text(text, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleans_conversation_turns() {
+        let text = "Hi Claude! 👋 Thanks,\n\n## Fix the **login** bug 🐛\n\n\
+            The `auth_token` expires early — see [the docs](https://example.com/auth) :tada:\n\n\
+            ```python\nrefresh(token)\n```\n\n- first step\n- second 👨\u{200d}💻 step\n\nHope this helps!\nSam\n";
+        let expected = "Fix the login bug\n\nThe auth_token expires early — see the docs\n\n\
+            refresh(token)\n\nfirst step\nsecond step";
+        assert_eq!(clean_text(text, &CleanOptions::default()), expected);
+    }
+
+    #[test]
+    fn test_options_select_what_is_removed() {
+        let text = "Hello! Use **bold** 🎉";
+        let only_emoji = CleanOptions { strip_markdown: false, strip_greetings: false, ..CleanOptions::default() };
+        assert_eq!(clean_text(text, &only_emoji), "Hello! Use **bold**");
+        assert_eq!(clean_text(text, &CleanOptions::default()), "Use bold");
+        // Content that merely starts like a greeting is kept
+        assert_eq!(
+            clean_text("Thanks to the new cache layer the p99 latency dropped by half.", &CleanOptions::default()),
+            "Thanks to the new cache layer the p99 latency dropped by half."
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+/// A BPE vocabulary, matching OpenAI's tiktoken encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// GPT-4 and GPT-3.5
+    Cl100k,
+    /// GPT-4o and later
+    O200k,
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cl100k" | "cl100k_base" => Some(Encoding::Cl100k),
+            "o200k" | "o200k_base" => Some(Encoding::O200k),
+            _ => None,
+        }
+    }
+
+    /// The encoder, built on first use (loading the ranks takes tens of
+    /// milliseconds) and shared afterwards
+    fn bpe(self) -> &'static CoreBPE {
+        static CL100K: OnceLock<CoreBPE> = OnceLock::new();
+        static O200K: OnceLock<CoreBPE> = OnceLock::new();
+        match self {
+            Encoding::Cl100k => CL100K.get_or_init(|| tiktoken_rs::cl100k_base().expect("bundled cl100k ranks")),
+            Encoding::O200k => O200K.get_or_init(|| tiktoken_rs::o200k_base().expect("bundled o200k ranks")),
+        }
+    }
+
+    /// Tokens in `text`. Special tokens such as `<|endoftext|>` are counted
+    /// as ordinary text, as they would be in a retrieved memory.
+    pub fn count(self, text: &str) -> usize {
+        self.bpe().encode_ordinary(text).len()
+    }
+}
+
+fn encoding(name: &str) -> PyResult<Encoding> {
+    Encoding::from_name(name)
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown encoding: {}", name)))
+}
+
+/// Count the tokens text will take in a model's context
+///
+/// Args:
+///     text: Text to count
+///     encoding: "cl100k_base" or "o200k_base"
+///
+/// Returns:
+///     Number of BPE tokens, as tiktoken counts them
+#[pyfunction]
+#[pyo3(signature = (text, encoding="cl100k_base"))]
+pub fn count_tokens(py: Python<'_>, text: &str, encoding: &str) -> PyResult<usize> {
+    let encoding = self::encoding(encoding)?;
+    Ok(py.detach(|| encoding.count(text)))
+}
+
+/// Count the tokens of many texts in parallel, e.g. to budget a retrieved
+/// memory set before sending it to the model
+///
+/// Args:
+///     texts: Texts to count
+///     encoding: "cl100k_base" or "o200k_base"
+///
+/// Returns:
+///     Token count of each text, in order
+#[pyfunction]
+#[pyo3(signature = (texts, encoding="cl100k_base"))]
+pub fn batch_count_tokens(py: Python<'_>, texts: Vec<String>, encoding: &str) -> PyResult<Vec<usize>> {
+    let encoding = self::encoding(encoding)?;
+    Ok(py.detach(|| texts.par_iter().map(|text| encoding.count(text)).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_match_tiktoken() {
+        assert_eq!(Encoding::Cl100k.count("hello world"), 2);
+        assert_eq!(Encoding::Cl100k.count("tiktoken is great!"), 6);
+        assert_eq!(Encoding::O200k.count("hello world"), 2);
+        assert_eq!(Encoding::Cl100k.count(""), 0);
+        assert_eq!(Encoding::from_name("O200K_BASE"), Some(Encoding::O200k));
+        assert_eq!(Encoding::from_name("p50k_base"), None);
+    }
+}
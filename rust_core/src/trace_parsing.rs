@@ -0,0 +1,482 @@
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::parsing::{parse_any_file, ParseOptions, SemanticUnit, SupportedLanguage};
+
+/// One frame of a stack trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct StackFrame {
+    #[pyo3(get)]
+    pub file_path: Option<String>,
+    #[pyo3(get)]
+    pub line: Option<usize>,
+    #[pyo3(get)]
+    pub column: Option<usize>,
+    #[pyo3(get)]
+    pub function: Option<String>, // As printed by the runtime, without arguments
+    #[pyo3(get)]
+    pub text: String, // The trace line(s) the frame was read from
+    #[pyo3(get)]
+    pub unit: Option<SemanticUnit>, // Innermost function/class containing the line, when resolved
+}
+
+#[pymethods]
+impl StackFrame {
+    fn __repr__(&self) -> String {
+        format!(
+            "StackFrame(function={}, file={}, line={})",
+            self.function.as_deref().unwrap_or("?"),
+            self.file_path.as_deref().unwrap_or("?"),
+            self.line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string())
+        )
+    }
+}
+
+/// A parsed stack trace; frames are ordered innermost (where the error was
+/// raised) first, whatever order the runtime printed them in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
+pub struct StackTrace {
+    #[pyo3(get)]
+    pub language: String,
+    #[pyo3(get)]
+    pub error_type: Option<String>,
+    #[pyo3(get)]
+    pub message: Option<String>,
+    #[pyo3(get)]
+    pub frames: Vec<StackFrame>,
+}
+
+#[pymethods]
+impl StackTrace {
+    fn __repr__(&self) -> String {
+        format!(
+            "StackTrace(language={}, error={}, frames={})",
+            self.language,
+            self.error_type.as_deref().unwrap_or("?"),
+            self.frames.len()
+        )
+    }
+}
+
+fn frame(file_path: Option<&str>, line: Option<usize>, column: Option<usize>, function: Option<&str>, text: &str) -> StackFrame {
+    StackFrame {
+        file_path: file_path.filter(|p| !p.is_empty()).map(str::to_string),
+        line,
+        column,
+        function: function.map(str::trim).filter(|f| !f.is_empty()).map(str::to_string),
+        text: text.to_string(),
+        unit: None,
+    }
+}
+
+/// Split `path:line:col` / `path:line`, leaving `path` alone when it has no
+/// numeric suffix. Windows drive letters survive since only digits are split off.
+fn split_location(location: &str) -> (&str, Option<usize>, Option<usize>) {
+    let location = location.trim();
+    let Some((rest, last)) = location.rsplit_once(':') else {
+        return (location, None, None);
+    };
+    let Ok(last) = last.parse::<usize>() else {
+        return (location, None, None);
+    };
+    match rest.rsplit_once(':') {
+        Some((path, line)) => match line.parse::<usize>() {
+            Ok(line) => (path, Some(line), Some(last)),
+            Err(_) => (rest, Some(last), None),
+        },
+        None => (rest, Some(last), None),
+    }
+}
+
+/// Split an `ErrorType: message` line
+fn error_line(line: &str) -> Option<(String, Option<String>)> {
+    let line = line.trim();
+    let (error_type, message) = match line.split_once(": ") {
+        Some((error_type, message)) => (error_type, Some(message.trim().to_string())),
+        None => (line.strip_suffix(':').unwrap_or(line), None),
+    };
+    let looks_like_type = !error_type.is_empty()
+        && error_type
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '$' | ' '))
+        && !error_type.contains("  ");
+    looks_like_type.then(|| (error_type.to_string(), message))
+}
+
+/// `  File "app/cart.py", line 12, in total`; printed outermost first
+fn parse_python(text: &str) -> (Option<(String, Option<String>)>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    let mut error = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("File \"") {
+            let Some((path, rest)) = rest.split_once('"') else { continue };
+            let mut line_number = None;
+            let mut function = None;
+            for part in rest.split(", ").map(str::trim) {
+                if let Some(n) = part.strip_prefix("line ") {
+                    line_number = n.parse().ok();
+                } else if let Some(f) = part.strip_prefix("in ") {
+                    function = Some(f);
+                }
+            }
+            frames.push(frame(Some(path), line_number, None, function, line));
+        } else if !line.starts_with(char::is_whitespace) && !line.starts_with("Traceback") && !frames.is_empty() {
+            // The exception line follows the frames (the last one wins for chained exceptions)
+            if let Some(parsed) = error_line(line) {
+                error = Some(parsed);
+            }
+        }
+    }
+    frames.reverse();
+    (error, frames)
+}
+
+/// `    at Cart.total (/app/src/cart.js:12:5)` or `    at /app/src/cart.js:12:5`
+fn parse_javascript(text: &str) -> (Option<(String, Option<String>)>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    let mut error = None;
+    for line in text.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("at ") else {
+            if frames.is_empty() && error.is_none() {
+                error = error_line(line);
+            }
+            continue;
+        };
+        let (function, location) = match rest.strip_suffix(')').and_then(|r| r.rsplit_once(" (")) {
+            Some((function, location)) => (Some(function), location),
+            None => (None, rest),
+        };
+        let function = function.map(|f| f.trim_start_matches("async ").trim_start_matches("new "));
+        let location = location.strip_prefix("file://").unwrap_or(location);
+        let (path, line_number, column) = split_location(location);
+        let path = (line_number.is_some()).then_some(path);
+        frames.push(frame(path, line_number, column, function, line));
+    }
+    (error, frames)
+}
+
+/// `\tat com.shop.Cart.total(Cart.java:12)`; the file path is rebuilt from
+/// the package (`com/shop/Cart.java`) so it can be matched against sources
+fn parse_java(text: &str) -> (Option<(String, Option<String>)>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    let mut error = None;
+    for line in text.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("at ") else {
+            if frames.is_empty() && error.is_none() {
+                error = error_line(line);
+            }
+            continue;
+        };
+        let Some((qualified, location)) = rest.split_once('(') else { continue };
+        // Drop a `java.base/` style module prefix
+        let qualified = qualified.rsplit('/').next().unwrap_or(qualified);
+        let location = location.trim_end_matches(')');
+        let (file, line_number, _) = split_location(location);
+        let path = line_number.map(|_| {
+            let class = qualified.rsplit_once('.').map(|(class, _)| class).unwrap_or("");
+            match class.rsplit_once('.') {
+                Some((package, _)) => format!("{}/{}", package.replace('.', "/"), file),
+                None => file.to_string(),
+            }
+        });
+        frames.push(frame(path.as_deref(), line_number, None, Some(qualified), line));
+    }
+    (error, frames)
+}
+
+/// Go prints a function line (`main.(*Cart).Total(...)`) followed by a
+/// tab-indented location line (`\t/app/cart.go:12 +0x1d`)
+fn parse_go(text: &str) -> (Option<(String, Option<String>)>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    let mut error = None;
+    let lines: Vec<&str> = text.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(message) = line.strip_prefix("panic: ").or_else(|| line.strip_prefix("fatal error: ")) {
+            error.get_or_insert(("panic".to_string(), Some(message.trim().to_string())));
+            continue;
+        }
+        let Some(location) = line.strip_prefix('\t') else { continue };
+        let location = location.split(" +0x").next().unwrap_or(location);
+        let (path, line_number, _) = split_location(location);
+        if line_number.is_none() {
+            continue;
+        }
+        let function_line = i.checked_sub(1).map(|j| lines[j]).filter(|l| !l.starts_with('\t') && !l.starts_with("goroutine "));
+        let function = function_line.map(|f| match f.rfind('(') {
+            Some(args) if f.ends_with(')') => &f[..args],
+            _ => f,
+        });
+        let text = match function_line {
+            Some(f) => format!("{}\n{}", f, line),
+            None => line.to_string(),
+        };
+        frames.push(frame(Some(path), line_number, None, function, &text));
+    }
+    (error, frames)
+}
+
+/// Rust panics (`thread 'main' panicked at src/main.rs:4:5:`) and
+/// `RUST_BACKTRACE` frames (`   3: app::cart::total` / `at ./src/cart.rs:12:5`)
+fn parse_rust(text: &str) -> (Option<(String, Option<String>)>, Vec<StackFrame>) {
+    let mut frames = Vec::new();
+    let mut error = None;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        if let Some((_, rest)) = line.split_once("panicked at ") {
+            // Old format: panicked at 'message', src/main.rs:4:5
+            if let Some(quoted) = rest.strip_prefix('\'') {
+                if let Some((message, location)) = quoted.rsplit_once("', ") {
+                    let (path, line_number, column) = split_location(location);
+                    frames.push(frame(Some(path), line_number, column, None, line));
+                    error = Some(("panic".to_string(), Some(message.to_string())));
+                }
+            } else {
+                // New format: the message is on the following line
+                let (path, line_number, column) = split_location(rest.trim_end_matches(':'));
+                frames.push(frame(Some(path), line_number, column, None, line));
+                let message = lines.get(i).map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+                error = Some(("panic".to_string(), message));
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let Some((index, function)) = trimmed.split_once(": ") else { continue };
+        if index.parse::<usize>().is_err() {
+            continue;
+        }
+        // Strip the symbol hash (`::h0123456789abcdef`)
+        let function = match function.rsplit_once("::h") {
+            Some((name, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => name,
+            _ => function,
+        };
+        let location_line = lines.get(i).filter(|l| l.trim_start().starts_with("at "));
+        match location_line {
+            Some(location_line) => {
+                i += 1;
+                let location = location_line.trim_start().trim_start_matches("at ");
+                let (path, line_number, column) = split_location(location);
+                let text = format!("{}\n{}", line, location_line);
+                frames.push(frame(Some(path.trim_start_matches("./")), line_number, column, Some(function), &text));
+            }
+            None => frames.push(frame(None, None, None, Some(function), line)),
+        }
+    }
+    (error, frames)
+}
+
+/// Pick the context file a frame path refers to: an exact match, or the
+/// longest path where one is a `/`-separated suffix of the other (traces
+/// often carry absolute or package-relative paths)
+pub(crate) fn match_context_path<'a>(frame_path: &str, context: &'a HashMap<String, String>) -> Option<&'a str> {
+    let frame_path = frame_path.replace('\\', "/");
+    context
+        .keys()
+        .filter(|key| {
+            let key = key.replace('\\', "/");
+            key == frame_path || frame_path.ends_with(&format!("/{}", key)) || key.ends_with(&format!("/{}", frame_path))
+        })
+        .max_by_key(|key| key.len())
+        .map(String::as_str)
+}
+
+/// Resolves file locations to the function or class containing them, parsing
+/// each referenced context file at most once
+pub struct LocationResolver<'a> {
+    context: &'a HashMap<String, String>,
+    parsed: HashMap<&'a str, Vec<SemanticUnit>>,
+}
+
+impl<'a> LocationResolver<'a> {
+    pub fn new(context: &'a HashMap<String, String>) -> Self {
+        Self { context, parsed: HashMap::new() }
+    }
+
+    /// Innermost function or class unit containing `line` of `path`
+    pub fn resolve(&mut self, path: &str, line: usize) -> Option<SemanticUnit> {
+        let (key, source) = self.context.get_key_value(match_context_path(path, self.context)?)?;
+        let units = self.parsed.entry(key.as_str()).or_insert_with(|| {
+            parse_any_file(key, source, &ParseOptions::default()).map(|r| r.units).unwrap_or_default()
+        });
+        units
+            .iter()
+            .filter(|u| matches!(u.unit_type.as_str(), "function" | "class"))
+            .filter(|u| u.start_line <= line && line <= u.end_line)
+            .min_by_key(|u| u.end_byte - u.start_byte)
+            .cloned()
+    }
+}
+
+/// Guess the language of a stack trace from its frame syntax
+pub fn detect_trace_language(text: &str) -> Option<SupportedLanguage> {
+    let lines = || text.lines().map(str::trim_start);
+    if lines().any(|l| l.starts_with("File \"") && l.contains("\", line ")) {
+        Some(SupportedLanguage::Python)
+    } else if text.contains("panicked at ") || lines().any(|l| l.starts_with("at ") && l.contains(".rs:")) {
+        Some(SupportedLanguage::Rust)
+    } else if text.contains("goroutine ") || text.lines().any(|l| l.starts_with('\t') && l.contains(".go:")) {
+        Some(SupportedLanguage::Go)
+    } else if lines().any(|l| l.starts_with("at ") && l.contains(".java:")) {
+        Some(SupportedLanguage::Java)
+    } else if lines().any(|l| l.starts_with("at ")) {
+        Some(SupportedLanguage::JavaScript)
+    } else {
+        None
+    }
+}
+
+/// Parse a stack trace and resolve its frames against the given sources.
+///
+/// `resolver` holds the context files; a frame resolves to the innermost
+/// function or class unit containing its line.
+pub fn resolve_stack_trace(
+    text: &str,
+    language: SupportedLanguage,
+    resolver: &mut LocationResolver,
+) -> Result<StackTrace, String> {
+    let (error, mut frames) = match language {
+        SupportedLanguage::Python => parse_python(text),
+        SupportedLanguage::JavaScript | SupportedLanguage::TypeScript => parse_javascript(text),
+        SupportedLanguage::Java => parse_java(text),
+        SupportedLanguage::Go => parse_go(text),
+        SupportedLanguage::Rust => parse_rust(text),
+        other => return Err(format!("Stack traces are not supported for {:?}", other)),
+    };
+
+    for frame in frames.iter_mut() {
+        if let (Some(path), Some(line)) = (frame.file_path.as_deref(), frame.line) {
+            frame.unit = resolver.resolve(path, line);
+        }
+    }
+
+    let (error_type, message) = match error {
+        Some((error_type, message)) => (Some(error_type), message),
+        None => (None, None),
+    };
+    Ok(StackTrace {
+        language: format!("{:?}", language),
+        error_type,
+        message,
+        frames,
+    })
+}
+
+/// Parse a Python, JavaScript/TypeScript, Java, Go, or Rust stack trace
+///
+/// Frames are returned innermost first. `parse_context` optionally maps file
+/// paths to contents so frames resolve to the function or class they point
+/// into; frame paths match context paths by exact match or path suffix.
+/// Raises ValueError for an unknown or unsupported language.
+#[pyfunction]
+#[pyo3(signature = (text, language, parse_context=None))]
+pub fn parse_stack_trace(
+    text: String,
+    language: String,
+    parse_context: Option<HashMap<String, String>>,
+) -> PyResult<StackTrace> {
+    let name = language.trim().to_ascii_lowercase();
+    let lang = match name.as_str() {
+        "node" | "nodejs" => Some(SupportedLanguage::JavaScript),
+        _ => SupportedLanguage::from_name(&name),
+    }
+    .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(format!("Unknown language: {}", language)))?;
+    let parse_context = parse_context.unwrap_or_default();
+    resolve_stack_trace(&text, lang, &mut LocationResolver::new(&parse_context))
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_names(trace: &StackTrace) -> Vec<&str> {
+        trace.frames.iter().map(|f| f.function.as_deref().unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn test_python_frames_innermost_first_and_resolved() {
+        let trace = "Traceback (most recent call last):\n  File \"/srv/app/main.py\", line 5, in <module>\n    run()\n  File \"/srv/app/cart.py\", line 3, in total\n    return sum(items) / 0\nZeroDivisionError: division by zero\n";
+        let cart = "def total(items):\n    items = list(items)\n    return sum(items) / 0\n";
+        let context = HashMap::from([("app/cart.py".to_string(), cart.to_string())]);
+
+        let parsed = resolve_stack_trace(trace, SupportedLanguage::Python, &mut LocationResolver::new(&context)).unwrap();
+        assert_eq!(function_names(&parsed), vec!["total", "<module>"]);
+        assert_eq!(parsed.error_type.as_deref(), Some("ZeroDivisionError"));
+        assert_eq!(parsed.message.as_deref(), Some("division by zero"));
+        assert_eq!(parsed.frames[0].unit.as_ref().unwrap().name, "total");
+        assert!(parsed.frames[1].unit.is_none());
+    }
+
+    #[test]
+    fn test_javascript_frames() {
+        let trace = "TypeError: Cannot read properties of undefined (reading 'price')\n    at Cart.total (/app/src/cart.js:12:5)\n    at async main (file:///app/src/index.js:3:1)\n    at /app/src/index.js:9:1\n    at node:internal/main:1:1\n";
+        let parsed = resolve_stack_trace(trace, SupportedLanguage::JavaScript, &mut LocationResolver::new(&HashMap::new())).unwrap();
+        assert_eq!(parsed.error_type.as_deref(), Some("TypeError"));
+        assert_eq!(function_names(&parsed), vec!["Cart.total", "main", "", ""]);
+        let first = &parsed.frames[0];
+        assert_eq!((first.file_path.as_deref(), first.line, first.column), (Some("/app/src/cart.js"), Some(12), Some(5)));
+        assert_eq!(parsed.frames[1].file_path.as_deref(), Some("/app/src/index.js"));
+    }
+
+    #[test]
+    fn test_java_frames_rebuild_package_paths() {
+        let trace = "java.lang.IllegalStateException: empty cart\n\tat com.shop.Cart.total(Cart.java:4)\n\tat java.base/java.lang.Thread.run(Thread.java:833)\n\tat com.shop.Native.call(Native Method)\n";
+        let cart = "package com.shop;\n\nclass Cart {\n    int total() { throw new IllegalStateException(\"empty cart\"); }\n}\n";
+        let context = HashMap::from([("src/main/java/com/shop/Cart.java".to_string(), cart.to_string())]);
+
+        let parsed = resolve_stack_trace(trace, SupportedLanguage::Java, &mut LocationResolver::new(&context)).unwrap();
+        assert_eq!(parsed.error_type.as_deref(), Some("java.lang.IllegalStateException"));
+        assert_eq!(parsed.frames[0].file_path.as_deref(), Some("com/shop/Cart.java"));
+        assert_eq!(parsed.frames[0].unit.as_ref().unwrap().name, "total");
+        assert_eq!(parsed.frames[1].function.as_deref(), Some("java.lang.Thread.run"));
+        assert_eq!(parsed.frames[2].line, None);
+    }
+
+    #[test]
+    fn test_go_frames_pair_function_and_location() {
+        let trace = "panic: runtime error: index out of range [3] with length 3\n\ngoroutine 1 [running]:\nmain.(*Cart).Total(...)\n\t/app/cart.go:12\nmain.main()\n\t/app/main.go:8 +0x1d\nexit status 2\n";
+        let parsed = resolve_stack_trace(trace, SupportedLanguage::Go, &mut LocationResolver::new(&HashMap::new())).unwrap();
+        assert_eq!(parsed.error_type.as_deref(), Some("panic"));
+        assert_eq!(parsed.message.as_deref(), Some("runtime error: index out of range [3] with length 3"));
+        assert_eq!(function_names(&parsed), vec!["main.(*Cart).Total", "main.main"]);
+        assert_eq!(parsed.frames[1].line, Some(8));
+    }
+
+    #[test]
+    fn test_rust_panic_and_backtrace() {
+        let trace = "thread 'main' panicked at src/cart.rs:2:5:\nempty cart\nstack backtrace:\n   0: rust_begin_unwind\n             at /rustc/abc/library/std/src/panicking.rs:645:5\n   1: shop::cart::total::h0123456789abcdef\n             at ./src/cart.rs:2:5\n";
+        let cart = "pub fn total() -> u32 {\n    panic!(\"empty cart\")\n}\n";
+        let context = HashMap::from([("src/cart.rs".to_string(), cart.to_string())]);
+
+        let parsed = resolve_stack_trace(trace, SupportedLanguage::Rust, &mut LocationResolver::new(&context)).unwrap();
+        assert_eq!(parsed.message.as_deref(), Some("empty cart"));
+        assert_eq!(function_names(&parsed), vec!["", "rust_begin_unwind", "shop::cart::total"]);
+        assert_eq!(parsed.frames[0].unit.as_ref().unwrap().name, "total");
+        assert_eq!(parsed.frames[2].file_path.as_deref(), Some("src/cart.rs"));
+    }
+
+    #[test]
+    fn test_split_location_keeps_paths_without_numbers() {
+        assert_eq!(split_location("a/b.js:1:2"), ("a/b.js", Some(1), Some(2)));
+        assert_eq!(split_location("C:\\src\\b.js:7"), ("C:\\src\\b.js", Some(7), None));
+        assert_eq!(split_location("<anonymous>"), ("<anonymous>", None, None));
+    }
+
+    #[test]
+    fn test_detect_trace_language() {
+        assert!(matches!(detect_trace_language("  File \"a.py\", line 1, in f"), Some(SupportedLanguage::Python)));
+        assert!(matches!(detect_trace_language("\tat com.a.B.c(B.java:3)"), Some(SupportedLanguage::Java)));
+        assert!(matches!(detect_trace_language("    at f (/a.js:1:2)"), Some(SupportedLanguage::JavaScript)));
+        assert!(matches!(detect_trace_language("goroutine 1 [running]:"), Some(SupportedLanguage::Go)));
+        assert!(matches!(detect_trace_language("thread 'main' panicked at src/a.rs:1:1:"), Some(SupportedLanguage::Rust)));
+        assert!(detect_trace_language("assert 1 == 2").is_none());
+    }
+}
@@ -0,0 +1,380 @@
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::keyword_index::KeywordIndex;
+use crate::relations::{RelationKind, Relations};
+use crate::retrieval::{relation_kind, RetrievalPipeline, Signals};
+use crate::vector_store::VectorStore;
+
+/// One queued change
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    PutVector(String, Vec<f32>),
+    IndexText(String, String),
+    /// Add (or replace) a document in the graph: id, embedding, text, metadata
+    AddDocument(String, Vec<f32>, String, HashMap<String, String>),
+    Relate(String, RelationKind, String),
+    Unrelate(String, RelationKind, String),
+    /// Remove the id from every index in the transaction
+    Delete(String),
+}
+
+/// Why a commit failed; nothing it touched was left changed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// An op was rejected before anything was written
+    Invalid(String),
+    /// Writing the vector store failed; its earlier writes were undone
+    Io(String),
+}
+
+impl From<Failure> for PyErr {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Invalid(message) => pyo3::exceptions::PyValueError::new_err(message),
+            Failure::Io(message) => pyo3::exceptions::PyIOError::new_err(message),
+        }
+    }
+}
+
+/// The graph's relations after `ops`, worked out on a copy so a rejected
+/// relation leaves the graph untouched. Documents added earlier in `ops`
+/// can be related; deleted ones can't, and pinned ones can't be deleted.
+fn plan_graph(graph: &RetrievalPipeline, ops: &[Op]) -> Result<Relations, Failure> {
+    let mut relations = graph.relations.clone();
+    let mut dimension = graph.dimension();
+    let (mut added, mut deleted) = (HashSet::new(), HashSet::new());
+    for op in ops {
+        match op {
+            Op::AddDocument(id, embedding, ..) => {
+                if let Some(expected) = dimension.filter(|&d| d != embedding.len()) {
+                    let message = format!("Embedding has {} dimensions, pipeline holds {}", embedding.len(), expected);
+                    return Err(Failure::Invalid(message));
+                }
+                dimension = Some(embedding.len());
+                deleted.remove(id);
+                added.insert(id.clone());
+            }
+            Op::Relate(source, kind, target) => {
+                let known = |id: &String| (graph.contains(id) || added.contains(id)) && !deleted.contains(id);
+                if let Some(id) = [source, target].into_iter().find(|id| !known(id)) {
+                    return Err(Failure::Invalid(format!("Unknown id: {}", id)));
+                }
+                relations.add(source, *kind, target).map_err(Failure::Invalid)?;
+            }
+            Op::Unrelate(source, kind, target) => {
+                relations.remove(source, *kind, target);
+            }
+            Op::Delete(id) => {
+                if graph.is_pinned(id) && !added.contains(id) {
+                    return Err(Failure::Invalid(format!("Pinned id: {}", id)));
+                }
+                relations.detach(id);
+                added.remove(id);
+                deleted.insert(id.clone());
+            }
+            Op::PutVector(..) | Op::IndexText(..) => {}
+        }
+    }
+    Ok(relations)
+}
+
+/// Apply the vector ops in order, putting back every touched id as it was
+/// if a write fails
+fn apply_vectors(store: &mut VectorStore, ops: &[Op]) -> Result<(), Failure> {
+    let mut before: HashMap<&str, Option<Vec<f32>>> = HashMap::new();
+    for op in ops {
+        if let Op::PutVector(id, vector) = op {
+            store.validate(id, vector).map_err(Failure::Invalid)?;
+        }
+        if let Op::PutVector(id, _) | Op::Delete(id) = op {
+            before.entry(id).or_insert_with(|| store.vector(id));
+        }
+    }
+    let applied = ops.iter().try_for_each(|op| match op {
+        Op::PutVector(id, vector) => store.write(&[(id.clone(), vector.clone())]),
+        Op::Delete(id) => store.remove(id).map(|_| ()),
+        _ => Ok(()),
+    });
+    if let Err(error) = applied {
+        for (id, vector) in before {
+            let _ = match vector {
+                Some(vector) if store.vector(id).as_ref() != Some(&vector) => store.write(&[(id.to_string(), vector)]),
+                Some(_) => Ok(()),
+                None => store.remove(id).map(|_| ()),
+            };
+        }
+        return Err(Failure::Io(error));
+    }
+    Ok(())
+}
+
+/// Apply `ops` to whichever indexes are given, all or nothing. Everything
+/// that can be rejected is checked, and the only fallible writes (the
+/// vector store's) made, before the keyword index and graph change.
+pub fn apply(
+    store: Option<&mut VectorStore>,
+    keywords: Option<&mut KeywordIndex>,
+    graph: Option<&mut RetrievalPipeline>,
+    ops: &[Op],
+) -> Result<(), Failure> {
+    let planned = graph.as_deref().map(|graph| plan_graph(graph, ops)).transpose()?;
+    if let Some(store) = store {
+        apply_vectors(store, ops)?;
+    }
+    if let Some(keywords) = keywords {
+        for op in ops {
+            match op {
+                Op::IndexText(id, text) => keywords.insert(id, text),
+                Op::Delete(id) => {
+                    keywords.delete(id);
+                }
+                _ => {}
+            }
+        }
+    }
+    if let (Some(graph), Some(relations)) = (graph, planned) {
+        for op in ops {
+            match op {
+                Op::AddDocument(id, embedding, text, metadata) => graph
+                    .insert(id.clone(), embedding, text, metadata.clone(), Signals::default())
+                    .expect("dimension checked by plan_graph"),
+                Op::Delete(id) => {
+                    graph.delete(id);
+                }
+                _ => {}
+            }
+        }
+        graph.relations = relations;
+    }
+    Ok(())
+}
+
+/// Changes to the vector store, keyword index and memory graph, queued and
+/// then committed together, so a memory is never left half created
+///
+/// Use as a context manager to commit on success and roll back on an
+/// exception:
+///
+/// ```python
+/// with begin(store=vectors, keywords=bm25, graph=pipeline) as txn:
+///     txn.put_vector(id, embedding)
+///     txn.index_text(id, text)
+///     txn.add_document(id, embedding, text)
+///     txn.relate(id, "supersedes", old_id)
+/// ```
+#[pyclass]
+pub struct Transaction {
+    store: Option<Py<VectorStore>>,
+    keywords: Option<Py<KeywordIndex>>,
+    graph: Option<Py<RetrievalPipeline>>,
+    ops: Vec<Op>,
+    closed: bool,
+}
+
+impl Transaction {
+    fn ensure_open(&self) -> PyResult<()> {
+        if self.closed {
+            return Err(pyo3::exceptions::PyRuntimeError::new_err("Transaction already committed or rolled back"));
+        }
+        Ok(())
+    }
+
+    /// Check an op on the index `name` can be queued
+    fn require(&self, attached: bool, name: &str) -> PyResult<()> {
+        self.ensure_open()?;
+        if !attached {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!("Transaction has no {}", name)));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Transaction {
+    /// Queue storing (or replacing) a vector
+    fn put_vector(&mut self, id: String, vector: Vec<f32>) -> PyResult<()> {
+        self.require(self.store.is_some(), "store")?;
+        self.ops.push(Op::PutVector(id, vector));
+        Ok(())
+    }
+
+    /// Queue indexing (or re-indexing) text for keyword search
+    fn index_text(&mut self, id: String, text: String) -> PyResult<()> {
+        self.require(self.keywords.is_some(), "keywords")?;
+        self.ops.push(Op::IndexText(id, text));
+        Ok(())
+    }
+
+    /// Queue adding (or replacing) a memory in the graph, so relations
+    /// queued after it can point at it
+    #[pyo3(signature = (id, embedding, text, metadata=None))]
+    fn add_document(
+        &mut self,
+        id: String,
+        embedding: Vec<f32>,
+        text: String,
+        metadata: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        self.require(self.graph.is_some(), "graph")?;
+        self.ops.push(Op::AddDocument(id, embedding, text, metadata.unwrap_or_default()));
+        Ok(())
+    }
+
+    /// Queue a relation between two memories in the graph; `kind` is
+    /// "supersedes", "contradicts", "derives_from" or "references"
+    fn relate(&mut self, source: String, kind: &str, target: String) -> PyResult<()> {
+        self.require(self.graph.is_some(), "graph")?;
+        self.ops.push(Op::Relate(source, relation_kind(kind)?, target));
+        Ok(())
+    }
+
+    /// Queue removing a relation from the graph
+    fn unrelate(&mut self, source: String, kind: &str, target: String) -> PyResult<()> {
+        self.require(self.graph.is_some(), "graph")?;
+        self.ops.push(Op::Unrelate(source, relation_kind(kind)?, target));
+        Ok(())
+    }
+
+    /// Queue removing a memory from every index in the transaction
+    fn delete(&mut self, id: String) -> PyResult<()> {
+        self.ensure_open()?;
+        self.ops.push(Op::Delete(id));
+        Ok(())
+    }
+
+    /// Apply the queued ops in order, all or nothing; returns how many were
+    /// applied. Raises ValueError for a rejected op (an unknown relation id,
+    /// a supersedes cycle, a vector or embedding of the wrong dimension,
+    /// deleting a memory the graph has pinned) and
+    /// IOError if the vector store can't be written; either way no index is
+    /// changed.
+    fn commit(&mut self, py: Python<'_>) -> PyResult<usize> {
+        self.ensure_open()?;
+        self.closed = true;
+        let ops = std::mem::take(&mut self.ops);
+        let mut store = self.store.as_ref().map(|store| store.try_borrow_mut(py)).transpose()?;
+        let mut keywords = self.keywords.as_ref().map(|keywords| keywords.try_borrow_mut(py)).transpose()?;
+        let mut graph = self.graph.as_ref().map(|graph| graph.try_borrow_mut(py)).transpose()?;
+        apply(store.as_deref_mut(), keywords.as_deref_mut(), graph.as_deref_mut(), &ops)?;
+        Ok(ops.len())
+    }
+
+    /// Discard the queued ops
+    fn rollback(&mut self) {
+        self.ops.clear();
+        self.closed = true;
+    }
+
+    fn __len__(&self) -> usize {
+        self.ops.len()
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Commit unless the block raised, in which case roll back
+    #[pyo3(signature = (exc_type, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        py: Python<'_>,
+        exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        if exc_type.is_none() && !self.closed {
+            self.commit(py)?;
+        } else {
+            self.rollback();
+        }
+        Ok(false)
+    }
+}
+
+/// Start a transaction over any of a vector store, keyword index and
+/// memory graph
+///
+/// Args:
+///     store: VectorStore holding the memories' embeddings
+///     keywords: KeywordIndex holding their text for BM25 search
+///     graph: RetrievalPipeline whose relations link the memories
+///
+/// Returns:
+///     Transaction queueing ops until `commit`
+#[pyfunction]
+#[pyo3(signature = (store=None, keywords=None, graph=None))]
+pub fn begin(
+    store: Option<Py<VectorStore>>,
+    keywords: Option<Py<KeywordIndex>>,
+    graph: Option<Py<RetrievalPipeline>>,
+) -> Transaction {
+    Transaction { store, keywords, graph, ops: Vec::new(), closed: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejected_op_leaves_every_index_unchanged() {
+        let path = std::env::temp_dir().join(format!("transaction_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut store = VectorStore::open(path, 2, 8).unwrap();
+        let mut keywords = KeywordIndex::default();
+        let mut graph = RetrievalPipeline::default();
+        for id in ["old", "new"] {
+            graph.insert(id.to_string(), &[1.0, 0.0], id, HashMap::new(), Signals::default()).unwrap();
+        }
+        let id = |id: &str| id.to_string();
+        let mut ops = vec![
+            Op::PutVector(id("new"), vec![0.0, 1.0]),
+            Op::IndexText(id("new"), id("parse python source")),
+            Op::Relate(id("new"), RelationKind::Supersedes, id("old")),
+            Op::Relate(id("old"), RelationKind::Supersedes, id("new")),
+        ];
+
+        let failure = apply(Some(&mut store), Some(&mut keywords), Some(&mut graph), &ops);
+        assert!(matches!(failure, Err(Failure::Invalid(_))));
+        assert!(store.vector("new").is_none());
+        assert!(keywords.search("python", 1).is_empty());
+        assert!(graph.relations.is_empty());
+
+        ops.pop();
+        ops.push(Op::PutVector(id("toolongid"), vec![1.0, 1.0]));
+        assert!(apply(Some(&mut store), Some(&mut keywords), Some(&mut graph), &ops).is_err());
+        assert!(store.vector("new").is_none() && graph.relations.is_empty());
+
+        ops.pop();
+        apply(Some(&mut store), Some(&mut keywords), Some(&mut graph), &ops).unwrap();
+        assert_eq!(store.vector("new"), Some(vec![0.0, 1.0]));
+        assert_eq!(keywords.search("python", 1)[0].0, "new");
+        assert_eq!(graph.relations.len(), 1);
+
+        apply(Some(&mut store), Some(&mut keywords), Some(&mut graph), &[Op::Delete(id("new"))]).unwrap();
+        assert!(store.vector("new").is_none() && keywords.search("python", 1).is_empty());
+        assert!(!graph.contains("new") && graph.relations.is_empty());
+
+        // A memory added in the transaction can be related in it, unless
+        // the transaction deletes it again first
+        let add = Op::AddDocument(id("fresh"), vec![0.0, 1.0], id("fresh note"), HashMap::new());
+        let relate = Op::Relate(id("fresh"), RelationKind::Supersedes, id("old"));
+        let ops = [add.clone(), Op::Delete(id("fresh")), relate.clone()];
+        assert!(apply(None, None, Some(&mut graph), &ops).is_err());
+        let wide = Op::AddDocument(id("wide"), vec![1.0; 3], id("wide"), HashMap::new());
+        assert!(apply(None, None, Some(&mut graph), &[wide, relate.clone()]).is_err());
+        assert!(!graph.contains("fresh") && !graph.contains("wide"));
+        apply(None, None, Some(&mut graph), &[add, relate]).unwrap();
+        assert!(graph.contains("fresh"));
+        assert_eq!(graph.relations.len(), 1);
+
+        // The graph's pins keep a memory in every index
+        let pinned = Signals { pinned: true, ..Default::default() };
+        graph.insert(id("rule"), &[1.0, 0.0], "rule", HashMap::new(), pinned).unwrap();
+        let ops = [Op::IndexText(id("rule"), id("never force push")), Op::Delete(id("rule"))];
+        assert!(matches!(apply(None, Some(&mut keywords), Some(&mut graph), &ops), Err(Failure::Invalid(_))));
+        assert!(graph.contains("rule") && keywords.search("force", 1).is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
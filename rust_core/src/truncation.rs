@@ -0,0 +1,138 @@
+use pyo3::prelude::*;
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// What a truncation limit counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measure {
+    /// Unicode scalar values, as Python's `len` counts them
+    Chars,
+    /// User-perceived characters: an emoji with modifiers or a letter with
+    /// combining accents counts once
+    Graphemes,
+    /// Terminal columns: wide (CJK, emoji) graphemes count twice
+    Width,
+}
+
+impl Measure {
+    /// `(end byte, size)` of each piece of `text` the measure never splits
+    fn pieces(self, text: &str) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        match self {
+            Measure::Chars => Box::new(text.char_indices().map(|(i, c)| (i + c.len_utf8(), 1))),
+            Measure::Graphemes => Box::new(text.grapheme_indices(true).map(|(i, g)| (i + g.len(), 1))),
+            Measure::Width => Box::new(text.grapheme_indices(true).map(|(i, g)| (i + g.len(), g.width()))),
+        }
+    }
+
+    pub fn size(self, text: &str) -> usize {
+        self.pieces(text).map(|(_, size)| size).sum()
+    }
+}
+
+/// `text` cut to at most `limit` under `measure`, never inside a character
+/// (or grapheme, for graphemes and width). When text is cut, `ellipsis` is
+/// appended within the limit; an ellipsis that doesn't fit is left off.
+pub fn truncate<'a>(text: &'a str, limit: usize, measure: Measure, ellipsis: &str) -> Cow<'a, str> {
+    if measure.size(text) <= limit {
+        return Cow::Borrowed(text);
+    }
+    let ellipsis_size = measure.size(ellipsis);
+    let (budget, ellipsis) = if ellipsis_size <= limit { (limit - ellipsis_size, ellipsis) } else { (limit, "") };
+
+    let mut used = 0;
+    let mut cut = 0;
+    for (end, size) in measure.pieces(text) {
+        if used + size > budget {
+            break;
+        }
+        used += size;
+        cut = end;
+    }
+    Cow::Owned(format!("{}{}", &text[..cut], ellipsis))
+}
+
+/// Truncate text to at most `max_chars` characters (code points)
+///
+/// Args:
+///     text: Text to truncate
+///     max_chars: Maximum length, including the ellipsis
+///     ellipsis: Appended when text is cut, e.g. "…"
+///
+/// Returns:
+///     The text, unchanged if it already fits
+#[pyfunction]
+#[pyo3(signature = (text, max_chars, ellipsis=""))]
+pub fn truncate_to_chars(text: &str, max_chars: usize, ellipsis: &str) -> String {
+    truncate(text, max_chars, Measure::Chars, ellipsis).into_owned()
+}
+
+/// Truncate text to at most `max_graphemes` user-perceived characters, so
+/// emoji sequences and combining accents are never split
+///
+/// Args:
+///     text: Text to truncate
+///     max_graphemes: Maximum length, including the ellipsis
+///     ellipsis: Appended when text is cut, e.g. "…"
+///
+/// Returns:
+///     The text, unchanged if it already fits
+#[pyfunction]
+#[pyo3(signature = (text, max_graphemes, ellipsis=""))]
+pub fn truncate_to_graphemes(text: &str, max_graphemes: usize, ellipsis: &str) -> String {
+    truncate(text, max_graphemes, Measure::Graphemes, ellipsis).into_owned()
+}
+
+/// Truncate text to at most `max_width` terminal columns, counting wide
+/// (CJK, emoji) characters as two and never splitting a grapheme
+///
+/// Args:
+///     text: Text to truncate
+///     max_width: Maximum display width, including the ellipsis
+///     ellipsis: Appended when text is cut, e.g. "…"
+///
+/// Returns:
+///     The text, unchanged if it already fits
+#[pyfunction]
+#[pyo3(signature = (text, max_width, ellipsis=""))]
+pub fn truncate_to_width(text: &str, max_width: usize, ellipsis: &str) -> String {
+    truncate(text, max_width, Measure::Width, ellipsis).into_owned()
+}
+
+/// Number of user-perceived characters (extended grapheme clusters) in text
+#[pyfunction]
+pub fn grapheme_count(text: &str) -> usize {
+    Measure::Graphemes.size(text)
+}
+
+/// Terminal columns text occupies; wide characters count as two
+#[pyfunction]
+pub fn display_width(text: &str) -> usize {
+    Measure::Width.size(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_never_splits_characters() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let text = format!("cafe\u{301} {}!", family);
+        assert_eq!(grapheme_count(&text), 7);
+        assert_eq!(truncate_to_graphemes(&text, 6, ""), format!("cafe\u{301} {}", family));
+        assert_eq!(truncate_to_graphemes(&text, 4, "…"), "caf…");
+        // A character cut lands between the e and its accent, never inside a code point
+        assert_eq!(truncate_to_chars(&text, 4, ""), "cafe");
+        assert_eq!(truncate_to_chars("short", 10, "…"), "short");
+        assert_eq!(truncate_to_chars("abc", 2, "..."), "ab");
+    }
+
+    #[test]
+    fn test_width_counts_wide_characters_twice() {
+        assert_eq!(display_width("日本語abc"), 9);
+        assert_eq!(truncate_to_width("日本語abc", 5, ""), "日本");
+        assert_eq!(truncate_to_width("日本語abc", 6, "…"), "日本…");
+        assert_eq!(truncate_to_width("日本語abc", 9, "…"), "日本語abc");
+    }
+}
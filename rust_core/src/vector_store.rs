@@ -0,0 +1,884 @@
+use memmap2::MmapMut;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::dry_run::{ChangePlan, Outcome};
+use crate::simd::cosine;
+
+/// File signature and format version
+const MAGIC: &[u8; 8] = b"MCPVEC01";
+const HEADER_SIZE: usize = 64;
+const DIMENSION_OFFSET: usize = 8;
+const ID_CAPACITY_OFFSET: usize = 12;
+const COUNT_OFFSET: usize = 24;
+/// When `compact` last rewrote the file, f64 seconds since the epoch; 0.0 for never
+const COMPACTED_AT_OFFSET: usize = 32;
+/// Slots added whenever the file has to grow, at minimum
+const MIN_GROWTH: usize = 1024;
+
+const DELETED: u8 = 0;
+const LIVE: u8 = 1;
+
+/// Archive file signature and format version
+const ARCHIVE_MAGIC: &[u8; 8] = b"MCPWAL01";
+const ARCHIVE_HEADER_SIZE: usize = 16;
+/// Bytes before each archived record: timestamp f64, then the CRC-32 of the
+/// timestamp and the record, then padding
+const ENTRY_PREFIX_SIZE: usize = 16;
+
+/// CRC-32 (IEEE) of `bytes`
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Seconds since the epoch
+fn now() -> f64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Size and fragmentation of a `VectorStore` file
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct VectorStoreStats {
+    #[pyo3(get)]
+    pub path: String,
+    #[pyo3(get)]
+    pub vectors: usize,
+    #[pyo3(get)]
+    pub tombstones: usize,
+    #[pyo3(get)]
+    pub tombstone_ratio: f64, // Share of committed slots that are tombstones
+    #[pyo3(get)]
+    pub capacity: usize, // Slots the file has room for
+    #[pyo3(get)]
+    pub file_bytes: u64,
+    #[pyo3(get)]
+    pub fragmentation: f64, // Share of slot space not holding live vectors
+    #[pyo3(get)]
+    pub last_compacted_at: Option<f64>, // Seconds since the epoch; None if never compacted
+    #[pyo3(get)]
+    pub archived_since: Option<f64>, // Earliest time `restore_to` can reach; None without archiving
+}
+
+#[pymethods]
+impl VectorStoreStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "VectorStoreStats(path={}, vectors={}, tombstones={}, file_bytes={}, fragmentation={:.4})",
+            self.path, self.vectors, self.tombstones, self.file_bytes, self.fragmentation
+        )
+    }
+}
+
+/// Byte layout of one slot:
+///
+/// | offset        | field                                   |
+/// |---------------|-----------------------------------------|
+/// | 0             | state (`LIVE` or `DELETED`)             |
+/// | 4             | id length, u16                          |
+/// | 8             | id bytes, padded to `id_capacity`       |
+/// | 8 + id cap    | vector, `dimension` little-endian f32   |
+/// | end - 4       | CRC-32 of bytes 4 .. end - 4            |
+///
+/// The checksum leaves out the state byte so a delete is a one-byte write.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    dimension: usize,
+    id_capacity: usize,
+}
+
+impl Layout {
+    fn record_size(&self) -> usize {
+        8 + self.id_capacity + self.dimension * 4 + 4
+    }
+
+    fn offset(&self, slot: usize) -> usize {
+        HEADER_SIZE + slot * self.record_size()
+    }
+
+    fn encode(&self, id: &str, vector: &[f32]) -> Vec<u8> {
+        let mut record = vec![0u8; self.record_size()];
+        record[0] = LIVE;
+        record[4..6].copy_from_slice(&(id.len() as u16).to_le_bytes());
+        record[8..8 + id.len()].copy_from_slice(id.as_bytes());
+        let vector_start = 8 + self.id_capacity;
+        for (i, value) in vector.iter().enumerate() {
+            record[vector_start + i * 4..vector_start + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let end = record.len() - 4;
+        let checksum = crc32(&record[4..end]);
+        record[end..].copy_from_slice(&checksum.to_le_bytes());
+        record
+    }
+
+    /// Id of a record whose checksum matches
+    fn decode_id<'a>(&self, record: &'a [u8]) -> Option<&'a str> {
+        let end = record.len() - 4;
+        if crc32(&record[4..end]) != read_u32(record, end) {
+            return None;
+        }
+        let length = u16::from_le_bytes([record[4], record[5]]) as usize;
+        std::str::from_utf8(record.get(8..8 + length)?).ok()
+    }
+
+    fn vector(&self, record: &[u8]) -> Vec<f32> {
+        let start = 8 + self.id_capacity;
+        record[start..start + self.dimension * 4]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+}
+
+/// Append-only history of every write and delete to a store, kept in
+/// `<store path>.archive` so the store can be rolled back to any point since
+/// archiving began. Entries are a timestamp and checksum followed by the
+/// record as laid out in the store, state `LIVE` for a write and `DELETED`
+/// for a delete. Archiving starts with a snapshot of the live records.
+struct Archive {
+    path: String,
+    file: File,
+    /// Time of the first entry; None while empty
+    since: Option<f64>,
+}
+
+impl Archive {
+    fn path_for(store_path: &str) -> String {
+        format!("{}.archive", store_path)
+    }
+
+    /// Open the archive of the store at `store_path`, if it has one, or
+    /// create it. A torn entry at the end, from a crash mid-append, is cut off.
+    fn open(store_path: &str, layout: &Layout, create: bool) -> Result<Option<Self>, String> {
+        let path = Self::path_for(store_path);
+        if !create && !std::path::Path::new(&path).exists() {
+            return Ok(None);
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let mut header = vec![0u8; ARCHIVE_HEADER_SIZE];
+        header[..8].copy_from_slice(ARCHIVE_MAGIC);
+        header[8..12].copy_from_slice(&(layout.dimension as u32).to_le_bytes());
+        header[12..16].copy_from_slice(&(layout.id_capacity as u32).to_le_bytes());
+        if file.metadata().map_err(|e| e.to_string())?.len() == 0 {
+            file.write_all(&header)
+                .and_then(|_| file.sync_all())
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+
+        let mut archive = Self { path, file, since: None };
+        let bytes = archive.read()?;
+        if bytes.get(..ARCHIVE_HEADER_SIZE) != Some(&header[..]) {
+            return Err(format!("{} is not an archive of this store", archive.path));
+        }
+        let entries = Self::entries(&bytes, layout);
+        archive.since = entries.first().map(|(timestamp, _)| *timestamp);
+        let valid = ARCHIVE_HEADER_SIZE + entries.len() * (ENTRY_PREFIX_SIZE + layout.record_size());
+        if valid < bytes.len() {
+            archive.file.set_len(valid as u64).map_err(|e| format!("Failed to truncate {}: {}", archive.path, e))?;
+        }
+        Ok(Some(archive))
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.read_to_end(&mut bytes))
+            .map_err(|e| format!("Failed to read {}: {}", self.path, e))?;
+        Ok(bytes)
+    }
+
+    /// Entries in order up to the first torn or corrupt one
+    fn entries<'a>(bytes: &'a [u8], layout: &Layout) -> Vec<(f64, &'a [u8])> {
+        let entry_size = ENTRY_PREFIX_SIZE + layout.record_size();
+        bytes[ARCHIVE_HEADER_SIZE.min(bytes.len())..]
+            .chunks_exact(entry_size)
+            .map_while(|entry| {
+                let timestamp = f64::from_bits(read_u64(entry, 0));
+                let mut checked = entry[..8].to_vec();
+                checked.extend_from_slice(&entry[ENTRY_PREFIX_SIZE..]);
+                (crc32(&checked) == read_u32(entry, 8)).then_some((timestamp, &entry[ENTRY_PREFIX_SIZE..]))
+            })
+            .collect()
+    }
+
+    /// Append encoded records and sync them to disk
+    fn append(&mut self, records: &[Vec<u8>], timestamp: f64) -> Result<(), String> {
+        let mut bytes = Vec::new();
+        for record in records {
+            let mut checked = timestamp.to_le_bytes().to_vec();
+            checked.extend_from_slice(record);
+            bytes.extend_from_slice(&timestamp.to_le_bytes());
+            bytes.extend_from_slice(&crc32(&checked).to_le_bytes());
+            bytes.extend_from_slice(&[0u8; ENTRY_PREFIX_SIZE - 12]);
+            bytes.extend_from_slice(record);
+        }
+        self.file
+            .seek(SeekFrom::End(0))
+            .and_then(|_| self.file.write_all(&bytes))
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| format!("Failed to write {}: {}", self.path, e))?;
+        self.since.get_or_insert(timestamp);
+        Ok(())
+    }
+
+    /// The live records as of `timestamp`, in the order they were last written
+    fn replay(&mut self, layout: &Layout, timestamp: f64) -> Result<Vec<(String, Vec<f32>)>, String> {
+        match self.since {
+            Some(since) if since <= timestamp => {}
+            Some(since) => return Err(format!("The archive only reaches back to {}", since)),
+            None => return Err("The archive is empty".to_string()),
+        }
+        let bytes = self.read()?;
+        let mut live: HashMap<String, (usize, Vec<f32>)> = HashMap::new();
+        for (order, (at, record)) in Self::entries(&bytes, layout).into_iter().enumerate() {
+            if at > timestamp {
+                break;
+            }
+            let Some(id) = layout.decode_id(record) else { continue };
+            match record[0] {
+                LIVE => live.insert(id.to_string(), (order, layout.vector(record))),
+                _ => live.remove(id),
+            };
+        }
+        let mut records: Vec<(usize, String, Vec<f32>)> =
+            live.into_iter().map(|(id, (order, vector))| (order, id, vector)).collect();
+        records.sort_by_key(|(order, _, _)| *order);
+        Ok(records.into_iter().map(|(_, id, vector)| (id, vector)).collect())
+    }
+}
+
+/// What `restore_to` would write back, and the ids it would delete
+type Rollback = (Vec<(String, Vec<f32>)>, Vec<String>);
+
+/// Memory-mapped, append-only store of id-keyed vectors
+///
+/// Records live in fixed-size slots after a 64-byte header whose count marks
+/// how many slots are committed. Writes fill slots past the count, flush
+/// them, then bump the count and flush the header, so a crash mid-write
+/// leaves the partial slots uncommitted. Updates append the new record before
+/// deleting the old one; on open, a record superseded by a later one with the
+/// same id is deleted. `compact` rewrites live records into a new file and
+/// renames it over the old. With archiving on, every write and delete is
+/// first appended to an `Archive`, which `restore_to` replays.
+#[pyclass]
+pub struct VectorStore {
+    path: String,
+    layout: Layout,
+    file: File,
+    map: MmapMut,
+    /// Committed slots
+    count: usize,
+    /// Live slot of each id
+    slots: HashMap<String, usize>,
+    archive: Option<Archive>,
+}
+
+impl VectorStore {
+    /// Open the store at `path`, creating it if missing. `dimension` must
+    /// match an existing file's; its `id_capacity` is kept. A store that
+    /// has an archive goes on archiving.
+    pub fn open(path: &str, dimension: usize, id_capacity: usize) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let length = file.metadata().map_err(|e| e.to_string())?.len() as usize;
+        let layout = Layout { dimension, id_capacity: id_capacity.div_ceil(4) * 4 };
+
+        if length == 0 {
+            let mut header = vec![0u8; HEADER_SIZE];
+            header[..8].copy_from_slice(MAGIC);
+            header[DIMENSION_OFFSET..DIMENSION_OFFSET + 4].copy_from_slice(&(dimension as u32).to_le_bytes());
+            header[ID_CAPACITY_OFFSET..ID_CAPACITY_OFFSET + 4]
+                .copy_from_slice(&(layout.id_capacity as u32).to_le_bytes());
+            std::fs::write(path, &header).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            file.sync_all().map_err(|e| e.to_string())?;
+        } else if length < HEADER_SIZE {
+            return Err(format!("{} is not a vector store", path));
+        }
+
+        // SAFETY: the store holds the only writable mapping; other processes
+        // must not modify the file while it is open
+        let map = unsafe { MmapMut::map_mut(&file) }.map_err(|e| format!("Failed to map {}: {}", path, e))?;
+        if &map[..8] != MAGIC {
+            return Err(format!("{} is not a vector store", path));
+        }
+        let stored = Layout {
+            dimension: read_u32(&map, DIMENSION_OFFSET) as usize,
+            id_capacity: read_u32(&map, ID_CAPACITY_OFFSET) as usize,
+        };
+        if stored.dimension != dimension {
+            return Err(format!("{} holds {}-dimensional vectors, not {}", path, stored.dimension, dimension));
+        }
+        let capacity = (map.len() - HEADER_SIZE) / stored.record_size();
+        let count = (read_u64(&map, COUNT_OFFSET) as usize).min(capacity);
+
+        let archive = Archive::open(path, &stored, false)?;
+        let slots = HashMap::new();
+        let mut store = Self { path: path.to_string(), layout: stored, file, map, count, slots, archive };
+        store.recover()?;
+        Ok(store)
+    }
+
+    /// Rebuild the id map from committed slots, deleting corrupt records and
+    /// records superseded by a later write of the same id
+    fn recover(&mut self) -> Result<(), String> {
+        let mut stale = Vec::new();
+        for slot in 0..self.count {
+            let record = self.record(slot);
+            if record[0] != LIVE {
+                continue;
+            }
+            match self.layout.decode_id(record) {
+                Some(id) => {
+                    if let Some(previous) = self.slots.insert(id.to_string(), slot) {
+                        stale.push(previous);
+                    }
+                }
+                None => stale.push(slot),
+            }
+        }
+        for slot in stale {
+            self.mark_deleted(slot)?;
+        }
+        Ok(())
+    }
+
+    fn record(&self, slot: usize) -> &[u8] {
+        let offset = self.layout.offset(slot);
+        &self.map[offset..offset + self.layout.record_size()]
+    }
+
+    fn capacity(&self) -> usize {
+        (self.map.len() - HEADER_SIZE) / self.layout.record_size()
+    }
+
+    /// Grow the file (and remap it) to hold at least `slots` records
+    fn reserve(&mut self, slots: usize) -> Result<(), String> {
+        if slots <= self.capacity() {
+            return Ok(());
+        }
+        let capacity = slots.max(self.capacity() * 2).max(MIN_GROWTH);
+        let length = self.layout.offset(capacity) as u64;
+        self.file.set_len(length).map_err(|e| format!("Failed to grow {}: {}", self.path, e))?;
+        // SAFETY: as in `open`
+        self.map = unsafe { MmapMut::map_mut(&self.file) }.map_err(|e| format!("Failed to map {}: {}", self.path, e))?;
+        Ok(())
+    }
+
+    fn mark_deleted(&mut self, slot: usize) -> Result<(), String> {
+        let offset = self.layout.offset(slot);
+        self.map[offset] = DELETED;
+        self.map.flush_range(offset, 1).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn validate(&self, id: &str, vector: &[f32]) -> Result<(), String> {
+        if vector.len() != self.layout.dimension {
+            return Err(format!("Vector has {} dimensions, store holds {}", vector.len(), self.layout.dimension));
+        }
+        if id.len() > self.layout.id_capacity || id.len() > u16::MAX as usize {
+            return Err(format!("Id is {} bytes, store allows {}", id.len(), self.layout.id_capacity));
+        }
+        Ok(())
+    }
+
+    /// Write records past the committed count, then commit them all at once.
+    /// Ids already present are superseded.
+    pub fn write(&mut self, records: &[(String, Vec<f32>)]) -> Result<(), String> {
+        for (id, vector) in records {
+            self.validate(id, vector)?;
+        }
+        if records.is_empty() {
+            return Ok(());
+        }
+        if let Some(archive) = self.archive.as_mut() {
+            let encoded: Vec<Vec<u8>> = records.iter().map(|(id, vector)| self.layout.encode(id, vector)).collect();
+            archive.append(&encoded, now())?;
+        }
+        self.reserve(self.count + records.len())?;
+        let start = self.layout.offset(self.count);
+        for (i, (id, vector)) in records.iter().enumerate() {
+            let offset = self.layout.offset(self.count + i);
+            self.map[offset..offset + self.layout.record_size()].copy_from_slice(&self.layout.encode(id, vector));
+        }
+        let written = self.layout.offset(self.count + records.len()) - start;
+        self.map.flush_range(start, written).map_err(|e| e.to_string())?;
+
+        let first = self.count;
+        self.count += records.len();
+        self.map[COUNT_OFFSET..COUNT_OFFSET + 8].copy_from_slice(&(self.count as u64).to_le_bytes());
+        self.map.flush_range(0, HEADER_SIZE).map_err(|e| e.to_string())?;
+
+        for (i, (id, _)) in records.iter().enumerate() {
+            if let Some(previous) = self.slots.insert(id.clone(), first + i) {
+                self.mark_deleted(previous)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<bool, String> {
+        let Some(&slot) = self.slots.get(id) else { return Ok(false) };
+        if let Some(archive) = self.archive.as_mut() {
+            let mut record = self.layout.encode(id, &vec![0.0; self.layout.dimension]);
+            record[0] = DELETED;
+            archive.append(&[record], now())?;
+        }
+        self.slots.remove(id);
+        self.mark_deleted(slot)?;
+        Ok(true)
+    }
+
+    /// Start archiving, with a snapshot of the live records as its first
+    /// entries. Does nothing if the store already archives.
+    pub fn enable_archiving(&mut self) -> Result<(), String> {
+        if self.archive.is_some() {
+            return Ok(());
+        }
+        let mut archive = Archive::open(&self.path, &self.layout, true)?.expect("created archive");
+        let snapshot: Vec<Vec<u8>> = self
+            .ordered_ids()
+            .iter()
+            .map(|id| self.layout.encode(id, &self.layout.vector(self.record(self.slots[id]))))
+            .collect();
+        archive.append(&snapshot, now())?;
+        self.archive = Some(archive);
+        Ok(())
+    }
+
+    /// Roll the store back to how it stood at `timestamp` (seconds since the
+    /// epoch): records written since are deleted or put back as they were,
+    /// records deleted since are restored. The rollback is archived like any
+    /// other write, so it can itself be undone. Returns the number of
+    /// records restored and deleted.
+    pub fn restore(&mut self, timestamp: f64) -> Result<(usize, usize), String> {
+        let (restored, stale) = self.rollback(timestamp)?;
+        for id in &stale {
+            self.remove(id)?;
+        }
+        self.write(&restored)?;
+        Ok((restored.len(), stale.len()))
+    }
+
+    /// The records `restore` would write back and the ids it would delete
+    fn rollback(&mut self, timestamp: f64) -> Result<Rollback, String> {
+        let layout = self.layout;
+        let archive = self.archive.as_mut().ok_or_else(|| format!("{} is not archived", self.path))?;
+        let target = archive.replay(&layout, timestamp)?;
+        let wanted: HashMap<&str, &Vec<f32>> = target.iter().map(|(id, vector)| (id.as_str(), vector)).collect();
+        let stale: Vec<String> =
+            self.ordered_ids().into_iter().filter(|id| !wanted.contains_key(id.as_str())).collect();
+        let restored: Vec<(String, Vec<f32>)> =
+            target.iter().filter(|(id, vector)| self.vector(id).as_ref() != Some(vector)).cloned().collect();
+        Ok((restored, stale))
+    }
+
+    /// What `restore(timestamp)` would change: the ids it would rewrite or
+    /// delete, and the record bytes involved
+    pub fn plan_restore(&mut self, timestamp: f64) -> Result<ChangePlan, String> {
+        let (restored, stale) = self.rollback(timestamp)?;
+        let ids: Vec<String> = restored.into_iter().map(|(id, _)| id).chain(stale).collect();
+        let bytes = (ids.len() * self.layout.record_size()) as u64;
+        Ok(ChangePlan::new("restore_to", ids.clone(), ids.len(), bytes))
+    }
+
+    pub fn vector(&self, id: &str) -> Option<Vec<f32>> {
+        self.slots.get(id).map(|&slot| self.layout.vector(self.record(slot)))
+    }
+
+    /// Live ids in slot (write) order
+    pub fn ordered_ids(&self) -> Vec<String> {
+        let mut slots: Vec<(&usize, &String)> = self.slots.iter().map(|(id, slot)| (slot, id)).collect();
+        slots.sort();
+        slots.into_iter().map(|(_, id)| id.clone()).collect()
+    }
+
+    /// Committed slots no longer holding a live record
+    pub fn tombstones(&self) -> usize {
+        self.count - self.slots.len()
+    }
+
+    /// The `k` stored vectors most similar to `query` by cosine, most similar first
+    pub fn nearest(&self, query: &[f32], k: usize) -> Result<Vec<(String, f32)>, String> {
+        if query.len() != self.layout.dimension {
+            return Err(format!("Query has {} dimensions, store holds {}", query.len(), self.layout.dimension));
+        }
+        let mut scored: Vec<(&String, f32)> = self
+            .slots
+            .par_iter()
+            .map(|(id, &slot)| {
+                let vector = self.layout.vector(self.record(slot));
+                (id, cosine(&vector, query))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        Ok(scored.into_iter().take(k).map(|(id, score)| (id.clone(), score)).collect())
+    }
+
+    /// What `compact_file` would reclaim: its tombstones, and the bytes the
+    /// file would shrink by
+    pub fn plan_compact(&self) -> ChangePlan {
+        let live = self.slots.len();
+        let compacted = if live == 0 { HEADER_SIZE } else { self.layout.offset(live.max(MIN_GROWTH)) };
+        let bytes = self.map.len().saturating_sub(compacted) as u64;
+        ChangePlan::new("compact", Vec::new(), self.tombstones(), bytes)
+    }
+
+    /// Rewrite live records, in slot order, into a fresh file renamed over
+    /// this one. Returns the number of slots reclaimed.
+    pub fn compact_file(&mut self) -> Result<usize, String> {
+        let reclaimed = self.tombstones();
+        let tmp_path = format!("{}.tmp", self.path);
+        let _ = std::fs::remove_file(&tmp_path);
+        let records: Vec<(String, Vec<f32>)> = self
+            .ordered_ids()
+            .into_iter()
+            .map(|id| {
+                let vector = self.vector(&id).unwrap();
+                (id, vector)
+            })
+            .collect();
+        {
+            let mut compacted = Self::open(&tmp_path, self.layout.dimension, self.layout.id_capacity)?;
+            compacted.write(&records)?;
+            compacted.map[COMPACTED_AT_OFFSET..COMPACTED_AT_OFFSET + 8].copy_from_slice(&now().to_le_bytes());
+            compacted.map.flush_range(0, HEADER_SIZE).map_err(|e| e.to_string())?;
+            compacted.file.sync_all().map_err(|e| e.to_string())?;
+        }
+        std::fs::rename(&tmp_path, &self.path).map_err(|e| format!("Failed to replace {}: {}", self.path, e))?;
+        *self = Self::open(&self.path, self.layout.dimension, self.layout.id_capacity)?;
+        Ok(reclaimed)
+    }
+
+    pub fn stats(&self) -> VectorStoreStats {
+        let capacity = self.capacity();
+        let ratio = |part: usize, whole: usize| if whole == 0 { 0.0 } else { part as f64 / whole as f64 };
+        let compacted_at = f64::from_bits(read_u64(&self.map, COMPACTED_AT_OFFSET));
+        VectorStoreStats {
+            path: self.path.clone(),
+            vectors: self.slots.len(),
+            tombstones: self.tombstones(),
+            tombstone_ratio: ratio(self.tombstones(), self.count),
+            capacity,
+            file_bytes: self.map.len() as u64,
+            fragmentation: ratio(capacity - self.slots.len(), capacity),
+            last_compacted_at: (compacted_at > 0.0).then_some(compacted_at),
+            archived_since: self.archive.as_ref().and_then(|archive| archive.since),
+        }
+    }
+}
+
+#[pymethods]
+impl VectorStore {
+    /// Open or create the store at `path` for `dimension`-length vectors with
+    /// ids of up to `id_capacity` UTF-8 bytes (fixed when the file is
+    /// created). Raises ValueError if an existing file holds a different
+    /// dimension.
+    ///
+    /// With `archive`, every write and delete is also kept in
+    /// `<path>.archive` so `restore_to` can roll the store back; a store
+    /// that was archived once keeps archiving when reopened.
+    #[new]
+    #[pyo3(signature = (path, dimension, id_capacity=128, archive=false))]
+    fn new(path: &str, dimension: usize, id_capacity: usize, archive: bool) -> PyResult<Self> {
+        let mut store = Self::open(path, dimension, id_capacity).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        if archive {
+            store.enable_archiving().map_err(pyo3::exceptions::PyIOError::new_err)?;
+        }
+        Ok(store)
+    }
+
+    #[getter]
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[getter]
+    fn dimension(&self) -> usize {
+        self.layout.dimension
+    }
+
+    /// Path of the archive, or None when the store isn't archived
+    #[getter]
+    fn archive_path(&self) -> Option<&str> {
+        self.archive.as_ref().map(|archive| archive.path.as_str())
+    }
+
+    /// Deleted or superseded slots awaiting `compact`
+    #[getter(tombstones)]
+    fn py_tombstones(&self) -> usize {
+        self.tombstones()
+    }
+
+    /// Store a new vector. Raises ValueError if the id exists.
+    fn append(&mut self, id: String, vector: Vec<f32>) -> PyResult<()> {
+        if self.slots.contains_key(&id) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!("Id already stored: {}", id)));
+        }
+        self.write(&[(id, vector)]).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Store or replace `(id, vector)` pairs, committed together
+    fn append_batch(&mut self, records: Vec<(String, Vec<f32>)>) -> PyResult<()> {
+        self.write(&records).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Replace a stored vector. Raises KeyError if the id is missing.
+    fn update(&mut self, id: String, vector: Vec<f32>) -> PyResult<()> {
+        if !self.slots.contains_key(&id) {
+            return Err(pyo3::exceptions::PyKeyError::new_err(id));
+        }
+        self.write(&[(id, vector)]).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Delete a vector; returns whether it was stored. With `dry_run`,
+    /// nothing is deleted and a ChangePlan of what would be is returned.
+    #[pyo3(signature = (id, dry_run=false))]
+    fn delete(&mut self, id: &str, dry_run: bool) -> PyResult<Outcome<bool>> {
+        if dry_run {
+            let ids: Vec<String> = self.slots.contains_key(id).then(|| id.to_string()).into_iter().collect();
+            let bytes = (ids.len() * self.layout.record_size()) as u64;
+            return Ok(Outcome::Planned(ChangePlan::new("delete", ids.clone(), ids.len(), bytes)));
+        }
+        self.remove(id).map(Outcome::Applied).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    /// The vector stored under `id`, or None
+    fn get(&self, id: &str) -> Option<Vec<f32>> {
+        self.vector(id)
+    }
+
+    /// Stored ids in write order
+    fn ids(&self) -> Vec<String> {
+        self.ordered_ids()
+    }
+
+    /// `k` nearest stored vectors by cosine similarity, computed over the
+    /// mapped file without copying vectors to Python
+    #[pyo3(signature = (query, k=10))]
+    fn search(&self, py: Python<'_>, query: Vec<f32>, k: usize) -> PyResult<Vec<(String, f32)>> {
+        py.detach(|| self.nearest(&query, k)).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// File size, tombstones, fragmentation and last compaction time, as
+    /// reported by the `memory_status` tool
+    fn store_stats(&self) -> VectorStoreStats {
+        self.stats()
+    }
+
+    /// Roll the store back to how it stood at `timestamp`, seconds since the
+    /// epoch, e.g. to undo what a misbehaving agent run wrote. Returns
+    /// `(restored, deleted)` record counts. Raises ValueError if the store
+    /// isn't archived or the archive doesn't reach back to `timestamp`
+    /// (see `store_stats().archived_since`). With `dry_run`, nothing is
+    /// rolled back and a ChangePlan of the ids that would be is returned.
+    #[pyo3(signature = (timestamp, dry_run=false))]
+    fn restore_to(&mut self, timestamp: f64, dry_run: bool) -> PyResult<Outcome<(usize, usize)>> {
+        let outcome = if dry_run {
+            self.plan_restore(timestamp).map(Outcome::Planned)
+        } else {
+            self.restore(timestamp).map(Outcome::Applied)
+        };
+        outcome.map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Reclaim deleted slots; returns how many were reclaimed. With
+    /// `dry_run`, the file is left alone and a ChangePlan of the slots and
+    /// bytes that would be reclaimed is returned.
+    #[pyo3(signature = (dry_run=false))]
+    fn compact(&mut self, dry_run: bool) -> PyResult<Outcome<usize>> {
+        if dry_run {
+            return Ok(Outcome::Planned(self.plan_compact()));
+        }
+        self.compact_file().map(Outcome::Applied).map_err(pyo3::exceptions::PyIOError::new_err)
+    }
+
+    /// Flush the whole mapping to disk
+    fn flush(&self) -> PyResult<()> {
+        self.map.flush().map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string()))
+    }
+
+    fn __contains__(&self, id: &str) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    fn __len__(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "VectorStore(path={}, dimension={}, vectors={}, tombstones={})",
+            self.path,
+            self.layout.dimension,
+            self.slots.len(),
+            self.tombstones()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("vector_store_{}_{}.bin", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn record(id: &str, vector: &[f32]) -> (String, Vec<f32>) {
+        (id.to_string(), vector.to_vec())
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_survives_reopen() {
+        let path = temp_path("reopen");
+        {
+            let mut store = VectorStore::open(&path, 3, 16).unwrap();
+            store.write(&[record("a", &[1.0, 0.0, 0.0]), record("b", &[0.0, 1.0, 0.0])]).unwrap();
+            store.write(&[record("a", &[0.0, 0.0, 1.0])]).unwrap();
+            assert!(store.remove("b").unwrap());
+        }
+        let store = VectorStore::open(&path, 3, 16).unwrap();
+        assert_eq!(store.ordered_ids(), vec!["a"]);
+        assert_eq!(store.vector("a"), Some(vec![0.0, 0.0, 1.0]));
+        assert_eq!(store.tombstones(), 2);
+        assert!(VectorStore::open(&path, 4, 16).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_uncommitted_and_superseded_records_are_recovered() {
+        let path = temp_path("recover");
+        {
+            let mut store = VectorStore::open(&path, 2, 8).unwrap();
+            store.write(&[record("a", &[1.0, 0.0])]).unwrap();
+            // Simulate a crash after appending a replacement but before deleting the original
+            let replacement = store.layout.encode("a", &[0.0, 1.0]);
+            store.reserve(2).unwrap();
+            let offset = store.layout.offset(1);
+            store.map[offset..offset + replacement.len()].copy_from_slice(&replacement);
+            store.map[COUNT_OFFSET..COUNT_OFFSET + 8].copy_from_slice(&2u64.to_le_bytes());
+            // ...and a torn write past the committed count
+            let torn = store.layout.encode("b", &[1.0, 1.0]);
+            store.reserve(3).unwrap();
+            let offset = store.layout.offset(2);
+            store.map[offset..offset + 6].copy_from_slice(&torn[..6]);
+            store.map.flush().unwrap();
+        }
+        let store = VectorStore::open(&path, 2, 8).unwrap();
+        assert_eq!(store.ordered_ids(), vec!["a"]);
+        assert_eq!(store.vector("a"), Some(vec![0.0, 1.0]));
+        assert_eq!(store.tombstones(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_search_and_compact() {
+        let path = temp_path("compact");
+        let mut store = VectorStore::open(&path, 2, 8).unwrap();
+        let records: Vec<(String, Vec<f32>)> =
+            (0..10).map(|i| record(&i.to_string(), &[i as f32, 10.0 - i as f32])).collect();
+        store.write(&records).unwrap();
+        for i in 0..5 {
+            store.remove(&i.to_string()).unwrap();
+        }
+        assert_eq!(store.nearest(&[1.0, 0.0], 2).unwrap()[0].0, "9");
+        let before = store.stats();
+        assert_eq!((before.vectors, before.tombstones, before.tombstone_ratio), (5, 5, 0.5));
+        assert_eq!(before.capacity, MIN_GROWTH);
+        assert_eq!(before.file_bytes, store.layout.offset(MIN_GROWTH) as u64);
+        assert!(before.last_compacted_at.is_none());
+        let plan = store.plan_compact();
+        assert_eq!(plan.count, 5);
+        assert_eq!(store.stats().file_bytes, before.file_bytes);
+        assert_eq!(store.compact_file().unwrap(), 5);
+        assert_eq!(plan.bytes, before.file_bytes - store.stats().file_bytes);
+        assert_eq!(store.tombstones(), 0);
+        let after = store.stats();
+        assert_eq!(after.tombstone_ratio, 0.0);
+        assert!(after.last_compacted_at.is_some_and(|t| t > 0.0));
+        assert_eq!(store.ordered_ids(), vec!["5", "6", "7", "8", "9"]);
+        assert_eq!(store.vector("7"), Some(vec![7.0, 3.0]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_restore_to_rolls_back_through_the_archive() {
+        let path = temp_path("restore");
+        let archive_path = Archive::path_for(&path);
+        let _ = std::fs::remove_file(&archive_path);
+        let pause = || std::thread::sleep(std::time::Duration::from_millis(5));
+        {
+            let mut store = VectorStore::open(&path, 2, 8).unwrap();
+            store.write(&[record("a", &[1.0, 0.0]), record("b", &[0.0, 1.0])]).unwrap();
+            assert!(store.restore(now()).is_err());
+            store.enable_archiving().unwrap();
+        }
+        let mut store = VectorStore::open(&path, 2, 8).unwrap();
+        pause();
+        let good = now();
+        pause();
+        // A bad run overwrites a, deletes b and adds c
+        store.write(&[record("a", &[9.0, 9.0]), record("c", &[1.0, 1.0])]).unwrap();
+        store.remove("b").unwrap();
+
+        let plan = store.plan_restore(good).unwrap();
+        assert_eq!((plan.ids, plan.count), (vec!["a".to_string(), "b".to_string(), "c".to_string()], 3));
+        assert_eq!(store.ordered_ids(), vec!["a", "c"]);
+        assert_eq!(store.restore(good).unwrap(), (2, 1));
+        assert_eq!(store.ordered_ids(), vec!["a", "b"]);
+        assert_eq!(store.vector("a"), Some(vec![1.0, 0.0]));
+        assert!(store.stats().archived_since.is_some_and(|since| since < good));
+        assert!(store.restore(good - 60.0).is_err());
+        // The rollback was archived too, and survives compaction
+        store.compact_file().unwrap();
+        assert_eq!(store.restore(now()).unwrap(), (0, 0));
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&archive_path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_bad_records() {
+        let path = temp_path("reject");
+        let mut store = VectorStore::open(&path, 2, 4).unwrap();
+        assert!(store.write(&[record("toolong", &[1.0, 0.0])]).is_err());
+        assert!(store.write(&[record("a", &[1.0])]).is_err());
+        assert!(store.nearest(&[1.0], 1).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
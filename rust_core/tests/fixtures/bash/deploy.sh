@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+readonly ROOT=/srv/app
+RETRIES=3
+log_file=deploy.log
+
+# Print a timestamped message to the log
+log() {
+  echo "$(date +%s) $*" >> "$log_file"
+}
+
+function build {
+  local target=$1
+  log "building $target"
+  make -C "$ROOT" "$target"
+}
+
+deploy() {
+  for attempt in $(seq "$RETRIES"); do
+    build release && return 0
+    log "attempt $attempt failed"
+  done
+  return 1
+}
+
+deploy
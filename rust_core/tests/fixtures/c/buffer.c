@@ -0,0 +1,21 @@
+#include <stdlib.h>
+#include "buffer.h"
+
+#define BUFFER_SIZE 256
+
+int allocations = 0;
+
+typedef unsigned char byte;
+
+enum mode { READ, WRITE };
+
+struct buffer {
+    byte *data;
+    enum mode mode;
+};
+
+int buffer_init(struct buffer *buf) {
+    buf->data = malloc(BUFFER_SIZE);
+    allocations++;
+    return buf->data != NULL;
+}
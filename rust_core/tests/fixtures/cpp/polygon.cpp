@@ -0,0 +1,19 @@
+#include <vector>
+
+using Points = std::vector<int>;
+
+struct Point {
+    int x, y;
+};
+
+class Polygon {
+public:
+    int sides() const { return static_cast<int>(points.size()); }
+
+private:
+    std::vector<Point> points;
+};
+
+int perimeter(const Polygon &polygon) {
+    return polygon.sides();
+}
@@ -0,0 +1,25 @@
+using System;
+using System.Collections.Generic;
+
+namespace Shop
+{
+    public enum OrderState { Open, Shipped }
+
+    public interface IOrder
+    {
+        decimal Total();
+    }
+
+    public class Order : IOrder
+    {
+        public const int MaxLines = 50;
+        private readonly List<decimal> lines = new List<decimal>();
+
+        public decimal Total()
+        {
+            decimal sum = 0;
+            foreach (var line in lines) sum += line;
+            return sum;
+        }
+    }
+}
@@ -0,0 +1,25 @@
+package server
+
+import (
+	"fmt"
+	"net/http"
+)
+
+const DefaultPort = 8080
+
+var requests int
+
+type Handler func(http.ResponseWriter, *http.Request)
+
+type Server struct {
+	Port int
+}
+
+func New() *Server {
+	requests++
+	return &Server{Port: DefaultPort}
+}
+
+func Describe(s *Server) string {
+	return fmt.Sprintf(":%d", s.Port)
+}
@@ -0,0 +1,20 @@
+package shop;
+
+import java.util.HashMap;
+import java.util.Map;
+
+public class Inventory {
+    public static final int MAX_STOCK = 500;
+
+    enum Status { IN_STOCK, SOLD_OUT }
+
+    private final Map<String, Integer> stock = new HashMap<>();
+
+    public void add(String sku, int count) {
+        stock.merge(sku, Math.min(count, MAX_STOCK), Integer::sum);
+    }
+
+    public Status status(String sku) {
+        return stock.getOrDefault(sku, 0) > 0 ? Status.IN_STOCK : Status.SOLD_OUT;
+    }
+}
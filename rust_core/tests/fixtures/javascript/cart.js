@@ -0,0 +1,20 @@
+import { formatPrice } from './format';
+const path = require('path');
+
+export const TAX_RATE = 0.2;
+let instances = 0;
+
+class Cart {
+  constructor() {
+    this.items = [];
+    instances += 1;
+  }
+
+  total() {
+    return this.items.reduce((sum, item) => sum + item.price, 0);
+  }
+}
+
+function checkout(cart) {
+  return formatPrice(cart.total() * (1 + TAX_RATE));
+}
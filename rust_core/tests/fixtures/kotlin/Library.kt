@@ -0,0 +1,34 @@
+package library
+
+import java.time.LocalDate
+
+const val LOAN_DAYS = 14
+var openLoans = 0
+
+/** A book that can be lent out */
+data class Book(val title: String, val author: String)
+
+interface Catalog {
+    fun find(title: String): Book?
+}
+
+class Library(private val books: MutableList<Book>) : Catalog {
+    override fun find(title: String): Book? = books.firstOrNull { it.title == title }
+
+    // Due date for a loan starting today
+    fun lend(book: Book): LocalDate {
+        openLoans += 1
+        return LocalDate.now().plusDays(LOAN_DAYS.toLong())
+    }
+}
+
+object Registry {
+    fun register(library: Library) {
+        println(library)
+    }
+}
+
+fun main() {
+    val library = Library(mutableListOf(Book("Dune", "Herbert")))
+    println(library.find("Dune"))
+}
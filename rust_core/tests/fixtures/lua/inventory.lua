@@ -0,0 +1,29 @@
+local json = require("json")
+
+local MAX_ITEMS = 64
+local items = {}
+
+-- Add an item, refusing past MAX_ITEMS
+local function add(name, count)
+  if #items >= MAX_ITEMS then
+    return false
+  end
+  items[#items + 1] = { name = name, count = count }
+  return true
+end
+
+local Inventory = {}
+
+function Inventory.total()
+  local total = 0
+  for _, item in ipairs(items) do
+    total = total + item.count
+  end
+  return total
+end
+
+function Inventory:dump()
+  return json.encode(items)
+end
+
+return { add = add, Inventory = Inventory }
@@ -0,0 +1,32 @@
+<?php
+
+namespace App;
+
+use App\Support\Template;
+require_once 'config.php';
+
+const DEFAULT_SENDER = 'noreply@example.com';
+
+enum Priority
+{
+    case Low;
+    case High;
+}
+
+interface Sender
+{
+    public function send(string $to): bool;
+}
+
+class Mailer implements Sender
+{
+    public function send(string $to): bool
+    {
+        return format_mail($to) !== '';
+    }
+}
+
+function format_mail(string $to): string
+{
+    return Template::render($to, DEFAULT_SENDER);
+}
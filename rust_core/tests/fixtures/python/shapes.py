@@ -0,0 +1,18 @@
+import math
+from typing import TypeAlias
+
+PI_DIGITS = 5
+registry = {}
+Point: TypeAlias = tuple
+
+
+class Circle:
+    def __init__(self, radius):
+        self.radius = radius
+
+    def area(self):
+        return round(math.pi * self.radius ** 2, PI_DIGITS)
+
+
+def describe(shape):
+    return f"{type(shape).__name__}: {shape.area()}"
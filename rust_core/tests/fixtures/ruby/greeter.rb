@@ -0,0 +1,18 @@
+require 'json'
+require_relative 'helpers'
+
+VERSION = '1.0'
+$greetings = 0
+
+module Greeting
+  class Greeter
+    def initialize(name)
+      @name = name
+    end
+
+    def greet
+      $greetings += 1
+      JSON.generate(message: "Hello, #{@name}")
+    end
+  end
+end
@@ -0,0 +1,25 @@
+use std::collections::VecDeque;
+
+pub const CAPACITY: usize = 64;
+static GREETING: &str = "hello";
+
+pub type Job = Box<dyn FnOnce() + Send>;
+
+pub enum Priority {
+    Low,
+    High,
+}
+
+pub struct Queue {
+    jobs: VecDeque<Job>,
+}
+
+impl Queue {
+    pub fn push(&mut self, job: Job) {
+        self.jobs.push_back(job);
+    }
+}
+
+fn main() {
+    println!("{}", GREETING);
+}
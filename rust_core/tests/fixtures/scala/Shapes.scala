@@ -0,0 +1,25 @@
+package shapes
+
+import scala.math.Pi
+
+/** Anything with an area */
+trait Shape {
+  def area: Double
+}
+
+case class Circle(radius: Double) extends Shape {
+  def area: Double = Pi * radius * radius
+}
+
+class Square(side: Double) extends Shape {
+  // Side squared
+  def area: Double = side * side
+
+  def scale(factor: Double): Square = new Square(side * factor)
+}
+
+object Shapes {
+  val Unit = new Square(1.0)
+
+  def largest(shapes: Seq[Shape]): Shape = shapes.maxBy(_.area)
+}
@@ -0,0 +1,9 @@
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY,
+    email TEXT NOT NULL
+);
+
+CREATE VIEW active_users AS
+    SELECT id, email FROM users WHERE email IS NOT NULL;
+
+CREATE INDEX idx_users_email ON users (email);
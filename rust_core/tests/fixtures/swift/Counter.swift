@@ -0,0 +1,38 @@
+import Foundation
+
+let maxCount = 100
+
+/// A value that only counts up
+protocol Incrementing {
+    func increment()
+}
+
+struct Point {
+    var x: Double
+    var y: Double
+
+    func distance(to other: Point) -> Double {
+        return ((x - other.x) * (x - other.x) + (y - other.y) * (y - other.y)).squareRoot()
+    }
+}
+
+class Counter: Incrementing {
+    private(set) var value = 0
+
+    init(start: Int) {
+        value = start
+    }
+
+    // Stops at maxCount
+    func increment() {
+        value = min(value + 1, maxCount)
+    }
+}
+
+enum Direction {
+    case up, down
+}
+
+func makeCounter() -> Counter {
+    return Counter(start: 0)
+}
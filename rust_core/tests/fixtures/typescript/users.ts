@@ -0,0 +1,26 @@
+import type { Request } from "express";
+
+export const MAX_USERS = 100;
+let cache: Map<string, User> | null = null;
+
+export enum Role {
+  Admin,
+  Member,
+}
+
+type UserId = string;
+
+interface User {
+  id: UserId;
+  role: Role;
+}
+
+export class UserService {
+  find(id: UserId): User | undefined {
+    return cache?.get(id);
+  }
+}
+
+export function currentUser(req: Request): UserId {
+  return req.header("x-user") ?? "";
+}
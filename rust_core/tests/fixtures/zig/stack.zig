@@ -0,0 +1,32 @@
+const std = @import("std");
+
+const max_depth: usize = 32;
+var pushes: u32 = 0;
+
+/// A fixed-capacity stack of integers
+const Stack = struct {
+    items: [max_depth]i32 = undefined,
+    len: usize = 0,
+
+    pub fn push(self: *Stack, value: i32) !void {
+        if (self.len == max_depth) return error.Overflow;
+        self.items[self.len] = value;
+        self.len += 1;
+        pushes += 1;
+    }
+
+    pub fn pop(self: *Stack) ?i32 {
+        if (self.len == 0) return null;
+        self.len -= 1;
+        return self.items[self.len];
+    }
+};
+
+const Color = enum { red, green };
+
+// Entry point
+pub fn main() void {
+    var stack = Stack{};
+    stack.push(1) catch {};
+    std.debug.print("{}\n", .{stack.pop()});
+}
@@ -67,7 +67,10 @@ class WatchCommand:
                 "Monitoring file types: .py, .js, .ts, .tsx, .jsx, .java, .go, .rs, .c, .cpp, .h, .hpp"
             )
             print(
-                "                        .swift, .kt, .rb, .php, .cs, .sql, .json, .yaml, .yml, .toml, .md"
+                "                        .swift, .kt, .scala, .lua, .sh, .zig, .rb, .php, .cs, .sql"
+            )
+            print(
+                "                        .json, .yaml, .yml, .toml, .md, .ipynb"
             )
             print(
                 "Ignoring: .git/, node_modules/, __pycache__/, venv/, .venv/, build/, dist/"
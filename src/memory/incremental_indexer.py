@@ -180,11 +180,29 @@ class IncrementalIndexer(BaseCodeIndexer):
         ".swift",
         ".kt",
         ".kts",
+        ".scala",
+        ".lua",
+        ".sh",
+        ".bash",
+        ".zig",
         ".php",
         ".json",
+        ".jsonc",
+        ".json5",
         ".yaml",
         ".yml",
         ".toml",
+        ".ini",
+        ".cfg",
+        ".properties",
+        ".env",
+        ".xml",
+        ".md",
+        ".markdown",
+        ".ipynb",
+        ".eml",
+        ".mbox",
+        ".ics",
         ".c",
         ".h",
         ".cpp",
@@ -195,6 +213,16 @@ class IncrementalIndexer(BaseCodeIndexer):
         ".hh",
         ".cs",
         ".sql",
+        ".tf",
+        ".hcl",
+        ".dockerfile",
+    }
+
+    # Supported files recognized by name rather than extension
+    SUPPORTED_FILENAMES = {
+        "Dockerfile",
+        "Containerfile",
+        ".env",
     }
 
     def __init__(
@@ -275,6 +303,20 @@ class IncrementalIndexer(BaseCodeIndexer):
 
         logger.info("Incremental indexer ready")
 
+    @staticmethod
+    def _base_name(file_path: Path) -> str:
+        """The file name up to its first dot, a leading one aside (e.g. .env.local -> .env)."""
+        name = file_path.name
+        if name.startswith("."):
+            return "." + name[1:].split(".")[0]
+        return name.split(".")[0]
+
+    def _is_supported(self, file_path: Path) -> bool:
+        """Whether the file has a supported extension or name (e.g. Dockerfile.dev, .env.local)."""
+        if file_path.suffix in self.SUPPORTED_EXTENSIONS:
+            return True
+        return self._base_name(file_path) in self.SUPPORTED_FILENAMES
+
     async def index_file(self, file_path: Path) -> Dict[str, Any]:
         """
         Index a single source file.
@@ -296,7 +338,7 @@ class IncrementalIndexer(BaseCodeIndexer):
         if not file_path.exists():
             raise FileNotFoundError(f"File not found: {file_path}")
 
-        if file_path.suffix not in self.SUPPORTED_EXTENSIONS:
+        if not self._is_supported(file_path):
             logger.debug(f"Skipping unsupported file: {file_path}")
             return {"units_indexed": 0, "skipped": True}
 
@@ -310,6 +352,8 @@ class IncrementalIndexer(BaseCodeIndexer):
             logger.debug(
                 f"Parsed {len(parse_result.units)} units in {parse_result.parse_time_ms:.2f}ms"
             )
+            for warning in getattr(parse_result, "warnings", []):
+                logger.warning(f"Parsing {file_path.name}: {warning}")
 
             # Extract imports for dependency tracking
             imports = self.import_extractor.extract_imports(
@@ -405,6 +449,9 @@ class IncrementalIndexer(BaseCodeIndexer):
         all_files = []
         for ext in self.SUPPORTED_EXTENSIONS:
             all_files.extend(dir_path.glob(f"{pattern}{ext}"))
+        for name in self.SUPPORTED_FILENAMES:
+            all_files.extend(dir_path.glob(f"{pattern[:-1]}{name}"))
+            all_files.extend(dir_path.glob(f"{pattern[:-1]}{name}.*"))
 
         # Filter out common unwanted directories (but allow other dot-prefixed paths)
         # BUG-022: Previous logic filtered ALL paths with dots (broke git worktrees, .config dirs, etc)
@@ -423,8 +470,8 @@ class IncrementalIndexer(BaseCodeIndexer):
 
         def should_include_file(file_path: Path) -> bool:
             """Check if file should be indexed (not in excluded directories or hidden)."""
-            # Skip hidden files (files starting with .)
-            if file_path.name.startswith("."):
+            # Skip hidden files (files starting with .), except dotenv files
+            if file_path.name.startswith(".") and self._base_name(file_path) not in self.SUPPORTED_FILENAMES:
                 return False
 
             # Get relative path from dir_path to check only subdirectories being indexed
@@ -1008,6 +1055,13 @@ class IncrementalIndexer(BaseCodeIndexer):
             else:
                 importance = 0.7  # Fallback to moderate importance
 
+            # Markdown frontmatter tags (comma-separated) become memory tags
+            tags = ["code", unit.unit_type, language.lower()]
+            frontmatter_tags = (getattr(unit, "metadata", None) or {}).get("tags", "")
+            tags.extend(
+                tag for tag in frontmatter_tags.split(",") if tag and tag not in tags
+            )
+
             metadata = {
                 "id": deterministic_id,  # Deterministic ID prevents duplicates
                 "category": MemoryCategory.CODE.value,
@@ -1015,7 +1069,7 @@ class IncrementalIndexer(BaseCodeIndexer):
                 "scope": MemoryScope.PROJECT.value,
                 "project_name": self.project_name,
                 "importance": importance,  # Dynamic importance (FEAT-049)
-                "tags": ["code", unit.unit_type, language.lower()],
+                "tags": tags,
                 "metadata": unit_metadata,
             }
 
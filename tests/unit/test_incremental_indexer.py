@@ -397,6 +397,20 @@ class TestIncrementalIndexer:
 
         await indexer.close()
 
+    def test_dotenv_files_are_supported(self, config, mock_store, mock_embedding_generator):
+        """Test that dotenv files are recognized by name despite having no suffix."""
+        indexer = IncrementalIndexer(
+            store=mock_store,
+            embedding_generator=mock_embedding_generator,
+            config=config,
+            project_name="test_project",
+        )
+
+        for name in [".env", ".env.local", "prod.env", "Dockerfile.dev"]:
+            assert indexer._is_supported(Path(name)), name
+        assert not indexer._is_supported(Path(".envrc"))
+        assert not indexer._is_supported(Path(".hidden"))
+
     @pytest.mark.asyncio
     async def test_skip_hidden_files(
         self, temp_dir, config, mock_store, mock_embedding_generator